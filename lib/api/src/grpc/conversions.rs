@@ -18,13 +18,16 @@ use crate::grpc::qdrant::value::Kind;
 use crate::grpc::qdrant::vectors::VectorsOptions;
 use crate::grpc::qdrant::with_payload_selector::SelectorOptions;
 use crate::grpc::qdrant::{
-    with_vectors_selector, CollectionDescription, CollectionOperationResponse, Condition, Distance,
-    FieldCondition, Filter, GeoBoundingBox, GeoPoint, GeoRadius, HasIdCondition, HealthCheckReply,
-    HnswConfigDiff, IsEmptyCondition, IsNullCondition, ListCollectionsResponse, ListValue, Match,
-    NamedVectors, PayloadExcludeSelector, PayloadIncludeSelector, PayloadIndexParams,
-    PayloadSchemaInfo, PayloadSchemaType, PointId, QuantizationConfig, QuantizationSearchParams,
-    Range, ScalarQuantization, ScoredPoint, SearchParams, Struct, TextIndexParams, TokenizerType,
-    Value, ValuesCount, Vector, Vectors, VectorsSelector, WithPayloadSelector, WithVectorsSelector,
+    with_vectors_selector, BoostFunction, CollectionDescription, CollectionOperationResponse,
+    Condition, Direction, Distance, EfPolicy, FieldCondition, Filter, GeoBoundingBox,
+    GeoLineString, GeoPoint, GeoPolygon, GeoRadius, HasIdCondition, HasVectorCondition,
+    HealthCheckReply, HnswConfigDiff, IsEmptyCondition, IsNullCondition, KeywordIndexParams,
+    ListCollectionsResponse, ListValue, Match, NamedVectors, NeighborSelection, NestedCondition,
+    OrderBy, PayloadExcludeSelector, PayloadIncludeSelector, PayloadIndexParams, PayloadSchemaInfo,
+    PayloadSchemaType, PointId, QuantizationConfig, QuantizationSearchParams, Range,
+    ScalarQuantization, ScoreBoost, ScoredPoint, SearchParams, Struct, TextIndexParams,
+    TokenizerType, Value, ValuesCount, Vector, Vectors, VectorsSelector, WithPayloadSelector,
+    WithVectorsSelector,
 };
 
 pub fn payload_to_proto(payload: segment::types::Payload) -> HashMap<String, Value> {
@@ -156,6 +159,25 @@ impl From<segment::data_types::text_index::TextIndexParams> for PayloadIndexPara
     }
 }
 
+impl From<segment::data_types::keyword_index::KeywordIndexParams> for PayloadIndexParams {
+    fn from(params: segment::data_types::keyword_index::KeywordIndexParams) -> Self {
+        PayloadIndexParams {
+            index_params: Some(IndexParams::KeywordIndexParams(KeywordIndexParams {
+                insensitive: params.insensitive,
+            })),
+        }
+    }
+}
+
+impl From<KeywordIndexParams> for segment::data_types::keyword_index::KeywordIndexParams {
+    fn from(params: KeywordIndexParams) -> Self {
+        segment::data_types::keyword_index::KeywordIndexParams {
+            r#type: Default::default(),
+            insensitive: params.insensitive,
+        }
+    }
+}
+
 impl From<segment::types::PayloadIndexInfo> for PayloadSchemaInfo {
     fn from(schema: segment::types::PayloadIndexInfo) -> Self {
         PayloadSchemaInfo {
@@ -171,6 +193,9 @@ impl From<segment::types::PayloadIndexInfo> for PayloadSchemaInfo {
                 segment::types::PayloadSchemaParams::Text(text_index_params) => {
                     text_index_params.into()
                 }
+                segment::types::PayloadSchemaParams::Keyword(keyword_index_params) => {
+                    keyword_index_params.into()
+                }
             }),
             points: Some(schema.points as u64),
         }
@@ -214,6 +239,9 @@ impl TryFrom<PayloadIndexParams> for segment::data_types::text_index::TextIndexP
             Some(IndexParams::TextIndexParams(text_index_params)) => {
                 Ok(text_index_params.try_into()?)
             }
+            Some(IndexParams::KeywordIndexParams(_)) => Err(Status::invalid_argument(
+                "expected text index params, got keyword index params",
+            )),
         }
     }
 }
@@ -226,6 +254,9 @@ impl TryFrom<IndexParams> for segment::types::PayloadSchemaParams {
             IndexParams::TextIndexParams(text_index_params) => Ok(
                 segment::types::PayloadSchemaParams::Text(text_index_params.try_into()?),
             ),
+            IndexParams::KeywordIndexParams(keyword_index_params) => Ok(
+                segment::types::PayloadSchemaParams::Keyword(keyword_index_params.into()),
+            ),
         }
     }
 }
@@ -355,6 +386,12 @@ impl From<SearchParams> for segment::types::SearchParams {
             hnsw_ef: params.hnsw_ef.map(|x| x as usize),
             exact: params.exact.unwrap_or(false),
             quantization: params.quantization.map(|q| q.into()),
+            strict_filter: params.strict_filter.unwrap_or(false),
+            ivf_nprobe: params.ivf_nprobe.map(|x| x as usize),
+            score_boost: params.score_boost.map(|score_boost| score_boost.into()),
+            timeout_ms: params.timeout_ms,
+            partial_on_timeout: params.partial_on_timeout.unwrap_or(false),
+            with_telemetry: params.with_telemetry.unwrap_or(false),
         }
     }
 }
@@ -365,6 +402,98 @@ impl From<segment::types::SearchParams> for SearchParams {
             hnsw_ef: params.hnsw_ef.map(|x| x as u64),
             exact: Some(params.exact),
             quantization: params.quantization.map(|q| q.into()),
+            strict_filter: Some(params.strict_filter),
+            ivf_nprobe: params.ivf_nprobe.map(|x| x as u64),
+            score_boost: params.score_boost.map(|score_boost| score_boost.into()),
+            timeout_ms: params.timeout_ms,
+            partial_on_timeout: Some(params.partial_on_timeout),
+            with_telemetry: Some(params.with_telemetry),
+        }
+    }
+}
+
+impl From<ScoreBoost> for segment::types::ScoreBoost {
+    fn from(score_boost: ScoreBoost) -> Self {
+        let function = score_boost
+            .function
+            .and_then(BoostFunction::from_i32)
+            .unwrap_or_default();
+        Self {
+            key: score_boost.key,
+            factor: score_boost.factor,
+            function: function.into(),
+        }
+    }
+}
+
+impl From<segment::types::ScoreBoost> for ScoreBoost {
+    fn from(score_boost: segment::types::ScoreBoost) -> Self {
+        Self {
+            key: score_boost.key,
+            factor: score_boost.factor,
+            function: Some(BoostFunction::from(score_boost.function) as i32),
+        }
+    }
+}
+
+impl From<BoostFunction> for segment::types::BoostFunction {
+    fn from(value: BoostFunction) -> Self {
+        match value {
+            BoostFunction::Linear => segment::types::BoostFunction::Linear,
+            BoostFunction::Log1p => segment::types::BoostFunction::Log1p,
+            BoostFunction::Sqrt => segment::types::BoostFunction::Sqrt,
+        }
+    }
+}
+
+impl From<segment::types::BoostFunction> for BoostFunction {
+    fn from(value: segment::types::BoostFunction) -> Self {
+        match value {
+            segment::types::BoostFunction::Linear => BoostFunction::Linear,
+            segment::types::BoostFunction::Log1p => BoostFunction::Log1p,
+            segment::types::BoostFunction::Sqrt => BoostFunction::Sqrt,
+        }
+    }
+}
+
+impl From<OrderBy> for segment::types::OrderBy {
+    fn from(order_by: OrderBy) -> Self {
+        let direction = order_by
+            .direction
+            .and_then(Direction::from_i32)
+            .unwrap_or_default();
+        Self {
+            key: order_by.key,
+            direction: direction.into(),
+            start_from: order_by.start_from,
+        }
+    }
+}
+
+impl From<segment::types::OrderBy> for OrderBy {
+    fn from(order_by: segment::types::OrderBy) -> Self {
+        Self {
+            key: order_by.key,
+            direction: Some(Direction::from(order_by.direction) as i32),
+            start_from: order_by.start_from,
+        }
+    }
+}
+
+impl From<Direction> for segment::types::Direction {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Asc => segment::types::Direction::Asc,
+            Direction::Desc => segment::types::Direction::Desc,
+        }
+    }
+}
+
+impl From<segment::types::Direction> for Direction {
+    fn from(value: segment::types::Direction) -> Self {
+        match value {
+            segment::types::Direction::Asc => Direction::Asc,
+            segment::types::Direction::Desc => Direction::Desc,
         }
     }
 }
@@ -375,6 +504,7 @@ impl From<segment::types::PointIdType> for PointId {
             point_id_options: Some(match point_id {
                 segment::types::PointIdType::NumId(num) => PointIdOptions::Num(num),
                 segment::types::PointIdType::Uuid(uuid) => PointIdOptions::Uuid(uuid.to_string()),
+                segment::types::PointIdType::Str(id) => PointIdOptions::Str(id.as_str().to_owned()),
             }),
         }
     }
@@ -414,7 +544,10 @@ impl From<segment::types::ScoredPoint> for ScoredPoint {
     fn from(point: segment::types::ScoredPoint) -> Self {
         Self {
             id: Some(point.id.into()),
-            payload: point.payload.map(payload_to_proto).unwrap_or_default(),
+            payload: point
+                .payload
+                .map(|payload| payload_to_proto(segment::types::Payload::from_shared(payload)))
+                .unwrap_or_default(),
             score: point.score,
             version: point.version,
             vectors: point.vector.map(|v| v.into()),
@@ -489,6 +622,12 @@ impl TryFrom<PointId> for segment::types::PointIdType {
                 .map_err(|_err| {
                     Status::invalid_argument(format!("Unable to parse UUID: {uui_str}"))
                 }),
+            Some(PointIdOptions::Str(id)) if id.is_empty() => Err(Status::invalid_argument(
+                "String point ID can't be empty".to_string(),
+            )),
+            Some(PointIdOptions::Str(id)) => Ok(segment::types::PointIdType::Str(
+                segment::types::StrPointId::new(&id),
+            )),
             _ => Err(Status::invalid_argument(
                 "No ID options provided".to_string(),
             )),
@@ -622,6 +761,12 @@ impl TryFrom<Condition> for segment::types::Condition {
                 ConditionOneOf::IsNull(is_null) => {
                     Ok(segment::types::Condition::IsNull(is_null.into()))
                 }
+                ConditionOneOf::Nested(nested) => {
+                    Ok(segment::types::Condition::Nested(nested.try_into()?))
+                }
+                ConditionOneOf::HasVector(has_vector) => {
+                    Ok(segment::types::Condition::HasVector(has_vector.into()))
+                }
             };
         }
         Err(Status::invalid_argument("Malformed Condition type"))
@@ -638,6 +783,10 @@ impl From<segment::types::Condition> for Condition {
             segment::types::Condition::IsNull(is_null) => ConditionOneOf::IsNull(is_null.into()),
             segment::types::Condition::HasId(has_id) => ConditionOneOf::HasId(has_id.into()),
             segment::types::Condition::Filter(filter) => ConditionOneOf::Filter(filter.into()),
+            segment::types::Condition::Nested(nested) => ConditionOneOf::Nested(nested.into()),
+            segment::types::Condition::HasVector(has_vector) => {
+                ConditionOneOf::HasVector(has_vector.into())
+            }
         };
 
         Self {
@@ -678,6 +827,47 @@ impl From<segment::types::IsNullCondition> for IsNullCondition {
     }
 }
 
+impl From<HasVectorCondition> for segment::types::HasVectorCondition {
+    fn from(value: HasVectorCondition) -> Self {
+        segment::types::HasVectorCondition {
+            has_vector: value.has_vector,
+        }
+    }
+}
+
+impl From<segment::types::HasVectorCondition> for HasVectorCondition {
+    fn from(value: segment::types::HasVectorCondition) -> Self {
+        Self {
+            has_vector: value.has_vector,
+        }
+    }
+}
+
+impl TryFrom<NestedCondition> for segment::types::NestedCondition {
+    type Error = Status;
+
+    fn try_from(value: NestedCondition) -> Result<Self, Self::Error> {
+        match value.filter {
+            Some(filter) => Ok(segment::types::NestedCondition::new(
+                value.key,
+                filter.try_into()?,
+            )),
+            None => Err(Status::invalid_argument(
+                "Nested condition must have a filter",
+            )),
+        }
+    }
+}
+
+impl From<segment::types::NestedCondition> for NestedCondition {
+    fn from(value: segment::types::NestedCondition) -> Self {
+        Self {
+            key: value.nested.key,
+            filter: Some(value.nested.filter.into()),
+        }
+    }
+}
+
 impl TryFrom<HasIdCondition> for segment::types::HasIdCondition {
     type Error = Status;
 
@@ -709,17 +899,20 @@ impl TryFrom<FieldCondition> for segment::types::FieldCondition {
             geo_bounding_box,
             geo_radius,
             values_count,
+            geo_polygon,
         } = value;
 
         let geo_bounding_box =
             geo_bounding_box.map_or_else(|| Ok(None), |g| g.try_into().map(Some))?;
         let geo_radius = geo_radius.map_or_else(|| Ok(None), |g| g.try_into().map(Some))?;
+        let geo_polygon = geo_polygon.map_or_else(|| Ok(None), |g| g.try_into().map(Some))?;
         Ok(Self {
             key,
             r#match: r#match.map_or_else(|| Ok(None), |m| m.try_into().map(Some))?,
             range: range.map(|r| r.into()),
             geo_bounding_box,
             geo_radius,
+            geo_polygon,
             values_count: values_count.map(|r| r.into()),
         })
     }
@@ -733,11 +926,13 @@ impl From<segment::types::FieldCondition> for FieldCondition {
             range,
             geo_bounding_box,
             geo_radius,
+            geo_polygon,
             values_count,
         } = value;
 
         let geo_bounding_box = geo_bounding_box.map(|g| g.into());
         let geo_radius = geo_radius.map(|g| g.into());
+        let geo_polygon = geo_polygon.map(|g| g.into());
         Self {
             key,
             r#match: r#match.map(|m| m.into()),
@@ -745,6 +940,7 @@ impl From<segment::types::FieldCondition> for FieldCondition {
             geo_bounding_box,
             geo_radius,
             values_count: values_count.map(|r| r.into()),
+            geo_polygon,
         }
     }
 }
@@ -775,6 +971,53 @@ impl From<segment::types::GeoBoundingBox> for GeoBoundingBox {
     }
 }
 
+impl From<GeoLineString> for segment::types::GeoLineString {
+    fn from(value: GeoLineString) -> Self {
+        Self {
+            points: value.points.into_iter().map(|p| p.into()).collect(),
+        }
+    }
+}
+
+impl From<segment::types::GeoLineString> for GeoLineString {
+    fn from(value: segment::types::GeoLineString) -> Self {
+        Self {
+            points: value.points.into_iter().map(|p| p.into()).collect(),
+        }
+    }
+}
+
+impl TryFrom<GeoPolygon> for segment::types::GeoPolygon {
+    type Error = Status;
+
+    fn try_from(value: GeoPolygon) -> Result<Self, Self::Error> {
+        match value {
+            GeoPolygon {
+                exterior: Some(e),
+                interiors,
+            } => Ok(Self {
+                exterior: e.into(),
+                interiors: Some(interiors.into_iter().map(|l| l.into()).collect()),
+            }),
+            _ => Err(Status::invalid_argument("Malformed GeoPolygon type")),
+        }
+    }
+}
+
+impl From<segment::types::GeoPolygon> for GeoPolygon {
+    fn from(value: segment::types::GeoPolygon) -> Self {
+        Self {
+            exterior: Some(value.exterior.into()),
+            interiors: value
+                .interiors
+                .into_iter()
+                .flatten()
+                .map(|l| l.into())
+                .collect(),
+        }
+    }
+}
+
 impl TryFrom<GeoRadius> for segment::types::GeoRadius {
     type Error = Status;
 
@@ -866,6 +1109,21 @@ impl TryFrom<Match> for segment::types::Match {
                 MatchValue::Text(text) => segment::types::Match::Text(text.into()),
                 MatchValue::Keywords(kwds) => kwds.strings.into(),
                 MatchValue::Integers(ints) => ints.integers.into(),
+                MatchValue::ExceptKeywords(kwds) => {
+                    segment::types::Match::Except(segment::types::MatchExcept {
+                        except: segment::types::AnyVariants::Keywords(kwds.strings),
+                    })
+                }
+                MatchValue::ExceptIntegers(ints) => {
+                    segment::types::Match::Except(segment::types::MatchExcept {
+                        except: segment::types::AnyVariants::Integers(ints.integers),
+                    })
+                }
+                MatchValue::Insensitive(insensitive) => {
+                    segment::types::Match::Insensitive(segment::types::MatchInsensitive {
+                        insensitive,
+                    })
+                }
             }),
             _ => Err(Status::invalid_argument("Malformed Match condition")),
         }
@@ -891,6 +1149,17 @@ impl From<segment::types::Match> for Match {
                     MatchValue::Integers(RepeatedIntegers { integers })
                 }
             },
+            segment::types::Match::Except(except) => match except.except {
+                segment::types::AnyVariants::Keywords(strings) => {
+                    MatchValue::ExceptKeywords(RepeatedStrings { strings })
+                }
+                segment::types::AnyVariants::Integers(integers) => {
+                    MatchValue::ExceptIntegers(RepeatedIntegers { integers })
+                }
+            },
+            segment::types::Match::Insensitive(segment::types::MatchInsensitive {
+                insensitive,
+            }) => MatchValue::Insensitive(insensitive),
         };
         Self {
             match_value: Some(match_value),
@@ -907,6 +1176,33 @@ impl From<HnswConfigDiff> for segment::types::HnswConfig {
             max_indexing_threads: hnsw_config.max_indexing_threads.unwrap_or_default() as usize,
             on_disk: hnsw_config.on_disk,
             payload_m: hnsw_config.payload_m.map(|x| x as usize),
+            neighbor_selection: hnsw_config
+                .neighbor_selection
+                .and_then(|x| from_grpc_neighbor_selection(x).ok()),
+            seed: hnsw_config.seed,
+            incremental: hnsw_config.incremental,
+            ef_policy: hnsw_config.ef_policy.map(Into::into),
+            max_build_memory_mb: hnsw_config.max_build_memory_mb.map(|x| x as usize),
+        }
+    }
+}
+
+impl From<EfPolicy> for segment::types::EfPolicy {
+    fn from(ef_policy: EfPolicy) -> Self {
+        Self {
+            factor: ef_policy.factor,
+            min_ef: ef_policy.min_ef.map(|x| x as usize),
+            target_recall: ef_policy.target_recall,
+        }
+    }
+}
+
+impl From<segment::types::EfPolicy> for EfPolicy {
+    fn from(ef_policy: segment::types::EfPolicy) -> Self {
+        Self {
+            factor: ef_policy.factor,
+            min_ef: ef_policy.min_ef.map(|x| x as u64),
+            target_recall: ef_policy.target_recall,
         }
     }
 }
@@ -935,6 +1231,48 @@ impl TryFrom<Distance> for segment::types::Distance {
     }
 }
 
+impl From<segment::types::NeighborSelection> for NeighborSelection {
+    fn from(value: segment::types::NeighborSelection) -> Self {
+        match value {
+            segment::types::NeighborSelection::Simple => NeighborSelection::Simple,
+            segment::types::NeighborSelection::Heuristic => NeighborSelection::Heuristic,
+            segment::types::NeighborSelection::HeuristicKeepPruned => {
+                NeighborSelection::HeuristicKeepPruned
+            }
+        }
+    }
+}
+
+impl TryFrom<NeighborSelection> for segment::types::NeighborSelection {
+    type Error = Status;
+
+    fn try_from(value: NeighborSelection) -> Result<Self, Self::Error> {
+        Ok(match value {
+            NeighborSelection::UnknownNeighborSelection => {
+                return Err(Status::invalid_argument(
+                    "Malformed neighbor selection parameter: UnknownNeighborSelection",
+                ))
+            }
+            NeighborSelection::Simple => segment::types::NeighborSelection::Simple,
+            NeighborSelection::Heuristic => segment::types::NeighborSelection::Heuristic,
+            NeighborSelection::HeuristicKeepPruned => {
+                segment::types::NeighborSelection::HeuristicKeepPruned
+            }
+        })
+    }
+}
+
+pub fn from_grpc_neighbor_selection(
+    value: i32,
+) -> Result<segment::types::NeighborSelection, Status> {
+    match NeighborSelection::from_i32(value) {
+        None => Err(Status::invalid_argument(format!(
+            "Malformed neighbor selection parameter, unexpected value: {value}"
+        ))),
+        Some(grpc_selection) => Ok(grpc_selection.try_into()?),
+    }
+}
+
 pub fn from_grpc_dist(dist: i32) -> Result<segment::types::Distance, Status> {
     match Distance::from_i32(dist) {
         None => Err(Status::invalid_argument(format!(
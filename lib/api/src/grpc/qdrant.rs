@@ -122,6 +122,39 @@ pub struct HnswConfigDiff {
     /// Number of additional payload-aware links per node in the index graph. If not set - regular M parameter will be used.
     #[prost(uint64, optional, tag = "6")]
     pub payload_m: ::core::option::Option<u64>,
+    ///
+    /// Strategy of selecting neighbours of a new point during the index building. Default: Heuristic
+    #[prost(enumeration = "NeighborSelection", optional, tag = "7")]
+    pub neighbor_selection: ::core::option::Option<i32>,
+    ///
+    /// Seed for the random generator, used during the index building. If not set - random seed will be used.
+    #[prost(uint64, optional, tag = "8")]
+    pub seed: ::core::option::Option<u64>,
+    ///
+    /// Link points, inserted into an indexed appendable segment, into the graph right away. If false - new points are only indexed by the next rebuild of the index. Default: false
+    #[prost(bool, optional, tag = "9")]
+    pub incremental: ::core::option::Option<bool>,
+    ///
+    /// Policy of choosing `ef` of the search by the requested `top`, if `hnsw_ef` is not set in the search params. If not set - `ef_construct` is used as `ef`.
+    #[prost(message, optional, tag = "10")]
+    pub ef_policy: ::core::option::Option<EfPolicy>,
+    ///
+    /// Max memory of a single build of the index in megabytes. Builds, estimated to use more, are refused before they start. If not set or 0 - the memory of the builds is not limited.
+    #[prost(uint64, optional, tag = "11")]
+    pub max_build_memory_mb: ::core::option::Option<u64>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EfPolicy {
+    /// Multiplier of `top`: `ef = max(top * factor, min_ef)`. Default: 1
+    #[prost(float, optional, tag = "1")]
+    pub factor: ::core::option::Option<f32>,
+    /// Minimal `ef`, used for small `top`. Default: 0
+    #[prost(uint64, optional, tag = "2")]
+    pub min_ef: ::core::option::Option<u64>,
+    /// Recall, the factor is calibrated for after each build of the index. If set - `factor` is replaced with the calibrated one
+    #[prost(float, optional, tag = "3")]
+    pub target_recall: ::core::option::Option<f32>,
 }
 #[derive(validator::Validate)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -379,8 +412,15 @@ pub struct TextIndexParams {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeywordIndexParams {
+    /// If true - index lowercase values for case-insensitive match
+    #[prost(bool, optional, tag = "1")]
+    pub insensitive: ::core::option::Option<bool>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PayloadIndexParams {
-    #[prost(oneof = "payload_index_params::IndexParams", tags = "1")]
+    #[prost(oneof = "payload_index_params::IndexParams", tags = "1, 2")]
     pub index_params: ::core::option::Option<payload_index_params::IndexParams>,
 }
 /// Nested message and enum types in `PayloadIndexParams`.
@@ -391,6 +431,9 @@ pub mod payload_index_params {
         /// Parameters for text index
         #[prost(message, tag = "1")]
         TextIndexParams(super::TextIndexParams),
+        /// Parameters for keyword index
+        #[prost(message, tag = "2")]
+        KeywordIndexParams(super::KeywordIndexParams),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -660,6 +703,41 @@ impl QuantizationType {
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum NeighborSelection {
+    UnknownNeighborSelection = 0,
+    /// Link the closest candidates
+    Simple = 1,
+    /// Skip candidates, which are closer to an already selected neighbour than to the new point
+    Heuristic = 2,
+    /// Same as Heuristic, but fill the remaining links with the skipped candidates
+    HeuristicKeepPruned = 3,
+}
+impl NeighborSelection {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            NeighborSelection::UnknownNeighborSelection => "UnknownNeighborSelection",
+            NeighborSelection::Simple => "Simple",
+            NeighborSelection::Heuristic => "Heuristic",
+            NeighborSelection::HeuristicKeepPruned => "HeuristicKeepPruned",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "UnknownNeighborSelection" => Some(Self::UnknownNeighborSelection),
+            "Simple" => Some(Self::Simple),
+            "Heuristic" => Some(Self::Heuristic),
+            "HeuristicKeepPruned" => Some(Self::HeuristicKeepPruned),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum TokenizerType {
     Unknown = 0,
     Prefix = 1,
@@ -2066,7 +2144,7 @@ pub mod read_consistency {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PointId {
-    #[prost(oneof = "point_id::PointIdOptions", tags = "1, 2")]
+    #[prost(oneof = "point_id::PointIdOptions", tags = "1, 2, 3")]
     pub point_id_options: ::core::option::Option<point_id::PointIdOptions>,
 }
 /// Nested message and enum types in `PointId`.
@@ -2080,6 +2158,9 @@ pub mod point_id {
         /// UUID
         #[prost(string, tag = "2")]
         Uuid(::prost::alloc::string::String),
+        /// Arbitrary non-empty string ID
+        #[prost(string, tag = "3")]
+        Str(::prost::alloc::string::String),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -2345,6 +2426,19 @@ pub struct QuantizationSearchParams {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScoreBoost {
+    /// Payload key with the numeric value
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// Weight of the boost
+    #[prost(float, tag = "2")]
+    pub factor: f32,
+    /// Function, applied to the payload value. Default: linear
+    #[prost(enumeration = "BoostFunction", optional, tag = "3")]
+    pub function: ::core::option::Option<i32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SearchParams {
     ///
     /// Params relevant to HNSW index. Size of the beam in a beam-search.
@@ -2359,6 +2453,32 @@ pub struct SearchParams {
     /// If set to true, search will ignore quantized vector data
     #[prost(message, optional, tag = "3")]
     pub quantization: ::core::option::Option<QuantizationSearchParams>,
+    ///
+    /// Strict filtering. If set to true, search fails if the filter refers to a payload key without an index.
+    #[prost(bool, optional, tag = "4")]
+    pub strict_filter: ::core::option::Option<bool>,
+    ///
+    /// Params relevant to IVF index. Number of the closest clusters to scan.
+    /// Larger the value - more accurate the result, more time required for search.
+    #[prost(uint64, optional, tag = "5")]
+    pub ivf_nprobe: ::core::option::Option<u64>,
+    ///
+    /// Boost of the scores by a numeric payload value: score = score + factor * function(value).
+    /// Points without a numeric value are not boosted.
+    #[prost(message, optional, tag = "6")]
+    pub score_boost: ::core::option::Option<ScoreBoost>,
+    ///
+    /// Timeout of the search in each segment, in milliseconds. If reached, search fails, unless `partial_on_timeout` is set.
+    #[prost(uint64, optional, tag = "7")]
+    pub timeout_ms: ::core::option::Option<u64>,
+    ///
+    /// If set to true, the search, which reached the timeout, returns the results found so far instead of the failure.
+    #[prost(bool, optional, tag = "8")]
+    pub partial_on_timeout: ::core::option::Option<bool>,
+    ///
+    /// If set to true, telemetry of the search in each segment is collected: numbers of the scored points, index hops and filter checks, and times of the search phases.
+    #[prost(bool, optional, tag = "9")]
+    pub with_telemetry: ::core::option::Option<bool>,
 }
 #[derive(validator::Validate)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -2416,6 +2536,19 @@ pub struct SearchBatchPoints {
     #[prost(message, optional, tag = "3")]
     pub read_consistency: ::core::option::Option<ReadConsistency>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OrderBy {
+    /// Payload key to order by, the field must have the integer or float index
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// Direction of the order. Default: asc
+    #[prost(enumeration = "Direction", optional, tag = "2")]
+    pub direction: ::core::option::Option<i32>,
+    /// Start from this value, inclusive. If not provided - from the first value in the direction
+    #[prost(double, optional, tag = "3")]
+    pub start_from: ::core::option::Option<f64>,
+}
 #[derive(validator::Validate)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -2442,6 +2575,9 @@ pub struct ScrollPoints {
     /// Options for specifying read consistency guarantees
     #[prost(message, optional, tag = "8")]
     pub read_consistency: ::core::option::Option<ReadConsistency>,
+    /// Order the points by the values of the payload field instead of the ID
+    #[prost(message, optional, tag = "9")]
+    pub order_by: ::core::option::Option<OrderBy>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -2609,6 +2745,9 @@ pub struct ScrollResponse {
     /// Time spent to process
     #[prost(double, tag = "3")]
     pub time: f64,
+    /// Use this value of `order_by.start_from` with the offset for the next query
+    #[prost(double, optional, tag = "4")]
+    pub next_page_start_from: ::core::option::Option<f64>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -2625,6 +2764,14 @@ pub struct RetrievedPoint {
     pub payload: ::std::collections::HashMap<::prost::alloc::string::String, Value>,
     #[prost(message, optional, tag = "4")]
     pub vectors: ::core::option::Option<Vectors>,
+    ///
+    /// Version of the point - number of the last operation, which changed its vectors or payload
+    #[prost(uint64, optional, tag = "5")]
+    pub version: ::core::option::Option<u64>,
+    ///
+    /// Value of the `order_by` field of the point, if the points are scrolled in the order of the field
+    #[prost(double, optional, tag = "6")]
+    pub order_value: ::core::option::Option<f64>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -2669,7 +2816,7 @@ pub struct Filter {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Condition {
-    #[prost(oneof = "condition::ConditionOneOf", tags = "1, 2, 3, 4, 5")]
+    #[prost(oneof = "condition::ConditionOneOf", tags = "1, 2, 3, 4, 5, 6, 7")]
     pub condition_one_of: ::core::option::Option<condition::ConditionOneOf>,
 }
 /// Nested message and enum types in `Condition`.
@@ -2687,6 +2834,10 @@ pub mod condition {
         Filter(super::Filter),
         #[prost(message, tag = "5")]
         IsNull(super::IsNullCondition),
+        #[prost(message, tag = "6")]
+        Nested(super::NestedCondition),
+        #[prost(message, tag = "7")]
+        HasVector(super::HasVectorCondition),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -2709,6 +2860,23 @@ pub struct HasIdCondition {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HasVectorCondition {
+    /// Name of the vector
+    #[prost(string, tag = "1")]
+    pub has_vector: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NestedCondition {
+    /// Key of the array of objects, keys of the nested filter are relative to the objects
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// Filter, which has to be satisfied by a single object of the array
+    #[prost(message, optional, tag = "2")]
+    pub filter: ::core::option::Option<Filter>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct FieldCondition {
     #[prost(string, tag = "1")]
     pub key: ::prost::alloc::string::String,
@@ -2727,11 +2895,14 @@ pub struct FieldCondition {
     /// Check number of values for a specific field
     #[prost(message, optional, tag = "6")]
     pub values_count: ::core::option::Option<ValuesCount>,
+    /// Check if geo point is within a given polygon
+    #[prost(message, optional, tag = "7")]
+    pub geo_polygon: ::core::option::Option<GeoPolygon>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Match {
-    #[prost(oneof = "r#match::MatchValue", tags = "1, 2, 3, 4, 5, 6")]
+    #[prost(oneof = "r#match::MatchValue", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9")]
     pub match_value: ::core::option::Option<r#match::MatchValue>,
 }
 /// Nested message and enum types in `Match`.
@@ -2757,6 +2928,15 @@ pub mod r#match {
         /// Match multiple integers
         #[prost(message, tag = "6")]
         Integers(super::RepeatedIntegers),
+        /// Match any other value except those keywords
+        #[prost(message, tag = "7")]
+        ExceptKeywords(super::RepeatedStrings),
+        /// Match any other value except those integers
+        #[prost(message, tag = "8")]
+        ExceptIntegers(super::RepeatedIntegers),
+        /// Match string keyword ignoring case
+        #[prost(string, tag = "9")]
+        Insensitive(::prost::alloc::string::String),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -2805,6 +2985,25 @@ pub struct GeoRadius {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GeoLineString {
+    /// Ordered sequence of GeoPoints representing the line
+    #[prost(message, repeated, tag = "1")]
+    pub points: ::prost::alloc::vec::Vec<GeoPoint>,
+}
+/// For a valid GeoPolygon, both the exterior and interior GeoLineStrings must consist of a minimum of 4 points.
+/// Additionally, the first and last points of each GeoLineString must be the same.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GeoPolygon {
+    /// The exterior line bounds the surface
+    #[prost(message, optional, tag = "1")]
+    pub exterior: ::core::option::Option<GeoLineString>,
+    /// Interior lines (if present) bound holes within the surface
+    #[prost(message, repeated, tag = "2")]
+    pub interiors: ::prost::alloc::vec::Vec<GeoLineString>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ValuesCount {
     #[prost(uint64, optional, tag = "1")]
     pub lt: ::core::option::Option<u64>,
@@ -2959,6 +3158,66 @@ impl FieldType {
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum BoostFunction {
+    /// Value as is
+    Linear = 0,
+    /// ln(1 + value), negative values are not boosted
+    Log1p = 1,
+    /// Square root of the value, negative values are not boosted
+    Sqrt = 2,
+}
+impl BoostFunction {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            BoostFunction::Linear => "BoostFunctionLinear",
+            BoostFunction::Log1p => "BoostFunctionLog1p",
+            BoostFunction::Sqrt => "BoostFunctionSqrt",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "BoostFunctionLinear" => Some(Self::Linear),
+            "BoostFunctionLog1p" => Some(Self::Log1p),
+            "BoostFunctionSqrt" => Some(Self::Sqrt),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Direction {
+    /// From the smallest value to the largest
+    Asc = 0,
+    /// From the largest value to the smallest
+    Desc = 1,
+}
+impl Direction {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Direction::Asc => "Asc",
+            Direction::Desc => "Desc",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Asc" => Some(Self::Asc),
+            "Desc" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum UpdateStatus {
     UnknownUpdateStatus = 0,
     /// Update is received, but not processed yet
@@ -932,7 +932,7 @@ impl Collection {
         shard_selection: Option<ShardId>,
     ) -> CollectionResult<Vec<ScoredPoint>> {
         let retrieve_request = PointRequest {
-            ids: search_result.iter().map(|x| x.id).collect(),
+            ids: search_result.iter().map(|x| x.id.clone()).collect(),
             with_payload,
             with_vector,
         };
@@ -1009,34 +1009,47 @@ impl Collection {
             let target_shards = shards_holder.target_shard(shard_selection)?;
             let scroll_futures = target_shards.into_iter().map(|shard| {
                 shard.scroll_by(
-                    offset,
+                    offset.clone(),
                     limit,
                     &with_payload_interface,
                     &with_vector,
                     request.filter.as_ref(),
+                    request.order_by.as_ref(),
                     read_consistency,
                 )
             });
 
             try_join_all(scroll_futures).await?
         };
-        let mut points: Vec<_> = retrieved_points
-            .into_iter()
-            .flatten()
-            .sorted_by_key(|point| point.id)
-            .take(limit)
-            .collect();
+        let retrieved_points = retrieved_points.into_iter().flatten();
+        let mut points: Vec<_> = match &request.order_by {
+            Some(order_by) => retrieved_points
+                .sorted_by(|a, b| {
+                    order_by.compare(
+                        (a.order_value.unwrap_or_default(), &a.id),
+                        (b.order_value.unwrap_or_default(), &b.id),
+                    )
+                })
+                .take(limit)
+                .collect(),
+            None => retrieved_points
+                .sorted_by_key(|point| point.id.clone())
+                .take(limit)
+                .collect(),
+        };
 
-        let next_page_offset = if points.len() < limit {
+        let (next_page_offset, next_page_start_from) = if points.len() < limit {
             // This was the last page
-            None
+            (None, None)
         } else {
             // remove extra point, it would be a first point of the next page
-            Some(points.pop().unwrap().id)
+            let next_point = points.pop().unwrap();
+            (Some(next_point.id), next_point.order_value)
         };
         Ok(ScrollResult {
             points,
             next_page_offset,
+            next_page_start_from,
         })
     }
 
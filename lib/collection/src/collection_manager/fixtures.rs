@@ -44,7 +44,9 @@ pub fn random_multi_vec_segment(
         let point_id: PointIdType = rnd.gen_range(1..100_000_000).into();
         let payload_value = rnd.gen_range(1..1_000);
         let payload: Payload = json!({ payload_key: vec![payload_value] }).into();
-        segment.upsert_vector(opnum, point_id, &vectors).unwrap();
+        segment
+            .upsert_vector(opnum, point_id.clone(), &vectors)
+            .unwrap();
         segment.set_payload(opnum, point_id, &payload).unwrap();
     }
     segment
@@ -60,7 +62,11 @@ pub fn random_segment(path: &Path, opnum: SeqNumberType, num_vectors: u64, dim:
         let payload_value = rnd.gen_range(1..1_000);
         let payload: Payload = json!({ payload_key: vec![payload_value] }).into();
         segment
-            .upsert_vector(opnum, point_id, &only_default_vector(&random_vector))
+            .upsert_vector(
+                opnum,
+                point_id.clone(),
+                &only_default_vector(&random_vector),
+            )
             .unwrap();
         segment.set_payload(opnum, point_id, &payload).unwrap();
     }
@@ -1,17 +1,33 @@
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use parking_lot::{RwLock, RwLockUpgradableReadGuard};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use segment::common::grouping::search_groups;
+use segment::common::jsonl::JsonlPoint;
+use segment::common::warm_up::WarmUpParts;
 use segment::data_types::named_vectors::NamedVectors;
 use segment::data_types::vectors::VectorElementType;
-use segment::entry::entry_point::{OperationResult, SegmentEntry, SegmentFailedState};
+use segment::entry::entry_point::{
+    OperationError, OperationResult, SegmentEntry, SegmentFailedState,
+};
 use segment::index::field_index::CardinalityEstimation;
+use segment::index::hnsw_index::graph_layers::IndexStats;
+use segment::index::index_build_handle::IndexBuildHandle;
+use segment::index::query_plan::QueryPlanExplain;
+use segment::index::SearchCostEstimate;
+use segment::payload_storage::filter_explain::{FilterExplain, FilterMatchCounts};
 use segment::telemetry::SegmentTelemetry;
 use segment::types::{
-    Condition, Filter, Payload, PayloadFieldSchema, PayloadKeyType, PayloadKeyTypeRef, PointIdType,
-    ScoredPoint, SearchParams, SegmentConfig, SegmentInfo, SegmentType, SeqNumberType, WithPayload,
+    Condition, Filter, Indexes, OrderBy, Payload, PayloadFieldSchema, PayloadKeyType,
+    PayloadKeyTypeRef, PayloadSelector, PointGroup, PointIdType, ScoredPoint, SearchParams,
+    SegmentConfig, SegmentInfo, SegmentType, SeqNumberType, SnapshotContents, WithPayload,
     WithVector,
 };
 
@@ -100,25 +116,25 @@ impl ProxySegment {
         }
         let wrapped_segment = self.wrapped_segment.get();
         let wrapped_segment_guard = wrapped_segment.read();
-        if !wrapped_segment_guard.has_point(point_id) {
+        if !wrapped_segment_guard.has_point(point_id.clone()) {
             // Point is not in wrapped segment
             return Ok(false);
         }
 
         let (all_vectors, payload) = (
-            wrapped_segment_guard.all_vectors(point_id)?,
-            wrapped_segment_guard.payload(point_id)?,
+            wrapped_segment_guard.all_vectors(point_id.clone())?,
+            wrapped_segment_guard.payload(point_id.clone())?,
         );
 
         {
             let mut deleted_points_write = RwLockUpgradableReadGuard::upgrade(deleted_points_guard);
-            deleted_points_write.insert(point_id);
+            deleted_points_write.insert(point_id.clone());
         }
 
         let segment_arc = self.write_segment.get();
         let mut write_segment = segment_arc.write();
 
-        write_segment.upsert_vector(op_num, point_id, &all_vectors)?;
+        write_segment.upsert_vector(op_num, point_id.clone(), &all_vectors)?;
         write_segment.set_full_payload(op_num, point_id, &payload)?;
 
         Ok(true)
@@ -129,23 +145,10 @@ impl ProxySegment {
         filter: Option<&Filter>,
         deleted_points: &HashSet<PointIdType>,
     ) -> Filter {
-        let wrapper_condition = Condition::HasId(deleted_points.clone().into());
+        let wrapper_filter = Filter::new_must_not(Condition::HasId(deleted_points.clone().into()));
         match filter {
-            None => Filter::new_must_not(wrapper_condition),
-            Some(f) => {
-                let mut new_filter = f.clone();
-                let must_not = new_filter.must_not;
-
-                let new_must_not = match must_not {
-                    None => Some(vec![wrapper_condition]),
-                    Some(mut conditions) => {
-                        conditions.push(wrapper_condition);
-                        Some(conditions)
-                    }
-                };
-                new_filter.must_not = new_must_not;
-                new_filter
-            }
+            None => wrapper_filter,
+            Some(f) => f.clone().merge_owned(wrapper_filter),
         }
     }
 }
@@ -163,7 +166,7 @@ impl SegmentEntry for ProxySegment {
         self.write_segment
             .get()
             .read()
-            .point_version(point_id)
+            .point_version(point_id.clone())
             .or_else(|| self.wrapped_segment.get().read().point_version(point_id))
     }
 
@@ -283,27 +286,73 @@ impl SegmentEntry for ProxySegment {
         Ok(wrapped_results)
     }
 
+    fn search_groups(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        group_by: &PayloadKeyType,
+        group_size: usize,
+        groups: usize,
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<PointGroup>> {
+        search_groups(
+            self,
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            group_by,
+            group_size,
+            groups,
+            filter,
+            params,
+        )
+    }
+
     fn upsert_vector(
         &mut self,
         op_num: SeqNumberType,
         point_id: PointIdType,
         vectors: &NamedVectors,
     ) -> OperationResult<bool> {
-        self.move_if_exists(op_num, point_id)?;
+        self.move_if_exists(op_num, point_id.clone())?;
         self.write_segment
             .get()
             .write()
             .upsert_vector(op_num, point_id, vectors)
     }
 
+    fn upsert_vectors(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+    ) -> OperationResult<Vec<bool>> {
+        for point_id in point_ids {
+            self.move_if_exists(op_num, point_id.clone())?;
+        }
+        self.write_segment
+            .get()
+            .write()
+            .upsert_vectors(op_num, point_ids, vectors)
+    }
+
     fn delete_point(
         &mut self,
         op_num: SeqNumberType,
         point_id: PointIdType,
     ) -> OperationResult<bool> {
         let mut was_deleted = false;
-        if self.wrapped_segment.get().read().has_point(point_id) {
-            self.deleted_points.write().insert(point_id);
+        if self
+            .wrapped_segment
+            .get()
+            .read()
+            .has_point(point_id.clone())
+        {
+            self.deleted_points.write().insert(point_id.clone());
             was_deleted = true;
         }
         let was_deleted_in_writable = self
@@ -321,7 +370,7 @@ impl SegmentEntry for ProxySegment {
         point_id: PointIdType,
         full_payload: &Payload,
     ) -> OperationResult<bool> {
-        self.move_if_exists(op_num, point_id)?;
+        self.move_if_exists(op_num, point_id.clone())?;
         self.write_segment
             .get()
             .write()
@@ -334,7 +383,7 @@ impl SegmentEntry for ProxySegment {
         point_id: PointIdType,
         payload: &Payload,
     ) -> OperationResult<bool> {
-        self.move_if_exists(op_num, point_id)?;
+        self.move_if_exists(op_num, point_id.clone())?;
         self.write_segment
             .get()
             .write()
@@ -347,7 +396,7 @@ impl SegmentEntry for ProxySegment {
         point_id: PointIdType,
         key: PayloadKeyTypeRef,
     ) -> OperationResult<bool> {
-        self.move_if_exists(op_num, point_id)?;
+        self.move_if_exists(op_num, point_id.clone())?;
         self.write_segment
             .get()
             .write()
@@ -359,7 +408,7 @@ impl SegmentEntry for ProxySegment {
         op_num: SeqNumberType,
         point_id: PointIdType,
     ) -> OperationResult<bool> {
-        self.move_if_exists(op_num, point_id)?;
+        self.move_if_exists(op_num, point_id.clone())?;
         self.write_segment
             .get()
             .write()
@@ -380,7 +429,7 @@ impl SegmentEntry for ProxySegment {
             {
                 let write_segment = self.write_segment.get();
                 let segment_guard = write_segment.read();
-                if segment_guard.has_point(point_id) {
+                if segment_guard.has_point(point_id.clone()) {
                     return segment_guard.vector(vector_name, point_id);
                 }
             }
@@ -401,19 +450,22 @@ impl SegmentEntry for ProxySegment {
             .vector_data
             .keys()
         {
-            result.insert(vector_name.clone(), self.vector(vector_name, point_id)?);
+            result.insert(
+                vector_name.clone(),
+                self.vector(vector_name, point_id.clone())?,
+            );
         }
         Ok(result)
     }
 
-    fn payload(&self, point_id: PointIdType) -> OperationResult<Payload> {
+    fn payload(&self, point_id: PointIdType) -> OperationResult<Arc<Payload>> {
         return if self.deleted_points.read().contains(&point_id) {
             self.write_segment.get().read().payload(point_id)
         } else {
             {
                 let write_segment = self.write_segment.get();
                 let segment_guard = write_segment.read();
-                if segment_guard.has_point(point_id) {
+                if segment_guard.has_point(point_id.clone()) {
                     return segment_guard.payload(point_id);
                 }
             }
@@ -421,6 +473,31 @@ impl SegmentEntry for ProxySegment {
         };
     }
 
+    fn selected_payload(
+        &self,
+        point_id: PointIdType,
+        selector: &PayloadSelector,
+    ) -> OperationResult<Payload> {
+        return if self.deleted_points.read().contains(&point_id) {
+            self.write_segment
+                .get()
+                .read()
+                .selected_payload(point_id, selector)
+        } else {
+            {
+                let write_segment = self.write_segment.get();
+                let segment_guard = write_segment.read();
+                if segment_guard.has_point(point_id.clone()) {
+                    return segment_guard.selected_payload(point_id, selector);
+                }
+            }
+            self.wrapped_segment
+                .get()
+                .read()
+                .selected_payload(point_id, selector)
+        };
+    }
+
     /// Not implemented for proxy
     fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
         // iter_points is not available for Proxy implementation
@@ -439,14 +516,15 @@ impl SegmentEntry for ProxySegment {
             self.wrapped_segment
                 .get()
                 .read()
-                .read_filtered(offset, limit, filter)
+                .read_filtered(offset.clone(), limit, filter)
         } else {
             let wrapped_filter =
                 self.add_deleted_points_condition_to_filter(filter, &deleted_points);
-            self.wrapped_segment
-                .get()
-                .read()
-                .read_filtered(offset, limit, Some(&wrapped_filter))
+            self.wrapped_segment.get().read().read_filtered(
+                offset.clone(),
+                limit,
+                Some(&wrapped_filter),
+            )
         };
         let mut write_segment_points = self
             .write_segment
@@ -458,10 +536,77 @@ impl SegmentEntry for ProxySegment {
         read_points
     }
 
+    fn read_ordered_filtered<'a>(
+        &'a self,
+        limit: Option<usize>,
+        filter: Option<&'a Filter>,
+        order_by: &'a OrderBy,
+        offset: Option<PointIdType>,
+    ) -> OperationResult<Vec<(f64, PointIdType)>> {
+        let deleted_points = self.deleted_points.read();
+        let mut read_points = if deleted_points.is_empty() {
+            self.wrapped_segment.get().read().read_ordered_filtered(
+                limit,
+                filter,
+                order_by,
+                offset.clone(),
+            )?
+        } else {
+            let wrapped_filter =
+                self.add_deleted_points_condition_to_filter(filter, &deleted_points);
+            self.wrapped_segment.get().read().read_ordered_filtered(
+                limit,
+                Some(&wrapped_filter),
+                order_by,
+                offset.clone(),
+            )?
+        };
+        let mut write_segment_points = self
+            .write_segment
+            .get()
+            .read()
+            .read_ordered_filtered(limit, filter, order_by, offset)?;
+        read_points.append(&mut write_segment_points);
+        read_points.sort_unstable_by(|a, b| order_by.compare((a.0, &a.1), (b.0, &b.1)));
+        if let Some(limit) = limit {
+            read_points.truncate(limit);
+        }
+        Ok(read_points)
+    }
+
+    /// Points of both segments are read by id, only the ids are collected up front
+    fn export_jsonl(
+        &self,
+        writer: &mut dyn Write,
+        with_vectors: bool,
+        filter: Option<&Filter>,
+    ) -> OperationResult<usize> {
+        let point_ids = self.read_filtered(None, None, filter);
+        for point_id in &point_ids {
+            let vector = if with_vectors {
+                Some(self.all_vectors(point_id.clone())?.into())
+            } else {
+                None
+            };
+            let point = JsonlPoint {
+                id: point_id.clone(),
+                vector,
+                payload: Payload::from_shared(self.payload(point_id.clone())?),
+            };
+            point.write_line(writer)?;
+        }
+        writer.flush()?;
+        Ok(point_ids.len())
+    }
+
     /// Read points in [from; to) range
     fn read_range(&self, from: Option<PointIdType>, to: Option<PointIdType>) -> Vec<PointIdType> {
         let deleted_points = self.deleted_points.read();
-        let mut read_points = self.wrapped_segment.get().read().read_range(from, to);
+        let mut read_points = self
+            .wrapped_segment
+            .get()
+            .read()
+            .read_range(from.clone(), to.clone());
         if !deleted_points.is_empty() {
             read_points.retain(|idx| !deleted_points.contains(idx))
         }
@@ -471,11 +616,43 @@ impl SegmentEntry for ProxySegment {
         read_points
     }
 
+    fn sample_ids<'a>(
+        &'a self,
+        filter: Option<&'a Filter>,
+        k: usize,
+        rng_seed: Option<u64>,
+    ) -> Vec<PointIdType> {
+        let deleted_points = self.deleted_points.read();
+        let mut sampled_points = self.wrapped_segment.get().read().sample_ids(
+            filter,
+            k + deleted_points.len(),
+            rng_seed,
+        );
+        if !deleted_points.is_empty() {
+            sampled_points.retain(|idx| !deleted_points.contains(idx));
+        }
+        let mut write_segment_points = self
+            .write_segment
+            .get()
+            .read()
+            .sample_ids(filter, k, rng_seed);
+        sampled_points.append(&mut write_segment_points);
+        if sampled_points.len() > k {
+            let mut rng = match rng_seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            sampled_points.shuffle(&mut rng);
+            sampled_points.truncate(k);
+        }
+        sampled_points
+    }
+
     fn has_point(&self, point_id: PointIdType) -> bool {
         return if self.deleted_points.read().contains(&point_id) {
             self.write_segment.get().read().has_point(point_id)
         } else {
-            self.write_segment.get().read().has_point(point_id)
+            self.write_segment.get().read().has_point(point_id.clone())
                 || self.wrapped_segment.get().read().has_point(point_id)
         };
     }
@@ -554,7 +731,12 @@ impl SegmentEntry for ProxySegment {
             ram_usage_bytes: wrapped_info.ram_usage_bytes + write_info.ram_usage_bytes,
             disk_usage_bytes: wrapped_info.disk_usage_bytes + write_info.disk_usage_bytes,
             is_appendable: false,
+            index_outdated: wrapped_info.index_outdated,
             index_schema: wrapped_info.index_schema,
+            vector_indexes: wrapped_info.vector_indexes,
+            flush_policy: write_info.flush_policy,
+            last_flush_time: write_info.last_flush_time,
+            last_flush_error: write_info.last_flush_error,
         }
     }
 
@@ -642,6 +824,34 @@ impl SegmentEntry for ProxySegment {
         Ok(true)
     }
 
+    fn update_index_config(
+        &mut self,
+        _op_num: SeqNumberType,
+        _config: Indexes,
+        _rebuild: bool,
+    ) -> OperationResult<bool> {
+        // Wrapped segment is being optimized, optimized segment is built with the collection config
+        Ok(false)
+    }
+
+    fn rebuild_index(
+        &mut self,
+        _op_num: SeqNumberType,
+        _stopped: Option<&AtomicBool>,
+    ) -> OperationResult<bool> {
+        Ok(false)
+    }
+
+    fn build_index_in_background(&self, _config: Indexes) -> OperationResult<IndexBuildHandle> {
+        Err(OperationError::service_error(
+            "Vector index can't be built for proxy segment",
+        ))
+    }
+
+    fn install_built_index(&mut self, _handle: IndexBuildHandle) -> OperationResult<bool> {
+        Ok(false)
+    }
+
     fn get_indexed_fields(&self) -> HashMap<PayloadKeyType, PayloadFieldSchema> {
         let indexed_fields = self.wrapped_segment.get().read().get_indexed_fields();
         indexed_fields
@@ -687,6 +897,146 @@ impl SegmentEntry for ProxySegment {
         Ok(deleted_points)
     }
 
+    fn explain_filter(
+        &self,
+        filter: &Filter,
+        point_id: PointIdType,
+    ) -> OperationResult<FilterExplain> {
+        {
+            let write_segment = self.write_segment.get();
+            let segment_guard = write_segment.read();
+            if segment_guard.has_point(point_id.clone())
+                || self.deleted_points.read().contains(&point_id)
+            {
+                return segment_guard.explain_filter(filter, point_id);
+            }
+        }
+        self.wrapped_segment
+            .get()
+            .read()
+            .explain_filter(filter, point_id)
+    }
+
+    fn explain_filter_counts(&self, filter: &Filter) -> OperationResult<FilterMatchCounts> {
+        let mut counts = FilterMatchCounts::new(filter);
+        {
+            let deleted_points = self.deleted_points.read();
+            let wrapped_segment = self.wrapped_segment.get();
+            let segment_guard = wrapped_segment.read();
+            for point_id in segment_guard.read_range(None, None) {
+                if !deleted_points.contains(&point_id) {
+                    counts.add(&segment_guard.explain_filter(filter, point_id)?);
+                }
+            }
+        }
+        let write_segment = self.write_segment.get();
+        let segment_guard = write_segment.read();
+        for point_id in segment_guard.read_range(None, None) {
+            counts.add(&segment_guard.explain_filter(filter, point_id)?);
+        }
+        Ok(counts)
+    }
+
+    /// Recall of the wrapped segment, which carries the vector index
+    fn estimate_recall(
+        &self,
+        vector_name: &str,
+        sample_queries: usize,
+        top: usize,
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<f64> {
+        self.wrapped_segment.get().read().estimate_recall(
+            vector_name,
+            sample_queries,
+            top,
+            filter,
+            params,
+        )
+    }
+
+    fn index_stats(
+        &self,
+        vector_name: &str,
+        check_reachability: bool,
+    ) -> OperationResult<Option<IndexStats>> {
+        self.wrapped_segment
+            .get()
+            .read()
+            .index_stats(vector_name, check_reachability)
+    }
+
+    fn warm_up(&self, parts: WarmUpParts) -> OperationResult<usize> {
+        let wrapped_bytes = self.wrapped_segment.get().read().warm_up(parts)?;
+        let write_bytes = self.write_segment.get().read().warm_up(parts)?;
+        Ok(wrapped_bytes + write_bytes)
+    }
+
+    /// Plan of the wrapped segment, the write segment is expected to be small
+    fn explain_search(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<QueryPlanExplain> {
+        let deleted_points = self.deleted_points.read();
+        if deleted_points.is_empty() {
+            self.wrapped_segment.get().read().explain_search(
+                vector_name,
+                vector,
+                filter,
+                top,
+                params,
+            )
+        } else {
+            let wrapped_filter =
+                self.add_deleted_points_condition_to_filter(filter, &deleted_points);
+            self.wrapped_segment.get().read().explain_search(
+                vector_name,
+                vector,
+                Some(&wrapped_filter),
+                top,
+                params,
+            )
+        }
+    }
+
+    fn estimate_search_cost(
+        &self,
+        vector_name: &str,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<SearchCostEstimate> {
+        let deleted_points = self.deleted_points.read();
+        let wrapped_estimate = if deleted_points.is_empty() {
+            self.wrapped_segment.get().read().estimate_search_cost(
+                vector_name,
+                filter,
+                top,
+                params,
+            )?
+        } else {
+            let wrapped_filter =
+                self.add_deleted_points_condition_to_filter(filter, &deleted_points);
+            self.wrapped_segment.get().read().estimate_search_cost(
+                vector_name,
+                Some(&wrapped_filter),
+                top,
+                params,
+            )?
+        };
+        let write_estimate = self.write_segment.get().read().estimate_search_cost(
+            vector_name,
+            filter,
+            top,
+            params,
+        )?;
+        Ok(wrapped_estimate.combine(write_estimate))
+    }
+
     fn vector_dim(&self, vector_name: &str) -> OperationResult<usize> {
         self.write_segment.get().read().vector_dim(vector_name)
     }
@@ -695,7 +1045,11 @@ impl SegmentEntry for ProxySegment {
         self.write_segment.get().read().vector_dims()
     }
 
-    fn take_snapshot(&self, snapshot_dir_path: &Path) -> OperationResult<PathBuf> {
+    fn take_snapshot(
+        &self,
+        snapshot_dir_path: &Path,
+        contents: SnapshotContents,
+    ) -> OperationResult<PathBuf> {
         log::info!(
             "Taking a snapshot of a proxy segment into {:?}",
             snapshot_dir_path
@@ -706,7 +1060,7 @@ impl SegmentEntry for ProxySegment {
             let wrapped_segment_guard = wrapped_segment_arc.read();
 
             // snapshot wrapped segment data into the temporary dir
-            wrapped_segment_guard.take_snapshot(snapshot_dir_path)?
+            wrapped_segment_guard.take_snapshot(snapshot_dir_path, contents)?
         };
 
         // snapshot write_segment
@@ -714,7 +1068,7 @@ impl SegmentEntry for ProxySegment {
         let write_segment_guard = write_segment_rw.read();
 
         // Write segment is not unique to the proxy segment, therefore it might overwrite an existing snapshot.
-        write_segment_guard.take_snapshot(snapshot_dir_path)?;
+        write_segment_guard.take_snapshot(snapshot_dir_path, contents)?;
 
         Ok(archive_path)
     }
@@ -1166,8 +1520,12 @@ mod tests {
         let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
         eprintln!("Snapshot into {:?}", snapshot_dir.path());
 
-        proxy_segment.take_snapshot(snapshot_dir.path()).unwrap();
-        proxy_segment2.take_snapshot(snapshot_dir.path()).unwrap();
+        proxy_segment
+            .take_snapshot(snapshot_dir.path(), Default::default())
+            .unwrap();
+        proxy_segment2
+            .take_snapshot(snapshot_dir.path(), Default::default())
+            .unwrap();
 
         // validate that 3 archives were created:
         // wrapped_segment1, wrapped_segment2 & shared write_segment
@@ -1,4 +1,5 @@
 use std::cmp::{max, min};
+use std::collections::hash_map::Entry;
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::ops::{Deref, Mul};
 use std::path::Path;
@@ -11,7 +12,7 @@ use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
 use segment::entry::entry_point::{OperationError, OperationResult, SegmentEntry};
 use segment::segment::Segment;
-use segment::types::{PointIdType, SeqNumberType};
+use segment::types::{PayloadIndexInfo, PayloadKeyType, PointIdType, SegmentType, SeqNumberType};
 
 use crate::collection_manager::holders::proxy_segment::ProxySegment;
 use crate::operations::types::CollectionError;
@@ -120,6 +121,19 @@ pub struct SegmentHolder {
 
 pub type LockedSegmentHolder = Arc<RwLock<SegmentHolder>>;
 
+/// Counts and payload schema, aggregated over all segments of the holder
+#[derive(Debug, Default)]
+pub struct SegmentsInfo {
+    pub segments_count: usize,
+    pub points_count: usize,
+    pub vectors_count: usize,
+    /// Vectors in the indexed segments, for proxy segments - in the wrapped ones
+    pub indexed_vectors_count: usize,
+    /// Some of the segments are special, e.g. are being optimized
+    pub has_special_segments: bool,
+    pub payload_schema: HashMap<PayloadKeyType, PayloadIndexInfo>,
+}
+
 impl<'s> SegmentHolder {
     pub fn iter(&'s self) -> impl Iterator<Item = (&SegmentId, &LockedSegment)> + 's {
         self.segments.iter()
@@ -224,7 +238,7 @@ impl<'s> SegmentHolder {
     fn segment_points(&self, ids: &[PointIdType], segment: &dyn SegmentEntry) -> Vec<PointIdType> {
         ids.iter()
             .cloned()
-            .filter(|id| segment.has_point(*id))
+            .filter(|id| segment.has_point(id.clone()))
             .collect()
     }
 
@@ -344,7 +358,7 @@ impl<'s> SegmentHolder {
         let mut applied_points: HashSet<PointIdType> = Default::default();
 
         let _applied_points_count = self.apply_points(ids, |point_id, _idx, write_segment| {
-            if let Some(point_version) = write_segment.point_version(point_id) {
+            if let Some(point_version) = write_segment.point_version(point_id.clone()) {
                 if point_version >= op_num {
                     applied_points.insert(point_id);
                     return Ok(false);
@@ -352,20 +366,28 @@ impl<'s> SegmentHolder {
             }
 
             let is_applied = if write_segment.is_appendable() {
-                f(point_id, write_segment)?
+                f(point_id.clone(), write_segment)?
             } else {
                 self.aloha_random_write(
                     &appendable_segments,
                     |_appendable_idx, appendable_write_segment| {
-                        let all_vectors = write_segment.all_vectors(point_id)?;
-                        let payload = write_segment.payload(point_id)?;
-
-                        appendable_write_segment.upsert_vector(op_num, point_id, &all_vectors)?;
-                        appendable_write_segment.set_full_payload(op_num, point_id, &payload)?;
-
-                        write_segment.delete_point(op_num, point_id)?;
-
-                        f(point_id, appendable_write_segment)
+                        let all_vectors = write_segment.all_vectors(point_id.clone())?;
+                        let payload = write_segment.payload(point_id.clone())?;
+
+                        appendable_write_segment.upsert_vector(
+                            op_num,
+                            point_id.clone(),
+                            &all_vectors,
+                        )?;
+                        appendable_write_segment.set_full_payload(
+                            op_num,
+                            point_id.clone(),
+                            &payload,
+                        )?;
+
+                        write_segment.delete_point(op_num, point_id.clone())?;
+
+                        f(point_id.clone(), appendable_write_segment)
                     },
                 )?
             };
@@ -383,7 +405,11 @@ impl<'s> SegmentHolder {
         for segment in self.segments.values() {
             let segment_arc = segment.get();
             let read_segment = segment_arc.read();
-            for point in ids.iter().cloned().filter(|id| read_segment.has_point(*id)) {
+            for point in ids
+                .iter()
+                .cloned()
+                .filter(|id| read_segment.has_point(id.clone()))
+            {
                 let is_ok = f(point, &read_segment)?;
                 read_points += is_ok as usize;
             }
@@ -442,7 +468,7 @@ impl<'s> SegmentHolder {
         for segment in self.segments.values() {
             let segment_lock = segment.get();
             let read_segment = segment_lock.read();
-            read_segment.take_snapshot(snapshot_dir_path)?;
+            read_segment.take_snapshot(snapshot_dir_path, Default::default())?;
         }
         Ok(())
     }
@@ -453,6 +479,53 @@ impl<'s> SegmentHolder {
         }
     }
 
+    /// Aggregate info of all segments
+    ///
+    /// Points, which are in several segments, are counted once per segment.
+    pub fn info(&self) -> SegmentsInfo {
+        let mut info = SegmentsInfo::default();
+        for segment in self.segments.values() {
+            info.segments_count += 1;
+
+            let segment_info = match segment {
+                LockedSegment::Original(original_segment) => {
+                    let segment_info = original_segment.read().info();
+                    if segment_info.segment_type == SegmentType::Indexed {
+                        info.indexed_vectors_count += segment_info.num_vectors;
+                    }
+                    segment_info
+                }
+                LockedSegment::Proxy(proxy_segment) => {
+                    let proxy_segment_lock = proxy_segment.read();
+                    let proxy_segment_info = proxy_segment_lock.info();
+
+                    let wrapped_info = proxy_segment_lock.wrapped_segment.get().read().info();
+                    if wrapped_info.segment_type == SegmentType::Indexed {
+                        info.indexed_vectors_count += wrapped_info.num_vectors;
+                    }
+                    proxy_segment_info
+                }
+            };
+
+            if segment_info.segment_type == SegmentType::Special {
+                info.has_special_segments = true;
+            }
+            info.vectors_count += segment_info.num_vectors;
+            info.points_count += segment_info.num_points;
+            for (key, val) in segment_info.index_schema {
+                match info.payload_schema.entry(key) {
+                    Entry::Occupied(o) => {
+                        o.into_mut().points += val.points;
+                    }
+                    Entry::Vacant(v) => {
+                        v.insert(val);
+                    }
+                }
+            }
+        }
+        info
+    }
+
     /// Duplicated points can appear in case of interrupted optimization.
     /// LocalShard can still work with duplicated points, but it is better to remove them.
     /// Duplicated points should not affect the search results.
@@ -471,7 +544,7 @@ impl<'s> SegmentHolder {
             let segment_arc = locked_segment.get();
             let read_segment = segment_arc.read();
             for point_id in read_segment.iter_points() {
-                let point_version_opt = read_segment.point_version(point_id);
+                let point_version_opt = read_segment.point_version(point_id.clone());
                 let seen = seen_points.get(&point_id).cloned();
 
                 // Ignore points without version, those are partially updated and will be overwritten
@@ -488,7 +561,7 @@ impl<'s> SegmentHolder {
                         } else {
                             // This version is newer
                             // Remove point from other segment and update seen
-                            seen_points.insert(point_id, (segment_id, point_version));
+                            seen_points.insert(point_id.clone(), (segment_id, point_version));
                             points_to_remove
                                 .entry(seen_segment_id)
                                 .or_default()
@@ -508,7 +581,7 @@ impl<'s> SegmentHolder {
             let segment_arc = locked_segment.get();
             let mut write_segment = segment_arc.write();
             for point_id in points {
-                if let Some(point_version) = write_segment.point_version(point_id) {
+                if let Some(point_version) = write_segment.point_version(point_id.clone()) {
                     removed_points += 1;
                     write_segment.delete_point(point_version, point_id)?;
                 }
@@ -524,7 +597,7 @@ mod tests {
     use std::{thread, time};
 
     use segment::segment_constructor::simple_segment_constructor::build_simple_segment;
-    use segment::types::Distance;
+    use segment::types::{Distance, PayloadSchemaType};
     use serde_json::json;
     use tempfile::Builder;
 
@@ -608,7 +681,7 @@ mod tests {
                 op_num,
                 &[1.into(), 2.into(), 11.into(), 12.into()],
                 |point_id, segment| {
-                    processed_points.push(point_id);
+                    processed_points.push(point_id.clone());
                     assert!(segment.has_point(point_id));
                     Ok(true)
                 },
@@ -669,6 +742,30 @@ mod tests {
         assert!(!holder.get(sid1).unwrap().get().read().has_point(5.into()));
     }
 
+    #[test]
+    fn test_info() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+
+        let mut segment1 = build_segment_1(dir.path());
+        segment1
+            .create_field_index(100, "color", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+        let segment2 = build_segment_2(dir.path());
+
+        let mut holder = SegmentHolder::default();
+        let _sid1 = holder.add(segment1);
+        let _sid2 = holder.add(segment2);
+
+        let info = holder.info();
+        assert_eq!(info.segments_count, 2);
+        // Points 4 and 5 are in both segments
+        assert_eq!(info.points_count, 12);
+        assert_eq!(info.vectors_count, 12);
+        assert_eq!(info.indexed_vectors_count, 0);
+        assert!(!info.has_special_segments);
+        assert_eq!(info.payload_schema["color"].points, 5);
+    }
+
     #[test]
     fn test_snapshot_all() {
         let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
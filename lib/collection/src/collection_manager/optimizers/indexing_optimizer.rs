@@ -87,7 +87,7 @@ impl IndexingOptimizer {
 
                 let is_vector_indexed = match segment_config.index {
                     Indexes::Plain {} => false,
-                    Indexes::Hnsw(_) => true,
+                    Indexes::Hnsw(_) | Indexes::Ivf(_) => true,
                 };
 
                 let is_memmaped = match segment_config.storage_type {
@@ -140,7 +140,7 @@ impl IndexingOptimizer {
                 // Apply indexing to plain segments which have grown too big
                 let is_vector_indexed = match segment_config.index {
                     Indexes::Plain {} => false,
-                    Indexes::Hnsw(_) => true,
+                    Indexes::Hnsw(_) | Indexes::Ivf(_) => true,
                 };
 
                 let is_memmaped = match segment_config.storage_type {
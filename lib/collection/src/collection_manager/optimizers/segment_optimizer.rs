@@ -84,6 +84,15 @@ pub trait SegmentOptimizer {
                 false => PayloadStorageType::InMemory,
             },
             quantization_config: None,
+            filter_cache_size: 0,
+            id_tracker_type: Default::default(),
+            indexing_threshold: None,
+            max_search_threads: None,
+            flush_policy: Default::default(),
+            checksum_verification: Default::default(),
+            background_flush_interval_ms: None,
+            vector_layout: Default::default(),
+            unknown_fields: Default::default(),
         };
         Ok(LockedSegment::new(build_segment(
             self.collection_path(),
@@ -144,6 +153,15 @@ pub trait SegmentOptimizer {
             } else {
                 Default::default()
             },
+            filter_cache_size: 0,
+            id_tracker_type: Default::default(),
+            indexing_threshold: None,
+            max_search_threads: None,
+            flush_policy: Default::default(),
+            checksum_verification: Default::default(),
+            background_flush_interval_ms: None,
+            vector_layout: Default::default(),
+            unknown_fields: Default::default(),
         };
 
         Ok(SegmentBuilder::new(
@@ -285,9 +303,9 @@ pub trait SegmentOptimizer {
         let deleted_points_snapshot: Vec<PointIdType> =
             proxy_deleted_points.read().iter().cloned().collect();
 
-        for &point_id in &deleted_points_snapshot {
+        for point_id in &deleted_points_snapshot {
             optimized_segment
-                .delete_point(optimized_segment.version(), point_id)
+                .delete_point(optimized_segment.version(), point_id.clone())
                 .unwrap();
         }
 
@@ -454,9 +472,9 @@ pub trait SegmentOptimizer {
             let mut write_segments_guard = segments.write();
             let deleted_points = proxy_deleted_points.read();
             let points_diff = deleted_points.difference(&already_remove_points);
-            for &point_id in points_diff {
+            for point_id in points_diff {
                 optimized_segment
-                    .delete_point(optimized_segment.version(), point_id)
+                    .delete_point(optimized_segment.version(), point_id.clone())
                     .unwrap();
             }
 
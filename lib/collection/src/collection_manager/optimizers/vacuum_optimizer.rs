@@ -176,8 +176,12 @@ mod tests {
             .filter(|_| rnd.gen_bool(0.5))
             .collect_vec();
 
-        for &point_id in &segment_points_to_delete {
-            segment.get().write().delete_point(101, point_id).unwrap();
+        for point_id in &segment_points_to_delete {
+            segment
+                .get()
+                .write()
+                .delete_point(101, point_id.clone())
+                .unwrap();
         }
 
         let segment_points_to_assign1 = segment
@@ -194,19 +198,19 @@ mod tests {
             .filter(|_| rnd.gen_bool(0.05))
             .collect_vec();
 
-        for &point_id in &segment_points_to_assign1 {
+        for point_id in &segment_points_to_assign1 {
             segment
                 .get()
                 .write()
-                .set_payload(102, point_id, &json!({ "color": "red" }).into())
+                .set_payload(102, point_id.clone(), &json!({ "color": "red" }).into())
                 .unwrap();
         }
 
-        for &point_id in &segment_points_to_assign2 {
+        for point_id in &segment_points_to_assign2 {
             segment
                 .get()
                 .write()
-                .set_payload(102, point_id, &json!({"size": 0.42}).into())
+                .set_payload(102, point_id.clone(), &json!({"size": 0.42}).into())
                 .unwrap();
         }
 
@@ -272,9 +276,9 @@ mod tests {
         );
 
         // Check payload is preserved in optimized segment
-        for &point_id in &segment_points_to_assign1 {
-            assert!(segment_guard.has_point(point_id));
-            let payload = segment_guard.payload(point_id).unwrap();
+        for point_id in &segment_points_to_assign1 {
+            assert!(segment_guard.has_point(point_id.clone()));
+            let payload = segment_guard.payload(point_id.clone()).unwrap();
             let payload_color = &(*payload.get_value("color").next().unwrap()).clone();
 
             match payload_color {
@@ -1,36 +1,47 @@
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 
+use ordered_float::OrderedFloat;
 use segment::spaces::tools::FixedLengthPriorityQueue;
-use segment::types::{PointIdType, ScoreType, ScoredPoint, SeqNumberType};
+use segment::types::{Order, PointIdType, ScoreType, ScoredPoint, SeqNumberType};
 
 pub struct SearchResultAggregator {
-    queue: FixedLengthPriorityQueue<ScoredPoint>,
+    // Points with the keys of their scores, so the best point is the largest one
+    queue: FixedLengthPriorityQueue<(OrderedFloat<ScoreType>, ScoredPoint)>,
+    order: Order,
     seen: HashSet<PointIdType>, // Point ids seen
 }
 
 impl SearchResultAggregator {
-    pub fn new(limit: usize) -> Self {
+    pub fn new(limit: usize, order: Order) -> Self {
         SearchResultAggregator {
             queue: FixedLengthPriorityQueue::new(limit),
+            order,
             seen: HashSet::new(),
         }
     }
 
     pub fn push(&mut self, point: ScoredPoint) {
-        let point_id = point.id;
+        let point_id = point.id.clone();
         if !self.seen.contains(&point_id) {
             self.seen.insert(point_id);
-            self.queue.push(point);
+            let key = OrderedFloat(self.order.score_key(point.score));
+            self.queue.push((key, point));
         }
     }
 
+    /// Aggregated points, best first
     pub fn into_vec(self) -> Vec<ScoredPoint> {
-        self.queue.into_vec()
+        self.queue
+            .into_vec()
+            .into_iter()
+            .map(|(_, point)| point)
+            .collect()
     }
 
+    /// The worst of the aggregated points
     pub fn lowest(&self) -> Option<&ScoredPoint> {
-        self.queue.top()
+        self.queue.top().map(|(_, point)| point)
     }
 }
 
@@ -42,10 +53,11 @@ pub struct BatchResultAggregator {
 }
 
 impl BatchResultAggregator {
-    pub fn new(tops: impl Iterator<Item = usize>) -> Self {
+    /// Create aggregators for the batched requests, given their limits and score orders
+    pub fn new(tops: impl Iterator<Item = (usize, Order)>) -> Self {
         let mut merged_results_per_batch = vec![];
-        for top in tops {
-            merged_results_per_batch.push(SearchResultAggregator::new(top));
+        for (top, order) in tops {
+            merged_results_per_batch.push(SearchResultAggregator::new(top, order));
         }
 
         BatchResultAggregator {
@@ -58,7 +70,7 @@ impl BatchResultAggregator {
         for segment_result in search_results {
             for segment_batch_result in segment_result {
                 for point in segment_batch_result {
-                    let point_id = point.id;
+                    let point_id = point.id.clone();
                     let point_version =
                         self.point_versions.entry(point_id).or_insert(point.version);
                     *point_version = max(*point_version, point.version);
@@ -92,7 +104,7 @@ impl BatchResultAggregator {
         }
     }
 
-    /// Return lowest acceptable score for given batch id
+    /// Return the worst acceptable score for given batch id
     pub fn batch_lowest_scores(&self, batch_id: usize) -> Option<ScoreType> {
         let batch_scores = &self.batch_aggregators[batch_id];
         batch_scores.lowest().map(|x| x.score)
@@ -8,7 +8,7 @@ use segment::data_types::named_vectors::NamedVectors;
 use segment::data_types::vectors::VectorElementType;
 use segment::entry::entry_point::OperationError;
 use segment::types::{
-    Filter, Indexes, PointIdType, ScoreType, ScoredPoint, SearchParams, SegmentConfig,
+    Filter, Indexes, Order, PointIdType, ScoreType, ScoredPoint, SearchParams, SegmentConfig,
     SeqNumberType, WithPayload, WithPayloadInterface, WithVector,
 };
 use tokio::runtime::Handle;
@@ -58,12 +58,14 @@ impl SegmentsSearcher {
     /// # Arguments
     /// * search_result - [segment_size x batch_size]
     /// * limits - [batch_size] - how many results to return for each batched request
+    /// * orders - [batch_size] - order of the scores of each batched request
     /// * further_searches - [segment_size x batch_size] - whether we can search further in the segment
     ///
     /// Returns batch results aggregated by [batch_size] and list of queries, grouped by segment to re-run
     pub(crate) fn process_search_result_step1(
         search_result: BatchSearchResult,
         limits: Vec<usize>,
+        orders: Vec<Order>,
         further_results: Vec<Vec<bool>>,
     ) -> (
         BatchResultAggregator,
@@ -72,7 +74,7 @@ impl SegmentsSearcher {
         let number_segments = search_result.len();
         let batch_size = limits.len();
 
-        // The lowest scored element must be larger or equal to the worst scored element in each segment.
+        // The worst scored element must be better or equal to the worst scored element in each segment.
         // Otherwise, the sampling is invalid and some points might be missing.
         // e.g. with 3 segments with the following sampled ranges:
         // s1 - [0.91 -> 0.87]
@@ -80,14 +82,16 @@ impl SegmentsSearcher {
         // s3 - [0.93 -> 0.85]
         // If the top merged scores result range is [0.93 -> 0.86] then we do not know if s1 could have contributed more points at the lower part between [0.87 -> 0.86]
         // In that case, we need to re-run the search without sampling on that segment.
+        // Scores are compared by their keys, see `Order::score_key`, so the "lowest" is the worst.
 
         // Initialize result aggregators for each batched request
-        let mut result_aggregator = BatchResultAggregator::new(limits.iter().copied());
+        let mut result_aggregator =
+            BatchResultAggregator::new(limits.iter().copied().zip(orders.iter().copied()));
         result_aggregator.update_point_versions(&search_result);
 
-        // Therefore we need to track the lowest scored element per segment for each batch
+        // Therefore we need to track the lowest score key per segment for each batch
         let mut lowest_scores_per_request: Vec<Vec<ScoreType>> = vec![
-            vec![f32::max_value(); batch_size]; // initial max score value for each batch
+            vec![f32::max_value(); batch_size]; // initial max score key for each batch
             number_segments
         ];
 
@@ -103,7 +107,7 @@ impl SegmentsSearcher {
                 retrieved_points_per_request[segment_idx][batch_req_idx] = query_res.len();
                 lowest_scores_per_request[segment_idx][batch_req_idx] = query_res
                     .last()
-                    .map(|x| x.score)
+                    .map(|x| orders[batch_req_idx].score_key(x.score))
                     .unwrap_or_else(f32::min_value);
                 result_aggregator.update_batch_results(batch_req_idx, query_res.into_iter());
             }
@@ -114,7 +118,9 @@ impl SegmentsSearcher {
 
         // Check if we want to re-run the search without sampling on some segments
         for (batch_id, required_limit) in limits.iter().copied().enumerate() {
-            let lowest_batch_score_opt = result_aggregator.batch_lowest_scores(batch_id);
+            let lowest_batch_score_opt = result_aggregator
+                .batch_lowest_scores(batch_id)
+                .map(|score| orders[batch_id].score_key(score));
 
             // If there are no results, we do not need to re-run the search
             if let Some(lowest_batch_score) = lowest_batch_score_opt {
@@ -152,14 +158,28 @@ impl SegmentsSearcher {
         // Using { } block to ensure segments variable is dropped in the end of it
         // and is not transferred across the all_searches.await? boundary as it
         // does not impl Send trait
-        let (locked_segments, searches): (Vec<_>, Vec<_>) = {
+        let (locked_segments, searches, orders): (Vec<_>, Vec<_>, Vec<_>) = {
             let segments = segments.read();
 
-            let some_segment = segments.iter().next();
+            let some_segment = match segments.iter().next() {
+                Some((_, segment)) => segment,
+                None => return Ok(vec![]),
+            };
 
-            if some_segment.is_none() {
-                return Ok(vec![]);
-            }
+            // Scores of the same vector are ordered the same way in all segments
+            let config = some_segment.get().read().config();
+            let orders = batch_request
+                .searches
+                .iter()
+                .map(|request| {
+                    config
+                        .vector_data
+                        .get(request.vector.get_name())
+                        .map_or(Order::LargeBetter, |vector_config| {
+                            vector_config.distance.distance_order()
+                        })
+                })
+                .collect();
 
             // Probabilistic sampling for the `limit` parameter avoids over-fetching points from segments.
             // e.g. 10 segments with limit 1000 would fetch 10000 points in total and discard 9000 points.
@@ -174,7 +194,7 @@ impl SegmentsSearcher {
                 .sum();
             let use_sampling = sampling_enabled && segments.len() > 1 && total_points_segments > 0;
 
-            segments
+            let (locked_segments, searches) = segments
                 .iter()
                 .map(|(_id, segment)| {
                     (
@@ -188,7 +208,8 @@ impl SegmentsSearcher {
                     )
                 })
                 .map(|(segment, f)| (segment, runtime_handle.spawn(f)))
-                .unzip()
+                .unzip();
+            (locked_segments, searches, orders)
         };
         // perform search on all segments concurrently
         // the resulting Vec is in the same order as the segment searches were provided.
@@ -203,6 +224,7 @@ impl SegmentsSearcher {
                 .iter()
                 .map(|request| request.limit + request.offset)
                 .collect(),
+            orders,
             further_results,
         );
         // The second step of the search is to re-run the search without sampling on some segments
@@ -262,38 +284,40 @@ impl SegmentsSearcher {
         let mut point_records: HashMap<PointIdType, Record> = Default::default();
 
         segments.read().read_points(points, |id, segment| {
-            let version = segment.point_version(id).ok_or_else(|| {
+            let version = segment.point_version(id.clone()).ok_or_else(|| {
                 OperationError::service_error(format!("No version for point {id}"))
             })?;
             // If this point was not found yet or this segment have later version
             if !point_version.contains_key(&id) || point_version[&id] < version {
                 point_records.insert(
-                    id,
+                    id.clone(),
                     Record {
-                        id,
+                        id: id.clone(),
                         payload: if with_payload.enable {
                             if let Some(selector) = &with_payload.payload_selector {
-                                Some(selector.process(segment.payload(id)?))
+                                Some(Arc::new(segment.selected_payload(id.clone(), selector)?))
                             } else {
-                                Some(segment.payload(id)?)
+                                Some(segment.payload(id.clone())?)
                             }
                         } else {
                             None
                         },
                         vector: match with_vector {
-                            WithVector::Bool(true) => Some(segment.all_vectors(id)?.into()),
+                            WithVector::Bool(true) => Some(segment.all_vectors(id.clone())?.into()),
                             WithVector::Bool(false) => None,
                             WithVector::Selector(vector_names) => {
                                 let mut selected_vectors = NamedVectors::default();
                                 for vector_name in vector_names {
                                     selected_vectors.insert(
                                         vector_name.clone(),
-                                        segment.vector(vector_name, id)?,
+                                        segment.vector(vector_name, id.clone())?,
                                     );
                                 }
                                 Some(selected_vectors.into())
                             }
                         },
+                        version: Some(version),
+                        order_value: None,
                     },
                 );
                 point_version.insert(id, version);
@@ -457,10 +481,10 @@ async fn search_in_segment(
 
 /// Find the maximum segment or vector specific HNSW ef_construct in this config
 ///
-/// If the index is `Plain`, `None` is returned.
+/// If the index is not `Hnsw`, `None` is returned.
 fn get_hnsw_ef_construct(config: SegmentConfig, vector_name: &str) -> Option<usize> {
     match config.index {
-        Indexes::Plain {} => None,
+        Indexes::Plain {} | Indexes::Ivf(_) => None,
         Indexes::Hnsw(hnsw_config) => Some(
             config
                 .vector_data
@@ -474,13 +498,34 @@ fn get_hnsw_ef_construct(config: SegmentConfig, vector_name: &str) -> Option<usi
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use segment::data_types::vectors::only_default_vector;
+    use segment::entry::entry_point::SegmentEntry;
     use segment::fixtures::index_fixtures::random_vector;
     use tempfile::Builder;
 
     use super::*;
-    use crate::collection_manager::fixtures::{build_test_holder, random_segment};
+    use crate::collection_manager::fixtures::{build_test_holder, empty_segment, random_segment};
     use crate::operations::types::SearchRequest;
 
+    fn search_request(vector: Vec<VectorElementType>, limit: usize) -> SearchRequestBatch {
+        SearchRequestBatch {
+            searches: vec![SearchRequest {
+                vector: vector.into(),
+                with_payload: None,
+                with_vector: None,
+                filter: None,
+                params: None,
+                limit,
+                score_threshold: None,
+                offset: 0,
+            }],
+        }
+    }
+
     #[tokio::test]
     async fn test_segments_search() {
         let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
@@ -599,6 +644,120 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_segments_search_duplicated_points() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        // Points 4 and 5 are in both segments, the copies of the second segment are newer
+        let segment_holder = build_test_holder(dir.path());
+
+        let result = SegmentsSearcher::search(
+            &segment_holder,
+            Arc::new(search_request(vec![1.0, 1.0, 0.0, 1.0], 10)),
+            &Handle::current(),
+            false,
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap();
+
+        // All 10 distinct points, each once
+        assert_eq!(result.len(), 10);
+        let ids: HashSet<_> = result.iter().map(|point| point.id.clone()).collect();
+        assert_eq!(ids.len(), result.len());
+        let version = |id: u64| {
+            result
+                .iter()
+                .find(|point| point.id == id.into())
+                .unwrap()
+                .version
+        };
+        assert_eq!(version(4), 7);
+        assert_eq!(version(5), 8);
+
+        let records = SegmentsSearcher::retrieve(
+            &segment_holder,
+            &[4.into(), 5.into()],
+            &WithPayload::from(true),
+            &false.into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 2);
+        for record in records {
+            let expected_version = if record.id == 4.into() { 7 } else { 8 };
+            assert_eq!(record.version, Some(expected_version));
+            // Newer copies have no payload
+            assert!(record.payload.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_segments_search_concurrent_writes() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+
+        let segment1 = random_segment(dir.path(), 10, 500, 4);
+        let point_ids: Vec<PointIdType> = segment1.iter_points().collect();
+        let mut holder = SegmentHolder::default();
+        let _sid1 = holder.add(segment1);
+        let sid2 = holder.add(empty_segment(dir.path()));
+        let segment_holder = Arc::new(RwLock::new(holder));
+
+        // Newer copies of the points are written into the second segment during the searches
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let segment_holder = segment_holder.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut rnd = rand::thread_rng();
+                let mut op_num = 100;
+                for point_id in point_ids.iter().cycle() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    op_num += 1;
+                    let vector = random_vector(&mut rnd, 4);
+                    let segment = segment_holder.read().get(sid2).unwrap().get();
+                    segment
+                        .write()
+                        .upsert_vector(op_num, point_id.clone(), &only_default_vector(&vector))
+                        .unwrap();
+                }
+            })
+        };
+
+        let mut rnd = rand::thread_rng();
+        for sampling_enabled in [false, true].into_iter().cycle().take(100) {
+            let result = SegmentsSearcher::search(
+                &segment_holder,
+                Arc::new(search_request(random_vector(&mut rnd, 4), 50)),
+                &Handle::current(),
+                sampling_enabled,
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+
+            // Sampled segments may return only the outdated copies of some of their best points
+            if sampling_enabled {
+                assert!(result.len() <= 50);
+            } else {
+                assert_eq!(result.len(), 50);
+            }
+            let ids: HashSet<_> = result.iter().map(|point| point.id.clone()).collect();
+            assert_eq!(ids.len(), result.len());
+            assert!(result
+                .windows(2)
+                .all(|points| points[0].score >= points[1].score));
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        writer.join().unwrap();
+    }
+
     #[tokio::test]
     async fn test_retrieve() {
         let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
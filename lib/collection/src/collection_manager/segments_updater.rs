@@ -125,7 +125,7 @@ pub(crate) fn delete_payload(
         segments.apply_points_to_appendable(op_num, points, |id, write_segment| {
             let mut res = true;
             for key in keys {
-                res = write_segment.delete_payload(op_num, id, key)? && res;
+                res = write_segment.delete_payload(op_num, id.clone(), key)? && res;
             }
             Ok(res)
         })?;
@@ -209,7 +209,7 @@ fn upsert_with_payload(
     vectors: &NamedVectors,
     payload: Option<&Payload>,
 ) -> OperationResult<bool> {
-    let mut res = segment.upsert_vector(op_num, point_id, vectors)?;
+    let mut res = segment.upsert_vector(op_num, point_id.clone(), vectors)?;
     if let Some(full_payload) = payload {
         res &= segment.set_full_payload(op_num, point_id, full_payload)?;
     }
@@ -235,35 +235,40 @@ pub(crate) fn sync_points(
 ) -> CollectionResult<(usize, usize, usize)> {
     let id_to_point = points
         .iter()
-        .map(|p| (p.id, p))
+        .map(|p| (p.id.clone(), p))
         .collect::<HashMap<PointIdType, &PointStruct>>();
-    let sync_points: HashSet<_> = points.iter().map(|p| p.id).collect();
+    let sync_points: HashSet<_> = points.iter().map(|p| p.id.clone()).collect();
     // 1. Retrieve existing points for a range
     let stored_point_ids: HashSet<_> = segments
         .iter()
-        .flat_map(|(_, segment)| segment.get().read().read_range(from_id, to_id))
+        .flat_map(|(_, segment)| {
+            segment
+                .get()
+                .read()
+                .read_range(from_id.clone(), to_id.clone())
+        })
         .collect();
     // 2. Remove points, which are not present in the sync operation
-    let points_to_remove: Vec<_> = stored_point_ids.difference(&sync_points).copied().collect();
+    let points_to_remove: Vec<_> = stored_point_ids.difference(&sync_points).cloned().collect();
     let deleted = delete_points(segments, op_num, points_to_remove.as_slice())?;
     // 3. Retrieve overlapping points, detect which one of them are changed
     let existing_point_ids: Vec<_> = stored_point_ids
         .intersection(&sync_points)
-        .copied()
+        .cloned()
         .collect();
 
     let mut points_to_update: Vec<_> = Vec::new();
     let _num_updated = segments.read_points(existing_point_ids.as_slice(), |id, segment| {
-        let all_vectors = segment.all_vectors(id)?;
-        let payload = segment.payload(id)?;
+        let all_vectors = segment.all_vectors(id.clone())?;
+        let payload = segment.payload(id.clone())?;
         let point = id_to_point.get(&id).unwrap();
         if point.get_vectors() != all_vectors {
             points_to_update.push(*point);
             Ok(true)
         } else {
             let payload_match = match point.payload {
-                Some(ref p) => p == &payload,
-                None => Payload::default() == payload,
+                Some(ref p) => p == payload.as_ref(),
+                None => Payload::default() == *payload,
             };
             if !payload_match {
                 points_to_update.push(*point);
@@ -279,7 +284,7 @@ pub(crate) fn sync_points(
     let mut num_new = 0;
     sync_points
         .difference(&stored_point_ids)
-        .copied()
+        .cloned()
         .for_each(|id| {
             num_new += 1;
             points_to_update.push(*id_to_point.get(&id).unwrap());
@@ -304,8 +309,8 @@ where
     T: IntoIterator<Item = &'a PointStruct>,
 {
     let points_map: HashMap<PointIdType, &PointStruct> =
-        points.into_iter().map(|p| (p.id, p)).collect();
-    let ids: Vec<PointIdType> = points_map.keys().copied().collect();
+        points.into_iter().map(|p| (p.id.clone(), p)).collect();
+    let ids: Vec<PointIdType> = points_map.keys().cloned().collect();
 
     // Update points in writable segments
     let updated_points =
@@ -322,30 +327,33 @@ where
 
     let mut res = updated_points.len();
     // Insert new points, which was not updated or existed
-    let new_point_ids = ids
+    let new_point_ids: Vec<PointIdType> = ids
         .iter()
         .cloned()
-        .filter(|x| !(updated_points.contains(x)));
+        .filter(|x| !(updated_points.contains(x)))
+        .collect();
 
-    {
+    if !new_point_ids.is_empty() {
         let default_write_segment = segments.random_appendable_segment().ok_or_else(|| {
             CollectionError::service_error("No segments exists, expected at least one".to_string())
         })?;
 
         let segment_arc = default_write_segment.get();
         let mut write_segment = segment_arc.write();
-        for point_id in new_point_ids {
-            let point = points_map[&point_id];
-            res += upsert_with_payload(
-                &mut write_segment,
-                op_num,
-                point_id,
-                &point.get_vectors(),
-                point.payload.as_ref(),
-            )? as usize;
-        }
+        // New points are inserted as a single block of offsets
+        let new_vectors: Vec<_> = new_point_ids
+            .iter()
+            .map(|point_id| points_map[point_id].get_vectors())
+            .collect();
+        let new_payloads: Vec<_> = new_point_ids
+            .iter()
+            .map(|point_id| points_map[point_id].payload.as_ref())
+            .collect();
+        let upserted =
+            write_segment.upsert_points(op_num, &new_point_ids, &new_vectors, &new_payloads)?;
+        res += upserted.into_iter().filter(|point_res| *point_res).count();
         RwLockWriteGuard::unlock_fair(write_segment);
-    };
+    }
 
     Ok(res)
 }
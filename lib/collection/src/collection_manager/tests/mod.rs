@@ -163,17 +163,17 @@ fn test_move_points_to_copy_on_write() {
     eprintln!("copy_on_write_points = {copy_on_write_points:#?}");
 
     for idx in copy_on_write_points {
-        let internal = id_mapper.borrow().internal_id(idx).unwrap();
+        let internal = id_mapper.read().internal_id(idx).unwrap();
         eprintln!("{idx} -> {internal}");
     }
 
     let id_tracker = copy_on_write_segment_read.id_tracker.clone();
-    let internal_ids = id_tracker.borrow().iter_ids().collect_vec();
+    let internal_ids = id_tracker.read().iter_ids().collect_vec();
 
     eprintln!("internal_ids = {internal_ids:#?}");
 
     for idx in internal_ids {
-        let external = id_mapper.borrow().external_id(idx).unwrap();
+        let external = id_mapper.read().external_id(idx).unwrap();
         eprintln!("{idx} -> {external}");
     }
 }
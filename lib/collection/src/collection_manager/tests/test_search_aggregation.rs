@@ -1,4 +1,4 @@
-use segment::types::{PointIdType, ScoreType, ScoredPoint, SeqNumberType};
+use segment::types::{Order, PointIdType, ScoreType, ScoredPoint, SeqNumberType};
 
 use crate::collection_manager::segments_searcher::SegmentsSearcher;
 
@@ -81,6 +81,7 @@ fn test_aggregation_of_batch_search_results() {
     let (aggregator, re_request) = SegmentsSearcher::process_search_result_step1(
         search_results,
         result_limits,
+        vec![Order::LargeBetter; 2],
         further_results,
     );
 
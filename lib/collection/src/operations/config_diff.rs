@@ -2,7 +2,7 @@ use std::num::NonZeroU32;
 
 use merge::Merge;
 use schemars::JsonSchema;
-use segment::types::HnswConfig;
+use segment::types::{EfPolicy, HnswConfig, NeighborSelection};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -65,6 +65,25 @@ pub struct HnswConfigDiff {
     /// Custom M param for additional payload-aware HNSW links. If not set, default M will be used.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub payload_m: Option<usize>,
+    /// Strategy of selecting neighbours of a new point during the index building. Default: heuristic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neighbor_selection: Option<NeighborSelection>,
+    /// Seed for the random generator, used during the index building. If not set, random seed will be used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Link points, inserted into an indexed appendable segment, into the graph right away.
+    /// If false, new points are only indexed by the next rebuild of the index. Default: false
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incremental: Option<bool>,
+    /// Policy of choosing `ef` of the search by the requested `top`, if `hnsw_ef` is not set in the search params.
+    /// If not set, `ef_construct` is used as `ef`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate]
+    pub ef_policy: Option<EfPolicy>,
+    /// Max memory of a single build of the index in megabytes. Builds, estimated to use more, are refused before they start.
+    /// If not set or 0 - the memory of the builds is not limited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_build_memory_mb: Option<usize>,
 }
 
 #[derive(
@@ -1,12 +1,15 @@
 use std::collections::{BTreeMap, HashMap};
 use std::num::{NonZeroU32, NonZeroU64};
+use std::sync::Arc;
 
-use api::grpc::conversions::{from_grpc_dist, payload_to_proto, proto_to_payloads};
+use api::grpc::conversions::{
+    from_grpc_dist, from_grpc_neighbor_selection, payload_to_proto, proto_to_payloads,
+};
 use api::grpc::qdrant::QuantizationType;
 use itertools::Itertools;
 use segment::data_types::vectors::{NamedVector, VectorStruct, DEFAULT_VECTOR_NAME};
 use segment::types::{
-    Distance, QuantizationConfig, ScalarQuantization, ScalarQuantizationConfig, ScalarType,
+    Distance, Payload, QuantizationConfig, ScalarQuantization, ScalarQuantizationConfig, ScalarType,
 };
 use tonic::Status;
 
@@ -73,7 +76,9 @@ pub fn try_record_from_grpc(
         .try_into()?;
 
     let payload = if with_payload {
-        Some(api::grpc::conversions::proto_to_payloads(point.payload)?)
+        Some(Arc::new(api::grpc::conversions::proto_to_payloads(
+            point.payload,
+        )?))
     } else {
         debug_assert!(point.payload.is_empty());
         None
@@ -88,6 +93,8 @@ pub fn try_record_from_grpc(
         id,
         payload,
         vector,
+        version: point.version,
+        order_value: point.order_value,
     })
 }
 
@@ -100,6 +107,13 @@ impl From<api::grpc::qdrant::HnswConfigDiff> for HnswConfigDiff {
             max_indexing_threads: value.max_indexing_threads.map(|v| v as usize),
             on_disk: value.on_disk,
             payload_m: value.payload_m.map(|v| v as usize),
+            neighbor_selection: value
+                .neighbor_selection
+                .and_then(|v| from_grpc_neighbor_selection(v).ok()),
+            seed: value.seed,
+            incremental: value.incremental,
+            ef_policy: value.ef_policy.map(Into::into),
+            max_build_memory_mb: value.max_build_memory_mb.map(|v| v as usize),
         }
     }
 }
@@ -113,6 +127,13 @@ impl From<HnswConfigDiff> for api::grpc::qdrant::HnswConfigDiff {
             max_indexing_threads: value.max_indexing_threads.map(|v| v as u64),
             on_disk: value.on_disk,
             payload_m: value.payload_m.map(|v| v as u64),
+            neighbor_selection: value
+                .neighbor_selection
+                .map(|v| api::grpc::qdrant::NeighborSelection::from(v) as i32),
+            seed: value.seed,
+            incremental: value.incremental,
+            ef_policy: value.ef_policy.map(Into::into),
+            max_build_memory_mb: value.max_build_memory_mb.map(|v| v as u64),
         }
     }
 }
@@ -234,6 +255,14 @@ impl From<CollectionInfo> for api::grpc::qdrant::CollectionInfo {
                     max_indexing_threads: Some(config.hnsw_config.max_indexing_threads as u64),
                     on_disk: config.hnsw_config.on_disk,
                     payload_m: config.hnsw_config.payload_m.map(|v| v as u64),
+                    neighbor_selection: config
+                        .hnsw_config
+                        .neighbor_selection
+                        .map(|v| api::grpc::qdrant::NeighborSelection::from(v) as i32),
+                    seed: config.hnsw_config.seed,
+                    incremental: config.hnsw_config.incremental,
+                    ef_policy: config.hnsw_config.ef_policy.map(Into::into),
+                    max_build_memory_mb: config.hnsw_config.max_build_memory_mb.map(|v| v as u64),
                 }),
                 optimizer_config: Some(api::grpc::qdrant::OptimizersConfigDiff {
                     deleted_threshold: Some(config.optimizer_config.deleted_threshold),
@@ -271,8 +300,13 @@ impl From<Record> for api::grpc::qdrant::RetrievedPoint {
 
         Self {
             id: Some(record.id.into()),
-            payload: record.payload.map(payload_to_proto).unwrap_or_default(),
+            payload: record
+                .payload
+                .map(|payload| payload_to_proto(Payload::from_shared(payload)))
+                .unwrap_or_default(),
             vectors,
+            version: record.version,
+            order_value: record.order_value,
         }
     }
 }
@@ -645,7 +679,7 @@ impl<'a> From<CollectionSearchRequest<'a>> for api::grpc::qdrant::SearchPoints {
             limit: request.limit as u64,
             with_vectors: request.with_vector.clone().map(|wv| wv.into()),
             with_payload: request.with_payload.clone().map(|wp| wp.into()),
-            params: request.params.map(|sp| sp.into()),
+            params: request.params.clone().map(|sp| sp.into()),
             score_threshold: request.score_threshold,
             offset: Some(request.offset as u64),
             vector_name: match request.vector.get_name() {
@@ -105,9 +105,9 @@ impl Validate for CollectionUpdateOperations {
     }
 }
 
-fn point_to_shard(point_id: ExtendedPointId, ring: &HashRing<ShardId>) -> ShardId {
+fn point_to_shard(point_id: &ExtendedPointId, ring: &HashRing<ShardId>) -> ShardId {
     *ring
-        .get(&point_id)
+        .get(point_id)
         .expect("Hash ring is guaranteed to be non-empty")
 }
 
@@ -123,7 +123,7 @@ where
 {
     let mut op_vec_by_shard: HashMap<ShardId, Vec<O>> = HashMap::new();
     for operation in iter {
-        let shard_id = point_to_shard(id_extractor(&operation), ring);
+        let shard_id = point_to_shard(&id_extractor(&operation), ring);
         op_vec_by_shard
             .entry(shard_id)
             .or_insert_with(Vec::new)
@@ -55,7 +55,7 @@ impl EstimateOperationEffectArea for point_ops::PointOperations {
                     false,
                     "SyncPoints operation should not be used during transfer"
                 );
-                OperationEffectArea::Points(sync_op.points.iter().map(|x| x.id).collect())
+                OperationEffectArea::Points(sync_op.points.iter().map(|x| x.id.clone()).collect())
             }
         }
     }
@@ -68,7 +68,7 @@ impl EstimateOperationEffectArea for point_ops::PointInsertOperations {
                 OperationEffectArea::Points(batch.ids.clone())
             }
             point_ops::PointInsertOperations::PointsList(list) => {
-                OperationEffectArea::Points(list.iter().map(|x| x.id).collect())
+                OperationEffectArea::Points(list.iter().map(|x| x.id.clone()).collect())
             }
         }
     }
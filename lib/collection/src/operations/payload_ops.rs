@@ -135,8 +135,10 @@ impl SplitByShard for PayloadOps {
             PayloadOps::DeletePayload(operation) => operation
                 .split_by_shard(ring)
                 .map(PayloadOps::DeletePayload),
-            PayloadOps::ClearPayload { points } => split_iter_by_shard(points, |id| *id, ring)
-                .map(|points| PayloadOps::ClearPayload { points }),
+            PayloadOps::ClearPayload { points } => {
+                split_iter_by_shard(points, |id| id.clone(), ring)
+                    .map(|points| PayloadOps::ClearPayload { points })
+            }
             operation @ PayloadOps::ClearPayloadByFilter(_) => OperationToShard::to_all(operation),
             PayloadOps::OverwritePayload(operation) => operation
                 .split_by_shard(ring)
@@ -149,7 +151,7 @@ impl SplitByShard for DeletePayload {
     fn split_by_shard(self, ring: &HashRing<ShardId>) -> OperationToShard<Self> {
         match (&self.points, &self.filter) {
             (Some(_), _) => {
-                split_iter_by_shard(self.points.unwrap(), |id| *id, ring).map(|points| {
+                split_iter_by_shard(self.points.unwrap(), |id| id.clone(), ring).map(|points| {
                     DeletePayload {
                         points: Some(points),
                         keys: self.keys.clone(),
@@ -167,10 +169,12 @@ impl SplitByShard for SetPayload {
     fn split_by_shard(self, ring: &HashRing<ShardId>) -> OperationToShard<Self> {
         match (&self.points, &self.filter) {
             (Some(_), _) => {
-                split_iter_by_shard(self.points.unwrap(), |id| *id, ring).map(|points| SetPayload {
-                    points: Some(points),
-                    payload: self.payload.clone(),
-                    filter: self.filter.clone(),
+                split_iter_by_shard(self.points.unwrap(), |id| id.clone(), ring).map(|points| {
+                    SetPayload {
+                        points: Some(points),
+                        payload: self.payload.clone(),
+                        filter: self.filter.clone(),
+                    }
                 })
             }
             (None, Some(_)) => OperationToShard::to_all(self),
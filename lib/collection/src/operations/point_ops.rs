@@ -55,6 +55,8 @@ impl TryFrom<Record> for PointStruct {
             id,
             payload,
             vector,
+            version: _,
+            order_value: _,
         } = record;
 
         if vector.is_none() {
@@ -63,7 +65,7 @@ impl TryFrom<Record> for PointStruct {
 
         Ok(Self {
             id,
-            payload,
+            payload: payload.map(Payload::from_shared),
             vector: vector.unwrap(),
         })
     }
@@ -104,6 +106,7 @@ pub struct PointIdsList {
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Validate)]
 #[serde(rename_all = "snake_case")]
 pub struct FilterSelector {
+    #[validate]
     pub filter: Filter,
 }
 
@@ -335,7 +338,7 @@ impl SplitByShard for Batch {
             match vectors {
                 BatchVectorStruct::Single(vectors) => {
                     for (id, vector, payload) in izip!(ids, vectors, payloads) {
-                        let shard_id = point_to_shard(id, ring);
+                        let shard_id = point_to_shard(&id, ring);
                         let batch = batch_by_shard.entry(shard_id).or_insert_with(|| Batch {
                             ids: vec![],
                             vectors: BatchVectorStruct::Single(vec![]),
@@ -353,7 +356,7 @@ impl SplitByShard for Batch {
                         vec![NamedVectors::default(); ids.len()]
                     };
                     for (id, named_vector, payload) in izip!(ids, named_vectors_list, payloads) {
-                        let shard_id = point_to_shard(id, ring);
+                        let shard_id = point_to_shard(&id, ring);
                         let batch = batch_by_shard.entry(shard_id).or_insert_with(|| Batch {
                             ids: vec![],
                             vectors: BatchVectorStruct::Multi(HashMap::new()),
@@ -377,7 +380,7 @@ impl SplitByShard for Batch {
             match vectors {
                 BatchVectorStruct::Single(vectors) => {
                     for (id, vector) in izip!(ids, vectors) {
-                        let shard_id = point_to_shard(id, ring);
+                        let shard_id = point_to_shard(&id, ring);
                         let batch = batch_by_shard.entry(shard_id).or_insert_with(|| Batch {
                             ids: vec![],
                             vectors: BatchVectorStruct::Single(vec![]),
@@ -394,7 +397,7 @@ impl SplitByShard for Batch {
                         vec![NamedVectors::default(); ids.len()]
                     };
                     for (id, named_vector) in izip!(ids, named_vectors_list) {
-                        let shard_id = point_to_shard(id, ring);
+                        let shard_id = point_to_shard(&id, ring);
                         let batch = batch_by_shard.entry(shard_id).or_insert_with(|| Batch {
                             ids: vec![],
                             vectors: BatchVectorStruct::Multi(HashMap::new()),
@@ -421,7 +424,7 @@ impl SplitByShard for Batch {
 
 impl SplitByShard for Vec<PointStruct> {
     fn split_by_shard(self, ring: &HashRing<ShardId>) -> OperationToShard<Self> {
-        split_iter_by_shard(self, |point| point.id, ring)
+        split_iter_by_shard(self, |point| point.id.clone(), ring)
     }
 }
 
@@ -431,8 +434,10 @@ impl SplitByShard for PointOperations {
             PointOperations::UpsertPoints(upsert_points) => upsert_points
                 .split_by_shard(ring)
                 .map(PointOperations::UpsertPoints),
-            PointOperations::DeletePoints { ids } => split_iter_by_shard(ids, |id| *id, ring)
-                .map(|ids| PointOperations::DeletePoints { ids }),
+            PointOperations::DeletePoints { ids } => {
+                split_iter_by_shard(ids, |id| id.clone(), ring)
+                    .map(|ids| PointOperations::DeletePoints { ids })
+            }
             by_filter @ PointOperations::DeletePointsByFilter(_) => {
                 OperationToShard::to_all(by_filter)
             }
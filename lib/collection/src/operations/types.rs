@@ -1,6 +1,7 @@
 use std::backtrace::Backtrace;
 use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroU64;
+use std::sync::Arc;
 use std::time::SystemTimeError;
 
 use api::grpc::transport_channel_pool::RequestError;
@@ -13,8 +14,8 @@ use segment::data_types::vectors::{
 };
 use segment::entry::entry_point::OperationError;
 use segment::types::{
-    Distance, Filter, Payload, PayloadIndexInfo, PayloadKeyType, PointIdType, QuantizationConfig,
-    ScoreType, SearchParams, SeqNumberType, WithPayloadInterface, WithVector,
+    Distance, Filter, OrderBy, Payload, PayloadIndexInfo, PayloadKeyType, PointIdType,
+    QuantizationConfig, ScoreType, SearchParams, SeqNumberType, WithPayloadInterface, WithVector,
 };
 use serde;
 use serde::{Deserialize, Serialize};
@@ -69,9 +70,13 @@ pub struct Record {
     /// Id of the point
     pub id: PointIdType,
     /// Payload - values assigned to the point
-    pub payload: Option<Payload>,
+    pub payload: Option<Arc<Payload>>,
     /// Vector of the point
     pub vector: Option<VectorStruct>,
+    /// Version of the point - number of the last operation, which changed its vectors or payload
+    pub version: Option<SeqNumberType>,
+    /// Value of the `order_by` field of the point, if the points are scrolled in the order of the field
+    pub order_value: Option<f64>,
 }
 
 /// Current statistics and configuration of the collection
@@ -172,18 +177,21 @@ pub struct UpdateResult {
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct ScrollRequest {
-    /// Start ID to read points from.
+    /// Start ID to read points from. With `order_by` - start ID among the points with the `start_from` value.
     pub offset: Option<PointIdType>,
     /// Page size. Default: 10
     #[validate(range(min = 1))]
     pub limit: Option<usize>,
     /// Look only for points which satisfies this conditions. If not provided - all points.
+    #[validate]
     pub filter: Option<Filter>,
     /// Select which payload to return with the response. Default: All
     pub with_payload: Option<WithPayloadInterface>,
     /// Whether to return the point vector with the result?
     #[serde(default, alias = "with_vectors")]
     pub with_vector: WithVector,
+    /// Order the points by the values of the payload field instead of the ID. Default: by ID
+    pub order_by: Option<OrderBy>,
 }
 
 impl Default for ScrollRequest {
@@ -194,6 +202,7 @@ impl Default for ScrollRequest {
             filter: None,
             with_payload: Some(WithPayloadInterface::Bool(true)),
             with_vector: WithVector::Bool(false),
+            order_by: None,
         }
     }
 }
@@ -206,6 +215,8 @@ pub struct ScrollResult {
     pub points: Vec<Record>,
     /// Offset which should be used to retrieve a next page result
     pub next_page_offset: Option<PointIdType>,
+    /// Value of `order_by.start_from`, which should be used with `next_page_offset` to retrieve a next page result
+    pub next_page_start_from: Option<f64>,
 }
 
 /// Search request.
@@ -217,6 +228,7 @@ pub struct SearchRequest {
     /// Look for vectors closest to this
     pub vector: NamedVectorStruct,
     /// Look only for points which satisfies this conditions
+    #[validate]
     pub filter: Option<Filter>,
     /// Additional search params
     pub params: Option<SearchParams>,
@@ -300,6 +312,7 @@ pub struct RecommendRequest {
     #[serde(default)]
     pub negative: Vec<PointIdType>,
     /// Look only for points which satisfies this conditions
+    #[validate]
     pub filter: Option<Filter>,
     /// Additional search params
     pub params: Option<SearchParams>,
@@ -343,6 +356,7 @@ pub struct RecommendRequestBatch {
 #[serde(rename_all = "snake_case")]
 pub struct CountRequest {
     /// Look only for points which satisfies this conditions
+    #[validate]
     pub filter: Option<Filter>,
     /// If true, count exact number of points. If false, count approximate number of points faster.
     /// Approximate count might be unreliable during the indexing process. Default: true
@@ -472,9 +486,46 @@ impl From<OperationError> for CollectionError {
                 description: format!("{err}"),
             },
             OperationError::Cancelled { description } => Self::Cancelled { description },
+            OperationError::Timeout { .. } => Self::Cancelled {
+                description: format!("{err}"),
+            },
             OperationError::TypeInferenceError { .. } => Self::BadInput {
                 description: format!("{err}"),
             },
+            OperationError::UnindexedField { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::UnknownField { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::UnorderedField { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::CapacityExceeded { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::EncryptedFieldIndex { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::InvalidConfig { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::UnsupportedSearchParams { .. } => Self::BadInput {
+                description: format!("{err}"),
+            },
+            OperationError::UnsupportedFormatVersion { .. }
+            | OperationError::SegmentLocked { .. }
+            | OperationError::EncryptionKeyRequired { .. }
+            | OperationError::WrongEncryptionKey { .. }
+            | OperationError::SegmentNotAppendable { .. }
+            | OperationError::MemoryBudgetExceeded { .. }
+            | OperationError::OutOfMemory { .. }
+            | OperationError::Io { .. }
+            | OperationError::Serialization { .. }
+            | OperationError::InconsistentStorage { .. } => Self::ServiceError {
+                error: format!("{err}"),
+                backtrace: None,
+            },
         }
     }
 }
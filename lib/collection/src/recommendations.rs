@@ -172,7 +172,7 @@ where
         vector_names.insert(get_search_vector_name(request));
 
         for point_id in request.positive.iter().chain(&request.negative) {
-            reference_vectors_ids.insert(*point_id);
+            reference_vectors_ids.insert(point_id.clone());
         }
     }
 
@@ -224,7 +224,7 @@ where
         collections_names.into_iter().zip(all_reference_vectors)
     {
         for rec in reference_vectors {
-            all_vectors_records_map.insert((collection_name, rec.id), rec);
+            all_vectors_records_map.insert((collection_name, rec.id.clone()), rec);
         }
     }
 
@@ -247,17 +247,17 @@ where
 
         let request_from_collection = request.lookup_from.as_ref().map(|x| &x.collection);
 
-        for &point_id in &reference_vectors_ids {
-            if !all_vectors_records_map.contains_key(&(request_from_collection, point_id)) {
+        for point_id in &reference_vectors_ids {
+            if !all_vectors_records_map.contains_key(&(request_from_collection, point_id.clone())) {
                 return Err(CollectionError::PointNotFound {
-                    missed_point_id: point_id,
+                    missed_point_id: point_id.clone(),
                 });
             }
         }
 
         let avg_positive = avg_vectors(request.positive.iter().filter_map(|vid| {
             let rec = all_vectors_records_map
-                .get(&(request_from_collection, *vid))
+                .get(&(request_from_collection, vid.clone()))
                 .unwrap();
             rec.get_vector_by_name(&lookup_vector_name)
         }));
@@ -267,7 +267,7 @@ where
         } else {
             let avg_negative = avg_vectors(request.negative.iter().filter_map(|vid| {
                 let rec = all_vectors_records_map
-                    .get(&(request_from_collection, *vid))
+                    .get(&(request_from_collection, vid.clone()))
                     .unwrap();
                 rec.get_vector_by_name(&lookup_vector_name)
             }));
@@ -298,7 +298,7 @@ where
             }),
             with_payload: request.with_payload.clone(),
             with_vector: request.with_vector.clone(),
-            params: request.params,
+            params: request.params.clone(),
             limit: request.limit,
             score_threshold: request.score_threshold,
             offset: request.offset,
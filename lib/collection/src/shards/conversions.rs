@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use api::grpc::conversions::payload_to_proto;
 use api::grpc::qdrant::points_selector::PointsSelectorOneOf;
 use api::grpc::qdrant::{
@@ -246,6 +248,10 @@ pub fn internal_create_index(
                     api::grpc::qdrant::FieldType::Text as i32,
                     Some(text_index_params.into()),
                 ),
+                PayloadSchemaParams::Keyword(keyword_index_params) => (
+                    api::grpc::qdrant::FieldType::Keyword as i32,
+                    Some(keyword_index_params.into()),
+                ),
             },
         })
         .map(|(field_type, field_params)| (Some(field_type), field_params))
@@ -292,7 +298,9 @@ pub fn try_scored_point_from_grpc(
         .try_into()?;
 
     let payload = if with_payload {
-        Some(api::grpc::conversions::proto_to_payloads(point.payload)?)
+        Some(Arc::new(api::grpc::conversions::proto_to_payloads(
+            point.payload,
+        )?))
     } else {
         debug_assert!(point.payload.is_empty());
         None
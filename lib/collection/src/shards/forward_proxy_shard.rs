@@ -3,7 +3,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use segment::types::{
-    ExtendedPointId, Filter, PointIdType, ScoredPoint, WithPayload, WithPayloadInterface,
+    ExtendedPointId, Filter, OrderBy, PointIdType, ScoredPoint, WithPayload, WithPayloadInterface,
     WithVector,
 };
 use tokio::runtime::Handle;
@@ -75,11 +75,12 @@ impl ForwardProxyShard {
         let mut batch = self
             .wrapped_shard
             .scroll_by(
-                offset,
+                offset.clone(),
                 limit,
                 &WithPayloadInterface::Bool(true),
                 &true.into(),
                 None,
+                None,
             )
             .await?;
         let next_page_offset = if batch.len() < limit {
@@ -100,7 +101,7 @@ impl ForwardProxyShard {
             CollectionUpdateOperations::PointOperation(PointOperations::SyncPoints(
                 PointSyncOperation {
                     from_id: offset,
-                    to_id: next_page_offset,
+                    to_id: next_page_offset.clone(),
                     points,
                 },
             ))
@@ -172,10 +173,18 @@ impl ShardOperation for ForwardProxyShard {
         with_payload_interface: &WithPayloadInterface,
         with_vector: &WithVector,
         filter: Option<&Filter>,
+        order_by: Option<&OrderBy>,
     ) -> CollectionResult<Vec<Record>> {
         let local_shard = &self.wrapped_shard;
         local_shard
-            .scroll_by(offset, limit, with_payload_interface, with_vector, filter)
+            .scroll_by(
+                offset,
+                limit,
+                with_payload_interface,
+                with_vector,
+                filter,
+                order_by,
+            )
             .await
     }
 
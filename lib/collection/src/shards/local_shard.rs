@@ -1,5 +1,4 @@
-use std::collections::hash_map::Entry;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::BTreeSet;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -13,10 +12,7 @@ use segment::entry::entry_point::SegmentEntry;
 use segment::index::field_index::CardinalityEstimation;
 use segment::segment::Segment;
 use segment::segment_constructor::{build_segment, load_segment};
-use segment::types::{
-    Filter, PayloadIndexInfo, PayloadKeyType, PayloadStorageType, PointIdType, SegmentConfig,
-    SegmentType,
-};
+use segment::types::{Filter, PayloadStorageType, PointIdType, SegmentConfig};
 use tokio::fs::{copy, create_dir_all, remove_dir_all};
 use tokio::runtime::Handle;
 use tokio::sync::mpsc::Sender;
@@ -24,7 +20,7 @@ use tokio::sync::{mpsc, oneshot, Mutex, RwLock as TokioRwLock};
 use wal::{Wal, WalOptions};
 
 use crate::collection_manager::collection_updater::CollectionUpdater;
-use crate::collection_manager::holders::segment_holder::{LockedSegment, SegmentHolder};
+use crate::collection_manager::holders::segment_holder::SegmentHolder;
 use crate::config::CollectionConfig;
 use crate::operations::shared_storage_config::SharedStorageConfig;
 use crate::operations::types::{
@@ -189,7 +185,7 @@ impl LocalShard {
                     .spawn(move || {
                         let mut res = load_segment(&segments_path)?;
                         if let Some(segment) = &mut res {
-                            segment.check_consistency_and_repair()?;
+                            segment.check_consistency_and_repair(None)?;
                         }
                         Ok::<_, CollectionError>(res)
                     })?,
@@ -320,6 +316,15 @@ impl LocalShard {
                     false => PayloadStorageType::InMemory,
                 },
                 quantization_config: Default::default(),
+                filter_cache_size: 0,
+                id_tracker_type: Default::default(),
+                indexing_threshold: None,
+                max_search_threads: None,
+                flush_policy: Default::default(),
+                checksum_verification: Default::default(),
+                background_flush_interval_ms: None,
+                vector_layout: Default::default(),
+                unknown_fields: Default::default(),
             };
             let segment = thread::Builder::new()
                 .name(format!("shard-build-{collection_id}-{id}"))
@@ -461,7 +466,7 @@ impl LocalShard {
         // recover segments
         let segments_path = LocalShard::segments_path(snapshot_path);
         // iterate over segments directory and recover each segment
-        for entry in std::fs::read_dir(segments_path)? {
+        for entry in std::fs::read_dir(&segments_path)? {
             let entry_path = entry?.path();
             if entry_path.extension().map(|s| s == "tar").unwrap_or(false) {
                 let segment_id_opt = entry_path
@@ -473,7 +478,7 @@ impl LocalShard {
                     ));
                 }
                 let segment_id = segment_id_opt.unwrap();
-                Segment::restore_snapshot(&entry_path, &segment_id)?;
+                Segment::restore_snapshot(&entry_path, &segments_path.join(segment_id), false)?;
                 std::fs::remove_file(&entry_path)?;
             }
         }
@@ -660,50 +665,11 @@ impl LocalShard {
     pub async fn local_shard_info(&self) -> CollectionInfo {
         let collection_config = self.collection_config.read().await.clone();
         let segments = self.segments().read();
-        let mut vectors_count = 0;
-        let mut indexed_vectors_count = 0;
-        let mut points_count = 0;
-        let mut segments_count = 0;
-        let mut status = CollectionStatus::Green;
-        let mut schema: HashMap<PayloadKeyType, PayloadIndexInfo> = Default::default();
-        for (_idx, segment) in segments.iter() {
-            segments_count += 1;
-
-            let segment_info = match segment {
-                LockedSegment::Original(original_segment) => {
-                    let info = original_segment.read().info();
-                    if info.segment_type == SegmentType::Indexed {
-                        indexed_vectors_count += info.num_vectors;
-                    }
-                    info
-                }
-                LockedSegment::Proxy(proxy_segment) => {
-                    let proxy_segment_lock = proxy_segment.read();
-                    let proxy_segment_info = proxy_segment_lock.info();
-
-                    let wrapped_info = proxy_segment_lock.wrapped_segment.get().read().info();
-                    if wrapped_info.segment_type == SegmentType::Indexed {
-                        indexed_vectors_count += wrapped_info.num_vectors;
-                    }
-                    proxy_segment_info
-                }
-            };
+        let info = segments.info();
 
-            if segment_info.segment_type == SegmentType::Special {
-                status = CollectionStatus::Yellow;
-            }
-            vectors_count += segment_info.num_vectors;
-            points_count += segment_info.num_points;
-            for (key, val) in segment_info.index_schema {
-                match schema.entry(key) {
-                    Entry::Occupied(o) => {
-                        o.into_mut().points += val.points;
-                    }
-                    Entry::Vacant(v) => {
-                        v.insert(val);
-                    }
-                }
-            }
+        let mut status = CollectionStatus::Green;
+        if info.has_special_segments {
+            status = CollectionStatus::Yellow;
         }
         if !segments.failed_operation.is_empty() || segments.optimizer_errors.is_some() {
             status = CollectionStatus::Red;
@@ -717,12 +683,12 @@ impl LocalShard {
         CollectionInfo {
             status,
             optimizer_status,
-            vectors_count,
-            indexed_vectors_count,
-            points_count,
-            segments_count,
+            vectors_count: info.vectors_count,
+            indexed_vectors_count: info.indexed_vectors_count,
+            points_count: info.points_count,
+            segments_count: info.segments_count,
             config: collection_config,
-            payload_schema: schema,
+            payload_schema: info.payload_schema,
         }
     }
 }
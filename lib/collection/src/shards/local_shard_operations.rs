@@ -1,9 +1,10 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use itertools::Itertools;
 use segment::types::{
-    ExtendedPointId, Filter, ScoredPoint, WithPayload, WithPayloadInterface, WithVector,
+    ExtendedPointId, Filter, OrderBy, ScoredPoint, WithPayload, WithPayloadInterface, WithVector,
 };
 use tokio::runtime::Handle;
 use tokio::sync::oneshot;
@@ -18,6 +19,53 @@ use crate::shards::local_shard::LocalShard;
 use crate::shards::shard_trait::ShardOperation;
 use crate::update_handler::{OperationData, UpdateSignal};
 
+impl LocalShard {
+    /// Scroll in the order of the values of the payload field, merging the first points of each segment
+    async fn scroll_by_order(
+        &self,
+        offset: Option<ExtendedPointId>,
+        limit: usize,
+        with_payload_interface: &WithPayloadInterface,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        order_by: &OrderBy,
+    ) -> CollectionResult<Vec<Record>> {
+        let segments = self.segments();
+        let mut ordered_points = Vec::new();
+        for (_, segment) in segments.read().iter() {
+            ordered_points.extend(segment.get().read().read_ordered_filtered(
+                Some(limit),
+                filter,
+                order_by,
+                offset.clone(),
+            )?);
+        }
+        ordered_points.sort_unstable_by(|a, b| order_by.compare((a.0, &a.1), (b.0, &b.1)));
+        let order_values: HashMap<_, _> = ordered_points
+            .into_iter()
+            .map(|(value, point_id)| (point_id, value))
+            .unique_by(|(point_id, _)| point_id.clone())
+            .take(limit)
+            .collect();
+        let point_ids = order_values.keys().cloned().collect_vec();
+
+        let with_payload = WithPayload::from(with_payload_interface);
+        let mut points =
+            SegmentsSearcher::retrieve(segments, &point_ids, &with_payload, with_vector).await?;
+        for point in &mut points {
+            point.order_value = order_values.get(&point.id).copied();
+        }
+        points.sort_by(|a, b| {
+            order_by.compare(
+                (a.order_value.unwrap_or_default(), &a.id),
+                (b.order_value.unwrap_or_default(), &b.id),
+            )
+        });
+
+        Ok(points)
+    }
+}
+
 #[async_trait]
 impl ShardOperation for LocalShard {
     /// Imply interior mutability.
@@ -69,7 +117,21 @@ impl ShardOperation for LocalShard {
         with_payload_interface: &WithPayloadInterface,
         with_vector: &WithVector,
         filter: Option<&Filter>,
+        order_by: Option<&OrderBy>,
     ) -> CollectionResult<Vec<Record>> {
+        if let Some(order_by) = order_by {
+            return self
+                .scroll_by_order(
+                    offset,
+                    limit,
+                    with_payload_interface,
+                    with_vector,
+                    filter,
+                    order_by,
+                )
+                .await;
+        }
+
         // ToDo: Make faster points selection with a set
         let segments = self.segments();
         let point_ids = segments
@@ -79,7 +141,7 @@ impl ShardOperation for LocalShard {
                 segment
                     .get()
                     .read()
-                    .read_filtered(offset, Some(limit), filter)
+                    .read_filtered(offset.clone(), Some(limit), filter)
             })
             .sorted()
             .dedup()
@@ -89,7 +151,7 @@ impl ShardOperation for LocalShard {
         let with_payload = WithPayload::from(with_payload_interface);
         let mut points =
             SegmentsSearcher::retrieve(segments, &point_ids, &with_payload, with_vector).await?;
-        points.sort_by_key(|point| point.id);
+        points.sort_by(|a, b| a.id.cmp(&b.id));
 
         Ok(points)
     }
@@ -125,19 +187,16 @@ impl ShardOperation for LocalShard {
                     .get_vector_params(vector_name)
                     .unwrap()
                     .distance;
-                let processed_res = vector_res.into_iter().map(|mut scored_point| {
-                    scored_point.score = distance.postprocess_score(scored_point.score);
-                    scored_point
-                });
-
+                // Scores are already postprocessed by the segments
                 if let Some(threshold) = req.score_threshold {
-                    processed_res
+                    vector_res
+                        .into_iter()
                         .take_while(|scored_point| {
                             distance.check_threshold(scored_point.score, threshold)
                         })
                         .collect()
                 } else {
-                    processed_res.collect()
+                    vector_res
                 }
             })
             .collect();
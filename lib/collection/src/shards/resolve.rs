@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::hash;
+use std::sync::Arc;
 
 use segment::types::{Payload, ScoredPoint};
 use tinyvec::TinyVec;
@@ -18,8 +19,9 @@ pub trait Resolve: Sized {
 
 impl Resolve for Vec<Record> {
     fn resolve(records: Vec<Self>, condition: ResolveCondition) -> Self {
-        let mut resolved = Resolver::resolve(records, |record| record.id, record_eq, condition);
-        resolved.sort_unstable_by_key(|record| record.id);
+        let mut resolved =
+            Resolver::resolve(records, |record| record.id.clone(), record_eq, condition);
+        resolved.sort_unstable_by(|a, b| a.id.cmp(&b.id));
         resolved
     }
 }
@@ -35,7 +37,7 @@ impl Resolve for Vec<Vec<ScoredPoint>> {
             .into_iter()
             .map(|points| {
                 let mut resolved =
-                    Resolver::resolve(points, |point| point.id, scored_point_eq, condition);
+                    Resolver::resolve(points, |point| point.id.clone(), scored_point_eq, condition);
 
                 resolved.sort_unstable();
                 resolved
@@ -69,7 +71,7 @@ fn scored_point_eq(this: &ScoredPoint, other: &ScoredPoint) -> bool {
         && payload_eq(&this.payload, &other.payload)
 }
 
-fn payload_eq(this: &Option<Payload>, other: &Option<Payload>) -> bool {
+fn payload_eq(this: &Option<Arc<Payload>>, other: &Option<Arc<Payload>>) -> bool {
     match (this, other) {
         (Some(payload), None) | (None, Some(payload)) => payload.is_empty(),
         (this, other) => this == other,
@@ -5,12 +5,13 @@ use collection::operations::point_ops::{Batch, PointOperations, PointStruct, Wri
 use collection::operations::types::{
     CountRequest, PointRequest, RecommendRequest, ScrollRequest, SearchRequest, UpdateStatus,
 };
-use collection::operations::CollectionUpdateOperations;
+use collection::operations::{CollectionUpdateOperations, CreateIndex, FieldIndexOperations};
 use collection::recommendations::recommend_by;
 use itertools::Itertools;
 use segment::data_types::vectors::VectorStruct;
 use segment::types::{
-    Condition, FieldCondition, Filter, HasIdCondition, Payload, PointIdType, WithPayloadInterface,
+    Condition, Direction, FieldCondition, Filter, HasIdCondition, OrderBy, Payload,
+    PayloadSchemaType, PointIdType, WithPayloadInterface,
 };
 use tempfile::Builder;
 
@@ -150,6 +151,7 @@ async fn test_collection_search_with_payload_and_vector_with_shards(shard_number
             range: None,
             geo_bounding_box: None,
             geo_radius: None,
+            geo_polygon: None,
             values_count: None,
         }))),
         exact: true,
@@ -393,6 +395,7 @@ async fn test_read_api_with_shards(shard_number: u32) {
                 filter: None,
                 with_payload: Some(WithPayloadInterface::Bool(true)),
                 with_vector: false.into(),
+                order_by: None,
             },
             None,
             None,
@@ -477,6 +480,7 @@ async fn test_collection_delete_points_by_filter_with_shards(shard_number: u32)
                 filter: None,
                 with_payload: Some(WithPayloadInterface::Bool(false)),
                 with_vector: false.into(),
+                order_by: None,
             },
             None,
             None,
@@ -491,3 +495,115 @@ async fn test_collection_delete_points_by_filter_with_shards(shard_number: u32)
     assert_eq!(result.points.get(2).unwrap().id, 4.into());
     collection.before_drop().await;
 }
+
+#[tokio::test]
+async fn test_ordered_scroll_api() {
+    test_ordered_scroll_api_with_shards(1).await;
+    test_ordered_scroll_api_with_shards(N_SHARDS).await;
+}
+
+async fn test_ordered_scroll_api_with_shards(shard_number: u32) {
+    let collection_dir = Builder::new().prefix("collection").tempdir().unwrap();
+    let mut collection = simple_collection_fixture(collection_dir.path(), shard_number).await;
+
+    let create_index = CollectionUpdateOperations::FieldIndexOperation(
+        FieldIndexOperations::CreateIndex(CreateIndex {
+            field_name: "timestamp".to_string(),
+            field_schema: Some(PayloadSchemaType::Integer.into()),
+        }),
+    );
+    collection
+        .update_from_client(create_index, true, WriteOrdering::default())
+        .await
+        .unwrap();
+
+    // Point 8 has no timestamp, so it is not scrolled
+    let insert_points = CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+        Batch {
+            ids: (0..9).map(|x: u64| x.into()).collect_vec(),
+            vectors: vec![vec![1.0, 0.0, 1.0, 1.0]; 9].into(),
+            payloads: serde_json::from_str(
+                r#"[
+                    {"timestamp": 30}, {"timestamp": 10}, {"timestamp": 20},
+                    {"timestamp": 30}, {"timestamp": 20}, {"timestamp": 40},
+                    {"timestamp": 30}, {"timestamp": 10}, {}
+                ]"#,
+            )
+            .unwrap(),
+        }
+        .into(),
+    ));
+    collection
+        .update_from_client(insert_points, true, WriteOrdering::default())
+        .await
+        .unwrap();
+
+    let mut scrolled = Vec::new();
+    let mut offset = None;
+    let mut start_from = None;
+    loop {
+        let result = collection
+            .scroll_by(
+                ScrollRequest {
+                    offset,
+                    limit: Some(2),
+                    filter: None,
+                    with_payload: Some(WithPayloadInterface::Bool(false)),
+                    with_vector: false.into(),
+                    order_by: Some(OrderBy {
+                        key: "timestamp".to_string(),
+                        direction: Direction::Desc,
+                        start_from,
+                    }),
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        scrolled.extend(
+            result
+                .points
+                .iter()
+                .map(|point| (point.order_value.unwrap(), point.id.clone())),
+        );
+        if result.next_page_offset.is_none() {
+            break;
+        }
+        offset = result.next_page_offset;
+        start_from = result.next_page_start_from;
+    }
+
+    // Points with equal timestamps are split between the pages, but each is scrolled once
+    let expected: Vec<(f64, PointIdType)> = [
+        (40.0, 5),
+        (30.0, 0),
+        (30.0, 3),
+        (30.0, 6),
+        (20.0, 2),
+        (20.0, 4),
+        (10.0, 1),
+        (10.0, 7),
+    ]
+    .into_iter()
+    .map(|(value, id)| (value, id.into()))
+    .collect();
+    assert_eq!(scrolled, expected);
+
+    let unindexed = collection
+        .scroll_by(
+            ScrollRequest {
+                order_by: Some(OrderBy {
+                    key: "price".to_string(),
+                    direction: Direction::Asc,
+                    start_from: None,
+                }),
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .await;
+    assert!(unindexed.is_err());
+    collection.before_drop().await;
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use segment::common::cpu::get_num_cpus;
+use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+use segment::entry::entry_point::SegmentEntry;
+use segment::fixtures::payload_fixtures::random_vector;
+use segment::index::VectorIndex;
+use segment::segment_constructor::build_segment;
+use segment::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+use tempfile::Builder;
+
+const NUM_VECTORS: u64 = 100_000;
+const DIM: usize = 128;
+const NUM_QUERIES: usize = 64;
+const TOP: usize = 10;
+
+fn batch_search_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+    let config = SegmentConfig {
+        vector_data: HashMap::from([(
+            DEFAULT_VECTOR_NAME.to_owned(),
+            VectorDataConfig {
+                size: DIM,
+                distance: Distance::Dot,
+                hnsw_config: None,
+                quantization_config: None,
+            },
+        )]),
+        index: Indexes::Plain {},
+        storage_type: StorageType::InMemory,
+        ..Default::default()
+    };
+    let mut segment = build_segment(dir.path(), &config).unwrap();
+    for n in 0..NUM_VECTORS {
+        let vector = random_vector(&mut rng, DIM);
+        segment
+            .upsert_vector(n, n.into(), &only_default_vector(&vector))
+            .unwrap();
+    }
+
+    let queries: Vec<_> = (0..NUM_QUERIES)
+        .map(|_| random_vector(&mut rng, DIM))
+        .collect();
+    let query_refs: Vec<_> = queries.iter().map(|query| query.as_slice()).collect();
+    let vector_index = segment.vector_data[DEFAULT_VECTOR_NAME]
+        .vector_index
+        .clone();
+
+    let mut group = c.benchmark_group("batch-search-group");
+
+    // Expected to scale near-linearly up to the number of the cores
+    let mut threads = 1;
+    while threads <= get_num_cpus() {
+        vector_index.write().set_search_threads(Some(threads));
+        group.bench_function(
+            format!("batch_search_{NUM_QUERIES}_threads_{threads}"),
+            |b| b.iter(|| vector_index.read().search(&query_refs, None, TOP, None)),
+        );
+        threads *= 2;
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, batch_search_benchmark);
+criterion_main!(benches);
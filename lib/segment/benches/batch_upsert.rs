@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::env;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use segment::data_types::named_vectors::NamedVectors;
+use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+use segment::entry::entry_point::SegmentEntry;
+use segment::fixtures::payload_fixtures::{random_keyword, random_vector};
+use segment::segment_constructor::build_segment;
+use segment::types::{
+    Distance, Indexes, Payload, PointIdType, SegmentConfig, StorageType, VectorDataConfig,
+};
+use serde_json::json;
+use tempfile::Builder;
+
+/// Default number of the upserted points, set `BATCH_UPSERT_BENCH_POINTS` to change it
+const DEFAULT_NUM_POINTS: u64 = 1_000_000;
+const BATCH_SIZE: usize = 1_000;
+const DIM: usize = 16;
+
+/// Upsert of the points one by one, which locks the storages per point,
+/// against the batches, which lock each storage once per batch
+fn batch_upsert_benchmark(c: &mut Criterion) {
+    let num_points = env::var("BATCH_UPSERT_BENCH_POINTS")
+        .ok()
+        .and_then(|points| points.parse().ok())
+        .unwrap_or(DEFAULT_NUM_POINTS);
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let point_ids: Vec<PointIdType> = (0..num_points).map(|n| n.into()).collect();
+    let vectors: Vec<NamedVectors> = (0..num_points)
+        .map(|_| only_default_vector(&random_vector(&mut rng, DIM)))
+        .collect();
+    let payloads: Vec<Payload> = (0..num_points)
+        .map(|_| json!({ "keyword": random_keyword(&mut rng) }).into())
+        .collect();
+
+    let config = SegmentConfig {
+        vector_data: HashMap::from([(
+            DEFAULT_VECTOR_NAME.to_owned(),
+            VectorDataConfig {
+                size: DIM,
+                distance: Distance::Dot,
+                hnsw_config: None,
+                quantization_config: None,
+            },
+        )]),
+        index: Indexes::Plain {},
+        storage_type: StorageType::InMemory,
+        ..Default::default()
+    };
+    let new_segment = || {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = build_segment(dir.path(), &config).unwrap();
+        (dir, segment)
+    };
+
+    let mut group = c.benchmark_group("batch-upsert-group");
+    group.sample_size(10);
+
+    group.bench_function("per_point", |b| {
+        b.iter_batched(
+            new_segment,
+            |(_dir, mut segment)| {
+                for (op_num, ((point_id, vectors), payload)) in
+                    point_ids.iter().zip(&vectors).zip(&payloads).enumerate()
+                {
+                    let op_num = op_num as u64;
+                    segment
+                        .upsert_vector(op_num, point_id.clone(), vectors)
+                        .unwrap();
+                    segment
+                        .set_full_payload(op_num, point_id.clone(), payload)
+                        .unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.bench_function("batched", |b| {
+        b.iter_batched(
+            new_segment,
+            |(_dir, mut segment)| {
+                let chunks = point_ids
+                    .chunks(BATCH_SIZE)
+                    .zip(vectors.chunks(BATCH_SIZE))
+                    .zip(payloads.chunks(BATCH_SIZE));
+                for (op_num, ((point_ids, vectors), payloads)) in chunks.enumerate() {
+                    let payloads: Vec<_> = payloads.iter().map(Some).collect();
+                    segment
+                        .upsert_points(op_num as u64, point_ids, vectors, &payloads)
+                        .unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, batch_upsert_benchmark);
+
+criterion_main!(benches);
@@ -8,9 +8,9 @@ use rand::{Rng, SeedableRng};
 use segment::fixtures::payload_context_fixture::{
     create_plain_payload_index, create_struct_payload_index,
 };
-use segment::fixtures::payload_fixtures::random_must_filter;
+use segment::fixtures::payload_fixtures::{random_keyword, random_must_filter, INT_KEY_2, STR_KEY};
 use segment::index::PayloadIndex;
-use segment::types::PointOffsetType;
+use segment::types::{Condition, FieldCondition, Filter, PointOffsetType, ValuesCount};
 use tempfile::Builder;
 
 const NUM_POINTS: usize = 100000;
@@ -141,6 +141,50 @@ fn conditional_struct_search_benchmark(c: &mut Criterion) {
         result_size / query_count
     );
 
+    let mut result_size = 0;
+    let mut query_count = 0;
+
+    // Non-indexed condition reads payload, so it should be checked after the indexed one
+    group.bench_function("struct-conditional-search-cheap-and-expensive-check", |b| {
+        b.iter(|| {
+            let filter = Filter {
+                should: None,
+                must: Some(vec![
+                    Condition::Field(FieldCondition::new_values_count(
+                        INT_KEY_2.to_string(),
+                        ValuesCount {
+                            lt: None,
+                            gt: None,
+                            gte: Some(1),
+                            lte: None,
+                        },
+                    )),
+                    Condition::Field(FieldCondition::new_match(
+                        STR_KEY.to_string(),
+                        random_keyword(&mut rng).into(),
+                    )),
+                ]),
+                must_not: None,
+            };
+            let sample = (0..CHECK_SAMPLE_SIZE)
+                .map(|_| rng.gen_range(0..NUM_POINTS) as PointOffsetType)
+                .collect_vec();
+            let context = struct_index.filter_context(&filter);
+
+            let filtered_sample = sample
+                .into_iter()
+                .filter(|id| context.check(*id))
+                .collect_vec();
+            result_size += filtered_sample.len();
+            query_count += 1;
+        })
+    });
+
+    eprintln!(
+        "result_size / query_count = {:#?}",
+        result_size / query_count
+    );
+
     group.finish();
 }
 
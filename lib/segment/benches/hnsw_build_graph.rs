@@ -1,9 +1,10 @@
 #[cfg(not(target_os = "windows"))]
 mod prof;
 
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use rand::rngs::StdRng;
 use rand::{thread_rng, SeedableRng};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use segment::fixtures::index_fixtures::{FakeFilterContext, TestRawScorerProducer};
 use segment::index::hnsw_index::graph_layers_builder::GraphLayersBuilder;
 use segment::index::hnsw_index::point_scorer::FilteredScorer;
@@ -40,18 +41,57 @@ fn hnsw_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
+fn hnsw_parallel_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let vector_holder = TestRawScorerProducer::<CosineMetric>::new(DIM, NUM_VECTORS, &mut rng);
+    let mut group = c.benchmark_group("hnsw-index-parallel-build-group");
+    group.sample_size(10);
+    for num_threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        group.bench_function(BenchmarkId::from_parameter(num_threads), |b| {
+            b.iter(|| {
+                let mut rng = thread_rng();
+                let mut graph_layers_builder =
+                    GraphLayersBuilder::new(NUM_VECTORS, M, M * 2, EF_CONSTRUCT, 10, USE_HEURISTIC);
+                for idx in 0..(NUM_VECTORS as PointOffsetType) {
+                    let level = graph_layers_builder.get_random_layer(&mut rng);
+                    graph_layers_builder.set_levels(idx, level);
+                }
+                pool.install(|| {
+                    (0..(NUM_VECTORS as PointOffsetType))
+                        .into_par_iter()
+                        .for_each(|idx| {
+                            let fake_filter_context = FakeFilterContext {};
+                            let added_vector = vector_holder.vectors.get(idx).to_vec();
+                            let raw_scorer = vector_holder.get_raw_scorer(added_vector);
+                            let scorer = FilteredScorer::new(
+                                raw_scorer.as_ref(),
+                                Some(&fake_filter_context),
+                            );
+                            graph_layers_builder.link_new_point(idx, scorer);
+                        })
+                });
+            })
+        });
+    }
+    group.finish();
+}
+
 #[cfg(not(target_os = "windows"))]
 criterion_group! {
     name = benches;
     config = Criterion::default().with_profiler(prof::FlamegraphProfiler::new(100));
-    targets = hnsw_benchmark
+    targets = hnsw_benchmark, hnsw_parallel_benchmark
 }
 
 #[cfg(target_os = "windows")]
 criterion_group! {
     name = benches;
     config = Criterion::default();
-    targets = hnsw_benchmark
+    targets = hnsw_benchmark, hnsw_parallel_benchmark
 }
 
 criterion_main!(benches);
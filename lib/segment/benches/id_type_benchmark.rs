@@ -171,10 +171,42 @@ fn enum_hash_search(c: &mut Criterion) {
     });
 }
 
+/// Lookup cost of ordered and unordered maps of external ids
+fn ordered_vs_hash_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("id-lookup-group");
+    let mut rng = rand::thread_rng();
+
+    let keys: Vec<u64> = (0..1_000_000).map(|_| rng.gen()).collect();
+    let btree: BTreeMap<u64, u32> = keys
+        .iter()
+        .enumerate()
+        .map(|(internal, external)| (*external, internal as u32))
+        .collect();
+    let hash: HashMap<u64, u32> = keys
+        .iter()
+        .enumerate()
+        .map(|(internal, external)| (*external, internal as u32))
+        .collect();
+
+    group.bench_function("btree-u64", |b| {
+        b.iter(|| {
+            let key = keys[rng.gen_range(0..keys.len())];
+            btree.get(&key).unwrap();
+        });
+    });
+
+    group.bench_function("hash-u64", |b| {
+        b.iter(|| {
+            let key = keys[rng.gen_range(0..keys.len())];
+            hash.get(&key).unwrap();
+        });
+    });
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default();
-    targets = id_serialization_speed, u128_hash_search, enum_hash_search
+    targets = id_serialization_speed, u128_hash_search, enum_hash_search, ordered_vs_hash_lookup
 }
 
 criterion_main!(benches);
@@ -0,0 +1,112 @@
+#[cfg(not(target_os = "windows"))]
+mod prof;
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{thread_rng, SeedableRng};
+use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+use segment::entry::entry_point::SegmentEntry;
+use segment::fixtures::payload_fixtures::random_vector;
+use segment::index::ivf_index::ivf::IvfIndex;
+use segment::index::VectorIndex;
+use segment::segment_constructor::build_segment;
+use segment::types::{
+    Distance, Indexes, IvfConfig, SearchParams, SegmentConfig, StorageType, VectorDataConfig,
+};
+use tempfile::Builder;
+
+const NUM_VECTORS: u64 = 100000;
+const DIM: usize = 64;
+const N_LISTS: usize = 256;
+const TOP: usize = 10;
+
+fn ivf_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+    let config = SegmentConfig {
+        vector_data: HashMap::from([(
+            DEFAULT_VECTOR_NAME.to_owned(),
+            VectorDataConfig {
+                size: DIM,
+                distance: Distance::Cosine,
+                hnsw_config: None,
+                quantization_config: None,
+            },
+        )]),
+        index: Indexes::Plain {},
+        storage_type: StorageType::InMemory,
+        ..Default::default()
+    };
+    let mut segment = build_segment(dir.path(), &config).unwrap();
+    for n in 0..NUM_VECTORS {
+        let vector = random_vector(&mut rng, DIM);
+        segment
+            .upsert_vector(n, n.into(), &only_default_vector(&vector))
+            .unwrap();
+    }
+
+    let ivf_dir = Builder::new().prefix("ivf_dir").tempdir().unwrap();
+    let mut ivf_index = IvfIndex::open(
+        ivf_dir.path(),
+        segment.id_tracker.clone(),
+        segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_storage
+            .clone(),
+        segment.payload_index.clone(),
+        IvfConfig {
+            n_lists: N_LISTS,
+            seed: Some(42),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    ivf_index.build_index(&AtomicBool::new(false)).unwrap();
+
+    let mut group = c.benchmark_group("ivf-index-search-group");
+    let mut rng = thread_rng();
+
+    for nprobe in [1, 8, 32] {
+        let params = SearchParams {
+            ivf_nprobe: Some(nprobe),
+            ..Default::default()
+        };
+        group.bench_function(format!("ivf_search_nprobe_{nprobe}"), |b| {
+            b.iter(|| {
+                let query = random_vector(&mut rng, DIM);
+                ivf_index.search(&[query.as_slice()], None, TOP, Some(&params));
+            })
+        });
+    }
+
+    let exact_params = SearchParams {
+        exact: true,
+        ..Default::default()
+    };
+    group.bench_function("plain_search", |b| {
+        b.iter(|| {
+            let query = random_vector(&mut rng, DIM);
+            ivf_index.search(&[query.as_slice()], None, TOP, Some(&exact_params));
+        })
+    });
+
+    group.finish();
+}
+
+#[cfg(not(target_os = "windows"))]
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_profiler(prof::FlamegraphProfiler::new(100));
+    targets = ivf_benchmark
+}
+
+#[cfg(target_os = "windows")]
+criterion_group! {
+    name = benches;
+    config = Criterion::default();
+    targets = ivf_benchmark
+}
+
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+use segment::entry::entry_point::SegmentEntry;
+use segment::fixtures::payload_fixtures::{random_keyword, random_vector};
+use segment::segment::Segment;
+use segment::segment_constructor::build_segment;
+use segment::types::{
+    Distance, Indexes, Payload, PayloadStorageType, ScoredPoint, SegmentConfig, StorageType,
+    VectorDataConfig, WithPayload,
+};
+use serde_json::json;
+use tempfile::Builder;
+
+/// Counts the allocations, so the allocations of a search are reported along with its time
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const NUM_POINTS: u64 = 10_000;
+const DIM: usize = 64;
+const TOP: usize = 100;
+/// Number of the keywords of each payload, which makes the payloads a few KB large
+const PAYLOAD_KEYWORDS: usize = 500;
+
+fn search_with_payload(segment: &Segment, query: &[f32]) -> Vec<ScoredPoint> {
+    segment
+        .search(
+            DEFAULT_VECTOR_NAME,
+            query,
+            &WithPayload::from(true),
+            &false.into(),
+            None,
+            TOP,
+            None,
+        )
+        .unwrap()
+}
+
+/// Number of the allocations, made by `f`
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// Searches, which request the payloads, with the shared payloads and with the deep copies of them,
+/// which were made by each read before the payloads were shared
+fn payload_read_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+    let config = SegmentConfig {
+        vector_data: HashMap::from([(
+            DEFAULT_VECTOR_NAME.to_owned(),
+            VectorDataConfig {
+                size: DIM,
+                distance: Distance::Dot,
+                hnsw_config: None,
+                quantization_config: None,
+            },
+        )]),
+        index: Indexes::Plain {},
+        storage_type: StorageType::InMemory,
+        payload_storage_type: PayloadStorageType::InMemory,
+        ..Default::default()
+    };
+    let mut segment = build_segment(dir.path(), &config).unwrap();
+    for n in 0..NUM_POINTS {
+        let vector = random_vector(&mut rng, DIM);
+        segment
+            .upsert_vector(n, n.into(), &only_default_vector(&vector))
+            .unwrap();
+        let keywords: Vec<_> = (0..PAYLOAD_KEYWORDS)
+            .map(|_| random_keyword(&mut rng))
+            .collect();
+        let payload: Payload = json!({ "id": n, "keywords": keywords }).into();
+        segment.set_full_payload(n, n.into(), &payload).unwrap();
+    }
+
+    let query = random_vector(&mut rng, DIM);
+    let shared_search = || search_with_payload(&segment, &query);
+    let deep_clone_search = || {
+        search_with_payload(&segment, &query)
+            .into_iter()
+            .map(|point| point.payload.map(|payload| payload.as_ref().clone()))
+            .collect::<Vec<Option<Payload>>>()
+    };
+
+    eprintln!(
+        "allocations per search: shared = {}, deep clone = {}",
+        count_allocations(|| drop(shared_search())),
+        count_allocations(|| drop(deep_clone_search())),
+    );
+
+    let mut group = c.benchmark_group("payload-read-group");
+
+    group.bench_function("shared_payloads", |b| b.iter(shared_search));
+    group.bench_function("deep_cloned_payloads", |b| b.iter(deep_clone_search));
+
+    group.finish();
+}
+
+criterion_group!(benches, payload_read_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::env;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use segment::common::parallel::{get_load_threads, set_load_threads};
+use segment::data_types::named_vectors::NamedVectors;
+use segment::entry::entry_point::SegmentEntry;
+use segment::fixtures::payload_fixtures::{random_keyword, random_vector};
+use segment::segment_constructor::{build_segment, load_segment};
+use segment::types::{
+    Distance, Indexes, Payload, PayloadSchemaType, SegmentConfig, StorageType, VectorDataConfig,
+};
+use serde_json::json;
+use tempfile::Builder;
+
+/// Default size of the synthetic segment is ~4 GB of vectors, set `SEGMENT_LOAD_BENCH_POINTS` to change it
+const DEFAULT_NUM_POINTS: u64 = 500_000;
+const NUM_VECTORS: usize = 4;
+const DIM: usize = 512;
+
+fn segment_load_benchmark(c: &mut Criterion) {
+    let num_points = env::var("SEGMENT_LOAD_BENCH_POINTS")
+        .ok()
+        .and_then(|points| points.parse().ok())
+        .unwrap_or(DEFAULT_NUM_POINTS);
+    let vector_names: Vec<_> = (0..NUM_VECTORS).map(|i| format!("vector_{i}")).collect();
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+    let config = SegmentConfig {
+        vector_data: vector_names
+            .iter()
+            .map(|vector_name| {
+                (
+                    vector_name.clone(),
+                    VectorDataConfig {
+                        size: DIM,
+                        distance: Distance::Dot,
+                        hnsw_config: None,
+                        quantization_config: None,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>(),
+        index: Indexes::Plain {},
+        storage_type: StorageType::InMemory,
+        ..Default::default()
+    };
+    let mut segment = build_segment(dir.path(), &config).unwrap();
+    segment
+        .create_field_index(0, "keyword", Some(&PayloadSchemaType::Keyword.into()))
+        .unwrap();
+    segment
+        .create_field_index(1, "count", Some(&PayloadSchemaType::Integer.into()))
+        .unwrap();
+    for n in 0..num_points {
+        let mut vectors = NamedVectors::default();
+        for vector_name in &vector_names {
+            vectors.insert(vector_name.clone(), random_vector(&mut rng, DIM));
+        }
+        segment.upsert_vector(n, n.into(), &vectors).unwrap();
+        let payload: Payload = json!({ "keyword": random_keyword(&mut rng), "count": n }).into();
+        segment.set_full_payload(n, n.into(), &payload).unwrap();
+    }
+    let path = segment.current_path.clone();
+    segment.close().unwrap();
+
+    let mut group = c.benchmark_group("segment-load-group");
+    group.sample_size(10);
+
+    for threads in [1, 0] {
+        set_load_threads(threads);
+        group.bench_function(
+            format!("load_segment_threads_{}", get_load_threads()),
+            |b| b.iter_with_large_drop(|| load_segment(&path).unwrap().unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, segment_load_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use segment::spaces::tools::peek_top_largest_iterable;
+use segment::vector_storage::ScoredPointOffset;
+
+const NUM_POINTS: u32 = 1_000_000;
+const TOP: usize = 10;
+
+/// Selection of a small top of a large segment: collecting and sorting all scores
+/// allocates for every point, the bounded queue only holds `TOP` of them
+fn top_selection_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let scores: Vec<f32> = (0..NUM_POINTS).map(|_| rng.gen()).collect();
+    let scored = || {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(idx, &score)| ScoredPointOffset {
+                idx: idx as u32,
+                score,
+            })
+    };
+
+    let mut group = c.benchmark_group("top-selection-group");
+
+    group.bench_function("collect_and_sort", |b| {
+        b.iter(|| {
+            let mut all: Vec<_> = scored().collect();
+            all.sort_unstable_by(|a, b| b.cmp(a));
+            all.truncate(TOP);
+            all
+        })
+    });
+
+    group.bench_function("bounded_queue", |b| {
+        b.iter(|| peek_top_largest_iterable(scored(), TOP))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, top_selection_benchmark);
+
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use parking_lot::RwLock;
+use rand::distributions::Standard;
+use rand::Rng;
+use segment::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
+use segment::data_types::vectors::VectorElementType;
+use segment::fixtures::payload_context_fixture::FixtureIdTracker;
+use segment::id_tracker::IdTracker;
+use segment::types::{Distance, PointOffsetType, VectorLayout};
+use segment::vector_storage::simple_vector_storage::open_simple_vector_storage;
+use segment::vector_storage::{new_raw_scorer, VectorStorage, VectorStorageEnum};
+use tempfile::Builder;
+
+const NUM_VECTORS: usize = 100_000;
+/// Dimensions, which are not the multiples of the SIMD loop, are slowed down by the scalar tail
+const DIMS: [usize; 3] = [100, 384, 1000];
+
+fn random_vector(size: usize) -> Vec<VectorElementType> {
+    let rng = rand::thread_rng();
+
+    rng.sample_iter(Standard).take(size).collect()
+}
+
+fn init_vector_storage(
+    path: &Path,
+    vectors: &[Vec<VectorElementType>],
+    layout: VectorLayout,
+) -> Arc<RwLock<VectorStorageEnum>> {
+    let db = open_db(path, &[DB_VECTOR_CF]).unwrap();
+    let dim = vectors[0].len();
+    let storage =
+        open_simple_vector_storage(db, DB_VECTOR_CF, dim, Distance::Dot, None, layout).unwrap();
+    {
+        let mut borrowed_storage = storage.write();
+        for (i, vector) in vectors.iter().enumerate() {
+            borrowed_storage
+                .insert_vector(i as PointOffsetType, vector)
+                .unwrap();
+        }
+    }
+    storage
+}
+
+fn vector_layout_benchmark(c: &mut Criterion) {
+    let id_tracker = FixtureIdTracker::new(NUM_VECTORS);
+    let mut group = c.benchmark_group("vector-layout-score-all");
+
+    for dim in DIMS {
+        let vectors: Vec<_> = (0..NUM_VECTORS).map(|_| random_vector(dim)).collect();
+        for (name, layout) in [
+            ("packed", VectorLayout::Packed),
+            ("aligned", VectorLayout::Aligned),
+        ] {
+            let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+            let storage = init_vector_storage(dir.path(), &vectors, layout);
+            let borrowed_storage = storage.read();
+
+            group.bench_function(format!("{name}-{dim}"), |b| {
+                b.iter(|| {
+                    let vector = random_vector(dim);
+                    new_raw_scorer(vector, &borrowed_storage, id_tracker.deleted_bitvec())
+                        .peek_top_all(10)
+                })
+            });
+        }
+    }
+}
+
+criterion_group!(benches, vector_layout_benchmark);
+criterion_main!(benches);
@@ -1,8 +1,8 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
 use criterion::{criterion_group, criterion_main, Criterion};
+use parking_lot::RwLock;
 use rand::distributions::Standard;
 use rand::Rng;
 use segment::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
@@ -28,15 +28,13 @@ fn init_vector_storage(
     dim: usize,
     num: usize,
     dist: Distance,
-) -> (
-    Arc<AtomicRefCell<VectorStorageEnum>>,
-    Arc<AtomicRefCell<IdTrackerSS>>,
-) {
+) -> (Arc<RwLock<VectorStorageEnum>>, Arc<RwLock<IdTrackerSS>>) {
     let db = open_db(path, &[DB_VECTOR_CF]).unwrap();
-    let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(num)));
-    let storage = open_simple_vector_storage(db, DB_VECTOR_CF, dim, dist).unwrap();
+    let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(num)));
+    let storage =
+        open_simple_vector_storage(db, DB_VECTOR_CF, dim, dist, None, Default::default()).unwrap();
     {
-        let mut borrowed_storage = storage.borrow_mut();
+        let mut borrowed_storage = storage.write();
         for i in 0..num {
             let vector: Vec<VectorElementType> = random_vector(dim);
             borrowed_storage
@@ -53,8 +51,8 @@ fn benchmark_naive(c: &mut Criterion) {
 
     let dist = Distance::Dot;
     let (storage, id_tracker) = init_vector_storage(dir.path(), DIM, NUM_VECTORS, dist);
-    let borrowed_storage = storage.borrow();
-    let borrowed_id_tracker = id_tracker.borrow();
+    let borrowed_storage = storage.read();
+    let borrowed_id_tracker = id_tracker.read();
 
     let mut group = c.benchmark_group("storage-score-all");
 
@@ -76,8 +74,8 @@ fn random_access_benchmark(c: &mut Criterion) {
 
     let dist = Distance::Dot;
     let (storage, id_tracker) = init_vector_storage(dir.path(), DIM, NUM_VECTORS, dist);
-    let borrowed_storage = storage.borrow();
-    let borrowed_id_tracker = id_tracker.borrow();
+    let borrowed_storage = storage.read();
+    let borrowed_id_tracker = id_tracker.read();
 
     let mut group = c.benchmark_group("storage-score-random");
 
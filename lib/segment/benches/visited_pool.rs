@@ -0,0 +1,65 @@
+#[cfg(not(target_os = "windows"))]
+mod prof;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use segment::index::visited_pool::{VisitedList, VisitedPool};
+use segment::types::PointOffsetType;
+
+const NUM_POINTS: usize = 1_000_000;
+/// Number of points, visited by a single search
+const VISITED_POINTS: usize = 2_000;
+
+fn visit(visited_list: &mut VisitedList, points: &[PointOffsetType]) -> usize {
+    points
+        .iter()
+        .filter(|&&point_id| !visited_list.check_and_update_visited(point_id))
+        .count()
+}
+
+/// Allocating a list per search zeroes the whole list, so its latency grows with the number of
+/// points, while a pooled list only touches the visited points
+fn visited_list_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("visited-list-group");
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let points: Vec<PointOffsetType> = (0..VISITED_POINTS)
+        .map(|_| rng.gen_range(0..NUM_POINTS as PointOffsetType))
+        .collect();
+
+    group.bench_function("allocate-per-search", |b| {
+        b.iter(|| {
+            let mut visited_list = VisitedList::new(NUM_POINTS);
+            black_box(visit(&mut visited_list, &points))
+        });
+    });
+
+    let pool = VisitedPool::new();
+    group.bench_function("pooled", |b| {
+        b.iter(|| {
+            let mut visited_list = pool.get(NUM_POINTS);
+            let visited = visit(&mut visited_list, &points);
+            pool.return_back(visited_list);
+            black_box(visited)
+        });
+    });
+
+    group.finish();
+}
+
+#[cfg(not(target_os = "windows"))]
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_profiler(prof::FlamegraphProfiler::new(100));
+    targets = visited_list_benchmark
+}
+
+#[cfg(target_os = "windows")]
+criterion_group! {
+    name = benches;
+    config = Criterion::default();
+    targets = visited_list_benchmark
+}
+
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::AtomicBool;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use segment::common::warm_up::WarmUpParts;
+use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+use segment::entry::entry_point::SegmentEntry;
+use segment::fixtures::payload_fixtures::random_vector;
+use segment::segment_constructor::segment_builder::SegmentBuilder;
+use segment::segment_constructor::{build_segment, load_segment};
+use segment::types::{
+    Distance, HnswConfig, Indexes, SegmentConfig, StorageType, VectorDataConfig, WithPayload,
+};
+use tempfile::Builder;
+
+/// Default number of the points of the segment, set `WARM_UP_BENCH_POINTS` to change it
+const DEFAULT_NUM_POINTS: u64 = 200_000;
+const DIM: usize = 128;
+const NUM_QUERIES: usize = 100;
+
+/// First queries of a freshly loaded mem-mapped segment, with and without the warm up
+///
+/// Page cache is only cold, if it is dropped before each load, e.g. with
+/// `echo 3 > /proc/sys/vm/drop_caches`, otherwise both variants run on the warm cache.
+fn warm_up_benchmark(c: &mut Criterion) {
+    let num_points = env::var("WARM_UP_BENCH_POINTS")
+        .ok()
+        .and_then(|points| points.parse().ok())
+        .unwrap_or(DEFAULT_NUM_POINTS);
+    let stopped = AtomicBool::new(false);
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let vector_data = HashMap::from([(
+        DEFAULT_VECTOR_NAME.to_owned(),
+        VectorDataConfig {
+            size: DIM,
+            distance: Distance::Dot,
+            hnsw_config: None,
+            quantization_config: None,
+        },
+    )]);
+    let plain_dir = Builder::new()
+        .prefix("plain_segment_dir")
+        .tempdir()
+        .unwrap();
+    let plain_config = SegmentConfig {
+        vector_data: vector_data.clone(),
+        index: Indexes::Plain {},
+        storage_type: StorageType::InMemory,
+        ..Default::default()
+    };
+    let mut plain_segment = build_segment(plain_dir.path(), &plain_config).unwrap();
+    for n in 0..num_points {
+        let vector = random_vector(&mut rng, DIM);
+        plain_segment
+            .upsert_vector(n, n.into(), &only_default_vector(&vector))
+            .unwrap();
+    }
+
+    let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+    let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+    let config = SegmentConfig {
+        vector_data,
+        index: Indexes::Hnsw(HnswConfig {
+            on_disk: Some(true),
+            ..Default::default()
+        }),
+        storage_type: StorageType::Mmap,
+        ..Default::default()
+    };
+    let mut builder = SegmentBuilder::new(segment_dir.path(), temp_dir.path(), &config).unwrap();
+    builder.update_from(&plain_segment, &stopped).unwrap();
+    let segment = builder.build(&stopped).unwrap();
+    let path = segment.current_path.clone();
+    segment.close().unwrap();
+    drop(plain_segment);
+
+    let queries: Vec<_> = (0..NUM_QUERIES)
+        .map(|_| random_vector(&mut rng, DIM))
+        .collect();
+    let run_queries = |segment: &dyn SegmentEntry| {
+        for query in &queries {
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    query,
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    10,
+                    None,
+                )
+                .unwrap();
+        }
+    };
+
+    let mut group = c.benchmark_group("warm-up-group");
+    group.sample_size(10);
+
+    group.bench_function("cold_start_queries", |b| {
+        b.iter_batched(
+            || load_segment(&path).unwrap().unwrap(),
+            |segment| run_queries(&segment),
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.bench_function("warmed_up_queries", |b| {
+        b.iter_batched(
+            || {
+                let segment = load_segment(&path).unwrap().unwrap();
+                segment.warm_up(WarmUpParts::all()).unwrap();
+                segment
+            },
+            |segment| run_queries(&segment),
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, warm_up_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+#ifndef SEGMENT_FFI_H
+#define SEGMENT_FFI_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Status of the call
+ */
+typedef enum SegmentStatus {
+  SEGMENT_STATUS_OK = 0,
+  /**
+   * Null pointer, string which is not UTF-8, malformed config or wrong vector dimension
+   */
+  SEGMENT_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * No segment in the directory
+   */
+  SEGMENT_STATUS_NOT_FOUND = 2,
+  /**
+   * Operation of the segment failed
+   */
+  SEGMENT_STATUS_OPERATION_FAILED = 3,
+  /**
+   * Panic was caught at the boundary, the handle should not be used anymore
+   */
+  SEGMENT_STATUS_PANIC = 4,
+} SegmentStatus;
+
+/**
+ * Opaque segment, owned by the caller until it is passed to [`segment_free`]
+ */
+typedef struct SegmentHandle SegmentHandle;
+
+/**
+ * Build a new segment in a subdirectory of `path`, with the config, serialized into JSON
+ *
+ * # Safety
+ *
+ * `path` and `config_json` are nul-terminated strings, `out` is a writable pointer.
+ */
+enum SegmentStatus segment_create(const char *path,
+                                  const char *config_json,
+                                  struct SegmentHandle **out);
+
+/**
+ * Load the segment from its directory
+ *
+ * # Safety
+ *
+ * `path` is a nul-terminated string, `out` is a writable pointer.
+ */
+enum SegmentStatus segment_open(const char *path, struct SegmentHandle **out);
+
+/**
+ * Insert or replace the vector of the point, as the next operation of the segment
+ *
+ * # Safety
+ *
+ * `handle` is returned by [`segment_create`] or [`segment_open`], `vector` points to `dim` floats.
+ */
+enum SegmentStatus segment_upsert(struct SegmentHandle *handle,
+                                  uint64_t id,
+                                  const float *vector,
+                                  size_t dim);
+
+/**
+ * Search for the `top` closest points, `out_found` is set to the number of the found ones
+ *
+ * # Safety
+ *
+ * `handle` is returned by [`segment_create`] or [`segment_open`], `vector` points to `dim` floats,
+ * `out_ids` and `out_scores` point to `top` elements each, `out_found` is a writable pointer.
+ */
+enum SegmentStatus segment_search(const struct SegmentHandle *handle,
+                                  const float *vector,
+                                  size_t dim,
+                                  size_t top,
+                                  uint64_t *out_ids,
+                                  float *out_scores,
+                                  size_t *out_found);
+
+/**
+ * Persist the applied operations
+ *
+ * # Safety
+ *
+ * `handle` is returned by [`segment_create`] or [`segment_open`].
+ */
+enum SegmentStatus segment_flush(struct SegmentHandle *handle);
+
+/**
+ * Flush and close the segment, null is ignored
+ *
+ * # Safety
+ *
+ * `handle` is returned by [`segment_create`] or [`segment_open`], and is not used after the call.
+ */
+void segment_free(struct SegmentHandle *handle);
+
+/**
+ * Message of the last failed call of the thread, null if no call failed
+ *
+ * The string is owned by the library, and is valid until the next failed call of the thread.
+ */
+const char *segment_last_error_message(void);
+
+#endif /* SEGMENT_FFI_H */
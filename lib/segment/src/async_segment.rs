@@ -0,0 +1,416 @@
+//! Async interface of the [`Segment`], available with the `async` feature
+//!
+//! Operations of the segment are blocking, so they are dispatched into a dedicated [`BlockingPool`]
+//! instead of running on the workers of the async executor.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::data_types::named_vectors::NamedVectors;
+use crate::data_types::vectors::{VectorElementType, VectorStruct};
+use crate::entry::entry_point::{
+    check_process_stopped, OperationError, OperationResult, SegmentEntry,
+};
+use crate::segment::Segment;
+use crate::types::{
+    Filter, Payload, PointIdType, ScoredPoint, SearchParams, SeqNumberType, WithPayload, WithVector,
+};
+
+/// Thread pool for the blocking operations of the segments, with the bounded queue
+///
+/// Operations, which don't fit into the queue, wait for a free slot without blocking the executor.
+pub struct BlockingPool {
+    pool: rayon::ThreadPool,
+    /// Slots of the operations, which are running or queued in the pool
+    slots: Arc<Semaphore>,
+}
+
+impl BlockingPool {
+    /// Pool of `num_threads` threads, which queues up to `max_queued` operations besides the running ones
+    pub fn new(num_threads: usize, max_queued: usize) -> OperationResult<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|idx| format!("segment-blocking-{idx}"))
+            .panic_handler(|_| log::error!("Blocking segment operation panicked"))
+            .build()
+            .map_err(|err| {
+                OperationError::service_error(format!("Failed to create blocking pool: {err}"))
+            })?;
+        Ok(BlockingPool {
+            pool,
+            slots: Arc::new(Semaphore::new(num_threads + max_queued)),
+        })
+    }
+
+    /// Run the operation in the pool, once there is a free slot in the queue
+    ///
+    /// Operation is given the stop flag, which is set once the returned future is dropped, e.g. by a
+    /// timeout or by a disconnected client. Operations, cancelled while queued, are not started.
+    pub async fn run<T, F>(&self, operation: F) -> OperationResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Arc<AtomicBool>) -> OperationResult<T> + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let _stop_on_drop = StopOnDrop(stopped.clone());
+
+        let slot = self
+            .slots
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| OperationError::service_error("Blocking pool is closed"))?;
+        let (sender, receiver) = oneshot::channel();
+        self.pool.spawn(move || {
+            let _slot = slot;
+            let result = check_process_stopped(&stopped).and_then(|_| operation(stopped));
+            // Receiver is gone, if the operation was cancelled
+            let _ = sender.send(result);
+        });
+        receiver
+            .await
+            .map_err(|_| OperationError::service_error("Blocking segment operation panicked"))?
+    }
+}
+
+/// Sets the stop flag, once the future of the operation is dropped
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Point, read by [`AsyncSegment::retrieve`]
+#[derive(Debug, Clone)]
+pub struct RetrievedPoint {
+    pub id: PointIdType,
+    pub version: SeqNumberType,
+    pub payload: Option<Arc<Payload>>,
+    pub vector: Option<VectorStruct>,
+}
+
+/// Segment with the async operations, which are run in the [`BlockingPool`]
+///
+/// Cancellation of a search stops it early. Writes are not interrupted once started,
+/// so a cancelled write is either applied completely or not started at all.
+#[derive(Clone)]
+pub struct AsyncSegment {
+    segment: Arc<RwLock<Segment>>,
+    pool: Arc<BlockingPool>,
+}
+
+impl AsyncSegment {
+    pub fn new(segment: Arc<RwLock<Segment>>, pool: Arc<BlockingPool>) -> Self {
+        AsyncSegment { segment, pool }
+    }
+
+    pub fn segment(&self) -> &Arc<RwLock<Segment>> {
+        &self.segment
+    }
+
+    /// Async [`SegmentEntry::search`]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search(
+        &self,
+        vector_name: String,
+        vector: Vec<VectorElementType>,
+        with_payload: WithPayload,
+        with_vector: WithVector,
+        filter: Option<Filter>,
+        top: usize,
+        params: Option<SearchParams>,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        let segment = self.segment.clone();
+        self.pool
+            .run(move |stopped| {
+                segment.read().search_with_stop_flag(
+                    &vector_name,
+                    &vector,
+                    &with_payload,
+                    &with_vector,
+                    filter.as_ref(),
+                    top,
+                    params.as_ref(),
+                    stopped,
+                )
+            })
+            .await
+    }
+
+    /// Read the points by ids, points, missing in the segment, are skipped
+    pub async fn retrieve(
+        &self,
+        point_ids: Vec<PointIdType>,
+        with_payload: WithPayload,
+        with_vector: WithVector,
+    ) -> OperationResult<Vec<RetrievedPoint>> {
+        let segment = self.segment.clone();
+        self.pool
+            .run(move |stopped| {
+                let segment = segment.read();
+                let mut points = Vec::with_capacity(point_ids.len());
+                for point_id in point_ids {
+                    check_process_stopped(&stopped)?;
+                    let Some(version) = segment.point_version(point_id.clone()) else {
+                        continue;
+                    };
+                    let payload = if with_payload.enable {
+                        Some(match &with_payload.payload_selector {
+                            Some(selector) => {
+                                Arc::new(segment.selected_payload(point_id.clone(), selector)?)
+                            }
+                            None => segment.payload(point_id.clone())?,
+                        })
+                    } else {
+                        None
+                    };
+                    let vector = match &with_vector {
+                        WithVector::Bool(false) => None,
+                        WithVector::Bool(true) => {
+                            Some(segment.all_vectors(point_id.clone())?.into())
+                        }
+                        WithVector::Selector(vector_names) => {
+                            let mut vectors = NamedVectors::default();
+                            for vector_name in vector_names {
+                                vectors.insert(
+                                    vector_name.clone(),
+                                    segment.vector(vector_name, point_id.clone())?,
+                                );
+                            }
+                            Some(vectors.into())
+                        }
+                    };
+                    points.push(RetrievedPoint {
+                        id: point_id,
+                        version,
+                        payload,
+                        vector,
+                    });
+                }
+                Ok(points)
+            })
+            .await
+    }
+
+    /// Async [`SegmentEntry::upsert_points`]
+    pub async fn upsert_points(
+        &self,
+        op_num: SeqNumberType,
+        point_ids: Vec<PointIdType>,
+        vectors: Vec<NamedVectors<'static>>,
+        payloads: Vec<Option<Payload>>,
+    ) -> OperationResult<Vec<bool>> {
+        let segment = self.segment.clone();
+        self.pool
+            .run(move |_stopped| {
+                let payloads: Vec<_> = payloads.iter().map(Option::as_ref).collect();
+                segment
+                    .write()
+                    .upsert_points(op_num, &point_ids, &vectors, &payloads)
+            })
+            .await
+    }
+
+    /// Async [`SegmentEntry::delete_point`]
+    pub async fn delete_point(
+        &self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+    ) -> OperationResult<bool> {
+        let segment = self.segment.clone();
+        self.pool
+            .run(move |_stopped| segment.write().delete_point(op_num, point_id))
+            .await
+    }
+
+    /// Async [`SegmentEntry::flush`]
+    pub async fn flush(&self, sync: bool) -> OperationResult<SeqNumberType> {
+        let segment = self.segment.clone();
+        self.pool
+            .run(move |_stopped| segment.read().flush(sync))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::fixtures::payload_fixtures::random_vector;
+    use crate::segment_constructor::build_segment;
+    use crate::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+
+    const DIM: usize = 16;
+    const NUM_POINTS: u64 = 1_000;
+
+    fn async_segment(dir: &std::path::Path, pool: BlockingPool) -> AsyncSegment {
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let segment = build_segment(dir, &config).unwrap();
+        AsyncSegment::new(Arc::new(RwLock::new(segment)), Arc::new(pool))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_searches() {
+        let mut rnd = StdRng::seed_from_u64(42);
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = async_segment(dir.path(), BlockingPool::new(2, 4).unwrap());
+
+        let point_ids: Vec<PointIdType> = (0..NUM_POINTS).map(PointIdType::from).collect();
+        let vectors: Vec<_> = (0..NUM_POINTS)
+            .map(|_| {
+                let mut vectors = NamedVectors::default();
+                vectors.insert(DEFAULT_VECTOR_NAME.to_owned(), random_vector(&mut rnd, DIM));
+                vectors
+            })
+            .collect();
+        let payloads = vec![None; NUM_POINTS as usize];
+        let inserted = segment
+            .upsert_points(1, point_ids, vectors, payloads)
+            .await
+            .unwrap();
+        assert_eq!(inserted, vec![false; NUM_POINTS as usize]);
+
+        // More searches than the slots of the pool, so some of them wait in the queue
+        let queries: Vec<_> = (0..32).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let searches: Vec<_> = queries
+            .iter()
+            .map(|query| {
+                let segment = segment.clone();
+                let query = query.clone();
+                tokio::spawn(async move {
+                    segment
+                        .search(
+                            DEFAULT_VECTOR_NAME.to_owned(),
+                            query,
+                            WithPayload::default(),
+                            false.into(),
+                            None,
+                            10,
+                            None,
+                        )
+                        .await
+                })
+            })
+            .collect();
+        for (search, query) in searches.into_iter().zip(&queries) {
+            let found = search.await.unwrap().unwrap();
+            let expected = segment
+                .segment()
+                .read()
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    query,
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    10,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(
+                found
+                    .iter()
+                    .map(|point| point.id.clone())
+                    .collect::<Vec<_>>(),
+                expected
+                    .iter()
+                    .map(|point| point.id.clone())
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        let retrieved = segment
+            .retrieve(vec![1.into(), NUM_POINTS.into()], true.into(), true.into())
+            .await
+            .unwrap();
+        assert_eq!(retrieved.len(), 1);
+        assert_eq!(retrieved[0].id, 1.into());
+        assert!(retrieved[0].vector.is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cancel_search() {
+        let mut rnd = StdRng::seed_from_u64(42);
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = async_segment(dir.path(), BlockingPool::new(1, 0).unwrap());
+        for n in 0..NUM_POINTS {
+            let vector = random_vector(&mut rnd, DIM);
+            segment
+                .segment()
+                .write()
+                .upsert_vector(n, n.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+
+        // Dropped future sets the stop flag of the in-flight operation
+        let (started_sender, started) = mpsc::channel();
+        let (stopped_sender, stopped) = mpsc::channel();
+        let operation = segment.pool.run(move |stopped: Arc<AtomicBool>| {
+            started_sender.send(()).unwrap();
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            stopped_sender.send(()).unwrap();
+            Ok(())
+        });
+        let operation = tokio::time::timeout(Duration::from_millis(50), operation);
+        assert!(operation.await.is_err());
+        started.recv_timeout(Duration::from_secs(5)).unwrap();
+        stopped.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // Search, which is stopped, is cancelled instead of returning the partial results
+        let stopped = Arc::new(AtomicBool::new(true));
+        let result = segment.segment().read().search_with_stop_flag(
+            DEFAULT_VECTOR_NAME,
+            &random_vector(&mut rnd, DIM),
+            &WithPayload::default(),
+            &false.into(),
+            None,
+            10,
+            None,
+            stopped,
+        );
+        assert!(matches!(result, Err(OperationError::Cancelled { .. })));
+
+        // Pool is released after the cancellation
+        let found = segment
+            .search(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                random_vector(&mut rnd, DIM),
+                WithPayload::default(),
+                false.into(),
+                None,
+                10,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 10);
+    }
+}
@@ -0,0 +1,55 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use parking_lot::{ArcRwLockReadGuard, RawRwLock, RwLock};
+
+/// Intermediate data structure intended to help returning an iterator
+/// which depends on some guarded internal object.
+///
+/// The main idea is that we create a special implementation of iterator, which holds
+/// all required temporary values, which guarantees that source data for the iterator
+/// will be available during the whole time of the iterator existence.
+///
+/// In this specific case the iterator holds the read guard of the `RwLock`,
+/// so the data can't be updated, while it is iterated.
+pub struct ArcRwLockIterator<T: ?Sized, I> {
+    // Declared before the guard, so it is dropped first
+    iterator: I,
+    _guard: ArcRwLockReadGuard<RawRwLock, T>,
+}
+
+impl<'a, T: 'a + ?Sized, I> ArcRwLockIterator<T, I> {
+    pub fn new<F>(holder: Arc<RwLock<T>>, f: F) -> Self
+    where
+        F: FnOnce(&'a T) -> I + 'a,
+    {
+        // We want to express that it's safe to keep the iterator around for as long as the
+        // guard is around. Unfortunately, we can't say this directly with lifetimes, because
+        // we have to return iterator which depends on the guard, stored along with it:
+        //
+        // ```
+        // let guard = holder.read_arc();
+        // return guard.iter()
+        // //     ^^^^^ - borrowed value, moved into the iterator
+        // ```
+        //
+        // Rust does not like that.
+        // That is why we use unsafe pointer dereference to bypass the borrow checker.
+        // This operation should be safe overall, once we are keeping the guard, which owns the `Arc`,
+        // in the iterator: data stays alive and is not updated until the iterator is dropped.
+        let guard = holder.read_arc();
+        let reference = unsafe { &*(guard.deref() as *const T) };
+        Self {
+            iterator: f(reference),
+            _guard: guard,
+        }
+    }
+}
+
+impl<T: ?Sized, I: Iterator> Iterator for ArcRwLockIterator<T, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.iterator.next()
+    }
+}
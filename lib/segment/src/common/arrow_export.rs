@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, FixedSizeListBuilder, Float32Builder, Float64Builder, Int64Builder, StringBuilder,
+};
+use arrow::record_batch::RecordBatch;
+use serde_json::Value;
+
+use crate::data_types::named_vectors::NamedVectors;
+use crate::data_types::vectors::DEFAULT_VECTOR_NAME;
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::types::{
+    Payload, PayloadFieldSchema, PayloadKeyType, PayloadSchemaParams, PayloadSchemaType,
+    PointIdType, SegmentConfig,
+};
+
+/// Number of points in the record batch, so the export memory stays bounded
+pub const ARROW_BATCH_SIZE: usize = 1024;
+
+/// Column of the point id, rendered as a string, as it is either a number or a UUID
+pub const ID_COLUMN: &str = "id";
+/// Column of the whole payload as JSON, so nothing is lost for the keys without the typed column
+pub const PAYLOAD_COLUMN: &str = "payload";
+
+/// Column of the vector, `vector` for the default one and `vector.<name>` for the named ones
+pub fn vector_column(vector_name: &str) -> String {
+    if vector_name == DEFAULT_VECTOR_NAME {
+        "vector".to_owned()
+    } else {
+        format!("vector.{vector_name}")
+    }
+}
+
+/// Typed column of the payload key of the schema
+pub fn payload_column(key: &str) -> String {
+    format!("payload.{key}")
+}
+
+/// Layout of the exported table, derived from the segment config and its payload schema
+///
+/// Columns are the id, the fixed-size-list vectors sorted by name, the typed payload keys sorted by key,
+/// and the whole payload as JSON. Key gets the typed column if it is indexed: keywords and texts are strings,
+/// integers and floats are numbers, and geo points are JSON strings. Value is null if the key is missing,
+/// has several values or the value of the other type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportTable {
+    vectors: Vec<(String, usize)>,
+    payload_fields: Vec<(PayloadKeyType, PayloadSchemaType)>,
+}
+
+impl ExportTable {
+    pub fn new(
+        config: &SegmentConfig,
+        payload_schema: &HashMap<PayloadKeyType, PayloadFieldSchema>,
+        with_vectors: bool,
+    ) -> Self {
+        let mut vectors: Vec<_> = if with_vectors {
+            config
+                .vector_data
+                .iter()
+                .map(|(vector_name, vector_config)| (vector_name.clone(), vector_config.size))
+                .collect()
+        } else {
+            vec![]
+        };
+        vectors.sort();
+
+        let mut payload_fields: Vec<_> = payload_schema
+            .iter()
+            .map(|(key, field_schema)| {
+                let schema_type = match field_schema {
+                    PayloadFieldSchema::FieldType(schema_type) => *schema_type,
+                    PayloadFieldSchema::FieldParams(PayloadSchemaParams::Text(_)) => {
+                        PayloadSchemaType::Text
+                    }
+                    PayloadFieldSchema::FieldParams(PayloadSchemaParams::Keyword(_)) => {
+                        PayloadSchemaType::Keyword
+                    }
+                };
+                (key.clone(), schema_type)
+            })
+            .collect();
+        payload_fields.sort_by(|(key_a, _), (key_b, _)| key_a.cmp(key_b));
+
+        ExportTable {
+            vectors,
+            payload_fields,
+        }
+    }
+
+    pub fn with_vectors(&self) -> bool {
+        !self.vectors.is_empty()
+    }
+
+    pub fn batch_builder(&self) -> BatchBuilder {
+        BatchBuilder {
+            table: self.clone(),
+            ids: StringBuilder::new(),
+            vectors: self
+                .vectors
+                .iter()
+                .map(|(_, size)| FixedSizeListBuilder::new(Float32Builder::new(), *size as i32))
+                .collect(),
+            payload_fields: self
+                .payload_fields
+                .iter()
+                .map(|(_, schema_type)| FieldBuilder::new(*schema_type))
+                .collect(),
+            payload: StringBuilder::new(),
+            len: 0,
+        }
+    }
+
+    /// Batch without rows, which carries the schema of the table
+    pub fn empty_batch(&self) -> OperationResult<RecordBatch> {
+        self.batch_builder().finish()
+    }
+}
+
+enum FieldBuilder {
+    Utf8(StringBuilder),
+    Int64(Int64Builder),
+    Float64(Float64Builder),
+    Json(StringBuilder),
+}
+
+impl FieldBuilder {
+    fn new(schema_type: PayloadSchemaType) -> Self {
+        match schema_type {
+            PayloadSchemaType::Keyword | PayloadSchemaType::Text => {
+                FieldBuilder::Utf8(StringBuilder::new())
+            }
+            PayloadSchemaType::Integer => FieldBuilder::Int64(Int64Builder::new()),
+            PayloadSchemaType::Float => FieldBuilder::Float64(Float64Builder::new()),
+            PayloadSchemaType::Geo => FieldBuilder::Json(StringBuilder::new()),
+        }
+    }
+
+    fn append(&mut self, value: Option<&Value>) {
+        match self {
+            FieldBuilder::Utf8(builder) => {
+                builder.append_option(value.and_then(Value::as_str));
+            }
+            FieldBuilder::Int64(builder) => builder.append_option(value.and_then(Value::as_i64)),
+            FieldBuilder::Float64(builder) => builder.append_option(value.and_then(Value::as_f64)),
+            FieldBuilder::Json(builder) => builder.append_option(
+                value
+                    .filter(|value| value.is_object())
+                    .map(Value::to_string),
+            ),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            FieldBuilder::Utf8(builder) | FieldBuilder::Json(builder) => Arc::new(builder.finish()),
+            FieldBuilder::Int64(builder) => Arc::new(builder.finish()),
+            FieldBuilder::Float64(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Collects the points into the record batch of the [`ExportTable`]
+pub struct BatchBuilder {
+    table: ExportTable,
+    ids: StringBuilder,
+    vectors: Vec<FixedSizeListBuilder<Float32Builder>>,
+    payload_fields: Vec<FieldBuilder>,
+    payload: StringBuilder,
+    len: usize,
+}
+
+impl BatchBuilder {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn append(
+        &mut self,
+        point_id: PointIdType,
+        vectors: &NamedVectors,
+        payload: &Payload,
+    ) -> OperationResult<()> {
+        self.ids.append_value(point_id.to_string());
+        for ((vector_name, _), builder) in self.table.vectors.iter().zip(&mut self.vectors) {
+            let (_, vector) = vectors
+                .iter()
+                .find(|(name, _)| *name == vector_name.as_str())
+                .ok_or_else(|| OperationError::MissedVectorName {
+                    received_name: vector_name.clone(),
+                })?;
+            builder.values().append_slice(vector);
+            builder.append(true);
+        }
+        for ((key, _), builder) in self
+            .table
+            .payload_fields
+            .iter()
+            .zip(&mut self.payload_fields)
+        {
+            match &payload.get_value(key).values()[..] {
+                [value] => builder.append(Some(*value)),
+                _ => builder.append(None),
+            }
+        }
+        self.payload.append_value(serde_json::to_string(payload)?);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Record batch of the appended points, the builder is left empty
+    pub fn finish(&mut self) -> OperationResult<RecordBatch> {
+        let mut columns: Vec<(String, ArrayRef, bool)> =
+            vec![(ID_COLUMN.to_owned(), Arc::new(self.ids.finish()), false)];
+        for ((vector_name, _), builder) in self.table.vectors.iter().zip(&mut self.vectors) {
+            columns.push((
+                vector_column(vector_name),
+                Arc::new(builder.finish()),
+                false,
+            ));
+        }
+        for ((key, _), builder) in self
+            .table
+            .payload_fields
+            .iter()
+            .zip(&mut self.payload_fields)
+        {
+            columns.push((payload_column(key), builder.finish(), true));
+        }
+        columns.push((
+            PAYLOAD_COLUMN.to_owned(),
+            Arc::new(self.payload.finish()),
+            false,
+        ));
+        self.len = 0;
+        RecordBatch::try_from_iter_with_nullable(columns).map_err(arrow_error)
+    }
+}
+
+pub fn arrow_error(err: impl std::fmt::Display) -> OperationError {
+    OperationError::service_error(format!("Arrow export failed: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use arrow::array::{
+        Array, FixedSizeListArray, Float32Array, Float64Array, Int64Array, StringArray,
+    };
+    use arrow::ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment::Segment;
+    use crate::segment_constructor::build_segment;
+    use crate::types::{
+        Condition, Distance, FieldCondition, Filter, Indexes, Range, StorageType, VectorDataConfig,
+    };
+
+    const NUM_POINTS: u64 = 2500;
+
+    fn segment(path: &std::path::Path) -> Segment {
+        let vector_config = VectorDataConfig {
+            size: 2,
+            distance: Distance::Dot,
+            hnsw_config: None,
+            quantization_config: None,
+        };
+        let config = SegmentConfig {
+            vector_data: HashMap::from([
+                ("text".to_owned(), vector_config.clone()),
+                ("image".to_owned(), vector_config),
+            ]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(path, &config).unwrap();
+        for point_id in 0..NUM_POINTS {
+            let vectors = NamedVectors::from([
+                ("image".to_owned(), vec![point_id as f32, 1.0]),
+                ("text".to_owned(), vec![1.0, point_id as f32]),
+            ]);
+            segment
+                .upsert_vector(point_id, point_id.into(), &vectors)
+                .unwrap();
+            // Price is missing for the odd points and the city has several values for every tenth one
+            let mut payload = json!({
+                "num": point_id,
+                "city": if point_id % 10 == 0 { json!(["Berlin", "Moscow"]) } else { json!("Berlin") },
+                "location": { "lon": 13.4, "lat": 52.5 },
+                "nested": { "a": [1, 2] },
+            });
+            if point_id % 2 == 0 {
+                payload["price"] = json!(point_id as f64 / 2.0);
+            }
+            segment
+                .set_full_payload(NUM_POINTS + point_id, point_id.into(), &payload.into())
+                .unwrap();
+        }
+        for (key, schema_type) in [
+            ("num", PayloadSchemaType::Integer),
+            ("price", PayloadSchemaType::Float),
+            ("city", PayloadSchemaType::Keyword),
+            ("location", PayloadSchemaType::Geo),
+        ] {
+            segment
+                .create_field_index(2 * NUM_POINTS, key, Some(&schema_type.into()))
+                .unwrap();
+        }
+        segment
+    }
+
+    fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+        batch
+            .column_by_name(name)
+            .unwrap_or_else(|| panic!("no column {name}"))
+            .as_any()
+            .downcast_ref::<T>()
+            .unwrap()
+    }
+
+    fn vector(batch: &RecordBatch, name: &str, row: usize) -> Vec<f32> {
+        let list = column::<FixedSizeListArray>(batch, name).value(row);
+        let values = list.as_any().downcast_ref::<Float32Array>().unwrap();
+        values.iter().map(Option::unwrap).collect()
+    }
+
+    /// Compare a few rows of the batches with the points they are exported from
+    fn check_rows(batches: &[RecordBatch], from_id: u64) {
+        let batch = &batches[0];
+        let column_names: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect();
+        assert_eq!(
+            column_names,
+            [
+                "id",
+                "vector.image",
+                "vector.text",
+                "payload.city",
+                "payload.location",
+                "payload.num",
+                "payload.price",
+                "payload",
+            ]
+        );
+
+        for row in [0, 1, 5] {
+            let point_id = from_id + row as u64;
+            assert_eq!(
+                column::<StringArray>(batch, "id").value(row),
+                point_id.to_string()
+            );
+            assert_eq!(vector(batch, "vector.image", row), [point_id as f32, 1.0]);
+            assert_eq!(vector(batch, "vector.text", row), [1.0, point_id as f32]);
+            assert_eq!(
+                column::<Int64Array>(batch, "payload.num").value(row),
+                point_id as i64
+            );
+            let price = column::<Float64Array>(batch, "payload.price");
+            assert_eq!(price.is_null(row), point_id % 2 == 1);
+            if point_id % 2 == 0 {
+                assert_eq!(price.value(row), point_id as f64 / 2.0);
+            }
+            let city = column::<StringArray>(batch, "payload.city");
+            assert_eq!(city.is_null(row), point_id % 10 == 0);
+            let location: Value =
+                serde_json::from_str(column::<StringArray>(batch, "payload.location").value(row))
+                    .unwrap();
+            assert_eq!(location, json!({ "lon": 13.4, "lat": 52.5 }));
+            let payload: Value =
+                serde_json::from_str(column::<StringArray>(batch, "payload").value(row)).unwrap();
+            assert_eq!(payload["nested"], json!({ "a": [1, 2] }));
+        }
+    }
+
+    #[test]
+    fn test_export_arrow() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let export_dir = Builder::new().prefix("export_dir").tempdir().unwrap();
+        let segment = segment(dir.path());
+
+        let path = export_dir.path().join("points.arrow");
+        assert_eq!(
+            segment.export_arrow(&path, true, None).unwrap(),
+            NUM_POINTS as usize
+        );
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        // Points are streamed in the bounded batches
+        assert_eq!(
+            reader.num_batches(),
+            (NUM_POINTS as usize + ARROW_BATCH_SIZE - 1) / ARROW_BATCH_SIZE
+        );
+        let batches: Vec<_> = reader.map(Result::unwrap).collect();
+        assert!(batches
+            .iter()
+            .all(|batch| batch.num_rows() <= ARROW_BATCH_SIZE));
+        check_rows(&batches, 0);
+
+        // Schema is derived deterministically
+        assert_eq!(
+            segment.export_arrow(&path, true, None).unwrap(),
+            NUM_POINTS as usize
+        );
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        assert_eq!(reader.schema(), batches[0].schema());
+
+        // Without vectors
+        segment.export_arrow(&path, false, None).unwrap();
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        assert!(reader.schema().column_with_name("vector.image").is_none());
+    }
+
+    #[test]
+    fn test_export_parquet() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let export_dir = Builder::new().prefix("export_dir").tempdir().unwrap();
+        let segment = segment(dir.path());
+
+        let filter = Filter::new_must(Condition::Field(FieldCondition::new_range(
+            "num".to_owned(),
+            Range {
+                lt: None,
+                gt: None,
+                gte: Some(100.0),
+                lte: None,
+            },
+        )));
+        let path = export_dir.path().join("points.parquet");
+        assert_eq!(
+            segment.export_parquet(&path, true, Some(&filter)).unwrap(),
+            NUM_POINTS as usize - 100
+        );
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.map(Result::unwrap).collect();
+        let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+        assert_eq!(rows, NUM_POINTS as usize - 100);
+        check_rows(&batches, 100);
+    }
+}
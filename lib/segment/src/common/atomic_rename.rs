@@ -0,0 +1,173 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+
+/// Extension of the sibling, which is written before it is renamed into place
+pub const TEMP_EXTENSION: &str = "tmp";
+
+/// File, written into the directory once all its files are synced, just before the rename
+pub const COMPLETION_MARKER_FILE: &str = ".complete";
+
+/// Temporary sibling of `target`: `{target}.tmp`
+pub fn temp_path(target: &Path) -> PathBuf {
+    let mut file_name = target.file_name().unwrap_or_default().to_owned();
+    file_name.push(format!(".{TEMP_EXTENSION}"));
+    target.with_file_name(file_name)
+}
+
+/// Whether the path is a temporary sibling, left by an interrupted write
+pub fn is_temp_path(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == TEMP_EXTENSION)
+}
+
+/// Write the directory into the temporary sibling of `target`, and rename it into place once complete
+///
+/// `write` must not keep any file of the directory open, as the directory is moved afterwards.
+/// If the process dies midway, `target` is either absent or complete, and the temporary sibling is left,
+/// see [`is_temp_path`].
+pub fn write_dir_atomically<T>(
+    target: &Path,
+    write: impl FnOnce(&Path) -> OperationResult<T>,
+) -> OperationResult<T> {
+    let temp = temp_path(target);
+    if temp.exists() {
+        fs::remove_dir_all(&temp)?;
+    }
+    fs::create_dir_all(&temp)?;
+    let result = write(&temp).and_then(|result| {
+        commit_dir(&temp, target)?;
+        Ok(result)
+    });
+    if result.is_err() && temp.exists() {
+        if let Err(err) = fs::remove_dir_all(&temp) {
+            log::warn!("Failed to remove incomplete directory {temp:?}: {err}");
+        }
+    }
+    result
+}
+
+/// Sync all files of the fully written `temp` directory, mark it complete and rename it into `target`
+pub fn commit_dir(temp: &Path, target: &Path) -> OperationResult<()> {
+    if target.exists() {
+        return Err(OperationError::service_error(format!(
+            "Can't move {temp:?} into place, {target:?} already exists"
+        )));
+    }
+    sync_dir_all(temp)?;
+    File::create(temp.join(COMPLETION_MARKER_FILE))?.sync_all()?;
+    sync_dir(temp)?;
+    rename_synced(temp, target)
+}
+
+/// Sync the fully written `temp` file and rename it into `target`, replacing the existing one
+pub fn commit_file(temp: &Path, target: &Path) -> OperationResult<()> {
+    File::open(temp)?.sync_all()?;
+    rename_synced(temp, target)
+}
+
+fn rename_synced(temp: &Path, target: &Path) -> OperationResult<()> {
+    fs::rename(temp, target)?;
+    // Rename is only durable once the parent directories are synced
+    for parent in [target.parent(), temp.parent()].into_iter().flatten() {
+        sync_dir(parent)?;
+    }
+    Ok(())
+}
+
+fn sync_dir_all(dir: &Path) -> OperationResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            sync_dir_all(&entry.path())?;
+        } else {
+            File::open(entry.path())?.sync_all()?;
+        }
+    }
+    sync_dir(dir)
+}
+
+fn sync_dir(dir: &Path) -> OperationResult<()> {
+    // Parent of a relative path is empty
+    if dir.as_os_str().is_empty() {
+        return Ok(());
+    }
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::panic;
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment::SEGMENT_STATE_FILE;
+    use crate::segment_constructor::{build_segment, load_segment};
+    use crate::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+
+    #[test]
+    fn test_interrupted_write_keeps_old_directory() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        assert!(segment.current_path.join(COMPLETION_MARKER_FILE).exists());
+        segment
+            .upsert_vector(1, 1.into(), &only_default_vector(&[1.0, 0.0]))
+            .unwrap();
+        segment.flush(true).unwrap();
+        let segment_path = segment.current_path.clone();
+        drop(segment);
+
+        // Process dies between the writes of the new version of the segment
+        let new_path = segment_path.with_file_name("new_segment");
+        let crash: fn() -> OperationResult<()> =
+            || panic!("crash before the rest of the files are written");
+        let crashed = panic::catch_unwind(|| {
+            write_dir_atomically(&new_path, |temp| {
+                fs::copy(
+                    segment_path.join(SEGMENT_STATE_FILE),
+                    temp.join(SEGMENT_STATE_FILE),
+                )?;
+                crash()
+            })
+        });
+        assert!(crashed.is_err());
+        let leftover = temp_path(&new_path);
+        assert!(leftover.exists());
+        assert!(!new_path.exists());
+
+        // Old directory is loaded, leftover is removed
+        let segment = load_segment(&segment_path).unwrap().unwrap();
+        assert_eq!(segment.points_count(), 1);
+        assert!(load_segment(&leftover).unwrap().is_none());
+        assert!(!leftover.exists());
+
+        // Failed write doesn't leave the temporary directory
+        let result: OperationResult<()> = write_dir_atomically(&new_path, |_| {
+            Err(OperationError::service_error("write failed"))
+        });
+        assert!(result.is_err());
+        assert!(!leftover.exists());
+        assert!(!new_path.exists());
+    }
+}
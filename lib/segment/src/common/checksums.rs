@@ -0,0 +1,351 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::file_operations::{atomic_save_json, read_json};
+use crate::entry::entry_point::{OperationError, OperationResult};
+
+pub const CHECKSUMS_FILE: &str = "checksums.json";
+
+const CHECKSUM_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Checksums of the segment files, written at flush
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct ChecksumManifest {
+    /// Checksums by the path, relative to the segment directory
+    files: BTreeMap<String, FileChecksum>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+struct FileChecksum {
+    size: u64,
+    /// Modification time of the file, in nanoseconds since the epoch
+    modified: u64,
+    crc32: u32,
+}
+
+impl FileChecksum {
+    /// Checksum of the file, reusing the previous one, if the file was not modified since
+    fn compute(path: &Path, previous: Option<&FileChecksum>) -> OperationResult<Self> {
+        let (size, modified) = file_stamp(path)?;
+        if let Some(previous) = previous {
+            if previous.size == size && previous.modified == modified {
+                return Ok(*previous);
+            }
+        }
+        Ok(FileChecksum {
+            size,
+            modified,
+            crc32: crc32(path)?,
+        })
+    }
+}
+
+fn file_stamp(path: &Path) -> OperationResult<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |modified| modified.as_nanos() as u64);
+    Ok((metadata.len(), modified))
+}
+
+fn crc32(path: &Path) -> OperationResult<u32> {
+    let mut reader = BufReader::with_capacity(CHECKSUM_BUFFER_SIZE, File::open(path)?);
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; CHECKSUM_BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
+
+fn load_manifest(segment_path: &Path) -> OperationResult<Option<ChecksumManifest>> {
+    let manifest_path = segment_path.join(CHECKSUMS_FILE);
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(read_json(&manifest_path)?))
+}
+
+/// Save checksums of the files of the segment
+///
+/// Checksums of the files, not modified since the previous save, are not computed again.
+pub fn save_checksums(segment_path: &Path, files: &[PathBuf]) -> OperationResult<()> {
+    let previous = load_manifest(segment_path).unwrap_or_else(|err| {
+        log::warn!("Checksums of {segment_path:?} are computed again: {err}");
+        None
+    });
+
+    let mut manifest = ChecksumManifest::default();
+    for file in files {
+        let relative_path = match file.strip_prefix(segment_path) {
+            Ok(relative_path) => relative_path.to_string_lossy().into_owned(),
+            Err(_) => continue,
+        };
+        let previous_checksum = previous
+            .as_ref()
+            .and_then(|previous| previous.files.get(&relative_path));
+        let checksum = FileChecksum::compute(file, previous_checksum)?;
+        manifest.files.insert(relative_path, checksum);
+    }
+    Ok(atomic_save_json(
+        &segment_path.join(CHECKSUMS_FILE),
+        &manifest,
+    )?)
+}
+
+/// Verify checksums of the files of the segment, the error names the corrupted file
+///
+/// Files, rewritten after the checksums were saved, e.g. by a rebuild of the index, are not verified.
+/// Segments without checksums are considered valid.
+pub fn verify_checksums(segment_path: &Path) -> OperationResult<()> {
+    match load_manifest(segment_path)? {
+        Some(manifest) => verify_manifest(segment_path, &manifest, true),
+        None => Ok(()),
+    }
+}
+
+/// Verify checksums of all files of the segment, unpacked from an archive
+///
+/// Archive doesn't keep the precise modification time, so the files are verified regardless of it.
+/// Files, excluded from the `partial` snapshot, are not verified.
+/// Verified checksums are saved with the modification times of the unpacked files.
+pub fn verify_unpacked_checksums(segment_path: &Path, partial: bool) -> OperationResult<()> {
+    let mut manifest = match load_manifest(segment_path)? {
+        Some(manifest) => manifest,
+        None => return Ok(()),
+    };
+    if partial {
+        manifest
+            .files
+            .retain(|relative_path, _| segment_path.join(relative_path).exists());
+    }
+    verify_manifest(segment_path, &manifest, false)?;
+
+    let mut unpacked_manifest = ChecksumManifest::default();
+    for (relative_path, checksum) in manifest.files {
+        let (size, modified) = file_stamp(&segment_path.join(&relative_path))?;
+        let unpacked_checksum = FileChecksum {
+            size,
+            modified,
+            crc32: checksum.crc32,
+        };
+        unpacked_manifest
+            .files
+            .insert(relative_path, unpacked_checksum);
+    }
+    Ok(atomic_save_json(
+        &segment_path.join(CHECKSUMS_FILE),
+        &unpacked_manifest,
+    )?)
+}
+
+/// Files of the segment, which fail the verification, relative to the segment directory
+///
+/// Unlike [`verify_checksums`], all files are verified, regardless of the modification time.
+/// Unreadable checksums are reported as the damaged [`CHECKSUMS_FILE`].
+pub fn damaged_files(segment_path: &Path) -> Vec<String> {
+    let manifest = match load_manifest(segment_path) {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => return vec![],
+        Err(err) => {
+            log::warn!("Can't read checksums of {segment_path:?}: {err}");
+            return vec![CHECKSUMS_FILE.to_string()];
+        }
+    };
+    manifest
+        .files
+        .iter()
+        .filter_map(|(relative_path, checksum)| {
+            let err = verify_file(segment_path, relative_path, checksum, false).err()?;
+            log::warn!("{err}");
+            Some(relative_path.clone())
+        })
+        .collect()
+}
+
+fn verify_manifest(
+    segment_path: &Path,
+    manifest: &ChecksumManifest,
+    skip_modified: bool,
+) -> OperationResult<()> {
+    for (relative_path, checksum) in &manifest.files {
+        verify_file(segment_path, relative_path, checksum, skip_modified)?;
+    }
+    Ok(())
+}
+
+fn verify_file(
+    segment_path: &Path,
+    relative_path: &str,
+    checksum: &FileChecksum,
+    skip_modified: bool,
+) -> OperationResult<()> {
+    let path = segment_path.join(relative_path);
+    if !path.exists() {
+        return Err(OperationError::service_error(format!(
+            "File {relative_path} of segment {} is missing",
+            segment_path.display()
+        )));
+    }
+    let (size, modified) = file_stamp(&path)?;
+    if skip_modified && modified != checksum.modified {
+        log::debug!("Skipping verification of {path:?}, it is modified after the checksum");
+        return Ok(());
+    }
+    let crc32 = if size == checksum.size {
+        crc32(&path)?
+    } else {
+        0
+    };
+    if size != checksum.size || crc32 != checksum.crc32 {
+        return Err(OperationError::service_error(format!(
+            "Checksum mismatch of file {relative_path} of segment {}: \
+            expected size {} and crc32 {:08x}, found size {size} and crc32 {crc32:08x}",
+            segment_path.display(),
+            checksum.size,
+            checksum.crc32,
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use filetime::{set_file_mtime, FileTime};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::only_default_vector;
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::fixtures::payload_fixtures::random_vector;
+    use crate::segment::Segment;
+    use crate::segment_constructor::segment_builder::SegmentBuilder;
+    use crate::segment_constructor::simple_segment_constructor::build_simple_segment;
+    use crate::segment_constructor::{
+        load_segment, PAYLOAD_INDEX_PATH, QUARANTINE_EXTENSION, VECTOR_INDEX_PATH,
+        VECTOR_STORAGE_PATH,
+    };
+    use crate::types::{
+        ChecksumVerification, Distance, HnswConfig, Indexes, SegmentConfig, StorageType,
+    };
+
+    fn indexed_segment(dir: &Path, temp_dir: &Path) -> PathBuf {
+        let stopped = AtomicBool::new(false);
+        let dim = 4;
+        let mut rnd = StdRng::seed_from_u64(42);
+        let mut plain_segment = build_simple_segment(dir, dim, Distance::Dot).unwrap();
+        for point_id in 0..200u64 {
+            let vector = random_vector(&mut rnd, dim);
+            plain_segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+
+        let config = SegmentConfig {
+            vector_data: plain_segment.segment_config.vector_data.clone(),
+            index: Indexes::Hnsw(HnswConfig {
+                full_scan_threshold: 1,
+                ..Default::default()
+            }),
+            storage_type: StorageType::Mmap,
+            ..Default::default()
+        };
+        let mut builder = SegmentBuilder::new(dir, temp_dir, &config).unwrap();
+        builder.update_from(&plain_segment, &stopped).unwrap();
+        let segment = builder.build(&stopped).unwrap();
+        segment.current_path.clone()
+    }
+
+    /// Flip a byte of the file, keeping its modification time
+    fn corrupt(path: &Path) -> Vec<u8> {
+        let modified = FileTime::from_last_modification_time(&fs::metadata(path).unwrap());
+        let original = fs::read(path).unwrap();
+        let mut corrupted = original.clone();
+        let position = corrupted.len() / 2;
+        corrupted[position] ^= 0xff;
+        fs::write(path, corrupted).unwrap();
+        set_file_mtime(path, modified).unwrap();
+        original
+    }
+
+    fn restore(path: &Path, original: Vec<u8>) {
+        let modified = FileTime::from_last_modification_time(&fs::metadata(path).unwrap());
+        fs::write(path, original).unwrap();
+        set_file_mtime(path, modified).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_files_are_detected() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let path = indexed_segment(dir.path(), temp_dir.path());
+
+        let manifest = load_manifest(&path).unwrap().unwrap();
+        for prefix in [VECTOR_STORAGE_PATH, VECTOR_INDEX_PATH, PAYLOAD_INDEX_PATH] {
+            assert!(
+                manifest.files.keys().any(|file| file.starts_with(prefix)),
+                "no checksums of {prefix} files in {:?}",
+                manifest.files.keys(),
+            );
+        }
+
+        for relative_path in manifest.files.keys() {
+            let file_path = path.join(relative_path);
+            let original = corrupt(&file_path);
+            let err = load_segment(&path).err().unwrap();
+            assert!(
+                err.to_string().contains(relative_path.as_str()),
+                "{relative_path} is not named in: {err}",
+            );
+            restore(&file_path, original);
+        }
+        assert!(load_segment(&path).unwrap().is_some());
+
+        // File, rewritten after the checksums were saved, is not verified
+        let (relative_path, _) = manifest.files.iter().next().unwrap();
+        let file_path = path.join(relative_path);
+        corrupt(&file_path);
+        set_file_mtime(&file_path, FileTime::from_unix_time(1, 0)).unwrap();
+        verify_checksums(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_segment_is_quarantined() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let path = indexed_segment(dir.path(), temp_dir.path());
+
+        let mut state = Segment::load_state(&path).unwrap();
+        state.config.checksum_verification = ChecksumVerification::Quarantine;
+        Segment::save_state(&state, &path).unwrap();
+
+        let manifest = load_manifest(&path).unwrap().unwrap();
+        let (relative_path, _) = manifest.files.iter().next().unwrap();
+        corrupt(&path.join(relative_path));
+
+        assert!(load_segment(&path).unwrap().is_none());
+        assert!(!path.exists());
+        let mut quarantine_path = path.into_os_string();
+        quarantine_path.push(format!(".{QUARANTINE_EXTENSION}"));
+        let quarantine_path = PathBuf::from(quarantine_path);
+        assert!(quarantine_path.exists());
+        assert!(load_segment(&quarantine_path).unwrap().is_none());
+        assert!(quarantine_path.exists());
+    }
+}
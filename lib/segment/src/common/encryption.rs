@@ -0,0 +1,301 @@
+use std::path::Path;
+
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload as AeadPayload};
+#[cfg(feature = "encryption")]
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "encryption")]
+use crate::common::file_operations::atomic_save_json;
+use crate::common::file_operations::read_json;
+use crate::entry::entry_point::{OperationError, OperationResult};
+
+/// Header of the encrypted segment, its presence marks the segment as encrypted
+pub const ENCRYPTION_FILE: &str = "encryption.json";
+
+/// Size of the nonce and the authentication tag, added to each encrypted value and block
+pub const ENCRYPTION_OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+const ENCRYPTION_ALGORITHM: &str = "aes-256-gcm";
+const KEY_CHECK_AAD: &[u8] = b"key_check";
+#[cfg(feature = "encryption")]
+const KEY_CHECK_PLAINTEXT: &[u8] = b"segment key check";
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct EncryptionHeader {
+    algorithm: String,
+    /// Known plaintext, encrypted with the key, so the wrong key is detected on open
+    key_check: Vec<u8>,
+}
+
+/// 256-bit key of the segment encryption, provided when the segment is opened
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+#[cfg(feature = "encryption")]
+impl EncryptionKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        EncryptionKey(bytes)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// Cipher of the vectors and payloads of the encrypted segment
+///
+/// Values are encrypted with AES-256-GCM and a random nonce, which is stored in front of the ciphertext.
+/// Additional data binds the ciphertext to its place, e.g. the key of the value or the number of the block,
+/// so ciphertexts can't be swapped around. Field indexes and the id mapping are not encrypted.
+#[cfg(feature = "encryption")]
+pub struct SegmentCipher {
+    cipher: Aes256Gcm,
+}
+
+/// Without the `encryption` feature the cipher can't be constructed, so the segments are never encrypted
+#[cfg(not(feature = "encryption"))]
+pub enum SegmentCipher {}
+
+#[cfg(feature = "encryption")]
+impl SegmentCipher {
+    pub fn new(key: &EncryptionKey) -> Self {
+        SegmentCipher {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.0)),
+        }
+    }
+
+    /// Nonce followed by the ciphertext and the authentication tag
+    pub fn encrypt(&self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                AeadPayload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("AES-GCM encryption of the bounded value can't fail");
+        let mut encrypted = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&ciphertext);
+        encrypted
+    }
+
+    pub fn decrypt(&self, aad: &[u8], encrypted: &[u8]) -> OperationResult<Vec<u8>> {
+        if encrypted.len() < ENCRYPTION_OVERHEAD {
+            return Err(OperationError::service_error(
+                "Encrypted value is truncated",
+            ));
+        }
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_SIZE);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                AeadPayload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| OperationError::service_error("Encrypted value failed the authentication"))
+    }
+
+    /// Mark the new segment as encrypted with this cipher
+    pub(crate) fn save_header(&self, segment_path: &Path) -> OperationResult<()> {
+        let header = EncryptionHeader {
+            algorithm: ENCRYPTION_ALGORITHM.to_owned(),
+            key_check: self.encrypt(KEY_CHECK_AAD, KEY_CHECK_PLAINTEXT),
+        };
+        Ok(atomic_save_json(
+            &segment_path.join(ENCRYPTION_FILE),
+            &header,
+        )?)
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+impl SegmentCipher {
+    pub fn encrypt(&self, _aad: &[u8], _plaintext: &[u8]) -> Vec<u8> {
+        match *self {}
+    }
+
+    pub fn decrypt(&self, _aad: &[u8], _encrypted: &[u8]) -> OperationResult<Vec<u8>> {
+        match *self {}
+    }
+
+    pub(crate) fn save_header(&self, _segment_path: &Path) -> OperationResult<()> {
+        match *self {}
+    }
+}
+
+pub fn is_encrypted(segment_path: &Path) -> bool {
+    segment_path.join(ENCRYPTION_FILE).exists()
+}
+
+/// Check the cipher is the one the segment is encrypted with, or none if the segment is not encrypted
+pub fn check_segment_cipher(
+    segment_path: &Path,
+    cipher: Option<&SegmentCipher>,
+) -> OperationResult<()> {
+    match (is_encrypted(segment_path), cipher) {
+        (false, None) => Ok(()),
+        (false, Some(_)) => Err(OperationError::service_error(format!(
+            "Segment {} is not encrypted, it can't be opened with the encryption key",
+            segment_path.display()
+        ))),
+        (true, None) => Err(OperationError::EncryptionKeyRequired {
+            path: segment_path.to_owned(),
+        }),
+        (true, Some(cipher)) => {
+            let header: EncryptionHeader = read_json(&segment_path.join(ENCRYPTION_FILE))?;
+            if header.algorithm != ENCRYPTION_ALGORITHM {
+                return Err(OperationError::service_error(format!(
+                    "Segment {} is encrypted with unsupported algorithm {}",
+                    segment_path.display(),
+                    header.algorithm
+                )));
+            }
+            cipher
+                .decrypt(KEY_CHECK_AAD, &header.key_check)
+                .map_err(|_| OperationError::WrongEncryptionKey {
+                    path: segment_path.to_owned(),
+                })?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+    use tempfile::Builder;
+    use walkdir::WalkDir;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment_constructor::{
+        build_encrypted_segment, load_encrypted_segment, load_segment,
+    };
+    use crate::types::{
+        Distance, Indexes, Payload, PayloadSchemaType, PayloadStorageType, SegmentConfig,
+        StorageType, VectorDataConfig,
+    };
+
+    const SECRET: &str = "top secret payload";
+
+    fn vector(id: u64, dim: usize) -> Vec<f32> {
+        (0..dim)
+            .map(|i| 0.123 + id as f32 + i as f32 / 7.0)
+            .collect()
+    }
+
+    fn vector_bytes(vector: &[f32]) -> Vec<u8> {
+        vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    /// Check none of the files in the directory contains the plaintext
+    fn assert_no_plaintext(dir: &Path, plaintexts: &[Vec<u8>]) {
+        for entry in WalkDir::new(dir) {
+            let entry = entry.unwrap();
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let data = std::fs::read(entry.path()).unwrap();
+            for plaintext in plaintexts {
+                assert!(
+                    !data
+                        .windows(plaintext.len())
+                        .any(|window| window == plaintext),
+                    "plaintext is found in {}",
+                    entry.path().display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypted_segment_round_trip() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let key = EncryptionKey::new([7; 32]);
+        let dim = 4;
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            payload_storage_type: PayloadStorageType::OnDisk,
+            ..Default::default()
+        };
+
+        let mut segment = build_encrypted_segment(dir.path(), &config, &key).unwrap();
+        let path = segment.current_path.clone();
+        let payload: Payload = json!({ "secret": SECRET }).into();
+        for point_id in 0..10 {
+            segment
+                .upsert_vector(
+                    point_id,
+                    point_id.into(),
+                    &only_default_vector(&vector(point_id, dim)),
+                )
+                .unwrap();
+            segment
+                .set_full_payload(10 + point_id, point_id.into(), &payload)
+                .unwrap();
+        }
+        // Keyword index would keep the secret in plaintext
+        assert!(matches!(
+            segment
+                .create_field_index(20, "secret", Some(&PayloadSchemaType::Keyword.into()))
+                .err()
+                .unwrap(),
+            OperationError::EncryptedFieldIndex { .. }
+        ));
+        assert!(segment.get_indexed_fields().is_empty());
+        segment.close().unwrap();
+
+        let segment = load_encrypted_segment(&path, &key).unwrap().unwrap();
+        assert_eq!(segment.points_count(), 10);
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 3.into()).unwrap(),
+            vector(3, dim)
+        );
+        assert_eq!(*segment.payload(3.into()).unwrap(), payload);
+        segment.close().unwrap();
+
+        let mut plaintexts: Vec<_> = (0..10).map(|id| vector_bytes(&vector(id, dim))).collect();
+        plaintexts.push(SECRET.as_bytes().to_vec());
+        assert_no_plaintext(&path, &plaintexts);
+
+        assert!(matches!(
+            load_segment(&path).err().unwrap(),
+            OperationError::EncryptionKeyRequired { .. }
+        ));
+        assert!(matches!(
+            load_encrypted_segment(&path, &EncryptionKey::new([8; 32]))
+                .err()
+                .unwrap(),
+            OperationError::WrongEncryptionKey { .. }
+        ));
+    }
+}
@@ -0,0 +1,581 @@
+//! Strict parsing of filters from user provided JSON
+//!
+//! Unlike plain deserialization, unknown fields are rejected and every error
+//! points at the JSON path of the offending element, e.g. `must[2].range`.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::types::{
+    AnyVariants, Condition, FieldCondition, Filter, HasIdCondition, HasVectorCondition,
+    IsEmptyCondition, IsNullCondition, Match, MatchAny, MatchExcept, MatchInsensitive, MatchText,
+    MatchValue, Nested, NestedCondition, PayloadField, PayloadFieldSchema, PayloadKeyType,
+    PayloadSchemaParams, PayloadSchemaType, PointIdType, ValueVariants,
+};
+
+const FILTER_FIELDS: [&str; 3] = ["should", "must", "must_not"];
+const FIELD_CONDITION_FIELDS: [&str; 7] = [
+    "key",
+    "match",
+    "range",
+    "geo_bounding_box",
+    "geo_radius",
+    "geo_polygon",
+    "values_count",
+];
+const MATCH_FIELDS: [&str; 5] = ["value", "text", "any", "except", "insensitive"];
+const RANGE_FIELDS: [&str; 4] = ["lt", "gt", "gte", "lte"];
+
+/// Error of the filter parsing or schema validation
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at {path}")]
+pub struct FilterParseError {
+    /// JSON path of the offending element, `root` for the filter itself
+    pub path: String,
+    pub message: String,
+}
+
+impl FilterParseError {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: if path.is_empty() {
+                "root".to_string()
+            } else {
+                path.to_string()
+            },
+            message: message.into(),
+        }
+    }
+}
+
+fn field_path(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{path}.{field}")
+    }
+}
+
+fn as_object<'a>(
+    value: &'a Value,
+    path: &str,
+    expected: &str,
+) -> Result<&'a Map<String, Value>, FilterParseError> {
+    value
+        .as_object()
+        .ok_or_else(|| FilterParseError::new(path, format!("expected {expected}")))
+}
+
+fn check_fields(
+    object: &Map<String, Value>,
+    allowed: &[&str],
+    path: &str,
+) -> Result<(), FilterParseError> {
+    match object.keys().find(|key| !allowed.contains(&key.as_str())) {
+        Some(key) => Err(FilterParseError::new(
+            path,
+            format!("unknown field `{key}`, expected one of {allowed:?}"),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn parse_value<T: DeserializeOwned>(
+    value: &Value,
+    path: &str,
+    field: &str,
+) -> Result<T, FilterParseError> {
+    serde_json::from_value(value.clone()).map_err(|err| {
+        FilterParseError::new(
+            &field_path(path, field),
+            format!("invalid `{field}`: {err}"),
+        )
+    })
+}
+
+fn parse_filter(value: &Value, path: &str) -> Result<Filter, FilterParseError> {
+    let object = as_object(value, path, "filter object")?;
+    check_fields(object, &FILTER_FIELDS, path)?;
+
+    let parse_clause = |name: &str| -> Result<Option<Vec<Condition>>, FilterParseError> {
+        match object.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::Array(conditions)) => conditions
+                .iter()
+                .enumerate()
+                .map(|(idx, condition)| {
+                    parse_condition(condition, &format!("{}[{idx}]", field_path(path, name)))
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            Some(_) => Err(FilterParseError::new(
+                &field_path(path, name),
+                "expected array of conditions",
+            )),
+        }
+    };
+
+    Ok(Filter {
+        should: parse_clause("should")?,
+        must: parse_clause("must")?,
+        must_not: parse_clause("must_not")?,
+    })
+}
+
+fn parse_condition(value: &Value, path: &str) -> Result<Condition, FilterParseError> {
+    let object = as_object(value, path, "condition object")?;
+
+    if object.contains_key("key") {
+        return parse_field_condition(object, path).map(Condition::Field);
+    }
+    if let Some(field) = object.get("is_empty") {
+        check_fields(object, &["is_empty"], path)?;
+        let is_empty = parse_payload_field(field, &field_path(path, "is_empty"))?;
+        return Ok(Condition::IsEmpty(IsEmptyCondition { is_empty }));
+    }
+    if let Some(field) = object.get("is_null") {
+        check_fields(object, &["is_null"], path)?;
+        let is_null = parse_payload_field(field, &field_path(path, "is_null"))?;
+        return Ok(Condition::IsNull(IsNullCondition { is_null }));
+    }
+    if let Some(ids) = object.get("has_id") {
+        check_fields(object, &["has_id"], path)?;
+        let has_id: HashSet<PointIdType> = parse_value(ids, path, "has_id")?;
+        return Ok(Condition::HasId(HasIdCondition { has_id }));
+    }
+    if let Some(vector_name) = object.get("has_vector") {
+        check_fields(object, &["has_vector"], path)?;
+        let has_vector: String = parse_value(vector_name, path, "has_vector")?;
+        return Ok(Condition::HasVector(HasVectorCondition { has_vector }));
+    }
+    if let Some(nested) = object.get("nested") {
+        check_fields(object, &["nested"], path)?;
+        let nested = parse_nested(nested, &field_path(path, "nested"))?;
+        return Ok(Condition::Nested(NestedCondition { nested }));
+    }
+    if FILTER_FIELDS
+        .iter()
+        .any(|field| object.contains_key(*field))
+    {
+        return parse_filter(value, path).map(Condition::Filter);
+    }
+
+    Err(FilterParseError::new(path, "unknown condition type"))
+}
+
+fn parse_nested(value: &Value, path: &str) -> Result<Nested, FilterParseError> {
+    let object = as_object(value, path, "object with payload `key` and `filter`")?;
+    check_fields(object, &["key", "filter"], path)?;
+    let key: PayloadKeyType = match object.get("key") {
+        Some(Value::String(key)) => key.clone(),
+        _ => {
+            return Err(FilterParseError::new(
+                &field_path(path, "key"),
+                "expected payload key string",
+            ))
+        }
+    };
+    let filter = match object.get("filter") {
+        Some(filter) => parse_filter(filter, &field_path(path, "filter"))?,
+        None => {
+            return Err(FilterParseError::new(
+                &field_path(path, "filter"),
+                "expected filter object",
+            ))
+        }
+    };
+    Ok(Nested { key, filter })
+}
+
+fn parse_payload_field(value: &Value, path: &str) -> Result<PayloadField, FilterParseError> {
+    let object = as_object(value, path, "object with payload `key`")?;
+    check_fields(object, &["key"], path)?;
+    match object.get("key") {
+        Some(Value::String(key)) => Ok(PayloadField { key: key.clone() }),
+        _ => Err(FilterParseError::new(
+            &field_path(path, "key"),
+            "expected payload key string",
+        )),
+    }
+}
+
+fn parse_field_condition(
+    object: &Map<String, Value>,
+    path: &str,
+) -> Result<FieldCondition, FilterParseError> {
+    check_fields(object, &FIELD_CONDITION_FIELDS, path)?;
+
+    let key: PayloadKeyType = match object.get("key") {
+        Some(Value::String(key)) => key.clone(),
+        _ => {
+            return Err(FilterParseError::new(
+                &field_path(path, "key"),
+                "expected payload key string",
+            ))
+        }
+    };
+
+    let optional = |field: &str| object.get(field).filter(|value| !value.is_null());
+
+    let r#match = optional("match")
+        .map(|value| parse_match(value, &field_path(path, "match")))
+        .transpose()?;
+    let range = optional("range")
+        .map(|value| {
+            let range_path = field_path(path, "range");
+            check_fields(
+                as_object(value, &range_path, "range object")?,
+                &RANGE_FIELDS,
+                &range_path,
+            )?;
+            parse_value(value, path, "range")
+        })
+        .transpose()?;
+    let values_count = optional("values_count")
+        .map(|value| {
+            let count_path = field_path(path, "values_count");
+            check_fields(
+                as_object(value, &count_path, "values_count object")?,
+                &RANGE_FIELDS,
+                &count_path,
+            )?;
+            parse_value(value, path, "values_count")
+        })
+        .transpose()?;
+    let geo_bounding_box = optional("geo_bounding_box")
+        .map(|value| parse_value(value, path, "geo_bounding_box"))
+        .transpose()?;
+    let geo_radius = optional("geo_radius")
+        .map(|value| parse_value(value, path, "geo_radius"))
+        .transpose()?;
+    let geo_polygon = optional("geo_polygon")
+        .map(|value| parse_value(value, path, "geo_polygon"))
+        .transpose()?;
+
+    let condition = FieldCondition {
+        key,
+        r#match,
+        range,
+        geo_bounding_box,
+        geo_radius,
+        geo_polygon,
+        values_count,
+    };
+
+    if condition.r#match.is_none()
+        && condition.range.is_none()
+        && condition.geo_bounding_box.is_none()
+        && condition.geo_radius.is_none()
+        && condition.geo_polygon.is_none()
+        && condition.values_count.is_none()
+    {
+        return Err(FilterParseError::new(
+            path,
+            format!("no condition specified for field `{}`", condition.key),
+        ));
+    }
+
+    Ok(condition)
+}
+
+fn parse_match(value: &Value, path: &str) -> Result<Match, FilterParseError> {
+    let object = as_object(value, path, "match object")?;
+    check_fields(object, &MATCH_FIELDS, path)?;
+    if object.len() != 1 {
+        return Err(FilterParseError::new(
+            path,
+            format!("expected exactly one of {MATCH_FIELDS:?}"),
+        ));
+    }
+    let (field, value) = object.iter().next().unwrap();
+    let parsed = match field.as_str() {
+        "value" => Match::Value(MatchValue {
+            value: parse_value(value, path, field)?,
+        }),
+        "text" => Match::Text(MatchText {
+            text: parse_value(value, path, field)?,
+        }),
+        "any" => Match::Any(MatchAny {
+            any: parse_value(value, path, field)?,
+        }),
+        "except" => Match::Except(MatchExcept {
+            except: parse_value(value, path, field)?,
+        }),
+        _ => Match::Insensitive(MatchInsensitive {
+            insensitive: parse_value(value, path, field)?,
+        }),
+    };
+    Ok(parsed)
+}
+
+fn schema_type(schema: &PayloadFieldSchema) -> PayloadSchemaType {
+    match schema {
+        PayloadFieldSchema::FieldType(schema_type) => *schema_type,
+        PayloadFieldSchema::FieldParams(PayloadSchemaParams::Text(_)) => PayloadSchemaType::Text,
+        PayloadFieldSchema::FieldParams(PayloadSchemaParams::Keyword(_)) => {
+            PayloadSchemaType::Keyword
+        }
+    }
+}
+
+/// Name of the first part of the condition, which can't be applied to the field of a given type
+fn incompatible_part(
+    condition: &FieldCondition,
+    schema_type: PayloadSchemaType,
+) -> Option<&'static str> {
+    use PayloadSchemaType::*;
+
+    let is_string = matches!(schema_type, Keyword | Text);
+    let is_number = matches!(schema_type, Integer | Float);
+
+    let match_compatible = match &condition.r#match {
+        None => true,
+        Some(Match::Value(value)) => match value.value {
+            ValueVariants::Keyword(_) => is_string,
+            ValueVariants::Integer(_) => is_number,
+            ValueVariants::Bool(_) => true,
+        },
+        Some(Match::Text(_)) | Some(Match::Insensitive(_)) => is_string,
+        Some(Match::Any(MatchAny { any: values }))
+        | Some(Match::Except(MatchExcept { except: values })) => match values {
+            AnyVariants::Keywords(_) => is_string,
+            AnyVariants::Integers(_) => is_number,
+        },
+    };
+
+    if !match_compatible {
+        Some("match")
+    } else if condition.range.is_some() && !is_number {
+        Some("range")
+    } else if condition.geo_bounding_box.is_some() && schema_type != Geo {
+        Some("geo_bounding_box")
+    } else if condition.geo_radius.is_some() && schema_type != Geo {
+        Some("geo_radius")
+    } else if condition.geo_polygon.is_some() && schema_type != Geo {
+        Some("geo_polygon")
+    } else {
+        None
+    }
+}
+
+fn validate_field_schema(
+    condition: &FieldCondition,
+    schema: &HashMap<PayloadKeyType, PayloadFieldSchema>,
+    path: &str,
+) -> Result<(), FilterParseError> {
+    let schema_type = match schema.get(&condition.key) {
+        Some(field_schema) => schema_type(field_schema),
+        None => return Ok(()),
+    };
+    match incompatible_part(condition, schema_type) {
+        Some(part) => Err(FilterParseError::new(
+            &field_path(path, part),
+            format!(
+                "`{part}` can't be applied to field `{}` of type {schema_type:?}",
+                condition.key
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn validate_filter_schema(
+    filter: &Filter,
+    schema: &HashMap<PayloadKeyType, PayloadFieldSchema>,
+    path: &str,
+) -> Result<(), FilterParseError> {
+    let clauses = [
+        ("should", &filter.should),
+        ("must", &filter.must),
+        ("must_not", &filter.must_not),
+    ];
+    for (name, conditions) in clauses {
+        for (idx, condition) in conditions.iter().flatten().enumerate() {
+            let condition_path = format!("{}[{idx}]", field_path(path, name));
+            match condition {
+                Condition::Field(field_condition) => {
+                    validate_field_schema(field_condition, schema, &condition_path)?
+                }
+                Condition::Filter(nested) => {
+                    validate_filter_schema(nested, schema, &condition_path)?
+                }
+                // Keys of the nested filter are relative to the objects of the array
+                Condition::IsEmpty(_)
+                | Condition::IsNull(_)
+                | Condition::HasId(_)
+                | Condition::HasVector(_)
+                | Condition::Nested(_) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Filter {
+    /// Strictly parse filter from JSON
+    ///
+    /// Rejects unknown fields and condition types, errors point at the JSON path of the
+    /// offending element.
+    pub fn from_json(json: &str) -> Result<Filter, FilterParseError> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|err| FilterParseError::new("", format!("invalid JSON: {err}")))?;
+        parse_filter(&value, "")
+    }
+
+    /// Check that the conditions are applicable to the types of the indexed fields
+    ///
+    /// Fields, which are not present in the `schema`, are not checked.
+    pub fn validate_schema(
+        &self,
+        schema: &HashMap<PayloadKeyType, PayloadFieldSchema>,
+    ) -> Result<(), FilterParseError> {
+        validate_filter_schema(self, schema, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(json: &str) -> FilterParseError {
+        Filter::from_json(json).unwrap_err()
+    }
+
+    #[test]
+    fn test_from_json_matches_deserialization() {
+        let json = r#"
+        {
+            "should": [
+                { "key": "city", "match": { "value": "Berlin" } },
+                { "key": "tags", "match": { "any": ["a", "b"] } },
+                { "key": "sizes", "match": { "except": [1, 2] } },
+                { "key": "description", "match": { "text": "good" } },
+                { "key": "brand", "match": { "insensitive": "Äpfel" } }
+            ],
+            "must": [
+                { "key": "price", "range": { "gte": 10.0, "lt": 100.5 } },
+                { "key": "location", "geo_radius": { "center": { "lon": 13.4, "lat": 52.5 }, "radius": 1000.0 } },
+                { "must_not": [ { "has_id": [1, 2, 3] } ] },
+                { "has_vector": "image" },
+                { "nested": { "key": "variants", "filter": { "must": [ { "key": "color", "match": { "value": "red" } } ] } } }
+            ],
+            "must_not": [
+                { "is_empty": { "key": "reviews" } },
+                { "is_null": { "key": "owner" } },
+                { "key": "reviews", "values_count": { "gt": 5 } }
+            ]
+        }"#;
+
+        let parsed = Filter::from_json(json).unwrap();
+        let deserialized: Filter = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed, deserialized);
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let error = parse_error(r#"{"must": [}"#);
+        assert_eq!(error.path, "root");
+        assert!(error.message.starts_with("invalid JSON"));
+
+        let error = parse_error(r#"{"musts": []}"#);
+        assert_eq!(error.path, "root");
+        assert!(error.message.contains("unknown field `musts`"));
+
+        let error = parse_error(r#"{"must": {"key": "city", "match": {"value": "Berlin"}}}"#);
+        assert_eq!(error.path, "must");
+        assert_eq!(error.message, "expected array of conditions");
+
+        let error = parse_error(
+            r#"{"must": [{"key": "a", "match": {"value": 1}}, {"key": "b", "match": {"value": 2}}, {"field": "c"}]}"#,
+        );
+        assert_eq!(error.to_string(), "unknown condition type at must[2]");
+
+        let error = parse_error(r#"{"must": [{"key": "city", "value": "Berlin"}]}"#);
+        assert_eq!(error.path, "must[0]");
+        assert!(error.message.contains("unknown field `value`"));
+
+        let error = parse_error(r#"{"must": [{"key": "city"}]}"#);
+        assert_eq!(error.path, "must[0]");
+        assert!(error.message.contains("no condition specified"));
+
+        let error = parse_error(r#"{"should": [{"key": "city", "match": {"values": "Berlin"}}]}"#);
+        assert_eq!(error.path, "should[0].match");
+        assert!(error.message.contains("unknown field `values`"));
+
+        let error = parse_error(r#"{"should": [{"key": "city", "match": {"value": [1]}}]}"#);
+        assert_eq!(error.path, "should[0].match.value");
+
+        let error = parse_error(r#"{"must_not": [{"key": "price", "range": {"gt": "ten"}}]}"#);
+        assert_eq!(error.path, "must_not[0].range");
+        assert!(error.message.starts_with("invalid `range`"));
+
+        let error = parse_error(r#"{"must_not": [{"key": "price", "range": {"from": 10}}]}"#);
+        assert_eq!(error.path, "must_not[0].range");
+        assert!(error.message.contains("unknown field `from`"));
+
+        let error = parse_error(r#"{"must": [{"should": [{"is_empty": {"field": "a"}}]}]}"#);
+        assert_eq!(error.path, "must[0].should[0].is_empty");
+
+        let error = parse_error(r#"{"must": [{"has_id": [1, -1]}]}"#);
+        assert_eq!(error.path, "must[0].has_id");
+
+        let error = parse_error(r#"{"must": [{"has_vector": ["image"]}]}"#);
+        assert_eq!(error.path, "must[0].has_vector");
+
+        let error = parse_error(r#"{"must": [{"has_id": [1], "key": "a"}]}"#);
+        assert_eq!(error.path, "must[0]");
+
+        let error = parse_error(
+            r#"{"must": [{"nested": {"key": "variants", "filter": {"must": [{"key": "size"}]}}}]}"#,
+        );
+        assert_eq!(error.path, "must[0].nested.filter.must[0]");
+
+        let error = parse_error(r#"{"must": [{"nested": {"key": "variants"}}]}"#);
+        assert_eq!(error.path, "must[0].nested.filter");
+
+        let error = parse_error(r#"{"must": ["city"]}"#);
+        assert_eq!(error.to_string(), "expected condition object at must[0]");
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let schema: HashMap<PayloadKeyType, PayloadFieldSchema> = HashMap::from([
+            ("city".to_string(), PayloadSchemaType::Keyword.into()),
+            ("price".to_string(), PayloadSchemaType::Float.into()),
+            ("location".to_string(), PayloadSchemaType::Geo.into()),
+        ]);
+
+        let valid = Filter::from_json(
+            r#"{"must": [
+                {"key": "city", "match": {"value": "Berlin"}},
+                {"key": "price", "range": {"gt": 10}},
+                {"key": "not_indexed", "range": {"gt": 10}}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(valid.validate_schema(&schema).is_ok());
+
+        let filter =
+            Filter::from_json(r#"{"must": [{"key": "city", "range": {"gt": 10}}]}"#).unwrap();
+        let error = filter.validate_schema(&schema).unwrap_err();
+        assert_eq!(error.path, "must[0].range");
+        assert!(error.message.contains("`city`"));
+
+        let filter = Filter::from_json(
+            r#"{"should": [{"must_not": [{"key": "price", "match": {"value": "ten"}}]}]}"#,
+        )
+        .unwrap();
+        let error = filter.validate_schema(&schema).unwrap_err();
+        assert_eq!(error.path, "should[0].must_not[0].match");
+
+        let filter = Filter::from_json(
+            r#"{"must": [{"key": "city", "geo_radius": {"center": {"lon": 0, "lat": 0}, "radius": 1}}]}"#,
+        )
+        .unwrap();
+        let error = filter.validate_schema(&schema).unwrap_err();
+        assert_eq!(error.path, "must[0].geo_radius");
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Condvar, Mutex, RwLock};
+
+use crate::entry::entry_point::{OperationResult, SegmentEntry};
+use crate::segment::Segment;
+use crate::types::{FlushPolicy, SeqNumberType};
+
+/// Decides, whether the applied operations are due to be flushed, according to the [`FlushPolicy`]
+#[derive(Debug)]
+pub struct FlushScheduler {
+    policy: FlushPolicy,
+    state: Mutex<FlushState>,
+}
+
+#[derive(Debug)]
+struct FlushState {
+    /// Number of operations, applied since the last flush
+    operations: u64,
+    last_flush: Instant,
+}
+
+impl FlushScheduler {
+    pub fn new(policy: FlushPolicy) -> Self {
+        FlushScheduler {
+            policy,
+            state: Mutex::new(FlushState {
+                operations: 0,
+                last_flush: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> FlushPolicy {
+        self.policy
+    }
+
+    /// Count the applied operation, returns if the flush is due
+    pub fn operation_applied(&self) -> bool {
+        let mut state = self.state.lock();
+        state.operations += 1;
+        match self.policy {
+            FlushPolicy::Always => true,
+            FlushPolicy::EveryNOps(ops) => state.operations >= ops,
+            FlushPolicy::Interval(interval) => state.last_flush.elapsed() >= interval,
+            FlushPolicy::Manual => false,
+        }
+    }
+
+    /// Start counting from the flush, either scheduled or explicit
+    pub fn flushed(&self) {
+        let mut state = self.state.lock();
+        state.operations = 0;
+        state.last_flush = Instant::now();
+    }
+}
+
+/// Outcome of the last flush of the segment, either explicit or background
+#[derive(Debug, Default, Clone)]
+pub struct FlushStats {
+    pub last_flush_time: Option<DateTime<Utc>>,
+    pub last_flush_error: Option<String>,
+}
+
+impl FlushStats {
+    pub fn record(&mut self, result: &OperationResult<SeqNumberType>) {
+        match result {
+            Ok(_) => {
+                self.last_flush_time = Some(Utc::now());
+                self.last_flush_error = None;
+            }
+            Err(err) => self.last_flush_error = Some(err.to_string()),
+        }
+    }
+}
+
+/// Thread, which periodically flushes the segment
+///
+/// Segment is not kept alive by the flusher: the thread stops on the next tick after the segment is dropped.
+/// The thread is stopped and joined on [`BackgroundFlusher::close`] or drop.
+pub struct BackgroundFlusher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundFlusher {
+    /// Start the flusher, if the background flush is enabled in the config of the segment
+    pub fn for_segment(segment: &Arc<RwLock<Segment>>) -> Option<Self> {
+        let interval_ms = segment.read().segment_config.background_flush_interval_ms?;
+        Some(Self::start(
+            Arc::downgrade(segment),
+            Duration::from_millis(interval_ms),
+        ))
+    }
+
+    pub fn start(segment: Weak<RwLock<Segment>>, interval: Duration) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = stop.clone();
+        let handle = std::thread::Builder::new()
+            .name("background_flusher".to_string())
+            .spawn(move || Self::run(segment, interval, thread_stop))
+            .unwrap();
+        BackgroundFlusher {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    fn run(segment: Weak<RwLock<Segment>>, interval: Duration, stop: Arc<(Mutex<bool>, Condvar)>) {
+        let (stopped, condvar) = &*stop;
+        loop {
+            {
+                let mut stopped = stopped.lock();
+                if !*stopped {
+                    condvar.wait_for(&mut stopped, interval);
+                }
+                if *stopped {
+                    return;
+                }
+            }
+            let segment = match segment.upgrade() {
+                Some(segment) => segment,
+                None => return,
+            };
+            let segment = segment.read();
+            // Back off until the next tick, flush of the segment is already running
+            if segment.is_background_flushing() {
+                continue;
+            }
+            // Failure is reported in the flush stats of the segment
+            if let Err(err) = segment.flush(true) {
+                log::error!(
+                    "Background flush of segment {} failed: {err}",
+                    segment.current_path.display()
+                );
+            }
+        }
+    }
+
+    /// Stop the thread and wait for the running flush to finish
+    pub fn close(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        let (stopped, condvar) = &*self.stop;
+        *stopped.lock() = true;
+        condvar.notify_all();
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                log::error!("Background flusher thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for BackgroundFlusher {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
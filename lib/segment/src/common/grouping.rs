@@ -0,0 +1,247 @@
+//! Search of the best hits, grouped by the value of a payload field
+//!
+//! Groups are collected from the hits of the ordinary search, which is repeated with a twice larger `top`,
+//! until the groups are full or all the matching points are found. So the groups are the same, as if
+//! all the found points were grouped, while the search reads only about as many hits, as the groups need.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::data_types::named_vectors::NamedVectors;
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::{OperationResult, SegmentEntry};
+use crate::types::{
+    Filter, PayloadKeyType, PayloadSelector, PointGroup, ScoredPoint, SearchParams, ValueVariants,
+    WithPayload, WithVector,
+};
+
+/// Key of the group of the payload value, only keywords, integers and booleans form groups
+fn group_key(value: &Value) -> Option<ValueVariants> {
+    match value {
+        Value::String(keyword) => Some(ValueVariants::Keyword(keyword.clone())),
+        Value::Number(number) => number.as_i64().map(ValueVariants::Integer),
+        Value::Bool(flag) => Some(ValueVariants::Bool(*flag)),
+        _ => None,
+    }
+}
+
+/// Distinct group keys of the hit, each value of an array is a key
+fn hit_group_keys(hit: &ScoredPoint, group_by: &str) -> Vec<ValueVariants> {
+    let Some(payload) = &hit.payload else {
+        return vec![];
+    };
+    let mut keys = Vec::new();
+    for value in payload.get_value(group_by) {
+        let values: Vec<&Value> = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for key in values.into_iter().filter_map(group_key) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    keys
+}
+
+/// Groups of the hits, which are added best first
+///
+/// So the groups are in the order of their best hits, a new group is not created once there are `max_groups`,
+/// and a group only takes the first `group_size` of its hits, which are the best ones.
+struct GroupsCollector {
+    groups: Vec<PointGroup>,
+    positions: HashMap<ValueVariants, usize>,
+    group_size: usize,
+    max_groups: usize,
+}
+
+impl GroupsCollector {
+    fn new(group_size: usize, max_groups: usize) -> Self {
+        Self {
+            groups: Vec::new(),
+            positions: HashMap::new(),
+            group_size,
+            max_groups,
+        }
+    }
+
+    fn add(&mut self, hit: &ScoredPoint, group_by: &str) {
+        for key in hit_group_keys(hit, group_by) {
+            match self.positions.get(&key) {
+                Some(&position) => {
+                    let hits = &mut self.groups[position].hits;
+                    if hits.len() < self.group_size {
+                        hits.push(hit.clone());
+                    }
+                }
+                None if self.groups.len() < self.max_groups => {
+                    self.positions.insert(key.clone(), self.groups.len());
+                    self.groups.push(PointGroup {
+                        id: key,
+                        hits: vec![hit.clone()],
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// No further hit can change the groups
+    fn is_full(&self) -> bool {
+        self.groups.len() == self.max_groups
+            && self
+                .groups
+                .iter()
+                .all(|group| group.hits.len() == self.group_size)
+    }
+}
+
+/// Up to `groups` groups of the best hits, which share the value of the `group_by` field
+///
+/// See [`SegmentEntry::search_groups`].
+#[allow(clippy::too_many_arguments)]
+pub fn search_groups<S: SegmentEntry + ?Sized>(
+    segment: &S,
+    vector_name: &str,
+    vector: &[VectorElementType],
+    with_payload: &WithPayload,
+    with_vector: &WithVector,
+    group_by: &PayloadKeyType,
+    group_size: usize,
+    groups: usize,
+    filter: Option<&Filter>,
+    params: Option<&SearchParams>,
+) -> OperationResult<Vec<PointGroup>> {
+    if group_size == 0 || groups == 0 {
+        return Ok(vec![]);
+    }
+    let points_count = segment.points_count();
+    // Only the group-by field is read while the hits are fetched
+    let group_key_payload = WithPayload {
+        enable: true,
+        payload_selector: Some(PayloadSelector::new_include(vec![group_by.clone()])),
+    };
+
+    let mut top = groups.saturating_mul(group_size).min(points_count);
+    let mut collector = loop {
+        let hits = segment.search(
+            vector_name,
+            vector,
+            &group_key_payload,
+            &false.into(),
+            filter,
+            top,
+            params,
+        )?;
+        let mut collector = GroupsCollector::new(group_size, groups);
+        for hit in &hits {
+            collector.add(hit, group_by);
+            if collector.is_full() {
+                break;
+            }
+        }
+        if collector.is_full() || hits.len() < top || top >= points_count {
+            break collector;
+        }
+        top = top.saturating_mul(2).min(points_count);
+    };
+
+    for hit in collector
+        .groups
+        .iter_mut()
+        .flat_map(|group| group.hits.iter_mut())
+    {
+        hit.payload = if with_payload.enable {
+            Some(match &with_payload.payload_selector {
+                Some(selector) => Arc::new(segment.selected_payload(hit.id.clone(), selector)?),
+                None => segment.payload(hit.id.clone())?,
+            })
+        } else {
+            None
+        };
+        hit.vector = match with_vector {
+            WithVector::Bool(false) => None,
+            WithVector::Bool(true) => Some(segment.all_vectors(hit.id.clone())?.into()),
+            WithVector::Selector(vector_names) => {
+                let mut vectors = NamedVectors::default();
+                for vector_name in vector_names {
+                    vectors.insert(
+                        vector_name.clone(),
+                        segment.vector(vector_name, hit.id.clone())?,
+                    );
+                }
+                Some(vectors.into())
+            }
+        };
+    }
+    Ok(collector.groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn hit(id: u64, payload: Value) -> ScoredPoint {
+        ScoredPoint {
+            id: id.into(),
+            version: 0,
+            score: 0.0,
+            payload: Some(Arc::new(payload.into())),
+            vector: None,
+        }
+    }
+
+    #[test]
+    fn test_hit_group_keys() {
+        let keys = |payload: Value| hit_group_keys(&hit(0, payload), "doc");
+        assert_eq!(
+            keys(json!({"doc": ["a", 1, "a", true, 1.5, null]})),
+            vec![
+                ValueVariants::Keyword("a".to_string()),
+                ValueVariants::Integer(1),
+                ValueVariants::Bool(true),
+            ]
+        );
+        assert!(keys(json!({"doc": 1.5})).is_empty());
+        assert!(keys(json!({"other": "a"})).is_empty());
+    }
+
+    #[test]
+    fn test_groups_collector() {
+        let mut collector = GroupsCollector::new(2, 2);
+        for (id, doc) in [(0, json!("a")), (1, json!(["b", "a"])), (2, json!("a"))] {
+            collector.add(&hit(id, json!({ "doc": doc })), "doc");
+        }
+        assert!(!collector.is_full());
+        // Group "c" is not created, as there are 2 groups already
+        collector.add(&hit(3, json!({"doc": ["c", "b"]})), "doc");
+        assert!(collector.is_full());
+
+        let groups: Vec<_> = collector
+            .groups
+            .iter()
+            .map(|group| {
+                let ids: Vec<_> = group.hits.iter().map(|hit| hit.id.clone()).collect();
+                (group.id.clone(), ids)
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    ValueVariants::Keyword("a".to_string()),
+                    vec![0.into(), 1.into()]
+                ),
+                (
+                    ValueVariants::Keyword("b".to_string()),
+                    vec![1.into(), 3.into()]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_types::vectors::VectorStruct;
+use crate::entry::entry_point::OperationResult;
+use crate::types::{Payload, PointIdType, SeqNumberType};
+
+/// Number of malformed lines, collected by the import before it is aborted
+pub const DEFAULT_IMPORT_ERROR_BUDGET: usize = 100;
+
+/// Point as a line of JSON Lines, see [`crate::entry::entry_point::SegmentEntry::export_jsonl`]
+///
+/// Payload is written as stored, so geo points are `{"lon": .., "lat": ..}` and datetimes are RFC 3339 strings.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct JsonlPoint {
+    pub id: PointIdType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<VectorStruct>,
+    #[serde(default)]
+    pub payload: Payload,
+}
+
+impl JsonlPoint {
+    pub fn write_line(&self, writer: &mut dyn Write) -> OperationResult<()> {
+        serde_json::to_writer(&mut *writer, self)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Outcome of the import of the JSON Lines, see [`crate::segment::Segment::import_jsonl`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Number of imported points
+    pub imported: usize,
+    /// Malformed lines, the import is aborted on the one beyond the error budget
+    pub errors: Vec<ImportLineError>,
+    pub aborted: bool,
+    /// Op num after the last one, applied by the import
+    pub next_op_num: SeqNumberType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportLineError {
+    /// Line number, starting from 1
+    pub line: usize,
+    pub error: String,
+}
+
+/// Points of the JSON Lines with their line numbers, one per non-empty line
+///
+/// Malformed line yields its parse error, failure to read ends the iteration with the error.
+pub fn read_points(
+    reader: &mut dyn BufRead,
+) -> impl Iterator<Item = OperationResult<(usize, serde_json::Result<JsonlPoint>)>> + '_ {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(index, line)| Ok((index + 1, serde_json::from_str(&line?))))
+}
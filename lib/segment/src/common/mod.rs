@@ -1,12 +1,30 @@
 pub mod anonymize;
-pub mod arc_atomic_ref_cell_iterator;
+pub mod arc_rwlock_iterator;
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+pub mod atomic_rename;
+pub mod checksums;
 pub mod cpu;
+pub mod encryption;
 pub mod error_logging;
 pub mod file_operations;
+pub mod filter_parser;
+pub mod flush_scheduler;
+pub mod grouping;
+pub mod jsonl;
+pub mod npy;
 pub mod operation_time_statistics;
+pub mod parallel;
+pub mod payload_selection;
 pub mod rocksdb_wrapper;
+pub mod search_deadline;
+pub mod search_telemetry;
+pub mod segment_lock;
+pub mod stats_cache;
 pub mod utils;
 pub mod version;
+pub mod warm_up;
+pub mod write_hook;
 
 use crate::data_types::named_vectors::NamedVectors;
 use crate::entry::entry_point::{OperationError, OperationResult};
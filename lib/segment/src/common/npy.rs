@@ -0,0 +1,345 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::{OperationError, OperationResult};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Element type of the matrix, only little-endian floats are supported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NpyDtype {
+    F32,
+    F64,
+}
+
+impl NpyDtype {
+    fn parse(descr: &str) -> OperationResult<Self> {
+        match descr {
+            "<f4" => Ok(NpyDtype::F32),
+            "<f8" => Ok(NpyDtype::F64),
+            _ => Err(npy_error(format!(
+                "unsupported dtype {descr:?}, only little-endian float32 '<f4' and float64 '<f8' are supported"
+            ))),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            NpyDtype::F32 => 4,
+            NpyDtype::F64 => 8,
+        }
+    }
+}
+
+/// Header of the 2-D C-order matrix in the `.npy` format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NpyHeader {
+    pub dtype: NpyDtype,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl NpyHeader {
+    fn read(reader: &mut impl Read) -> OperationResult<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic[..NPY_MAGIC.len()] != NPY_MAGIC {
+            return Err(npy_error("not a .npy file"));
+        }
+        let major_version = magic[NPY_MAGIC.len()];
+        let header_len = match major_version {
+            1 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            _ => {
+                return Err(npy_error(format!(
+                    "unsupported format version {major_version}"
+                )))
+            }
+        };
+        let mut header = vec![0; header_len];
+        reader.read_exact(&mut header)?;
+        let header = String::from_utf8_lossy(&header);
+        Self::parse(&header)
+    }
+
+    /// Parse the header dict, e.g. `{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }`
+    fn parse(header: &str) -> OperationResult<Self> {
+        let descr = header_value(header, "descr")?;
+        let descr = descr
+            .split(['\'', '"'])
+            .nth(1)
+            .ok_or_else(|| npy_error(format!("invalid descr in header {header:?}")))?;
+        let dtype = NpyDtype::parse(descr)?;
+
+        if header_value(header, "fortran_order")?.starts_with("True") {
+            return Err(npy_error(
+                "Fortran-order matrix is not supported, save it in C-order",
+            ));
+        }
+
+        let shape = header_value(header, "shape")?;
+        let shape = shape
+            .strip_prefix('(')
+            .and_then(|shape| shape.split(')').next())
+            .ok_or_else(|| npy_error(format!("invalid shape in header {header:?}")))?;
+        let dims = shape
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| {
+                dim.parse::<usize>()
+                    .map_err(|err| npy_error(format!("invalid shape dimension {dim:?}: {err}")))
+            })
+            .collect::<OperationResult<Vec<_>>>()?;
+        match dims[..] {
+            [rows, cols] => Ok(NpyHeader { dtype, rows, cols }),
+            _ => Err(npy_error(format!(
+                "only 2-D matrices are supported, found shape ({shape})"
+            ))),
+        }
+    }
+}
+
+/// Value of the `key` in the header dict, up to the end of the header
+fn header_value<'a>(header: &'a str, key: &str) -> OperationResult<&'a str> {
+    let key_start = header
+        .find(&format!("'{key}'"))
+        .ok_or_else(|| npy_error(format!("no {key:?} in header {header:?}")))?;
+    let value = &header[key_start + key.len() + 2..];
+    Ok(value.trim_start().trim_start_matches(':').trim_start())
+}
+
+fn npy_error(description: impl std::fmt::Display) -> OperationError {
+    OperationError::service_error(format!("Can't read .npy matrix: {description}"))
+}
+
+/// Streams the rows of the `.npy` matrix, converted into vectors
+pub struct NpyReader<R: Read> {
+    reader: R,
+    header: NpyHeader,
+    rows_read: usize,
+    buffer: Vec<u8>,
+}
+
+impl NpyReader<BufReader<File>> {
+    pub fn open(path: &Path) -> OperationResult<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> NpyReader<R> {
+    pub fn new(mut reader: R) -> OperationResult<Self> {
+        let header = NpyHeader::read(&mut reader)?;
+        Ok(NpyReader {
+            reader,
+            header,
+            rows_read: 0,
+            buffer: vec![0; header.cols * header.dtype.size()],
+        })
+    }
+
+    pub fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
+    /// Next row of the matrix, None after the last one
+    pub fn read_row(&mut self) -> OperationResult<Option<Vec<VectorElementType>>> {
+        if self.rows_read == self.header.rows {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut self.buffer).map_err(|err| {
+            npy_error(format!(
+                "row {} of {} is truncated: {err}",
+                self.rows_read, self.header.rows
+            ))
+        })?;
+        self.rows_read += 1;
+        let row = match self.header.dtype {
+            NpyDtype::F32 => self
+                .buffer
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect(),
+            NpyDtype::F64 => self
+                .buffer
+                .chunks_exact(8)
+                .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()) as VectorElementType)
+                .collect(),
+        };
+        Ok(Some(row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::Write;
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::DEFAULT_VECTOR_NAME;
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment::Segment;
+    use crate::segment_constructor::build_segment;
+    use crate::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+
+    const DIM: usize = 3;
+
+    /// Write the `.npy` file of version 1 with the header dict, padded to 64 bytes, and the data
+    fn write_npy(path: &Path, descr: &str, fortran_order: bool, shape: &str, data: &[u8]) {
+        let fortran_order = if fortran_order { "True" } else { "False" };
+        let mut header =
+            format!("{{'descr': '{descr}', 'fortran_order': {fortran_order}, 'shape': {shape}, }}");
+        let padding = 64 - (NPY_MAGIC.len() + 4 + header.len() + 1) % 64;
+        header.push_str(&" ".repeat(padding));
+        header.push('\n');
+
+        let mut file = File::create(path).unwrap();
+        file.write_all(NPY_MAGIC).unwrap();
+        file.write_all(&[1, 0]).unwrap();
+        file.write_all(&(header.len() as u16).to_le_bytes())
+            .unwrap();
+        file.write_all(header.as_bytes()).unwrap();
+        file.write_all(data).unwrap();
+    }
+
+    fn matrix(rows: usize) -> Vec<Vec<f32>> {
+        (0..rows)
+            .map(|row| (0..DIM).map(|col| (row * DIM + col) as f32).collect())
+            .collect()
+    }
+
+    fn f32_data(matrix: &[Vec<f32>]) -> Vec<u8> {
+        matrix
+            .iter()
+            .flatten()
+            .flat_map(|x| x.to_le_bytes())
+            .collect()
+    }
+
+    fn segment(path: &Path) -> Segment {
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        build_segment(path, &config).unwrap()
+    }
+
+    #[test]
+    fn test_import_npy() {
+        let dir = Builder::new().prefix("npy_dir").tempdir().unwrap();
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let mut segment = segment(segment_dir.path());
+        let vectors = matrix(600);
+
+        // Sequential ids, rows are upserted in several batches
+        let f32_path = dir.path().join("vectors_f32.npy");
+        write_npy(&f32_path, "<f4", false, "(600, 3)", &f32_data(&vectors));
+        assert_eq!(segment.import_npy(&f32_path, None, 1000, 1).unwrap(), 600);
+        assert_eq!(segment.points_count(), 600);
+        for (row, vector) in vectors.iter().enumerate() {
+            let point_id = (1000 + row as u64).into();
+            assert_eq!(
+                &segment.vector(DEFAULT_VECTOR_NAME, point_id).unwrap(),
+                vector
+            );
+        }
+
+        // Ids of the sidecar file, float64 rows are converted
+        let f64_path = dir.path().join("vectors_f64.npy");
+        let f64_data: Vec<u8> = vectors[..3]
+            .iter()
+            .flatten()
+            .flat_map(|x| (*x as f64 + 0.5).to_le_bytes())
+            .collect();
+        write_npy(&f64_path, "<f8", false, "(3, 3)", &f64_data);
+        let ids_path = dir.path().join("ids.txt");
+        fs::write(&ids_path, "7\n5511f2e6-6c2a-4e1a-8a6e-c5c5b0c2d3f1\n7\n").unwrap();
+        assert_eq!(
+            segment
+                .import_npy(&f64_path, Some(&ids_path), 0, 2)
+                .unwrap(),
+            3
+        );
+        assert_eq!(segment.points_count(), 602);
+        // Repeated id is upserted by the later row
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 7.into()).unwrap(),
+            vec![6.5, 7.5, 8.5]
+        );
+        let uuid_id = "5511f2e6-6c2a-4e1a-8a6e-c5c5b0c2d3f1".parse().unwrap();
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, uuid_id).unwrap(),
+            vec![3.5, 4.5, 5.5]
+        );
+
+        // Ids file with fewer ids than rows
+        fs::write(&ids_path, "8\n").unwrap();
+        assert!(segment
+            .import_npy(&f64_path, Some(&ids_path), 0, 3)
+            .is_err());
+    }
+
+    #[test]
+    fn test_import_npy_invalid_matrix() {
+        let dir = Builder::new().prefix("npy_dir").tempdir().unwrap();
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let mut segment = segment(segment_dir.path());
+        let data = f32_data(&matrix(2));
+        let path = dir.path().join("vectors.npy");
+
+        let import_error = |segment: &mut Segment, descr, fortran_order, shape| {
+            write_npy(&path, descr, fortran_order, shape, &data);
+            segment
+                .import_npy(&path, None, 0, 1)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let error = import_error(&mut segment, "<f4", true, "(2, 3)");
+        assert!(error.contains("Fortran-order"), "{error}");
+        let error = import_error(&mut segment, ">f4", false, "(2, 3)");
+        assert!(error.contains("unsupported dtype \">f4\""), "{error}");
+        let error = import_error(&mut segment, "<i4", false, "(2, 3)");
+        assert!(error.contains("unsupported dtype \"<i4\""), "{error}");
+        let error = import_error(&mut segment, "<f4", false, "(6,)");
+        assert!(error.contains("only 2-D"), "{error}");
+        assert!(matches!(
+            {
+                write_npy(&path, "<f4", false, "(3, 2)", &data);
+                segment.import_npy(&path, None, 0, 1).unwrap_err()
+            },
+            OperationError::WrongVector {
+                expected_dim: DIM,
+                received_dim: 2,
+            }
+        ));
+        // Truncated data
+        let error = import_error(&mut segment, "<f4", false, "(3, 3)");
+        assert!(error.contains("truncated"), "{error}");
+        // Batch of the complete rows is not upserted either
+        assert_eq!(segment.points_count(), 0);
+    }
+}
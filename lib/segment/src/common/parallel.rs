@@ -0,0 +1,193 @@
+//! Parallel execution of the independent tasks, e.g. of the loads of the segment components
+//! or of the queries of a search batch
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use parking_lot::Mutex;
+
+use crate::common::cpu::get_num_cpus;
+use crate::entry::entry_point::OperationResult;
+
+/// Task of [`run_parallel`]
+pub type ParallelTask<'a, T> = Box<dyn FnOnce() -> OperationResult<T> + Send + 'a>;
+
+/// Global number of the threads, which load the components of a segment, 0 - auto selection
+static LOAD_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the number of the threads, which load the components of a segment in parallel
+///
+/// If 0 - the number of the available CPUs is used.
+/// It is recommended to set it before any segment is loaded, like [`crate::madvise::set_global`].
+pub fn set_load_threads(threads: usize) {
+    LOAD_THREADS.store(threads, Ordering::Relaxed);
+}
+
+/// Number of the threads, which load the components of a segment
+pub fn get_load_threads() -> usize {
+    match LOAD_THREADS.load(Ordering::Relaxed) {
+        0 => get_num_cpus(),
+        threads => threads,
+    }
+}
+
+/// Run the tasks on up to `threads` scoped threads, results are returned in the order of the tasks
+///
+/// Once a task fails, the tasks, which are not started yet, are skipped, and the first error is returned.
+pub fn run_parallel<T: Send>(
+    threads: usize,
+    tasks: Vec<ParallelTask<'_, T>>,
+) -> OperationResult<Vec<T>> {
+    let threads = threads.clamp(1, tasks.len().max(1));
+    if threads == 1 {
+        return tasks.into_iter().map(|task| task()).collect();
+    }
+
+    let num_tasks = tasks.len();
+    let tasks: Vec<_> = tasks
+        .into_iter()
+        .map(|task| Mutex::new(Some(task)))
+        .collect();
+    let results: Vec<_> = (0..num_tasks).map(|_| Mutex::new(None)).collect();
+    let next_task = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let first_error = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next_task.fetch_add(1, Ordering::Relaxed);
+                    if index >= num_tasks {
+                        break;
+                    }
+                    let task = tasks[index].lock().take().unwrap();
+                    match task() {
+                        Ok(result) => *results[index].lock() = Some(result),
+                        Err(err) => {
+                            failed.store(true, Ordering::Relaxed);
+                            first_error.lock().get_or_insert(err);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(err) = first_error.into_inner() {
+        return Err(err);
+    }
+    Ok(results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap())
+        .collect())
+}
+
+/// Number of the search threads by the `max_search_threads` of the segment config
+///
+/// If `None` - the search is done in the calling thread, if 0 - the number of the available CPUs is used.
+pub fn resolve_search_threads(max_search_threads: Option<usize>) -> usize {
+    match max_search_threads {
+        None => 1,
+        Some(0) => get_num_cpus(),
+        Some(threads) => threads,
+    }
+}
+
+/// Split the search threads between the queries of a batch and the scoring of each query
+///
+/// Queries of the batch are parallelized first, so the threads are not spawned by both levels at once.
+pub fn split_search_threads(search_threads: usize, num_queries: usize) -> (usize, usize) {
+    let batch_threads = search_threads.clamp(1, num_queries.max(1));
+    (batch_threads, (search_threads / batch_threads).max(1))
+}
+
+/// Map the items on up to `threads` scoped threads, results are returned in the order of the items
+pub fn map_parallel<T, R, F>(threads: usize, items: &[T], map: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items.iter().map(map).collect();
+    }
+
+    let results: Vec<_> = (0..items.len()).map(|_| Mutex::new(None)).collect();
+    let next_item = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next_item.fetch_add(1, Ordering::Relaxed);
+                if index >= items.len() {
+                    break;
+                }
+                *results[index].lock() = Some(map(&items[index]));
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::entry::entry_point::OperationError;
+
+    #[test]
+    fn test_run_parallel() {
+        let tasks: Vec<ParallelTask<_>> = (0..10)
+            .map(|index| {
+                Box::new(move || {
+                    // Later tasks finish first
+                    thread::sleep(Duration::from_millis(10 - index));
+                    Ok(index)
+                }) as ParallelTask<_>
+            })
+            .collect();
+        assert_eq!(run_parallel(4, tasks).unwrap(), (0..10).collect::<Vec<_>>());
+        assert_eq!(run_parallel::<u64>(4, vec![]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_map_parallel() {
+        let items: Vec<u64> = (0..100).collect();
+        let mapped = map_parallel(8, &items, |item| item * 2);
+        assert_eq!(
+            mapped,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert_eq!(split_search_threads(8, 64), (8, 1));
+        assert_eq!(split_search_threads(8, 2), (2, 4));
+        assert_eq!(split_search_threads(1, 64), (1, 1));
+    }
+
+    #[test]
+    fn test_run_parallel_error() {
+        let started = AtomicUsize::new(0);
+        let tasks: Vec<ParallelTask<()>> = (0..100)
+            .map(|index| {
+                let started = &started;
+                Box::new(move || {
+                    started.fetch_add(1, Ordering::Relaxed);
+                    if index == 1 {
+                        return Err(OperationError::service_error("task failed"));
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                    Ok(())
+                }) as ParallelTask<_>
+            })
+            .collect();
+        let error = run_parallel(2, tasks).unwrap_err();
+        assert!(error.to_string().contains("task failed"));
+        // Tasks after the failure are skipped
+        assert!(started.load(Ordering::Relaxed) < 100);
+    }
+}
@@ -0,0 +1,274 @@
+//! Selection of the payload fields by the paths of [`PayloadSelector`]
+//!
+//! Paths are dot-separated, like `address.city`, an array is selected into for each of its objects,
+//! `items.name` and `items[].name` are the same path. Selecting a field, which does not exist,
+//! gives no field, and the objects on the way to it are kept empty.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_json::{Map, Value};
+
+use crate::types::{Payload, PayloadSelector};
+
+/// Node of the tree of the selected paths
+#[derive(Debug, Default)]
+struct SelectionNode {
+    /// One of the paths ends here, so the whole value is selected
+    leaf: bool,
+    children: HashMap<String, SelectionNode>,
+}
+
+impl SelectionNode {
+    fn build<'a>(paths: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut root = SelectionNode::default();
+        for path in paths {
+            let mut node = &mut root;
+            for element in path.split('.') {
+                let element = element.strip_suffix("[]").unwrap_or(element);
+                node = node.children.entry(element.to_string()).or_default();
+            }
+            node.leaf = true;
+        }
+        root
+    }
+}
+
+fn include_object(map: &Map<String, Value>, node: &SelectionNode) -> Map<String, Value> {
+    map.iter()
+        .filter_map(|(key, value)| {
+            let child = node.children.get(key)?;
+            Some((key.clone(), include_value(value, child)?))
+        })
+        .collect()
+}
+
+fn include_value(value: &Value, node: &SelectionNode) -> Option<Value> {
+    if node.leaf {
+        return Some(value.clone());
+    }
+    match value {
+        Value::Object(map) => Some(Value::Object(include_object(map, node))),
+        Value::Array(values) => Some(Value::Array(
+            values
+                .iter()
+                .filter_map(Value::as_object)
+                .map(|map| Value::Object(include_object(map, node)))
+                .collect(),
+        )),
+        // Nested path does not exist in a scalar
+        _ => None,
+    }
+}
+
+fn exclude_object(map: &Map<String, Value>, node: &SelectionNode) -> Map<String, Value> {
+    map.iter()
+        .filter_map(|(key, value)| match node.children.get(key) {
+            None => Some((key.clone(), value.clone())),
+            Some(child) if child.leaf => None,
+            Some(child) => Some((key.clone(), exclude_value(value, child))),
+        })
+        .collect()
+}
+
+fn exclude_value(value: &Value, node: &SelectionNode) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(exclude_object(map, node)),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Object(map) => Value::Object(exclude_object(map, node)),
+                    value => value.clone(),
+                })
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Payload selector, prepared to be applied to many payloads
+#[derive(Debug)]
+pub struct PayloadSelection {
+    root: SelectionNode,
+    exclude: bool,
+}
+
+impl PayloadSelection {
+    pub fn new(selector: &PayloadSelector) -> Self {
+        match selector {
+            PayloadSelector::Include(selector) => Self {
+                root: SelectionNode::build(&selector.include),
+                exclude: false,
+            },
+            PayloadSelector::Exclude(selector) => Self {
+                root: SelectionNode::build(&selector.exclude),
+                exclude: true,
+            },
+        }
+    }
+
+    /// Selected fields of the payload, only they are copied
+    pub fn select(&self, payload: &Payload) -> Payload {
+        if self.exclude {
+            Payload(exclude_object(&payload.0, &self.root))
+        } else {
+            Payload(include_object(&payload.0, &self.root))
+        }
+    }
+
+    fn select_value(&self, value: &Value, node: &SelectionNode) -> Option<Value> {
+        if self.exclude {
+            Some(exclude_value(value, node))
+        } else {
+            include_value(value, node)
+        }
+    }
+}
+
+/// Deserialize only the selected fields of the serialized payload
+///
+/// Values of the top-level fields, which are not selected, are skipped without being deserialized,
+/// fields with the nested paths are deserialized and then selected from.
+impl<'de> DeserializeSeed<'de> for &PayloadSelection {
+    type Value = Payload;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Payload, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for &PayloadSelection {
+    type Value = Payload;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("payload object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Payload, A::Error> {
+        let mut payload = Map::new();
+        while let Some(key) = access.next_key::<String>()? {
+            match self.root.children.get(&key) {
+                Some(child) if child.leaf => {
+                    if self.exclude {
+                        access.next_value::<IgnoredAny>()?;
+                    } else {
+                        payload.insert(key, access.next_value()?);
+                    }
+                }
+                Some(child) => {
+                    let value: Value = access.next_value()?;
+                    if let Some(value) = self.select_value(&value, child) {
+                        payload.insert(key, value);
+                    }
+                }
+                None => {
+                    if self.exclude {
+                        payload.insert(key, access.next_value()?);
+                    } else {
+                        access.next_value::<IgnoredAny>()?;
+                    }
+                }
+            }
+        }
+        Ok(Payload(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::PayloadSelectorExclude;
+
+    fn payload() -> Payload {
+        json!({
+            "name": "Berlin",
+            "country": {"name": "Germany", "code": "DE", "capital": {"name": "Berlin"}},
+            "districts": [{"name": "Mitte", "area": 39.5}, {"name": "Pankow", "area": 103.0}, 1],
+            "population": 3_677_472,
+        })
+        .into()
+    }
+
+    fn select(selector: PayloadSelector, payload: &Payload) -> Value {
+        let selection = PayloadSelection::new(&selector);
+        let selected = selection.select(payload);
+
+        // Reading from the serialized payload gives the same fields
+        let serialized = serde_cbor::to_vec(payload).unwrap();
+        let deserialized = selection
+            .deserialize(&mut serde_cbor::Deserializer::from_slice(&serialized))
+            .unwrap();
+        assert_eq!(selected, deserialized);
+
+        Value::Object(selected.0)
+    }
+
+    fn include(paths: &[&str]) -> PayloadSelector {
+        PayloadSelector::new_include(paths.iter().map(|path| path.to_string()).collect())
+    }
+
+    fn exclude(paths: &[&str]) -> PayloadSelector {
+        PayloadSelector::Exclude(PayloadSelectorExclude::new(
+            paths.iter().map(|path| path.to_string()).collect(),
+        ))
+    }
+
+    #[test]
+    fn test_include_nested() {
+        let payload = payload();
+        assert_eq!(
+            select(include(&["name", "country.code"]), &payload),
+            json!({"name": "Berlin", "country": {"code": "DE"}})
+        );
+        assert_eq!(
+            select(
+                include(&["country.capital.name", "districts[].name"]),
+                &payload
+            ),
+            json!({
+                "country": {"capital": {"name": "Berlin"}},
+                "districts": [{"name": "Mitte"}, {"name": "Pankow"}],
+            })
+        );
+        // Whole field wins over its nested path
+        assert_eq!(
+            select(include(&["country.code", "country"]), &payload),
+            json!({"country": payload.0["country"]})
+        );
+    }
+
+    #[test]
+    fn test_exclude_nested() {
+        let payload = payload();
+        assert_eq!(
+            select(exclude(&["country", "districts.area"]), &payload),
+            json!({
+                "name": "Berlin",
+                "districts": [{"name": "Mitte"}, {"name": "Pankow"}, 1],
+                "population": 3_677_472,
+            })
+        );
+        assert_eq!(
+            select(exclude(&["country.capital", "name", "districts"]), &payload),
+            json!({"country": {"name": "Germany", "code": "DE"}, "population": 3_677_472})
+        );
+    }
+
+    #[test]
+    fn test_select_missing() {
+        let payload = payload();
+        assert_eq!(select(include(&["mayor"]), &payload), json!({}));
+        assert_eq!(
+            select(include(&["country.mayor", "population.value"]), &payload),
+            json!({"country": {}})
+        );
+        assert_eq!(
+            select(exclude(&["mayor", "country.mayor"]), &payload),
+            Value::Object(payload.0.clone())
+        );
+    }
+}
@@ -1,12 +1,12 @@
+use std::borrow::Cow;
 use std::path::Path;
 use std::sync::Arc;
 
 use parking_lot::RwLock;
-//use atomic_refcell::{AtomicRef, AtomicRefCell};
 use rocksdb::{ColumnFamily, LogLevel, Options, WriteOptions, DB};
 
+use crate::common::encryption::SegmentCipher;
 use crate::common::Flusher;
-//use crate::common::arc_rwlock_iterator::ArcRwLockIterator;
 use crate::entry::entry_point::{OperationError, OperationResult};
 
 const DB_CACHE_SIZE: usize = 10 * 1024 * 1024; // 10 mb
@@ -21,6 +21,8 @@ pub const DB_VERSIONS_CF: &str = "version";
 pub struct DatabaseColumnWrapper {
     pub database: Arc<RwLock<DB>>,
     pub column_name: String,
+    /// Cipher of the values of the encrypted segment, keys are stored as is
+    cipher: Option<Arc<SegmentCipher>>,
 }
 
 pub struct DatabaseColumnIterator<'a> {
@@ -111,14 +113,52 @@ impl DatabaseColumnWrapper {
         Self {
             database,
             column_name: column_name.to_string(),
+            cipher: None,
         }
     }
 
+    /// Encrypt the values with the cipher, if it is given
+    pub fn with_cipher(mut self, cipher: Option<Arc<SegmentCipher>>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Value, as it was put, of the value read from the column, e.g. by the iterator
+    pub fn decrypt_value<'v>(&self, key: &[u8], value: &'v [u8]) -> OperationResult<Cow<'v, [u8]>> {
+        match &self.cipher {
+            None => Ok(Cow::Borrowed(value)),
+            Some(cipher) => cipher
+                .decrypt(&self.value_aad(key), value)
+                .map(Cow::Owned)
+                .map_err(|err| {
+                    OperationError::service_error(format!(
+                        "Failed to decrypt value of column family {}: {err}",
+                        self.column_name
+                    ))
+                }),
+        }
+    }
+
+    /// Encrypted value is bound to its column and key
+    fn value_aad(&self, key: &[u8]) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(self.column_name.len() + 1 + key.len());
+        aad.extend_from_slice(self.column_name.as_bytes());
+        aad.push(0);
+        aad.extend_from_slice(key);
+        aad
+    }
+
     pub fn put<K, V>(&self, key: K, value: V) -> OperationResult<()>
     where
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        let value = match &self.cipher {
+            None => Cow::Borrowed(value.as_ref()),
+            Some(cipher) => {
+                Cow::Owned(cipher.encrypt(&self.value_aad(key.as_ref()), value.as_ref()))
+            }
+        };
         let db = self.database.read();
         let cf_handle = self.get_column_family(&db)?;
         db.put_cf_opt(cf_handle, key, value, &Self::get_write_options())
@@ -137,7 +177,8 @@ impl DatabaseColumnWrapper {
             .map_err(|err| {
                 OperationError::service_error(format!("RocksDB get_pinned_cf error: {err}"))
             })?
-            .map(|value| f(&value));
+            .map(|value| self.decrypt_value(key, &value).map(|value| f(&value)))
+            .transpose()?;
         Ok(result)
     }
 
@@ -153,6 +194,19 @@ impl DatabaseColumnWrapper {
         Ok(())
     }
 
+    /// Move the value to another key, replacing its value, or remove the other key, if there is no value
+    ///
+    /// Value is moved as bytes, encrypted values are re-encrypted for the new key.
+    pub fn rename(&self, from: &[u8], to: &[u8]) -> OperationResult<()> {
+        match self.get_pinned(from, |value| value.to_vec())? {
+            Some(value) => {
+                self.put(to, value)?;
+                self.remove(from)
+            }
+            None => self.remove(to),
+        }
+    }
+
     pub fn lock_db(&self) -> LockedDatabaseColumnWrapper {
         LockedDatabaseColumnWrapper {
             guard: self.database.read(),
@@ -205,6 +259,18 @@ impl DatabaseColumnWrapper {
         Ok(())
     }
 
+    /// Size of the column family files on disk, not including data which is not flushed yet
+    pub fn disk_usage(&self) -> OperationResult<usize> {
+        let db = self.database.read();
+        let cf_handle = self.get_column_family(&db)?;
+        let size = db
+            .property_int_value_cf(cf_handle, "rocksdb.total-sst-files-size")
+            .map_err(|err| {
+                OperationError::service_error(format!("RocksDB property_int_value_cf error: {err}"))
+            })?;
+        Ok(size.unwrap_or(0) as usize)
+    }
+
     pub fn has_column_family(&self) -> OperationResult<bool> {
         let db = self.database.read();
         Ok(db.cf_handle(&self.column_name).is_some())
@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::types::SearchParams;
+
+/// Number of the points, processed between the checks of the deadline
+pub const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+/// Deadline of a search, checked cooperatively by its scoring and traversal loops
+///
+/// Once the deadline is reached, or the stop flag is set, loops stop early, so the search returns
+/// the results, gathered so far.
+/// Deadline is shared by the queries of a batch, which may be searched in parallel.
+#[derive(Debug)]
+pub struct SearchDeadline {
+    deadline: Option<Instant>,
+    /// Flag, set by the caller, which is no longer interested in the results
+    stopped: Option<Arc<AtomicBool>>,
+    expired: AtomicBool,
+}
+
+impl SearchDeadline {
+    /// Deadline, which is never reached
+    pub fn none() -> Self {
+        SearchDeadline {
+            deadline: None,
+            stopped: None,
+            expired: AtomicBool::new(false),
+        }
+    }
+
+    pub fn new(timeout: Duration) -> Self {
+        SearchDeadline {
+            deadline: Some(Instant::now() + timeout),
+            stopped: None,
+            expired: AtomicBool::new(false),
+        }
+    }
+
+    /// Deadline by the `timeout_ms` of the params, starting now
+    pub fn from_params(params: Option<&SearchParams>) -> Self {
+        match params.and_then(|params| params.timeout_ms) {
+            None => Self::none(),
+            Some(timeout_ms) => Self::new(Duration::from_millis(timeout_ms)),
+        }
+    }
+
+    /// Expire the deadline also once the `stopped` flag is set
+    pub fn with_stop_flag(mut self, stopped: Arc<AtomicBool>) -> Self {
+        self.stopped = Some(stopped);
+        self
+    }
+
+    /// Check the clock and the stop flag, the deadline stays expired once reached
+    pub fn check(&self) -> bool {
+        if !self.is_checked() {
+            return false;
+        }
+        if self.expired.load(Ordering::Relaxed) {
+            return true;
+        }
+        let expired = self.is_stopped()
+            || self
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline);
+        if expired {
+            self.expired.store(true, Ordering::Relaxed);
+        }
+        expired
+    }
+
+    /// If the stop flag is set, so the search is cancelled rather than timed out
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+            .as_ref()
+            .map_or(false, |stopped| stopped.load(Ordering::Relaxed))
+    }
+
+    /// If the deadline may expire at all
+    fn is_checked(&self) -> bool {
+        self.deadline.is_some() || self.stopped.is_some()
+    }
+
+    /// If the deadline was reached by any check, the clock is not read
+    pub fn is_expired(&self) -> bool {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    /// Iterator, which ends once the deadline is reached, the clock is read every [`DEADLINE_CHECK_INTERVAL`] items
+    pub fn wrap<I: Iterator>(&self, iter: I) -> DeadlineIter<'_, I> {
+        DeadlineIter {
+            inner: iter,
+            deadline: self.is_checked().then_some(self),
+            counter: 0,
+        }
+    }
+}
+
+pub struct DeadlineIter<'a, I> {
+    inner: I,
+    /// None if the deadline is never reached, so the items are not counted
+    deadline: Option<&'a SearchDeadline>,
+    counter: usize,
+}
+
+impl<'a, I: Iterator> Iterator for DeadlineIter<'a, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(deadline) = self.deadline {
+            if self.counter % DEADLINE_CHECK_INTERVAL == 0 && deadline.check() {
+                return None;
+            }
+            self.counter += 1;
+        }
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_deadline_iter() {
+        let deadline = SearchDeadline::none();
+        assert_eq!(deadline.wrap(0..10_000).count(), 10_000);
+        assert!(!deadline.check());
+
+        let deadline = SearchDeadline::new(Duration::from_millis(100));
+        let mut iter = deadline.wrap(0..usize::MAX);
+        // Clock is not read between the checks
+        assert_eq!(
+            iter.by_ref().take(DEADLINE_CHECK_INTERVAL).count(),
+            DEADLINE_CHECK_INTERVAL
+        );
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(iter.count(), 0);
+        assert!(deadline.is_expired());
+
+        // Stop flag expires the deadline without the timeout
+        let stopped = Arc::new(AtomicBool::new(false));
+        let deadline = SearchDeadline::none().with_stop_flag(stopped.clone());
+        let mut iter = deadline.wrap(0..usize::MAX);
+        assert_eq!(iter.by_ref().take(10).count(), 10);
+        stopped.store(true, Ordering::Relaxed);
+        assert_eq!(iter.count(), DEADLINE_CHECK_INTERVAL - 10);
+        assert!(deadline.is_expired());
+        assert!(deadline.is_stopped());
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::types::SearchParams;
+
+/// Telemetry of a single search in a segment, returned along with its results
+///
+/// Times of the phases are summed over the threads, which search the queries of a batch in parallel.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct SearchTelemetry {
+    /// Number of the candidates, passed to the scorer of the query
+    pub scored_points: usize,
+    /// Number of the steps of the index: expanded graph points or probed lists
+    pub index_hops: usize,
+    /// Number of the points, checked against the filter by the vector index
+    pub filter_checks: usize,
+    /// Number of the found points, rescored with the original vectors
+    pub rescored_points: usize,
+    /// Time of checking the query and choosing the search strategy
+    pub planning_us: u64,
+    /// Time of collecting the points, matching the filter, or of choosing the probed lists
+    pub candidate_generation_us: u64,
+    /// Time of scoring the points, including the graph traversal and the rescoring
+    pub scoring_us: u64,
+    /// Time of mapping the found points to the external ids, with their payloads and vectors
+    pub id_mapping_us: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SearchCounter {
+    ScoredPoints,
+    IndexHops,
+    FilterChecks,
+    RescoredPoints,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SearchPhase {
+    Planning,
+    CandidateGeneration,
+    Scoring,
+    IdMapping,
+}
+
+/// Counters of a single search, collected by the planner, the vector index and the scorers
+///
+/// If disabled, nothing is counted or measured, so the search is not slowed down.
+/// Counters are shared by the queries of a batch, which may be searched in parallel.
+#[derive(Debug, Default)]
+pub struct SearchCounters {
+    enabled: bool,
+    scored_points: AtomicUsize,
+    index_hops: AtomicUsize,
+    filter_checks: AtomicUsize,
+    rescored_points: AtomicUsize,
+    planning_us: AtomicU64,
+    candidate_generation_us: AtomicU64,
+    scoring_us: AtomicU64,
+    id_mapping_us: AtomicU64,
+}
+
+impl SearchCounters {
+    /// Counters, which count nothing
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn new() -> Self {
+        SearchCounters {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Counters, enabled by `with_telemetry` of the params
+    pub fn from_params(params: Option<&SearchParams>) -> Self {
+        if params.map_or(false, |params| params.with_telemetry) {
+            Self::new()
+        } else {
+            Self::disabled()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn add(&self, counter: SearchCounter, count: usize) {
+        if !self.enabled {
+            return;
+        }
+        let counter = match counter {
+            SearchCounter::ScoredPoints => &self.scored_points,
+            SearchCounter::IndexHops => &self.index_hops,
+            SearchCounter::FilterChecks => &self.filter_checks,
+            SearchCounter::RescoredPoints => &self.rescored_points,
+        };
+        counter.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Iterator, which adds the number of the passed items to the counter, once it is dropped
+    pub fn count<I: Iterator>(&self, counter: SearchCounter, iter: I) -> CountedIter<'_, I> {
+        CountedIter {
+            inner: iter,
+            counters: self,
+            counter,
+            count: 0,
+        }
+    }
+
+    /// Measure the time of the phase, until the timer is dropped
+    pub fn measure(&self, phase: SearchPhase) -> PhaseTimer<'_> {
+        PhaseTimer {
+            counters: self,
+            phase,
+            start: self.enabled.then(Instant::now),
+        }
+    }
+
+    fn add_elapsed(&self, phase: SearchPhase, start: Instant) {
+        let phase_us = match phase {
+            SearchPhase::Planning => &self.planning_us,
+            SearchPhase::CandidateGeneration => &self.candidate_generation_us,
+            SearchPhase::Scoring => &self.scoring_us,
+            SearchPhase::IdMapping => &self.id_mapping_us,
+        };
+        phase_us.fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Telemetry of the search, `None` if the counters are disabled
+    pub fn telemetry(&self) -> Option<SearchTelemetry> {
+        self.enabled.then(|| SearchTelemetry {
+            scored_points: self.scored_points.load(Ordering::Relaxed),
+            index_hops: self.index_hops.load(Ordering::Relaxed),
+            filter_checks: self.filter_checks.load(Ordering::Relaxed),
+            rescored_points: self.rescored_points.load(Ordering::Relaxed),
+            planning_us: self.planning_us.load(Ordering::Relaxed),
+            candidate_generation_us: self.candidate_generation_us.load(Ordering::Relaxed),
+            scoring_us: self.scoring_us.load(Ordering::Relaxed),
+            id_mapping_us: self.id_mapping_us.load(Ordering::Relaxed),
+        })
+    }
+}
+
+pub struct CountedIter<'a, I> {
+    inner: I,
+    counters: &'a SearchCounters,
+    counter: SearchCounter,
+    count: usize,
+}
+
+impl<'a, I: Iterator> Iterator for CountedIter<'a, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next();
+        if item.is_some() {
+            self.count += 1;
+        }
+        item
+    }
+}
+
+impl<'a, I> Drop for CountedIter<'a, I> {
+    fn drop(&mut self) {
+        self.counters.add(self.counter, self.count);
+    }
+}
+
+pub struct PhaseTimer<'a> {
+    counters: &'a SearchCounters,
+    phase: SearchPhase,
+    /// None if the counters are disabled, so the clock is not read
+    start: Option<Instant>,
+}
+
+impl<'a> Drop for PhaseTimer<'a> {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            self.counters.add_elapsed(self.phase, start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_counters() {
+        let counters = SearchCounters::disabled();
+        assert_eq!(
+            counters.count(SearchCounter::ScoredPoints, 0..10).count(),
+            10
+        );
+        counters.add(SearchCounter::IndexHops, 5);
+        assert_eq!(counters.telemetry(), None);
+
+        let counters = SearchCounters::new();
+        {
+            let _timer = counters.measure(SearchPhase::Scoring);
+            assert_eq!(
+                counters.count(SearchCounter::ScoredPoints, 0..10).count(),
+                10
+            );
+            // Partially consumed iterator counts only the passed items
+            assert_eq!(
+                counters
+                    .count(SearchCounter::ScoredPoints, 0..10)
+                    .take(3)
+                    .count(),
+                3
+            );
+            counters.add(SearchCounter::IndexHops, 5);
+        }
+        let telemetry = counters.telemetry().unwrap();
+        assert_eq!(telemetry.scored_points, 13);
+        assert_eq!(telemetry.index_hops, 5);
+        assert_eq!(telemetry.filter_checks, 0);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use fs4::FileExt;
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+
+/// Advisory lock file in the segment directory
+pub const LOCK_FILE: &str = ".lock";
+
+/// Advisory lock of the segment directory, held while the segment is open
+///
+/// Writable open holds the exclusive lock and records its process id in the lock file, until it is released.
+/// Read-only opens hold the shared lock, so a writer can't open the segment underneath them.
+/// Lock is released, when it is dropped.
+#[derive(Debug)]
+pub struct SegmentLock {
+    file: File,
+    exclusive: bool,
+}
+
+impl SegmentLock {
+    /// Lock the segment for writing, fails if it is opened by anyone else
+    pub fn exclusive(segment_path: &Path) -> OperationResult<Self> {
+        let mut file = Self::open_file(segment_path)?;
+        if let Err(err) = file.try_lock_exclusive() {
+            return Err(Self::locked_error(segment_path, &mut file, err));
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(std::process::id().to_string().as_bytes())?;
+        file.sync_data()?;
+        Ok(SegmentLock {
+            file,
+            exclusive: true,
+        })
+    }
+
+    /// Lock the segment for reading, fails if it is opened for writing
+    pub fn shared(segment_path: &Path) -> OperationResult<Self> {
+        let mut file = Self::open_file(segment_path)?;
+        if let Err(err) = file.try_lock_shared() {
+            return Err(Self::locked_error(segment_path, &mut file, err));
+        }
+        Ok(SegmentLock {
+            file,
+            exclusive: false,
+        })
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Convert the exclusive lock into the shared one, e.g. once the segment turns out to be read-only
+    pub fn downgrade(&mut self) -> OperationResult<()> {
+        if self.exclusive {
+            self.file.set_len(0)?;
+            self.file.try_lock_shared()?;
+            self.exclusive = false;
+        }
+        Ok(())
+    }
+
+    fn open_file(segment_path: &Path) -> OperationResult<File> {
+        Ok(OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(lock_path(segment_path))?)
+    }
+
+    fn locked_error(segment_path: &Path, file: &mut File, err: std::io::Error) -> OperationError {
+        if err.kind() != fs4::lock_contended_error().kind() {
+            return err.into();
+        }
+        // Process id is recorded by the writer, readers leave it empty
+        let mut holder_pid = String::new();
+        let holder_pid = file
+            .read_to_string(&mut holder_pid)
+            .ok()
+            .and_then(|_| holder_pid.trim().parse().ok());
+        OperationError::SegmentLocked {
+            path: segment_path.to_owned(),
+            holder_pid,
+        }
+    }
+}
+
+impl Drop for SegmentLock {
+    fn drop(&mut self) {
+        // Process id of the writer is not left for the next holders
+        if self.exclusive {
+            let _ = self.file.set_len(0);
+        }
+        if let Err(err) = self.file.unlock() {
+            log::warn!("Failed to unlock segment lock file: {err}");
+        }
+    }
+}
+
+pub fn lock_path(segment_path: &Path) -> PathBuf {
+    segment_path.join(LOCK_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment_constructor::{build_segment, load_segment, load_segment_read_only};
+    use crate::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+
+    #[test]
+    fn test_second_writable_open_fails() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        assert!(segment.lock.as_ref().unwrap().is_exclusive());
+        segment
+            .upsert_vector(1, 1.into(), &only_default_vector(&[1.0, 0.0]))
+            .unwrap();
+        segment.flush(true).unwrap();
+        let path = segment.current_path.clone();
+
+        // Writer excludes both writers and readers
+        for result in [load_segment(&path), load_segment_read_only(&path)] {
+            match result.err().unwrap() {
+                OperationError::SegmentLocked {
+                    path: locked_path,
+                    holder_pid,
+                } => {
+                    assert_eq!(locked_path, path);
+                    assert_eq!(holder_pid, Some(std::process::id()));
+                }
+                err => panic!("unexpected error: {err}"),
+            }
+        }
+        assert_eq!(segment.points_count(), 1);
+        drop(segment);
+
+        // Reader excludes writers
+        let mut reader = load_segment_read_only(&path).unwrap().unwrap();
+        assert!(!reader.lock.as_ref().unwrap().is_exclusive());
+        assert_eq!(reader.points_count(), 1);
+        assert!(reader
+            .upsert_vector(2, 2.into(), &only_default_vector(&[0.0, 1.0]))
+            .is_err());
+        assert!(matches!(
+            load_segment(&path).err().unwrap(),
+            OperationError::SegmentLocked {
+                holder_pid: None,
+                ..
+            }
+        ));
+        drop(reader);
+
+        // Lock is released on drop
+        let segment = load_segment(&path).unwrap().unwrap();
+        assert_eq!(segment.points_count(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{PayloadIndexInfo, PayloadKeyType};
+
+/// Stats of the segment, which walk its storages, so they are cached between the writes
+///
+/// Segment holds at most [`crate::types::MAX_SEGMENT_POINTS`] points, including the deleted ones,
+/// as the offsets of the points are 32-bit. Inserts over the limit fail with
+/// `OperationError::CapacityExceeded`, the offsets are never wrapped.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SegmentStats {
+    /// Size of the vectors and payloads, kept in memory
+    pub ram_usage_bytes: usize,
+    /// Size of the files in the segment directory
+    pub disk_usage_bytes: usize,
+    pub index_schema: HashMap<PayloadKeyType, PayloadIndexInfo>,
+}
+
+/// Cache of [`SegmentStats`], which is invalidated by every write to the segment
+///
+/// Stats are recomputed lazily by the next read. Reads, concurrent with the recomputation, wait for it,
+/// so the stats are computed once and never returned partially.
+#[derive(Debug, Default)]
+pub struct StatsCache {
+    /// Incremented by each invalidation
+    generation: AtomicU64,
+    /// Stats with the generation, they were computed at
+    cached: Mutex<Option<(u64, SegmentStats)>>,
+    /// Number of the recomputations of the stats
+    computations: AtomicUsize,
+}
+
+impl StatsCache {
+    /// Mark the cached stats as outdated, it is cheap enough to be called by every write
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Cached stats, if they are up to date, or the stats, recomputed by `compute`
+    pub fn get_or_compute(&self, compute: impl FnOnce() -> SegmentStats) -> SegmentStats {
+        let mut cached = self.cached.lock();
+        let generation = self.generation.load(Ordering::Acquire);
+        if let Some((cached_generation, stats)) = cached.as_ref() {
+            if *cached_generation == generation {
+                return stats.clone();
+            }
+        }
+        // Writes during the computation change the generation, so their stats are computed again
+        let stats = compute();
+        self.computations.fetch_add(1, Ordering::Relaxed);
+        *cached = Some((generation, stats.clone()));
+        stats
+    }
+
+    pub fn computations(&self) -> usize {
+        self.computations.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn stats(ram_usage_bytes: usize) -> SegmentStats {
+        SegmentStats {
+            ram_usage_bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_cache() {
+        let cache = StatsCache::default();
+        assert_eq!(cache.get_or_compute(|| stats(1)), stats(1));
+        assert_eq!(cache.get_or_compute(|| stats(2)), stats(1));
+        assert_eq!(cache.computations(), 1);
+
+        cache.invalidate();
+        assert_eq!(cache.get_or_compute(|| stats(3)), stats(3));
+        assert_eq!(cache.computations(), 2);
+
+        // Concurrent reads wait for the single recomputation
+        cache.invalidate();
+        let cache = Arc::new(cache);
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    cache.get_or_compute(|| {
+                        thread::sleep(Duration::from_millis(50));
+                        stats(4)
+                    })
+                })
+            })
+            .collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), stats(4));
+        }
+        assert_eq!(cache.computations(), 3);
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde_json::Value;
 
 use crate::data_types::named_vectors::NamedVectors;
@@ -113,6 +115,33 @@ pub fn rev_range(a: usize, b: usize) -> impl Iterator<Item = usize> {
     (b + 1..=a).rev()
 }
 
+/// Up to `k` random items of the stream, each item has the same probability to be sampled
+///
+/// Reservoir sampling, so only the sample is kept in memory. Same `rng_seed` gives the same sample
+/// of the same stream.
+pub fn reservoir_sample<T>(
+    items: impl Iterator<Item = T>,
+    k: usize,
+    rng_seed: Option<u64>,
+) -> Vec<T> {
+    let mut rng = match rng_seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut sample = Vec::with_capacity(k);
+    for (seen, item) in items.enumerate() {
+        if sample.len() < k {
+            sample.push(item);
+        } else {
+            let position = rng.gen_range(0..=seen);
+            if position < k {
+                sample[position] = item;
+            }
+        }
+    }
+    sample
+}
+
 /// Parse array path and index from path
 ///
 /// return Some((path, Some(index))) if path is an array path with index
@@ -0,0 +1,130 @@
+//! Population of the page cache with the files of the segment, which are accessed through memmaps,
+//! so the first searches after the load don't fault on the cold pages
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::entry::entry_point::OperationResult;
+
+/// Size of the sequential reads of a warmed up file
+const WARM_UP_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Parts of the segment, which are warmed up by [`crate::entry::entry_point::SegmentEntry::warm_up`]
+///
+/// Parts are skipped, if they are kept in memory by the segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmUpParts {
+    /// Memmapped vectors and their quantized versions
+    pub vectors: bool,
+    /// Links of the on-disk HNSW index
+    pub vector_index: bool,
+    /// On-disk payload storage and the payload index
+    pub payload_index: bool,
+    /// Limit of the read rate, if None - files are read as fast as possible
+    pub max_bytes_per_sec: Option<usize>,
+}
+
+impl WarmUpParts {
+    pub fn all() -> Self {
+        WarmUpParts {
+            vectors: true,
+            vector_index: true,
+            payload_index: true,
+            max_bytes_per_sec: None,
+        }
+    }
+}
+
+/// Sequentially read the files or the files in the directories, returns the number of the read bytes
+///
+/// Missing files are skipped, as they may be removed by the concurrent optimization.
+pub fn warm_up_files(
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    max_bytes_per_sec: Option<usize>,
+) -> OperationResult<usize> {
+    let mut warm_up = WarmUp {
+        buffer: vec![0; WARM_UP_CHUNK_BYTES],
+        max_bytes_per_sec,
+        start: Instant::now(),
+        bytes: 0,
+    };
+    for path in paths {
+        warm_up.read_path(path.as_ref())?;
+    }
+    Ok(warm_up.bytes)
+}
+
+struct WarmUp {
+    buffer: Vec<u8>,
+    max_bytes_per_sec: Option<usize>,
+    start: Instant,
+    bytes: usize,
+}
+
+impl WarmUp {
+    fn read_path(&mut self, path: &Path) -> OperationResult<()> {
+        let Ok(metadata) = fs::metadata(path) else {
+            return Ok(());
+        };
+        if metadata.is_dir() {
+            for entry in fs::read_dir(path)? {
+                self.read_path(&entry?.path())?;
+            }
+            return Ok(());
+        }
+
+        let Ok(mut file) = File::open(path) else {
+            return Ok(());
+        };
+        loop {
+            let read = file.read(&mut self.buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.bytes += read;
+            self.throttle();
+        }
+    }
+
+    /// Sleep, until the read rate is within the limit
+    fn throttle(&self) {
+        if let Some(max_bytes_per_sec) = self.max_bytes_per_sec.filter(|limit| *limit > 0) {
+            let expected = Duration::from_secs_f64(self.bytes as f64 / max_bytes_per_sec as f64);
+            let elapsed = self.start.elapsed();
+            if expected > elapsed {
+                thread::sleep(expected - elapsed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_warm_up_files() {
+        let dir = Builder::new().prefix("warm_up_dir").tempdir().unwrap();
+        fs::write(dir.path().join("a"), vec![1u8; 3 * WARM_UP_CHUNK_BYTES / 2]).unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("b"), vec![2u8; 100]).unwrap();
+
+        let expected = 3 * WARM_UP_CHUNK_BYTES / 2 + 100;
+        assert_eq!(warm_up_files([dir.path()], None).unwrap(), expected);
+        assert_eq!(
+            warm_up_files([dir.path().join("missing")], None).unwrap(),
+            0
+        );
+
+        // Reads are throttled by the limit
+        let start = Instant::now();
+        let bytes = warm_up_files([dir.path().join("a")], Some(10 * WARM_UP_CHUNK_BYTES)).unwrap();
+        assert_eq!(bytes, 3 * WARM_UP_CHUNK_BYTES / 2);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
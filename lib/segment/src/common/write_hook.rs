@@ -0,0 +1,44 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use log::error;
+
+use crate::types::{PointIdType, SeqNumberType};
+
+/// Kind of the operation, reported to the write hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WriteOperationKind {
+    /// Upsert of a single point, or of each point of a batch
+    UpsertPoint,
+    DeletePoint,
+    SetFullPayload,
+    SetPayload,
+    DeletePayload,
+    ClearPayload,
+    CreateFieldIndex,
+    DeleteFieldIndex,
+    UpdateIndexConfig,
+    RebuildIndex,
+    UpdateVectorIndexConfig,
+    RebuildVectorIndex,
+}
+
+/// Operation, applied to the segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteEvent {
+    pub kind: WriteOperationKind,
+    /// Point of the operation, None for the operations of the whole segment
+    pub point_id: Option<PointIdType>,
+    pub op_num: SeqNumberType,
+    /// Operation is older than the version of the point or of the segment, so nothing was changed
+    pub skipped: bool,
+}
+
+/// Called after each successful write operation of the segment, see [`crate::segment::Segment::set_on_write_hook`]
+pub type WriteHook = Box<dyn Fn(&WriteEvent) + Send + Sync>;
+
+/// Call the hook, panic of the hook is logged and does not propagate into the segment
+pub(crate) fn call_write_hook(hook: &WriteHook, event: &WriteEvent) {
+    if catch_unwind(AssertUnwindSafe(|| hook(event))).is_err() {
+        error!("Write hook panicked on {event:?}");
+    }
+}
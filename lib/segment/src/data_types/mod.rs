@@ -1,3 +1,4 @@
+pub mod keyword_index;
 pub mod named_vectors;
 pub mod text_index;
 pub mod tiny_map;
@@ -1,23 +1,33 @@
 use std::backtrace::Backtrace;
 use std::collections::HashMap;
-use std::io::Error as IoError;
+use std::fmt::Display;
+use std::io::{Error as IoError, Write};
 use std::path::{Path, PathBuf};
 use std::result;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use atomicwrites::Error as AtomicIoError;
 use rayon::ThreadPoolBuildError;
 use thiserror::Error;
 
 use crate::common::file_operations::FileStorageError;
+use crate::common::search_telemetry::SearchTelemetry;
+use crate::common::warm_up::WarmUpParts;
 use crate::data_types::named_vectors::NamedVectors;
 use crate::data_types::vectors::VectorElementType;
 use crate::index::field_index::CardinalityEstimation;
+use crate::index::hnsw_index::graph_layers::IndexStats;
+use crate::index::index_build_handle::IndexBuildHandle;
+use crate::index::query_plan::QueryPlanExplain;
+use crate::index::SearchCostEstimate;
+use crate::payload_storage::filter_explain::{FilterExplain, FilterMatchCounts};
 use crate::telemetry::SegmentTelemetry;
 use crate::types::{
-    Filter, Payload, PayloadFieldSchema, PayloadKeyType, PayloadKeyTypeRef, PointIdType,
-    ScoredPoint, SearchParams, SegmentConfig, SegmentInfo, SegmentType, SeqNumberType, WithPayload,
-    WithVector,
+    Filter, Indexes, OrderBy, Payload, PayloadFieldSchema, PayloadKeyType, PayloadKeyTypeRef,
+    PayloadSelector, PointGroup, PointIdType, PointOffsetType, ScoredPoint, SearchParams,
+    SegmentConfig, SegmentInfo, SegmentType, SeqNumberType, SnapshotContents, WithPayload,
+    WithVector, MAX_SEGMENT_POINTS,
 };
 
 #[derive(Error, Debug, Clone)]
@@ -41,15 +51,66 @@ pub enum OperationError {
     },
     #[error("Unable to infer type for the field '{field_name}'. Please specify `field_type`")]
     TypeInferenceError { field_name: PayloadKeyType },
+    #[error("Strict filtering: field '{field_name}' is not indexed, filtering by it requires a full scan. Create payload index for it")]
+    UnindexedField { field_name: PayloadKeyType },
+    #[error("Strict filtering: field '{field_name}' is not present in any payload")]
+    UnknownField { field_name: PayloadKeyType },
+    #[error("Ordering by field '{field_name}' requires the integer or float index. Create payload index for it")]
+    UnorderedField { field_name: PayloadKeyType },
     /// Service Error prevents further update of the collection until it is fixed.
     /// Should only be used for hardware, data corruption, IO, or other unexpected internal errors.
+    /// `OutOfMemory`, `Io`, `Serialization` and `InconsistentStorage` errors are treated the same way.
     #[error("Service runtime error: {description}")]
     ServiceError {
         description: String,
         backtrace: Option<String>,
     },
+    #[error("Out of memory: {description}")]
+    OutOfMemory { description: String },
+    /// IO error, shared to keep the error cloneable, e.g. into the [`SegmentFailedState`]
+    #[error("IO error: {description}")]
+    Io {
+        description: String,
+        source: Arc<IoError>,
+    },
+    #[error("Serialization error: {description}")]
+    Serialization { description: String },
+    /// Storages of the segment disagree, e.g. an id is tracked without its vector
+    #[error("Inconsistent storage: {description}")]
+    InconsistentStorage { description: String },
     #[error("Operation cancelled: {description}")]
     Cancelled { description: String },
+    #[error("Segment format version {format_version} is newer than the supported format version {supported_version}. Please upgrade the application")]
+    UnsupportedFormatVersion {
+        format_version: u32,
+        supported_version: u32,
+    },
+    #[error("Segment {} is locked by {}", .path.display(), .holder_pid.map_or_else(|| "another process".to_string(), |pid| format!("process {pid}")))]
+    SegmentLocked {
+        path: PathBuf,
+        holder_pid: Option<u32>,
+    },
+    #[error("Segment {} is encrypted, it can't be opened without the encryption key", .path.display())]
+    EncryptionKeyRequired { path: PathBuf },
+    #[error("Encryption key doesn't match the key of segment {}", .path.display())]
+    WrongEncryptionKey { path: PathBuf },
+    #[error("Field '{field_name}' can't be indexed in the encrypted segment, field indexes are not encrypted")]
+    EncryptedFieldIndex { field_name: PayloadKeyType },
+    #[error("Segment {} is not appendable, it is opened from the snapshot archive", .path.display())]
+    SegmentNotAppendable { path: PathBuf },
+    #[error("Search timed out after {timeout_ms} ms")]
+    Timeout { timeout_ms: u64 },
+    #[error("Index build is estimated to use {estimated_bytes} bytes of memory, which exceeds the budget of {budget_bytes} bytes. Increase `max_build_memory_mb` of the HNSW config or reduce the size of the segments")]
+    MemoryBudgetExceeded {
+        estimated_bytes: usize,
+        budget_bytes: usize,
+    },
+    #[error("Invalid segment config: {description}")]
+    InvalidConfig { description: String },
+    #[error("Unsupported search params: {description}")]
+    UnsupportedSearchParams { description: String },
+    #[error("Segment can't hold more than {capacity} points, including the deleted ones. Optimize the segment or insert into another one")]
+    CapacityExceeded { capacity: usize },
 }
 
 impl OperationError {
@@ -59,8 +120,70 @@ impl OperationError {
             backtrace: Some(Backtrace::force_capture().to_string()),
         }
     }
+
+    pub fn inconsistent_storage(description: impl Into<String>) -> OperationError {
+        OperationError::InconsistentStorage {
+            description: description.into(),
+        }
+    }
+
+    /// Whether the error prevents further updates of the segment, see [`get_service_error`]
+    pub fn is_service_error(&self) -> bool {
+        matches!(
+            self,
+            OperationError::ServiceError { .. }
+                | OperationError::OutOfMemory { .. }
+                | OperationError::Io { .. }
+                | OperationError::Serialization { .. }
+                | OperationError::InconsistentStorage { .. }
+        )
+    }
+
+    /// Prefix the description of the error with the context, e.g. the file or the point involved
+    ///
+    /// Errors without a description, like `WrongVector`, are returned as is.
+    pub fn add_context(mut self, context: impl Display) -> Self {
+        match &mut self {
+            OperationError::ServiceError { description, .. }
+            | OperationError::OutOfMemory { description }
+            | OperationError::Io { description, .. }
+            | OperationError::Serialization { description }
+            | OperationError::InconsistentStorage { description }
+            | OperationError::Cancelled { description } => {
+                *description = format!("{context}: {description}");
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+/// Attaching of the context to the errors, convertible into [`OperationError`]
+///
+/// ```ignore
+/// fs::read(&path).with_context(|| format!("failed to read {path:?}"))?;
+/// ```
+pub trait OperationErrorContext<T> {
+    fn context(self, context: impl Display) -> OperationResult<T>;
+
+    fn with_context<C: Display>(self, context: impl FnOnce() -> C) -> OperationResult<T>;
+}
+
+impl<T, E: Into<OperationError>> OperationErrorContext<T> for result::Result<T, E> {
+    fn context(self, context: impl Display) -> OperationResult<T> {
+        self.map_err(|err| err.into().add_context(context))
+    }
+
+    fn with_context<C: Display>(self, context: impl FnOnce() -> C) -> OperationResult<T> {
+        self.map_err(|err| err.into().add_context(context()))
+    }
 }
 
+/// Check the stop flag of a long-running operation, e.g. an index build, a merge or a consistency check
+///
+/// Operations, accepting the flag, check it between the steps and return `OperationError::Cancelled`,
+/// once it is set. The cancelled operation leaves the segment in its state before the operation:
+/// the results are built aside and installed only after the last check.
 pub fn check_process_stopped(stopped: &AtomicBool) -> OperationResult<()> {
     if stopped.load(Ordering::Relaxed) {
         return Err(OperationError::Cancelled {
@@ -70,6 +193,18 @@ pub fn check_process_stopped(stopped: &AtomicBool) -> OperationResult<()> {
     Ok(())
 }
 
+/// Check, that a segment can hold `num_points` points, returns the number as the offset type
+///
+/// Offsets are not wrapped, points over [`MAX_SEGMENT_POINTS`] are rejected with `OperationError::CapacityExceeded`.
+pub fn check_points_capacity(num_points: usize) -> OperationResult<PointOffsetType> {
+    if num_points > MAX_SEGMENT_POINTS {
+        return Err(OperationError::CapacityExceeded {
+            capacity: MAX_SEGMENT_POINTS,
+        });
+    }
+    Ok(num_points as PointOffsetType)
+}
+
 /// Contains information regarding last operation error, which should be fixed before next operation could be processed
 #[derive(Debug, Clone)]
 pub struct SegmentFailedState {
@@ -114,7 +249,9 @@ impl From<FileStorageError> for OperationError {
 
 impl From<serde_cbor::Error> for OperationError {
     fn from(err: serde_cbor::Error) -> Self {
-        OperationError::service_error(format!("Failed to parse data: {err}"))
+        OperationError::Serialization {
+            description: format!("Failed to parse data: {err}"),
+        }
     }
 }
 
@@ -131,13 +268,18 @@ impl<E> From<AtomicIoError<E>> for OperationError {
 
 impl From<IoError> for OperationError {
     fn from(err: IoError) -> Self {
-        OperationError::service_error(format!("IO Error: {err}"))
+        OperationError::Io {
+            description: err.to_string(),
+            source: Arc::new(err),
+        }
     }
 }
 
 impl From<serde_json::Error> for OperationError {
     fn from(err: serde_json::Error) -> Self {
-        OperationError::service_error(format!("Json error: {err}"))
+        OperationError::Serialization {
+            description: format!("Json error: {err}"),
+        }
     }
 }
 
@@ -149,13 +291,13 @@ impl From<fs_extra::error::Error> for OperationError {
 
 pub type OperationResult<T> = result::Result<T, OperationError>;
 
+/// Shorter alias of [`OperationResult`] for the storage implementations
+pub type Result<T> = OperationResult<T>;
+
 pub fn get_service_error<T>(err: &OperationResult<T>) -> Option<OperationError> {
     match err {
         Ok(_) => None,
-        Err(error) => match error {
-            OperationError::ServiceError { .. } => Some(error.clone()),
-            _ => None,
-        },
+        Err(error) => error.is_service_error().then(|| error.clone()),
     }
 }
 
@@ -167,8 +309,15 @@ pub trait SegmentEntry {
     fn version(&self) -> SeqNumberType;
 
     /// Get version of specified point
+    ///
+    /// Version is the number of the last operation, which changed the vectors or the payload of the point.
+    /// It is persisted with the id tracker, `None` if the point is not in the segment.
     fn point_version(&self, point_id: PointIdType) -> Option<SeqNumberType>;
 
+    /// Search for the `top` points, best first
+    ///
+    /// Scores are postprocessed into the user-facing form of the vector distance,
+    /// and ordered by [`crate::types::Distance::distance_order`].
     #[allow(clippy::too_many_arguments)]
     fn search(
         &self,
@@ -181,6 +330,33 @@ pub trait SegmentEntry {
         params: Option<&SearchParams>,
     ) -> OperationResult<Vec<ScoredPoint>>;
 
+    /// [`SegmentEntry::search`], which also returns the telemetry of the search
+    ///
+    /// Telemetry is gathered only if `with_telemetry` of the params is set, and if the segment supports it.
+    #[allow(clippy::too_many_arguments)]
+    fn search_with_telemetry(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<(Vec<ScoredPoint>, Option<SearchTelemetry>)> {
+        let result = self.search(
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            filter,
+            top,
+            params,
+        )?;
+        Ok((result, None))
+    }
+
+    /// Batched variant of [`SegmentEntry::search`]
     #[allow(clippy::too_many_arguments)]
     fn search_batch(
         &self,
@@ -193,6 +369,25 @@ pub trait SegmentEntry {
         params: Option<&SearchParams>,
     ) -> OperationResult<Vec<Vec<ScoredPoint>>>;
 
+    /// Search for up to `groups` groups of the hits, which share the value of the `group_by` field
+    ///
+    /// Each group holds its best `group_size` hits, groups are ordered by their best hits. Keywords, integers
+    /// and booleans form groups, point with several values is a hit of each of their groups once. Points
+    /// without such a value are in no group.
+    #[allow(clippy::too_many_arguments)]
+    fn search_groups(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        group_by: &PayloadKeyType,
+        group_size: usize,
+        groups: usize,
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<PointGroup>>;
+
     fn upsert_vector(
         &mut self,
         op_num: SeqNumberType,
@@ -200,6 +395,36 @@ pub trait SegmentEntry {
         vectors: &NamedVectors,
     ) -> OperationResult<bool>;
 
+    /// Upsert a batch of points, new points are inserted into a contiguous block of offsets
+    ///
+    /// Returns for each point, if it replaced an existing one.
+    fn upsert_vectors(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+    ) -> OperationResult<Vec<bool>>;
+
+    /// [`SegmentEntry::upsert_vectors`], which also replaces the full payloads of the points
+    ///
+    /// Payload is not changed for the points, which have `None` in `payloads`.
+    /// Returns for each point, if it replaced an existing one.
+    fn upsert_points(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+        payloads: &[Option<&Payload>],
+    ) -> OperationResult<Vec<bool>> {
+        let mut results = self.upsert_vectors(op_num, point_ids, vectors)?;
+        for ((point_id, payload), result) in point_ids.iter().zip(payloads).zip(&mut results) {
+            if let Some(payload) = payload {
+                *result &= self.set_full_payload(op_num, point_id.clone(), payload)?;
+            }
+        }
+        Ok(results)
+    }
+
     fn delete_point(
         &mut self,
         op_num: SeqNumberType,
@@ -241,7 +466,17 @@ pub trait SegmentEntry {
 
     fn all_vectors(&self, point_id: PointIdType) -> OperationResult<NamedVectors>;
 
-    fn payload(&self, point_id: PointIdType) -> OperationResult<Payload>;
+    /// Payload of the point, shared with the segment if possible, so the read is not a deep copy
+    fn payload(&self, point_id: PointIdType) -> OperationResult<Arc<Payload>>;
+
+    /// Fields of the payload of the point, selected by the selector
+    ///
+    /// Fields, which are not selected, are not copied, and are not deserialized, if the storage is on disk.
+    fn selected_payload(
+        &self,
+        point_id: PointIdType,
+        selector: &PayloadSelector,
+    ) -> OperationResult<Payload>;
 
     fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_>;
 
@@ -253,9 +488,39 @@ pub trait SegmentEntry {
         filter: Option<&'a Filter>,
     ) -> Vec<PointIdType>;
 
+    /// Up to `limit` points, which satisfy filtering condition, in the order of the values of the payload field
+    ///
+    /// Returns the points with their values, see [`OrderBy`] for the order. Points with the `start_from` value
+    /// start with `offset` id including, so the next page continues with the first point, which was not returned.
+    fn read_ordered_filtered<'a>(
+        &'a self,
+        limit: Option<usize>,
+        filter: Option<&'a Filter>,
+        order_by: &'a OrderBy,
+        offset: Option<PointIdType>,
+    ) -> OperationResult<Vec<(f64, PointIdType)>>;
+
+    /// Write the points, matching the filter, ordered by id, one JSON object per line, see [`crate::common::jsonl::JsonlPoint`]
+    ///
+    /// Points are streamed into the `writer`, returns the number of exported points.
+    fn export_jsonl(
+        &self,
+        writer: &mut dyn Write,
+        with_vectors: bool,
+        filter: Option<&Filter>,
+    ) -> OperationResult<usize>;
+
     /// Read points in [from; to) range
     fn read_range(&self, from: Option<PointIdType>, to: Option<PointIdType>) -> Vec<PointIdType>;
 
+    /// Up to `k` distinct random points, matching the filter, same `rng_seed` gives the same sample
+    fn sample_ids<'a>(
+        &'a self,
+        filter: Option<&'a Filter>,
+        k: usize,
+        rng_seed: Option<u64>,
+    ) -> Vec<PointIdType>;
+
     /// Check if there is point with `point_id` in this segment.
     fn has_point(&self, point_id: PointIdType) -> bool;
 
@@ -311,6 +576,39 @@ pub trait SegmentEntry {
         field_schema: Option<&PayloadFieldSchema>,
     ) -> OperationResult<bool>;
 
+    /// Change config of the vector index
+    ///
+    /// New config is persisted and the current index is reported as outdated in `info()`.
+    /// If `rebuild` is false, the current index keeps serving searches until [`SegmentEntry::rebuild_index`].
+    fn update_index_config(
+        &mut self,
+        op_num: SeqNumberType,
+        config: Indexes,
+        rebuild: bool,
+    ) -> OperationResult<bool>;
+
+    /// Build vector index from scratch over the current non-deleted points
+    ///
+    /// Config, set by [`SegmentEntry::update_index_config`], is applied, if any.
+    /// The current index is replaced only after the new one is built.
+    /// Returns `OperationError::Cancelled`, if `stopped` is set during the build.
+    fn rebuild_index(
+        &mut self,
+        op_num: SeqNumberType,
+        stopped: Option<&AtomicBool>,
+    ) -> OperationResult<bool>;
+
+    /// Start building vector index with the given config in a background thread
+    ///
+    /// Searches keep using the current index until the new one is installed with
+    /// [`SegmentEntry::install_built_index`]. Updates are not blocked for the whole build.
+    fn build_index_in_background(&self, config: Indexes) -> OperationResult<IndexBuildHandle>;
+
+    /// Wait for the background build to finish and replace the current index with the built one
+    ///
+    /// Returns false, if the build was cancelled. On any failure the current index is kept.
+    fn install_built_index(&mut self, handle: IndexBuildHandle) -> OperationResult<bool>;
+
     /// Get indexed fields
     fn get_indexed_fields(&self) -> HashMap<PayloadKeyType, PayloadFieldSchema>;
 
@@ -324,11 +622,105 @@ pub trait SegmentEntry {
         filter: &'a Filter,
     ) -> OperationResult<usize>;
 
+    /// Explain evaluation of the filter against the point
+    fn explain_filter(
+        &self,
+        filter: &Filter,
+        point_id: PointIdType,
+    ) -> OperationResult<FilterExplain>;
+
+    /// Count points, matched by each condition of the filter
+    fn explain_filter_counts(&self, filter: &Filter) -> OperationResult<FilterMatchCounts>;
+
+    /// Estimate mean recall@`top` of the vector index
+    ///
+    /// Vectors of up to `sample_queries` random points are used as queries.
+    /// Results of the index search with `params` are compared with the exact search.
+    fn estimate_recall(
+        &self,
+        vector_name: &str,
+        sample_queries: usize,
+        top: usize,
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<f64>;
+
+    /// Statistics of the HNSW graph of the vector, `None` if there is no graph
+    ///
+    /// Reachability of the points is only checked if `check_reachability` is set, as it is
+    /// expensive.
+    fn index_stats(
+        &self,
+        vector_name: &str,
+        check_reachability: bool,
+    ) -> OperationResult<Option<IndexStats>>;
+
+    /// Populate the page cache with the selected memmapped files, returns the number of the read bytes
+    ///
+    /// Parts, kept in memory, are skipped, so it is a no-op for fully in-memory segments.
+    /// Only a read access to the segment is needed, so it may run in the background along with the searches.
+    fn warm_up(&self, parts: WarmUpParts) -> OperationResult<usize>;
+
+    /// Plan of [`SegmentEntry::search`], chosen by the vector index without scoring any points
+    fn explain_search(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<QueryPlanExplain>;
+
+    /// Estimate the cost of [`SegmentEntry::search`] of a single vector, without touching the vectors
+    fn estimate_search_cost(
+        &self,
+        vector_name: &str,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<SearchCostEstimate>;
+
     /// Take a snapshot of the segment.
     ///
     /// Creates a tar archive of the segment directory into `snapshot_dir_path`.
-    fn take_snapshot(&self, snapshot_dir_path: &Path) -> OperationResult<PathBuf>;
+    /// Partial snapshot includes only the selected `contents`, and records them in the segment state.
+    fn take_snapshot(
+        &self,
+        snapshot_dir_path: &Path,
+        contents: SnapshotContents,
+    ) -> OperationResult<PathBuf>;
 
     // Get collected telemetry data of segment
     fn get_telemetry_data(&self) -> SegmentTelemetry;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_error_context() {
+        let result: result::Result<(), IoError> = Err(IoError::new(ErrorKind::NotFound, "missing"));
+        let error = result.context("failed to read vectors.dat").unwrap_err();
+        assert!(matches!(error, OperationError::Io { .. }));
+        assert_eq!(
+            error.to_string(),
+            "IO error: failed to read vectors.dat: missing"
+        );
+        // IO errors stop the updates of the segment, like the service errors
+        assert!(get_service_error(&OperationResult::<()>::Err(error)).is_some());
+
+        let error: OperationResult<()> = Err(OperationError::WrongVector {
+            expected_dim: 4,
+            received_dim: 3,
+        });
+        let error = error.with_context(|| "point 10").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Vector inserting error: expected dim: 4, got 3"
+        );
+        assert!(!error.is_service_error());
+    }
+}
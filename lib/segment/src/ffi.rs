@@ -0,0 +1,292 @@
+//! C interface of the segment, enabled by the `ffi` feature
+//!
+//! The shared library is built with `cargo rustc -p segment --features ffi --lib --crate-type cdylib`,
+//! the header is generated with `cbindgen` into `include/segment_ffi.h`, see `cbindgen.toml`.
+//!
+//! Segment is passed around as an opaque [`SegmentHandle`]. Functions return [`SegmentStatus`],
+//! the message of the last failure of the calling thread is returned by [`segment_last_error_message`].
+//! Panics are caught at the boundary and reported as [`SegmentStatus::Panic`].
+//! Only the default vector and the numeric point ids are supported.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::{ptr, slice};
+
+use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+use crate::entry::entry_point::{OperationError, SegmentEntry};
+use crate::segment::Segment;
+use crate::segment_constructor::{build_segment, load_segment};
+use crate::types::{ExtendedPointId, SegmentConfig, WithPayload};
+
+/// Status of the call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentStatus {
+    Ok = 0,
+    /// Null pointer, string which is not UTF-8, malformed config or wrong vector dimension
+    InvalidArgument = 1,
+    /// No segment in the directory
+    NotFound = 2,
+    /// Operation of the segment failed
+    OperationFailed = 3,
+    /// Panic was caught at the boundary, the handle should not be used anymore
+    Panic = 4,
+}
+
+/// Opaque segment, owned by the caller until it is passed to [`segment_free`]
+pub struct SegmentHandle {
+    segment: Segment,
+}
+
+/// Failure of the call, converted into the status and the last error message
+struct FfiError {
+    status: SegmentStatus,
+    message: String,
+}
+
+impl FfiError {
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Self {
+            status: SegmentStatus::InvalidArgument,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<OperationError> for FfiError {
+    fn from(err: OperationError) -> Self {
+        let status = match err {
+            OperationError::WrongVector { .. }
+            | OperationError::InvalidConfig { .. }
+            | OperationError::UnsupportedSearchParams { .. }
+            | OperationError::VectorNameNotExists { .. } => SegmentStatus::InvalidArgument,
+            _ => SegmentStatus::OperationFailed,
+        };
+        Self {
+            status,
+            message: err.to_string(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: String) {
+    // Interior nul bytes would truncate the message on the C side anyway
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Run the call, catching the panics, and record the message of the failure
+fn ffi_call(call: impl FnOnce() -> Result<(), FfiError>) -> SegmentStatus {
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => SegmentStatus::Ok,
+        Ok(Err(err)) => {
+            set_last_error(err.message);
+            err.status
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {message}"));
+            SegmentStatus::Panic
+        }
+    }
+}
+
+unsafe fn path_arg<'a>(path: *const c_char) -> Result<&'a Path, FfiError> {
+    if path.is_null() {
+        return Err(FfiError::invalid_argument("path is null"));
+    }
+    CStr::from_ptr(path)
+        .to_str()
+        .map(Path::new)
+        .map_err(|_| FfiError::invalid_argument("path is not valid UTF-8"))
+}
+
+unsafe fn handle_arg<'a>(handle: *mut SegmentHandle) -> Result<&'a mut SegmentHandle, FfiError> {
+    handle
+        .as_mut()
+        .ok_or_else(|| FfiError::invalid_argument("segment handle is null"))
+}
+
+unsafe fn vector_arg<'a>(vector: *const f32, dim: usize) -> Result<&'a [f32], FfiError> {
+    if vector.is_null() {
+        return Err(FfiError::invalid_argument("vector is null"));
+    }
+    Ok(slice::from_raw_parts(vector, dim))
+}
+
+fn write_handle(out: *mut *mut SegmentHandle, segment: Segment) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::invalid_argument("output handle is null"));
+    }
+    let handle = Box::into_raw(Box::new(SegmentHandle { segment }));
+    // SAFETY: checked for null, the caller provides a writable pointer
+    unsafe { *out = handle };
+    Ok(())
+}
+
+/// Build a new segment in a subdirectory of `path`, with the config, serialized into JSON
+///
+/// # Safety
+///
+/// `path` and `config_json` are nul-terminated strings, `out` is a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn segment_create(
+    path: *const c_char,
+    config_json: *const c_char,
+    out: *mut *mut SegmentHandle,
+) -> SegmentStatus {
+    ffi_call(|| {
+        let path = path_arg(path)?;
+        if config_json.is_null() {
+            return Err(FfiError::invalid_argument("config is null"));
+        }
+        let config: SegmentConfig = serde_json::from_slice(CStr::from_ptr(config_json).to_bytes())
+            .map_err(|err| FfiError::invalid_argument(format!("malformed config: {err}")))?;
+        write_handle(out, build_segment(path, &config)?)
+    })
+}
+
+/// Load the segment from its directory
+///
+/// # Safety
+///
+/// `path` is a nul-terminated string, `out` is a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn segment_open(
+    path: *const c_char,
+    out: *mut *mut SegmentHandle,
+) -> SegmentStatus {
+    ffi_call(|| {
+        let path = path_arg(path)?;
+        let segment = load_segment(path)?.ok_or_else(|| FfiError {
+            status: SegmentStatus::NotFound,
+            message: format!("no segment in {}", path.display()),
+        })?;
+        write_handle(out, segment)
+    })
+}
+
+/// Insert or replace the vector of the point, as the next operation of the segment
+///
+/// # Safety
+///
+/// `handle` is returned by [`segment_create`] or [`segment_open`], `vector` points to `dim` floats.
+#[no_mangle]
+pub unsafe extern "C" fn segment_upsert(
+    handle: *mut SegmentHandle,
+    id: u64,
+    vector: *const f32,
+    dim: usize,
+) -> SegmentStatus {
+    ffi_call(|| {
+        let segment = &mut handle_arg(handle)?.segment;
+        let vector = vector_arg(vector, dim)?;
+        let op_num = segment.version() + 1;
+        segment.upsert_vector(op_num, id.into(), &only_default_vector(vector))?;
+        Ok(())
+    })
+}
+
+/// Search for the `top` closest points, `out_found` is set to the number of the found ones
+///
+/// # Safety
+///
+/// `handle` is returned by [`segment_create`] or [`segment_open`], `vector` points to `dim` floats,
+/// `out_ids` and `out_scores` point to `top` elements each, `out_found` is a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn segment_search(
+    handle: *const SegmentHandle,
+    vector: *const f32,
+    dim: usize,
+    top: usize,
+    out_ids: *mut u64,
+    out_scores: *mut f32,
+    out_found: *mut usize,
+) -> SegmentStatus {
+    ffi_call(|| {
+        let segment = &handle
+            .as_ref()
+            .ok_or_else(|| FfiError::invalid_argument("segment handle is null"))?
+            .segment;
+        let vector = vector_arg(vector, dim)?;
+        if out_ids.is_null() || out_scores.is_null() || out_found.is_null() {
+            return Err(FfiError::invalid_argument("output pointer is null"));
+        }
+        let found = segment.search(
+            DEFAULT_VECTOR_NAME,
+            vector,
+            &WithPayload::default(),
+            &false.into(),
+            None,
+            top,
+            None,
+        )?;
+        let ids = slice::from_raw_parts_mut(out_ids, top);
+        let scores = slice::from_raw_parts_mut(out_scores, top);
+        for (position, point) in found.iter().enumerate() {
+            ids[position] = match &point.id {
+                ExtendedPointId::NumId(id) => *id,
+                id @ (ExtendedPointId::Uuid(_) | ExtendedPointId::Str(_)) => {
+                    return Err(FfiError {
+                        status: SegmentStatus::OperationFailed,
+                        message: format!("point id {id} is not numeric"),
+                    })
+                }
+            };
+            scores[position] = point.score;
+        }
+        *out_found = found.len();
+        Ok(())
+    })
+}
+
+/// Persist the applied operations
+///
+/// # Safety
+///
+/// `handle` is returned by [`segment_create`] or [`segment_open`].
+#[no_mangle]
+pub unsafe extern "C" fn segment_flush(handle: *mut SegmentHandle) -> SegmentStatus {
+    ffi_call(|| {
+        handle_arg(handle)?.segment.flush(true)?;
+        Ok(())
+    })
+}
+
+/// Flush and close the segment, null is ignored
+///
+/// # Safety
+///
+/// `handle` is returned by [`segment_create`] or [`segment_open`], and is not used after the call.
+#[no_mangle]
+pub unsafe extern "C" fn segment_free(handle: *mut SegmentHandle) {
+    if handle.is_null() {
+        return;
+    }
+    let handle = Box::from_raw(handle);
+    // Panics of the drop must not unwind into the caller
+    let _ = catch_unwind(AssertUnwindSafe(move || drop(handle)));
+}
+
+/// Message of the last failed call of the thread, null if no call failed
+///
+/// The string is owned by the library, and is valid until the next failed call of the thread.
+#[no_mangle]
+pub extern "C" fn segment_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
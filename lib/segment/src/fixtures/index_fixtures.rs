@@ -63,6 +63,10 @@ where
         point < self.points_count && !self.data.deleted[point as usize]
     }
 
+    fn is_deleted(&self, point: PointOffsetType) -> bool {
+        point < self.points_count && self.data.deleted[point as usize]
+    }
+
     fn score_point(&self, point: PointOffsetType) -> ScoreType {
         let other_vector = self.data.vectors.get(point);
         TMetric::similarity(&self.query, other_vector)
@@ -2,3 +2,5 @@ pub mod index_fixtures;
 pub mod payload_context_fixture;
 pub mod payload_fixtures;
 pub mod segment_fixtures;
+#[cfg(feature = "testing")]
+pub mod test_utils;
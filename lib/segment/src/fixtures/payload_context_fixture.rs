@@ -1,8 +1,8 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
 use bitvec::vec::BitVec;
+use parking_lot::RwLock;
 use rand::prelude::StdRng;
 use rand::SeedableRng;
 
@@ -52,13 +52,29 @@ impl IdTracker for FixtureIdTracker {
         Ok(())
     }
 
+    fn deleted_version(&self, _external_id: PointIdType) -> Option<SeqNumberType> {
+        None
+    }
+
+    fn set_deleted_version(
+        &mut self,
+        _external_id: PointIdType,
+        _version: SeqNumberType,
+    ) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn purge_deleted_versions(&mut self, _persisted_version: SeqNumberType) -> OperationResult<()> {
+        Ok(())
+    }
+
     fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType> {
         Some(match external_id {
             PointIdType::NumId(id) => {
                 assert!(id < self.ids.len() as u64);
                 id as PointOffsetType
             }
-            PointIdType::Uuid(_) => unreachable!(),
+            PointIdType::Uuid(_) | PointIdType::Str(_) => unreachable!(),
         })
     }
 
@@ -71,6 +87,13 @@ impl IdTracker for FixtureIdTracker {
         &mut self,
         _external_id: PointIdType,
         _internal_id: PointOffsetType,
+    ) -> OperationResult<Option<PointOffsetType>> {
+        Ok(None)
+    }
+
+    fn rebuild_from(
+        &mut self,
+        _mappings: &mut dyn Iterator<Item = (PointIdType, PointOffsetType)>,
     ) -> OperationResult<()> {
         Ok(())
     }
@@ -102,7 +125,7 @@ impl IdTracker for FixtureIdTracker {
             None => 0,
             Some(id) => match id {
                 PointIdType::NumId(num) => num,
-                PointIdType::Uuid(_) => unreachable!(),
+                PointIdType::Uuid(_) | PointIdType::Str(_) => unreachable!(),
             },
         } as PointOffsetType;
 
@@ -140,6 +163,10 @@ impl IdTracker for FixtureIdTracker {
         Box::new(|| Ok(()))
     }
 
+    fn disk_usage(&self) -> OperationResult<usize> {
+        Ok(0)
+    }
+
     fn is_deleted(&self, key: PointOffsetType) -> bool {
         let key = key as usize;
         if key >= self.deleted.len() {
@@ -190,10 +217,10 @@ fn create_payload_storage_fixture(num_points: usize, seed: u64) -> InMemoryPaylo
 ///
 pub fn create_plain_payload_index(path: &Path, num_points: usize, seed: u64) -> PlainPayloadIndex {
     let payload_storage = create_payload_storage_fixture(num_points, seed);
-    let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(num_points)));
+    let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(num_points)));
 
     let condition_checker = Arc::new(SimpleConditionChecker::new(
-        Arc::new(AtomicRefCell::new(payload_storage.into())),
+        Arc::new(RwLock::new(payload_storage.into())),
         id_tracker.clone(),
     ));
 
@@ -217,12 +244,12 @@ pub fn create_struct_payload_index(
     num_points: usize,
     seed: u64,
 ) -> StructPayloadIndex {
-    let payload_storage = Arc::new(AtomicRefCell::new(
+    let payload_storage = Arc::new(RwLock::new(
         create_payload_storage_fixture(num_points, seed).into(),
     ));
-    let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(num_points)));
+    let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(num_points)));
 
-    let mut index = StructPayloadIndex::open(payload_storage, id_tracker, path).unwrap();
+    let mut index = StructPayloadIndex::open(payload_storage, id_tracker, path, 0).unwrap();
 
     index
         .set_indexed(STR_KEY, PayloadSchemaType::Keyword.into())
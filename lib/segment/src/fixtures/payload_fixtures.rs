@@ -221,6 +221,43 @@ pub fn random_filter<R: Rng + ?Sized>(rnd_gen: &mut R, total_conditions: usize)
     }
 }
 
+fn random_redundant_clause<R: Rng + ?Sized>(
+    rnd_gen: &mut R,
+    depth: usize,
+) -> Option<Vec<Condition>> {
+    match rnd_gen.gen_range(0..4) {
+        0 => None,
+        1 => Some(vec![]),
+        _ => {
+            let num_conditions = rnd_gen.gen_range(1..=3);
+            let mut conditions = (0..num_conditions)
+                .map(|_| {
+                    if depth > 0 && rnd_gen.gen_bool(0.4) {
+                        Condition::Filter(random_redundant_filter(rnd_gen, depth - 1))
+                    } else {
+                        random_condition(rnd_gen)
+                    }
+                })
+                .collect_vec();
+            if rnd_gen.gen_bool(0.3) {
+                let duplicate = conditions.choose(rnd_gen).unwrap().clone();
+                conditions.push(duplicate);
+            }
+            Some(conditions)
+        }
+    }
+}
+
+/// Random filter tree with redundant structure: empty clauses, duplicated conditions,
+/// single-child and same-type nested filters
+pub fn random_redundant_filter<R: Rng + ?Sized>(rnd_gen: &mut R, depth: usize) -> Filter {
+    Filter {
+        should: random_redundant_clause(rnd_gen, depth),
+        must: random_redundant_clause(rnd_gen, depth),
+        must_not: random_redundant_clause(rnd_gen, depth),
+    }
+}
+
 pub fn random_nested_filter<R: Rng + ?Sized>(rnd_gen: &mut R) -> Filter {
     let nested_or_proj: bool = rnd_gen.gen();
     let nested_str_key = if nested_or_proj {
@@ -0,0 +1,270 @@
+//! Segments with deterministic random data, and the brute-force references to check the searches against
+//!
+//! Enabled by the `testing` feature, so the integration tests, the benchmarks and the dependent crates share them.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde_json::{json, Map, Value};
+
+use crate::data_types::vectors::{only_default_vector, VectorElementType, DEFAULT_VECTOR_NAME};
+use crate::entry::entry_point::SegmentEntry;
+use crate::fixtures::payload_fixtures::random_vector;
+use crate::segment::Segment;
+use crate::segment_constructor::build_segment;
+use crate::types::{
+    Condition, Distance, FieldCondition, Filter, Indexes, Match, MatchValue, Payload,
+    PayloadKeyType, PayloadSchemaType, PointIdType, Range as RangeCondition, ScoreType,
+    SegmentConfig, ValueVariants, VectorDataConfig,
+};
+
+/// Values of a field of the generated payloads
+#[derive(Debug, Clone)]
+pub enum FieldSpec {
+    /// One of the keywords
+    Keyword(Vec<String>),
+    /// Integer from the range
+    Integer(Range<i64>),
+    /// Float from the range
+    Float(Range<f64>),
+}
+
+impl FieldSpec {
+    fn schema(&self) -> PayloadSchemaType {
+        match self {
+            FieldSpec::Keyword(_) => PayloadSchemaType::Keyword,
+            FieldSpec::Integer(_) => PayloadSchemaType::Integer,
+            FieldSpec::Float(_) => PayloadSchemaType::Float,
+        }
+    }
+
+    fn random_value<R: Rng + ?Sized>(&self, rnd_gen: &mut R) -> Value {
+        match self {
+            FieldSpec::Keyword(keywords) => json!(keywords.choose(rnd_gen).unwrap()),
+            FieldSpec::Integer(range) => json!(rnd_gen.gen_range(range.clone())),
+            FieldSpec::Float(range) => json!(rnd_gen.gen_range(range.clone())),
+        }
+    }
+}
+
+/// Schema of the generated payloads, each field is missing from a payload with `missing_probability`
+#[derive(Debug, Clone, Default)]
+pub struct PayloadSpec {
+    pub fields: Vec<(PayloadKeyType, FieldSpec)>,
+    pub missing_probability: f64,
+}
+
+impl PayloadSpec {
+    pub fn random_payload<R: Rng + ?Sized>(&self, rnd_gen: &mut R) -> Payload {
+        let mut payload = Map::new();
+        for (key, field) in &self.fields {
+            if !rnd_gen.gen_bool(self.missing_probability) {
+                payload.insert(key.clone(), field.random_value(rnd_gen));
+            }
+        }
+        Payload(payload)
+    }
+
+    /// Match or range condition on one of the fields, with the values, which occur in the payloads
+    pub fn random_condition<R: Rng + ?Sized>(&self, rnd_gen: &mut R) -> Condition {
+        let (key, field) = self
+            .fields
+            .choose(rnd_gen)
+            .expect("payload spec has no fields");
+        let key = key.clone();
+        let condition = match field {
+            FieldSpec::Keyword(keywords) => {
+                FieldCondition::new_match(key, keywords.choose(rnd_gen).unwrap().clone().into())
+            }
+            FieldSpec::Integer(range) if rnd_gen.gen() => {
+                FieldCondition::new_match(key, rnd_gen.gen_range(range.clone()).into())
+            }
+            FieldSpec::Integer(range) => FieldCondition::new_range(
+                key,
+                random_range(rnd_gen, range.start as f64..range.end as f64),
+            ),
+            FieldSpec::Float(range) => {
+                FieldCondition::new_range(key, random_range(rnd_gen, range.clone()))
+            }
+        };
+        Condition::Field(condition)
+    }
+
+    /// Filter of `num_conditions` conditions, randomly split between `must`, `should` and `must_not`
+    pub fn random_filter<R: Rng + ?Sized>(&self, rnd_gen: &mut R, num_conditions: usize) -> Filter {
+        let mut clauses: [Vec<Condition>; 3] = Default::default();
+        for _ in 0..num_conditions {
+            clauses[rnd_gen.gen_range(0..3)].push(self.random_condition(rnd_gen));
+        }
+        let [must, should, must_not] = clauses.map(|conditions| {
+            if conditions.is_empty() {
+                None
+            } else {
+                Some(conditions)
+            }
+        });
+        Filter {
+            should,
+            must,
+            must_not,
+        }
+    }
+}
+
+/// Range, starting in `values`, with one or both of the bounds
+fn random_range<R: Rng + ?Sized>(rnd_gen: &mut R, values: Range<f64>) -> RangeCondition {
+    let start = rnd_gen.gen_range(values.clone());
+    let end = start + rnd_gen.gen_range(0.0..values.end - values.start);
+    RangeCondition {
+        lt: None,
+        gt: None,
+        gte: rnd_gen.gen_bool(0.8).then_some(start),
+        lte: rnd_gen.gen_bool(0.8).then_some(end),
+    }
+}
+
+/// Segment with the default vector of `dim` and the plain index, without points
+pub fn empty_segment(path: &Path, dim: usize, distance: Distance) -> Segment {
+    let config = SegmentConfig {
+        vector_data: HashMap::from([(
+            DEFAULT_VECTOR_NAME.to_owned(),
+            VectorDataConfig {
+                size: dim,
+                distance,
+                hnsw_config: None,
+                quantization_config: None,
+            },
+        )]),
+        index: Indexes::Plain {},
+        ..Default::default()
+    };
+    build_segment(path, &config).unwrap()
+}
+
+/// Segment with `num_points` random points, which are the same for the same `seed`
+///
+/// Point ids are `0..num_points`, the payloads follow `payload_spec`, and all the fields of the spec are indexed.
+pub fn random_segment(
+    path: &Path,
+    dim: usize,
+    distance: Distance,
+    num_points: usize,
+    payload_spec: &PayloadSpec,
+    seed: u64,
+) -> Segment {
+    let mut rnd_gen = StdRng::seed_from_u64(seed);
+    let mut segment = empty_segment(path, dim, distance);
+    let mut op_num = 0;
+    for (key, field) in &payload_spec.fields {
+        op_num += 1;
+        segment
+            .create_field_index(op_num, key, Some(&field.schema().into()))
+            .unwrap();
+    }
+    for point_id in 0..num_points as u64 {
+        op_num += 1;
+        let vector = random_vector(&mut rnd_gen, dim);
+        segment
+            .upsert_vector(op_num, point_id.into(), &only_default_vector(&vector))
+            .unwrap();
+        let payload = payload_spec.random_payload(&mut rnd_gen);
+        segment
+            .set_full_payload(op_num, point_id.into(), &payload)
+            .unwrap();
+    }
+    segment
+}
+
+/// Check the filter against the payload directly, without the payload index
+///
+/// Only the conditions, generated by [`PayloadSpec::random_condition`], are supported.
+pub fn reference_check(filter: &Filter, payload: &Payload) -> bool {
+    let check = |condition: &Condition| reference_check_condition(condition, payload);
+    filter.must.iter().flatten().all(check)
+        && !filter.must_not.iter().flatten().any(check)
+        && filter
+            .should
+            .as_ref()
+            .map_or(true, |should| should.is_empty() || should.iter().any(check))
+}
+
+fn reference_check_condition(condition: &Condition, payload: &Payload) -> bool {
+    let Condition::Field(field_condition) = condition else {
+        panic!("unsupported condition {condition:?}");
+    };
+    let Some(value) = payload.0.get(&field_condition.key) else {
+        return false;
+    };
+    if let Some(r#match) = &field_condition.r#match {
+        return match (r#match, value) {
+            (
+                Match::Value(MatchValue {
+                    value: ValueVariants::Keyword(keyword),
+                }),
+                Value::String(value),
+            ) => keyword == value,
+            (
+                Match::Value(MatchValue {
+                    value: ValueVariants::Integer(integer),
+                }),
+                value,
+            ) => value.as_i64() == Some(*integer),
+            _ => panic!("unsupported match {:?}", r#match),
+        };
+    }
+    if let Some(range) = &field_condition.range {
+        let Some(value) = value.as_f64() else {
+            return false;
+        };
+        return range.lt.map_or(true, |lt| value < lt)
+            && range.gt.map_or(true, |gt| value > gt)
+            && range.gte.map_or(true, |gte| value >= gte)
+            && range.lte.map_or(true, |lte| value <= lte);
+    }
+    panic!("unsupported condition {condition:?}")
+}
+
+/// Top of the points of the default vector by brute force, to measure the recall of the searches against
+///
+/// Scores are postprocessed, like the scores of [`SegmentEntry::search`]. Points with equal scores are ordered by id.
+pub fn reference_search(
+    segment: &Segment,
+    vector: &[VectorElementType],
+    filter: Option<&Filter>,
+    top: usize,
+) -> Vec<(PointIdType, ScoreType)> {
+    let distance = segment.segment_config.vector_data[DEFAULT_VECTOR_NAME].distance;
+    let query = distance
+        .preprocess_vector(vector)
+        .unwrap_or_else(|| vector.to_vec());
+    let mut scored: Vec<_> = segment
+        .iter_points()
+        .filter(|point_id| {
+            filter.map_or(true, |filter| {
+                reference_check(filter, &segment.payload(point_id.clone()).unwrap())
+            })
+        })
+        .map(|point_id| {
+            let stored = segment
+                .vector(DEFAULT_VECTOR_NAME, point_id.clone())
+                .unwrap();
+            (point_id, distance.similarity(&query, &stored))
+        })
+        .collect();
+    scored.sort_by(|(id1, score1), (id2, score2)| {
+        score2
+            .partial_cmp(score1)
+            .unwrap_or(Ordering::Equal)
+            .then(id1.cmp(id2))
+    });
+    scored.truncate(top);
+    scored
+        .into_iter()
+        .map(|(point_id, score)| (point_id, distance.postprocess_score(score)))
+        .collect()
+}
@@ -0,0 +1,638 @@
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use bincode;
+use bitvec::vec::BitVec;
+use itertools::Itertools;
+use parking_lot::RwLock;
+use rocksdb::DB;
+
+use crate::common::rocksdb_wrapper::{DatabaseColumnWrapper, DB_MAPPING_CF, DB_VERSIONS_CF};
+use crate::common::Flusher;
+use crate::entry::entry_point::OperationResult;
+use crate::id_tracker::id_tracker_base::lookup_sorted;
+use crate::id_tracker::simple_id_tracker::SimpleIdTracker;
+use crate::id_tracker::IdTracker;
+use crate::types::{PointIdType, PointOffsetType, SeqNumberType};
+
+/// Pending links are merged into the sorted index, once they exceed this fraction of it
+const COMPACTION_RATIO: usize = 16;
+
+/// Min number of pending links and tombstones, which triggers compaction
+const MIN_COMPACTION_SIZE: usize = 1024;
+
+/// Id tracker for large segments, which keeps the external to internal mapping as a sorted index
+///
+/// Instead of maps of external ids, only offsets are kept, sorted by their external ids,
+/// so a lookup is a binary search over `internal_to_external`. The index takes 4 bytes per point,
+/// which is several times less than ordered maps of ids, at the cost of slower lookups.
+///
+/// Newly linked points are collected in a small ordered map, and dropped points stay in the index
+/// as tombstones, until compaction merges both into the index.
+/// Storage format is the same as in [`SimpleIdTracker`], so a segment can be opened with either.
+pub struct CompactIdTracker {
+    deleted: BitVec,
+    /// External ids of dropped points are kept, as the sorted index may still refer to them
+    internal_to_external: Vec<PointIdType>,
+    internal_to_version: Vec<SeqNumberType>,
+    /// Offsets of the points, ordered by their external ids
+    sorted_index: Vec<PointOffsetType>,
+    /// Number of dropped points, which are still present in `sorted_index`
+    index_tombstones: usize,
+    /// Links, not merged into `sorted_index` yet
+    pending_links: BTreeMap<PointIdType, PointOffsetType>,
+    deleted_versions: BTreeMap<PointIdType, SeqNumberType>,
+    mapping_db_wrapper: DatabaseColumnWrapper,
+    versions_db_wrapper: DatabaseColumnWrapper,
+}
+
+impl CompactIdTracker {
+    pub fn open(store: Arc<RwLock<DB>>) -> OperationResult<Self> {
+        let mut deleted = BitVec::new();
+        let mut internal_to_external: Vec<PointIdType> = Default::default();
+
+        let mapping_db_wrapper = DatabaseColumnWrapper::new(store.clone(), DB_MAPPING_CF);
+        for (key, val) in mapping_db_wrapper.lock_db().iter()? {
+            let external_id = SimpleIdTracker::restore_key(&key);
+            let internal_id = bincode::deserialize::<PointOffsetType>(&val).unwrap() as usize;
+            if internal_id >= internal_to_external.len() {
+                internal_to_external.resize(internal_id + 1, PointIdType::NumId(u64::MAX));
+            }
+            if internal_id >= deleted.len() {
+                deleted.resize(internal_id + 1, true);
+            }
+            if !deleted[internal_id] {
+                // Fixing corrupted mapping - this id should be recovered from WAL
+                log::warn!(
+                    "removing duplicated external id {} in internal id {}",
+                    internal_to_external[internal_id],
+                    internal_id
+                );
+            }
+            internal_to_external[internal_id] = external_id;
+            deleted.set(internal_id, false);
+        }
+
+        let mut sorted_index: Vec<PointOffsetType> = (0..internal_to_external.len())
+            .filter(|internal_id| !deleted[*internal_id])
+            .map(|internal_id| internal_id as PointOffsetType)
+            .collect();
+        sorted_index.sort_unstable_by(|a, b| {
+            internal_to_external[*a as usize].cmp(&internal_to_external[*b as usize])
+        });
+        sorted_index.shrink_to_fit();
+
+        let mut id_tracker = CompactIdTracker {
+            deleted,
+            internal_to_external,
+            internal_to_version: Default::default(),
+            sorted_index,
+            index_tombstones: 0,
+            pending_links: Default::default(),
+            deleted_versions: Default::default(),
+            mapping_db_wrapper,
+            versions_db_wrapper: DatabaseColumnWrapper::new(store, DB_VERSIONS_CF),
+        };
+
+        for (key, val) in id_tracker.versions_db_wrapper.lock_db().iter()? {
+            let external_id = SimpleIdTracker::restore_key(&key);
+            let version: SeqNumberType = bincode::deserialize(&val).unwrap();
+            if let Some(internal_id) = id_tracker.internal_id(external_id.clone()) {
+                let internal_id = internal_id as usize;
+                if internal_id >= id_tracker.internal_to_version.len() {
+                    id_tracker.internal_to_version.resize(internal_id + 1, 0);
+                }
+                id_tracker.internal_to_version[internal_id] = version;
+            } else {
+                // Version of the deleted point
+                id_tracker.deleted_versions.insert(external_id, version);
+            }
+        }
+
+        Ok(id_tracker)
+    }
+
+    /// Merge pending links into the sorted index and remove tombstones from it
+    pub fn compact(&mut self) {
+        let mut compacted = Vec::with_capacity(self.points_count());
+        let deleted = &self.deleted;
+        let internal_to_external = &self.internal_to_external;
+        let sorted_index = mem::take(&mut self.sorted_index);
+        let pending_links = mem::take(&mut self.pending_links);
+        compacted.extend(
+            sorted_index
+                .into_iter()
+                .filter(|internal_id| !deleted[*internal_id as usize])
+                .merge_by(pending_links.into_values(), |a, b| {
+                    internal_to_external[*a as usize] <= internal_to_external[*b as usize]
+                }),
+        );
+        self.sorted_index = compacted;
+        self.index_tombstones = 0;
+    }
+
+    fn maybe_compact(&mut self) {
+        let garbage = self.pending_links.len() + self.index_tombstones;
+        if garbage >= MIN_COMPACTION_SIZE.max(self.sorted_index.len() / COMPACTION_RATIO) {
+            self.compact();
+        }
+    }
+
+    /// Position of the external id in the sorted index, or the position to insert it at
+    fn index_position(&self, external_id: &PointIdType) -> Result<usize, usize> {
+        self.sorted_index.binary_search_by(|internal_id| {
+            self.internal_to_external[*internal_id as usize].cmp(external_id)
+        })
+    }
+
+    /// Mark the point of the external id as deleted, returns its offset
+    fn unlink_external(&mut self, external_id: &PointIdType) -> Option<PointOffsetType> {
+        let internal_id = match self.pending_links.remove(external_id) {
+            Some(internal_id) => internal_id,
+            None => {
+                let internal_id = self.sorted_index[self.index_position(external_id).ok()?];
+                if self.deleted[internal_id as usize] {
+                    return None;
+                }
+                self.index_tombstones += 1;
+                internal_id
+            }
+        };
+        self.deleted.set(internal_id as usize, true);
+        Some(internal_id)
+    }
+
+    /// Remove all references to the offset, before linking it to another external id
+    ///
+    /// Otherwise the sorted index would refer to the offset by the new external id out of order.
+    fn unlink_offset(&mut self, internal_id: PointOffsetType) {
+        let external_id = self.internal_to_external[internal_id as usize].clone();
+        if self.pending_links.get(&external_id) == Some(&internal_id) {
+            self.pending_links.remove(&external_id);
+            return;
+        }
+        if let Ok(position) = self.index_position(&external_id) {
+            if self.sorted_index[position] == internal_id {
+                self.sorted_index.remove(position);
+                if self.deleted[internal_id as usize] {
+                    self.index_tombstones -= 1;
+                }
+            }
+        }
+    }
+    /// Same as [`IdTracker::set_link`], but without compaction
+    fn link(
+        &mut self,
+        external_id: PointIdType,
+        internal_id: PointOffsetType,
+    ) -> OperationResult<Option<PointOffsetType>> {
+        let previous_internal_id = self.internal_id(external_id.clone());
+        // Offset is taken from another point
+        if let Some(displaced_id) = self
+            .external_id(internal_id)
+            .filter(|displaced_id| *displaced_id != external_id)
+        {
+            self.drop_link(&displaced_id)?;
+        }
+        self.deleted_versions.remove(&external_id);
+        // Point is moved from another offset
+        self.unlink_external(&external_id);
+
+        let offset = internal_id as usize;
+        if offset < self.internal_to_external.len() {
+            self.unlink_offset(internal_id);
+        } else {
+            self.internal_to_external
+                .resize(offset + 1, PointIdType::NumId(u64::MAX));
+        }
+        if offset >= self.deleted.len() {
+            self.deleted.resize(offset + 1, true);
+        }
+        self.internal_to_external[offset] = external_id.clone();
+        self.deleted.set(offset, false);
+        self.pending_links.insert(external_id.clone(), internal_id);
+
+        self.mapping_db_wrapper.put(
+            SimpleIdTracker::store_key(&external_id),
+            bincode::serialize(&internal_id).unwrap(),
+        )?;
+        Ok(previous_internal_id)
+    }
+
+    /// Same as [`IdTracker::drop`], but without compaction
+    fn drop_link(&mut self, external_id: &PointIdType) -> OperationResult<()> {
+        self.unlink_external(external_id);
+        self.mapping_db_wrapper
+            .remove(SimpleIdTracker::store_key(external_id))?;
+        self.versions_db_wrapper
+            .remove(SimpleIdTracker::store_key(external_id))?;
+        Ok(())
+    }
+}
+
+impl IdTracker for CompactIdTracker {
+    fn internal_version(&self, internal_id: PointOffsetType) -> Option<SeqNumberType> {
+        self.internal_to_version.get(internal_id as usize).copied()
+    }
+
+    fn set_internal_version(
+        &mut self,
+        internal_id: PointOffsetType,
+        version: SeqNumberType,
+    ) -> OperationResult<()> {
+        if let Some(external_id) = self.external_id(internal_id) {
+            if internal_id as usize >= self.internal_to_version.len() {
+                self.internal_to_version.resize(internal_id as usize + 1, 0);
+            }
+            self.internal_to_version[internal_id as usize] = version;
+            self.versions_db_wrapper.put(
+                SimpleIdTracker::store_key(&external_id),
+                bincode::serialize(&version).unwrap(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn deleted_version(&self, external_id: PointIdType) -> Option<SeqNumberType> {
+        self.deleted_versions.get(&external_id).copied()
+    }
+
+    fn set_deleted_version(
+        &mut self,
+        external_id: PointIdType,
+        version: SeqNumberType,
+    ) -> OperationResult<()> {
+        let key = SimpleIdTracker::store_key(&external_id);
+        self.deleted_versions.insert(external_id, version);
+        self.versions_db_wrapper
+            .put(key, bincode::serialize(&version).unwrap())?;
+        Ok(())
+    }
+
+    fn purge_deleted_versions(&mut self, persisted_version: SeqNumberType) -> OperationResult<()> {
+        let purged: Vec<_> = self
+            .deleted_versions
+            .iter()
+            .filter(|(_, version)| **version <= persisted_version)
+            .map(|(external_id, _)| external_id.clone())
+            .collect();
+        for external_id in purged {
+            self.deleted_versions.remove(&external_id);
+            self.versions_db_wrapper
+                .remove(SimpleIdTracker::store_key(&external_id))?;
+        }
+        Ok(())
+    }
+
+    fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType> {
+        if let Some(internal_id) = self.pending_links.get(&external_id) {
+            return Some(*internal_id);
+        }
+        let internal_id = self.sorted_index[self.index_position(&external_id).ok()?];
+        (!self.deleted[internal_id as usize]).then_some(internal_id)
+    }
+
+    fn internal_ids(&self, external_ids: &[PointIdType]) -> Vec<Option<PointOffsetType>> {
+        // Ids come in ascending order, so each one is searched after the position of the previous one
+        let mut index_start = 0;
+        lookup_sorted(external_ids, |external_id| {
+            if let Some(internal_id) = self.pending_links.get(external_id) {
+                return Some(*internal_id);
+            }
+            let position = self.sorted_index[index_start..].binary_search_by(|internal_id| {
+                self.internal_to_external[*internal_id as usize].cmp(external_id)
+            });
+            match position {
+                Ok(position) => {
+                    index_start += position;
+                    let internal_id = self.sorted_index[index_start];
+                    (!self.deleted[internal_id as usize]).then_some(internal_id)
+                }
+                Err(position) => {
+                    index_start += position;
+                    None
+                }
+            }
+        })
+    }
+
+    fn external_id(&self, internal_id: PointOffsetType) -> Option<PointIdType> {
+        if let Some(deleted) = self.deleted.get(internal_id as usize) {
+            if !deleted {
+                return self.internal_to_external.get(internal_id as usize).cloned();
+            }
+        }
+        None
+    }
+
+    fn set_link(
+        &mut self,
+        external_id: PointIdType,
+        internal_id: PointOffsetType,
+    ) -> OperationResult<Option<PointOffsetType>> {
+        let previous_internal_id = self.link(external_id, internal_id)?;
+        self.maybe_compact();
+        Ok(previous_internal_id)
+    }
+
+    fn relink_batch(&mut self, mappings: &[(PointIdType, PointOffsetType)]) -> OperationResult<()> {
+        for (external_id, internal_id) in mappings {
+            self.link(external_id.clone(), *internal_id)?;
+        }
+        // Compact once for the whole batch
+        self.maybe_compact();
+        Ok(())
+    }
+
+    fn drop(&mut self, external_id: PointIdType) -> OperationResult<()> {
+        self.drop_link(&external_id)?;
+        self.maybe_compact();
+        Ok(())
+    }
+
+    fn rebuild_from(
+        &mut self,
+        mappings: &mut dyn Iterator<Item = (PointIdType, PointOffsetType)>,
+    ) -> OperationResult<()> {
+        // Ordered by external ids
+        let versions = self
+            .iter_from(None)
+            .map(|(external_id, internal_id)| (external_id, self.internal_version(internal_id)))
+            .collect::<Vec<_>>();
+        for (external_id, _) in &versions {
+            self.mapping_db_wrapper
+                .remove(SimpleIdTracker::store_key(external_id))?;
+            self.versions_db_wrapper
+                .remove(SimpleIdTracker::store_key(external_id))?;
+        }
+
+        self.deleted = BitVec::new();
+        self.internal_to_external.clear();
+        self.internal_to_version.clear();
+        self.sorted_index.clear();
+        self.index_tombstones = 0;
+        self.pending_links.clear();
+        for (external_id, internal_id) in mappings {
+            self.set_link(external_id, internal_id)?;
+        }
+        self.compact();
+
+        for (external_id, internal_id) in self.iter_from(None).collect::<Vec<_>>() {
+            let version = versions
+                .binary_search_by(|(versioned_id, _)| versioned_id.cmp(&external_id))
+                .ok()
+                .and_then(|position| versions[position].1);
+            if let Some(version) = version {
+                self.set_internal_version(internal_id, version)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn iter_external(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
+        Box::new(self.iter_from(None).map(|(external_id, _)| external_id))
+    }
+
+    fn iter_internal(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        Box::new(
+            (0..self.internal_to_external.len() as PointOffsetType)
+                .filter(move |i| !self.deleted[*i as usize]),
+        )
+    }
+
+    fn iter_from(
+        &self,
+        external_id: Option<PointIdType>,
+    ) -> Box<dyn Iterator<Item = (PointIdType, PointOffsetType)> + '_> {
+        let index_start = match &external_id {
+            None => 0,
+            Some(external_id) => self
+                .index_position(external_id)
+                .unwrap_or_else(|position| position),
+        };
+        let index_iter = self.sorted_index[index_start..]
+            .iter()
+            .copied()
+            .filter(|internal_id| !self.deleted[*internal_id as usize])
+            .map(|internal_id| {
+                (
+                    self.internal_to_external[internal_id as usize].clone(),
+                    internal_id,
+                )
+            });
+
+        let pending_start = external_id.map_or(Bound::Unbounded, Bound::Included);
+        let pending_iter = self
+            .pending_links
+            .range((pending_start, Bound::Unbounded))
+            .map(|(external_id, internal_id)| (external_id.clone(), *internal_id));
+
+        Box::new(index_iter.merge_by(pending_iter, |a, b| a.0 <= b.0))
+    }
+
+    fn points_count(&self) -> usize {
+        self.sorted_index.len() - self.index_tombstones + self.pending_links.len()
+    }
+
+    fn iter_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        self.iter_internal()
+    }
+
+    fn internal_size(&self) -> usize {
+        self.internal_to_external.len()
+    }
+
+    fn mapping_flusher(&self) -> Flusher {
+        self.mapping_db_wrapper.flusher()
+    }
+
+    fn versions_flusher(&self) -> Flusher {
+        self.versions_db_wrapper.flusher()
+    }
+
+    fn disk_usage(&self) -> OperationResult<usize> {
+        Ok(self.mapping_db_wrapper.disk_usage()? + self.versions_db_wrapper.disk_usage()?)
+    }
+
+    fn is_deleted(&self, key: PointOffsetType) -> bool {
+        let key = key as usize;
+        if key >= self.deleted.len() {
+            return true;
+        }
+        self.deleted[key]
+    }
+
+    fn deleted_bitvec(&self) -> &BitVec {
+        &self.deleted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use tempfile::Builder;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
+    use crate::types::StrPointId;
+
+    fn random_id(rng: &mut StdRng) -> PointIdType {
+        match rng.gen_range(0..3) {
+            0 => PointIdType::NumId(rng.gen_range(0..300)),
+            1 => PointIdType::Uuid(Uuid::from_u128(rng.gen_range(0..300))),
+            _ => PointIdType::Str(StrPointId::new(&format!("doc-{}", rng.gen_range(0..300)))),
+        }
+    }
+
+    #[test]
+    fn test_same_as_simple_id_tracker() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let simple_dir = Builder::new()
+            .prefix("simple_storage_dir")
+            .tempdir()
+            .unwrap();
+        let mut compact =
+            CompactIdTracker::open(open_db(dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+        let mut simple =
+            SimpleIdTracker::open(open_db(simple_dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for step in 0..20_000 {
+            let external_id = random_id(&mut rng);
+            if rng.gen_bool(0.3) {
+                compact.drop(external_id.clone()).unwrap();
+                simple.drop(external_id.clone()).unwrap();
+            } else if simple.internal_id(external_id.clone()).is_none() {
+                let internal_id = simple.internal_size() as PointOffsetType;
+                compact.set_link(external_id.clone(), internal_id).unwrap();
+                simple.set_link(external_id.clone(), internal_id).unwrap();
+            }
+
+            if step % 1000 == 0 {
+                compact.compact();
+            }
+            assert_eq!(compact.points_count(), simple.points_count());
+            assert_eq!(
+                compact.internal_id(external_id.clone()),
+                simple.internal_id(external_id)
+            );
+
+            if step % 100 == 0 {
+                // Batches with duplicated and missing ids, some of them are pending links
+                let external_ids: Vec<_> = (0..50).map(|_| random_id(&mut rng)).collect();
+                let expected: Vec<_> = external_ids
+                    .iter()
+                    .map(|external_id| simple.internal_id(external_id.clone()))
+                    .collect();
+                assert_eq!(compact.internal_ids(&external_ids), expected);
+                assert_eq!(simple.internal_ids(&external_ids), expected);
+            }
+        }
+
+        assert!(compact.iter_from(None).eq(simple.iter_from(None)));
+        for _ in 0..100 {
+            let start = random_id(&mut rng);
+            assert!(compact
+                .iter_from(Some(start.clone()))
+                .eq(simple.iter_from(Some(start))));
+        }
+        assert!(compact.iter_internal().eq(simple.iter_internal()));
+        for internal_id in 0..simple.internal_size() as PointOffsetType {
+            assert_eq!(
+                compact.external_id(internal_id),
+                simple.external_id(internal_id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_relink_batch() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let simple_dir = Builder::new()
+            .prefix("simple_storage_dir")
+            .tempdir()
+            .unwrap();
+        let mut compact =
+            CompactIdTracker::open(open_db(dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+        let mut simple =
+            SimpleIdTracker::open(open_db(simple_dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..10 {
+            // Batches with both duplicated external ids and offsets
+            let mappings = (0..500)
+                .map(|_| (random_id(&mut rng), rng.gen_range(0..1000)))
+                .collect::<Vec<_>>();
+            compact.relink_batch(&mappings).unwrap();
+            simple.relink_batch(&mappings).unwrap();
+
+            assert_eq!(compact.points_count(), simple.points_count());
+            assert!(compact.iter_from(None).eq(simple.iter_from(None)));
+            for internal_id in 0..simple.internal_size() as PointOffsetType {
+                assert_eq!(
+                    compact.external_id(internal_id),
+                    simple.external_id(internal_id)
+                );
+            }
+        }
+
+        let mappings = (0..100)
+            .map(|internal_id| (random_id(&mut rng), internal_id))
+            .collect::<Vec<_>>();
+        compact.rebuild_from(&mut mappings.iter().cloned()).unwrap();
+        simple.rebuild_from(&mut mappings.into_iter()).unwrap();
+        assert_eq!(compact.internal_size(), 100);
+        assert!(compact.iter_from(None).eq(simple.iter_from(None)));
+    }
+
+    #[test]
+    fn test_reopen() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        {
+            let mut id_tracker = SimpleIdTracker::open(db.clone()).unwrap();
+            for internal_id in 0..100 {
+                id_tracker
+                    .set_link(PointIdType::NumId(1000 - internal_id as u64), internal_id)
+                    .unwrap();
+                id_tracker.set_internal_version(internal_id, 5).unwrap();
+            }
+            id_tracker.drop(1000.into()).unwrap();
+            id_tracker.set_deleted_version(1000.into(), 7).unwrap();
+        }
+
+        // Storage of the simple id tracker is reused as is
+        let mut id_tracker = CompactIdTracker::open(db.clone()).unwrap();
+        assert_eq!(id_tracker.points_count(), 99);
+        assert_eq!(id_tracker.internal_id(1000.into()), None);
+        assert_eq!(id_tracker.deleted_version(1000.into()), Some(7));
+        assert_eq!(id_tracker.internal_id(950.into()), Some(50));
+        assert_eq!(id_tracker.internal_version(50), Some(5));
+        let ids = id_tracker.iter_external().collect::<Vec<_>>();
+        assert_eq!(ids.len(), 99);
+        assert_eq!(ids[0], PointIdType::NumId(901));
+
+        // Offset is linked to another point
+        id_tracker.set_link(5000.into(), 50).unwrap();
+        assert_eq!(id_tracker.internal_id(950.into()), None);
+        assert_eq!(id_tracker.internal_id(5000.into()), Some(50));
+        id_tracker.compact();
+        assert_eq!(id_tracker.internal_id(5000.into()), Some(50));
+        assert_eq!(id_tracker.iter_external().last(), Some(5000.into()));
+
+        // Tombstone is purged once its version is persisted
+        id_tracker.purge_deleted_versions(6).unwrap();
+        assert_eq!(id_tracker.deleted_version(1000.into()), Some(7));
+        id_tracker.purge_deleted_versions(7).unwrap();
+        assert_eq!(id_tracker.deleted_version(1000.into()), None);
+        drop(id_tracker);
+        let id_tracker = SimpleIdTracker::open(db).unwrap();
+        assert_eq!(id_tracker.deleted_version(1000.into()), None);
+        assert_eq!(id_tracker.internal_version(49), Some(5));
+    }
+}
@@ -1,9 +1,9 @@
-use bitvec::vec::BitVec;
-use rand::Rng;
-
+use crate::common::utils::reservoir_sample;
 use crate::common::Flusher;
-use crate::entry::entry_point::OperationResult;
+use crate::entry::entry_point::{OperationError, OperationResult};
 use crate::types::{PointIdType, PointOffsetType, SeqNumberType};
+use bitvec::vec::BitVec;
+use rand::Rng;
 
 /// Trait for point ids tracker.
 ///
@@ -19,29 +19,133 @@ pub trait IdTracker {
         version: SeqNumberType,
     ) -> OperationResult<()>;
 
+    /// Version of the operation, which deleted the point, if it wasn't inserted again since
+    fn deleted_version(&self, external_id: PointIdType) -> Option<SeqNumberType>;
+
+    /// Remember deletion of the point, so that older operations can't bring it back
+    fn set_deleted_version(
+        &mut self,
+        external_id: PointIdType,
+        version: SeqNumberType,
+    ) -> OperationResult<()>;
+
+    /// Forget deletions, which are not newer than the persisted version of the segment
+    ///
+    /// Older operations are skipped by the segment version anyway, so these tombstones are not needed.
+    fn purge_deleted_versions(&mut self, persisted_version: SeqNumberType) -> OperationResult<()>;
+
     /// Returns internal ID of the point, which is used inside this segment
     fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType>;
 
     /// Return external ID for internal point, defined by user
     fn external_id(&self, internal_id: PointOffsetType) -> Option<PointIdType>;
 
+    /// Internal IDs of the given points in the same order, `None` for missing ones
+    ///
+    /// Default implementation looks the ids up one by one.
+    fn internal_ids(&self, external_ids: &[PointIdType]) -> Vec<Option<PointOffsetType>> {
+        external_ids
+            .iter()
+            .map(|external_id| self.internal_id(external_id.clone()))
+            .collect()
+    }
+
+    /// External IDs of the given internal points in the same order, `None` for missing ones
+    ///
+    /// Default implementation looks the offsets up one by one, as trackers index external ids by offset.
+    fn external_ids(&self, internal_ids: &[PointOffsetType]) -> Vec<Option<PointIdType>> {
+        internal_ids
+            .iter()
+            .map(|internal_id| self.external_id(*internal_id))
+            .collect()
+    }
+
     /// Set mapping
+    ///
+    /// Previous links of both the external id and the offset are replaced.
+    /// Returns the offset, previously linked to the external id, if any.
     fn set_link(
         &mut self,
         external_id: PointIdType,
         internal_id: PointOffsetType,
+    ) -> OperationResult<Option<PointOffsetType>>;
+
+    /// Same as [`IdTracker::set_link`], but fails instead of replacing existing links
+    ///
+    /// Should be used by callers, which expect both the external id and the offset to be free.
+    /// Setting the same link again is not a collision.
+    fn set_new_link(
+        &mut self,
+        external_id: PointIdType,
+        internal_id: PointOffsetType,
+    ) -> OperationResult<()> {
+        if let Some(existing_internal_id) = self
+            .internal_id(external_id.clone())
+            .filter(|existing_internal_id| *existing_internal_id != internal_id)
+        {
+            return Err(OperationError::service_error(format!(
+                "Point {external_id} is already linked to offset {existing_internal_id}"
+            )));
+        }
+        if let Some(existing_external_id) = self
+            .external_id(internal_id)
+            .filter(|existing_external_id| *existing_external_id != external_id)
+        {
+            return Err(OperationError::service_error(format!(
+                "Offset {internal_id} is already linked to point {existing_external_id}"
+            )));
+        }
+        self.set_link(external_id, internal_id)?;
+        Ok(())
+    }
+
+    /// Set mappings in the given order, the last link of the same external id or offset wins
+    ///
+    /// Mapping stays consistent in both directions, as after separate [`IdTracker::set_link`] calls.
+    fn relink_batch(&mut self, mappings: &[(PointIdType, PointOffsetType)]) -> OperationResult<()> {
+        for (external_id, internal_id) in mappings {
+            self.set_link(external_id.clone(), *internal_id)?;
+        }
+        Ok(())
+    }
+
+    /// Link `external_ids` to the contiguous block of offsets, starting from `start_offset`
+    ///
+    /// If linking fails halfway, links set so far are kept and the rest of the block stays
+    /// unlinked, so these offsets are treated as deleted.
+    fn set_links_range(
+        &mut self,
+        external_ids: &[PointIdType],
+        start_offset: PointOffsetType,
+    ) -> OperationResult<()> {
+        let mappings = external_ids
+            .iter()
+            .cloned()
+            .zip(start_offset..)
+            .collect::<Vec<_>>();
+        self.relink_batch(&mappings)
+    }
+
+    /// Replace the whole mapping with the given one, duplicates are resolved as in `relink_batch`
+    ///
+    /// Versions are kept for the external ids, points missing in `mappings` are dropped.
+    fn rebuild_from(
+        &mut self,
+        mappings: &mut dyn Iterator<Item = (PointIdType, PointOffsetType)>,
     ) -> OperationResult<()>;
 
     /// Drop mapping
     fn drop(&mut self, external_id: PointIdType) -> OperationResult<()>;
 
-    /// Iterate over all external ids
+    /// Iterate over all external ids in ascending order
     fn iter_external(&self) -> Box<dyn Iterator<Item = PointIdType> + '_>;
 
     /// Iterate over all internal ids
     fn iter_internal(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_>;
 
-    /// Iterate starting from a given ID
+    /// Iterate in ascending order of external ids, starting from a given ID
+    ///
+    /// Start ID is included if present, otherwise iteration begins at the next greater one.
     fn iter_from(
         &self,
         external_id: Option<PointIdType>,
@@ -62,6 +166,9 @@ pub trait IdTracker {
     /// Flush points versions to disk
     fn versions_flusher(&self) -> Flusher;
 
+    /// Size of the persisted id mapping and versions in bytes
+    fn disk_usage(&self) -> OperationResult<usize>;
+
     fn deleted_bitvec(&self) -> &BitVec;
 
     fn is_deleted(&self, internal_id: PointOffsetType) -> bool;
@@ -72,7 +179,7 @@ pub trait IdTracker {
     }
 
     /// Iterator over `n` random ids which are not deleted
-    fn sample_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+    fn sample_internal_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
         let total = self.internal_size() as PointOffsetType;
         let mut rng = rand::thread_rng();
         Box::new(
@@ -81,6 +188,41 @@ pub trait IdTracker {
                 .filter(move |x| !self.is_deleted(*x)),
         )
     }
+
+    /// Up to `k` distinct random external ids of existing points
+    ///
+    /// Reservoir sampling over the whole internal to external mapping, so each point has
+    /// the same probability to be sampled. Same `rng_seed` gives the same sample.
+    fn sample_ids(&self, k: usize, rng_seed: Option<u64>) -> Vec<PointIdType> {
+        // Deleted points have no external id
+        let external_ids = (0..self.internal_size() as PointOffsetType)
+            .filter_map(|internal_id| self.external_id(internal_id));
+        reservoir_sample(external_ids, k, rng_seed)
+    }
 }
 
 pub type IdTrackerSS = dyn IdTracker + Sync + Send;
+
+/// Look up the ids in ascending order, each of the duplicated ids once
+///
+/// `lookup` gets the distinct ids in ascending order, so it can continue the search from the previous one.
+/// Results are returned in the order of `external_ids`.
+pub(crate) fn lookup_sorted(
+    external_ids: &[PointIdType],
+    mut lookup: impl FnMut(&PointIdType) -> Option<PointOffsetType>,
+) -> Vec<Option<PointOffsetType>> {
+    let mut order: Vec<usize> = (0..external_ids.len()).collect();
+    order.sort_unstable_by(|&a, &b| external_ids[a].cmp(&external_ids[b]));
+    let mut internal_ids = vec![None; external_ids.len()];
+    let mut previous: Option<usize> = None;
+    for position in order {
+        internal_ids[position] = match previous {
+            Some(previous) if external_ids[previous] == external_ids[position] => {
+                internal_ids[previous]
+            }
+            _ => lookup(&external_ids[position]),
+        };
+        previous = Some(position);
+    }
+    internal_ids
+}
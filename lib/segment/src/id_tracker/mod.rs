@@ -1,3 +1,4 @@
+pub mod compact_id_tracker;
 pub mod id_tracker_base;
 pub mod simple_id_tracker;
 
@@ -11,8 +11,9 @@ use uuid::Uuid;
 use crate::common::rocksdb_wrapper::{DatabaseColumnWrapper, DB_MAPPING_CF, DB_VERSIONS_CF};
 use crate::common::Flusher;
 use crate::entry::entry_point::OperationResult;
+use crate::id_tracker::id_tracker_base::lookup_sorted;
 use crate::id_tracker::IdTracker;
-use crate::types::{ExtendedPointId, PointIdType, PointOffsetType, SeqNumberType};
+use crate::types::{ExtendedPointId, PointIdType, PointOffsetType, SeqNumberType, StrPointId};
 
 /// Point Id type used for storing ids internally
 /// Should be serializable by `bincode`, therefore is not untagged.
@@ -28,6 +29,7 @@ impl From<&ExtendedPointId> for StoredPointId {
         match point_id {
             ExtendedPointId::NumId(idx) => StoredPointId::NumId(*idx),
             ExtendedPointId::Uuid(uuid) => StoredPointId::Uuid(*uuid),
+            ExtendedPointId::Str(id) => StoredPointId::String(id.as_str().to_owned()),
         }
     }
 }
@@ -37,7 +39,7 @@ impl From<StoredPointId> for ExtendedPointId {
         match point_id {
             StoredPointId::NumId(idx) => ExtendedPointId::NumId(idx),
             StoredPointId::Uuid(uuid) => ExtendedPointId::Uuid(uuid),
-            StoredPointId::String(_str) => unimplemented!(),
+            StoredPointId::String(id) => ExtendedPointId::Str(StrPointId::new(&id)),
         }
     }
 }
@@ -52,12 +54,21 @@ fn external_to_stored_id(point_id: &PointIdType) -> StoredPointId {
     point_id.into()
 }
 
+/// Id tracker, which keeps all mappings in memory and writes them through to RocksDB
+///
+/// External ids are kept in `BTreeMap`s, as scroll needs them in ascending order.
+/// Lookups are slower than in a `HashMap`, compare them with `id_type_benchmark`.
+///
+/// Versions of deleted points are kept as tombstones, until the point is inserted again,
+/// or the segment is flushed with a later version.
 pub struct SimpleIdTracker {
     deleted: BitVec,
     internal_to_external: Vec<PointIdType>,
     internal_to_version: Vec<SeqNumberType>,
     external_to_internal_num: BTreeMap<u64, PointOffsetType>,
     external_to_internal_uuid: BTreeMap<Uuid, PointOffsetType>,
+    external_to_internal_str: BTreeMap<StrPointId, PointOffsetType>,
+    deleted_versions: BTreeMap<PointIdType, SeqNumberType>,
     mapping_db_wrapper: DatabaseColumnWrapper,
     versions_db_wrapper: DatabaseColumnWrapper,
 }
@@ -68,6 +79,8 @@ impl SimpleIdTracker {
         let mut internal_to_external: Vec<PointIdType> = Default::default();
         let mut external_to_internal_num: BTreeMap<u64, PointOffsetType> = Default::default();
         let mut external_to_internal_uuid: BTreeMap<Uuid, PointOffsetType> = Default::default();
+        let mut external_to_internal_str: BTreeMap<StrPointId, PointOffsetType> =
+            Default::default();
 
         let mapping_db_wrapper = DatabaseColumnWrapper::new(store.clone(), DB_MAPPING_CF);
         for (key, val) in mapping_db_wrapper.lock_db().iter()? {
@@ -81,8 +94,8 @@ impl SimpleIdTracker {
                 deleted.resize(internal_id as usize + 1, true);
             }
 
-            let replaced_id = internal_to_external[internal_id as usize];
-            internal_to_external[internal_id as usize] = external_id;
+            let replaced_id = internal_to_external[internal_id as usize].clone();
+            internal_to_external[internal_id as usize] = external_id.clone();
             if !deleted[internal_id as usize] {
                 // Fixing corrupted mapping - this id should be recovered from WAL
                 // This should not happen in normal operation, but it can happen if
@@ -92,12 +105,15 @@ impl SimpleIdTracker {
                     external_id,
                     replaced_id
                 );
-                match external_id {
+                match &external_id {
                     PointIdType::NumId(idx) => {
-                        external_to_internal_num.remove(&idx);
+                        external_to_internal_num.remove(idx);
                     }
                     PointIdType::Uuid(uuid) => {
-                        external_to_internal_uuid.remove(&uuid);
+                        external_to_internal_uuid.remove(uuid);
+                    }
+                    PointIdType::Str(id) => {
+                        external_to_internal_str.remove(id);
                     }
                 }
             }
@@ -110,17 +126,22 @@ impl SimpleIdTracker {
                 PointIdType::Uuid(uuid) => {
                     external_to_internal_uuid.insert(uuid, internal_id);
                 }
+                PointIdType::Str(id) => {
+                    external_to_internal_str.insert(id, internal_id);
+                }
             }
         }
 
         let mut internal_to_version: Vec<SeqNumberType> = Default::default();
+        let mut deleted_versions: BTreeMap<PointIdType, SeqNumberType> = Default::default();
         let versions_db_wrapper = DatabaseColumnWrapper::new(store, DB_VERSIONS_CF);
         for (key, val) in versions_db_wrapper.lock_db().iter()? {
             let external_id = Self::restore_key(&key);
             let version: SeqNumberType = bincode::deserialize(&val).unwrap();
-            let internal_id = match external_id {
-                PointIdType::NumId(idx) => external_to_internal_num.get(&idx).copied(),
-                PointIdType::Uuid(uuid) => external_to_internal_uuid.get(&uuid).copied(),
+            let internal_id = match &external_id {
+                PointIdType::NumId(idx) => external_to_internal_num.get(idx).copied(),
+                PointIdType::Uuid(uuid) => external_to_internal_uuid.get(uuid).copied(),
+                PointIdType::Str(id) => external_to_internal_str.get(id).copied(),
             };
             if let Some(internal_id) = internal_id {
                 if internal_id as usize >= internal_to_version.len() {
@@ -128,10 +149,8 @@ impl SimpleIdTracker {
                 }
                 internal_to_version[internal_id as usize] = version;
             } else {
-                log::debug!(
-                    "Found version without internal id, external id: {}",
-                    external_id
-                );
+                // Version of the deleted point
+                deleted_versions.insert(external_id, version);
             }
         }
 
@@ -153,16 +172,18 @@ impl SimpleIdTracker {
             internal_to_version,
             external_to_internal_num,
             external_to_internal_uuid,
+            external_to_internal_str,
+            deleted_versions,
             mapping_db_wrapper,
             versions_db_wrapper,
         })
     }
 
-    fn store_key(external_id: &PointIdType) -> Vec<u8> {
+    pub(crate) fn store_key(external_id: &PointIdType) -> Vec<u8> {
         bincode::serialize(&external_to_stored_id(external_id)).unwrap()
     }
 
-    fn restore_key(data: &[u8]) -> PointIdType {
+    pub(crate) fn restore_key(data: &[u8]) -> PointIdType {
         let stored_external_id: StoredPointId = bincode::deserialize(data).unwrap();
         stored_to_external_id(stored_external_id)
     }
@@ -191,17 +212,55 @@ impl IdTracker for SimpleIdTracker {
         Ok(())
     }
 
+    fn deleted_version(&self, external_id: PointIdType) -> Option<SeqNumberType> {
+        self.deleted_versions.get(&external_id).copied()
+    }
+
+    fn set_deleted_version(
+        &mut self,
+        external_id: PointIdType,
+        version: SeqNumberType,
+    ) -> OperationResult<()> {
+        let key = Self::store_key(&external_id);
+        self.deleted_versions.insert(external_id, version);
+        self.versions_db_wrapper
+            .put(key, bincode::serialize(&version).unwrap())?;
+        Ok(())
+    }
+
+    fn purge_deleted_versions(&mut self, persisted_version: SeqNumberType) -> OperationResult<()> {
+        let purged: Vec<_> = self
+            .deleted_versions
+            .iter()
+            .filter(|(_, version)| **version <= persisted_version)
+            .map(|(external_id, _)| external_id.clone())
+            .collect();
+        for external_id in purged {
+            self.deleted_versions.remove(&external_id);
+            self.versions_db_wrapper
+                .remove(Self::store_key(&external_id))?;
+        }
+        Ok(())
+    }
+
     fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType> {
         match external_id {
             PointIdType::NumId(idx) => self.external_to_internal_num.get(&idx).copied(),
             PointIdType::Uuid(uuid) => self.external_to_internal_uuid.get(&uuid).copied(),
+            PointIdType::Str(id) => self.external_to_internal_str.get(&id).copied(),
         }
     }
 
+    fn internal_ids(&self, external_ids: &[PointIdType]) -> Vec<Option<PointOffsetType>> {
+        lookup_sorted(external_ids, |external_id| {
+            self.internal_id(external_id.clone())
+        })
+    }
+
     fn external_id(&self, internal_id: PointOffsetType) -> Option<PointIdType> {
         if let Some(deleted) = self.deleted.get(internal_id as usize) {
             if !deleted {
-                return self.internal_to_external.get(internal_id as usize).copied();
+                return self.internal_to_external.get(internal_id as usize).cloned();
             }
         }
         None
@@ -211,13 +270,34 @@ impl IdTracker for SimpleIdTracker {
         &mut self,
         external_id: PointIdType,
         internal_id: PointOffsetType,
-    ) -> OperationResult<()> {
-        match external_id {
+    ) -> OperationResult<Option<PointOffsetType>> {
+        let previous_internal_id = self.internal_id(external_id.clone());
+        // Offset is taken from another point
+        if let Some(displaced_id) = self
+            .external_id(internal_id)
+            .filter(|displaced_id| *displaced_id != external_id)
+        {
+            self.drop(displaced_id)?;
+        }
+        // Point is moved from another offset
+        if let Some(previous_internal_id) =
+            previous_internal_id.filter(|previous_internal_id| *previous_internal_id != internal_id)
+        {
+            self.deleted.set(previous_internal_id as usize, true);
+            self.internal_to_external[previous_internal_id as usize] = PointIdType::NumId(u64::MAX);
+        }
+
+        self.deleted_versions.remove(&external_id);
+        match &external_id {
             PointIdType::NumId(idx) => {
-                self.external_to_internal_num.insert(idx, internal_id);
+                self.external_to_internal_num.insert(*idx, internal_id);
             }
             PointIdType::Uuid(uuid) => {
-                self.external_to_internal_uuid.insert(uuid, internal_id);
+                self.external_to_internal_uuid.insert(*uuid, internal_id);
+            }
+            PointIdType::Str(id) => {
+                self.external_to_internal_str
+                    .insert(id.clone(), internal_id);
             }
         }
 
@@ -229,20 +309,21 @@ impl IdTracker for SimpleIdTracker {
         if internal_id >= self.deleted.len() {
             self.deleted.resize(internal_id + 1, true);
         }
-        self.internal_to_external[internal_id] = external_id;
+        self.internal_to_external[internal_id] = external_id.clone();
         self.deleted.set(internal_id, false);
 
         self.mapping_db_wrapper.put(
             Self::store_key(&external_id),
             bincode::serialize(&internal_id).unwrap(),
         )?;
-        Ok(())
+        Ok(previous_internal_id)
     }
 
     fn drop(&mut self, external_id: PointIdType) -> OperationResult<()> {
         let internal_id = match &external_id {
             PointIdType::NumId(idx) => self.external_to_internal_num.remove(idx),
             PointIdType::Uuid(uuid) => self.external_to_internal_uuid.remove(uuid),
+            PointIdType::Str(id) => self.external_to_internal_str.remove(id),
         };
         if let Some(internal_id) = internal_id {
             self.deleted.set(internal_id as usize, true);
@@ -255,6 +336,44 @@ impl IdTracker for SimpleIdTracker {
         Ok(())
     }
 
+    fn rebuild_from(
+        &mut self,
+        mappings: &mut dyn Iterator<Item = (PointIdType, PointOffsetType)>,
+    ) -> OperationResult<()> {
+        // Ordered by external ids
+        let versions = self
+            .iter_from(None)
+            .map(|(external_id, internal_id)| (external_id, self.internal_version(internal_id)))
+            .collect::<Vec<_>>();
+        for (external_id, _) in &versions {
+            self.mapping_db_wrapper
+                .remove(Self::store_key(external_id))?;
+            self.versions_db_wrapper
+                .remove(Self::store_key(external_id))?;
+        }
+
+        self.deleted = BitVec::new();
+        self.internal_to_external.clear();
+        self.internal_to_version.clear();
+        self.external_to_internal_num.clear();
+        self.external_to_internal_uuid.clear();
+        self.external_to_internal_str.clear();
+        for (external_id, internal_id) in mappings {
+            self.set_link(external_id, internal_id)?;
+        }
+
+        for (external_id, internal_id) in self.iter_from(None).collect::<Vec<_>>() {
+            let version = versions
+                .binary_search_by(|(versioned_id, _)| versioned_id.cmp(&external_id))
+                .ok()
+                .and_then(|position| versions[position].1);
+            if let Some(version) = version {
+                self.set_internal_version(internal_id, version)?;
+            }
+        }
+        Ok(())
+    }
+
     fn iter_external(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
         let iter_num = self
             .external_to_internal_num
@@ -266,8 +385,13 @@ impl IdTracker for SimpleIdTracker {
             .keys()
             .copied()
             .map(PointIdType::Uuid);
-        // order is important here, we want to iterate over the u64 ids first
-        Box::new(iter_num.chain(iter_uuid))
+        let iter_str = self
+            .external_to_internal_str
+            .keys()
+            .cloned()
+            .map(PointIdType::Str);
+        // order is important here, we want to iterate over the u64 ids first, then uuids
+        Box::new(iter_num.chain(iter_uuid).chain(iter_str))
     }
 
     fn iter_internal(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
@@ -301,32 +425,50 @@ impl IdTracker for SimpleIdTracker {
                 .range(offset..)
                 .map(|(k, v)| (PointIdType::Uuid(*k), *v))
         };
+        let full_str_iter = || {
+            self.external_to_internal_str
+                .iter()
+                .map(|(k, v)| (PointIdType::Str(k.clone()), *v))
+        };
+        let offset_str_iter = |offset: StrPointId| {
+            self.external_to_internal_str
+                .range(offset..)
+                .map(|(k, v)| (PointIdType::Str(k.clone()), *v))
+        };
 
         match external_id {
             None => {
                 let iter_num = full_num_iter();
                 let iter_uuid = full_uuid_iter();
-                // order is important here, we want to iterate over the u64 ids first
-                Box::new(iter_num.chain(iter_uuid))
+                let iter_str = full_str_iter();
+                // order is important here, we want to iterate over the u64 ids first, then uuids
+                Box::new(iter_num.chain(iter_uuid).chain(iter_str))
             }
             Some(offset) => match offset {
                 PointIdType::NumId(idx) => {
                     // Because u64 keys are less that uuid key, we can just use the full iterator for uuid
                     let iter_num = offset_num_iter(idx);
                     let iter_uuid = full_uuid_iter();
-                    // order is important here, we want to iterate over the u64 ids first
-                    Box::new(iter_num.chain(iter_uuid))
+                    let iter_str = full_str_iter();
+                    // order is important here, we want to iterate over the u64 ids first, then uuids
+                    Box::new(iter_num.chain(iter_uuid).chain(iter_str))
                 }
                 PointIdType::Uuid(uuid) => {
-                    // if offset is a uuid, we can only iterate over uuids
-                    Box::new(offset_uuid_iter(uuid))
+                    // if offset is a uuid, we can skip all u64 ids
+                    Box::new(offset_uuid_iter(uuid).chain(full_str_iter()))
+                }
+                PointIdType::Str(id) => {
+                    // string ids go last, so only they are left
+                    Box::new(offset_str_iter(id))
                 }
             },
         }
     }
 
     fn points_count(&self) -> usize {
-        self.external_to_internal_num.len() + self.external_to_internal_uuid.len()
+        self.external_to_internal_num.len()
+            + self.external_to_internal_uuid.len()
+            + self.external_to_internal_str.len()
     }
 
     fn iter_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
@@ -345,6 +487,10 @@ impl IdTracker for SimpleIdTracker {
         self.versions_db_wrapper.flusher()
     }
 
+    fn disk_usage(&self) -> OperationResult<usize> {
+        Ok(self.mapping_db_wrapper.disk_usage()? + self.versions_db_wrapper.disk_usage()?)
+    }
+
     fn is_deleted(&self, key: PointOffsetType) -> bool {
         let key = key as usize;
         if key >= self.deleted.len() {
@@ -360,6 +506,8 @@ impl IdTracker for SimpleIdTracker {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use itertools::Itertools;
     use serde::de::DeserializeOwned;
     use tempfile::Builder;
@@ -408,10 +556,78 @@ mod tests {
         assert_eq!(first_four.len(), 4);
         assert_eq!(first_four[0].0, 100.into());
 
-        let last = id_tracker.iter_from(Some(first_four[3].0)).collect_vec();
+        let last = id_tracker
+            .iter_from(Some(first_four[3].0.clone()))
+            .collect_vec();
         assert_eq!(last.len(), 7);
     }
 
+    #[test]
+    fn test_batch_lookups() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+
+        let uuid_id = PointIdType::Uuid(Uuid::from_u128(123_u128));
+        id_tracker.set_link(10.into(), 0).unwrap();
+        id_tracker.set_link(uuid_id.clone(), 1).unwrap();
+        id_tracker.set_link(30.into(), 2).unwrap();
+        id_tracker.drop(30.into()).unwrap();
+
+        let external_ids = [
+            uuid_id.clone(),
+            20.into(),
+            10.into(),
+            uuid_id.clone(),
+            30.into(),
+        ];
+        assert_eq!(
+            id_tracker.internal_ids(&external_ids),
+            vec![Some(1), None, Some(0), Some(1), None]
+        );
+
+        // Deleted and out of range offsets are missing
+        assert_eq!(
+            id_tracker.external_ids(&[0, 2, 1, 100, 0]),
+            vec![Some(10.into()), None, Some(uuid_id), None, Some(10.into())]
+        );
+        assert!(id_tracker.internal_ids(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_iter_from_missing_id() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+
+        let uuid_id = |id: u128| PointIdType::Uuid(Uuid::from_u128(id));
+        let values: Vec<PointIdType> = vec![10.into(), 20.into(), 30.into(), uuid_id(5)];
+        for (id, value) in values.iter().enumerate() {
+            id_tracker
+                .set_link(value.clone(), id as PointOffsetType)
+                .unwrap();
+        }
+
+        let iter_from = |start: PointIdType| {
+            id_tracker
+                .iter_from(Some(start))
+                .map(|(k, _)| k)
+                .collect_vec()
+        };
+
+        // Existing start id is included
+        assert_eq!(iter_from(20.into()), values[1..]);
+        // Missing start id begins at the next greater one
+        assert_eq!(iter_from(15.into()), values[1..]);
+        assert_eq!(iter_from(31.into()), values[3..]);
+        assert_eq!(iter_from(uuid_id(1)), values[3..]);
+        assert!(iter_from(uuid_id(6)).is_empty());
+
+        assert_eq!(id_tracker.iter_external().collect_vec(), values);
+    }
+
     #[test]
     fn test_mixed_types_iterator() {
         let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
@@ -436,7 +652,9 @@ mod tests {
         ];
 
         for (id, value) in values.iter().enumerate() {
-            id_tracker.set_link(*value, id as PointOffsetType).unwrap();
+            id_tracker
+                .set_link(value.clone(), id as PointOffsetType)
+                .unwrap();
         }
 
         let sorted_from_tracker = id_tracker.iter_from(None).map(|(k, _)| k).collect_vec();
@@ -444,4 +662,329 @@ mod tests {
 
         assert_eq!(sorted_from_tracker, values);
     }
+
+    /// Copy files of the open database, as they would be left on disk if the process was killed
+    fn copy_db_files(from: &Path, to: &Path) {
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_name() != "LOCK" {
+                std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_kill_and_reopen() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let killed_dir = Builder::new()
+            .prefix("killed_storage_dir")
+            .tempdir()
+            .unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        let acknowledged: Vec<PointIdType> = (0..1000)
+            .map(|i| match i % 3 {
+                0 => PointIdType::NumId(i),
+                1 => PointIdType::Uuid(Uuid::from_u128(i as u128)),
+                _ => PointIdType::Str(StrPointId::new(&format!("doc-{i}"))),
+            })
+            .collect();
+        for (internal_id, external_id) in acknowledged.iter().enumerate() {
+            let internal_id = internal_id as PointOffsetType;
+            id_tracker
+                .set_link(external_id.clone(), internal_id)
+                .unwrap();
+            id_tracker.set_internal_version(internal_id, 1).unwrap();
+        }
+        id_tracker.drop(acknowledged[0].clone()).unwrap();
+
+        // Same order as in segment flush, points are acknowledged once versions are flushed
+        id_tracker.mapping_flusher()().unwrap();
+        id_tracker.versions_flusher()().unwrap();
+        assert!(id_tracker.disk_usage().unwrap() > 0);
+
+        // Not flushed, may be lost and recovered from WAL
+        id_tracker.set_link(100_000.into(), 1000).unwrap();
+
+        copy_db_files(dir.path(), killed_dir.path());
+        let reopened =
+            SimpleIdTracker::open(open_db(killed_dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+
+        assert_eq!(reopened.internal_id(acknowledged[0].clone()), None);
+        for (internal_id, external_id) in acknowledged.iter().enumerate().skip(1) {
+            let internal_id = internal_id as PointOffsetType;
+            assert_eq!(reopened.internal_id(external_id.clone()), Some(internal_id));
+            assert_eq!(reopened.external_id(internal_id), Some(external_id.clone()));
+            assert_eq!(reopened.internal_version(internal_id), Some(1));
+        }
+        assert!(reopened.points_count() >= acknowledged.len() - 1);
+    }
+
+    #[test]
+    fn test_deleted_versions() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let reopened_dir = Builder::new()
+            .prefix("reopened_storage_dir")
+            .tempdir()
+            .unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        id_tracker.set_link(1.into(), 0).unwrap();
+        id_tracker.set_internal_version(0, 5).unwrap();
+        assert_eq!(id_tracker.deleted_version(1.into()), None);
+
+        id_tracker.drop(1.into()).unwrap();
+        id_tracker.set_deleted_version(1.into(), 7).unwrap();
+        assert_eq!(id_tracker.deleted_version(1.into()), Some(7));
+        id_tracker.mapping_flusher()().unwrap();
+        id_tracker.versions_flusher()().unwrap();
+
+        copy_db_files(dir.path(), reopened_dir.path());
+        let mut reopened =
+            SimpleIdTracker::open(open_db(reopened_dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+        assert_eq!(reopened.internal_id(1.into()), None);
+        assert_eq!(reopened.points_count(), 0);
+        assert_eq!(reopened.deleted_version(1.into()), Some(7));
+
+        // Inserting the point again removes the tombstone
+        reopened.set_link(1.into(), 1).unwrap();
+        assert_eq!(reopened.deleted_version(1.into()), None);
+    }
+
+    #[test]
+    fn test_purge_deleted_versions() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let reopened_dir = Builder::new()
+            .prefix("reopened_storage_dir")
+            .tempdir()
+            .unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        for (external_id, version) in [(1, 7), (2, 8), (3, 9)] {
+            id_tracker
+                .set_deleted_version(PointIdType::NumId(external_id), version)
+                .unwrap();
+        }
+        id_tracker.set_link(4.into(), 0).unwrap();
+        id_tracker.set_internal_version(0, 5).unwrap();
+
+        // Tombstones up to the persisted version are purged, versions of existing points are kept
+        id_tracker.purge_deleted_versions(8).unwrap();
+        assert_eq!(id_tracker.deleted_version(1.into()), None);
+        assert_eq!(id_tracker.deleted_version(2.into()), None);
+        assert_eq!(id_tracker.deleted_version(3.into()), Some(9));
+        assert_eq!(id_tracker.internal_version(0), Some(5));
+        id_tracker.mapping_flusher()().unwrap();
+        id_tracker.versions_flusher()().unwrap();
+
+        copy_db_files(dir.path(), reopened_dir.path());
+        let reopened =
+            SimpleIdTracker::open(open_db(reopened_dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+        assert_eq!(reopened.deleted_version(1.into()), None);
+        assert_eq!(reopened.deleted_version(2.into()), None);
+        assert_eq!(reopened.deleted_version(3.into()), Some(9));
+        assert_eq!(reopened.internal_version(0), Some(5));
+    }
+
+    #[test]
+    fn test_set_link_collisions() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        assert_eq!(id_tracker.set_link(10.into(), 0).unwrap(), None);
+        assert_eq!(id_tracker.set_link(10.into(), 1).unwrap(), Some(0));
+        assert!(id_tracker.is_deleted(0));
+
+        id_tracker.set_new_link(20.into(), 2).unwrap();
+        // Same link again is not a collision
+        id_tracker.set_new_link(20.into(), 2).unwrap();
+        // Point is already linked to another offset
+        assert!(id_tracker.set_new_link(10.into(), 3).is_err());
+        // Offset is already taken by another point
+        assert!(id_tracker.set_new_link(30.into(), 1).is_err());
+
+        assert_eq!(id_tracker.internal_id(10.into()), Some(1));
+        assert_eq!(id_tracker.internal_id(30.into()), None);
+        assert_eq!(id_tracker.external_id(3), None);
+        assert_eq!(id_tracker.points_count(), 2);
+    }
+
+    #[test]
+    fn test_sample_ids() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        for internal_id in 0..100 {
+            id_tracker
+                .set_link((internal_id as u64).into(), internal_id)
+                .unwrap();
+        }
+        for external_id in 0..50 {
+            id_tracker.drop((external_id * 2).into()).unwrap();
+        }
+
+        let sample = id_tracker.sample_ids(10, Some(42));
+        assert_eq!(sample.len(), 10);
+        assert_eq!(sample, id_tracker.sample_ids(10, Some(42)));
+        assert_ne!(sample, id_tracker.sample_ids(10, Some(43)));
+        assert_eq!(sample.iter().unique().count(), 10);
+        for external_id in &sample {
+            // Deleted points are never sampled
+            assert!(id_tracker.internal_id(external_id.clone()).is_some());
+        }
+
+        // All existing points, if there are not enough of them
+        let mut sample = id_tracker.sample_ids(1000, None);
+        sample.sort();
+        assert_eq!(sample, id_tracker.iter_external().collect::<Vec<_>>());
+        assert!(id_tracker.sample_ids(0, None).is_empty());
+    }
+
+    #[test]
+    fn test_relink_batch() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        id_tracker.set_link(10.into(), 0).unwrap();
+        id_tracker.set_link(20.into(), 1).unwrap();
+
+        id_tracker
+            .relink_batch(&[
+                (10.into(), 2),
+                (30.into(), 3),
+                // Same external id again, last link wins
+                (30.into(), 4),
+                // Offset of another point, which is dropped
+                (40.into(), 1),
+            ])
+            .unwrap();
+
+        assert_eq!(id_tracker.internal_id(10.into()), Some(2));
+        assert_eq!(id_tracker.internal_id(20.into()), None);
+        assert_eq!(id_tracker.internal_id(30.into()), Some(4));
+        assert_eq!(id_tracker.internal_id(40.into()), Some(1));
+        assert_eq!(id_tracker.points_count(), 3);
+        for internal_id in [0, 3] {
+            assert!(id_tracker.is_deleted(internal_id));
+            assert_eq!(id_tracker.external_id(internal_id), None);
+        }
+        for (external_id, internal_id) in id_tracker.iter_from(None) {
+            assert_eq!(id_tracker.external_id(internal_id), Some(external_id));
+        }
+    }
+
+    #[test]
+    fn test_rebuild_from() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let reopened_dir = Builder::new()
+            .prefix("reopened_storage_dir")
+            .tempdir()
+            .unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let mut id_tracker = SimpleIdTracker::open(db).unwrap();
+        for internal_id in 0..10 {
+            id_tracker
+                .set_link((internal_id as u64).into(), internal_id)
+                .unwrap();
+            id_tracker
+                .set_internal_version(internal_id, internal_id as SeqNumberType)
+                .unwrap();
+        }
+
+        // Compact even ids into the beginning, odd ids are dropped
+        id_tracker
+            .rebuild_from(
+                &mut (0..5)
+                    .map(|internal_id| (PointIdType::NumId(internal_id as u64 * 2), internal_id)),
+            )
+            .unwrap();
+        id_tracker.mapping_flusher()().unwrap();
+        id_tracker.versions_flusher()().unwrap();
+
+        copy_db_files(dir.path(), reopened_dir.path());
+        let reopened =
+            SimpleIdTracker::open(open_db(reopened_dir.path(), &[DB_VECTOR_CF]).unwrap()).unwrap();
+        for id_tracker in [&id_tracker, &reopened] {
+            assert_eq!(id_tracker.internal_size(), 5);
+            assert_eq!(id_tracker.points_count(), 5);
+            assert_eq!(id_tracker.internal_id(3.into()), None);
+            assert_eq!(id_tracker.internal_id(8.into()), Some(4));
+            assert_eq!(id_tracker.external_id(4), Some(8.into()));
+            // Version follows the point
+            assert_eq!(id_tracker.internal_version(4), Some(8));
+        }
+    }
+
+    #[test]
+    fn test_str_ids() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let str_id = |id: &str| PointIdType::Str(StrPointId::new(id));
+        let uuid_id = PointIdType::Uuid(Uuid::from_u128(123_u128));
+
+        let mut values: Vec<PointIdType> = vec![
+            str_id("doc-2024-00017"),
+            100.into(),
+            str_id("doc-2023-00001"),
+            uuid_id.clone(),
+            str_id("Doc"),
+            50.into(),
+        ];
+
+        {
+            let mut id_tracker = SimpleIdTracker::open(db.clone()).unwrap();
+            for (id, value) in values.iter().enumerate() {
+                id_tracker
+                    .set_link(value.clone(), id as PointOffsetType)
+                    .unwrap();
+                id_tracker
+                    .set_internal_version(id as PointOffsetType, 10 + id as SeqNumberType)
+                    .unwrap();
+            }
+            id_tracker.drop(str_id("Doc")).unwrap();
+            id_tracker.mapping_flusher()().unwrap();
+            id_tracker.versions_flusher()().unwrap();
+        }
+        values.retain(|value| *value != str_id("Doc"));
+
+        // Mappings of string ids are persisted
+        let id_tracker = SimpleIdTracker::open(db).unwrap();
+        assert_eq!(id_tracker.points_count(), 5);
+        assert_eq!(id_tracker.internal_id(str_id("doc-2024-00017")), Some(0));
+        assert_eq!(id_tracker.external_id(2), Some(str_id("doc-2023-00001")));
+        assert_eq!(id_tracker.internal_version(2), Some(12));
+        assert_eq!(id_tracker.internal_id(str_id("Doc")), None);
+
+        // Strings go after all other ids in lexicographic order
+        let sorted_from_tracker = id_tracker.iter_from(None).map(|(k, _)| k).collect_vec();
+        values.sort();
+        assert_eq!(sorted_from_tracker, values);
+        assert_eq!(
+            id_tracker.iter_external().collect_vec(),
+            sorted_from_tracker
+        );
+
+        let from_uuid = id_tracker
+            .iter_from(Some(uuid_id.clone()))
+            .map(|(k, _)| k)
+            .collect_vec();
+        assert_eq!(
+            from_uuid,
+            vec![uuid_id, str_id("doc-2023-00001"), str_id("doc-2024-00017")]
+        );
+
+        let from_str = id_tracker
+            .iter_from(Some(str_id("doc-2024")))
+            .map(|(k, _)| k)
+            .collect_vec();
+        assert_eq!(from_str, vec![str_id("doc-2024-00017")]);
+    }
 }
@@ -541,6 +541,21 @@ impl PayloadFieldIndex for GeoMapIndex {
             )));
         }
 
+        if let Some(geo_polygon) = &condition.geo_polygon {
+            // Pre-select candidates by the bounding box, precise check is only done for them
+            let geo_hashes = rectangle_hashes(&geo_polygon.bounding_box(), GEO_QUERY_MAX_REGION);
+            let geo_condition_copy = geo_polygon.clone();
+            return Some(Box::new(self.get_iterator(geo_hashes).filter(
+                move |point| {
+                    self.point_to_values
+                        .get(*point as usize)
+                        .unwrap()
+                        .iter()
+                        .any(|point| geo_condition_copy.check_point(point.lon, point.lat))
+                },
+            )));
+        }
+
         None
     }
 
@@ -563,6 +578,15 @@ impl PayloadFieldIndex for GeoMapIndex {
             return Some(estimation);
         }
 
+        if let Some(geo_polygon) = &condition.geo_polygon {
+            let geo_hashes = rectangle_hashes(&geo_polygon.bounding_box(), GEO_QUERY_MAX_REGION);
+            let mut estimation = self.match_cardinality(&geo_hashes);
+            estimation
+                .primary_clauses
+                .push(PrimaryCondition::Condition(condition.clone()));
+            return Some(estimation);
+        }
+
         None
     }
 
@@ -600,7 +624,7 @@ mod tests {
     use crate::common::rocksdb_wrapper::open_db_with_existing_cf;
     use crate::common::utils::MultiValue;
     use crate::fixtures::payload_fixtures::random_geo_payload;
-    use crate::types::GeoRadius;
+    use crate::types::{GeoLineString, GeoPolygon, GeoRadius};
 
     const NYC: GeoPoint = GeoPoint {
         lat: 40.75798,
@@ -713,6 +737,86 @@ mod tests {
         assert_eq!(matched_points, indexed_matched_points);
     }
 
+    fn geo_polygon(exterior: &[(f64, f64)], interiors: &[&[(f64, f64)]]) -> GeoPolygon {
+        let ring = |points: &[(f64, f64)]| GeoLineString {
+            points: points
+                .iter()
+                .map(|&(lon, lat)| GeoPoint { lon, lat })
+                .collect(),
+        };
+        GeoPolygon {
+            exterior: ring(exterior),
+            interiors: Some(interiors.iter().map(|points| ring(points)).collect()),
+        }
+    }
+
+    #[test]
+    fn geo_polygon_indexed_filtering() {
+        let field_index = build_random_index(1000, 5);
+
+        let polygons = [
+            // Concave polygon with a hole around NYC
+            geo_polygon(
+                &[
+                    (-100., 20.),
+                    (-50., 20.),
+                    (-50., 60.),
+                    (-70., 35.),
+                    (-100., 60.),
+                    (-100., 20.),
+                ],
+                &[&[
+                    (-90., 25.),
+                    (-80., 25.),
+                    (-80., 30.),
+                    (-90., 30.),
+                    (-90., 25.),
+                ]],
+            ),
+            // Polygon crossing the antimeridian
+            geo_polygon(
+                &[
+                    (150., -40.),
+                    (-150., -40.),
+                    (-150., 40.),
+                    (150., 40.),
+                    (150., -40.),
+                ],
+                &[],
+            ),
+        ];
+
+        for geo_polygon in polygons {
+            let mut matched_points = field_index
+                .point_to_values
+                .iter()
+                .enumerate()
+                .filter(|(_idx, geo_points)| {
+                    geo_points
+                        .iter()
+                        .any(|geo_point| geo_polygon.check_point(geo_point.lon, geo_point.lat))
+                })
+                .map(|(idx, _geo_points)| idx as PointOffsetType)
+                .collect_vec();
+
+            assert!(!matched_points.is_empty());
+
+            let field_condition = FieldCondition::new_geo_polygon("test".to_string(), geo_polygon);
+
+            let mut indexed_matched_points =
+                field_index.filter(&field_condition).unwrap().collect_vec();
+
+            matched_points.sort_unstable();
+            indexed_matched_points.sort_unstable();
+
+            assert_eq!(matched_points, indexed_matched_points);
+
+            let card = field_index.estimate_cardinality(&field_condition).unwrap();
+            assert!(card.min <= matched_points.len());
+            assert!(card.max >= matched_points.len());
+        }
+    }
+
     #[test]
     fn test_payload_blocks() {
         let field_index = build_random_index(1000, 5);
@@ -5,7 +5,7 @@ use rocksdb::DB;
 
 use crate::index::field_index::full_text_index::text_index::FullTextIndex;
 use crate::index::field_index::geo_index::GeoMapIndex;
-use crate::index::field_index::map_index::MapIndex;
+use crate::index::field_index::map_index::{InsensitiveMapIndex, MapIndex};
 use crate::index::field_index::numeric_index::NumericIndex;
 use crate::index::field_index::FieldIndex;
 use crate::types::{
@@ -43,6 +43,15 @@ pub fn index_selector(
             PayloadSchemaParams::Text(text_index_params) => vec![FieldIndex::FullTextIndex(
                 FullTextIndex::new(db, text_index_params.clone(), field),
             )],
+            PayloadSchemaParams::Keyword(keyword_index_params) => {
+                if keyword_index_params.insensitive.unwrap_or(false) {
+                    vec![FieldIndex::InsensitiveKeywordIndex(
+                        InsensitiveMapIndex::new(db, field),
+                    )]
+                } else {
+                    vec![FieldIndex::KeywordIndex(MapIndex::new(db, field))]
+                }
+            }
         },
     }
 }
@@ -5,6 +5,7 @@ use std::iter;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use itertools::Itertools;
 use parking_lot::RwLock;
 use rocksdb::DB;
 use serde_json::Value;
@@ -18,8 +19,8 @@ use crate::index::field_index::{
 use crate::index::query_estimator::combine_should_estimations;
 use crate::telemetry::PayloadIndexTelemetry;
 use crate::types::{
-    AnyVariants, FieldCondition, IntPayloadType, Match, MatchAny, MatchValue, PayloadKeyType,
-    PointOffsetType, ValueVariants,
+    AnyVariants, FieldCondition, IntPayloadType, Match, MatchAny, MatchInsensitive, MatchValue,
+    PayloadKeyType, PointOffsetType, ValueVariants,
 };
 
 /// HashMap-based type of index
@@ -94,6 +95,16 @@ impl<N: Hash + Eq + Clone + Display + FromStr> MapIndex<N> {
         }
     }
 
+    /// Estimation of points with any of the given values
+    pub fn match_any_cardinality(&self, values: &[N]) -> CardinalityEstimation {
+        let estimations = values
+            .iter()
+            .unique()
+            .map(|value| self.match_cardinality(value))
+            .collect_vec();
+        combine_should_estimations(&estimations, self.indexed_points)
+    }
+
     pub fn get_values(&self, idx: PointOffsetType) -> Option<&Vec<N>> {
         self.point_to_values.get(idx as usize)
     }
@@ -135,6 +146,21 @@ impl<N: Hash + Eq + Clone + Display + FromStr> MapIndex<N> {
             .unwrap_or_else(|| Box::new(iter::empty::<PointOffsetType>()))
     }
 
+    /// Union of the points with any of the given values
+    fn get_any_iterator(&self, values: &[N]) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        // Postings are looked up in advance, so the iterator only borrows the index
+        let postings = values
+            .iter()
+            .filter_map(|value| self.map.get(value))
+            .collect_vec();
+        Box::new(
+            postings
+                .into_iter()
+                .flat_map(|ids| ids.iter().copied())
+                .unique(),
+        )
+    }
+
     fn encode_db_record(value: &N, idx: PointOffsetType) -> String {
         format!("{value}/{idx}")
     }
@@ -205,6 +231,9 @@ impl PayloadFieldIndex for MapIndex<String> {
             Some(Match::Value(MatchValue {
                 value: ValueVariants::Keyword(keyword),
             })) => Some(self.get_iterator(keyword)),
+            Some(Match::Any(MatchAny {
+                any: AnyVariants::Keywords(keywords),
+            })) => Some(self.get_any_iterator(keywords)),
             _ => None,
         }
     }
@@ -223,14 +252,11 @@ impl PayloadFieldIndex for MapIndex<String> {
             Some(Match::Any(MatchAny {
                 any: AnyVariants::Keywords(keywords),
             })) => {
-                let estimations = keywords
-                    .iter()
-                    .map(|keyword| self.match_cardinality(keyword))
-                    .collect::<Vec<_>>();
-                Some(combine_should_estimations(
-                    &estimations,
-                    self.indexed_points,
-                ))
+                let mut estimation = self.match_any_cardinality(keywords);
+                estimation
+                    .primary_clauses
+                    .push(PrimaryCondition::Condition(condition.clone()));
+                Some(estimation)
             }
             _ => None,
         }
@@ -282,6 +308,9 @@ impl PayloadFieldIndex for MapIndex<IntPayloadType> {
             Some(Match::Value(MatchValue {
                 value: ValueVariants::Integer(integer),
             })) => Some(self.get_iterator(integer)),
+            Some(Match::Any(MatchAny {
+                any: AnyVariants::Integers(integers),
+            })) => Some(self.get_any_iterator(integers)),
             _ => None,
         }
     }
@@ -300,14 +329,11 @@ impl PayloadFieldIndex for MapIndex<IntPayloadType> {
             Some(Match::Any(MatchAny {
                 any: AnyVariants::Integers(integers),
             })) => {
-                let estimations = integers
-                    .iter()
-                    .map(|integer| self.match_cardinality(integer))
-                    .collect::<Vec<_>>();
-                Some(combine_should_estimations(
-                    &estimations,
-                    self.indexed_points,
-                ))
+                let mut estimation = self.match_any_cardinality(integers);
+                estimation
+                    .primary_clauses
+                    .push(PrimaryCondition::Condition(condition.clone()));
+                Some(estimation)
             }
             _ => None,
         }
@@ -351,6 +377,125 @@ impl ValueIndexer<String> for MapIndex<String> {
     }
 }
 
+/// Keyword index of lowercased values
+///
+/// Serves case-insensitive matches only, exact matches have to be checked against the payload.
+pub struct InsensitiveMapIndex {
+    index: MapIndex<String>,
+}
+
+impl InsensitiveMapIndex {
+    pub fn new(db: Arc<RwLock<DB>>, field_name: &str) -> Self {
+        // Separate column family, so values of the case-sensitive index are never loaded here
+        Self {
+            index: MapIndex::new(db, &format!("{field_name}_insensitive")),
+        }
+    }
+
+    pub fn recreate(&self) -> OperationResult<()> {
+        self.index.recreate()
+    }
+
+    pub fn match_cardinality(&self, keyword: &str) -> CardinalityEstimation {
+        self.index.match_cardinality(&keyword.to_lowercase())
+    }
+
+    /// Lowercased values of the point
+    pub fn get_values(&self, idx: PointOffsetType) -> Option<&Vec<String>> {
+        self.index.get_values(idx)
+    }
+
+    pub fn get_telemetry_data(&self) -> PayloadIndexTelemetry {
+        self.index.get_telemetry_data()
+    }
+}
+
+impl PayloadFieldIndex for InsensitiveMapIndex {
+    fn indexed_points(&self) -> usize {
+        self.index.indexed_points
+    }
+
+    fn load(&mut self) -> OperationResult<bool> {
+        self.index.load()
+    }
+
+    fn clear(self) -> OperationResult<()> {
+        self.index.clear()
+    }
+
+    fn flusher(&self) -> Flusher {
+        self.index.flusher()
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        match &condition.r#match {
+            Some(Match::Insensitive(MatchInsensitive { insensitive })) => {
+                Some(self.index.get_iterator(&insensitive.to_lowercase()))
+            }
+            _ => None,
+        }
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        match &condition.r#match {
+            Some(Match::Insensitive(MatchInsensitive { insensitive })) => {
+                let mut estimation = self.match_cardinality(insensitive);
+                estimation
+                    .primary_clauses
+                    .push(PrimaryCondition::Condition(condition.clone()));
+                Some(estimation)
+            }
+            _ => None,
+        }
+    }
+
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        let iter = self
+            .index
+            .map
+            .iter()
+            .filter(move |(_value, point_ids)| point_ids.len() > threshold)
+            .map(move |(value, point_ids)| PayloadBlockCondition {
+                condition: FieldCondition::new_match(
+                    key.clone(),
+                    Match::Insensitive(MatchInsensitive {
+                        insensitive: value.to_owned(),
+                    }),
+                ),
+                cardinality: point_ids.len(),
+            });
+        Box::new(iter)
+    }
+
+    fn count_indexed_points(&self) -> usize {
+        self.index.indexed_points
+    }
+}
+
+impl ValueIndexer<String> for InsensitiveMapIndex {
+    fn add_many(&mut self, id: PointOffsetType, values: Vec<String>) -> OperationResult<()> {
+        self.index.add_many_to_map(id, values)
+    }
+
+    fn get_value(&self, value: &Value) -> Option<String> {
+        if let Value::String(keyword) = value {
+            return Some(keyword.to_lowercase());
+        }
+        None
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) -> OperationResult<()> {
+        self.index.remove_point(id)
+    }
+}
+
 impl ValueIndexer<IntPayloadType> for MapIndex<IntPayloadType> {
     fn add_many(
         &mut self,
@@ -379,10 +524,12 @@ mod tests {
     use std::iter::FromIterator;
     use std::path::Path;
 
+    use serde_json::json;
     use tempfile::Builder;
 
     use super::*;
     use crate::common::rocksdb_wrapper::open_db_with_existing_cf;
+    use crate::common::utils::MultiValue;
 
     const FIELD_NAME: &str = "test";
 
@@ -419,6 +566,115 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_match_any_filter() {
+        let tmp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+        let mut index = MapIndex::<IntPayloadType>::new(
+            open_db_with_existing_cf(tmp_dir.path()).unwrap(),
+            FIELD_NAME,
+        );
+        index.recreate().unwrap();
+
+        let num_points = 10_000;
+        for idx in 0..num_points {
+            // Every point has two values, so the same point is in several postings
+            let values = vec![idx % 1000, idx % 1000 + 1];
+            index
+                .add_many_to_map(idx as PointOffsetType, values)
+                .unwrap();
+        }
+
+        // Thousands of values, most of which are not in the index
+        let integers: Vec<IntPayloadType> = (0..5000).map(|i| i * 10).chain([0, 10]).collect();
+        let condition = FieldCondition::new_match("test".to_string(), integers.clone().into());
+
+        let mut filtered = index.filter(&condition).unwrap().collect_vec();
+        filtered.sort_unstable();
+        let expected = (0..num_points)
+            .filter(|idx| {
+                let values = [idx % 1000, idx % 1000 + 1];
+                values.iter().any(|value| integers.contains(value))
+            })
+            .map(|idx| idx as PointOffsetType)
+            .collect_vec();
+        assert_eq!(filtered, expected);
+
+        let estimation = index.estimate_cardinality(&condition).unwrap();
+        assert_eq!(
+            estimation.primary_clauses,
+            vec![PrimaryCondition::Condition(condition.clone())]
+        );
+        assert!(estimation.min <= expected.len());
+        assert!(estimation.exp <= estimation.max);
+        assert!(estimation.max >= expected.len());
+        assert!(estimation.max <= num_points as usize);
+
+        let empty =
+            FieldCondition::new_match("test".to_string(), Vec::<IntPayloadType>::new().into());
+        assert_eq!(index.filter(&empty).unwrap().count(), 0);
+        assert_eq!(index.estimate_cardinality(&empty).unwrap().max, 0);
+    }
+
+    #[test]
+    fn test_insensitive_map_index() {
+        let tmp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+        let db = open_db_with_existing_cf(tmp_dir.path()).unwrap();
+        let mut index = InsensitiveMapIndex::new(db.clone(), FIELD_NAME);
+        index.recreate().unwrap();
+
+        let payloads = [
+            json!("Äpfel"),
+            json!(["äpfel", "Birnen"]),
+            json!("ÄPFEL"),
+            json!("apfel"),
+            json!(42),
+        ];
+        for (idx, payload) in payloads.iter().enumerate() {
+            index
+                .add_point(idx as PointOffsetType, &MultiValue::one(payload))
+                .unwrap();
+        }
+        index.flusher()().unwrap();
+
+        let insensitive = |keyword: &str| {
+            FieldCondition::new_match(
+                FIELD_NAME.to_string(),
+                Match::Insensitive(MatchInsensitive {
+                    insensitive: keyword.to_string(),
+                }),
+            )
+        };
+        let filtered = |index: &InsensitiveMapIndex, keyword: &str| {
+            index
+                .filter(&insensitive(keyword))
+                .unwrap()
+                .sorted()
+                .collect_vec()
+        };
+
+        assert_eq!(filtered(&index, "äPfEl"), vec![0, 1, 2]);
+        assert_eq!(filtered(&index, "BIRNEN"), vec![1]);
+        assert_eq!(
+            index
+                .estimate_cardinality(&insensitive("Äpfel"))
+                .unwrap()
+                .exp,
+            3
+        );
+        assert_eq!(index.count_indexed_points(), 4);
+        // Exact matches are not served by the lowercased values
+        assert!(index
+            .filter(&FieldCondition::new_match(
+                FIELD_NAME.to_string(),
+                "Äpfel".to_string().into()
+            ))
+            .is_none());
+
+        let mut loaded = InsensitiveMapIndex::new(db, FIELD_NAME);
+        assert!(loaded.load().unwrap());
+        assert_eq!(filtered(&loaded, "ÄPFEL"), vec![0, 1, 2]);
+    }
+
     #[test]
     fn test_int_disk_map_index() {
         let data = vec![
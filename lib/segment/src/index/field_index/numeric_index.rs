@@ -1,4 +1,4 @@
-use std::cmp::{max, min};
+use std::cmp::{max, min, Ordering};
 use std::collections::BTreeMap;
 use std::ops::Bound;
 use std::ops::Bound::{Excluded, Included, Unbounded};
@@ -22,7 +22,8 @@ use crate::index::key_encoding::{
 };
 use crate::telemetry::PayloadIndexTelemetry;
 use crate::types::{
-    FieldCondition, FloatPayloadType, IntPayloadType, PayloadKeyType, PointOffsetType, Range,
+    Direction, FieldCondition, FloatPayloadType, IntPayloadType, PayloadKeyType, PointOffsetType,
+    Range,
 };
 
 const HISTOGRAM_MAX_BUCKET_SIZE: usize = 10_000;
@@ -167,6 +168,49 @@ impl<T: Encodable + Numericable> NumericIndex<T> {
         self.point_to_values.get(idx as usize)
     }
 
+    /// Points with their values in the `direction`, starting from the `start_from` value inclusive
+    ///
+    /// Point with several values is yielded once, with its first value in the direction.
+    pub fn ordered_values(
+        &self,
+        start_from: Option<f64>,
+        direction: Direction,
+    ) -> Box<dyn Iterator<Item = (f64, PointOffsetType)> + '_> {
+        let decode = |(key, _): (&Vec<u8>, &PointOffsetType)| {
+            let (idx, value) = T::decode_key(key);
+            (value.to_f64(), idx)
+        };
+        // Bounds are rounded for the integers, so the values are checked against `start_from` too
+        let values: Box<dyn Iterator<Item = (f64, PointOffsetType)> + '_> = match direction {
+            Direction::Asc => {
+                let start_bound = start_from.map_or(Unbounded, |start_from| {
+                    Included(T::from_f64(start_from).encode_key(PointOffsetType::MIN))
+                });
+                Box::new(self.map.range((start_bound, Unbounded)).map(decode))
+            }
+            Direction::Desc => {
+                let end_bound = start_from.map_or(Unbounded, |start_from| {
+                    Included(T::from_f64(start_from).encode_key(PointOffsetType::MAX))
+                });
+                Box::new(self.map.range((Unbounded, end_bound)).rev().map(decode))
+            }
+        };
+        Box::new(values.filter(move |&(value, idx)| {
+            let is_started = start_from.map_or(true, |start_from| {
+                direction.compare(value, start_from) != Ordering::Less
+            });
+            is_started && self.is_first_value(idx, value, direction)
+        }))
+    }
+
+    fn is_first_value(&self, idx: PointOffsetType, value: f64, direction: Direction) -> bool {
+        self.get_values(idx).map_or(false, |values| {
+            values
+                .iter()
+                .all(|other| direction.compare(value, other.to_f64()) != Ordering::Greater)
+        })
+    }
+
     #[allow(clippy::manual_clamp)] // false positive
     fn range_cardinality(&self, range: &Range) -> CardinalityEstimation {
         let lbound = if let Some(lte) = range.lte {
@@ -794,6 +838,7 @@ mod tests {
             range: Some(rng),
             geo_bounding_box: None,
             geo_radius: None,
+            geo_polygon: None,
             values_count: None,
         };
 
@@ -801,4 +846,36 @@ mod tests {
 
         assert_eq!(offsets, result);
     }
+
+    #[test]
+    fn test_ordered_values() {
+        let (_tmp_dir, mut index) = get_index();
+        index.add_many_to_list(0, [3.0, 7.0]).unwrap();
+        index.add_many_to_list(1, [5.0]).unwrap();
+        index.add_many_to_list(2, [1.0]).unwrap();
+        index.add_many_to_list(3, [5.0, 5.0]).unwrap();
+        index.add_many_to_list(4, []).unwrap();
+
+        let ordered = |start_from, direction| {
+            index
+                .ordered_values(start_from, direction)
+                .collect::<Vec<_>>()
+        };
+
+        // Each point is yielded once, with its first value in the direction
+        assert_eq!(
+            ordered(None, Direction::Asc),
+            vec![(1.0, 2), (3.0, 0), (5.0, 1), (5.0, 3)]
+        );
+        assert_eq!(
+            ordered(None, Direction::Desc),
+            vec![(7.0, 0), (5.0, 3), (5.0, 1), (1.0, 2)]
+        );
+        // Point, which starts before the start value, is skipped
+        assert_eq!(ordered(Some(4.0), Direction::Asc), vec![(5.0, 1), (5.0, 3)]);
+        assert_eq!(
+            ordered(Some(5.0), Direction::Desc),
+            vec![(5.0, 3), (5.0, 1), (1.0, 2)]
+        );
+    }
 }
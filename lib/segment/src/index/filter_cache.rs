@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bitvec::vec::BitVec;
+use parking_lot::Mutex;
+use serde_json::Value;
+
+use crate::payload_storage::FilterContext;
+use crate::telemetry::FilterCacheTelemetry;
+use crate::types::{Filter, PointOffsetType};
+
+/// Small LRU cache of the points, matched by filters
+///
+/// Cached points are only valid for the current state of the segment,
+/// so the cache must be invalidated on every update.
+pub struct FilterCache {
+    capacity: usize,
+    state: Mutex<FilterCacheState>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+struct FilterCacheState {
+    /// Incremented on every invalidation, prevents caching of points resolved before it
+    generation: usize,
+    /// Most recently used entries first
+    entries: VecDeque<(String, Arc<BitVec>)>,
+}
+
+impl FilterCache {
+    /// Create cache for `capacity` filters, zero capacity disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(FilterCacheState {
+                generation: 0,
+                entries: VecDeque::with_capacity(capacity),
+            }),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Key of the filter in cache
+    ///
+    /// Filter is simplified, and the sets of ids and values are sorted, so equal filters share the entry.
+    /// Keys are compared as a whole, so hash collisions can't produce wrong results.
+    pub fn key(filter: &Filter) -> String {
+        let mut key = serde_json::to_value(filter.simplify()).expect("filter is serializable");
+        sort_sets(&mut key);
+        key.to_string()
+    }
+
+    /// Current generation of the cache, should be obtained before resolving the points to cache
+    pub fn generation(&self) -> usize {
+        self.state.lock().generation
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<BitVec>> {
+        let mut state = self.state.lock();
+        let position = state
+            .entries
+            .iter()
+            .position(|(entry_key, _)| entry_key == key);
+        match position {
+            Some(position) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                let entry = state.entries.remove(position).unwrap();
+                let points = entry.1.clone();
+                state.entries.push_front(entry);
+                Some(points)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Save resolved points of the filter
+    ///
+    /// Points are ignored, if the cache was invalidated after obtaining `generation`,
+    /// as they might be resolved from the outdated state of the segment.
+    pub fn insert(&self, key: String, points: Arc<BitVec>, generation: usize) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut state = self.state.lock();
+        if state.generation != generation {
+            return;
+        }
+        state.entries.retain(|(entry_key, _)| entry_key != &key);
+        state.entries.push_front((key, points));
+        state.entries.truncate(self.capacity);
+    }
+
+    /// Drop all cached points, must be called on any change of the segment
+    pub fn invalidate(&self) {
+        let mut state = self.state.lock();
+        state.generation = state.generation.wrapping_add(1);
+        state.entries.clear();
+    }
+
+    pub fn get_telemetry_data(&self) -> FilterCacheTelemetry {
+        FilterCacheTelemetry {
+            capacity: self.capacity,
+            size: self.state.lock().entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Sort values of `has_id`, `any` and `except` conditions, which order doesn't matter
+fn sort_sets(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match (key.as_str(), value) {
+                    ("has_id" | "any" | "except", Value::Array(values)) => {
+                        values.sort_by_cached_key(|value| value.to_string())
+                    }
+                    (_, value) => sort_sets(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(sort_sets),
+        _ => {}
+    }
+}
+
+/// Filter context, which checks points against cached filter results
+pub struct CachedFilterContext {
+    points: Arc<BitVec>,
+}
+
+impl CachedFilterContext {
+    pub fn new(points: Arc<BitVec>) -> Self {
+        Self { points }
+    }
+}
+
+impl FilterContext for CachedFilterContext {
+    fn check(&self, point_id: PointOffsetType) -> bool {
+        let point_id = point_id as usize;
+        point_id < self.points.len() && self.points[point_id]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(ids: &[usize]) -> Arc<BitVec> {
+        let mut points = BitVec::repeat(false, 10);
+        for id in ids {
+            points.set(*id, true);
+        }
+        Arc::new(points)
+    }
+
+    #[test]
+    fn test_filter_cache_lru() {
+        let cache = FilterCache::new(2);
+
+        cache.insert("a".to_string(), points(&[1]), cache.generation());
+        cache.insert("b".to_string(), points(&[2]), cache.generation());
+        assert!(cache.get("a").is_some());
+
+        // `b` is the least recently used one
+        cache.insert("c".to_string(), points(&[3]), cache.generation());
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get("a").unwrap(), points(&[1]));
+        assert_eq!(cache.get("c").unwrap(), points(&[3]));
+
+        let telemetry = cache.get_telemetry_data();
+        assert_eq!(telemetry.size, 2);
+        assert_eq!(telemetry.hits, 3);
+        assert_eq!(telemetry.misses, 1);
+    }
+
+    #[test]
+    fn test_filter_cache_invalidation() {
+        let cache = FilterCache::new(2);
+
+        cache.insert("a".to_string(), points(&[1]), cache.generation());
+        let generation = cache.generation();
+        cache.invalidate();
+        assert!(cache.get("a").is_none());
+
+        // Points, resolved before invalidation, are outdated
+        cache.insert("a".to_string(), points(&[1]), generation);
+        assert!(cache.get("a").is_none());
+
+        cache.insert("a".to_string(), points(&[1, 2]), cache.generation());
+        assert_eq!(cache.get("a").unwrap(), points(&[1, 2]));
+    }
+
+    #[test]
+    fn test_disabled_filter_cache() {
+        let cache = FilterCache::new(0);
+        assert!(!cache.is_enabled());
+        cache.insert("a".to_string(), points(&[1]), cache.generation());
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn test_filter_cache_key() {
+        let filter = |json: &str| serde_json::from_str::<Filter>(json).unwrap();
+        let ids: Vec<u64> = (0..100).collect();
+        let reversed_ids: Vec<u64> = ids.iter().rev().copied().collect();
+        let has_id = |ids: &[u64]| {
+            filter(&format!(
+                r#"{{"must": [{{"has_id": {}}}]}}"#,
+                serde_json::to_string(ids).unwrap()
+            ))
+        };
+
+        // Sets of the ids are hashed differently, but the filters are equal
+        let cache = FilterCache::new(2);
+        cache.insert(
+            FilterCache::key(&has_id(&ids)),
+            points(&[1]),
+            cache.generation(),
+        );
+        assert_eq!(
+            cache
+                .get(&FilterCache::key(&has_id(&reversed_ids)))
+                .unwrap(),
+            points(&[1])
+        );
+
+        assert_eq!(
+            FilterCache::key(&filter(
+                r#"{"must": [{"key": "color", "match": {"any": ["red", "blue"]}}]}"#
+            )),
+            FilterCache::key(&filter(
+                r#"{"must": [{"key": "color", "match": {"any": ["blue", "red"]}}]}"#
+            ))
+        );
+        assert_ne!(
+            FilterCache::key(&has_id(&ids[..10])),
+            FilterCache::key(&has_id(&ids))
+        );
+    }
+
+    #[test]
+    fn test_cached_filter_context() {
+        let context = CachedFilterContext::new(points(&[1, 5]));
+        assert!(context.check(1));
+        assert!(context.check(5));
+        assert!(!context.check(2));
+        // Points, added after caching, are out of range
+        assert!(!context.check(100));
+    }
+}
@@ -0,0 +1,143 @@
+//! Estimation of the peak memory of the HNSW index build, checked against the budget of the index config
+
+use std::cmp::max;
+use std::mem::size_of;
+
+use parking_lot::RwLock;
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::types::{BuildMemoryEstimate, PointOffsetType, VectorElementType};
+
+/// Parameters of the build, the memory is estimated by
+#[derive(Debug, Clone, Copy)]
+pub struct BuildMemoryParams {
+    pub num_points: usize,
+    pub dim: usize,
+    pub m: usize,
+    pub m0: usize,
+    /// M on level 0 of the graphs of the payload blocks, if they are built
+    pub payload_m0: Option<usize>,
+    pub threads: usize,
+    /// Built links are loaded into RAM, rather than read from the file
+    pub links_in_ram: bool,
+}
+
+/// Expected number of the levels of a point above level 0
+///
+/// Level `l` is reached with probability `m^-l`, so the sum is `1 / (m - 1)`.
+fn expected_upper_levels(m: usize) -> f64 {
+    1.0 / (max(m, 2) - 1) as f64
+}
+
+/// Memory of a graph under construction with `num_points` containers, with `linked_points` of them linked
+fn builder_bytes(num_points: usize, linked_points: usize, m: usize, m0: usize) -> usize {
+    let upper_levels = expected_upper_levels(m);
+    let container_bytes = size_of::<Vec<RwLock<Vec<PointOffsetType>>>>() as f64
+        + (1.0 + upper_levels) * size_of::<RwLock<Vec<PointOffsetType>>>() as f64;
+    let links_bytes = (m0 as f64 + m as f64 * upper_levels) * size_of::<PointOffsetType>() as f64;
+    (num_points as f64 * container_bytes + linked_points as f64 * links_bytes) as usize
+}
+
+pub fn estimate_build_memory(params: BuildMemoryParams) -> BuildMemoryEstimate {
+    let BuildMemoryParams {
+        num_points,
+        dim,
+        m,
+        m0,
+        payload_m0,
+        threads,
+        links_in_ram,
+    } = params;
+
+    // Links of the payload blocks are merged into the main graph
+    let main_m0 = m0 + payload_m0.unwrap_or(0);
+    let main_graph_bytes = builder_bytes(num_points, num_points, m, main_m0);
+    // Payload blocks are built one by one, each of them covers up to `2 / m` of the points
+    let payload_graph_bytes = payload_m0
+        .map(|payload_m0| builder_bytes(num_points, num_points * 2 / max(m, 1), m, payload_m0))
+        .unwrap_or(0);
+    // Each thread has its own visited list and a copy of the vector, being linked
+    let threads_bytes =
+        threads * (num_points + 2 * dim * size_of::<VectorElementType>()) + num_points;
+    let linking_bytes = main_graph_bytes + payload_graph_bytes + threads_bytes;
+
+    // Links are released while they are written in slices, and then loaded from the file.
+    // Reindex is kept by the converter until the links are loaded
+    let reindex_bytes = num_points * (size_of::<PointOffsetType>() + size_of::<usize>());
+    let converted_bytes = if links_in_ram {
+        let upper_levels = expected_upper_levels(m);
+        let links = num_points as f64 * (main_m0 as f64 + m as f64 * upper_levels);
+        let offsets = num_points as f64 * (1.0 + upper_levels);
+        (links * size_of::<PointOffsetType>() as f64 + offsets * size_of::<u64>() as f64) as usize
+            + num_points * size_of::<PointOffsetType>()
+    } else {
+        0
+    };
+
+    BuildMemoryEstimate {
+        linking_bytes,
+        converted_bytes,
+        peak_bytes: max(linking_bytes, converted_bytes + reindex_bytes),
+    }
+}
+
+/// Refuse to start the build, if its estimation exceeds the budget in bytes, `None` - not limited
+pub fn check_build_memory(
+    estimate: &BuildMemoryEstimate,
+    budget_bytes: Option<usize>,
+) -> OperationResult<()> {
+    match budget_bytes {
+        Some(budget_bytes) if estimate.peak_bytes > budget_bytes => {
+            Err(OperationError::MemoryBudgetExceeded {
+                estimated_bytes: estimate.peak_bytes,
+                budget_bytes,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_build_memory() {
+        let params = BuildMemoryParams {
+            num_points: 1_000_000,
+            dim: 128,
+            m: 16,
+            m0: 32,
+            payload_m0: None,
+            threads: 8,
+            links_in_ram: true,
+        };
+        let estimate = estimate_build_memory(params);
+        // At least the links of level 0
+        assert!(estimate.linking_bytes > 1_000_000 * 32 * size_of::<PointOffsetType>());
+        assert!(estimate.peak_bytes >= estimate.linking_bytes);
+
+        // Links of the on-disk index are not loaded
+        let on_disk = estimate_build_memory(BuildMemoryParams {
+            links_in_ram: false,
+            ..params
+        });
+        assert_eq!(on_disk.converted_bytes, 0);
+        assert_eq!(on_disk.linking_bytes, estimate.linking_bytes);
+
+        // Payload graphs require more
+        let with_payload = estimate_build_memory(BuildMemoryParams {
+            payload_m0: Some(32),
+            ..params
+        });
+        assert!(with_payload.peak_bytes > estimate.peak_bytes);
+
+        // Estimation grows with M
+        let larger_m = estimate_build_memory(BuildMemoryParams {
+            m: 32,
+            m0: 64,
+            ..params
+        });
+        assert!(larger_m.peak_bytes > estimate.peak_bytes);
+    }
+}
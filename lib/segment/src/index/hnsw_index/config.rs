@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::common::cpu::get_num_cpus;
 use crate::common::file_operations::{atomic_save_json, read_json};
 use crate::entry::entry_point::OperationResult;
+use crate::types::{EfPolicy, NeighborSelection};
 
 pub const HNSW_INDEX_CONFIG_FILE: &str = "hnsw_config.json";
 
@@ -25,8 +26,34 @@ pub struct HnswGraphConfig {
     pub payload_m: Option<usize>,
     #[serde(default)]
     pub payload_m0: Option<usize>,
+    #[serde(default)]
+    pub neighbor_selection: NeighborSelection,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Link points into the built graph on insertion
+    #[serde(default)]
+    pub incremental: bool,
+    #[serde(default)]
+    pub ef_policy: Option<EfPolicy>,
+    /// Max memory of a single build in megabytes, not limited if not set or 0
+    #[serde(default)]
+    pub max_build_memory_mb: Option<usize>,
+    /// Factor of the `ef` policy, calibrated after the last build
+    #[serde(default)]
+    pub ef_calibration: Option<EfCalibration>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
+pub struct EfCalibration {
+    /// Target recall of the policy at the time of the calibration
+    pub target_recall: f32,
+    pub factor: f32,
+    /// Mean recall, reached with the factor
+    pub recall: f32,
+}
+
+impl Eq for EfCalibration {}
+
 impl HnswGraphConfig {
     pub fn new(
         m: usize,
@@ -34,6 +61,8 @@ impl HnswGraphConfig {
         indexing_threshold: usize,
         max_indexing_threads: usize,
         payload_m: Option<usize>,
+        neighbor_selection: NeighborSelection,
+        seed: Option<u64>,
     ) -> Self {
         HnswGraphConfig {
             m,
@@ -44,6 +73,12 @@ impl HnswGraphConfig {
             max_indexing_threads,
             payload_m,
             payload_m0: payload_m.map(|v| v * 2),
+            neighbor_selection,
+            seed,
+            incremental: false,
+            ef_policy: None,
+            max_build_memory_mb: None,
+            ef_calibration: None,
         }
     }
 
@@ -59,6 +94,13 @@ impl HnswGraphConfig {
         Ok(atomic_save_json(path, self)?)
     }
 
+    /// Memory budget of a single build in bytes, if it is limited
+    pub fn build_memory_budget(&self) -> Option<usize> {
+        self.max_build_memory_mb
+            .filter(|mb| *mb > 0)
+            .map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
     pub fn max_rayon_threads(&self) -> usize {
         let max_threads = self.max_indexing_threads;
 
@@ -93,6 +93,11 @@ impl EntryPoints {
         None
     }
 
+    /// Stop using the point as a main entry point, e.g. before it is relinked
+    pub fn remove_point(&mut self, point_id: PointOffsetType) {
+        self.entry_points.retain(|entry| entry.point_id != point_id);
+    }
+
     /// Find the highest `EntryPoint` which satisfies filtering condition of `checker`
     pub fn get_entry_point<F>(&self, checker: F) -> Option<EntryPoint>
     where
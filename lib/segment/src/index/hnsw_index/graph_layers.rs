@@ -1,14 +1,16 @@
 use std::cmp::max;
+use std::mem::size_of;
 use std::path::{Path, PathBuf};
 
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::graph_links::GraphLinks;
 use crate::common::file_operations::{atomic_save_bin, read_bin, FileStorageError};
 use crate::common::utils::rev_range;
 use crate::entry::entry_point::OperationResult;
-use crate::index::hnsw_index::entry_points::EntryPoints;
+use crate::index::hnsw_index::entry_points::{EntryPoint, EntryPoints};
 use crate::index::hnsw_index::graph_links::GraphLinksConverter;
 use crate::index::hnsw_index::point_scorer::FilteredScorer;
 use crate::index::hnsw_index::search_context::SearchContext;
@@ -34,6 +36,40 @@ pub struct GraphLayersBackwardCompatibility {
     pub(super) entry_points: EntryPoints,
 }
 
+/// Statistics of the HNSW graph
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+pub struct IndexStats {
+    /// Statistics of each level, starting from level 0
+    pub levels: Vec<LevelStats>,
+    /// Memory used by the links and their offsets
+    pub links_bytes: usize,
+    /// Number of points, which can't be reached from the entry point by the links of any level.
+    /// Only computed on request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unreachable_points: Option<usize>,
+    /// Number of points with links on level 0, including the deleted ones
+    #[serde(default)]
+    pub linked_points: usize,
+    /// Number of linked points, deleted after the graph was built
+    #[serde(default)]
+    pub deleted_points: usize,
+    /// Share of deleted points among the linked ones, from 0 to 1.
+    /// Searches still traverse deleted points, so a high ratio means the graph should be rebuilt
+    #[serde(default)]
+    pub deleted_ratio: f64,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+pub struct LevelStats {
+    /// Number of points, present on the level
+    pub points: usize,
+    /// Number of points, for which the level is the highest one
+    pub top_level_points: usize,
+    pub min_degree: usize,
+    pub max_degree: usize,
+    pub avg_degree: f64,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct GraphLayers<TGraphLinks: GraphLinks> {
     pub(super) m: usize,
@@ -83,6 +119,16 @@ pub trait GraphLayersBase {
                 }
             });
 
+            // Points, deleted after the graph is built, still connect their neighbours
+            for &point_id in &points_ids {
+                if points_scorer.is_deleted(point_id) {
+                    searcher.process_pass_through(ScoredPointOffset {
+                        idx: point_id,
+                        score: points_scorer.score_point(point_id),
+                    });
+                }
+            }
+
             let scores = points_scorer.score_points(&mut points_ids, limit);
             scores
                 .iter()
@@ -156,6 +202,21 @@ pub trait GraphLayersBase {
         }
         current_point
     }
+
+    /// Search for the `top` closest points, descending from `entry_point` to level 0
+    fn search_from_entry(
+        &self,
+        entry_point: EntryPoint,
+        top: usize,
+        ef: usize,
+        points_scorer: &mut FilteredScorer,
+    ) -> Vec<ScoredPointOffset> {
+        let zero_level_entry =
+            self.search_entry(entry_point.point_id, entry_point.level, 0, points_scorer);
+
+        let nearest = self.search_on_level(zero_level_entry, 0, max(top, ef), points_scorer, &[]);
+        nearest.into_iter().take(top).collect_vec()
+    }
 }
 
 impl<TGraphLinks: GraphLinks> GraphLayersBase for GraphLayers<TGraphLinks> {
@@ -193,6 +254,98 @@ impl<TGraphLinks: GraphLinks> GraphLayers<TGraphLinks> {
         self.links.point_level(point_id)
     }
 
+    /// Statistics of the graph over the points, accepted by `is_indexed`
+    ///
+    /// Linked points, not accepted by `is_indexed`, are counted as deleted.
+    /// Reachability check traverses all the links of the graph.
+    pub fn stats<F>(&self, is_indexed: F, check_reachability: bool) -> IndexStats
+    where
+        F: Fn(PointOffsetType) -> bool,
+    {
+        let num_points = self.links.num_points();
+        let mut levels: Vec<LevelStats> = vec![];
+        let mut degree_sums: Vec<usize> = vec![];
+        let mut links_count = 0;
+        let mut linked_points = 0;
+        let mut deleted_points = 0;
+
+        for point_id in 0..num_points as PointOffsetType {
+            let point_level = self.point_level(point_id);
+            let indexed = is_indexed(point_id);
+            if !self.links.links(point_id, 0).is_empty() {
+                linked_points += 1;
+                if !indexed {
+                    deleted_points += 1;
+                }
+            }
+            for level in 0..=point_level {
+                let degree = self.links.links(point_id, level).len();
+                links_count += degree;
+                if !indexed {
+                    continue;
+                }
+                if levels.len() <= level {
+                    levels.resize_with(level + 1, Default::default);
+                    degree_sums.resize(level + 1, 0);
+                }
+                let level_stats = &mut levels[level];
+                level_stats.min_degree = if level_stats.points == 0 {
+                    degree
+                } else {
+                    level_stats.min_degree.min(degree)
+                };
+                level_stats.max_degree = level_stats.max_degree.max(degree);
+                level_stats.points += 1;
+                degree_sums[level] += degree;
+            }
+            if indexed {
+                levels[point_level].top_level_points += 1;
+            }
+        }
+
+        for (level_stats, degree_sum) in levels.iter_mut().zip(degree_sums) {
+            if level_stats.points > 0 {
+                level_stats.avg_degree = degree_sum as f64 / level_stats.points as f64;
+            }
+        }
+
+        let unreachable_points = check_reachability.then(|| {
+            let mut reached = vec![false; num_points];
+            let mut stack = vec![];
+            if let Some(entry_point) = self.entry_points.get_entry_point(&is_indexed) {
+                reached[entry_point.point_id as usize] = true;
+                stack.push(entry_point.point_id);
+            }
+            while let Some(point_id) = stack.pop() {
+                for level in 0..=self.point_level(point_id) {
+                    self.links_map(point_id, level, |link| {
+                        if !reached[link as usize] {
+                            reached[link as usize] = true;
+                            stack.push(link);
+                        }
+                    });
+                }
+            }
+            (0..num_points as PointOffsetType)
+                .filter(|&point_id| is_indexed(point_id) && !reached[point_id as usize])
+                .count()
+        });
+
+        IndexStats {
+            levels,
+            links_bytes: links_count * size_of::<PointOffsetType>()
+                + self.links.offsets_len() * size_of::<u64>(),
+            unreachable_points,
+            linked_points,
+            deleted_points,
+            deleted_ratio: if linked_points > 0 {
+                deleted_points as f64 / linked_points as f64
+            } else {
+                0.0
+            },
+        }
+    }
+
     pub fn search(
         &self,
         top: usize,
@@ -207,16 +360,7 @@ impl<TGraphLinks: GraphLinks> GraphLayers<TGraphLinks> {
             Some(ep) => ep,
         };
 
-        let zero_level_entry = self.search_entry(
-            entry_point.point_id,
-            entry_point.level,
-            0,
-            &mut points_scorer,
-        );
-
-        let nearest =
-            self.search_on_level(zero_level_entry, 0, max(top, ef), &mut points_scorer, &[]);
-        nearest.into_iter().take(top).collect_vec()
+        self.search_from_entry(entry_point, top, ef, &mut points_scorer)
     }
 
     pub fn get_path(path: &Path) -> PathBuf {
@@ -11,11 +11,13 @@ use super::graph_links::GraphLinks;
 use crate::entry::entry_point::OperationResult;
 use crate::index::hnsw_index::entry_points::EntryPoints;
 use crate::index::hnsw_index::graph_layers::{GraphLayers, GraphLayersBase, LinkContainer};
-use crate::index::hnsw_index::graph_links::GraphLinksConverter;
+use crate::index::hnsw_index::graph_links::{GraphLinksConverter, GraphLinksRam};
 use crate::index::hnsw_index::point_scorer::FilteredScorer;
 use crate::index::visited_pool::{VisitedList, VisitedPool};
+use crate::payload_storage::FilterContext;
 use crate::spaces::tools::FixedLengthPriorityQueue;
-use crate::types::{PointOffsetType, ScoreType};
+use crate::types::{NeighborSelection, PointOffsetType, ScoreType};
+use crate::vector_storage::RawScorer;
 use crate::vector_storage::ScoredPointOffset;
 
 pub type LockedLinkContainer = RwLock<LinkContainer>;
@@ -30,8 +32,8 @@ pub struct GraphLayersBuilder {
     ef_construct: usize,
     // Factor of level probability
     level_factor: f64,
-    // Exclude points according to "not closer than base" heuristic?
-    use_heuristic: bool,
+    // Exclude points according to "not closer than base" heuristic, and keep excluded ones?
+    neighbor_selection: NeighborSelection,
     links_layers: Vec<LockedLayersContainer>,
     entry_points: Mutex<EntryPoints>,
 
@@ -67,6 +69,17 @@ impl GraphLayersBase for GraphLayersBuilder {
     }
 }
 
+/// Excludes the point, which is being linked, from its own entry points and neighbours
+struct LinkedPointFilter {
+    point_id: PointOffsetType,
+}
+
+impl FilterContext for LinkedPointFilter {
+    fn check(&self, point_id: PointOffsetType) -> bool {
+        point_id != self.point_id
+    }
+}
+
 impl GraphLayersBuilder {
     pub fn get_entry_points(&self) -> MutexGuard<EntryPoints> {
         self.entry_points.lock()
@@ -82,12 +95,15 @@ impl GraphLayersBuilder {
             .map(|l| l.into_iter().map(|l| l.into_inner()).collect())
             .collect();
 
-        let mut links_converter = GraphLinksConverter::new(unlocker_links_layers);
-        if let Some(path) = path {
-            links_converter.save_as(path)?;
-        }
-
-        let links = TGraphLinks::from_converter(links_converter)?;
+        let links_converter = GraphLinksConverter::new(unlocker_links_layers);
+        let links = match path {
+            // Links are released while written, so they are not held twice in memory
+            Some(path) => {
+                links_converter.save_in_slices(path)?;
+                TGraphLinks::load_from_file(path)?
+            }
+            None => TGraphLinks::from_converter(links_converter)?,
+        };
         Ok(GraphLayers {
             m: self.m,
             m0: self.m0,
@@ -98,13 +114,63 @@ impl GraphLayersBuilder {
         })
     }
 
+    /// Mutable copy of a built graph, so new points can be linked into it
+    pub fn from_graph_layers<TGraphLinks: GraphLinks>(
+        graph: &GraphLayers<TGraphLinks>,
+        neighbor_selection: NeighborSelection,
+    ) -> Self {
+        let num_points = graph.links.num_points();
+        let mut max_level = 0;
+        let links_layers = (0..num_points as PointOffsetType)
+            .map(|point_id| {
+                let point_level = graph.links.point_level(point_id);
+                max_level = max(max_level, point_level);
+                (0..=point_level)
+                    .map(|level| RwLock::new(graph.links.links(point_id, level).to_vec()))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            max_level: AtomicUsize::new(max_level),
+            m: graph.m,
+            m0: graph.m0,
+            ef_construct: graph.ef_construct,
+            level_factor: 1.0 / (max(graph.m, 2) as f64).ln(),
+            neighbor_selection,
+            links_layers,
+            entry_points: Mutex::new(graph.entry_points.clone()),
+            visited_pool: VisitedPool::new(),
+        }
+    }
+
+    /// Save the graph in the format of [`GraphLayers`], without consuming the builder
+    pub fn save(&self, graph_path: &Path, links_path: &Path) -> OperationResult<()> {
+        let links_layers = self
+            .links_layers
+            .iter()
+            .map(|layers| layers.iter().map(|links| links.read().clone()).collect())
+            .collect();
+        GraphLinksConverter::new(links_layers).save_as(links_path)?;
+
+        let graph = GraphLayers::<GraphLinksRam> {
+            m: self.m,
+            m0: self.m0,
+            ef_construct: self.ef_construct,
+            links: Default::default(),
+            entry_points: self.entry_points.lock().clone(),
+            visited_pool: VisitedPool::new(),
+        };
+        graph.save(graph_path)
+    }
+
     pub fn new_with_params(
         num_vectors: usize, // Initial number of points in index
         m: usize,           // Expected M for non-first layer
         m0: usize,          // Expected M for first layer
         ef_construct: usize,
         entry_points_num: usize, // Depends on number of points
-        use_heuristic: bool,
+        neighbor_selection: NeighborSelection,
         reserve: bool,
     ) -> Self {
         let mut links_layers: Vec<LockedLayersContainer> = vec![];
@@ -123,7 +189,7 @@ impl GraphLayersBuilder {
             m0,
             ef_construct,
             level_factor: 1.0 / (max(m, 2) as f64).ln(),
-            use_heuristic,
+            neighbor_selection,
             links_layers,
             entry_points: Mutex::new(EntryPoints::new(entry_points_num)),
             visited_pool: VisitedPool::new(),
@@ -144,7 +210,11 @@ impl GraphLayersBuilder {
             m0,
             ef_construct,
             entry_points_num,
-            use_heuristic,
+            if use_heuristic {
+                NeighborSelection::Heuristic
+            } else {
+                NeighborSelection::Simple
+            },
             true,
         )
     }
@@ -186,10 +256,14 @@ impl GraphLayersBuilder {
         self.visited_pool.return_back(visited_list);
     }
 
-    fn num_points(&self) -> usize {
+    pub fn num_points(&self) -> usize {
         self.links_layers.len()
     }
 
+    pub fn levels_count(&self) -> usize {
+        self.max_level.load(std::sync::atomic::Ordering::Relaxed) + 1
+    }
+
     /// Generate random level for a new point, according to geometric distribution
     pub fn get_random_layer<R>(&self, rng: &mut R) -> usize
     where
@@ -252,15 +326,19 @@ impl GraphLayersBuilder {
     }
 
     /// <https://github.com/nmslib/hnswlib/issues/99>
+    ///
+    /// If `keep_pruned` is set, skipped candidates are used to fill the remaining links.
     fn select_candidate_with_heuristic_from_sorted<F>(
         candidates: impl Iterator<Item = ScoredPointOffset>,
         m: usize,
+        keep_pruned: bool,
         mut score_internal: F,
     ) -> Vec<PointOffsetType>
     where
         F: FnMut(PointOffsetType, PointOffsetType) -> ScoreType,
     {
         let mut result_list = vec![];
+        let mut pruned_list = vec![];
         result_list.reserve(m);
         for current_closest in candidates {
             if result_list.len() >= m {
@@ -276,9 +354,16 @@ impl GraphLayersBuilder {
             }
             if is_good {
                 result_list.push(current_closest.idx);
+            } else if keep_pruned {
+                pruned_list.push(current_closest.idx);
             }
         }
 
+        if keep_pruned {
+            let missing = m.saturating_sub(result_list.len());
+            result_list.extend(pruned_list.into_iter().take(missing));
+        }
+
         result_list
     }
 
@@ -286,13 +371,84 @@ impl GraphLayersBuilder {
     fn select_candidates_with_heuristic<F>(
         candidates: FixedLengthPriorityQueue<ScoredPointOffset>,
         m: usize,
+        keep_pruned: bool,
         score_internal: F,
     ) -> Vec<PointOffsetType>
     where
         F: FnMut(PointOffsetType, PointOffsetType) -> ScoreType,
     {
         let closest_iter = candidates.into_iter();
-        Self::select_candidate_with_heuristic_from_sorted(closest_iter, m, score_internal)
+        Self::select_candidate_with_heuristic_from_sorted(
+            closest_iter,
+            m,
+            keep_pruned,
+            score_internal,
+        )
+    }
+
+    /// Search the graph, same as [`GraphLayers::search`]
+    pub fn search(
+        &self,
+        top: usize,
+        ef: usize,
+        mut points_scorer: FilteredScorer,
+    ) -> Vec<ScoredPointOffset> {
+        let entry_point = self
+            .entry_points
+            .lock()
+            .get_entry_point(|point_id| points_scorer.check_point(point_id));
+        match entry_point {
+            None => vec![],
+            Some(entry_point) => self.search_from_entry(entry_point, top, ef, &mut points_scorer),
+        }
+    }
+
+    /// Link a point into the already built graph, or relink it after its vector is changed
+    ///
+    /// A new point gets a random level, a relinked one keeps its level. Links of a relinked point
+    /// are replaced, and it is removed from the links of its former neighbours. Links of other
+    /// points to it are kept, they are slightly worse, but still lead to a valid point.
+    /// Costs a single search with `ef_construct` per level of the point.
+    pub fn insert_point<R>(
+        &mut self,
+        point_id: PointOffsetType,
+        raw_scorer: &dyn RawScorer,
+        rng: &mut R,
+    ) where
+        R: Rng + ?Sized,
+    {
+        let is_linked = self
+            .links_layers
+            .get(point_id as usize)
+            .map(|layers| !layers.is_empty())
+            .unwrap_or(false);
+        if is_linked {
+            self.unlink_point(point_id);
+        } else {
+            // Points in between, e.g. removed before being linked, are left without links
+            while self.links_layers.len() < point_id as usize {
+                self.links_layers.push(vec![RwLock::new(vec![])]);
+            }
+            let level = self.get_random_layer(rng);
+            self.set_levels(point_id, level);
+        }
+
+        let linked_point_filter = LinkedPointFilter { point_id };
+        let points_scorer = FilteredScorer::new(raw_scorer, Some(&linked_point_filter));
+        self.link_new_point(point_id, points_scorer);
+    }
+
+    /// Remove all links of the point, and links to it from its neighbours
+    fn unlink_point(&self, point_id: PointOffsetType) {
+        self.entry_points.lock().remove_point(point_id);
+        for (level, links) in self.links_layers[point_id as usize].iter().enumerate() {
+            let links = std::mem::take(&mut *links.write());
+            for neighbour in links {
+                if let Some(neighbour_links) = self.links_layers[neighbour as usize].get(level) {
+                    neighbour_links.write().retain(|&link| link != point_id);
+                }
+            }
+        }
     }
 
     pub fn link_new_point(&self, point_id: PointOffsetType, mut points_scorer: FilteredScorer) {
@@ -351,10 +507,16 @@ impl GraphLayersBuilder {
                     };
 
                     let scorer = |a, b| points_scorer.score_internal(a, b);
-
-                    if self.use_heuristic {
-                        let selected_nearest =
-                            Self::select_candidates_with_heuristic(nearest_points, level_m, scorer);
+                    let keep_pruned =
+                        self.neighbor_selection == NeighborSelection::HeuristicKeepPruned;
+
+                    if self.neighbor_selection != NeighborSelection::Simple {
+                        let selected_nearest = Self::select_candidates_with_heuristic(
+                            nearest_points,
+                            level_m,
+                            keep_pruned,
+                            scorer,
+                        );
                         self.links_layers[point_id as usize][curr_level]
                             .write()
                             .clone_from(&selected_nearest);
@@ -383,6 +545,7 @@ impl GraphLayersBuilder {
                                     Self::select_candidate_with_heuristic_from_sorted(
                                         candidates.into_sorted_vec().into_iter().rev(),
                                         level_m,
+                                        keep_pruned,
                                         scorer,
                                     );
                                 other_point_links.clear(); // this do not free memory, which is good
@@ -773,6 +936,7 @@ mod tests {
         let selected_candidates = GraphLayersBuilder::select_candidate_with_heuristic_from_sorted(
             sorted_candidates.into_iter(),
             M,
+            false,
             |a, b| scorer.score_internal(a, b),
         );
 
@@ -818,10 +982,21 @@ mod tests {
             });
         }
 
-        let res = GraphLayersBuilder::select_candidates_with_heuristic(candidates, m, scorer);
+        let res = GraphLayersBuilder::select_candidates_with_heuristic(
+            candidates.clone(),
+            m,
+            false,
+            scorer,
+        );
 
         assert_eq!(&res, &vec![1, 3, 6]);
 
+        // Pruned candidates fill the remaining links in order of closeness
+        let res = GraphLayersBuilder::select_candidates_with_heuristic(candidates, m, true, scorer);
+        assert_eq!(res.len(), m);
+        assert_eq!(&res[..3], &[1, 3, 6]);
+        assert_eq!(&res[3..], &[2, 4, 5]);
+
         let mut rng = StdRng::seed_from_u64(42);
 
         let graph_layers_builder = GraphLayersBuilder::new(num_points, m, m, ef_construct, 1, true);
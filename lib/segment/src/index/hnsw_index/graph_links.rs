@@ -1,5 +1,6 @@
 use std::cmp::max;
 use std::fs::OpenOptions;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
 use std::mem::size_of;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
@@ -12,12 +13,21 @@ use crate::types::PointOffsetType;
 
 pub const MMAP_PANIC_MESSAGE: &str = "Mmap links are not loaded";
 
+/// Size of the slices, the links are written to the file in by [`GraphLinksConverter::save_in_slices`]
+pub const LINKS_WRITE_SLICE_BYTES: usize = 4 * 1024 * 1024;
+
 fn transmute_from_u8<T>(data: &[u8]) -> &[T] {
     let len = data.len() / size_of::<T>();
     let ptr = data.as_ptr() as *const T;
     unsafe { std::slice::from_raw_parts(ptr, len) }
 }
 
+fn transmute_to_u8<T>(data: &[T]) -> &[u8] {
+    let len = data.len() * size_of::<T>();
+    let ptr = data.as_ptr() as *const u8;
+    unsafe { std::slice::from_raw_parts(ptr, len) }
+}
+
 fn transmute_from_u8_mut<T>(data: &mut [u8]) -> &mut [T] {
     let len = data.len() / size_of::<T>();
     let ptr = data.as_mut_ptr() as *mut T;
@@ -56,12 +66,18 @@ for lvl > 0:
 links offset = level_offsets[level] + offsets[reindex[point_id]]
 */
 
+/// Version of the links file format, files written before versioning have version 0
+const GRAPH_LINKS_VERSION: u64 = 1;
+
 #[derive(Default)]
 struct GraphLinksFileHeader {
     pub point_count: u64,
     pub levels_count: u64,
     pub total_links_len: u64,
     pub total_offsets_len: u64,
+    pub version: u64,
+    /// Hash of the data after the header, not checked for version 0
+    pub checksum: u64,
 }
 
 fn reindex_slice<'a>(data: &'a [u8], header: &'a GraphLinksFileHeader) -> &'a [PointOffsetType] {
@@ -91,7 +107,7 @@ fn level_offsets(data: &[u8], header: &GraphLinksFileHeader) -> Vec<u64> {
 
 impl GraphLinksFileHeader {
     pub fn raw_size() -> usize {
-        size_of::<u64>() * 4
+        size_of::<u64>() * 6
     }
 
     pub fn serialize_bytes_to(&self, raw_data: &mut [u8]) {
@@ -101,6 +117,8 @@ impl GraphLinksFileHeader {
         arr[1] = self.levels_count;
         arr[2] = self.total_links_len;
         arr[3] = self.total_offsets_len;
+        arr[4] = self.version;
+        arr[5] = self.checksum;
     }
 
     pub fn deserialize_bytes_from(raw_data: &[u8]) -> GraphLinksFileHeader {
@@ -111,7 +129,41 @@ impl GraphLinksFileHeader {
             levels_count: arr[1],
             total_links_len: arr[2],
             total_offsets_len: arr[3],
+            version: arr[4],
+            checksum: arr[5],
+        }
+    }
+
+    /// Read the header and check, that the data is complete and not corrupted
+    pub fn load(raw_data: &[u8]) -> OperationResult<GraphLinksFileHeader> {
+        if raw_data.len() < Self::raw_size() {
+            return Err(OperationError::service_error(
+                "HNSW links header is truncated",
+            ));
+        }
+        let header = Self::deserialize_bytes_from(raw_data);
+        if header.version > GRAPH_LINKS_VERSION {
+            return Err(OperationError::service_error(format!(
+                "Unsupported HNSW links format version {}",
+                header.version,
+            )));
+        }
+        if (raw_data.len() as u64) < header.get_data_size() {
+            return Err(OperationError::service_error(
+                "HNSW links data is truncated",
+            ));
+        }
+        if header.version > 0 && header.checksum != header.data_checksum(raw_data) {
+            return Err(OperationError::service_error(
+                "HNSW links checksum mismatch",
+            ));
         }
+        Ok(header)
+    }
+
+    fn data_checksum(&self, raw_data: &[u8]) -> u64 {
+        let data_range = self.get_level_offsets_range().start..self.get_data_size() as usize;
+        seahash::hash(&raw_data[data_range])
     }
 
     pub fn get_data_size(&self) -> u64 {
@@ -205,6 +257,8 @@ impl GraphLinksConverter {
             levels_count: self.get_levels_count() as u64,
             total_links_len: self.total_links_len as u64,
             total_offsets_len: self.total_offsets_len as u64,
+            version: GRAPH_LINKS_VERSION,
+            checksum: 0,
         }
     }
 
@@ -214,14 +268,7 @@ impl GraphLinksConverter {
     }
 
     pub fn serialize_to(&self, bytes_data: &mut [u8]) {
-        let header = GraphLinksFileHeader {
-            point_count: self.reindex.len() as u64,
-            levels_count: self.get_levels_count() as u64,
-            total_links_len: self.total_links_len as u64,
-            total_offsets_len: self.total_offsets_len as u64,
-        };
-
-        header.serialize_bytes_to(bytes_data);
+        let mut header = self.get_header();
 
         {
             let reindex_range = header.get_reindex_range();
@@ -262,6 +309,9 @@ impl GraphLinksConverter {
             let level_offsets_slice: &mut [u64] = transmute_from_u8_mut(level_offsets_byte_slice);
             level_offsets_slice.copy_from_slice(&level_offsets);
         }
+
+        header.checksum = header.data_checksum(bytes_data);
+        header.serialize_bytes_to(bytes_data);
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -293,6 +343,89 @@ impl GraphLinksConverter {
         Ok(())
     }
 
+    /// Save the links to the file in slices, releasing the links of each point once they are written
+    ///
+    /// Unlike [`GraphLinksConverter::save_as`], the converted links are never held in memory:
+    /// they are streamed to the file through buffers of [`LINKS_WRITE_SLICE_BYTES`],
+    /// so the memory, used by the conversion, only shrinks. The file is the same as of `save_as`.
+    pub fn save_in_slices(mut self, path: &Path) -> OperationResult<()> {
+        let mut header = self.get_header();
+        let level_offsets = self.get_level_offsets();
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(tmp_path.as_path())?;
+            file.set_len(header.get_data_size())?;
+
+            let mut links_writer =
+                BufWriter::with_capacity(LINKS_WRITE_SLICE_BYTES, file.try_clone()?);
+            links_writer.seek(SeekFrom::Start(
+                header.get_level_offsets_range().start as u64,
+            ))?;
+            links_writer.write_all(transmute_to_u8(&level_offsets))?;
+            links_writer.write_all(transmute_to_u8(&self.reindex))?;
+
+            // Offsets are stored after all links, so they are written by their own writer
+            let mut offsets_writer =
+                BufWriter::with_capacity(LINKS_WRITE_SLICE_BYTES, file.try_clone()?);
+            offsets_writer.seek(SeekFrom::Start(header.get_offsets_range().start as u64))?;
+            offsets_writer.write_all(&0u64.to_ne_bytes())?;
+
+            let mut links_pos = 0u64;
+            for level in 0..header.levels_count as usize {
+                for i in 0..self.edges.len() {
+                    // Same order of the points as of `iterate_level_points`
+                    let point_id = if level == 0 { i } else { self.back_index[i] };
+                    let point_edges = &mut self.edges[point_id];
+                    if level >= point_edges.len() {
+                        break;
+                    }
+                    let links = std::mem::take(&mut point_edges[level]);
+                    links_writer.write_all(transmute_to_u8(&links))?;
+                    links_pos += links.len() as u64;
+                    offsets_writer.write_all(&links_pos.to_ne_bytes())?;
+                }
+            }
+            links_writer.flush()?;
+            offsets_writer.flush()?;
+            drop(links_writer);
+            drop(offsets_writer);
+
+            // Checksum is computed over the written data, read back through the page cache
+            let mmap = unsafe { Mmap::map(&file)? };
+            header.checksum = header.data_checksum(&mmap);
+            drop(mmap);
+
+            let mut header_bytes = vec![0; GraphLinksFileHeader::raw_size()];
+            header.serialize_bytes_to(&mut header_bytes);
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&header_bytes)?;
+            file.sync_all()?;
+        }
+        std::fs::rename(tmp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Start of each level in the offsets, same as stored by [`GraphLinksConverter::serialize_to`]
+    fn get_level_offsets(&self) -> Vec<u64> {
+        let mut level_offsets = Vec::with_capacity(self.get_levels_count());
+        let mut offsets_len = 0;
+        for level in 0..self.get_levels_count() {
+            level_offsets.push(offsets_len as u64);
+            offsets_len += self
+                .edges
+                .iter()
+                .filter(|point_edges| point_edges.len() > level)
+                .count();
+        }
+        level_offsets
+    }
+
     pub fn get_levels_count(&self) -> usize {
         if self.back_index.is_empty() {
             return 0;
@@ -321,6 +454,9 @@ impl GraphLinksConverter {
 }
 
 pub trait GraphLinks: Default {
+    /// Links are loaded into RAM, rather than read from the mapped file
+    const IN_RAM: bool;
+
     fn load_from_file(path: &Path) -> OperationResult<Self>;
 
     fn from_converter(converter: GraphLinksConverter) -> OperationResult<Self>;
@@ -415,6 +551,8 @@ impl GraphLinksRam {
 }
 
 impl GraphLinks for GraphLinksRam {
+    const IN_RAM: bool = true;
+
     fn load_from_file(path: &Path) -> OperationResult<Self> {
         let file = OpenOptions::new()
             .read(true)
@@ -423,6 +561,7 @@ impl GraphLinks for GraphLinksRam {
             .open(path)?;
 
         let mmap = unsafe { Mmap::map(&file)? };
+        GraphLinksFileHeader::load(&mmap)?;
 
         Ok(Self::load_from_memory(&mmap))
     }
@@ -499,6 +638,8 @@ impl GraphLinksMmap {
 }
 
 impl GraphLinks for GraphLinksMmap {
+    const IN_RAM: bool = false;
+
     fn load_from_file(path: &Path) -> OperationResult<Self> {
         let file = OpenOptions::new()
             .read(true)
@@ -509,7 +650,7 @@ impl GraphLinks for GraphLinksMmap {
         let mmap = unsafe { Mmap::map(&file)? };
         madvise::madvise(&mmap, madvise::get_global())?;
 
-        let header = GraphLinksFileHeader::deserialize_bytes_from(&mmap);
+        let header = GraphLinksFileHeader::load(&mmap)?;
         let level_offsets = level_offsets(&mmap, &header);
 
         Ok(Self {
@@ -616,6 +757,18 @@ mod tests {
         }
         let cmp_links = to_vec(&B::load_from_file(&links_file).unwrap());
         assert_eq!(links, cmp_links);
+
+        // File, written in slices, is the same
+        let sliced_file = path.path().join("links_sliced.bin");
+        GraphLinksConverter::new(links.clone())
+            .save_in_slices(&sliced_file)
+            .unwrap();
+        assert_eq!(
+            std::fs::read(&links_file).unwrap(),
+            std::fs::read(&sliced_file).unwrap()
+        );
+        let cmp_links = to_vec(&B::load_from_file(&sliced_file).unwrap());
+        assert_eq!(links, cmp_links);
     }
 
     #[test]
@@ -684,6 +837,45 @@ mod tests {
         assert_eq!(links, cmp_links);
     }
 
+    #[test]
+    fn test_graph_links_corruption() {
+        let path = Builder::new().prefix("graph_dir").tempdir().unwrap();
+        let links_file = path.path().join("links.bin");
+        let links = random_links(100, 10);
+        let mut links_converter = GraphLinksConverter::new(links.clone());
+        links_converter.save_as(&links_file).unwrap();
+
+        let mut data = std::fs::read(&links_file).unwrap();
+        let header = GraphLinksFileHeader::load(&data).unwrap();
+        assert_eq!(header.version, GRAPH_LINKS_VERSION);
+
+        // Files without version are not checked
+        let legacy_data = {
+            let mut legacy_data = data.clone();
+            let mut legacy_header = GraphLinksFileHeader::deserialize_bytes_from(&legacy_data);
+            legacy_header.version = 0;
+            legacy_header.checksum = 0;
+            legacy_header.serialize_bytes_to(&mut legacy_data);
+            legacy_data
+        };
+        std::fs::write(&links_file, &legacy_data).unwrap();
+        assert_eq!(
+            to_vec(&GraphLinksRam::load_from_file(&links_file).unwrap()),
+            links
+        );
+
+        let links_start = header.get_links_range().start;
+        data[links_start] ^= 0xFF;
+        std::fs::write(&links_file, &data).unwrap();
+        assert!(GraphLinksRam::load_from_file(&links_file).is_err());
+        assert!(GraphLinksMmap::load_from_file(&links_file).is_err());
+
+        data.truncate(links_start);
+        std::fs::write(&links_file, &data).unwrap();
+        assert!(GraphLinksRam::load_from_file(&links_file).is_err());
+        assert!(GraphLinksMmap::load_from_file(&links_file).is_err());
+    }
+
     #[test]
     fn test_graph_links_mmap_ram_compability() {
         test_save_load::<GraphLinksRam, GraphLinksRam>(1000, 10);
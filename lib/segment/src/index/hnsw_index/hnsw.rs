@@ -1,13 +1,15 @@
 use std::cmp::max;
+use std::collections::HashSet;
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
 use log::debug;
-use parking_lot::Mutex;
-use rand::thread_rng;
+use parking_lot::{Mutex, RwLock};
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
 use rayon::prelude::*;
 use rayon::ThreadPool;
 
@@ -15,40 +17,78 @@ use super::graph_links::GraphLinks;
 use crate::common::operation_time_statistics::{
     OperationDurationsAggregator, ScopeDurationMeasurer,
 };
+use crate::common::parallel::{map_parallel, resolve_search_threads, split_search_threads};
+use crate::common::search_deadline::SearchDeadline;
+use crate::common::search_telemetry::{SearchCounter, SearchCounters, SearchPhase};
 use crate::data_types::vectors::VectorElementType;
 use crate::entry::entry_point::{check_process_stopped, OperationError, OperationResult};
 use crate::id_tracker::IdTrackerSS;
+use crate::index::field_index::CardinalityEstimation;
 use crate::index::hnsw_index::build_condition_checker::BuildConditionChecker;
-use crate::index::hnsw_index::config::HnswGraphConfig;
-use crate::index::hnsw_index::graph_layers::GraphLayers;
+use crate::index::hnsw_index::build_memory::{
+    check_build_memory, estimate_build_memory, BuildMemoryParams,
+};
+use crate::index::hnsw_index::config::{EfCalibration, HnswGraphConfig};
+use crate::index::hnsw_index::graph_layers::{GraphLayers, IndexStats};
 use crate::index::hnsw_index::graph_layers_builder::GraphLayersBuilder;
 use crate::index::hnsw_index::point_scorer::FilteredScorer;
+use crate::index::query_plan::{
+    quantization_plan, CardinalityExplain, PlanReason, QueryPlanExplain, SearchStrategy,
+};
 use crate::index::sample_estimation::sample_check_cardinality;
 use crate::index::struct_payload_index::StructPayloadIndex;
 use crate::index::visited_pool::VisitedList;
-use crate::index::{PayloadIndex, VectorIndex};
+use crate::index::{PayloadIndex, SearchCostEstimate, VectorIndex};
+use crate::payload_storage::FilterContext;
 use crate::telemetry::VectorIndexSearchesTelemetry;
 use crate::types::Condition::Field;
 use crate::types::{
-    default_quantization_ignore_value, default_quantization_rescore_value, FieldCondition, Filter,
-    HnswConfig, QuantizationSearchParams, SearchParams, VECTOR_ELEMENT_SIZE,
+    BuildMemoryEstimate, EfPolicy, FieldCondition, Filter, HnswConfig, PointOffsetType,
+    QuantizationSearchParams, SearchParams, VectorIndexType, VECTOR_ELEMENT_SIZE,
 };
+use crate::vector_storage::batch_scorer::{rescore_points, BatchScorerSS};
 use crate::vector_storage::quantized::quantized_vectors_base::QuantizedVectors;
 use crate::vector_storage::{new_raw_scorer, ScoredPointOffset, VectorStorage, VectorStorageEnum};
 
-const HNSW_USE_HEURISTIC: bool = true;
 const BYTES_IN_KB: usize = 1024;
+const DEFAULT_EF_FACTOR: f32 = 1.0;
+/// Number of sampled points, which vectors are used as queries for the calibration of the `ef` policy
+const EF_CALIBRATION_QUERIES: usize = 100;
+const EF_CALIBRATION_TOP: usize = 10;
+/// Factors of the `ef` policy, tried by the calibration in order
+const EF_CALIBRATION_FACTORS: &[f32] = &[
+    1.0, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0, 32.0, 48.0, 64.0,
+];
+/// Number of points, linked into the graph within a single borrow of the storages
+const BUILD_CHUNK_SIZE: usize = 1024;
 
 pub struct HNSWIndex<TGraphLinks: GraphLinks> {
-    id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
-    vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
-    payload_index: Arc<AtomicRefCell<StructPayloadIndex>>,
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
+    vector_storage: Arc<RwLock<VectorStorageEnum>>,
+    payload_index: Arc<RwLock<StructPayloadIndex>>,
     config: HnswGraphConfig,
     path: PathBuf,
     graph: Option<GraphLayers<TGraphLinks>>,
+    /// Copy of the graph with points, linked after the build, if `incremental` is enabled
+    incremental_graph: Option<GraphLayersBuilder>,
+    /// Incremental graph has changes, which are not saved yet
+    incremental_changed: AtomicBool,
+    /// External scorer, used for rescoring with the original vectors
+    batch_scorer: Option<Arc<BatchScorerSS>>,
+    /// Number of threads, searching the queries of a batch
+    search_threads: usize,
     searches_telemetry: SearchesTelemetry,
 }
 
+/// Way of the filtered search, chosen by the cardinality of the filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilteredSearchPlan {
+    /// Score all points, matching the filter
+    Plain,
+    /// Search the graph, skipping points which don't match the filter
+    Graph,
+}
+
 struct SearchesTelemetry {
     unfiltered: Arc<Mutex<OperationDurationsAggregator>>,
     small_cardinality: Arc<Mutex<OperationDurationsAggregator>>,
@@ -57,12 +97,18 @@ struct SearchesTelemetry {
     exact_unfiltered: Arc<Mutex<OperationDurationsAggregator>>,
 }
 
+/// `ef = max(top * factor, min_ef)`
+fn policy_ef(ef_policy: &EfPolicy, factor: f32, top: usize) -> usize {
+    let ef = (top as f32 * factor).ceil() as usize;
+    max(ef, ef_policy.min_ef.unwrap_or(0))
+}
+
 impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
     pub fn open(
         path: &Path,
-        id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
-        vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
-        payload_index: Arc<AtomicRefCell<StructPayloadIndex>>,
+        id_tracker: Arc<RwLock<IdTrackerSS>>,
+        vector_storage: Arc<RwLock<VectorStorageEnum>>,
+        payload_index: Arc<RwLock<StructPayloadIndex>>,
         hnsw_config: HnswConfig,
     ) -> OperationResult<Self> {
         create_dir_all(path)?;
@@ -72,32 +118,53 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
             HnswGraphConfig::load(&config_path)?
         } else {
             let indexing_threshold = hnsw_config.full_scan_threshold.saturating_mul(BYTES_IN_KB)
-                / (vector_storage.borrow().vector_dim() * VECTOR_ELEMENT_SIZE);
+                / (vector_storage.read().vector_dim() * VECTOR_ELEMENT_SIZE);
 
-            HnswGraphConfig::new(
+            let mut config = HnswGraphConfig::new(
                 hnsw_config.m,
                 hnsw_config.ef_construct,
                 indexing_threshold,
                 hnsw_config.max_indexing_threads,
                 hnsw_config.payload_m,
-            )
+                hnsw_config.neighbor_selection.unwrap_or_default(),
+                hnsw_config.seed,
+            );
+            config.incremental = hnsw_config.incremental.unwrap_or_default();
+            config.ef_policy = hnsw_config.ef_policy;
+            config.max_build_memory_mb = hnsw_config.max_build_memory_mb;
+            config
         };
 
         let graph_path = GraphLayers::<TGraphLinks>::get_path(path);
         let graph_links_path = GraphLayers::<TGraphLinks>::get_links_path(path);
+        let mut rebuild = false;
         let graph = if graph_path.exists() {
-            Some(GraphLayers::load(&graph_path, &graph_links_path)?)
+            match GraphLayers::load(&graph_path, &graph_links_path) {
+                Ok(graph) => Some(graph),
+                Err(err) => {
+                    log::warn!(
+                        "Failed to load HNSW graph from {}, rebuilding: {err}",
+                        path.display(),
+                    );
+                    rebuild = true;
+                    None
+                }
+            }
         } else {
             None
         };
 
-        Ok(HNSWIndex {
+        let mut index = HNSWIndex {
             id_tracker,
             vector_storage,
             payload_index,
             config,
             path: path.to_owned(),
             graph,
+            incremental_graph: None,
+            incremental_changed: AtomicBool::new(false),
+            batch_scorer: None,
+            search_threads: 1,
             searches_telemetry: SearchesTelemetry {
                 unfiltered: OperationDurationsAggregator::new(),
                 small_cardinality: OperationDurationsAggregator::new(),
@@ -105,7 +172,30 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
                 exact_filtered: OperationDurationsAggregator::new(),
                 exact_unfiltered: OperationDurationsAggregator::new(),
             },
-        })
+        };
+
+        if rebuild {
+            index.build_index(&AtomicBool::new(false))?;
+        }
+
+        Ok(index)
+    }
+
+    /// Graph of the index, `None` if the index is not built yet
+    pub fn graph(&self) -> Option<&GraphLayers<TGraphLinks>> {
+        self.graph.as_ref()
+    }
+
+    /// Statistics of the built graph over not deleted points, `None` if the index is not built yet
+    ///
+    /// Points, linked incrementally since the last build or load, are not counted.
+    pub fn index_stats(&self, check_reachability: bool) -> Option<IndexStats> {
+        let graph = self.graph.as_ref()?;
+        let id_tracker = self.id_tracker.read();
+        Some(graph.stats(
+            |point_id| !id_tracker.is_deleted(point_id),
+            check_reachability,
+        ))
     }
 
     fn save_config(&self) -> OperationResult<()> {
@@ -128,6 +218,285 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
         Ok(())
     }
 
+    /// Estimate the peak memory of the build over the current points
+    pub fn estimate_build_memory(&self) -> BuildMemoryEstimate {
+        let vector_storage = self.vector_storage.read();
+        let payload_m = self.config.payload_m.unwrap_or(self.config.m);
+        let has_payload_blocks =
+            payload_m > 0 && !self.payload_index.read().indexed_fields().is_empty();
+        estimate_build_memory(BuildMemoryParams {
+            num_points: vector_storage.total_vector_count(),
+            dim: vector_storage.vector_dim(),
+            m: self.config.m,
+            m0: self.config.m0,
+            payload_m0: has_payload_blocks
+                .then_some(self.config.payload_m0.unwrap_or(self.config.m0)),
+            threads: self.config.max_rayon_threads(),
+            links_in_ram: TGraphLinks::IN_RAM,
+        })
+    }
+
+    /// Build the graph, borrowing the storages only for a chunk of points at a time
+    ///
+    /// Storages are borrowed while `update_lock` is held for reading, so updates of the segment,
+    /// holding it for writing, are applied between the chunks.
+    /// Only points, existing at the start of the build, are indexed.
+    /// `progress` is incremented for each point, linked into the main graph.
+    ///
+    /// With the seed in the config and a single indexing thread, the build is reproducible.
+    /// The build is refused before it starts, if its memory estimation exceeds the budget in the config.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "build_hnsw_index",
+            level = "debug",
+            skip_all,
+            fields(points = self.id_tracker.read().points_count()),
+            err
+        )
+    )]
+    pub fn build_index_with_progress(
+        &mut self,
+        stopped: &AtomicBool,
+        update_lock: &RwLock<()>,
+        progress: &AtomicUsize,
+    ) -> OperationResult<()> {
+        {
+            let _update_guard = update_lock.read();
+            let estimate = self.estimate_build_memory();
+            debug!(
+                "hnsw build is estimated to use {} bytes",
+                estimate.peak_bytes
+            );
+            check_build_memory(&estimate, self.config.build_memory_budget())?;
+        }
+
+        // Build main index graph
+        let mut rng = match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let (ids, total_points) = {
+            let _update_guard = update_lock.read();
+            let ids: Vec<_> = self.id_tracker.read().iter_ids().collect();
+            (ids, self.vector_storage.read().total_vector_count())
+        };
+
+        debug!("building hnsw for {}", total_points);
+        let mut graph_layers_builder = GraphLayersBuilder::new_with_params(
+            total_points,
+            self.config.m,
+            self.config.m0,
+            self.config.ef_construct,
+            max(1, total_points / self.config.indexing_threshold * 10),
+            self.config.neighbor_selection,
+            true,
+        );
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .thread_name(|idx| format!("hnsw-build-{idx}"))
+            .num_threads(self.config.max_rayon_threads())
+            .build()?;
+
+        for &vector_id in &ids {
+            check_process_stopped(stopped)?;
+            let level = graph_layers_builder.get_random_layer(&mut rng);
+            graph_layers_builder.set_levels(vector_id, level);
+        }
+
+        if self.config.m > 0 {
+            for chunk in ids.chunks(BUILD_CHUNK_SIZE) {
+                check_process_stopped(stopped)?;
+                let _update_guard = update_lock.read();
+                let vector_storage = self.vector_storage.read();
+                let id_tracker = self.id_tracker.read();
+
+                let link_point = |&vector_id: &PointOffsetType| {
+                    check_process_stopped(stopped)?;
+                    let vector = vector_storage.get_vector(vector_id).to_vec();
+                    let raw_scorer =
+                        if let Some(quantized_storage) = vector_storage.quantized_storage() {
+                            quantized_storage.raw_scorer(&vector, id_tracker.deleted_bitvec())
+                        } else {
+                            new_raw_scorer(
+                                vector.to_owned(),
+                                &vector_storage,
+                                id_tracker.deleted_bitvec(),
+                            )
+                        };
+                    let points_scorer = FilteredScorer::new(raw_scorer.as_ref(), None);
+
+                    graph_layers_builder.link_new_point(vector_id, points_scorer);
+                    Ok::<_, OperationError>(())
+                };
+
+                if pool.current_num_threads() == 1 {
+                    // Link points in the order of ids, so the build is deterministic
+                    chunk.iter().try_for_each(link_point)?;
+                } else {
+                    pool.install(|| chunk.par_iter().try_for_each(link_point))?;
+                }
+                progress.fetch_add(chunk.len(), Ordering::Relaxed);
+            }
+
+            debug!("finish main graph");
+        } else {
+            debug!("skip building main HNSW graph");
+        }
+
+        let mut block_filter_list = VisitedList::new(total_points);
+
+        let payload_m = self.config.payload_m.unwrap_or(self.config.m);
+
+        if payload_m > 0 {
+            // It is expected, that graph will become disconnected less than
+            // $1/m$ points left.
+            // So blocks larger than $1/m$ are not needed.
+            // We add multiplier for the extra safety.
+            let percolation_multiplier = 2;
+            let max_block_size = if self.config.m > 0 {
+                total_points / self.config.m * percolation_multiplier
+            } else {
+                usize::MAX
+            };
+            let min_block_size = self.config.indexing_threshold;
+
+            let mut payload_blocks: Vec<_> = {
+                let _update_guard = update_lock.read();
+                let payload_index = self.payload_index.read();
+                payload_index
+                    .indexed_fields()
+                    .into_keys()
+                    .flat_map(|field| {
+                        debug!("collecting payload blocks for field {}", &field);
+                        payload_index
+                            .payload_blocks(&field, min_block_size)
+                            .filter(|payload_block| payload_block.cardinality <= max_block_size)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect()
+            };
+            // Fields and blocks come in the order of hash maps,
+            // but the order of merging affects the resulting links
+            payload_blocks
+                .sort_by_cached_key(|payload_block| format!("{:?}", payload_block.condition));
+
+            for payload_block in payload_blocks {
+                check_process_stopped(stopped)?;
+                // ToDo: re-use graph layer for same payload
+                let mut additional_graph = GraphLayersBuilder::new_with_params(
+                    total_points,
+                    payload_m,
+                    self.config.payload_m0.unwrap_or(self.config.m0),
+                    self.config.ef_construct,
+                    1,
+                    self.config.neighbor_selection,
+                    false,
+                );
+                let _update_guard = update_lock.read();
+                self.build_filtered_graph(
+                    &pool,
+                    stopped,
+                    &mut additional_graph,
+                    payload_block.condition,
+                    &mut block_filter_list,
+                )?;
+                graph_layers_builder.merge_from_other(additional_graph);
+            }
+        } else {
+            debug!("skip building additional HNSW links");
+        }
+
+        let graph_links_path = GraphLayers::<TGraphLinks>::get_links_path(&self.path);
+        self.graph = Some(graph_layers_builder.into_graph_layers(Some(&graph_links_path))?);
+        self.incremental_graph = None;
+        self.incremental_changed.store(false, Ordering::Relaxed);
+        debug!("finish additional payload field indexing");
+
+        let target_recall = self
+            .config
+            .ef_policy
+            .and_then(|ef_policy| ef_policy.target_recall);
+        self.config.ef_calibration = match target_recall {
+            Some(target_recall) => {
+                let _update_guard = update_lock.read();
+                self.calibrate_ef_factor(target_recall, stopped, &mut rng)?
+            }
+            None => None,
+        };
+        self.save()
+    }
+
+    /// Smallest of the tried factors of the `ef` policy, reaching `target_recall`
+    ///
+    /// Vectors of sampled points are used as queries, results of the graph search are compared with
+    /// the exact ones. If no factor reaches the target, the largest one is used.
+    /// `None` if there are no points to sample.
+    fn calibrate_ef_factor(
+        &self,
+        target_recall: f32,
+        stopped: &AtomicBool,
+        rng: &mut StdRng,
+    ) -> OperationResult<Option<EfCalibration>> {
+        let ef_policy = self.config.ef_policy.unwrap_or_default();
+        let (queries, exact_results) = {
+            let vector_storage = self.vector_storage.read();
+            let id_tracker = self.id_tracker.read();
+            let queries: Vec<_> = id_tracker
+                .iter_ids()
+                .choose_multiple(rng, EF_CALIBRATION_QUERIES)
+                .into_iter()
+                .map(|point_id| vector_storage.get_vector(point_id).to_vec())
+                .collect();
+            let exact_results: Vec<HashSet<_>> = queries
+                .iter()
+                .map(|query| {
+                    new_raw_scorer(query.clone(), &vector_storage, id_tracker.deleted_bitvec())
+                        .peek_top_all(EF_CALIBRATION_TOP)
+                        .into_iter()
+                        .map(|scored_point| scored_point.idx)
+                        .collect()
+                })
+                .collect();
+            (queries, exact_results)
+        };
+        if queries.is_empty() {
+            return Ok(None);
+        }
+
+        let mut calibration = None;
+        for &factor in EF_CALIBRATION_FACTORS {
+            check_process_stopped(stopped)?;
+            let params = SearchParams {
+                hnsw_ef: Some(policy_ef(&ef_policy, factor, EF_CALIBRATION_TOP)),
+                ..Default::default()
+            };
+            let recalls = queries.iter().zip(&exact_results).map(|(query, exact)| {
+                if exact.is_empty() {
+                    return 1.0;
+                }
+                let found = self
+                    .search_with_graph(query, None, EF_CALIBRATION_TOP, Some(&params))
+                    .into_iter()
+                    .filter(|scored_point| exact.contains(&scored_point.idx))
+                    .count();
+                found as f32 / exact.len() as f32
+            });
+            let recall = recalls.sum::<f32>() / queries.len() as f32;
+            calibration = Some(EfCalibration {
+                target_recall,
+                factor,
+                recall,
+            });
+            if recall >= target_recall {
+                break;
+            }
+        }
+        debug!("calibrated ef policy: {calibration:?}");
+        Ok(calibration)
+    }
+
     pub fn build_filtered_graph(
         &self,
         pool: &ThreadPool,
@@ -140,11 +509,15 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
 
         let filter = Filter::new_must(Field(condition));
 
-        let payload_index = self.payload_index.borrow();
-        let vector_storage = self.vector_storage.borrow();
-        let id_tracker = self.id_tracker.borrow();
+        let payload_index = self.payload_index.read();
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
 
-        let points_to_index: Vec<_> = payload_index.query_points(&filter).collect();
+        // Points, added after the start of the build, are not known to the graph
+        let points_to_index: Vec<_> = payload_index
+            .query_points(&filter)
+            .filter(|&point_id| (point_id as usize) < graph_layers_builder.num_points())
+            .collect();
 
         for block_point_id in points_to_index.iter().copied() {
             block_filter_list.check_and_update_visited(block_point_id);
@@ -195,71 +568,170 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
         top: usize,
         params: Option<&SearchParams>,
     ) -> Vec<ScoredPointOffset> {
-        let req_ef = params
-            .and_then(|params| params.hnsw_ef)
-            .unwrap_or(self.config.ef);
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        let payload_index = self.payload_index.read();
+        let filter_context = filter.map(|f| payload_index.filter_context(f));
+        self.search_with_graph_context(
+            vector,
+            filter_context.as_deref(),
+            top,
+            params,
+            &vector_storage,
+            &id_tracker,
+            &SearchDeadline::from_params(params),
+            &SearchCounters::disabled(),
+        )
+    }
+
+    /// Graph search of a single vector with the already locked storages and the prepared filter context
+    ///
+    /// It is called from multiple threads for the queries of a batch, see [`split_search_threads`].
+    #[allow(clippy::too_many_arguments)]
+    fn search_with_graph_context(
+        &self,
+        vector: &[VectorElementType],
+        filter_context: Option<&(dyn FilterContext + Sync)>,
+        top: usize,
+        params: Option<&SearchParams>,
+        vector_storage: &VectorStorageEnum,
+        id_tracker: &IdTrackerSS,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
+    ) -> Vec<ScoredPointOffset> {
+        let _phase_timer = counters.measure(SearchPhase::Scoring);
+        let ef = self.search_ef(top, params);
+        let (quantized, rescore) =
+            quantization_plan(params, vector_storage.quantized_storage().is_some());
 
-        // ef should always be bigger that required top
-        let ef = max(req_ef, top);
+        let raw_scorer = match vector_storage.quantized_storage().filter(|_| quantized) {
+            Some(quantized_storage) => {
+                quantized_storage.raw_scorer(vector, id_tracker.deleted_bitvec())
+            }
+            None => new_raw_scorer(
+                vector.to_owned(),
+                vector_storage,
+                id_tracker.deleted_bitvec(),
+            ),
+        };
 
-        let vector_storage = self.vector_storage.borrow();
-        let id_tracker = self.id_tracker.borrow();
-        let ignore_quantization = params
-            .and_then(|p| p.quantization)
-            .map(|q| q.ignore)
-            .unwrap_or(default_quantization_ignore_value());
+        let points_scorer = FilteredScorer::new(
+            raw_scorer.as_ref(),
+            filter_context.map(|context| context as &dyn FilterContext),
+        )
+        .with_deadline(deadline)
+        .with_counters(counters);
 
-        let (raw_scorer, quantized) = if ignore_quantization {
-            (
-                new_raw_scorer(
-                    vector.to_owned(),
-                    &vector_storage,
-                    id_tracker.deleted_bitvec(),
-                ),
-                false,
-            )
-        } else if let Some(quantized_storage) = vector_storage.quantized_storage() {
-            (
-                quantized_storage.raw_scorer(vector, id_tracker.deleted_bitvec()),
-                true,
-            )
+        let mut search_result = if let Some(incremental_graph) = &self.incremental_graph {
+            incremental_graph.search(top, ef, points_scorer)
+        } else if let Some(graph) = &self.graph {
+            graph.search(top, ef, points_scorer)
         } else {
-            (
-                new_raw_scorer(
-                    vector.to_owned(),
-                    &vector_storage,
-                    id_tracker.deleted_bitvec(),
-                ),
-                false,
-            )
+            return Vec::new();
         };
-        let payload_index = self.payload_index.borrow();
+        if rescore {
+            counters.add(SearchCounter::RescoredPoints, search_result.len());
+            let raw_scorer = new_raw_scorer(
+                vector.to_owned(),
+                vector_storage,
+                id_tracker.deleted_bitvec(),
+            );
+            search_result = rescore_points(
+                self.batch_scorer.as_deref(),
+                raw_scorer.as_ref(),
+                vector,
+                vector_storage,
+                search_result,
+                top,
+            );
+        }
+        search_result
+    }
 
-        let filter_context = filter.map(|f| payload_index.filter_context(f));
+    /// Size of the candidates list of the graph search, which should always be bigger than `top`
+    ///
+    /// Explicit `hnsw_ef` of the params wins over the `ef` policy of the index.
+    fn search_ef(&self, top: usize, params: Option<&SearchParams>) -> usize {
+        let req_ef = match (
+            params.and_then(|params| params.hnsw_ef),
+            self.config.ef_policy,
+        ) {
+            (Some(ef), _) => ef,
+            (None, Some(ef_policy)) => {
+                let calibrated_factor = self
+                    .config
+                    .ef_calibration
+                    .filter(|calibration| {
+                        Some(calibration.target_recall) == ef_policy.target_recall
+                    })
+                    .map(|calibration| calibration.factor);
+                let factor = calibrated_factor
+                    .or(ef_policy.factor)
+                    .unwrap_or(DEFAULT_EF_FACTOR);
+                policy_ef(&ef_policy, factor, top)
+            }
+            (None, None) => self.config.ef,
+        };
+        max(req_ef, top)
+    }
 
-        let points_scorer = FilteredScorer::new(raw_scorer.as_ref(), filter_context.as_deref());
+    /// Calibration of the `ef` policy, made after the last build
+    pub fn ef_calibration(&self) -> Option<EfCalibration> {
+        self.config.ef_calibration
+    }
 
-        if let Some(graph) = &self.graph {
-            let mut search_result = graph.search(top, ef, points_scorer);
-            let if_rescore = params
-                .and_then(|p| p.quantization)
-                .map(|q| q.rescore)
-                .unwrap_or(default_quantization_rescore_value());
-            if quantized && if_rescore {
-                let raw_scorer = new_raw_scorer(
-                    vector.to_owned(),
-                    &vector_storage,
-                    id_tracker.deleted_bitvec(),
-                );
-                search_result.iter_mut().for_each(|scored_point| {
-                    scored_point.score = raw_scorer.score_point(scored_point.idx);
-                });
-                search_result
-            } else {
-                search_result
-            }
+    /// Choose the way of the filtered search by the estimated number of matching points
+    ///
+    /// Filters, matching less than `indexing_threshold` points, are checked without the graph.
+    pub fn filtered_search_plan(&self, filter: &Filter) -> FilteredSearchPlan {
+        self.explain_filtered_search_plan(filter).0
+    }
+
+    /// Same as [`HNSWIndex::filtered_search_plan`], with the reason and the estimated cardinality
+    fn explain_filtered_search_plan(
+        &self,
+        filter: &Filter,
+    ) -> (FilteredSearchPlan, PlanReason, CardinalityEstimation) {
+        let payload_index = self.payload_index.read();
+        let query_cardinality = payload_index.estimate_cardinality(filter);
+
+        if query_cardinality.max < self.config.indexing_threshold {
+            return (
+                FilteredSearchPlan::Plain,
+                PlanReason::SmallCardinality,
+                query_cardinality,
+            );
+        }
+
+        if query_cardinality.min > self.config.indexing_threshold {
+            return (
+                FilteredSearchPlan::Graph,
+                PlanReason::LargeCardinality,
+                query_cardinality,
+            );
+        }
+
+        let filter_context = payload_index.filter_context(filter);
+
+        // Fast cardinality estimation is not enough, do sample estimation of cardinality
+        let id_tracker = self.id_tracker.read();
+        if sample_check_cardinality(
+            id_tracker.sample_internal_ids(),
+            |idx| filter_context.check(idx),
+            self.config.indexing_threshold,
+            id_tracker.points_count(),
+        ) {
+            (
+                FilteredSearchPlan::Graph,
+                PlanReason::SampledLargeCardinality,
+                query_cardinality,
+            )
         } else {
-            Vec::new()
+            (
+                FilteredSearchPlan::Plain,
+                PlanReason::SampledSmallCardinality,
+                query_cardinality,
+            )
         }
     }
 
@@ -269,11 +741,30 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
         filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
     ) -> Vec<Vec<ScoredPointOffset>> {
-        vectors
-            .iter()
-            .map(|vector| self.search_with_graph(vector, filter, top, params))
-            .collect()
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        let payload_index = self.payload_index.read();
+        // Filter context is shared by the queries of the batch, each search has its own visited list
+        let filter_context = {
+            let _phase_timer = counters.measure(SearchPhase::CandidateGeneration);
+            filter.map(|f| payload_index.filter_context(f))
+        };
+        let (batch_threads, _) = split_search_threads(self.search_threads, vectors.len());
+        map_parallel(batch_threads, vectors, |vector| {
+            self.search_with_graph_context(
+                vector,
+                filter_context.as_deref(),
+                top,
+                params,
+                &vector_storage,
+                &id_tracker,
+                deadline,
+                counters,
+            )
+        })
     }
 
     fn search_vectors_plain(
@@ -282,56 +773,106 @@ impl<TGraphLinks: GraphLinks> HNSWIndex<TGraphLinks> {
         filter: &Filter,
         top: usize,
         params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
     ) -> Vec<Vec<ScoredPointOffset>> {
-        let payload_index = self.payload_index.borrow();
-        let vector_storage = self.vector_storage.borrow();
-        let id_tracker = self.id_tracker.borrow();
-        let mut filtered_iter = payload_index.query_points(filter);
-        let ignore_quantization = params
-            .and_then(|p| p.quantization)
-            .map(|q| q.ignore)
-            .unwrap_or(default_quantization_ignore_value());
-        if ignore_quantization {
-            vectors
-                .iter()
-                .map(|vector| {
-                    new_raw_scorer(
-                        vector.to_vec(),
-                        &vector_storage,
-                        id_tracker.deleted_bitvec(),
-                    )
-                    .peek_top_iter(filtered_iter.as_mut(), top)
-                })
-                .collect()
-        } else {
-            vectors
-                .iter()
-                .map(|vector| {
-                    if let Some(quantized_storage) = vector_storage.quantized_storage() {
-                        quantized_storage
-                            .raw_scorer(vector, id_tracker.deleted_bitvec())
-                            .peek_top_iter(filtered_iter.as_mut(), top)
-                    } else {
-                        new_raw_scorer(
-                            vector.to_vec(),
-                            &vector_storage,
-                            id_tracker.deleted_bitvec(),
-                        )
-                        .peek_top_iter(filtered_iter.as_mut(), top)
-                    }
-                })
-                .collect()
+        let payload_index = self.payload_index.read();
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        // Candidates are found once and shared by the queries of the batch
+        let filtered_ids: Vec<_> = {
+            let _phase_timer = counters.measure(SearchPhase::CandidateGeneration);
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("filter").entered();
+            payload_index.query_points(filter).collect()
+        };
+        // Found points are not rescored
+        let (quantized, _) =
+            quantization_plan(params, vector_storage.quantized_storage().is_some());
+        let (batch_threads, _) = split_search_threads(self.search_threads, vectors.len());
+        map_parallel(batch_threads, vectors, |vector| {
+            let _phase_timer = counters.measure(SearchPhase::Scoring);
+            let mut filtered_iter = counters.count(
+                SearchCounter::ScoredPoints,
+                deadline.wrap(filtered_ids.iter().copied()),
+            );
+            match vector_storage.quantized_storage().filter(|_| quantized) {
+                Some(quantized_storage) => quantized_storage
+                    .raw_scorer(vector, id_tracker.deleted_bitvec())
+                    .peek_top_iter(&mut filtered_iter, top),
+                None => new_raw_scorer(
+                    vector.to_vec(),
+                    &vector_storage,
+                    id_tracker.deleted_bitvec(),
+                )
+                .peek_top_iter(&mut filtered_iter, top),
+            }
+        })
+    }
+
+    /// Plan of [`VectorIndex::search`], made by the same decisions as the search itself
+    fn search_plan(
+        &self,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> QueryPlanExplain {
+        let exact = params.map(|params| params.exact).unwrap_or(false);
+        if exact {
+            // Filtered exact search is done without quantization too
+            return QueryPlanExplain::full_scan(VectorIndexType::Hnsw, PlanReason::Exact);
+        }
+
+        let has_quantized = self.vector_storage.read().quantized_storage().is_some();
+        let (quantized, rescore) = quantization_plan(params, has_quantized);
+        let (filtered_plan, reason, filter_cardinality) = match filter {
+            Some(filter) => {
+                let (filtered_plan, reason, query_cardinality) =
+                    self.explain_filtered_search_plan(filter);
+                let filter_cardinality = Some(CardinalityExplain::from(&query_cardinality));
+                (filtered_plan, reason, filter_cardinality)
+            }
+            None => (FilteredSearchPlan::Graph, PlanReason::Unfiltered, None),
+        };
+        let threshold = filter.map(|_| self.config.indexing_threshold);
+
+        match filtered_plan {
+            FilteredSearchPlan::Plain => QueryPlanExplain {
+                filter_cardinality,
+                threshold,
+                // Found points are not rescored
+                quantized,
+                ..QueryPlanExplain::full_scan(VectorIndexType::Hnsw, reason)
+            },
+            FilteredSearchPlan::Graph => QueryPlanExplain {
+                index_type: VectorIndexType::Hnsw,
+                strategy: SearchStrategy::Index,
+                // Nothing is found without the graph
+                reason: if self.graph.is_none() && self.incremental_graph.is_none() {
+                    PlanReason::IndexNotBuilt
+                } else {
+                    reason
+                },
+                filter_cardinality,
+                threshold,
+                ef: Some(self.search_ef(top, params)),
+                nprobe: None,
+                quantized,
+                rescore,
+            },
         }
     }
 }
 
 impl<TGraphLinks: GraphLinks> VectorIndex for HNSWIndex<TGraphLinks> {
-    fn search(
+    fn search_with_deadline(
         &self,
         vectors: &[&[VectorElementType]],
         filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
     ) -> Vec<Vec<ScoredPointOffset>> {
         let exact = params.map(|params| params.exact).unwrap_or(false);
         match filter {
@@ -339,22 +880,27 @@ impl<TGraphLinks: GraphLinks> VectorIndex for HNSWIndex<TGraphLinks> {
                 if exact {
                     let _timer =
                         ScopeDurationMeasurer::new(&self.searches_telemetry.exact_unfiltered);
-                    let vector_storage = self.vector_storage.borrow();
-                    let id_tracker = self.id_tracker.borrow();
-                    vectors
-                        .iter()
-                        .map(|vector| {
-                            new_raw_scorer(
-                                vector.to_vec(),
-                                &vector_storage,
-                                id_tracker.deleted_bitvec(),
-                            )
-                            .peek_top_all(top)
-                        })
-                        .collect()
+                    let vector_storage = self.vector_storage.read();
+                    let id_tracker = self.id_tracker.read();
+                    let (batch_threads, _) =
+                        split_search_threads(self.search_threads, vectors.len());
+                    let num_points = vector_storage.total_vector_count() as PointOffsetType;
+                    map_parallel(batch_threads, vectors, |vector| {
+                        let _phase_timer = counters.measure(SearchPhase::Scoring);
+                        new_raw_scorer(
+                            vector.to_vec(),
+                            &vector_storage,
+                            id_tracker.deleted_bitvec(),
+                        )
+                        .peek_top_iter(
+                            &mut counters
+                                .count(SearchCounter::ScoredPoints, deadline.wrap(0..num_points)),
+                            top,
+                        )
+                    })
                 } else {
                     let _timer = ScopeDurationMeasurer::new(&self.searches_telemetry.unfiltered);
-                    self.search_vectors_with_graph(vectors, None, top, params)
+                    self.search_vectors_with_graph(vectors, None, top, params, deadline, counters)
                 }
             }
             Some(query_filter) => {
@@ -365,7 +911,7 @@ impl<TGraphLinks: GraphLinks> VectorIndex for HNSWIndex<TGraphLinks> {
                 // if exact search is requested, we should not use HNSW index
                 if exact {
                     let exact_params = params.map(|params| {
-                        let mut params = *params;
+                        let mut params = params.clone();
                         params.quantization = Some(QuantizationSearchParams {
                             ignore: true,
                             rescore: false,
@@ -379,167 +925,102 @@ impl<TGraphLinks: GraphLinks> VectorIndex for HNSWIndex<TGraphLinks> {
                         query_filter,
                         top,
                         exact_params.as_ref(),
+                        deadline,
+                        counters,
                     );
                 }
 
-                let payload_index = self.payload_index.borrow();
-                let query_cardinality = payload_index.estimate_cardinality(query_filter);
-
-                // debug!("query_cardinality: {:#?}", query_cardinality);
-
-                if query_cardinality.max < self.config.indexing_threshold {
-                    // if cardinality is small - use plain index
-                    let _timer =
-                        ScopeDurationMeasurer::new(&self.searches_telemetry.small_cardinality);
-                    return self.search_vectors_plain(vectors, query_filter, top, params);
-                }
-
-                if query_cardinality.min > self.config.indexing_threshold {
-                    // if cardinality is high enough - use HNSW index
-                    let _timer =
-                        ScopeDurationMeasurer::new(&self.searches_telemetry.large_cardinality);
-                    return self.search_vectors_with_graph(vectors, filter, top, params);
-                }
-
-                let filter_context = payload_index.filter_context(query_filter);
-
-                // Fast cardinality estimation is not enough, do sample estimation of cardinality
-                let id_tracker = self.id_tracker.borrow();
-                if sample_check_cardinality(
-                    id_tracker.sample_ids(),
-                    |idx| filter_context.check(idx),
-                    self.config.indexing_threshold,
-                    id_tracker.points_count(),
-                ) {
-                    // if cardinality is high enough - use HNSW index
-                    let _timer =
-                        ScopeDurationMeasurer::new(&self.searches_telemetry.large_cardinality);
-                    self.search_vectors_with_graph(vectors, filter, top, params)
-                } else {
-                    // if cardinality is small - use plain index
-                    let _timer =
-                        ScopeDurationMeasurer::new(&self.searches_telemetry.small_cardinality);
-                    self.search_vectors_plain(vectors, query_filter, top, params)
+                let (filtered_plan, _reason, _cardinality) = {
+                    let _phase_timer = counters.measure(SearchPhase::Planning);
+                    self.explain_filtered_search_plan(query_filter)
+                };
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    plan = ?filtered_plan,
+                    reason = ?_reason,
+                    estimated_points = _cardinality.exp,
+                    "filtered search plan is chosen"
+                );
+                match filtered_plan {
+                    FilteredSearchPlan::Plain => {
+                        // if cardinality is small - use plain index
+                        let _timer =
+                            ScopeDurationMeasurer::new(&self.searches_telemetry.small_cardinality);
+                        self.search_vectors_plain(
+                            vectors,
+                            query_filter,
+                            top,
+                            params,
+                            deadline,
+                            counters,
+                        )
+                    }
+                    FilteredSearchPlan::Graph => {
+                        // if cardinality is high enough - use HNSW index
+                        let _timer =
+                            ScopeDurationMeasurer::new(&self.searches_telemetry.large_cardinality);
+                        self.search_vectors_with_graph(
+                            vectors, filter, top, params, deadline, counters,
+                        )
+                    }
                 }
             }
         }
     }
 
-    fn build_index(&mut self, stopped: &AtomicBool) -> OperationResult<()> {
-        // Build main index graph
-        let vector_storage = self.vector_storage.borrow();
-        let id_tracker = self.id_tracker.borrow();
-        let mut rng = thread_rng();
-
-        let total_points = vector_storage.total_vector_count();
-
-        debug!("building hnsw for {}", total_points);
-        let mut graph_layers_builder = GraphLayersBuilder::new(
-            total_points,
-            self.config.m,
-            self.config.m0,
-            self.config.ef_construct,
-            max(1, total_points / self.config.indexing_threshold * 10),
-            HNSW_USE_HEURISTIC,
-        );
-
-        let pool = rayon::ThreadPoolBuilder::new()
-            .thread_name(|idx| format!("hnsw-build-{idx}"))
-            .num_threads(self.config.max_rayon_threads())
-            .build()?;
-
-        for vector_id in id_tracker.iter_ids() {
-            check_process_stopped(stopped)?;
-            let level = graph_layers_builder.get_random_layer(&mut rng);
-            graph_layers_builder.set_levels(vector_id, level);
-        }
-
-        if self.config.m > 0 {
-            let ids: Vec<_> = id_tracker.iter_ids().collect();
-
-            pool.install(|| {
-                ids.into_par_iter().try_for_each(|vector_id| {
-                    check_process_stopped(stopped)?;
-                    let vector = vector_storage.get_vector(vector_id).to_vec();
-                    let raw_scorer =
-                        if let Some(quantized_storage) = vector_storage.quantized_storage() {
-                            quantized_storage.raw_scorer(&vector, id_tracker.deleted_bitvec())
-                        } else {
-                            new_raw_scorer(
-                                vector.to_owned(),
-                                &vector_storage,
-                                id_tracker.deleted_bitvec(),
-                            )
-                        };
-                    let points_scorer = FilteredScorer::new(raw_scorer.as_ref(), None);
-
-                    graph_layers_builder.link_new_point(vector_id, points_scorer);
-                    Ok::<_, OperationError>(())
-                })
-            })?;
+    fn explain_search(
+        &self,
+        _vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> QueryPlanExplain {
+        self.search_plan(filter, top, params)
+    }
 
-            debug!("finish main graph");
-        } else {
-            debug!("skip building main HNSW graph");
+    fn estimate_search_cost(
+        &self,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> SearchCostEstimate {
+        let exact = params.map(|params| params.exact).unwrap_or(false);
+        let points_count = self.id_tracker.read().points_count();
+        let matching_points = match filter {
+            Some(filter) => self.payload_index.read().estimate_cardinality(filter).exp,
+            None => points_count,
+        };
+        let plain = filter
+            .map(|filter| self.filtered_search_plan(filter) == FilteredSearchPlan::Plain)
+            .unwrap_or(false);
+        if exact || plain {
+            return SearchCostEstimate::full_scan(matching_points);
         }
 
-        let total_vectors_count = vector_storage.total_vector_count();
-        let mut block_filter_list = VisitedList::new(total_vectors_count);
-
-        let payload_index = self.payload_index.borrow();
-
-        let payload_m = self.config.payload_m.unwrap_or(self.config.m);
-
-        if payload_m > 0 {
-            for (field, _) in payload_index.indexed_fields() {
-                debug!("building additional index for field {}", &field);
-
-                // It is expected, that graph will become disconnected less than
-                // $1/m$ points left.
-                // So blocks larger than $1/m$ are not needed.
-                // We add multiplier for the extra safety.
-                let percolation_multiplier = 2;
-                let max_block_size = if self.config.m > 0 {
-                    total_points / self.config.m * percolation_multiplier
-                } else {
-                    usize::MAX
-                };
-                let min_block_size = self.config.indexing_threshold;
-
-                for payload_block in payload_index.payload_blocks(&field, min_block_size) {
-                    check_process_stopped(stopped)?;
-                    if payload_block.cardinality > max_block_size {
-                        continue;
-                    }
-                    // ToDo: re-use graph layer for same payload
-                    let mut additional_graph = GraphLayersBuilder::new_with_params(
-                        self.vector_storage.borrow().total_vector_count(),
-                        payload_m,
-                        self.config.payload_m0.unwrap_or(self.config.m0),
-                        self.config.ef_construct,
-                        1,
-                        HNSW_USE_HEURISTIC,
-                        false,
-                    );
-                    self.build_filtered_graph(
-                        &pool,
-                        stopped,
-                        &mut additional_graph,
-                        payload_block.condition,
-                        &mut block_filter_list,
-                    )?;
-                    graph_layers_builder.merge_from_other(additional_graph);
-                }
-            }
-        } else {
-            debug!("skip building additional HNSW links");
+        let levels = match (&self.incremental_graph, &self.graph) {
+            (Some(incremental_graph), _) => incremental_graph.levels_count(),
+            (None, Some(graph)) => graph.links.levels_count(),
+            // Nothing is found without the graph
+            (None, None) => return SearchCostEstimate::default(),
+        };
+        let ef = self.search_ef(top, params);
+        // Search descends the upper levels greedily and expands about `ef` points on level 0.
+        // Points, not matching the filter, are passed through, so more of them are expanded.
+        let level_hops = ef.saturating_mul(points_count) / matching_points.max(1);
+        let index_hops = (levels.saturating_sub(1) + level_hops).min(points_count);
+        // Only the matching links of the expanded points are scored
+        let scored_points = index_hops.saturating_mul(self.config.m0).min(points_count)
+            * matching_points
+            / points_count.max(1);
+        SearchCostEstimate {
+            scored_points,
+            index_hops,
+            full_scan: false,
         }
+    }
 
-        let graph_links_path = GraphLayers::<TGraphLinks>::get_links_path(&self.path);
-        self.graph = Some(graph_layers_builder.into_graph_layers(Some(&graph_links_path))?);
-
-        debug!("finish additional payload field indexing");
-        self.save()
+    fn build_index(&mut self, stopped: &AtomicBool) -> OperationResult<()> {
+        self.build_index_with_progress(stopped, &RwLock::new(()), &AtomicUsize::new(0))
     }
 
     fn get_telemetry_data(&self) -> VectorIndexSearchesTelemetry {
@@ -547,9 +1028,11 @@ impl<TGraphLinks: GraphLinks> VectorIndex for HNSWIndex<TGraphLinks> {
 
         VectorIndexSearchesTelemetry {
             index_name: None,
+            indexing_threshold: Some(self.config.indexing_threshold),
             unfiltered_plain: Default::default(),
             filtered_plain: Default::default(),
             unfiltered_hnsw: tm.unfiltered.lock().get_statistics(),
+            unfiltered_ivf: Default::default(),
             filtered_small_cardinality: tm.small_cardinality.lock().get_statistics(),
             filtered_large_cardinality: tm.large_cardinality.lock().get_statistics(),
             filtered_exact: tm.exact_filtered.lock().get_statistics(),
@@ -567,4 +1050,68 @@ impl<TGraphLinks: GraphLinks> VectorIndex for HNSWIndex<TGraphLinks> {
             vec![]
         }
     }
+
+    /// Link the point into the main graph, if `incremental` is enabled
+    ///
+    /// The graph is copied into a mutable builder on the first update, and is searched instead
+    /// of the built one until the next build. A new point gets a random level, and is linked
+    /// with a single search per level, same as during the build. A changed point keeps its level,
+    /// and gets its links replaced. Links of the payload blocks are not updated.
+    fn update_vector(&mut self, id: PointOffsetType) -> OperationResult<()> {
+        if !self.config.incremental || self.config.m == 0 {
+            return Ok(());
+        }
+        let graph = match &self.graph {
+            Some(graph) => graph,
+            // All points are linked by the first build
+            None => return Ok(()),
+        };
+        let neighbor_selection = self.config.neighbor_selection;
+        let incremental_graph = self.incremental_graph.get_or_insert_with(|| {
+            GraphLayersBuilder::from_graph_layers(graph, neighbor_selection)
+        });
+
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        let vector = vector_storage.get_vector(id).to_vec();
+        let raw_scorer = if let Some(quantized_storage) = vector_storage.quantized_storage() {
+            quantized_storage.raw_scorer(&vector, id_tracker.deleted_bitvec())
+        } else {
+            new_raw_scorer(vector, &vector_storage, id_tracker.deleted_bitvec())
+        };
+        let mut rng = match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(id as u64)),
+            None => StdRng::from_entropy(),
+        };
+        incremental_graph.insert_point(id, raw_scorer.as_ref(), &mut rng);
+        self.incremental_changed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Save the incremental graph, replacing the built one on disk
+    fn flush(&self) -> OperationResult<()> {
+        let incremental_graph = match &self.incremental_graph {
+            Some(incremental_graph) => incremental_graph,
+            None => return Ok(()),
+        };
+        if self.incremental_changed.swap(false, Ordering::Relaxed) {
+            let result = incremental_graph.save(
+                &GraphLayers::<TGraphLinks>::get_path(&self.path),
+                &GraphLayers::<TGraphLinks>::get_links_path(&self.path),
+            );
+            if result.is_err() {
+                self.incremental_changed.store(true, Ordering::Relaxed);
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    fn set_batch_scorer(&mut self, batch_scorer: Option<Arc<BatchScorerSS>>) {
+        self.batch_scorer = batch_scorer;
+    }
+
+    fn set_search_threads(&mut self, max_search_threads: Option<usize>) {
+        self.search_threads = resolve_search_threads(max_search_threads);
+    }
 }
@@ -1,3 +1,5 @@
+use crate::common::search_deadline::{SearchDeadline, DEADLINE_CHECK_INTERVAL};
+use crate::common::search_telemetry::{SearchCounter, SearchCounters};
 use crate::payload_storage::FilterContext;
 use crate::types::{PointOffsetType, ScoreType};
 use crate::vector_storage::{RawScorer, ScoredPointOffset};
@@ -6,6 +8,11 @@ pub struct FilteredScorer<'a> {
     pub raw_scorer: &'a dyn RawScorer,
     pub filter_context: Option<&'a dyn FilterContext>,
     points_buffer: Vec<ScoredPointOffset>,
+    /// Once reached, no more points are scored, so the graph traversal stops
+    deadline: Option<&'a SearchDeadline>,
+    points_since_check: usize,
+    /// Hops of the traversal, checks of the filter and scored points, counted for the search telemetry
+    counters: Option<&'a SearchCounters>,
 }
 
 impl<'a> FilteredScorer<'a> {
@@ -17,9 +24,23 @@ impl<'a> FilteredScorer<'a> {
             raw_scorer,
             filter_context,
             points_buffer: Vec::new(),
+            deadline: None,
+            points_since_check: 0,
+            counters: None,
         }
     }
 
+    pub fn with_deadline(mut self, deadline: &'a SearchDeadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Counters are not updated, unless the telemetry of the search is enabled
+    pub fn with_counters(mut self, counters: &'a SearchCounters) -> Self {
+        self.counters = counters.is_enabled().then_some(counters);
+        self
+    }
+
     pub fn check_point(&self, point_id: PointOffsetType) -> bool {
         match self.filter_context {
             None => self.raw_scorer.check_point(point_id),
@@ -27,6 +48,11 @@ impl<'a> FilteredScorer<'a> {
         }
     }
 
+    /// Deleted points are never returned, but graph traversal still passes through them
+    pub fn is_deleted(&self, point_id: PointOffsetType) -> bool {
+        self.raw_scorer.is_deleted(point_id)
+    }
+
     /// Method filters and calculates scores for the given slice of points
     ///
     /// For performance reasons this function mutates input values.
@@ -42,6 +68,24 @@ impl<'a> FilteredScorer<'a> {
         point_ids: &mut [PointOffsetType],
         limit: usize,
     ) -> &[ScoredPointOffset] {
+        if let Some(deadline) = self.deadline {
+            self.points_since_check += point_ids.len();
+            if self.points_since_check >= DEADLINE_CHECK_INTERVAL {
+                self.points_since_check = 0;
+                deadline.check();
+            }
+            if deadline.is_expired() {
+                return &[];
+            }
+        }
+
+        if let Some(counters) = self.counters {
+            counters.add(SearchCounter::IndexHops, 1);
+            if self.filter_context.is_some() {
+                counters.add(SearchCounter::FilterChecks, point_ids.len());
+            }
+        }
+
         // apply filter and store filtered ids to source slice memory
         let filtered_point_ids = match self.filter_context {
             None => point_ids,
@@ -68,10 +112,16 @@ impl<'a> FilteredScorer<'a> {
         let count = self
             .raw_scorer
             .score_points(filtered_point_ids, &mut self.points_buffer);
+        if let Some(counters) = self.counters {
+            counters.add(SearchCounter::ScoredPoints, count);
+        }
         &self.points_buffer[0..count]
     }
 
     pub fn score_point(&self, point_id: PointOffsetType) -> ScoreType {
+        if let Some(counters) = self.counters {
+            counters.add(SearchCounter::ScoredPoints, 1);
+        }
         self.raw_scorer.score_point(point_id)
     }
 
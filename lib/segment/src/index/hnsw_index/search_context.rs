@@ -43,4 +43,13 @@ impl SearchContext {
             self.candidates.push(score_point);
         }
     }
+
+    /// Add the point to candidates only, if it may lead to points closer than the found ones
+    ///
+    /// Used for deleted points, which are not results, but keep the graph connected.
+    pub fn process_pass_through(&mut self, score_point: ScoredPointOffset) {
+        if !self.nearest.is_full() || score_point.score > self.lower_bound() {
+            self.candidates.push(score_point);
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::types::Indexes;
+
+/// Vector index, which is being built in a background thread
+///
+/// The new index is built next to the current one and is not used for searches,
+/// until the segment installs it. Dropping the handle cancels the build.
+pub struct IndexBuildHandle {
+    /// Config of the index being built
+    pub(crate) index: Indexes,
+    /// Number of vectors in the segment at the beginning of the build
+    pub(crate) vectors_count: usize,
+    stopped: Arc<AtomicBool>,
+    processed: Arc<AtomicUsize>,
+    total: usize,
+    thread: Option<JoinHandle<OperationResult<()>>>,
+    result: Option<OperationResult<()>>,
+}
+
+impl IndexBuildHandle {
+    /// Run `build` in a new thread
+    ///
+    /// `build` should stop as soon as the first argument is set,
+    /// and increment the second one for each processed point out of `total`.
+    pub(crate) fn spawn<F>(
+        index: Indexes,
+        vectors_count: usize,
+        total: usize,
+        build: F,
+    ) -> OperationResult<Self>
+    where
+        F: FnOnce(&AtomicBool, &AtomicUsize) -> OperationResult<()> + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let processed = Arc::new(AtomicUsize::new(0));
+
+        let thread = {
+            let stopped = stopped.clone();
+            let processed = processed.clone();
+            std::thread::Builder::new()
+                .name("index-build".to_string())
+                .spawn(move || build(&stopped, &processed))?
+        };
+
+        Ok(Self {
+            index,
+            vectors_count,
+            stopped,
+            processed,
+            total,
+            thread: Some(thread),
+            result: None,
+        })
+    }
+
+    /// Share of the processed points, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.is_finished() {
+            return 1.0;
+        }
+        if self.total == 0 {
+            return 0.0;
+        }
+        let processed = self.processed.load(Ordering::Relaxed);
+        (processed as f32 / self.total as f32).min(1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match &self.thread {
+            Some(thread) => thread.is_finished(),
+            None => true,
+        }
+    }
+
+    /// Request the build to stop, the segment keeps using its current index
+    pub fn cancel(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the build to finish
+    ///
+    /// Returns `OperationError::Cancelled`, if the build was cancelled.
+    pub fn join(&mut self) -> OperationResult<()> {
+        if let Some(thread) = self.thread.take() {
+            let result = thread.join().unwrap_or_else(|_| {
+                Err(OperationError::service_error(
+                    "Index building thread panicked",
+                ))
+            });
+            self.result = Some(result);
+        }
+        self.result.clone().unwrap_or(Ok(()))
+    }
+}
+
+impl Drop for IndexBuildHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.cancel();
+            let _ = self.join();
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use crate::common::file_operations::{atomic_save_bin, read_bin};
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::OperationResult;
+use crate::spaces::tools::peek_top_largest_iterable;
+use crate::types::{Distance, PointOffsetType};
+
+pub const IVF_LISTS_FILE: &str = "ivf_lists.bin";
+
+/// Coarse quantizer of the IVF index: trained centroids and ids of the points, closest to each of them
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct InvertedLists {
+    /// Centroids, preprocessed for the distance of the vector storage
+    centroids: Vec<Vec<VectorElementType>>,
+    /// Posting list of each centroid, sorted by id
+    postings: Vec<Vec<PointOffsetType>>,
+}
+
+impl InvertedLists {
+    /// Empty lists for the given centroids
+    pub fn new(centroids: Vec<Vec<VectorElementType>>) -> Self {
+        let postings = vec![vec![]; centroids.len()];
+        Self {
+            centroids,
+            postings,
+        }
+    }
+
+    pub fn get_path(path: &Path) -> PathBuf {
+        path.join(IVF_LISTS_FILE)
+    }
+
+    pub fn load(path: &Path) -> OperationResult<Self> {
+        Ok(read_bin(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> OperationResult<()> {
+        Ok(atomic_save_bin(path, self)?)
+    }
+
+    pub fn centroids(&self) -> &[Vec<VectorElementType>] {
+        &self.centroids
+    }
+
+    pub fn num_lists(&self) -> usize {
+        self.centroids.len()
+    }
+
+    pub fn posting(&self, list: usize) -> &[PointOffsetType] {
+        &self.postings[list]
+    }
+
+    /// Add point to the posting list, points have to be added in the order of ids
+    pub fn add_point(&mut self, list: usize, point_id: PointOffsetType) {
+        self.postings[list].push(point_id);
+    }
+
+    /// Lists of the `nprobe` centroids, closest to the query
+    ///
+    /// `query` is expected to be preprocessed for the `distance`.
+    pub fn closest_lists(
+        &self,
+        query: &[VectorElementType],
+        nprobe: usize,
+        distance: Distance,
+    ) -> Vec<usize> {
+        // Lists with the same score are ordered by their number
+        let scored = self.centroids.iter().enumerate().map(|(list, centroid)| {
+            (
+                OrderedFloat(distance.similarity(query, centroid)),
+                Reverse(list),
+            )
+        });
+        peek_top_largest_iterable(scored, nprobe)
+            .into_iter()
+            .map(|(_, Reverse(list))| list)
+            .collect()
+    }
+
+    /// Number of points in the given lists
+    pub fn lists_size(&self, lists: &[usize]) -> usize {
+        lists.iter().map(|&list| self.postings[list].len()).sum()
+    }
+}
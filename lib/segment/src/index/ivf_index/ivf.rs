@@ -0,0 +1,546 @@
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log::debug;
+use parking_lot::{Mutex, RwLock};
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use super::inverted_lists::InvertedLists;
+use super::kmeans::{nearest_centroid, train_centroids};
+use crate::common::cpu::get_num_cpus;
+use crate::common::file_operations::{atomic_save_json, read_json};
+use crate::common::operation_time_statistics::{
+    OperationDurationsAggregator, ScopeDurationMeasurer,
+};
+use crate::common::parallel::{map_parallel, resolve_search_threads, split_search_threads};
+use crate::common::search_deadline::SearchDeadline;
+use crate::common::search_telemetry::{SearchCounter, SearchCounters, SearchPhase};
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::{check_process_stopped, OperationResult};
+use crate::id_tracker::IdTrackerSS;
+use crate::index::query_plan::{
+    quantization_plan, CardinalityExplain, PlanReason, QueryPlanExplain, SearchStrategy,
+};
+use crate::index::struct_payload_index::StructPayloadIndex;
+use crate::index::{PayloadIndex, SearchCostEstimate, VectorIndex};
+use crate::telemetry::VectorIndexSearchesTelemetry;
+use crate::types::{Filter, IvfConfig, PointOffsetType, SearchParams, VectorIndexType};
+use crate::vector_storage::batch_scorer::{rescore_points, BatchScorerSS};
+use crate::vector_storage::quantized::quantized_vectors_base::QuantizedVectors;
+use crate::vector_storage::{new_raw_scorer, ScoredPointOffset, VectorStorage, VectorStorageEnum};
+
+pub const IVF_INDEX_CONFIG_FILE: &str = "ivf_config.json";
+/// Number of points, assigned to the lists within a single borrow of the storages
+const BUILD_CHUNK_SIZE: usize = 1024;
+/// Maximal number of training points per centroid, larger samples don't improve the clustering much
+const MAX_TRAINING_POINTS_PER_LIST: usize = 256;
+const KMEANS_MAX_ITERATIONS: usize = 20;
+
+/// Inverted file index
+///
+/// Points are assigned to the closest of the centroids, trained with k-means.
+/// Search only scores points of the `nprobe` lists with the centroids closest to the query.
+pub struct IvfIndex {
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
+    vector_storage: Arc<RwLock<VectorStorageEnum>>,
+    payload_index: Arc<RwLock<StructPayloadIndex>>,
+    config: IvfConfig,
+    path: PathBuf,
+    lists: Option<InvertedLists>,
+    /// External scorer, used for rescoring with the original vectors
+    batch_scorer: Option<Arc<BatchScorerSS>>,
+    /// Number of threads, searching the queries of a batch
+    search_threads: usize,
+    searches_telemetry: SearchesTelemetry,
+}
+
+struct SearchesTelemetry {
+    unfiltered: Arc<Mutex<OperationDurationsAggregator>>,
+    small_cardinality: Arc<Mutex<OperationDurationsAggregator>>,
+    large_cardinality: Arc<Mutex<OperationDurationsAggregator>>,
+    exact_filtered: Arc<Mutex<OperationDurationsAggregator>>,
+    exact_unfiltered: Arc<Mutex<OperationDurationsAggregator>>,
+}
+
+impl IvfIndex {
+    pub fn open(
+        path: &Path,
+        id_tracker: Arc<RwLock<IdTrackerSS>>,
+        vector_storage: Arc<RwLock<VectorStorageEnum>>,
+        payload_index: Arc<RwLock<StructPayloadIndex>>,
+        ivf_config: IvfConfig,
+    ) -> OperationResult<Self> {
+        create_dir_all(path)?;
+
+        let config_path = Self::get_config_path(path);
+        let config = if config_path.exists() {
+            read_json(&config_path)?
+        } else {
+            ivf_config
+        };
+
+        let lists_path = InvertedLists::get_path(path);
+        let mut rebuild = false;
+        let lists = if lists_path.exists() {
+            match InvertedLists::load(&lists_path) {
+                Ok(lists) => Some(lists),
+                Err(err) => {
+                    log::warn!(
+                        "Failed to load IVF lists from {}, rebuilding: {err}",
+                        path.display(),
+                    );
+                    rebuild = true;
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut index = IvfIndex {
+            id_tracker,
+            vector_storage,
+            payload_index,
+            config,
+            path: path.to_owned(),
+            lists,
+            batch_scorer: None,
+            search_threads: 1,
+            searches_telemetry: SearchesTelemetry {
+                unfiltered: OperationDurationsAggregator::new(),
+                small_cardinality: OperationDurationsAggregator::new(),
+                large_cardinality: OperationDurationsAggregator::new(),
+                exact_filtered: OperationDurationsAggregator::new(),
+                exact_unfiltered: OperationDurationsAggregator::new(),
+            },
+        };
+
+        if rebuild {
+            index.build_index(&AtomicBool::new(false))?;
+        }
+
+        Ok(index)
+    }
+
+    pub fn get_config_path(path: &Path) -> PathBuf {
+        path.join(IVF_INDEX_CONFIG_FILE)
+    }
+
+    /// Inverted lists of the index, `None` if the index is not built yet
+    pub fn lists(&self) -> Option<&InvertedLists> {
+        self.lists.as_ref()
+    }
+
+    pub fn save(&self) -> OperationResult<()> {
+        atomic_save_json(&Self::get_config_path(&self.path), &self.config)?;
+        if let Some(lists) = &self.lists {
+            lists.save(&InvertedLists::get_path(&self.path))?;
+        }
+        Ok(())
+    }
+
+    fn max_rayon_threads(&self) -> usize {
+        if self.config.max_indexing_threads == 0 {
+            std::cmp::max(1, get_num_cpus() - 1)
+        } else {
+            self.config.max_indexing_threads
+        }
+    }
+
+    /// Train centroids and assign points to the lists
+    ///
+    /// Centroids are trained on a random sample of the points, existing at the start of the build.
+    /// Storages are borrowed while `update_lock` is held for reading, one chunk of points at a time.
+    /// `progress` is incremented for each point, assigned to a list.
+    pub fn build_index_with_progress(
+        &mut self,
+        stopped: &AtomicBool,
+        update_lock: &RwLock<()>,
+        progress: &AtomicUsize,
+    ) -> OperationResult<()> {
+        let mut rng = match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .thread_name(|idx| format!("ivf-build-{idx}"))
+            .num_threads(self.max_rayon_threads())
+            .build()?;
+
+        let (ids, distance, training_vectors) = {
+            let _update_guard = update_lock.read();
+            let ids: Vec<_> = self.id_tracker.read().iter_ids().collect();
+            let vector_storage = self.vector_storage.read();
+            let sample_size = ids.len().min(
+                self.config
+                    .n_lists
+                    .saturating_mul(MAX_TRAINING_POINTS_PER_LIST),
+            );
+            // Stored vectors are already preprocessed for the distance
+            let training_vectors: Vec<_> = sample(&mut rng, ids.len(), sample_size)
+                .into_iter()
+                .map(|idx| vector_storage.get_vector(ids[idx]).to_vec())
+                .collect();
+            (ids, vector_storage.distance(), training_vectors)
+        };
+
+        debug!(
+            "training {} ivf centroids on {} points",
+            self.config.n_lists,
+            training_vectors.len()
+        );
+        let centroids = train_centroids(
+            &training_vectors,
+            self.config.n_lists,
+            distance,
+            KMEANS_MAX_ITERATIONS,
+            &pool,
+            stopped,
+            &mut rng,
+        )?;
+        drop(training_vectors);
+
+        let mut lists = InvertedLists::new(centroids);
+        for chunk in ids.chunks(BUILD_CHUNK_SIZE) {
+            check_process_stopped(stopped)?;
+            let _update_guard = update_lock.read();
+            let vector_storage = self.vector_storage.read();
+
+            let assignments: Vec<_> = pool.install(|| {
+                chunk
+                    .par_iter()
+                    .map(|&point_id| {
+                        let vector = vector_storage.get_vector(point_id);
+                        nearest_centroid(lists.centroids(), vector, distance)
+                    })
+                    .collect()
+            });
+            for (&point_id, list) in chunk.iter().zip(assignments) {
+                lists.add_point(list, point_id);
+            }
+            progress.fetch_add(chunk.len(), Ordering::Relaxed);
+        }
+
+        debug!("finish ivf lists for {} points", ids.len());
+        self.lists = Some(lists);
+        self.save()
+    }
+
+    /// Plan of the search with the lists: the lists to probe and the way to apply the filter
+    ///
+    /// If the filter is estimated to match fewer points than the probed lists contain,
+    /// matching points are scored directly, which is both faster and exact.
+    fn lists_search_plan(
+        &self,
+        lists: &InvertedLists,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> (Vec<usize>, QueryPlanExplain) {
+        let nprobe = params
+            .and_then(|params| params.ivf_nprobe)
+            .unwrap_or(self.config.nprobe)
+            .max(1);
+
+        let vector_storage = self.vector_storage.read();
+        let distance = vector_storage.distance();
+        let query = distance
+            .preprocess_vector(vector)
+            .unwrap_or_else(|| vector.to_vec());
+        let probed_lists = lists.closest_lists(&query, nprobe, distance);
+
+        let (quantized, rescore) =
+            quantization_plan(params, vector_storage.quantized_storage().is_some());
+        let mut plan = QueryPlanExplain {
+            index_type: VectorIndexType::Ivf,
+            strategy: SearchStrategy::Index,
+            reason: PlanReason::Unfiltered,
+            filter_cardinality: None,
+            threshold: None,
+            ef: None,
+            nprobe: Some(nprobe),
+            quantized,
+            rescore,
+        };
+        if let Some(filter) = filter {
+            let query_cardinality = self.payload_index.read().estimate_cardinality(filter);
+            let threshold = lists.lists_size(&probed_lists);
+            plan.filter_cardinality = Some(CardinalityExplain::from(&query_cardinality));
+            plan.threshold = Some(threshold);
+            if query_cardinality.max <= threshold {
+                plan.strategy = SearchStrategy::FullScan;
+                plan.reason = PlanReason::SmallCardinality;
+            } else {
+                plan.reason = PlanReason::LargeCardinality;
+            }
+        }
+        (probed_lists, plan)
+    }
+
+    /// Search points of the lists, closest to the query, as planned by [`Self::lists_search_plan`]
+    #[allow(clippy::too_many_arguments)]
+    fn search_with_lists(
+        &self,
+        lists: &InvertedLists,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
+    ) -> Vec<ScoredPointOffset> {
+        let (probed_lists, plan) = {
+            let _phase_timer = counters.measure(SearchPhase::Planning);
+            self.lists_search_plan(lists, vector, filter, params)
+        };
+        // Each probed list is a hop of the index
+        counters.add(SearchCounter::IndexHops, probed_lists.len());
+        let _phase_timer = counters.measure(SearchPhase::Scoring);
+
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        let payload_index = self.payload_index.read();
+
+        let quantized_storage = vector_storage
+            .quantized_storage()
+            .filter(|_| plan.quantized);
+        let raw_scorer = match quantized_storage {
+            Some(quantized_storage) => {
+                quantized_storage.raw_scorer(vector, id_tracker.deleted_bitvec())
+            }
+            None => new_raw_scorer(
+                vector.to_owned(),
+                &vector_storage,
+                id_tracker.deleted_bitvec(),
+            ),
+        };
+
+        let probed_points = deadline.wrap(
+            probed_lists
+                .iter()
+                .flat_map(|&list| lists.posting(list).iter().copied()),
+        );
+
+        let mut search_result = match (filter, plan.strategy) {
+            (None, _) => {
+                let _timer = ScopeDurationMeasurer::new(&self.searches_telemetry.unfiltered);
+                raw_scorer.peek_top_iter(
+                    &mut counters.count(SearchCounter::ScoredPoints, probed_points),
+                    top,
+                )
+            }
+            (Some(filter), SearchStrategy::FullScan) => {
+                let _timer = ScopeDurationMeasurer::new(&self.searches_telemetry.small_cardinality);
+                raw_scorer.peek_top_iter(
+                    &mut counters.count(
+                        SearchCounter::ScoredPoints,
+                        deadline.wrap(payload_index.query_points(filter)),
+                    ),
+                    top,
+                )
+            }
+            (Some(filter), SearchStrategy::Index) => {
+                let _timer = ScopeDurationMeasurer::new(&self.searches_telemetry.large_cardinality);
+                let filter_context = payload_index.filter_context(filter);
+                let filtered_points = counters
+                    .count(SearchCounter::FilterChecks, probed_points)
+                    .filter(|&point_id| filter_context.check(point_id));
+                raw_scorer.peek_top_iter(
+                    &mut counters.count(SearchCounter::ScoredPoints, filtered_points),
+                    top,
+                )
+            }
+        };
+
+        if plan.rescore {
+            counters.add(SearchCounter::RescoredPoints, search_result.len());
+            let raw_scorer = new_raw_scorer(
+                vector.to_owned(),
+                &vector_storage,
+                id_tracker.deleted_bitvec(),
+            );
+            search_result = rescore_points(
+                self.batch_scorer.as_deref(),
+                raw_scorer.as_ref(),
+                vector,
+                &vector_storage,
+                search_result,
+                top,
+            );
+        }
+        search_result
+    }
+
+    /// Score all points, matching the filter, without quantization
+    fn search_exact(
+        &self,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
+    ) -> Vec<ScoredPointOffset> {
+        let _phase_timer = counters.measure(SearchPhase::Scoring);
+        let vector_storage = self.vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        let raw_scorer = new_raw_scorer(
+            vector.to_owned(),
+            &vector_storage,
+            id_tracker.deleted_bitvec(),
+        );
+        match filter {
+            None => {
+                let _timer = ScopeDurationMeasurer::new(&self.searches_telemetry.exact_unfiltered);
+                let num_points = vector_storage.total_vector_count() as PointOffsetType;
+                raw_scorer.peek_top_iter(
+                    &mut counters.count(SearchCounter::ScoredPoints, deadline.wrap(0..num_points)),
+                    top,
+                )
+            }
+            Some(filter) => {
+                let _timer = ScopeDurationMeasurer::new(&self.searches_telemetry.exact_filtered);
+                let payload_index = self.payload_index.read();
+                raw_scorer.peek_top_iter(
+                    &mut counters.count(
+                        SearchCounter::ScoredPoints,
+                        deadline.wrap(payload_index.query_points(filter)),
+                    ),
+                    top,
+                )
+            }
+        }
+    }
+}
+
+impl VectorIndex for IvfIndex {
+    fn search_with_deadline(
+        &self,
+        vectors: &[&[VectorElementType]],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
+    ) -> Vec<Vec<ScoredPointOffset>> {
+        let exact = params.map(|params| params.exact).unwrap_or(false);
+        // Probed lists are chosen by each query, so the queries are planned independently
+        let (batch_threads, _) = split_search_threads(self.search_threads, vectors.len());
+        map_parallel(batch_threads, vectors, |vector| match &self.lists {
+            Some(lists) if !exact => {
+                self.search_with_lists(lists, vector, filter, top, params, deadline, counters)
+            }
+            // Index is not built yet, or exact search is requested
+            _ => self.search_exact(vector, filter, top, deadline, counters),
+        })
+    }
+
+    fn explain_search(
+        &self,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        _top: usize,
+        params: Option<&SearchParams>,
+    ) -> QueryPlanExplain {
+        let exact = params.map(|params| params.exact).unwrap_or(false);
+        match &self.lists {
+            Some(lists) if !exact => self.lists_search_plan(lists, vector, filter, params).1,
+            _ => {
+                let reason = if exact {
+                    PlanReason::Exact
+                } else {
+                    PlanReason::IndexNotBuilt
+                };
+                QueryPlanExplain::full_scan(VectorIndexType::Ivf, reason)
+            }
+        }
+    }
+
+    fn estimate_search_cost(
+        &self,
+        filter: Option<&Filter>,
+        _top: usize,
+        params: Option<&SearchParams>,
+    ) -> SearchCostEstimate {
+        let exact = params.map(|params| params.exact).unwrap_or(false);
+        let points_count = self.id_tracker.read().points_count();
+        let query_cardinality =
+            filter.map(|filter| self.payload_index.read().estimate_cardinality(filter));
+        let matching_points = query_cardinality
+            .as_ref()
+            .map(|cardinality| cardinality.exp)
+            .unwrap_or(points_count);
+
+        let lists = match &self.lists {
+            Some(lists) if !exact && lists.num_lists() > 0 => lists,
+            _ => return SearchCostEstimate::full_scan(matching_points),
+        };
+        let nprobe = params
+            .and_then(|params| params.ivf_nprobe)
+            .unwrap_or(self.config.nprobe)
+            .clamp(1, lists.num_lists());
+        // Points are assumed to be evenly distributed among the lists
+        let probed_points = points_count * nprobe / lists.num_lists();
+
+        match query_cardinality {
+            Some(cardinality) if cardinality.max <= probed_points => {
+                SearchCostEstimate::full_scan(cardinality.exp)
+            }
+            _ => SearchCostEstimate {
+                scored_points: probed_points * matching_points / points_count.max(1),
+                index_hops: nprobe,
+                full_scan: false,
+            },
+        }
+    }
+
+    fn build_index(&mut self, stopped: &AtomicBool) -> OperationResult<()> {
+        self.build_index_with_progress(stopped, &RwLock::new(()), &AtomicUsize::new(0))
+    }
+
+    fn get_telemetry_data(&self) -> VectorIndexSearchesTelemetry {
+        let tm = &self.searches_telemetry;
+
+        VectorIndexSearchesTelemetry {
+            index_name: None,
+            indexing_threshold: None,
+            unfiltered_plain: Default::default(),
+            filtered_plain: Default::default(),
+            unfiltered_hnsw: Default::default(),
+            unfiltered_ivf: tm.unfiltered.lock().get_statistics(),
+            filtered_small_cardinality: tm.small_cardinality.lock().get_statistics(),
+            filtered_large_cardinality: tm.large_cardinality.lock().get_statistics(),
+            filtered_exact: tm.exact_filtered.lock().get_statistics(),
+            unfiltered_exact: tm.exact_unfiltered.lock().get_statistics(),
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        if self.lists.is_some() {
+            vec![InvertedLists::get_path(&self.path)]
+        } else {
+            vec![]
+        }
+    }
+
+    fn update_vector(&mut self, _id: PointOffsetType) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn set_batch_scorer(&mut self, batch_scorer: Option<Arc<BatchScorerSS>>) {
+        self.batch_scorer = batch_scorer;
+    }
+
+    fn set_search_threads(&mut self, max_search_threads: Option<usize>) {
+        self.search_threads = resolve_search_threads(max_search_threads);
+    }
+}
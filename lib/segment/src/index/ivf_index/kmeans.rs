@@ -0,0 +1,201 @@
+use std::sync::atomic::AtomicBool;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use rayon::prelude::*;
+use rayon::ThreadPool;
+
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::{check_process_stopped, OperationResult};
+use crate::types::Distance;
+
+/// Index of the centroid with the largest similarity to the vector
+///
+/// `vector` and `centroids` are expected to be preprocessed for the `distance`.
+pub fn nearest_centroid(
+    centroids: &[Vec<VectorElementType>],
+    vector: &[VectorElementType],
+    distance: Distance,
+) -> usize {
+    centroids
+        .iter()
+        .map(|centroid| distance.similarity(vector, centroid))
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+/// Choose `k` initial centroids among the `vectors` with k-means++
+///
+/// Each next centroid is sampled with the probability, proportional to the dissimilarity
+/// of the vector with the closest of already chosen centroids.
+fn init_centroids<R: Rng + ?Sized>(
+    vectors: &[Vec<VectorElementType>],
+    k: usize,
+    distance: Distance,
+    pool: &ThreadPool,
+    rng: &mut R,
+) -> Vec<Vec<VectorElementType>> {
+    let dissimilarity = |vector: &[VectorElementType], centroid: &[VectorElementType]| {
+        (distance.similarity(vector, vector) - distance.similarity(vector, centroid)).max(0.0)
+    };
+
+    let first = vectors[rng.gen_range(0..vectors.len())].clone();
+    let mut min_dissimilarity: Vec<_> = pool.install(|| {
+        vectors
+            .par_iter()
+            .map(|vector| dissimilarity(vector, &first))
+            .collect()
+    });
+    let mut centroids = vec![first];
+
+    while centroids.len() < k {
+        let idx = match WeightedIndex::new(&min_dissimilarity) {
+            Ok(weights) => weights.sample(rng),
+            // All vectors coincide with the chosen centroids
+            Err(_) => rng.gen_range(0..vectors.len()),
+        };
+        let centroid = vectors[idx].clone();
+        pool.install(|| {
+            min_dissimilarity
+                .par_iter_mut()
+                .zip(vectors.par_iter())
+                .for_each(|(min, vector)| *min = min.min(dissimilarity(vector, &centroid)));
+        });
+        centroids.push(centroid);
+    }
+
+    centroids
+}
+
+/// Train up to `k` centroids over the `vectors` with Lloyd's algorithm
+///
+/// Centroids are initialized with k-means++.
+/// Training stops, when centroids don't change or `max_iterations` is reached.
+/// Vectors are expected to be preprocessed for the `distance`, so are the returned centroids.
+pub fn train_centroids<R: Rng + ?Sized>(
+    vectors: &[Vec<VectorElementType>],
+    k: usize,
+    distance: Distance,
+    max_iterations: usize,
+    pool: &ThreadPool,
+    stopped: &AtomicBool,
+    rng: &mut R,
+) -> OperationResult<Vec<Vec<VectorElementType>>> {
+    let k = k.min(vectors.len());
+    if k == 0 {
+        return Ok(vec![]);
+    }
+    let dim = vectors[0].len();
+
+    let mut centroids = init_centroids(vectors, k, distance, pool, rng);
+
+    for _ in 0..max_iterations {
+        check_process_stopped(stopped)?;
+
+        let assignments: Vec<_> = pool.install(|| {
+            vectors
+                .par_iter()
+                .map(|vector| nearest_centroid(&centroids, vector, distance))
+                .collect()
+        });
+
+        let mut sums = vec![vec![0.0; dim]; k];
+        let mut counts = vec![0usize; k];
+        for (vector, &cluster) in vectors.iter().zip(&assignments) {
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(vector) {
+                *sum += value;
+            }
+        }
+
+        let mut changed = false;
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // Empty cluster keeps its previous centroid
+            if count == 0 {
+                continue;
+            }
+            let mean: Vec<_> = sum
+                .into_iter()
+                .map(|value| value / count as VectorElementType)
+                .collect();
+            let mean = distance.preprocess_vector(&mean).unwrap_or(mean);
+            if mean != *centroid {
+                *centroid = mean;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(centroids)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_train_centroids() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+
+        let centers = [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]];
+        let vectors: Vec<Vec<VectorElementType>> = (0..300)
+            .map(|i| {
+                let center = centers[i % centers.len()];
+                vec![
+                    center[0] + rng.gen_range(-1.0..1.0),
+                    center[1] + rng.gen_range(-1.0..1.0),
+                ]
+            })
+            .collect();
+
+        let centroids = train_centroids(
+            &vectors,
+            3,
+            Distance::Euclid,
+            20,
+            &pool,
+            &AtomicBool::new(false),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(centroids.len(), 3);
+
+        // Points of the same cluster share the centroid, different clusters don't
+        let clusters: Vec<_> = vectors
+            .iter()
+            .map(|vector| nearest_centroid(&centroids, vector, Distance::Euclid))
+            .collect();
+        for (i, cluster) in clusters.iter().enumerate() {
+            assert_eq!(*cluster, clusters[i % centers.len()]);
+        }
+        assert_ne!(clusters[0], clusters[1]);
+        assert_ne!(clusters[0], clusters[2]);
+        assert_ne!(clusters[1], clusters[2]);
+
+        // Can't have more centroids than vectors
+        let centroids = train_centroids(
+            &vectors[..2],
+            3,
+            Distance::Euclid,
+            20,
+            &pool,
+            &AtomicBool::new(false),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(centroids.len(), 2);
+    }
+}
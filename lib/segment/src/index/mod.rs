@@ -1,15 +1,19 @@
 pub mod field_index;
+pub mod filter_cache;
 pub mod hnsw_index;
+pub mod index_build_handle;
+pub mod ivf_index;
 mod key_encoding;
 mod payload_config;
 mod payload_index_base;
 pub mod plain_payload_index;
 pub mod query_estimator;
 mod query_optimization;
+pub mod query_plan;
 mod sample_estimation;
 mod struct_filter_context;
 pub mod struct_payload_index;
 mod vector_index_base;
-mod visited_pool;
+pub mod visited_pool;
 pub use payload_index_base::*;
 pub use vector_index_base::*;
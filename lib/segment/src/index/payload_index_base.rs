@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use serde_json::Value;
 
+use crate::common::payload_selection::PayloadSelection;
 use crate::common::Flusher;
 use crate::entry::entry_point::OperationResult;
 use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
@@ -38,7 +40,8 @@ pub trait PayloadIndex {
     /// Return number of points, indexed by this field
     fn indexed_points(&self, field: PayloadKeyTypeRef) -> usize;
 
-    fn filter_context<'a>(&'a self, filter: &'a Filter) -> Box<dyn FilterContext + 'a>;
+    /// Prepare the filter for checking points one by one
+    fn filter_context<'a>(&'a self, filter: &'a Filter) -> Box<dyn FilterContext + Sync + 'a>;
 
     /// Iterate conditions for payload blocks with minimum size of `threshold`
     /// Required for building HNSW index
@@ -59,7 +62,16 @@ pub trait PayloadIndex {
     fn assign(&mut self, point_id: PointOffsetType, payload: &Payload) -> OperationResult<()>;
 
     /// Get payload for point
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload>;
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>>;
+
+    /// Get the selected fields of the payload for point, read from the storage directly if it supports it
+    fn selected_payload(
+        &self,
+        point_id: PointOffsetType,
+        selection: &PayloadSelection,
+    ) -> OperationResult<Payload> {
+        Ok(selection.select(&self.payload(point_id)?))
+    }
 
     /// Delete payload by key
     fn delete(
@@ -71,6 +83,9 @@ pub trait PayloadIndex {
     /// Drop all payload of the point
     fn drop(&mut self, point_id: PointOffsetType) -> OperationResult<Option<Payload>>;
 
+    /// Move all payload of the point `from` to the point `to`, replacing the payload of `to`
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()>;
+
     /// Completely drop payload. Pufff!
     fn wipe(&mut self) -> OperationResult<()>;
 
@@ -4,29 +4,35 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use schemars::_serde_json::Value;
 
-use crate::common::arc_atomic_ref_cell_iterator::ArcAtomicRefCellIterator;
+use crate::common::arc_rwlock_iterator::ArcRwLockIterator;
 use crate::common::operation_time_statistics::{
     OperationDurationStatistics, OperationDurationsAggregator, ScopeDurationMeasurer,
 };
+use crate::common::parallel::{map_parallel, resolve_search_threads, split_search_threads};
+use crate::common::search_deadline::SearchDeadline;
+use crate::common::search_telemetry::{SearchCounter, SearchCounters, SearchPhase};
 use crate::common::Flusher;
 use crate::data_types::vectors::VectorElementType;
 use crate::entry::entry_point::OperationResult;
 use crate::id_tracker::IdTrackerSS;
 use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
 use crate::index::payload_config::PayloadConfig;
+use crate::index::query_plan::{CardinalityExplain, PlanReason, QueryPlanExplain};
 use crate::index::struct_payload_index::StructPayloadIndex;
-use crate::index::{PayloadIndex, VectorIndex};
+use crate::index::{PayloadIndex, SearchCostEstimate, VectorIndex};
 use crate::payload_storage::{ConditionCheckerSS, FilterContext};
 use crate::telemetry::VectorIndexSearchesTelemetry;
 use crate::types::{
     Filter, Payload, PayloadFieldSchema, PayloadKeyType, PayloadKeyTypeRef, PayloadSchemaType,
-    PointOffsetType, SearchParams,
+    PointOffsetType, SearchParams, VectorIndexType,
+};
+use crate::vector_storage::batch_scorer::{peek_top_with_provider, BatchScorerSS};
+use crate::vector_storage::{
+    new_raw_scorer, peek_top_chunked, ScoredPointOffset, VectorStorage, VectorStorageEnum,
 };
-use crate::vector_storage::{new_raw_scorer, ScoredPointOffset, VectorStorageEnum};
 
 /// Implementation of `PayloadIndex` which does not really indexes anything.
 ///
@@ -34,7 +40,7 @@ use crate::vector_storage::{new_raw_scorer, ScoredPointOffset, VectorStorageEnum
 /// rather than spend time for index re-building
 pub struct PlainPayloadIndex {
     condition_checker: Arc<ConditionCheckerSS>,
-    id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
     config: PayloadConfig,
     path: PathBuf,
 }
@@ -51,7 +57,7 @@ impl PlainPayloadIndex {
 
     pub fn open(
         condition_checker: Arc<ConditionCheckerSS>,
-        id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+        id_tracker: Arc<RwLock<IdTrackerSS>>,
         path: &Path,
     ) -> OperationResult<Self> {
         create_dir_all(path)?;
@@ -105,7 +111,7 @@ impl PayloadIndex for PlainPayloadIndex {
     }
 
     fn estimate_cardinality(&self, _query: &Filter) -> CardinalityEstimation {
-        let total_points = self.id_tracker.borrow().points_count();
+        let total_points = self.id_tracker.read().points_count();
         CardinalityEstimation {
             primary_clauses: vec![],
             min: 0,
@@ -119,7 +125,7 @@ impl PayloadIndex for PlainPayloadIndex {
         query: &'a Filter,
     ) -> Box<dyn Iterator<Item = PointOffsetType> + 'a> {
         let filter_context = self.filter_context(query);
-        Box::new(ArcAtomicRefCellIterator::new(
+        Box::new(ArcRwLockIterator::new(
             self.id_tracker.clone(),
             move |points_iterator| {
                 points_iterator
@@ -133,7 +139,7 @@ impl PayloadIndex for PlainPayloadIndex {
         0 // No points are indexed in the plain index
     }
 
-    fn filter_context<'a>(&'a self, filter: &'a Filter) -> Box<dyn FilterContext + 'a> {
+    fn filter_context<'a>(&'a self, filter: &'a Filter) -> Box<dyn FilterContext + Sync + 'a> {
         Box::new(PlainFilterContext {
             filter,
             condition_checker: self.condition_checker.clone(),
@@ -153,7 +159,7 @@ impl PayloadIndex for PlainPayloadIndex {
         unreachable!()
     }
 
-    fn payload(&self, _point_id: PointOffsetType) -> OperationResult<Payload> {
+    fn payload(&self, _point_id: PointOffsetType) -> OperationResult<Arc<Payload>> {
         unreachable!()
     }
 
@@ -169,6 +175,10 @@ impl PayloadIndex for PlainPayloadIndex {
         unreachable!()
     }
 
+    fn move_entry(&mut self, _from: PointOffsetType, _to: PointOffsetType) -> OperationResult<()> {
+        unreachable!()
+    }
+
     fn wipe(&mut self) -> OperationResult<()> {
         unreachable!()
     }
@@ -194,18 +204,25 @@ impl PayloadIndex for PlainPayloadIndex {
 }
 
 pub struct PlainIndex {
-    id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
-    vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
-    payload_index: Arc<AtomicRefCell<StructPayloadIndex>>,
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
+    vector_storage: Arc<RwLock<VectorStorageEnum>>,
+    payload_index: Arc<RwLock<StructPayloadIndex>>,
     filtered_searches_telemetry: Arc<Mutex<OperationDurationsAggregator>>,
     unfiltered_searches_telemetry: Arc<Mutex<OperationDurationsAggregator>>,
+    /// Number of threads, scoring a single query
+    search_threads: usize,
+    /// External scorer, which is offered the points before scoring them on CPU
+    batch_scorer: Option<Arc<BatchScorerSS>>,
 }
 
 impl PlainIndex {
+    /// `max_search_threads` - limit of threads, scoring a single query.
+    /// If `None` - query is scored in the calling thread, if 0 - auto selection.
     pub fn new(
-        id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
-        vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
-        payload_index: Arc<AtomicRefCell<StructPayloadIndex>>,
+        id_tracker: Arc<RwLock<IdTrackerSS>>,
+        vector_storage: Arc<RwLock<VectorStorageEnum>>,
+        payload_index: Arc<RwLock<StructPayloadIndex>>,
+        max_search_threads: Option<usize>,
     ) -> PlainIndex {
         PlainIndex {
             id_tracker,
@@ -213,56 +230,150 @@ impl PlainIndex {
             payload_index,
             filtered_searches_telemetry: OperationDurationsAggregator::new(),
             unfiltered_searches_telemetry: OperationDurationsAggregator::new(),
+            search_threads: resolve_search_threads(max_search_threads),
+            batch_scorer: None,
         }
     }
 }
 
 impl VectorIndex for PlainIndex {
-    fn search(
+    fn search_with_deadline(
         &self,
         vectors: &[&[VectorElementType]],
         filter: Option<&Filter>,
         top: usize,
         _params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
     ) -> Vec<Vec<ScoredPointOffset>> {
         match filter {
             Some(filter) => {
                 let _timer = ScopeDurationMeasurer::new(&self.filtered_searches_telemetry);
-                let payload_index = self.payload_index.borrow();
-                let vector_storage = self.vector_storage.borrow();
-                let id_tracker = self.id_tracker.borrow();
-                let filtered_ids_vec: Vec<_> = payload_index.query_points(filter).collect();
-                vectors
-                    .iter()
-                    .map(|vector| {
+                let payload_index = self.payload_index.read();
+                let vector_storage = self.vector_storage.read();
+                let id_tracker = self.id_tracker.read();
+                // Candidates are found once and shared by the queries of the batch
+                let filtered_ids_vec: Vec<_> = {
+                    let _phase_timer = counters.measure(SearchPhase::CandidateGeneration);
+                    #[cfg(feature = "tracing")]
+                    let _span = tracing::debug_span!("filter").entered();
+                    payload_index.query_points(filter).collect()
+                };
+                let (batch_threads, query_threads) =
+                    split_search_threads(self.search_threads, vectors.len());
+                map_parallel(batch_threads, vectors, |vector| {
+                    let _phase_timer = counters.measure(SearchPhase::Scoring);
+                    if let Some(batch_scorer) = &self.batch_scorer {
+                        let raw_scorer = new_raw_scorer(
+                            vector.to_vec(),
+                            &vector_storage,
+                            id_tracker.deleted_bitvec(),
+                        );
+                        return peek_top_with_provider(
+                            batch_scorer.as_ref(),
+                            raw_scorer.as_ref(),
+                            vector,
+                            &vector_storage,
+                            &mut counters.count(
+                                SearchCounter::ScoredPoints,
+                                deadline.wrap(filtered_ids_vec.iter().copied()),
+                            ),
+                            top,
+                        );
+                    }
+                    peek_top_chunked(filtered_ids_vec.len(), top, query_threads, |range| {
                         new_raw_scorer(
                             vector.to_vec(),
                             &vector_storage,
                             id_tracker.deleted_bitvec(),
                         )
-                        .peek_top_iter(&mut filtered_ids_vec.iter().copied(), top)
+                        .peek_top_iter(
+                            &mut counters.count(
+                                SearchCounter::ScoredPoints,
+                                deadline.wrap(filtered_ids_vec[range].iter().copied()),
+                            ),
+                            top,
+                        )
                     })
-                    .collect()
+                })
             }
             None => {
                 let _timer = ScopeDurationMeasurer::new(&self.unfiltered_searches_telemetry);
-                let vector_storage = self.vector_storage.borrow();
-                let id_tracker = self.id_tracker.borrow();
-                vectors
-                    .iter()
-                    .map(|vector| {
+                let vector_storage = self.vector_storage.read();
+                let id_tracker = self.id_tracker.read();
+                let num_points = vector_storage.total_vector_count();
+                let (batch_threads, query_threads) =
+                    split_search_threads(self.search_threads, vectors.len());
+                map_parallel(batch_threads, vectors, |vector| {
+                    let _phase_timer = counters.measure(SearchPhase::Scoring);
+                    if let Some(batch_scorer) = &self.batch_scorer {
+                        let raw_scorer = new_raw_scorer(
+                            vector.to_vec(),
+                            &vector_storage,
+                            id_tracker.deleted_bitvec(),
+                        );
+                        return peek_top_with_provider(
+                            batch_scorer.as_ref(),
+                            raw_scorer.as_ref(),
+                            vector,
+                            &vector_storage,
+                            &mut counters.count(
+                                SearchCounter::ScoredPoints,
+                                deadline.wrap(0..num_points as PointOffsetType),
+                            ),
+                            top,
+                        );
+                    }
+                    peek_top_chunked(num_points, top, query_threads, |range| {
                         new_raw_scorer(
                             vector.to_vec(),
                             &vector_storage,
                             id_tracker.deleted_bitvec(),
                         )
-                        .peek_top_all(top)
+                        .peek_top_iter(
+                            &mut counters.count(
+                                SearchCounter::ScoredPoints,
+                                deadline.wrap(
+                                    range.start as PointOffsetType..range.end as PointOffsetType,
+                                ),
+                            ),
+                            top,
+                        )
                     })
-                    .collect()
+                })
             }
         }
     }
 
+    fn explain_search(
+        &self,
+        _vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        _top: usize,
+        _params: Option<&SearchParams>,
+    ) -> QueryPlanExplain {
+        let filter_cardinality = filter.map(|filter| {
+            CardinalityExplain::from(&self.payload_index.read().estimate_cardinality(filter))
+        });
+        QueryPlanExplain {
+            filter_cardinality,
+            ..QueryPlanExplain::full_scan(VectorIndexType::Plain, PlanReason::NoIndex)
+        }
+    }
+
+    fn estimate_search_cost(
+        &self,
+        filter: Option<&Filter>,
+        _top: usize,
+        _params: Option<&SearchParams>,
+    ) -> SearchCostEstimate {
+        let scored_points = match filter {
+            Some(filter) => self.payload_index.read().estimate_cardinality(filter).exp,
+            None => self.id_tracker.read().points_count(),
+        };
+        SearchCostEstimate::full_scan(scored_points)
+    }
+
     fn build_index(&mut self, _stopped: &AtomicBool) -> OperationResult<()> {
         Ok(())
     }
@@ -270,9 +381,11 @@ impl VectorIndex for PlainIndex {
     fn get_telemetry_data(&self) -> VectorIndexSearchesTelemetry {
         VectorIndexSearchesTelemetry {
             index_name: None,
+            indexing_threshold: None,
             unfiltered_plain: self.unfiltered_searches_telemetry.lock().get_statistics(),
             filtered_plain: self.filtered_searches_telemetry.lock().get_statistics(),
             unfiltered_hnsw: OperationDurationStatistics::default(),
+            unfiltered_ivf: OperationDurationStatistics::default(),
             filtered_small_cardinality: OperationDurationStatistics::default(),
             filtered_large_cardinality: OperationDurationStatistics::default(),
             filtered_exact: OperationDurationStatistics::default(),
@@ -283,6 +396,22 @@ impl VectorIndex for PlainIndex {
     fn files(&self) -> Vec<PathBuf> {
         vec![]
     }
+
+    fn update_vector(&mut self, _id: PointOffsetType) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn set_batch_scorer(&mut self, batch_scorer: Option<Arc<BatchScorerSS>>) {
+        self.batch_scorer = batch_scorer;
+    }
+
+    fn set_search_threads(&mut self, max_search_threads: Option<usize>) {
+        self.search_threads = resolve_search_threads(max_search_threads);
+    }
 }
 
 pub struct PlainFilterContext<'a> {
@@ -148,11 +148,13 @@ pub fn invert_estimation(
     estimation: &CardinalityEstimation,
     total: usize,
 ) -> CardinalityEstimation {
+    // Estimations of nested conditions might exceed `total` (e.g. with stale index counts),
+    // so clamp them instead of underflowing
     CardinalityEstimation {
         primary_clauses: vec![],
-        min: total - estimation.max,
-        exp: total - estimation.exp,
-        max: total - estimation.min,
+        min: total.saturating_sub(estimation.max),
+        exp: total.saturating_sub(estimation.exp),
+        max: total.saturating_sub(estimation.min),
     }
 }
 
@@ -186,6 +188,7 @@ mod tests {
             range: None,
             geo_bounding_box: None,
             geo_radius: None,
+            geo_polygon: None,
             values_count: None,
         })
     }
@@ -219,7 +222,7 @@ mod tests {
                     has_id
                         .has_id
                         .iter()
-                        .map(|&x| format!("{x}").parse().unwrap()) // hack to convert ID as "number"
+                        .map(|x| format!("{x}").parse().unwrap()) // hack to convert ID as "number"
                         .collect(),
                 )],
                 min: has_id.has_id.len(),
@@ -238,6 +241,7 @@ mod tests {
                 exp: TOTAL / 2,
                 max: TOTAL,
             },
+            Condition::Nested(_) | Condition::HasVector(_) => CardinalityEstimation::unknown(TOTAL),
         }
     }
 
@@ -384,6 +388,68 @@ mod tests {
         assert!(estimation.min <= estimation.exp);
     }
 
+    #[test]
+    fn must_and_must_not_same_key_estimation_test() {
+        let query = Filter {
+            should: None,
+            must: Some(vec![test_condition("color".to_owned())]),
+            must_not: Some(vec![test_condition("color".to_owned())]),
+        };
+
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        // Only the `must` clause can be used for the primary selection
+        assert_eq!(estimation.primary_clauses.len(), 1);
+        assert!(estimation.max <= TOTAL);
+        assert!(estimation.exp <= estimation.max);
+        assert!(estimation.min <= estimation.exp);
+    }
+
+    #[test]
+    fn empty_should_estimation_test() {
+        let query = Filter {
+            should: Some(vec![]),
+            must: Some(vec![test_condition("size".to_owned())]),
+            must_not: None,
+        };
+
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert_eq!(estimation.exp, 100);
+        assert_eq!(estimation.min, 100);
+        assert_eq!(estimation.max, 100);
+    }
+
+    #[test]
+    fn should_with_must_same_key_estimation_test() {
+        let query = Filter {
+            should: Some(vec![
+                test_condition("color".to_owned()),
+                test_condition("size".to_owned()),
+            ]),
+            must: Some(vec![test_condition("size".to_owned())]),
+            must_not: None,
+        };
+
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert!(estimation.max <= 100);
+        assert!(estimation.exp <= estimation.max);
+        assert!(estimation.min <= estimation.exp);
+    }
+
+    #[test]
+    fn test_invert_estimation_clamped() {
+        let estimation = CardinalityEstimation {
+            primary_clauses: vec![],
+            min: 10,
+            exp: TOTAL + 5,
+            max: TOTAL + 10,
+        };
+
+        let inverted = invert_estimation(&estimation, TOTAL);
+        assert_eq!(inverted.min, 0);
+        assert_eq!(inverted.exp, 0);
+        assert_eq!(inverted.max, TOTAL - 10);
+    }
+
     #[test]
     fn test_combine_must_estimations() {
         let estimations = vec![CardinalityEstimation {
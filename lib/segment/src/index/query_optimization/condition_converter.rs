@@ -6,11 +6,12 @@ use crate::index::query_optimization::optimized_filter::ConditionCheckerFn;
 use crate::index::query_optimization::optimizer::IndexesMap;
 use crate::index::query_optimization::payload_provider::PayloadProvider;
 use crate::payload_storage::query_checker::{
-    check_field_condition, check_is_empty_condition, check_is_null_condition,
+    check_is_empty_condition, check_is_null_condition, MatchAnySets,
 };
 use crate::types::{
-    AnyVariants, Condition, FieldCondition, FloatPayloadType, GeoBoundingBox, GeoRadius, Match,
-    MatchAny, MatchText, MatchValue, PointOffsetType, Range, ValueVariants,
+    AnyVariants, Condition, FieldCondition, FloatPayloadType, GeoBoundingBox, GeoPolygon,
+    GeoRadius, IntPayloadType, Match, MatchAny, MatchExcept, MatchInsensitive, MatchText,
+    MatchValue, PointOffsetType, Range, ValueVariants,
 };
 
 pub fn condition_converter<'a>(
@@ -29,9 +30,11 @@ pub fn condition_converter<'a>(
                     .next()
             })
             .unwrap_or_else(|| {
+                // Values of `any` are hashed once for all the points
+                let any_sets = MatchAnySets::of_condition(field_condition);
                 Box::new(move |point_id| {
                     payload_provider.with_payload(point_id, |payload| {
-                        check_field_condition(field_condition, &payload)
+                        any_sets.check_field_condition(field_condition, &payload)
                     })
                 })
             }),
@@ -48,19 +51,76 @@ pub fn condition_converter<'a>(
                 check_is_null_condition(is_null, &payload)
             })
         }),
+        Condition::Nested(nested) => {
+            let any_sets = MatchAnySets::of_filter(&nested.nested.filter);
+            Box::new(move |point_id| {
+                payload_provider.with_payload(point_id, |payload| {
+                    any_sets.check_nested_condition(nested, &payload)
+                })
+            })
+        }
         // ToDo: It might be possible to make this condition faster by using `VisitedPool` instead of HashSet
         Condition::HasId(has_id) => {
             let segment_ids: HashSet<_> = has_id
                 .has_id
                 .iter()
-                .filter_map(|external_id| id_tracker.internal_id(*external_id))
+                .filter_map(|external_id| id_tracker.internal_id(external_id.clone()))
                 .collect();
             Box::new(move |point_id| segment_ids.contains(&point_id))
         }
+        // Resolved by the segment against its vector storages, see `Filter::resolve_has_vector`
+        Condition::HasVector(has_vector) => {
+            debug_assert!(
+                false,
+                "has_vector condition on {} is not resolved",
+                has_vector.has_vector
+            );
+            Box::new(|_| false)
+        }
         Condition::Filter(_) => unreachable!(),
     }
 }
 
+/// Relative cost of a condition check, which uses only payload index or point ids
+const INDEXED_CHECK_COST: usize = 1;
+/// Relative cost of a condition check, which has to read the payload
+const PAYLOAD_CHECK_COST: usize = 10;
+/// Relative cost of full-text match and precise geo checks, which have to read the payload
+const EXPENSIVE_PAYLOAD_CHECK_COST: usize = 20;
+
+/// Estimate relative cost of checking the condition for a single point
+pub fn condition_cost(condition: &Condition, field_indexes: &IndexesMap) -> usize {
+    match condition {
+        Condition::Field(field_condition) => {
+            let is_indexed = field_indexes
+                .get(&field_condition.key)
+                .map_or(false, |indexes| {
+                    indexes
+                        .iter()
+                        .any(|index| field_condition_index(index, field_condition).is_some())
+                });
+            if is_indexed {
+                INDEXED_CHECK_COST
+            } else if matches!(field_condition.r#match, Some(Match::Text(_)))
+                || field_condition.geo_radius.is_some()
+                || field_condition.geo_polygon.is_some()
+            {
+                EXPENSIVE_PAYLOAD_CHECK_COST
+            } else {
+                PAYLOAD_CHECK_COST
+            }
+        }
+        Condition::IsEmpty(_) | Condition::IsNull(_) => PAYLOAD_CHECK_COST,
+        // Every object of the array is checked against the whole nested filter
+        Condition::Nested(_) => EXPENSIVE_PAYLOAD_CHECK_COST,
+        Condition::HasId(_) | Condition::HasVector(_) => INDEXED_CHECK_COST,
+        Condition::Filter(filter) => filter
+            .iter_conditions()
+            .map(|condition| condition_cost(condition, field_indexes))
+            .sum(),
+    }
+}
+
 pub fn field_condition_index<'a>(
     index: &'a FieldIndex,
     field_condition: &FieldCondition,
@@ -97,6 +157,14 @@ pub fn field_condition_index<'a>(
         return Some(checker);
     }
 
+    if let Some(checker) = field_condition
+        .geo_polygon
+        .clone()
+        .and_then(|cond| get_geo_polygon_checkers(index, cond))
+    {
+        return Some(checker);
+    }
+
     None
 }
 
@@ -134,6 +202,23 @@ pub fn get_geo_bounding_box_checkers(
     }
 }
 
+pub fn get_geo_polygon_checkers(
+    index: &FieldIndex,
+    geo_polygon: GeoPolygon,
+) -> Option<ConditionCheckerFn> {
+    match index {
+        FieldIndex::GeoIndex(geo_index) => Some(Box::new(move |point_id: PointOffsetType| {
+            match geo_index.get_values(point_id) {
+                None => false,
+                Some(values) => values
+                    .iter()
+                    .any(|geo_point| geo_polygon.check_point(geo_point.lon, geo_point.lat)),
+            }
+        })),
+        _ => None,
+    }
+}
+
 pub fn get_range_checkers(index: &FieldIndex, range: Range) -> Option<ConditionCheckerFn> {
     match index {
         FieldIndex::IntIndex(num_index) => Some(Box::new(move |point_id: PointOffsetType| {
@@ -192,18 +277,55 @@ pub fn get_match_checkers(index: &FieldIndex, cond_match: Match) -> Option<Condi
         },
         Match::Any(MatchAny { any }) => match (any, index) {
             (AnyVariants::Keywords(list), FieldIndex::KeywordIndex(index)) => {
+                let set: HashSet<String> = list.into_iter().collect();
                 Some(Box::new(move |point_id: PointOffsetType| {
                     match index.get_values(point_id) {
                         None => false,
-                        Some(values) => values.iter().any(|k| list.contains(k)),
+                        Some(values) => values.iter().any(|k| set.contains(k)),
                     }
                 }))
             }
             (AnyVariants::Integers(list), FieldIndex::IntMapIndex(index)) => {
+                let set: HashSet<IntPayloadType> = list.into_iter().collect();
+                Some(Box::new(move |point_id: PointOffsetType| {
+                    match index.get_values(point_id) {
+                        None => false,
+                        Some(values) => values.iter().any(|i| set.contains(i)),
+                    }
+                }))
+            }
+            _ => None,
+        },
+        // Case-sensitive keyword index can't serve this condition, payload is checked instead
+        Match::Insensitive(MatchInsensitive { insensitive }) => match index {
+            FieldIndex::InsensitiveKeywordIndex(index) => {
+                let keyword = insensitive.to_lowercase();
                 Some(Box::new(move |point_id: PointOffsetType| {
                     match index.get_values(point_id) {
                         None => false,
-                        Some(values) => values.iter().any(|i| list.contains(i)),
+                        Some(values) => values.iter().any(|k| k == &keyword),
+                    }
+                }))
+            }
+            _ => None,
+        },
+        // Values of other types are not indexed, but can't be excepted either
+        Match::Except(MatchExcept { except }) => match (except, index) {
+            (AnyVariants::Keywords(list), FieldIndex::KeywordIndex(index)) => {
+                let set: HashSet<String> = list.into_iter().collect();
+                Some(Box::new(move |point_id: PointOffsetType| {
+                    match index.get_values(point_id) {
+                        None => true,
+                        Some(values) => !values.iter().any(|k| set.contains(k)),
+                    }
+                }))
+            }
+            (AnyVariants::Integers(list), FieldIndex::IntMapIndex(index)) => {
+                let set: HashSet<IntPayloadType> = list.into_iter().collect();
+                Some(Box::new(move |point_id: PointOffsetType| {
+                    match index.get_values(point_id) {
+                        None => true,
+                        Some(values) => !values.iter().any(|i| set.contains(i)),
                     }
                 }))
             }
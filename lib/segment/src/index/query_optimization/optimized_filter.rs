@@ -1,6 +1,6 @@
 use crate::types::PointOffsetType;
 
-pub type ConditionCheckerFn<'a> = Box<dyn Fn(PointOffsetType) -> bool + 'a>;
+pub type ConditionCheckerFn<'a> = Box<dyn Fn(PointOffsetType) -> bool + Sync + 'a>;
 
 pub enum OptimizedCondition<'a> {
     Checker(ConditionCheckerFn<'a>),
@@ -34,6 +34,8 @@ fn check_should(should: &Option<Vec<OptimizedCondition>>, point_id: PointOffsetT
     let check = |condition| check_condition(condition, point_id);
     match should {
         None => true,
+        // Empty `should` clause is not a constraint
+        Some(conditions) if conditions.is_empty() => true,
         Some(conditions) => conditions.iter().any(check),
     }
 }
@@ -1,14 +1,14 @@
-use std::cmp::Reverse;
 use std::collections::HashMap;
 
 use itertools::Itertools;
+use ordered_float::OrderedFloat;
 
 use crate::id_tracker::IdTrackerSS;
 use crate::index::field_index::{CardinalityEstimation, FieldIndex};
 use crate::index::query_estimator::{
     combine_must_estimations, combine_should_estimations, invert_estimation,
 };
-use crate::index::query_optimization::condition_converter::condition_converter;
+use crate::index::query_optimization::condition_converter::{condition_converter, condition_cost};
 use crate::index::query_optimization::optimized_filter::{OptimizedCondition, OptimizedFilter};
 use crate::index::query_optimization::payload_provider::PayloadProvider;
 use crate::types::{Condition, Filter, PayloadKeyType};
@@ -21,7 +21,7 @@ pub type IndexesMap = HashMap<PayloadKeyType, Vec<FieldIndex>>;
 ///
 /// * Convert each condition into a checker function
 /// * Use column index, avoid reading Payload, if possible
-/// * Re-order operations using estimated cardinalities
+/// * Re-order operations using estimated cardinalities and costs of the checks
 ///
 /// ToDo: Add optimizations between clauses
 ///
@@ -105,6 +105,23 @@ where
     )
 }
 
+/// Expected cost of the checks until the one, which decides the outcome of the clause
+///
+/// Ordering conditions by `cost / p`, where `p` is the probability of the decisive result,
+/// minimizes the expected cost of the whole clause.
+fn decision_cost(cost: usize, probability: f64) -> OrderedFloat<f64> {
+    if probability > 0.0 {
+        OrderedFloat(cost as f64 / probability)
+    } else {
+        OrderedFloat(f64::INFINITY)
+    }
+}
+
+/// Probability of a point to satisfy the condition
+fn match_probability(estimation: &CardinalityEstimation, total: usize) -> f64 {
+    (estimation.exp as f64 / total.max(1) as f64).min(1.0)
+}
+
 fn convert_conditions<'a, F>(
     conditions: &'a [Condition],
     id_tracker: &IdTrackerSS,
@@ -112,38 +129,59 @@ fn convert_conditions<'a, F>(
     payload_provider: PayloadProvider,
     estimator: &F,
     total: usize,
-) -> Vec<(OptimizedCondition<'a>, CardinalityEstimation)>
+) -> Vec<(OptimizedCondition<'a>, CardinalityEstimation, usize)>
 where
     F: Fn(&Condition) -> CardinalityEstimation,
 {
     conditions
         .iter()
-        .map(|condition| match condition {
-            Condition::Filter(filter) => {
-                let (optimized_filter, estimation) = optimize_filter(
-                    filter,
-                    id_tracker,
-                    field_indexes,
-                    payload_provider.clone(),
-                    estimator,
-                    total,
-                );
-                (OptimizedCondition::Filter(optimized_filter), estimation)
-            }
-            _ => {
-                let estimation = estimator(condition);
-                let condition_checker = condition_converter(
-                    condition,
-                    field_indexes,
-                    payload_provider.clone(),
-                    id_tracker,
-                );
-                (OptimizedCondition::Checker(condition_checker), estimation)
-            }
+        .map(|condition| {
+            let cost = condition_cost(condition, field_indexes);
+            let (optimized_condition, estimation) = convert_condition(
+                condition,
+                id_tracker,
+                field_indexes,
+                payload_provider.clone(),
+                estimator,
+                total,
+            );
+            (optimized_condition, estimation, cost)
         })
         .collect()
 }
 
+fn convert_condition<'a, F>(
+    condition: &'a Condition,
+    id_tracker: &IdTrackerSS,
+    field_indexes: &'a IndexesMap,
+    payload_provider: PayloadProvider,
+    estimator: &F,
+    total: usize,
+) -> (OptimizedCondition<'a>, CardinalityEstimation)
+where
+    F: Fn(&Condition) -> CardinalityEstimation,
+{
+    match condition {
+        Condition::Filter(filter) => {
+            let (optimized_filter, estimation) = optimize_filter(
+                filter,
+                id_tracker,
+                field_indexes,
+                payload_provider,
+                estimator,
+                total,
+            );
+            (OptimizedCondition::Filter(optimized_filter), estimation)
+        }
+        _ => {
+            let estimation = estimator(condition);
+            let condition_checker =
+                condition_converter(condition, field_indexes, payload_provider, id_tracker);
+            (OptimizedCondition::Checker(condition_checker), estimation)
+        }
+    }
+}
+
 fn optimize_should<'a, F>(
     conditions: &'a [Condition],
     id_tracker: &IdTrackerSS,
@@ -163,9 +201,14 @@ where
         estimator,
         total,
     );
-    // More probable conditions first
-    converted.sort_by_key(|(_, estimation)| Reverse(estimation.exp));
-    let (conditions, estimations): (Vec<_>, Vec<_>) = converted.into_iter().unzip();
+    // Cheap and probable conditions first, as any match is enough
+    converted.sort_by_key(|(_, estimation, cost)| {
+        decision_cost(*cost, match_probability(estimation, total))
+    });
+    let (conditions, estimations): (Vec<_>, Vec<_>) = converted
+        .into_iter()
+        .map(|(condition, estimation, _)| (condition, estimation))
+        .unzip();
 
     (conditions, combine_should_estimations(&estimations, total))
 }
@@ -189,9 +232,14 @@ where
         estimator,
         total,
     );
-    // Less probable conditions first
-    converted.sort_by_key(|(_, estimation)| estimation.exp);
-    let (conditions, estimations): (Vec<_>, Vec<_>) = converted.into_iter().unzip();
+    // Cheap and less probable conditions first, as any mismatch is enough
+    converted.sort_by_key(|(_, estimation, cost)| {
+        decision_cost(*cost, 1.0 - match_probability(estimation, total))
+    });
+    let (conditions, estimations): (Vec<_>, Vec<_>) = converted
+        .into_iter()
+        .map(|(condition, estimation, _)| (condition, estimation))
+        .unzip();
 
     (conditions, combine_must_estimations(&estimations, total))
 }
@@ -215,9 +263,14 @@ where
         estimator,
         total,
     );
-    // More probable conditions first, as it will be reverted
-    converted.sort_by_key(|(_, estimation)| estimation.exp);
-    let (conditions, estimations): (Vec<_>, Vec<_>) = converted.into_iter().unzip();
+    // Cheap and more probable conditions first, as it will be reverted
+    converted.sort_by_key(|(_, estimation, cost)| {
+        decision_cost(*cost, match_probability(estimation, total))
+    });
+    let (conditions, estimations): (Vec<_>, Vec<_>) = converted
+        .into_iter()
+        .map(|(condition, estimation, _)| (condition, estimation))
+        .unzip();
 
     (
         conditions,
@@ -1,19 +1,19 @@
 use std::ops::Deref;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
+use parking_lot::RwLock;
 
 use crate::payload_storage::payload_storage_enum::PayloadStorageEnum;
 use crate::types::{OwnedPayloadRef, Payload, PointOffsetType};
 
 #[derive(Clone)]
 pub struct PayloadProvider {
-    payload_storage: Arc<AtomicRefCell<PayloadStorageEnum>>,
+    payload_storage: Arc<RwLock<PayloadStorageEnum>>,
     empty_payload: Payload,
 }
 
 impl PayloadProvider {
-    pub fn new(payload_storage: Arc<AtomicRefCell<PayloadStorageEnum>>) -> Self {
+    pub fn new(payload_storage: Arc<RwLock<PayloadStorageEnum>>) -> Self {
         Self {
             payload_storage,
             empty_payload: Default::default(),
@@ -24,7 +24,7 @@ impl PayloadProvider {
     where
         F: FnOnce(OwnedPayloadRef) -> G,
     {
-        let payload_storage_guard = self.payload_storage.borrow();
+        let payload_storage_guard = self.payload_storage.read();
         let payload_ptr_opt = match payload_storage_guard.deref() {
             PayloadStorageEnum::InMemoryPayloadStorage(s) => {
                 s.payload_ptr(point_id).map(|x| x.into())
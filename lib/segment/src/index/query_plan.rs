@@ -0,0 +1,117 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::index::field_index::CardinalityEstimation;
+use crate::types::{
+    default_quantization_ignore_value, default_quantization_rescore_value, SearchParams,
+    VectorIndexType,
+};
+
+/// Plan of a search, chosen by the vector index before any point is scored
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct QueryPlanExplain {
+    pub index_type: VectorIndexType,
+    pub strategy: SearchStrategy,
+    pub reason: PlanReason,
+    /// Estimated number of points, matching the filter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_cardinality: Option<CardinalityExplain>,
+    /// Number of matching points, compared with the cardinality of the filter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<usize>,
+    /// Size of the candidates list of the graph search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ef: Option<usize>,
+    /// Number of the probed lists of the IVF index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nprobe: Option<usize>,
+    /// Points are scored with the quantized vectors
+    pub quantized: bool,
+    /// Found points are rescored with the original vectors
+    pub rescore: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchStrategy {
+    /// Score all points, matching the filter
+    FullScan,
+    /// Search the vector index, skipping points which don't match the filter
+    Index,
+}
+
+/// Reason of the chosen [`SearchStrategy`]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanReason {
+    /// Only plain index is available
+    NoIndex,
+    /// Vector index is not built yet
+    IndexNotBuilt,
+    /// Exact search is requested
+    Exact,
+    /// There is no filter
+    Unfiltered,
+    /// Filter is estimated to match fewer points than the threshold
+    SmallCardinality,
+    /// Filter is estimated to match more points than the threshold
+    LargeCardinality,
+    /// Estimation is ambiguous, sampled points match fewer than the threshold
+    SampledSmallCardinality,
+    /// Estimation is ambiguous, sampled points match more than the threshold
+    SampledLargeCardinality,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct CardinalityExplain {
+    pub min: usize,
+    pub exp: usize,
+    pub max: usize,
+}
+
+impl From<&CardinalityEstimation> for CardinalityExplain {
+    fn from(estimation: &CardinalityEstimation) -> Self {
+        CardinalityExplain {
+            min: estimation.min,
+            exp: estimation.exp,
+            max: estimation.max,
+        }
+    }
+}
+
+impl QueryPlanExplain {
+    /// Plan of scoring all points without quantization
+    pub fn full_scan(index_type: VectorIndexType, reason: PlanReason) -> Self {
+        QueryPlanExplain {
+            index_type,
+            strategy: SearchStrategy::FullScan,
+            reason,
+            filter_cardinality: None,
+            threshold: None,
+            ef: None,
+            nprobe: None,
+            quantized: false,
+            rescore: false,
+        }
+    }
+}
+
+/// Whether the search scores points with quantized vectors, and rescores the found ones
+///
+/// Decision of the search itself, so the explanation of the plan matches it.
+pub(crate) fn quantization_plan(
+    params: Option<&SearchParams>,
+    has_quantized: bool,
+) -> (bool, bool) {
+    let quantization = params.and_then(|params| params.quantization);
+    let ignore = quantization
+        .map(|quantization| quantization.ignore)
+        .unwrap_or(default_quantization_ignore_value());
+    let rescore = quantization
+        .map(|quantization| quantization.rescore)
+        .unwrap_or(default_quantization_rescore_value());
+    let quantized = has_quantized && !ignore;
+    (quantized, quantized && rescore)
+}
@@ -4,13 +4,15 @@ use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
+use bitvec::vec::BitVec;
 use log::debug;
 use parking_lot::RwLock;
 use rocksdb::DB;
 use schemars::_serde_json::Value;
 
-use crate::common::arc_atomic_ref_cell_iterator::ArcAtomicRefCellIterator;
+use crate::common::arc_rwlock_iterator::ArcRwLockIterator;
+use crate::common::parallel::{get_load_threads, run_parallel, ParallelTask};
+use crate::common::payload_selection::PayloadSelection;
 use crate::common::rocksdb_wrapper::open_db_with_existing_cf;
 use crate::common::utils::MultiValue;
 use crate::common::Flusher;
@@ -20,8 +22,9 @@ use crate::index::field_index::index_selector::index_selector;
 use crate::index::field_index::{
     CardinalityEstimation, FieldIndex, PayloadBlockCondition, PrimaryCondition,
 };
+use crate::index::filter_cache::{CachedFilterContext, FilterCache};
 use crate::index::payload_config::PayloadConfig;
-use crate::index::query_estimator::estimate_filter;
+use crate::index::query_estimator::{estimate_filter, invert_estimation};
 use crate::index::query_optimization::optimizer::IndexesMap;
 use crate::index::query_optimization::payload_provider::PayloadProvider;
 use crate::index::struct_filter_context::StructFilterContext;
@@ -29,11 +32,11 @@ use crate::index::visited_pool::VisitedPool;
 use crate::index::PayloadIndex;
 use crate::payload_storage::payload_storage_enum::PayloadStorageEnum;
 use crate::payload_storage::{FilterContext, PayloadStorage};
-use crate::telemetry::PayloadIndexTelemetry;
+use crate::telemetry::{FilterCacheTelemetry, PayloadIndexTelemetry};
 use crate::types::{
-    infer_collection_value_type, infer_value_type, Condition, FieldCondition, Filter,
-    IsEmptyCondition, IsNullCondition, Payload, PayloadFieldSchema, PayloadKeyType,
-    PayloadKeyTypeRef, PayloadSchemaType, PointOffsetType,
+    infer_collection_value_type, infer_value_type, Condition, Direction, FieldCondition, Filter,
+    IsEmptyCondition, IsNullCondition, Match, MatchAny, MatchExcept, Payload, PayloadFieldSchema,
+    PayloadKeyType, PayloadKeyTypeRef, PayloadSchemaType, PointOffsetType,
 };
 
 pub const PAYLOAD_FIELD_INDEX_PATH: &str = "fields";
@@ -41,8 +44,8 @@ pub const PAYLOAD_FIELD_INDEX_PATH: &str = "fields";
 /// `PayloadIndex` implementation, which actually uses index structures for providing faster search
 pub struct StructPayloadIndex {
     /// Payload storage
-    payload: Arc<AtomicRefCell<PayloadStorageEnum>>,
-    id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+    payload: Arc<RwLock<PayloadStorageEnum>>,
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
     /// Indexes, associated with fields
     pub field_indexes: IndexesMap,
     config: PayloadConfig,
@@ -50,6 +53,8 @@ pub struct StructPayloadIndex {
     path: PathBuf,
     visited_pool: VisitedPool,
     db: Arc<RwLock<DB>>,
+    /// Points, matched by recently used filters
+    filter_cache: FilterCache,
 }
 
 impl StructPayloadIndex {
@@ -57,6 +62,20 @@ impl StructPayloadIndex {
         &self,
         condition: &FieldCondition,
     ) -> Option<CardinalityEstimation> {
+        // Points without values match `except` as well, so it is estimated
+        // by subtracting points with any of the listed values from all the points
+        if let Some(Match::Except(MatchExcept { except })) = &condition.r#match {
+            let any_condition = FieldCondition::new_match(
+                condition.key.clone(),
+                Match::Any(MatchAny {
+                    any: except.clone(),
+                }),
+            );
+            return self
+                .estimate_field_condition(&any_condition)
+                .map(|estimation| invert_estimation(&estimation, self.total_points()));
+        }
+
         self.field_indexes.get(&condition.key).and_then(|indexes| {
             let mut result_estimation: Option<CardinalityEstimation> = None;
             for index in indexes {
@@ -102,13 +121,23 @@ impl StructPayloadIndex {
         Self::get_field_index_dir(path).join(format!("{field}.idx"))
     }
 
+    /// Load the indexes of all fields in parallel, the missing ones are rebuilt from the payload
     fn load_all_fields(&mut self) -> OperationResult<()> {
-        let mut field_indexes: IndexesMap = Default::default();
-
-        for (field, payload_schema) in &self.config.indexed_fields {
-            let field_index = self.load_from_db(field, payload_schema.to_owned())?;
-            field_indexes.insert(field.clone(), field_index);
-        }
+        let this = &*self;
+        let tasks: Vec<ParallelTask<_>> = this
+            .config
+            .indexed_fields
+            .iter()
+            .map(|(field, payload_schema)| {
+                Box::new(move || {
+                    let field_index = this.load_from_db(field, payload_schema.to_owned())?;
+                    Ok((field.clone(), field_index))
+                }) as ParallelTask<_>
+            })
+            .collect();
+        let field_indexes: IndexesMap = run_parallel(get_load_threads(), tasks)?
+            .into_iter()
+            .collect();
         self.field_indexes = field_indexes;
         Ok(())
     }
@@ -136,9 +165,10 @@ impl StructPayloadIndex {
     }
 
     pub fn open(
-        payload: Arc<AtomicRefCell<PayloadStorageEnum>>,
-        id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+        payload: Arc<RwLock<PayloadStorageEnum>>,
+        id_tracker: Arc<RwLock<IdTrackerSS>>,
         path: &Path,
+        filter_cache_size: usize,
     ) -> OperationResult<Self> {
         create_dir_all(path)?;
         let config_path = PayloadConfig::get_config_path(path);
@@ -159,6 +189,7 @@ impl StructPayloadIndex {
             path: path.to_owned(),
             visited_pool: Default::default(),
             db,
+            filter_cache: FilterCache::new(filter_cache_size),
         };
 
         if !index.config_path().exists() {
@@ -176,7 +207,7 @@ impl StructPayloadIndex {
         field: PayloadKeyTypeRef,
         payload_schema: PayloadFieldSchema,
     ) -> OperationResult<Vec<FieldIndex>> {
-        let payload_storage = self.payload.borrow();
+        let payload_storage = self.payload.read();
         let mut field_indexes = index_selector(field, &payload_schema, self.db.clone());
         for index in &field_indexes {
             index.recreate()?;
@@ -203,12 +234,12 @@ impl StructPayloadIndex {
     }
 
     pub fn total_points(&self) -> usize {
-        self.id_tracker.borrow().points_count()
+        self.id_tracker.read().points_count()
     }
 
     fn struct_filtered_context<'a>(&'a self, filter: &'a Filter) -> StructFilterContext<'a> {
         let estimator = |condition: &Condition| self.condition_cardinality(condition);
-        let id_tracker = self.id_tracker.borrow();
+        let id_tracker = self.id_tracker.read();
         let payload_provider = PayloadProvider::new(self.payload.clone());
         StructFilterContext::new(
             filter,
@@ -225,64 +256,53 @@ impl StructPayloadIndex {
             Condition::Filter(_) => panic!("Unexpected branching"),
             Condition::IsEmpty(IsEmptyCondition { is_empty: field }) => {
                 let total_points = self.total_points();
-
-                let mut indexed_points = 0;
-                if let Some(field_indexes) = self.field_indexes.get(&field.key) {
-                    for index in field_indexes {
-                        indexed_points = indexed_points.max(index.count_indexed_points())
-                    }
-                    CardinalityEstimation {
-                        primary_clauses: vec![PrimaryCondition::IsEmpty(IsEmptyCondition {
-                            is_empty: field.to_owned(),
-                        })],
+                let primary_clauses = vec![PrimaryCondition::IsEmpty(IsEmptyCondition {
+                    is_empty: field.to_owned(),
+                })];
+                match self.points_with_key(&field.key) {
+                    // Point with an indexed value is never empty
+                    Some(indexed_points) => CardinalityEstimation {
+                        primary_clauses,
                         min: 0, // It is possible, that some non-empty payloads are not indexed
                         exp: total_points.saturating_sub(indexed_points), // Expect field type consistency
                         max: total_points.saturating_sub(indexed_points),
-                    }
-                } else {
-                    CardinalityEstimation {
-                        primary_clauses: vec![PrimaryCondition::IsEmpty(IsEmptyCondition {
-                            is_empty: field.to_owned(),
-                        })],
+                    },
+                    None => CardinalityEstimation {
+                        primary_clauses,
                         min: 0,
                         exp: total_points / 2,
                         max: total_points,
-                    }
+                    },
                 }
             }
             Condition::IsNull(IsNullCondition { is_null: field }) => {
                 let total_points = self.total_points();
-
-                let mut indexed_points = 0;
-                if let Some(field_indexes) = self.field_indexes.get(&field.key) {
-                    for index in field_indexes {
-                        indexed_points = indexed_points.max(index.count_indexed_points())
-                    }
-                    CardinalityEstimation {
-                        primary_clauses: vec![PrimaryCondition::IsNull(IsNullCondition {
-                            is_null: field.to_owned(),
-                        })],
+                let primary_clauses = vec![PrimaryCondition::IsNull(IsNullCondition {
+                    is_null: field.to_owned(),
+                })];
+                match self.points_with_key(&field.key) {
+                    // Nulls are not indexed, but multi-valued fields might contain
+                    // a null next to an indexed value, so indexed points can't be excluded
+                    Some(indexed_points) => CardinalityEstimation {
+                        primary_clauses,
                         min: 0,
                         exp: total_points.saturating_sub(indexed_points),
-                        max: total_points.saturating_sub(indexed_points),
-                    }
-                } else {
-                    CardinalityEstimation {
-                        primary_clauses: vec![PrimaryCondition::IsNull(IsNullCondition {
-                            is_null: field.to_owned(),
-                        })],
+                        max: total_points,
+                    },
+                    None => CardinalityEstimation {
+                        primary_clauses,
                         min: 0,
                         exp: total_points / 2,
                         max: total_points,
-                    }
+                    },
                 }
             }
             Condition::HasId(has_id) => {
-                let id_tracker_ref = self.id_tracker.borrow();
+                let id_tracker_ref = self.id_tracker.read();
                 let mapped_ids: HashSet<PointOffsetType> = has_id
                     .has_id
                     .iter()
-                    .filter_map(|external_id| id_tracker_ref.internal_id(*external_id))
+                    .filter_map(|external_id| id_tracker_ref.internal_id(external_id.clone()))
                     .collect();
                 let num_ids = mapped_ids.len();
                 CardinalityEstimation {
@@ -295,9 +315,147 @@ impl StructPayloadIndex {
             Condition::Field(field_condition) => self
                 .estimate_field_condition(field_condition)
                 .unwrap_or_else(|| CardinalityEstimation::unknown(self.total_points())),
+            Condition::Nested(_) => CardinalityEstimation::unknown(self.total_points()),
+            // Resolved by the segment against its vector storages, see `Filter::resolve_has_vector`
+            Condition::HasVector(_) => CardinalityEstimation::exact(0),
         }
     }
 
+    /// Number of points with at least one indexed value of the key, `None` if key is not indexed
+    fn points_with_key(&self, key: PayloadKeyTypeRef) -> Option<usize> {
+        self.field_indexes.get(key).map(|field_indexes| {
+            field_indexes
+                .iter()
+                .map(|index| index.count_indexed_points())
+                .max()
+                .unwrap_or(0)
+        })
+    }
+
+    /// Check if any point has a value for the given key
+    pub fn has_payload_key(&self, key: PayloadKeyTypeRef) -> OperationResult<bool> {
+        let mut found = false;
+        self.payload.read().iter(|_id, payload: &Payload| {
+            found = !payload.get_value(key).values().is_empty();
+            Ok(!found)
+        })?;
+        Ok(found)
+    }
+
+    /// Points in the order of the values of the field, see [`FieldIndex::ordered_values`]
+    ///
+    /// The field must have the integer or float index.
+    pub fn ordered_values(
+        &self,
+        key: PayloadKeyTypeRef,
+        start_from: Option<f64>,
+        direction: Direction,
+    ) -> OperationResult<Box<dyn Iterator<Item = (f64, PointOffsetType)> + '_>> {
+        self.field_indexes
+            .get(key)
+            .into_iter()
+            .flatten()
+            .find_map(|index| index.ordered_values(start_from, direction))
+            .ok_or_else(|| OperationError::UnorderedField {
+                field_name: key.to_owned(),
+            })
+    }
+
+    /// Drop cached filter results, must be called on any change of the segment
+    pub fn invalidate_filter_cache(&self) {
+        self.filter_cache.invalidate();
+    }
+
+    /// Number of the payloads, serialized by the payload storage
+    pub fn serialized_payloads(&self) -> usize {
+        self.payload.read().serialized_payloads()
+    }
+
+    pub fn get_filter_cache_telemetry(&self) -> Option<FilterCacheTelemetry> {
+        self.filter_cache
+            .is_enabled()
+            .then(|| self.filter_cache.get_telemetry_data())
+    }
+
+    /// Points, matched by the filter, if filter caching is enabled
+    fn cached_points(&self, filter: &Filter) -> Option<Arc<BitVec>> {
+        if !self.filter_cache.is_enabled() {
+            return None;
+        }
+
+        let key = FilterCache::key(filter);
+        if let Some(points) = self.filter_cache.get(&key) {
+            return Some(points);
+        }
+
+        let generation = self.filter_cache.generation();
+        let mut points = BitVec::repeat(false, self.id_tracker.read().internal_size());
+        for point_id in self.query_points_uncached(filter) {
+            let point_id = point_id as usize;
+            if point_id >= points.len() {
+                points.resize(point_id + 1, false);
+            }
+            points.set(point_id, true);
+        }
+        let points = Arc::new(points);
+        self.filter_cache.insert(key, points.clone(), generation);
+        Some(points)
+    }
+
+    fn query_points_uncached<'a>(
+        &'a self,
+        query: &'a Filter,
+    ) -> Box<dyn Iterator<Item = PointOffsetType> + 'a> {
+        // Assume query is already estimated to be small enough so we can iterate over all matched ids
+
+        let query_cardinality = self.estimate_cardinality(query);
+        return if query_cardinality.primary_clauses.is_empty() {
+            let full_scan_iterator =
+                ArcRwLockIterator::new(self.id_tracker.clone(), |points_iterator| {
+                    points_iterator.iter_ids()
+                });
+
+            let struct_filtered_context = self.struct_filtered_context(query);
+            // Worst case: query expected to return few matches, but index can't be used
+            let matched_points =
+                full_scan_iterator.filter(move |i| struct_filtered_context.check(*i));
+
+            Box::new(matched_points)
+        } else {
+            let points_iterator_ref = self.id_tracker.read();
+            let struct_filtered_context = self.struct_filtered_context(query);
+
+            // CPU-optimized strategy here: points are made unique before applying other filters.
+            // ToDo: Implement iterator which holds the `visited_pool` and borrowed `vector_storage_ref` to prevent `preselected` array creation
+            let mut visited_list = self.visited_pool.get(points_iterator_ref.internal_size());
+
+            #[allow(clippy::needless_collect)]
+                let preselected: Vec<PointOffsetType> = query_cardinality
+                .primary_clauses
+                .iter()
+                .flat_map(|clause| {
+                    match clause {
+                        PrimaryCondition::Condition(field_condition) => {
+                            self.query_field(field_condition).unwrap_or_else(
+                                || points_iterator_ref.iter_ids(), /* index is not built */
+                            )
+                        }
+                        PrimaryCondition::Ids(ids) => Box::new(ids.iter().copied()),
+                        PrimaryCondition::IsEmpty(_) => points_iterator_ref.iter_ids(), /* there are no fast index for IsEmpty */
+                        PrimaryCondition::IsNull(_) => points_iterator_ref.iter_ids(),  /* no fast index for IsNull too */
+                    }
+                })
+                .filter(|&id| !visited_list.check_and_update_visited(id))
+                .filter(move |&i| struct_filtered_context.check(i))
+                .collect();
+
+            self.visited_pool.return_back(visited_list);
+
+            let matched_points_iter = preselected.into_iter();
+            Box::new(matched_points_iter)
+        };
+    }
+
     pub fn get_telemetry_data(&self) -> Vec<PayloadIndexTelemetry> {
         self.field_indexes
             .iter()
@@ -367,54 +525,14 @@ impl PayloadIndex for StructPayloadIndex {
         &'a self,
         query: &'a Filter,
     ) -> Box<dyn Iterator<Item = PointOffsetType> + 'a> {
-        // Assume query is already estimated to be small enough so we can iterate over all matched ids
-
-        let query_cardinality = self.estimate_cardinality(query);
-        return if query_cardinality.primary_clauses.is_empty() {
-            let full_scan_iterator =
-                ArcAtomicRefCellIterator::new(self.id_tracker.clone(), |points_iterator| {
-                    points_iterator.iter_ids()
-                });
-
-            let struct_filtered_context = self.struct_filtered_context(query);
-            // Worst case: query expected to return few matches, but index can't be used
-            let matched_points =
-                full_scan_iterator.filter(move |i| struct_filtered_context.check(*i));
-
-            Box::new(matched_points)
-        } else {
-            let points_iterator_ref = self.id_tracker.borrow();
-            let struct_filtered_context = self.struct_filtered_context(query);
-
-            // CPU-optimized strategy here: points are made unique before applying other filters.
-            // ToDo: Implement iterator which holds the `visited_pool` and borrowed `vector_storage_ref` to prevent `preselected` array creation
-            let mut visited_list = self.visited_pool.get(points_iterator_ref.internal_size());
-
-            #[allow(clippy::needless_collect)]
-                let preselected: Vec<PointOffsetType> = query_cardinality
-                .primary_clauses
-                .iter()
-                .flat_map(|clause| {
-                    match clause {
-                        PrimaryCondition::Condition(field_condition) => {
-                            self.query_field(field_condition).unwrap_or_else(
-                                || points_iterator_ref.iter_ids(), /* index is not built */
-                            )
-                        }
-                        PrimaryCondition::Ids(ids) => Box::new(ids.iter().copied()),
-                        PrimaryCondition::IsEmpty(_) => points_iterator_ref.iter_ids(), /* there are no fast index for IsEmpty */
-                        PrimaryCondition::IsNull(_) => points_iterator_ref.iter_ids(),  /* no fast index for IsNull too */
-                    }
-                })
-                .filter(|&id| !visited_list.check_and_update_visited(id))
-                .filter(move |&i| struct_filtered_context.check(i))
-                .collect();
-
-            self.visited_pool.return_back(visited_list);
-
-            let matched_points_iter = preselected.into_iter();
-            Box::new(matched_points_iter)
-        };
+        match self.cached_points(query) {
+            Some(points) => Box::new(
+                (0..points.len())
+                    .filter(move |&point_id| points[point_id])
+                    .map(|point_id| point_id as PointOffsetType),
+            ),
+            None => self.query_points_uncached(query),
+        }
     }
 
     fn indexed_points(&self, field: PayloadKeyTypeRef) -> usize {
@@ -430,8 +548,11 @@ impl PayloadIndex for StructPayloadIndex {
         })
     }
 
-    fn filter_context<'a>(&'a self, filter: &'a Filter) -> Box<dyn FilterContext + 'a> {
-        Box::new(self.struct_filtered_context(filter))
+    fn filter_context<'a>(&'a self, filter: &'a Filter) -> Box<dyn FilterContext + Sync + 'a> {
+        match self.cached_points(filter) {
+            Some(points) => Box::new(CachedFilterContext::new(points)),
+            None => Box::new(self.struct_filtered_context(filter)),
+        }
     }
 
     fn payload_blocks(
@@ -457,11 +578,20 @@ impl PayloadIndex for StructPayloadIndex {
                 index.add_point(point_id, field_value)?;
             }
         }
-        self.payload.borrow_mut().assign(point_id, payload)
+        self.filter_cache.invalidate();
+        self.payload.write().assign(point_id, payload)
     }
 
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload> {
-        self.payload.borrow().payload(point_id)
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>> {
+        self.payload.read().payload(point_id)
+    }
+
+    fn selected_payload(
+        &self,
+        point_id: PointOffsetType,
+        selection: &PayloadSelection,
+    ) -> OperationResult<Payload> {
+        self.payload.read().selected_payload(point_id, selection)
     }
 
     fn delete(
@@ -474,7 +604,8 @@ impl PayloadIndex for StructPayloadIndex {
                 index.remove_point(point_id)?;
             }
         }
-        self.payload.borrow_mut().delete(point_id, key)
+        self.filter_cache.invalidate();
+        self.payload.write().delete(point_id, key)
     }
 
     fn drop(&mut self, point_id: PointOffsetType) -> OperationResult<Option<Payload>> {
@@ -483,11 +614,30 @@ impl PayloadIndex for StructPayloadIndex {
                 index.remove_point(point_id)?;
             }
         }
-        self.payload.borrow_mut().drop(point_id)
+        self.filter_cache.invalidate();
+        self.payload.write().drop(point_id)
+    }
+
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()> {
+        // Only the field indexes need the values, the storage moves the payload as it is
+        if !self.field_indexes.is_empty() {
+            let payload = self.payload.read().payload(from)?;
+            for (field, field_indexes) in self.field_indexes.iter_mut() {
+                let field_value = &payload.get_value(field);
+                for index in field_indexes {
+                    index.remove_point(from)?;
+                    index.remove_point(to)?;
+                    index.add_point(to, field_value)?;
+                }
+            }
+        }
+        self.filter_cache.invalidate();
+        self.payload.write().move_entry(from, to)
     }
 
     fn wipe(&mut self) -> OperationResult<()> {
-        self.payload.borrow_mut().wipe()?;
+        self.filter_cache.invalidate();
+        self.payload.write().wipe()?;
         for (_, field_indexes) in self.field_indexes.iter_mut() {
             for index in field_indexes.drain(..) {
                 index.clear()?;
@@ -503,7 +653,7 @@ impl PayloadIndex for StructPayloadIndex {
                 flushers.push(index.flusher());
             }
         }
-        flushers.push(self.payload.borrow().flusher());
+        flushers.push(self.payload.read().flusher());
         Box::new(move || {
             for flusher in flushers {
                 flusher()?
@@ -517,7 +667,7 @@ impl PayloadIndex for StructPayloadIndex {
         key: PayloadKeyTypeRef,
     ) -> OperationResult<Option<PayloadSchemaType>> {
         let mut schema = None;
-        self.payload.borrow().iter(|_id, payload: &Payload| {
+        self.payload.read().iter(|_id, payload: &Payload| {
             let field_value = payload.get_value(key);
             match field_value {
                 MultiValue::Single(field_value) => schema = field_value.and_then(infer_value_type),
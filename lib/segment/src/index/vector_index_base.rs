@@ -1,15 +1,51 @@
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 use super::hnsw_index::graph_links::{GraphLinksMmap, GraphLinksRam};
 use super::hnsw_index::hnsw::HNSWIndex;
+use super::ivf_index::ivf::IvfIndex;
 use super::plain_payload_index::PlainIndex;
+use super::query_plan::QueryPlanExplain;
+use crate::common::search_deadline::SearchDeadline;
+use crate::common::search_telemetry::SearchCounters;
 use crate::data_types::vectors::VectorElementType;
 use crate::entry::entry_point::OperationResult;
 use crate::telemetry::VectorIndexSearchesTelemetry;
-use crate::types::{Filter, SearchParams};
+use crate::types::{Filter, PointOffsetType, SearchParams};
+use crate::vector_storage::batch_scorer::BatchScorerSS;
 use crate::vector_storage::ScoredPointOffset;
 
+/// Expected cost of a search, estimated without touching the vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchCostEstimate {
+    /// Expected number of points to score
+    pub scored_points: usize,
+    /// Expected number of steps of the index: expanded graph points or probed lists
+    pub index_hops: usize,
+    /// All points, matching the filter, are scored without the index
+    pub full_scan: bool,
+}
+
+impl SearchCostEstimate {
+    pub fn full_scan(scored_points: usize) -> Self {
+        SearchCostEstimate {
+            scored_points,
+            index_hops: 0,
+            full_scan: true,
+        }
+    }
+
+    /// Cost of doing both searches
+    pub fn combine(self, other: SearchCostEstimate) -> Self {
+        SearchCostEstimate {
+            scored_points: self.scored_points + other.scored_points,
+            index_hops: self.index_hops + other.index_hops,
+            full_scan: self.full_scan || other.full_scan,
+        }
+    }
+}
+
 /// Trait for vector searching
 pub trait VectorIndex {
     /// Return list of Ids with fitting
@@ -19,34 +55,132 @@ pub trait VectorIndex {
         filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
+    ) -> Vec<Vec<ScoredPointOffset>> {
+        let deadline = SearchDeadline::from_params(params);
+        self.search_with_deadline(
+            vectors,
+            filter,
+            top,
+            params,
+            &deadline,
+            &SearchCounters::disabled(),
+        )
+    }
+
+    /// [`VectorIndex::search`], which stops early once the deadline is reached
+    ///
+    /// Results, gathered before the deadline, are returned, check [`SearchDeadline::is_expired`] to tell them apart.
+    /// Scored points, index hops and times of the phases are added to `counters`, if they are enabled.
+    fn search_with_deadline(
+        &self,
+        vectors: &[&[VectorElementType]],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
     ) -> Vec<Vec<ScoredPointOffset>>;
 
+    /// Plan of [`VectorIndex::search`] of a single vector, made without scoring any points
+    fn explain_search(
+        &self,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> QueryPlanExplain;
+
+    /// Estimate the cost of [`VectorIndex::search`] of a single vector
+    ///
+    /// Uses only the cardinality estimation of the filter and the structure of the index.
+    fn estimate_search_cost(
+        &self,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> SearchCostEstimate;
+
     /// Force internal index rebuild.
     fn build_index(&mut self, stopped: &AtomicBool) -> OperationResult<()>;
 
     fn get_telemetry_data(&self) -> VectorIndexSearchesTelemetry;
 
     fn files(&self) -> Vec<PathBuf>;
+
+    /// Index the point, inserted or changed after the index was built
+    ///
+    /// Indexes, which are only rebuilt, ignore it.
+    fn update_vector(&mut self, id: PointOffsetType) -> OperationResult<()>;
+
+    /// Save changes, made by [`VectorIndex::update_vector`]
+    fn flush(&self) -> OperationResult<()>;
+
+    /// Register external scorer, exact scoring is offered to it before scoring on CPU
+    fn set_batch_scorer(&mut self, batch_scorer: Option<Arc<BatchScorerSS>>);
+
+    /// Limit the threads, searching the queries of a batch, see [`crate::common::parallel::resolve_search_threads`]
+    fn set_search_threads(&mut self, max_search_threads: Option<usize>);
 }
 
 pub enum VectorIndexEnum {
     Plain(PlainIndex),
     HnswRam(HNSWIndex<GraphLinksRam>),
     HnswMmap(HNSWIndex<GraphLinksMmap>),
+    Ivf(IvfIndex),
 }
 
 impl VectorIndex for VectorIndexEnum {
-    fn search(
+    fn search_with_deadline(
         &self,
         vectors: &[&[VectorElementType]],
         filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
+        deadline: &SearchDeadline,
+        counters: &SearchCounters,
     ) -> Vec<Vec<ScoredPointOffset>> {
         match self {
-            VectorIndexEnum::Plain(index) => index.search(vectors, filter, top, params),
-            VectorIndexEnum::HnswRam(index) => index.search(vectors, filter, top, params),
-            VectorIndexEnum::HnswMmap(index) => index.search(vectors, filter, top, params),
+            VectorIndexEnum::Plain(index) => {
+                index.search_with_deadline(vectors, filter, top, params, deadline, counters)
+            }
+            VectorIndexEnum::HnswRam(index) => {
+                index.search_with_deadline(vectors, filter, top, params, deadline, counters)
+            }
+            VectorIndexEnum::HnswMmap(index) => {
+                index.search_with_deadline(vectors, filter, top, params, deadline, counters)
+            }
+            VectorIndexEnum::Ivf(index) => {
+                index.search_with_deadline(vectors, filter, top, params, deadline, counters)
+            }
+        }
+    }
+
+    fn explain_search(
+        &self,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> QueryPlanExplain {
+        match self {
+            VectorIndexEnum::Plain(index) => index.explain_search(vector, filter, top, params),
+            VectorIndexEnum::HnswRam(index) => index.explain_search(vector, filter, top, params),
+            VectorIndexEnum::HnswMmap(index) => index.explain_search(vector, filter, top, params),
+            VectorIndexEnum::Ivf(index) => index.explain_search(vector, filter, top, params),
+        }
+    }
+
+    fn estimate_search_cost(
+        &self,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> SearchCostEstimate {
+        match self {
+            VectorIndexEnum::Plain(index) => index.estimate_search_cost(filter, top, params),
+            VectorIndexEnum::HnswRam(index) => index.estimate_search_cost(filter, top, params),
+            VectorIndexEnum::HnswMmap(index) => index.estimate_search_cost(filter, top, params),
+            VectorIndexEnum::Ivf(index) => index.estimate_search_cost(filter, top, params),
         }
     }
 
@@ -55,6 +189,7 @@ impl VectorIndex for VectorIndexEnum {
             VectorIndexEnum::Plain(index) => index.build_index(stopped),
             VectorIndexEnum::HnswRam(index) => index.build_index(stopped),
             VectorIndexEnum::HnswMmap(index) => index.build_index(stopped),
+            VectorIndexEnum::Ivf(index) => index.build_index(stopped),
         }
     }
 
@@ -63,6 +198,7 @@ impl VectorIndex for VectorIndexEnum {
             VectorIndexEnum::Plain(index) => index.get_telemetry_data(),
             VectorIndexEnum::HnswRam(index) => index.get_telemetry_data(),
             VectorIndexEnum::HnswMmap(index) => index.get_telemetry_data(),
+            VectorIndexEnum::Ivf(index) => index.get_telemetry_data(),
         }
     }
 
@@ -71,6 +207,43 @@ impl VectorIndex for VectorIndexEnum {
             VectorIndexEnum::Plain(index) => index.files(),
             VectorIndexEnum::HnswRam(index) => index.files(),
             VectorIndexEnum::HnswMmap(index) => index.files(),
+            VectorIndexEnum::Ivf(index) => index.files(),
+        }
+    }
+
+    fn update_vector(&mut self, id: PointOffsetType) -> OperationResult<()> {
+        match self {
+            VectorIndexEnum::Plain(index) => index.update_vector(id),
+            VectorIndexEnum::HnswRam(index) => index.update_vector(id),
+            VectorIndexEnum::HnswMmap(index) => index.update_vector(id),
+            VectorIndexEnum::Ivf(index) => index.update_vector(id),
+        }
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        match self {
+            VectorIndexEnum::Plain(index) => index.flush(),
+            VectorIndexEnum::HnswRam(index) => index.flush(),
+            VectorIndexEnum::HnswMmap(index) => index.flush(),
+            VectorIndexEnum::Ivf(index) => index.flush(),
+        }
+    }
+
+    fn set_batch_scorer(&mut self, batch_scorer: Option<Arc<BatchScorerSS>>) {
+        match self {
+            VectorIndexEnum::Plain(index) => index.set_batch_scorer(batch_scorer),
+            VectorIndexEnum::HnswRam(index) => index.set_batch_scorer(batch_scorer),
+            VectorIndexEnum::HnswMmap(index) => index.set_batch_scorer(batch_scorer),
+            VectorIndexEnum::Ivf(index) => index.set_batch_scorer(batch_scorer),
+        }
+    }
+
+    fn set_search_threads(&mut self, max_search_threads: Option<usize>) {
+        match self {
+            VectorIndexEnum::Plain(index) => index.set_search_threads(max_search_threads),
+            VectorIndexEnum::HnswRam(index) => index.set_search_threads(max_search_threads),
+            VectorIndexEnum::HnswMmap(index) => index.set_search_threads(max_search_threads),
+            VectorIndexEnum::Ivf(index) => index.set_search_threads(max_search_threads),
         }
     }
 }
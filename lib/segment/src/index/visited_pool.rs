@@ -10,14 +10,18 @@ use crate::types::PointOffsetType;
 /// Implemented in order to limit memory leak
 const POOL_KEEP_LIMIT: usize = 16;
 
+/// Epoch of the visited list, a single byte per point to keep the list small
+type Epoch = u8;
+
 /// Visited list reuses same memory to keep track of visited points ids among multiple consequent queries
 ///
 /// It stores the sequence number of last processed operation next to the point ID, which allows to avoid memory allocation
 /// and re-use same counter for multiple queries.
+/// Once the sequence number overflows, the counters are zeroed, so marks of the old queries are never observed.
 #[derive(Debug)]
 pub struct VisitedList {
-    current_iter: usize,
-    visit_counters: Vec<usize>,
+    current_iter: Epoch,
+    visit_counters: Vec<Epoch>,
 }
 
 impl VisitedList {
@@ -32,7 +36,7 @@ impl VisitedList {
     pub fn check(&self, point_id: PointOffsetType) -> bool {
         self.visit_counters
             .get(point_id as usize)
-            .map_or(false, |x| *x >= self.current_iter)
+            .map_or(false, |x| *x == self.current_iter)
     }
 
     /// Updates visited list
@@ -44,11 +48,23 @@ impl VisitedList {
         }
         let prev_value = self.visit_counters[idx];
         self.visit_counters[idx] = self.current_iter;
-        prev_value >= self.current_iter
+        prev_value == self.current_iter
     }
 
     pub fn next_iteration(&mut self) {
-        self.current_iter += 1;
+        self.current_iter = self.current_iter.wrapping_add(1);
+        if self.current_iter == 0 {
+            // Counters of the previous queries could match the new sequence numbers
+            self.visit_counters.fill(0);
+            self.current_iter = 1;
+        }
+    }
+
+    /// Make sure there is a counter for each of `num_points` points
+    fn grow(&mut self, num_points: usize) {
+        if self.visit_counters.len() < num_points {
+            self.visit_counters.resize(num_points, 0);
+        }
     }
 }
 
@@ -67,11 +83,17 @@ impl VisitedPool {
         }
     }
 
+    /// Visited list for `num_points` points, with no points visited
+    ///
+    /// Pooled lists are reused by starting a new iteration, and grown if the number of points grew.
     pub fn get(&self, num_points: usize) -> VisitedList {
-        match self.pool.write().pop() {
+        // Lock is released before the list is touched
+        let pooled = self.pool.write().pop();
+        match pooled {
             None => VisitedList::new(num_points),
             Some(mut vl) => {
                 vl.next_iteration();
+                vl.grow(num_points);
                 vl
             }
         }
@@ -90,3 +112,76 @@ impl Default for VisitedPool {
         VisitedPool::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+
+    use super::*;
+
+    #[test]
+    fn test_epoch_overflow() {
+        let mut visited_list = VisitedList::new(10);
+        assert!(!visited_list.check_and_update_visited(3));
+        assert!(visited_list.check(3));
+
+        // Point is marked only in the very first iteration, then overflow brings the same epoch back
+        for _ in 0..=Epoch::MAX as usize * 2 + 1 {
+            visited_list.next_iteration();
+            assert!(!visited_list.check(3));
+            assert!(!visited_list.check(5));
+        }
+        assert!(!visited_list.check_and_update_visited(5));
+        assert!(visited_list.check_and_update_visited(5));
+        assert!(!visited_list.check(3));
+    }
+
+    #[test]
+    fn test_pooled_list_grows() {
+        let pool = VisitedPool::new();
+        let mut visited_list = pool.get(10);
+        visited_list.check_and_update_visited(7);
+        pool.return_back(visited_list);
+
+        let visited_list = pool.get(1000);
+        assert_eq!(visited_list.visit_counters.len(), 1000);
+        assert!((0..1000).all(|point_id| !visited_list.check(point_id)));
+    }
+
+    #[test]
+    fn test_concurrent_searches_are_isolated() {
+        let num_threads = 8;
+        let num_points = 1000;
+        let pool = VisitedPool::new();
+        let barrier = Barrier::new(num_threads);
+
+        std::thread::scope(|scope| {
+            for thread_id in 0..num_threads {
+                let pool = &pool;
+                let barrier = &barrier;
+                scope.spawn(move || {
+                    // Enough searches to overflow the epoch of each list
+                    for search in 0..(Epoch::MAX as usize + 10) {
+                        let mut visited_list = pool.get(num_points);
+                        let own_points = (0..num_points as PointOffsetType)
+                            .filter(|point_id| *point_id as usize % num_threads == thread_id);
+                        for point_id in own_points.clone() {
+                            assert!(
+                                !visited_list.check_and_update_visited(point_id),
+                                "thread {thread_id} observed a mark of another search {search}",
+                            );
+                        }
+                        assert!((0..num_points as PointOffsetType)
+                            .filter(|point_id| *point_id as usize % num_threads != thread_id)
+                            .all(|point_id| !visited_list.check(point_id)));
+                        if search == 0 {
+                            // All threads hold a list at once at least once
+                            barrier.wait();
+                        }
+                        pool.return_back(visited_list);
+                    }
+                });
+            }
+        });
+    }
+}
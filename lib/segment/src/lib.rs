@@ -1,15 +1,25 @@
 #![allow(deprecated)]
 
+#[cfg(feature = "async")]
+pub mod async_segment;
 pub mod common;
 pub mod entry;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod fixtures;
 pub mod id_tracker;
 pub mod index;
 pub mod madvise;
 pub mod payload_storage;
+#[cfg(feature = "python")]
+pub mod python;
 pub mod rocksdb_backup;
+pub mod scored_points_iterator;
 pub mod segment;
 pub mod segment_constructor;
+pub mod segment_wal;
+pub mod snapshot_archive;
+pub mod snapshot_delta;
 pub mod spaces;
 pub mod telemetry;
 
@@ -1,10 +1,12 @@
 //! Contains functions for interpreting filter queries and defining if given points pass the conditions
 
+use std::collections::HashSet;
+
 use serde_json::Value;
 
 use crate::types::{
-    AnyVariants, GeoBoundingBox, GeoRadius, Match, MatchAny, MatchText, MatchValue, Range,
-    ValueVariants, ValuesCount,
+    AnyVariants, GeoBoundingBox, GeoPolygon, GeoRadius, IntPayloadType, Match, MatchAny,
+    MatchExcept, MatchInsensitive, MatchText, MatchValue, Range, ValueVariants, ValuesCount,
 };
 
 pub trait ValueChecker {
@@ -41,6 +43,60 @@ impl ValueChecker for Match {
                     .unwrap_or(false),
                 _ => false,
             },
+            Match::Except(MatchExcept { except }) => match (payload, except) {
+                (Value::String(stored), AnyVariants::Keywords(list)) => !list.contains(stored),
+                (Value::Number(stored), AnyVariants::Integers(list)) => stored
+                    .as_i64()
+                    .map(|num| !list.contains(&num))
+                    .unwrap_or(true),
+                // Values of other types can't be equal to any of the listed values
+                _ => true,
+            },
+            Match::Insensitive(MatchInsensitive { insensitive }) => match payload {
+                Value::String(stored) => stored.to_lowercase() == insensitive.to_lowercase(),
+                _ => false,
+            },
+        }
+    }
+
+    fn check(&self, payload: &Value) -> bool {
+        match (self, payload) {
+            // None of the values should be excepted, unlike other matches
+            (Match::Except(_), Value::Array(values)) => values.iter().all(|x| self.check_match(x)),
+            (_, Value::Array(values)) => values.iter().any(|x| self.check_match(x)),
+            _ => self.check_match(payload),
+        }
+    }
+}
+
+/// Hashed values of [`MatchAny`]
+///
+/// Checks a value in constant time regardless of the list size,
+/// should be built once per condition and reused for all the points,
+/// see [`crate::payload_storage::query_checker::MatchAnySets`].
+pub enum MatchAnySet {
+    Keywords(HashSet<String>),
+    Integers(HashSet<IntPayloadType>),
+}
+
+impl From<&AnyVariants> for MatchAnySet {
+    fn from(any: &AnyVariants) -> Self {
+        match any {
+            AnyVariants::Keywords(list) => MatchAnySet::Keywords(list.iter().cloned().collect()),
+            AnyVariants::Integers(list) => MatchAnySet::Integers(list.iter().copied().collect()),
+        }
+    }
+}
+
+impl ValueChecker for MatchAnySet {
+    fn check_match(&self, payload: &Value) -> bool {
+        match (payload, self) {
+            (Value::String(stored), MatchAnySet::Keywords(set)) => set.contains(stored),
+            (Value::Number(stored), MatchAnySet::Integers(set)) => stored
+                .as_i64()
+                .map(|num| set.contains(&num))
+                .unwrap_or(false),
+            _ => false,
         }
     }
 }
@@ -91,6 +147,23 @@ impl ValueChecker for GeoRadius {
     }
 }
 
+impl ValueChecker for GeoPolygon {
+    fn check_match(&self, payload: &Value) -> bool {
+        match payload {
+            Value::Object(obj) => {
+                let lon_op = obj.get("lon").and_then(|x| x.as_f64());
+                let lat_op = obj.get("lat").and_then(|x| x.as_f64());
+
+                if let (Some(lon), Some(lat)) = (lon_op, lat_op) {
+                    return self.check_point(lon, lat);
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
 impl ValueChecker for ValuesCount {
     fn check_match(&self, payload: &Value) -> bool {
         self.check_count(payload)
@@ -140,6 +213,86 @@ mod tests {
         assert!(!miss_geo_query.check(&berlin_and_moscow));
     }
 
+    #[test]
+    fn test_match_any_set() {
+        let keywords: Vec<String> = (0..5000).map(|i| format!("keyword_{i}")).collect();
+        let integers: Vec<IntPayloadType> = (0..5000).map(|i| i * 2).collect();
+
+        let match_keywords = Match::Any(MatchAny {
+            any: AnyVariants::Keywords(keywords.clone()),
+        });
+        let match_integers = Match::Any(MatchAny {
+            any: AnyVariants::Integers(integers.clone()),
+        });
+        let keywords_set = MatchAnySet::from(&AnyVariants::Keywords(keywords));
+        let integers_set = MatchAnySet::from(&AnyVariants::Integers(integers));
+
+        let values = [
+            json!("keyword_4999"),
+            json!("keyword_5000"),
+            json!(["other", "keyword_0"]),
+            json!(9998),
+            json!(9999),
+            json!([1, 3, 42]),
+            json!(42.5),
+            json!(null),
+        ];
+
+        // Hashed values must behave exactly as the list
+        for value in &values {
+            assert_eq!(keywords_set.check(value), match_keywords.check(value));
+            assert_eq!(integers_set.check(value), match_integers.check(value));
+        }
+        assert!(keywords_set.check(&values[0]));
+        assert!(!keywords_set.check(&values[1]));
+        assert!(keywords_set.check(&values[2]));
+        assert!(integers_set.check(&values[3]));
+        assert!(!integers_set.check(&values[4]));
+        assert!(integers_set.check(&values[5]));
+        assert!(!integers_set.check(&values[6]));
+    }
+
+    #[test]
+    fn test_match_except() {
+        let except_keywords = Match::Except(MatchExcept {
+            except: AnyVariants::Keywords(vec!["red".to_string(), "blue".to_string()]),
+        });
+        assert!(except_keywords.check(&json!("green")));
+        assert!(!except_keywords.check(&json!("red")));
+        // One of the values is excepted
+        assert!(!except_keywords.check(&json!(["green", "blue"])));
+        assert!(except_keywords.check(&json!(["green", "yellow"])));
+        assert!(except_keywords.check(&json!([])));
+        assert!(except_keywords.check(&json!(42)));
+
+        let except_integers = Match::Except(MatchExcept {
+            except: AnyVariants::Integers(vec![1, 2]),
+        });
+        assert!(except_integers.check(&json!(3)));
+        assert!(!except_integers.check(&json!(2)));
+        assert!(!except_integers.check(&json!([3, 1])));
+        assert!(except_integers.check(&json!([3, 4])));
+        assert!(except_integers.check(&json!(1.5)));
+    }
+
+    #[test]
+    fn test_match_insensitive() {
+        let insensitive = Match::Insensitive(MatchInsensitive {
+            insensitive: "Äpfel".to_string(),
+        });
+        assert!(insensitive.check(&json!("äpfel")));
+        assert!(insensitive.check(&json!("ÄPFEL")));
+        assert!(insensitive.check(&json!(["Birnen", "äPfEl"])));
+        assert!(!insensitive.check(&json!("apfel")));
+        assert!(!insensitive.check(&json!("Äpfel und Birnen")));
+        assert!(!insensitive.check(&json!(42)));
+
+        // Exact match is still case-sensitive
+        let exact: Match = "Äpfel".to_string().into();
+        assert!(exact.check(&json!("Äpfel")));
+        assert!(!exact.check(&json!("äpfel")));
+    }
+
     #[test]
     fn test_value_count() {
         let countries = json!([
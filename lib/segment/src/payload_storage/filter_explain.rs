@@ -0,0 +1,271 @@
+use std::ops::Deref;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::id_tracker::IdTrackerSS;
+use crate::payload_storage::query_checker::{check_filter, check_point_condition};
+use crate::types::{Condition, Filter, OwnedPayloadRef, PointOffsetType};
+
+/// Evaluation of the filter against a single point, mirrors the structure of the filter
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct FilterExplain {
+    /// Point satisfies the whole filter
+    pub matched: bool,
+    pub should: Option<Vec<ConditionExplain>>,
+    pub must: Option<Vec<ConditionExplain>>,
+    pub must_not: Option<Vec<ConditionExplain>>,
+}
+
+/// Evaluation of a single condition against a point
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ConditionExplain {
+    /// Point satisfies the condition, not taking into account the clause it belongs to
+    pub matched: bool,
+    /// Checked condition, `None` for nested filters
+    pub condition: Option<Condition>,
+    /// Values of the point, which were compared with the condition
+    pub values: Vec<Value>,
+    /// Evaluation of the nested filter
+    pub filter: Option<FilterExplain>,
+}
+
+/// Number of points, matched by each part of the filter
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct FilterMatchCounts {
+    /// Number of points, which satisfy the whole filter
+    pub matched: usize,
+    pub should: Option<Vec<ConditionMatchCounts>>,
+    pub must: Option<Vec<ConditionMatchCounts>>,
+    pub must_not: Option<Vec<ConditionMatchCounts>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ConditionMatchCounts {
+    /// Number of points, which satisfy the condition
+    pub matched: usize,
+    /// Checked condition, `None` for nested filters
+    pub condition: Option<Condition>,
+    /// Counts of the nested filter
+    pub filter: Option<FilterMatchCounts>,
+}
+
+impl FilterMatchCounts {
+    /// Zero counts for the given filter
+    pub fn new(filter: &Filter) -> Self {
+        let empty_clause = |conditions: &Option<Vec<Condition>>| {
+            conditions.as_ref().map(|conditions| {
+                conditions
+                    .iter()
+                    .map(|condition| match condition {
+                        Condition::Filter(nested) => ConditionMatchCounts {
+                            matched: 0,
+                            condition: None,
+                            filter: Some(FilterMatchCounts::new(nested)),
+                        },
+                        _ => ConditionMatchCounts {
+                            matched: 0,
+                            condition: Some(condition.clone()),
+                            filter: None,
+                        },
+                    })
+                    .collect()
+            })
+        };
+
+        Self {
+            matched: 0,
+            should: empty_clause(&filter.should),
+            must: empty_clause(&filter.must),
+            must_not: empty_clause(&filter.must_not),
+        }
+    }
+
+    /// Account evaluation of the same filter for one more point
+    pub fn add(&mut self, explain: &FilterExplain) {
+        fn add_clause(
+            counts: &mut Option<Vec<ConditionMatchCounts>>,
+            explains: &Option<Vec<ConditionExplain>>,
+        ) {
+            let (counts, explains) = match (counts, explains) {
+                (Some(counts), Some(explains)) => (counts, explains),
+                _ => return,
+            };
+            for (counts, explain) in counts.iter_mut().zip(explains) {
+                counts.matched += usize::from(explain.matched);
+                if let (Some(counts), Some(explain)) = (&mut counts.filter, &explain.filter) {
+                    counts.add(explain);
+                }
+            }
+        }
+
+        self.matched += usize::from(explain.matched);
+        add_clause(&mut self.should, &explain.should);
+        add_clause(&mut self.must, &explain.must);
+        add_clause(&mut self.must_not, &explain.must_not);
+    }
+}
+
+/// Explain the filter using `checker` for non-nested conditions
+///
+/// `checker` and the way of combining conditions are the same as in [`check_filter`],
+/// so the explanation always agrees with the actual evaluation.
+pub fn explain_filter<F, V>(checker: &F, values: &V, filter: &Filter) -> FilterExplain
+where
+    F: Fn(&Condition) -> bool,
+    V: Fn(&Condition) -> Vec<Value>,
+{
+    let explain_clause = |conditions: &Option<Vec<Condition>>| {
+        conditions.as_ref().map(|conditions| {
+            conditions
+                .iter()
+                .map(|condition| match condition {
+                    Condition::Filter(nested) => {
+                        let explain = explain_filter(checker, values, nested);
+                        ConditionExplain {
+                            matched: explain.matched,
+                            condition: None,
+                            values: vec![],
+                            filter: Some(explain),
+                        }
+                    }
+                    _ => ConditionExplain {
+                        matched: checker(condition),
+                        condition: Some(condition.clone()),
+                        values: values(condition),
+                        filter: None,
+                    },
+                })
+                .collect()
+        })
+    };
+
+    FilterExplain {
+        matched: check_filter(checker, filter),
+        should: explain_clause(&filter.should),
+        must: explain_clause(&filter.must),
+        must_not: explain_clause(&filter.must_not),
+    }
+}
+
+/// Explain the filter against the payload of the point
+pub fn explain_payload<'a, F>(
+    get_payload: F,
+    id_tracker: &IdTrackerSS,
+    query: &Filter,
+    point_id: PointOffsetType,
+) -> FilterExplain
+where
+    F: Fn() -> OwnedPayloadRef<'a>,
+{
+    let checker = |condition: &Condition| {
+        check_point_condition(&get_payload, id_tracker, condition, point_id)
+    };
+
+    let values = |condition: &Condition| -> Vec<Value> {
+        let key = match condition {
+            Condition::Field(field_condition) => &field_condition.key,
+            Condition::IsEmpty(is_empty) => &is_empty.is_empty.key,
+            Condition::IsNull(is_null) => &is_null.is_null.key,
+            Condition::Nested(nested) => &nested.nested.key,
+            Condition::HasId(_) => {
+                return id_tracker
+                    .external_id(point_id)
+                    .and_then(|id| serde_json::to_value(id).ok())
+                    .into_iter()
+                    .collect();
+            }
+            Condition::HasVector(_) | Condition::Filter(_) => return vec![],
+        };
+        let payload = get_payload();
+        let values = payload.deref().get_value(key);
+        values.into_iter().cloned().collect()
+    };
+
+    explain_filter(&checker, &values, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::payload_storage::query_checker::check_field_condition;
+    use crate::types::{FieldCondition, Payload};
+
+    #[test]
+    fn test_explain_filter() {
+        let payload: Payload = json!({
+            "city": "Berlin",
+            "price": [10, 20],
+        })
+        .into();
+
+        let filter = Filter {
+            should: None,
+            must: Some(vec![
+                Condition::Field(FieldCondition::new_match(
+                    "city".to_string(),
+                    "Berlin".to_string().into(),
+                )),
+                Condition::Filter(Filter::new_must_not(Condition::Field(
+                    FieldCondition::new_match("price".to_string(), 20.into()),
+                ))),
+            ]),
+            must_not: None,
+        };
+
+        let checker = |condition: &Condition| match condition {
+            Condition::Field(field_condition) => check_field_condition(field_condition, &payload),
+            _ => unreachable!(),
+        };
+        let values = |condition: &Condition| match condition {
+            Condition::Field(field_condition) => payload
+                .get_value(&field_condition.key)
+                .into_iter()
+                .cloned()
+                .collect(),
+            _ => unreachable!(),
+        };
+
+        let explain = explain_filter(&checker, &values, &filter);
+        assert!(!explain.matched);
+        assert_eq!(explain.matched, check_filter(&checker, &filter));
+
+        let must = explain.must.as_ref().unwrap();
+        assert!(must[0].matched);
+        assert_eq!(must[0].values, vec![json!("Berlin")]);
+
+        let nested = must[1].filter.as_ref().unwrap();
+        assert!(!nested.matched);
+        let nested_must_not = nested.must_not.as_ref().unwrap();
+        // Condition itself matches, so `must_not` fails
+        assert!(nested_must_not[0].matched);
+        assert_eq!(nested_must_not[0].values.len(), 2);
+        assert!(nested_must_not[0].values.contains(&json!(20)));
+
+        let mut counts = FilterMatchCounts::new(&filter);
+        counts.add(&explain);
+        counts.add(&explain);
+        assert_eq!(counts.matched, 0);
+        let must_counts = counts.must.as_ref().unwrap();
+        assert_eq!(must_counts[0].matched, 2);
+        assert_eq!(must_counts[1].matched, 0);
+        assert_eq!(
+            must_counts[1]
+                .filter
+                .as_ref()
+                .unwrap()
+                .must_not
+                .as_ref()
+                .unwrap()[0]
+                .matched,
+            2
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::entry::entry_point::OperationResult;
 use crate::types::{Payload, PointOffsetType};
@@ -7,12 +8,13 @@ use crate::types::{Payload, PointOffsetType};
 /// Warn: for tests only
 #[derive(Default)]
 pub struct InMemoryPayloadStorage {
-    pub(crate) payload: HashMap<PointOffsetType, Payload>,
+    /// Payloads are shared with the readers, and copied on write, if still referenced
+    pub(crate) payload: HashMap<PointOffsetType, Arc<Payload>>,
 }
 
 impl InMemoryPayloadStorage {
     pub fn payload_ptr(&self, point_id: PointOffsetType) -> Option<&Payload> {
-        self.payload.get(&point_id)
+        self.payload.get(&point_id).map(Arc::as_ref)
     }
 
     pub fn iter<F>(&self, mut callback: F) -> OperationResult<()>
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use serde_json::Value;
 
@@ -11,17 +12,17 @@ use crate::types::{Payload, PayloadKeyTypeRef, PointOffsetType};
 impl PayloadStorage for InMemoryPayloadStorage {
     fn assign(&mut self, point_id: PointOffsetType, payload: &Payload) -> OperationResult<()> {
         match self.payload.get_mut(&point_id) {
-            Some(point_payload) => point_payload.merge(payload),
+            Some(point_payload) => Arc::make_mut(point_payload).merge(payload),
             None => {
-                self.payload.insert(point_id, payload.to_owned());
+                self.payload.insert(point_id, Arc::new(payload.to_owned()));
             }
         }
         Ok(())
     }
 
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload> {
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>> {
         match self.payload.get(&point_id) {
-            Some(payload) => Ok(payload.to_owned()),
+            Some(payload) => Ok(payload.clone()),
             None => Ok(Default::default()),
         }
     }
@@ -33,7 +34,7 @@ impl PayloadStorage for InMemoryPayloadStorage {
     ) -> OperationResult<Vec<Value>> {
         match self.payload.get_mut(&point_id) {
             Some(payload) => {
-                let res = payload.remove(key);
+                let res = Arc::make_mut(payload).remove(key);
                 Ok(res)
             }
             None => Ok(vec![]),
@@ -41,10 +42,18 @@ impl PayloadStorage for InMemoryPayloadStorage {
     }
 
     fn drop(&mut self, point_id: PointOffsetType) -> OperationResult<Option<Payload>> {
-        let res = self.payload.remove(&point_id);
+        let res = self.payload.remove(&point_id).map(Payload::from_shared);
         Ok(res)
     }
 
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()> {
+        match self.payload.remove(&from) {
+            Some(payload) => self.payload.insert(to, payload),
+            None => self.payload.remove(&to),
+        };
+        Ok(())
+    }
+
     fn wipe(&mut self) -> OperationResult<()> {
         self.payload = HashMap::new();
         Ok(())
@@ -157,6 +166,32 @@ mod tests {
         let mut storage = InMemoryPayloadStorage::default();
         storage.assign(100, &payload).unwrap();
         let pload = storage.payload(100).unwrap();
-        assert_eq!(pload, payload);
+        assert_eq!(*pload, payload);
+    }
+
+    #[test]
+    fn test_shared_payload_reads() {
+        let mut storage = InMemoryPayloadStorage::default();
+        let payload: Payload = serde_json::from_str(r#"{"name": "John Doe"}"#).unwrap();
+        storage.assign(100, &payload).unwrap();
+
+        // Reads share the stored payload
+        let read = storage.payload(100).unwrap();
+        assert!(Arc::ptr_eq(&read, &storage.payload(100).unwrap()));
+
+        // Writes copy the payload, which is still held by the readers
+        let update: Payload = serde_json::from_str(r#"{"age": 43}"#).unwrap();
+        storage.assign(100, &update).unwrap();
+        assert_eq!(*read, payload);
+        let updated = storage.payload(100).unwrap();
+        assert!(!Arc::ptr_eq(&read, &updated));
+        assert!(updated.contains_key("age"));
+
+        // Without the readers, the payload is updated in place
+        drop(read);
+        let ptr = Arc::as_ptr(&updated);
+        drop(updated);
+        storage.delete(100, "age").unwrap();
+        assert_eq!(Arc::as_ptr(&storage.payload(100).unwrap()), ptr);
     }
 }
@@ -1,4 +1,5 @@
 pub mod condition_checker;
+pub mod filter_explain;
 pub mod in_memory_payload_storage;
 pub mod in_memory_payload_storage_impl;
 pub mod on_disk_payload_storage;
@@ -1,9 +1,13 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use parking_lot::RwLock;
 use rocksdb::DB;
+use serde::de::DeserializeSeed;
 use serde_json::Value;
 
+use crate::common::encryption::SegmentCipher;
+use crate::common::payload_selection::PayloadSelection;
 use crate::common::rocksdb_wrapper::{DatabaseColumnWrapper, DB_PAYLOAD_CF};
 use crate::common::Flusher;
 use crate::entry::entry_point::{OperationError, OperationResult};
@@ -14,12 +18,20 @@ use crate::types::{Payload, PayloadKeyTypeRef, PointOffsetType};
 /// Persists all changes to disk using `store`, does not keep payload in memory
 pub struct OnDiskPayloadStorage {
     db_wrapper: DatabaseColumnWrapper,
+    /// Number of the payloads, serialized to be persisted
+    serialized_payloads: AtomicUsize,
 }
 
 impl OnDiskPayloadStorage {
-    pub fn open(database: Arc<RwLock<DB>>) -> OperationResult<Self> {
-        let db_wrapper = DatabaseColumnWrapper::new(database, DB_PAYLOAD_CF);
-        Ok(OnDiskPayloadStorage { db_wrapper })
+    pub fn open(
+        database: Arc<RwLock<DB>>,
+        cipher: Option<Arc<SegmentCipher>>,
+    ) -> OperationResult<Self> {
+        let db_wrapper = DatabaseColumnWrapper::new(database, DB_PAYLOAD_CF).with_cipher(cipher);
+        Ok(OnDiskPayloadStorage {
+            db_wrapper,
+            serialized_payloads: AtomicUsize::new(0),
+        })
     }
 
     pub fn remove_from_storage(&self, point_id: PointOffsetType) -> OperationResult<()> {
@@ -32,12 +44,17 @@ impl OnDiskPayloadStorage {
         point_id: PointOffsetType,
         payload: &Payload,
     ) -> OperationResult<()> {
+        self.serialized_payloads.fetch_add(1, Ordering::Relaxed);
         self.db_wrapper.put(
             serde_cbor::to_vec(&point_id).unwrap(),
             serde_cbor::to_vec(payload).unwrap(),
         )
     }
 
+    pub fn serialized_payloads(&self) -> usize {
+        self.serialized_payloads.load(Ordering::Relaxed)
+    }
+
     pub fn read_payload(&self, point_id: PointOffsetType) -> OperationResult<Option<Payload>> {
         let key = serde_cbor::to_vec(&point_id).unwrap();
         self.db_wrapper
@@ -51,6 +68,7 @@ impl OnDiskPayloadStorage {
         F: FnMut(PointOffsetType, &Payload) -> OperationResult<bool>,
     {
         for (key, val) in self.db_wrapper.lock_db().iter()? {
+            let val = self.db_wrapper.decrypt_value(&key, &val)?;
             let do_continue = callback(
                 serde_cbor::from_slice(&key)?,
                 &serde_cbor::from_slice(&val)?,
@@ -80,14 +98,29 @@ impl PayloadStorage for OnDiskPayloadStorage {
         Ok(())
     }
 
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload> {
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>> {
         let payload = self.read_payload(point_id)?;
         match payload {
-            Some(payload) => Ok(payload),
+            Some(payload) => Ok(Arc::new(payload)),
             None => Ok(Default::default()),
         }
     }
 
+    fn selected_payload(
+        &self,
+        point_id: PointOffsetType,
+        selection: &PayloadSelection,
+    ) -> OperationResult<Payload> {
+        let key = serde_cbor::to_vec(&point_id).unwrap();
+        let payload = self
+            .db_wrapper
+            .get_pinned(&key, |raw| {
+                selection.deserialize(&mut serde_cbor::Deserializer::from_slice(raw))
+            })?
+            .transpose()?;
+        Ok(payload.unwrap_or_default())
+    }
+
     fn delete(
         &mut self,
         point_id: PointOffsetType,
@@ -113,6 +146,13 @@ impl PayloadStorage for OnDiskPayloadStorage {
         Ok(payload)
     }
 
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()> {
+        self.db_wrapper.rename(
+            &serde_cbor::to_vec(&from).unwrap(),
+            &serde_cbor::to_vec(&to).unwrap(),
+        )
+    }
+
     fn wipe(&mut self) -> OperationResult<()> {
         self.db_wrapper.recreate_column_family()
     }
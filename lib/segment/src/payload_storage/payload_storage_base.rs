@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use serde_json::Value;
 
+use crate::common::payload_selection::PayloadSelection;
 use crate::common::Flusher;
 use crate::entry::entry_point::OperationResult;
 use crate::types::{Filter, Payload, PayloadKeyTypeRef, PointOffsetType};
@@ -17,7 +20,20 @@ pub trait PayloadStorage {
     fn assign(&mut self, point_id: PointOffsetType, payload: &Payload) -> OperationResult<()>;
 
     /// Get payload for point
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload>;
+    ///
+    /// Payload may be shared with the storage, so the read doesn't copy it.
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>>;
+
+    /// Get the selected fields of the payload for point
+    ///
+    /// Storages, which deserialize the payload on read, skip the fields, which are not selected.
+    fn selected_payload(
+        &self,
+        point_id: PointOffsetType,
+        selection: &PayloadSelection,
+    ) -> OperationResult<Payload> {
+        Ok(selection.select(&self.payload(point_id)?))
+    }
 
     /// Delete payload by key
     fn delete(
@@ -29,6 +45,11 @@ pub trait PayloadStorage {
     /// Drop all payload of the point
     fn drop(&mut self, point_id: PointOffsetType) -> OperationResult<Option<Payload>>;
 
+    /// Move all payload of the point `from` to the point `to`, replacing the payload of `to`
+    ///
+    /// Payload is relabeled without being deserialized or serialized again.
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()>;
+
     /// Completely drop payload. Pufff!
     fn wipe(&mut self) -> OperationResult<()>;
 
@@ -36,11 +57,16 @@ pub trait PayloadStorage {
     fn flusher(&self) -> Flusher;
 }
 
+/// Evaluates arbitrary filters against the stored payload and point ids
 pub trait ConditionChecker {
     /// Check if point satisfies filter condition. Return true if satisfies
     fn check(&self, point_id: PointOffsetType, query: &Filter) -> bool;
 }
 
+/// Filter, prepared for checking points one by one, e.g. during graph traversal
+///
+/// Created once per query, so per-query state like resolved ids of `has_id` conditions
+/// or checkers, built over payload indexes, are computed only once.
 pub trait FilterContext {
     /// Check if point satisfies filter condition. Return true if satisfies
     fn check(&self, point_id: PointOffsetType) -> bool;
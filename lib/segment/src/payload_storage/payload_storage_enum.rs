@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use serde_json::Value;
 
+use crate::common::payload_selection::PayloadSelection;
 use crate::common::Flusher;
 use crate::entry::entry_point::OperationResult;
 use crate::payload_storage::in_memory_payload_storage::InMemoryPayloadStorage;
@@ -43,6 +46,15 @@ impl PayloadStorageEnum {
             PayloadStorageEnum::OnDiskPayloadStorage(s) => s.iter(callback),
         }
     }
+
+    /// Number of the payloads, serialized to be persisted
+    pub fn serialized_payloads(&self) -> usize {
+        match self {
+            PayloadStorageEnum::InMemoryPayloadStorage(_) => 0,
+            PayloadStorageEnum::SimplePayloadStorage(s) => s.serialized_payloads(),
+            PayloadStorageEnum::OnDiskPayloadStorage(s) => s.serialized_payloads(),
+        }
+    }
 }
 
 impl PayloadStorage for PayloadStorageEnum {
@@ -54,7 +66,7 @@ impl PayloadStorage for PayloadStorageEnum {
         }
     }
 
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload> {
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>> {
         match self {
             PayloadStorageEnum::InMemoryPayloadStorage(s) => s.payload(point_id),
             PayloadStorageEnum::SimplePayloadStorage(s) => s.payload(point_id),
@@ -62,6 +74,20 @@ impl PayloadStorage for PayloadStorageEnum {
         }
     }
 
+    fn selected_payload(
+        &self,
+        point_id: PointOffsetType,
+        selection: &PayloadSelection,
+    ) -> OperationResult<Payload> {
+        match self {
+            PayloadStorageEnum::InMemoryPayloadStorage(s) => {
+                s.selected_payload(point_id, selection)
+            }
+            PayloadStorageEnum::SimplePayloadStorage(s) => s.selected_payload(point_id, selection),
+            PayloadStorageEnum::OnDiskPayloadStorage(s) => s.selected_payload(point_id, selection),
+        }
+    }
+
     fn delete(
         &mut self,
         point_id: PointOffsetType,
@@ -74,6 +100,14 @@ impl PayloadStorage for PayloadStorageEnum {
         }
     }
 
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()> {
+        match self {
+            PayloadStorageEnum::InMemoryPayloadStorage(s) => s.move_entry(from, to),
+            PayloadStorageEnum::SimplePayloadStorage(s) => s.move_entry(from, to),
+            PayloadStorageEnum::OnDiskPayloadStorage(s) => s.move_entry(from, to),
+        }
+    }
+
     fn drop(&mut self, point_id: PointOffsetType) -> OperationResult<Option<Payload>> {
         match self {
             PayloadStorageEnum::InMemoryPayloadStorage(s) => s.drop(point_id),
@@ -105,14 +139,14 @@ mod tests {
 
     use super::*;
     use crate::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
-    use crate::types::Payload;
+    use crate::types::{Payload, PayloadSelector, PayloadSelectorExclude};
 
     #[test]
     fn test_storage() {
         let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
         let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
 
-        let mut storage: PayloadStorageEnum = SimplePayloadStorage::open(db).unwrap().into();
+        let mut storage: PayloadStorageEnum = SimplePayloadStorage::open(db, None).unwrap().into();
         let payload: Payload = serde_json::from_str(r#"{"name": "John Doe"}"#).unwrap();
         storage.assign(100, &payload).unwrap();
         storage.wipe().unwrap();
@@ -131,7 +165,7 @@ mod tests {
 
         {
             let mut storage: PayloadStorageEnum =
-                SimplePayloadStorage::open(db.clone()).unwrap().into();
+                SimplePayloadStorage::open(db.clone(), None).unwrap().into();
             let payload: Payload = serde_json::from_str(
                 r#"{
                 "name": "John Doe",
@@ -162,7 +196,8 @@ mod tests {
         }
 
         {
-            let mut storage: PayloadStorageEnum = OnDiskPayloadStorage::open(db).unwrap().into();
+            let mut storage: PayloadStorageEnum =
+                OnDiskPayloadStorage::open(db, None).unwrap().into();
 
             let res = storage.payload(100).unwrap();
 
@@ -188,4 +223,96 @@ mod tests {
             eprintln!("res = {res:#?}");
         }
     }
+
+    #[test]
+    fn test_move_entry() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let payload: Payload = serde_json::from_str(r#"{"name": "John Doe"}"#).unwrap();
+        let other: Payload = serde_json::from_str(r#"{"name": "Jane Doe"}"#).unwrap();
+        let storages: Vec<PayloadStorageEnum> = vec![
+            SimplePayloadStorage::open(db.clone(), None).unwrap().into(),
+            OnDiskPayloadStorage::open(db.clone(), None).unwrap().into(),
+        ];
+
+        for mut storage in storages {
+            storage.wipe().unwrap();
+            storage.assign(1, &payload).unwrap();
+            storage.assign(2, &other).unwrap();
+            let serialized_payloads = storage.serialized_payloads();
+
+            storage.move_entry(1, 2).unwrap();
+            assert_eq!(*storage.payload(2).unwrap(), payload);
+            assert!(storage.payload(1).unwrap().is_empty());
+
+            // Moving the missing payload clears the target
+            storage.assign(3, &other).unwrap();
+            storage.move_entry(4, 3).unwrap();
+            assert!(storage.payload(3).unwrap().is_empty());
+
+            // Payload is moved without being serialized again
+            assert_eq!(storage.serialized_payloads(), serialized_payloads + 1);
+            storage.flusher()().unwrap();
+
+            // Moved payload is persisted
+            let restored: PayloadStorageEnum = match storage {
+                PayloadStorageEnum::SimplePayloadStorage(_) => {
+                    SimplePayloadStorage::open(db.clone(), None).unwrap().into()
+                }
+                _ => storage,
+            };
+            assert_eq!(*restored.payload(2).unwrap(), payload);
+            assert!(restored.payload(1).unwrap().is_empty());
+            assert!(restored.payload(3).unwrap().is_empty());
+        }
+
+        let mut storage: PayloadStorageEnum = InMemoryPayloadStorage::default().into();
+        storage.assign(1, &payload).unwrap();
+        storage.move_entry(1, 2).unwrap();
+        assert_eq!(*storage.payload(2).unwrap(), payload);
+        assert!(storage.payload(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_selected_payload() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        let payload: Payload = serde_json::from_str(
+            r#"{
+                "name": "John Doe",
+                "location": {"city": "Melbourne", "geo": {"lon": 144.9631, "lat": 37.8136}},
+                "pets": [{"name": "Rex", "kind": "dog"}, {"name": "Tom", "kind": "cat"}]
+            }"#,
+        )
+        .unwrap();
+        let selectors = [
+            PayloadSelector::new_include(vec!["name".to_string(), "location.geo.lat".to_string()]),
+            PayloadSelector::new_include(vec!["pets[].kind".to_string(), "age".to_string()]),
+            PayloadSelector::Exclude(PayloadSelectorExclude::new(vec![
+                "location.geo".to_string(),
+                "pets.name".to_string(),
+            ])),
+        ];
+        let storages: Vec<PayloadStorageEnum> = vec![
+            InMemoryPayloadStorage::default().into(),
+            SimplePayloadStorage::open(db.clone(), None).unwrap().into(),
+            OnDiskPayloadStorage::open(db, None).unwrap().into(),
+        ];
+
+        for mut storage in storages {
+            storage.wipe().unwrap();
+            storage.assign(1, &payload).unwrap();
+            for selector in &selectors {
+                // On-disk storage deserializes only the selected fields
+                let selection = PayloadSelection::new(selector);
+                assert_eq!(
+                    storage.selected_payload(1, &selection).unwrap(),
+                    selector.process(&payload)
+                );
+                assert!(storage.selected_payload(2, &selection).unwrap().is_empty());
+            }
+        }
+    }
 }
@@ -2,15 +2,17 @@ use std::cell::RefCell;
 use std::ops::Deref;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
 
+use crate::common::utils::{get_value_from_json_map, MultiValue};
 use crate::id_tracker::IdTrackerSS;
-use crate::payload_storage::condition_checker::ValueChecker;
+use crate::payload_storage::condition_checker::{MatchAnySet, ValueChecker};
 use crate::payload_storage::payload_storage_enum::PayloadStorageEnum;
 use crate::payload_storage::ConditionChecker;
 use crate::types::{
-    Condition, FieldCondition, Filter, IsEmptyCondition, IsNullCondition, OwnedPayloadRef, Payload,
-    PointOffsetType,
+    Condition, FieldCondition, Filter, IsEmptyCondition, IsNullCondition, Match, MatchAny,
+    NestedCondition, OwnedPayloadRef, Payload, PointOffsetType,
 };
 
 fn check_condition<F>(checker: &F, condition: &Condition) -> bool
@@ -39,6 +41,8 @@ where
     let check = |x| check_condition(checker, x);
     match should {
         None => true,
+        // Empty `should` clause is not a constraint
+        Some(conditions) if conditions.is_empty() => true,
         Some(conditions) => conditions.iter().any(check),
     }
 }
@@ -74,7 +78,24 @@ pub fn check_payload<'a, F>(
 where
     F: Fn() -> OwnedPayloadRef<'a>,
 {
-    let checker = |condition: &Condition| match condition {
+    let checker = |condition: &Condition| {
+        check_point_condition(&get_payload, id_tracker, condition, point_id)
+    };
+
+    check_filter(&checker, query)
+}
+
+/// Check single non-nested condition against the point
+pub fn check_point_condition<'a, F>(
+    get_payload: &F,
+    id_tracker: &IdTrackerSS,
+    condition: &Condition,
+    point_id: PointOffsetType,
+) -> bool
+where
+    F: Fn() -> OwnedPayloadRef<'a>,
+{
+    match condition {
         Condition::Field(field_condition) => {
             check_field_condition(field_condition, get_payload().deref())
         }
@@ -87,10 +108,139 @@ where
             };
             has_id.has_id.contains(&external_id)
         }
+        Condition::Nested(nested) => check_nested_condition(nested, get_payload().deref()),
+        // Resolved by the segment against its vector storages, see `Filter::resolve_has_vector`
+        Condition::HasVector(has_vector) => {
+            debug_assert!(
+                false,
+                "has_vector condition on {} is not resolved",
+                has_vector.has_vector
+            );
+            false
+        }
         Condition::Filter(_) => unreachable!(),
+    }
+}
+
+pub fn check_nested_condition(nested: &NestedCondition, payload: &Payload) -> bool {
+    MatchAnySets::default().check_nested_condition(nested, payload)
+}
+
+/// Hashed values of the `any` conditions, built once per query and shared by the checks of all the points
+///
+/// Conditions are found by their address, so the sets only apply to the conditions they are built from,
+/// others are checked against the list of their values.
+#[derive(Default)]
+pub struct MatchAnySets<'a> {
+    sets: Vec<(&'a FieldCondition, MatchAnySet)>,
+}
+
+impl<'a> MatchAnySets<'a> {
+    pub fn of_condition(field_condition: &'a FieldCondition) -> Self {
+        let mut sets = Self::default();
+        sets.add_condition(field_condition);
+        sets
+    }
+
+    /// Sets of all the conditions of the filter, including the ones of the nested filters
+    pub fn of_filter(filter: &'a Filter) -> Self {
+        let mut sets = Self::default();
+        sets.add_filter(filter);
+        sets
+    }
+
+    fn add_filter(&mut self, filter: &'a Filter) {
+        for condition in filter.iter_conditions() {
+            match condition {
+                Condition::Field(field_condition) => self.add_condition(field_condition),
+                Condition::Filter(filter) => self.add_filter(filter),
+                Condition::Nested(nested) => self.add_filter(&nested.nested.filter),
+                Condition::IsEmpty(_)
+                | Condition::IsNull(_)
+                | Condition::HasId(_)
+                | Condition::HasVector(_) => {}
+            }
+        }
+    }
+
+    fn add_condition(&mut self, field_condition: &'a FieldCondition) {
+        if let Some(Match::Any(MatchAny { any })) = &field_condition.r#match {
+            self.sets.push((field_condition, MatchAnySet::from(any)));
+        }
+    }
+
+    fn get(&self, field_condition: &FieldCondition) -> Option<&MatchAnySet> {
+        self.sets
+            .iter()
+            .find(|(condition, _)| std::ptr::eq(*condition, field_condition))
+            .map(|(_, set)| set)
+    }
+
+    pub fn check_field_condition(
+        &self,
+        field_condition: &FieldCondition,
+        payload: &Payload,
+    ) -> bool {
+        check_field_values(
+            field_condition,
+            payload.get_value(&field_condition.key),
+            self,
+        )
+    }
+
+    pub fn check_nested_condition(&self, nested: &NestedCondition, payload: &Payload) -> bool {
+        check_nested_values(
+            &nested.nested.filter,
+            payload.get_value(&nested.nested.key),
+            self,
+        )
+    }
+}
+
+/// Check if any object of the values satisfies the whole filter
+fn check_nested_values(
+    filter: &Filter,
+    values: MultiValue<&Value>,
+    any_sets: &MatchAnySets,
+) -> bool {
+    let check_object = |value: &Value| match value {
+        Value::Object(object) => check_nested_object(filter, object, any_sets),
+        _ => false,
     };
+    values.into_iter().any(|value| match value {
+        Value::Array(elements) => elements.iter().any(check_object),
+        value => check_object(value),
+    })
+}
 
-    check_filter(&checker, query)
+/// Check the filter against a single object, keys of the conditions are relative to the object
+fn check_nested_object(
+    filter: &Filter,
+    object: &Map<String, Value>,
+    any_sets: &MatchAnySets,
+) -> bool {
+    let checker = |condition: &Condition| match condition {
+        Condition::Field(field_condition) => check_field_values(
+            field_condition,
+            get_value_from_json_map(&field_condition.key, object),
+            any_sets,
+        ),
+        Condition::IsEmpty(is_empty) => {
+            get_value_from_json_map(&is_empty.is_empty.key, object).check_is_empty()
+        }
+        Condition::IsNull(is_null) => {
+            get_value_from_json_map(&is_null.is_null.key, object).check_is_null()
+        }
+        Condition::Nested(nested) => check_nested_values(
+            &nested.nested.filter,
+            get_value_from_json_map(&nested.nested.key, object),
+            any_sets,
+        ),
+        // Not supported inside of nested filters, rejected by the filter validation
+        Condition::HasId(_) | Condition::HasVector(_) => false,
+        Condition::Filter(_) => unreachable!(),
+    };
+    check_filter(&checker, filter)
 }
 
 pub fn check_is_empty_condition(is_empty: &IsEmptyCondition, payload: &Payload) -> bool {
@@ -102,24 +252,44 @@ pub fn check_is_null_condition(is_null: &IsNullCondition, payload: &Payload) ->
 }
 
 pub fn check_field_condition(field_condition: &FieldCondition, payload: &Payload) -> bool {
-    let field_values = payload.get_value(&field_condition.key);
+    MatchAnySets::default().check_field_condition(field_condition, payload)
+}
 
+fn check_field_values(
+    field_condition: &FieldCondition,
+    field_values: MultiValue<&Value>,
+    any_sets: &MatchAnySets,
+) -> bool {
+    // `except` has to hold for all the values, so it also matches points without values
+    if let Some(except @ Match::Except(_)) = &field_condition.r#match {
+        return field_values.into_iter().all(|p| except.check(p));
+    }
+
+    let any_set = any_sets.get(field_condition);
     let mut res = false;
     for p in field_values {
         // ToDo: Convert onto iterator over checkers, so it would be impossible to forget a condition
+        res = res
+            || match any_set {
+                Some(any_set) => any_set.check(p),
+                None => field_condition
+                    .r#match
+                    .as_ref()
+                    .map_or(false, |condition| condition.check(p)),
+            };
         res = res
             || field_condition
-                .r#match
+                .range
                 .as_ref()
                 .map_or(false, |condition| condition.check(p));
         res = res
             || field_condition
-                .range
+                .geo_radius
                 .as_ref()
                 .map_or(false, |condition| condition.check(p));
         res = res
             || field_condition
-                .geo_radius
+                .geo_polygon
                 .as_ref()
                 .map_or(false, |condition| condition.check(p));
         res = res
@@ -137,15 +307,15 @@ pub fn check_field_condition(field_condition: &FieldCondition, payload: &Payload
 }
 
 pub struct SimpleConditionChecker {
-    payload_storage: Arc<AtomicRefCell<PayloadStorageEnum>>,
-    id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+    payload_storage: Arc<RwLock<PayloadStorageEnum>>,
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
     empty_payload: Payload,
 }
 
 impl SimpleConditionChecker {
     pub fn new(
-        payload_storage: Arc<AtomicRefCell<PayloadStorageEnum>>,
-        id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+        payload_storage: Arc<RwLock<PayloadStorageEnum>>,
+        id_tracker: Arc<RwLock<IdTrackerSS>>,
     ) -> Self {
         SimpleConditionChecker {
             payload_storage,
@@ -157,7 +327,7 @@ impl SimpleConditionChecker {
 
 impl ConditionChecker for SimpleConditionChecker {
     fn check(&self, point_id: PointOffsetType, query: &Filter) -> bool {
-        let payload_storage_guard = self.payload_storage.borrow();
+        let payload_storage_guard = self.payload_storage.read();
 
         let payload_ref_cell: RefCell<Option<OwnedPayloadRef>> = RefCell::new(None);
         check_payload(
@@ -197,7 +367,7 @@ impl ConditionChecker for SimpleConditionChecker {
                 }
                 payload_ref_cell.borrow().as_ref().cloned().unwrap()
             },
-            self.id_tracker.borrow().deref(),
+            self.id_tracker.read().deref(),
             query,
             point_id,
         )
@@ -218,7 +388,8 @@ mod tests {
     use crate::payload_storage::simple_payload_storage::SimplePayloadStorage;
     use crate::payload_storage::PayloadStorage;
     use crate::types::{
-        FieldCondition, GeoBoundingBox, GeoPoint, PayloadField, Range, ValuesCount,
+        AnyVariants, FieldCondition, GeoBoundingBox, GeoPoint, MatchExcept, PayloadField, Range,
+        ValuesCount,
     };
 
     #[test]
@@ -244,7 +415,7 @@ mod tests {
         .into();
 
         let mut payload_storage: PayloadStorageEnum =
-            SimplePayloadStorage::open(db.clone()).unwrap().into();
+            SimplePayloadStorage::open(db.clone(), None).unwrap().into();
         let mut id_tracker = SimpleIdTracker::open(db).unwrap();
 
         id_tracker.set_link(0.into(), 0).unwrap();
@@ -254,8 +425,8 @@ mod tests {
         payload_storage.assign_all(0, &payload).unwrap();
 
         let payload_checker = SimpleConditionChecker::new(
-            Arc::new(AtomicRefCell::new(payload_storage)),
-            Arc::new(AtomicRefCell::new(id_tracker)),
+            Arc::new(RwLock::new(payload_storage)),
+            Arc::new(RwLock::new(id_tracker)),
         );
 
         let is_empty_condition = Filter::new_must(Condition::IsEmpty(IsEmptyCondition {
@@ -286,6 +457,33 @@ mod tests {
         }));
         assert!(!payload_checker.check(0, &is_empty_condition));
 
+        // "not is_empty" means "has at least one value"
+        let has_value = |key: &str| {
+            Filter::new_must_not(Condition::IsEmpty(IsEmptyCondition {
+                is_empty: PayloadField {
+                    key: key.to_string(),
+                },
+            }))
+        };
+        assert!(payload_checker.check(0, &has_value("price")));
+        assert!(payload_checker.check(0, &has_value("rating")));
+        assert!(!payload_checker.check(0, &has_value("something_new")));
+        assert!(!payload_checker.check(0, &has_value("parts")));
+        assert!(!payload_checker.check(0, &has_value("packaging")));
+
+        // "not is_null" also matches missing and empty fields
+        let not_null = |key: &str| {
+            Filter::new_must_not(Condition::IsNull(IsNullCondition {
+                is_null: PayloadField {
+                    key: key.to_string(),
+                },
+            }))
+        };
+        assert!(!payload_checker.check(0, &not_null("packaging")));
+        assert!(payload_checker.check(0, &not_null("amount")));
+        assert!(payload_checker.check(0, &not_null("parts")));
+        assert!(payload_checker.check(0, &not_null("something_else")));
+
         let is_null_condition = Filter::new_must(Condition::IsNull(IsNullCondition {
             is_null: PayloadField {
                 key: "amount".to_string(),
@@ -438,6 +636,29 @@ mod tests {
         };
         assert!(!payload_checker.check(0, &query));
 
+        // Empty `should` is not a constraint
+        let query = Filter {
+            should: Some(vec![]),
+            must: Some(vec![match_red.clone()]),
+            must_not: None,
+        };
+        assert!(payload_checker.check(0, &query));
+
+        // `should` and `must` on overlapping keys
+        let query = Filter {
+            should: Some(vec![match_red.clone(), match_blue.clone()]),
+            must: Some(vec![match_blue.clone()]),
+            must_not: None,
+        };
+        assert!(!payload_checker.check(0, &query));
+
+        let query = Filter {
+            should: Some(vec![match_blue.clone(), in_berlin.clone()]),
+            must: Some(vec![match_red.clone()]),
+            must_not: None,
+        };
+        assert!(payload_checker.check(0, &query));
+
         let query = Filter {
             should: Some(vec![
                 Condition::Filter(Filter {
@@ -481,6 +702,47 @@ mod tests {
         };
         assert!(!payload_checker.check(0, &query));
 
+        // `must` and `must_not` on the same key
+        let rating_in_range = Condition::Field(FieldCondition::new_range(
+            "rating".to_string(),
+            Range {
+                lt: None,
+                gt: None,
+                gte: Some(3.),
+                lte: None,
+            },
+        ));
+        let rating_too_high = Condition::Field(FieldCondition::new_range(
+            "rating".to_string(),
+            Range {
+                lt: None,
+                gt: Some(10.),
+                gte: None,
+                lte: None,
+            },
+        ));
+
+        let query = Filter {
+            should: None,
+            must: Some(vec![rating_in_range.clone()]),
+            must_not: Some(vec![rating_too_high]),
+        };
+        assert!(payload_checker.check(0, &query));
+
+        let query = Filter {
+            should: None,
+            must: Some(vec![rating_in_range.clone()]),
+            must_not: Some(vec![rating_in_range]),
+        };
+        assert!(!payload_checker.check(0, &query));
+
+        // Missing key does not match the inner condition, so it passes `must_not`
+        let query = Filter::new_must_not(Condition::Field(FieldCondition::new_match(
+            "missing_key".to_string(),
+            "spam".to_owned().into(),
+        )));
+        assert!(payload_checker.check(0, &query));
+
         let ids: HashSet<_> = vec![1, 2, 3].into_iter().map(|x| x.into()).collect();
 
         let query = Filter {
@@ -508,4 +770,349 @@ mod tests {
         };
         assert!(payload_checker.check(2, &query));
     }
+
+    #[test]
+    fn test_range_field_condition() {
+        let payload: Payload = json!({
+            "int": [1, 5, 10],
+            "float": 2.5,
+        })
+        .into();
+
+        let check = |key: &str, range: Range| {
+            check_field_condition(&FieldCondition::new_range(key.to_owned(), range), &payload)
+        };
+
+        // Any of the array values may match
+        assert!(check(
+            "int",
+            Range {
+                lt: None,
+                gt: Some(9.),
+                gte: None,
+                lte: None,
+            }
+        ));
+        assert!(!check(
+            "int",
+            Range {
+                lt: None,
+                gt: Some(10.),
+                gte: None,
+                lte: None,
+            }
+        ));
+        assert!(check(
+            "int",
+            Range {
+                lt: None,
+                gt: None,
+                gte: Some(10.),
+                lte: None,
+            }
+        ));
+
+        // Integer values against fractional bounds
+        assert!(check(
+            "int",
+            Range {
+                lt: Some(5.5),
+                gt: Some(4.5),
+                gte: None,
+                lte: None,
+            }
+        ));
+
+        // Float values against integer bounds
+        assert!(check(
+            "float",
+            Range {
+                lt: Some(3.),
+                gt: None,
+                gte: Some(2.),
+                lte: None,
+            }
+        ));
+        assert!(!check(
+            "float",
+            Range {
+                lt: None,
+                gt: None,
+                gte: None,
+                lte: Some(2.),
+            }
+        ));
+
+        // Missing key never matches
+        assert!(!check(
+            "missing",
+            Range {
+                lt: None,
+                gt: None,
+                gte: None,
+                lte: Some(100.),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_match_except_field_condition() {
+        let payload: Payload = json!({
+            "color": "red",
+            "tags": ["new", "sale"],
+            "sizes": [1, 2],
+            "items": [{ "color": "red" }, { "color": "green" }],
+        })
+        .into();
+
+        let check = |key: &str, except: AnyVariants| {
+            let r#match = Match::Except(MatchExcept { except });
+            check_field_condition(
+                &FieldCondition::new_match(key.to_owned(), r#match),
+                &payload,
+            )
+        };
+        let keywords =
+            |list: &[&str]| AnyVariants::Keywords(list.iter().map(|x| x.to_string()).collect());
+
+        assert!(!check("color", keywords(&["red"])));
+        assert!(check("color", keywords(&["blue"])));
+
+        // Multi-valued field, where one value is excepted and another is not
+        assert!(!check("tags", keywords(&["sale"])));
+        assert!(check("tags", keywords(&["old"])));
+        assert!(!check("items[].color", keywords(&["green"])));
+        assert!(check("items[].color", keywords(&["blue"])));
+        assert!(!check("sizes", AnyVariants::Integers(vec![2, 3])));
+        assert!(check("sizes", AnyVariants::Integers(vec![3])));
+
+        // Points without values match, same as with `must_not`
+        assert!(check("missing", keywords(&["red"])));
+        let must_not_any = Filter::new_must_not(Condition::Field(FieldCondition::new_match(
+            "missing".to_owned(),
+            vec!["red".to_string()].into(),
+        )));
+        assert!(check_filter(
+            &|condition: &Condition| match condition {
+                Condition::Field(field_condition) =>
+                    check_field_condition(field_condition, &payload),
+                _ => unreachable!(),
+            },
+            &must_not_any,
+        ));
+    }
+
+    #[test]
+    fn test_match_any_field_condition() {
+        let payload: Payload = json!({
+            "color": "red",
+            "tags": ["new", "sale"],
+            "sizes": [1, 2],
+            "items": [{ "color": "red" }, { "color": "green" }],
+        })
+        .into();
+
+        let check = |key: &str, any: AnyVariants| {
+            let r#match = Match::Any(MatchAny { any });
+            check_field_condition(
+                &FieldCondition::new_match(key.to_owned(), r#match),
+                &payload,
+            )
+        };
+        let keywords =
+            |list: &[&str]| AnyVariants::Keywords(list.iter().map(|x| x.to_string()).collect());
+
+        assert!(check("color", keywords(&["blue", "red"])));
+        assert!(!check("color", keywords(&["blue"])));
+
+        // Any of the values of a multi-valued field may match
+        assert!(check("tags", keywords(&["old", "sale"])));
+        assert!(!check("tags", keywords(&["old"])));
+        assert!(check("items[].color", keywords(&["green"])));
+        assert!(!check("items[].color", keywords(&["blue"])));
+        assert!(check("sizes", AnyVariants::Integers(vec![2, 3])));
+        assert!(!check("sizes", AnyVariants::Integers(vec![3])));
+        // Values of the other type don't match
+        assert!(!check("sizes", keywords(&["1"])));
+        assert!(!check("missing", keywords(&["red"])));
+    }
+
+    #[test]
+    fn test_match_any_sets() {
+        let payload: Payload = json!({
+            "tags": ["new", "sale"],
+            "items": [{ "color": "red" }, { "color": "green" }],
+        })
+        .into();
+        let filter: Filter = serde_json::from_value(json!({
+            "must": [
+                { "key": "tags", "match": { "any": ["old", "sale"] } },
+                { "nested": { "key": "items", "filter": {
+                    "must": [{ "key": "color", "match": { "any": ["blue", "green"] } }]
+                } } },
+                { "key": "tags", "match": { "value": "new" } },
+            ]
+        }))
+        .unwrap();
+        let conditions = filter.must.as_ref().unwrap();
+        let (Condition::Field(tags), Condition::Nested(nested), Condition::Field(tag_value)) =
+            (&conditions[0], &conditions[1], &conditions[2])
+        else {
+            panic!("unexpected conditions {conditions:?}");
+        };
+        let Some(Condition::Field(color)) = nested.nested.filter.must.as_ref().unwrap().first()
+        else {
+            panic!("unexpected nested filter {:?}", nested.nested.filter);
+        };
+
+        // Sets are built for the `any` conditions only, including the nested ones
+        let any_sets = MatchAnySets::of_filter(&filter);
+        assert!(any_sets.get(tags).is_some());
+        assert!(any_sets.get(color).is_some());
+        assert!(any_sets.get(tag_value).is_none());
+        // Equal condition of the other filter is checked without the sets
+        let other_tags = tags.clone();
+        assert!(any_sets.get(&other_tags).is_none());
+
+        assert!(any_sets.check_field_condition(tags, &payload));
+        assert!(any_sets.check_field_condition(&other_tags, &payload));
+        assert!(any_sets.check_nested_condition(nested, &payload));
+        assert_eq!(
+            MatchAnySets::of_condition(tags).check_field_condition(tags, &payload),
+            check_field_condition(tags, &payload)
+        );
+    }
+
+    #[test]
+    fn test_nested_condition() {
+        let payload: Payload = json!({
+            "variants": [
+                { "color": "red", "size": 10 },
+                { "color": "blue", "size": 12, "tags": ["sale"] },
+                "not an object",
+            ],
+            "info": { "variants": { "color": "green", "size": 8 } },
+        })
+        .into();
+
+        let color = |color: &str| {
+            Condition::Field(FieldCondition::new_match(
+                "color".to_owned(),
+                color.to_owned().into(),
+            ))
+        };
+        let size =
+            |size: i64| Condition::Field(FieldCondition::new_match("size".to_owned(), size.into()));
+        let check = |key: &str, filter: Filter| {
+            check_nested_condition(&NestedCondition::new(key.to_owned(), filter), &payload)
+        };
+        let all = |conditions: Vec<Condition>| Filter {
+            should: None,
+            must: Some(conditions),
+            must_not: None,
+        };
+
+        assert!(check("variants", all(vec![color("red"), size(10)])));
+        assert!(check("variants", all(vec![color("blue"), size(12)])));
+        // Each value matches some object, but no object matches both
+        assert!(!check("variants", all(vec![color("red"), size(12)])));
+        let flattened = Filter::new_must(Condition::Filter(all(vec![
+            Condition::Field(FieldCondition::new_match(
+                "variants[].color".to_owned(),
+                "red".to_owned().into(),
+            )),
+            Condition::Field(FieldCondition::new_match(
+                "variants[].size".to_owned(),
+                12.into(),
+            )),
+        ])));
+        assert!(check_filter(
+            &|condition: &Condition| match condition {
+                Condition::Field(field_condition) =>
+                    check_field_condition(field_condition, &payload),
+                _ => unreachable!(),
+            },
+            &flattened,
+        ));
+
+        // `must_not` is also applied to the same object
+        let red_not_small = Filter {
+            should: None,
+            must: Some(vec![color("red")]),
+            must_not: Some(vec![size(10)]),
+        };
+        assert!(!check("variants", red_not_small));
+        let not_red = Filter::new_must_not(color("red"));
+        assert!(check("variants", not_red));
+
+        let no_tags = Filter::new_must(Condition::IsEmpty(IsEmptyCondition {
+            is_empty: PayloadField {
+                key: "tags".to_owned(),
+            },
+        }));
+        assert!(check("variants", no_tags));
+
+        // Single object is treated as an array of one object
+        assert!(check("info.variants", all(vec![color("green"), size(8)])));
+        assert!(!check("missing", Filter::default()));
+        assert!(check("variants", Filter::default()));
+    }
+
+    #[test]
+    fn test_nested_filter_truth_table() {
+        let flag =
+            |key: &str| Condition::Field(FieldCondition::new_match(key.to_string(), true.into()));
+        let all = |conditions: Vec<Condition>| Filter {
+            should: None,
+            must: Some(conditions),
+            must_not: None,
+        };
+        let any = |conditions: Vec<Condition>| Filter {
+            should: Some(conditions),
+            must: None,
+            must_not: None,
+        };
+
+        // (A AND B) OR (C AND NOT D)
+        let query = any(vec![
+            Condition::Filter(all(vec![flag("a"), flag("b")])),
+            Condition::Filter(Filter {
+                should: None,
+                must: Some(vec![flag("c")]),
+                must_not: Some(vec![flag("d")]),
+            }),
+        ]);
+
+        // (A OR B) AND NOT (NOT (NOT (C AND D)))
+        let double_negation = Filter::new_must_not(Condition::Filter(Filter::new_must_not(
+            Condition::Filter(all(vec![flag("c"), flag("d")])),
+        )));
+        let deep_query = Filter {
+            should: None,
+            must: Some(vec![Condition::Filter(any(vec![flag("a"), flag("b")]))]),
+            must_not: Some(vec![Condition::Filter(double_negation)]),
+        };
+
+        for mask in 0..16u8 {
+            let (a, b, c, d) = (mask & 1 > 0, mask & 2 > 0, mask & 4 > 0, mask & 8 > 0);
+            let payload: Payload = json!({ "a": a, "b": b, "c": c, "d": d }).into();
+            let checker = |condition: &Condition| match condition {
+                Condition::Field(field_condition) => {
+                    check_field_condition(field_condition, &payload)
+                }
+                _ => unreachable!(),
+            };
+
+            assert_eq!(
+                check_filter(&checker, &query),
+                (a && b) || (c && !d),
+                "a={a} b={b} c={c} d={d}"
+            );
+            assert_eq!(
+                check_filter(&checker, &deep_query),
+                (a || b) && !(c && d),
+                "a={a} b={b} c={c} d={d}"
+            );
+        }
+    }
 }
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use parking_lot::RwLock;
 use rocksdb::DB;
 
+use crate::common::encryption::SegmentCipher;
 use crate::common::rocksdb_wrapper::{DatabaseColumnWrapper, DB_PAYLOAD_CF};
 use crate::entry::entry_point::{OperationError, OperationResult};
 use crate::types::{Payload, PointOffsetType};
@@ -11,26 +13,34 @@ use crate::types::{Payload, PointOffsetType};
 /// In-memory implementation of `PayloadStorage`.
 /// Persists all changes to disk using `store`, but only uses this storage during the initial load
 pub struct SimplePayloadStorage {
-    pub(crate) payload: HashMap<PointOffsetType, Payload>,
+    /// Payloads are shared with the readers, and copied on write, if still referenced
+    pub(crate) payload: HashMap<PointOffsetType, Arc<Payload>>,
     pub(crate) db_wrapper: DatabaseColumnWrapper,
+    /// Number of the payloads, serialized to be persisted
+    serialized_payloads: AtomicUsize,
 }
 
 impl SimplePayloadStorage {
-    pub fn open(database: Arc<RwLock<DB>>) -> OperationResult<Self> {
-        let mut payload_map: HashMap<PointOffsetType, Payload> = Default::default();
+    pub fn open(
+        database: Arc<RwLock<DB>>,
+        cipher: Option<Arc<SegmentCipher>>,
+    ) -> OperationResult<Self> {
+        let mut payload_map: HashMap<PointOffsetType, Arc<Payload>> = Default::default();
 
-        let db_wrapper = DatabaseColumnWrapper::new(database, DB_PAYLOAD_CF);
+        let db_wrapper = DatabaseColumnWrapper::new(database, DB_PAYLOAD_CF).with_cipher(cipher);
         for (key, val) in db_wrapper.lock_db().iter()? {
             let point_id: PointOffsetType = serde_cbor::from_slice(&key)
                 .map_err(|_| OperationError::service_error("cannot deserialize point id"))?;
+            let val = db_wrapper.decrypt_value(&key, &val)?;
             let payload: Payload = serde_cbor::from_slice(&val)
                 .map_err(|_| OperationError::service_error("cannot deserialize payload"))?;
-            payload_map.insert(point_id, payload);
+            payload_map.insert(point_id, Arc::new(payload));
         }
 
         Ok(SimplePayloadStorage {
             payload: payload_map,
             db_wrapper,
+            serialized_payloads: AtomicUsize::new(0),
         })
     }
 
@@ -39,15 +49,22 @@ impl SimplePayloadStorage {
             None => self
                 .db_wrapper
                 .remove(serde_cbor::to_vec(&point_id).unwrap()),
-            Some(payload) => self.db_wrapper.put(
-                serde_cbor::to_vec(&point_id).unwrap(),
-                serde_cbor::to_vec(payload).unwrap(),
-            ),
+            Some(payload) => {
+                self.serialized_payloads.fetch_add(1, Ordering::Relaxed);
+                self.db_wrapper.put(
+                    serde_cbor::to_vec(&point_id).unwrap(),
+                    serde_cbor::to_vec(payload.as_ref()).unwrap(),
+                )
+            }
         }
     }
 
+    pub fn serialized_payloads(&self) -> usize {
+        self.serialized_payloads.load(Ordering::Relaxed)
+    }
+
     pub fn payload_ptr(&self, point_id: PointOffsetType) -> Option<&Payload> {
-        self.payload.get(&point_id)
+        self.payload.get(&point_id).map(Arc::as_ref)
     }
 
     pub fn iter<F>(&self, mut callback: F) -> OperationResult<()>
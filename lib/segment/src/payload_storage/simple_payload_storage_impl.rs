@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use serde_json::Value;
 
@@ -11,9 +12,9 @@ use crate::types::{Payload, PayloadKeyTypeRef, PointOffsetType};
 impl PayloadStorage for SimplePayloadStorage {
     fn assign(&mut self, point_id: PointOffsetType, payload: &Payload) -> OperationResult<()> {
         match self.payload.get_mut(&point_id) {
-            Some(point_payload) => point_payload.merge(payload),
+            Some(point_payload) => Arc::make_mut(point_payload).merge(payload),
             None => {
-                self.payload.insert(point_id, payload.to_owned());
+                self.payload.insert(point_id, Arc::new(payload.to_owned()));
             }
         }
 
@@ -22,9 +23,9 @@ impl PayloadStorage for SimplePayloadStorage {
         Ok(())
     }
 
-    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Payload> {
+    fn payload(&self, point_id: PointOffsetType) -> OperationResult<Arc<Payload>> {
         match self.payload.get(&point_id) {
-            Some(payload) => Ok(payload.to_owned()),
+            Some(payload) => Ok(payload.clone()),
             None => Ok(Default::default()),
         }
     }
@@ -36,7 +37,7 @@ impl PayloadStorage for SimplePayloadStorage {
     ) -> OperationResult<Vec<Value>> {
         match self.payload.get_mut(&point_id) {
             Some(payload) => {
-                let res = payload.remove(key);
+                let res = Arc::make_mut(payload).remove(key);
                 if !res.is_empty() {
                     self.update_storage(&point_id)?;
                 }
@@ -47,11 +48,22 @@ impl PayloadStorage for SimplePayloadStorage {
     }
 
     fn drop(&mut self, point_id: PointOffsetType) -> OperationResult<Option<Payload>> {
-        let res = self.payload.remove(&point_id);
+        let res = self.payload.remove(&point_id).map(Payload::from_shared);
         self.update_storage(&point_id)?;
         Ok(res)
     }
 
+    fn move_entry(&mut self, from: PointOffsetType, to: PointOffsetType) -> OperationResult<()> {
+        match self.payload.remove(&from) {
+            Some(payload) => self.payload.insert(to, payload),
+            None => self.payload.remove(&to),
+        };
+        self.db_wrapper.rename(
+            &serde_cbor::to_vec(&from).unwrap(),
+            &serde_cbor::to_vec(&to).unwrap(),
+        )
+    }
+
     fn wipe(&mut self) -> OperationResult<()> {
         self.payload = HashMap::new();
         self.db_wrapper.recreate_column_family()
@@ -74,7 +86,7 @@ mod tests {
         let dir = Builder::new().prefix("db_dir").tempdir().unwrap();
         let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
 
-        let mut storage = SimplePayloadStorage::open(db).unwrap();
+        let mut storage = SimplePayloadStorage::open(db, None).unwrap();
         let payload: Payload = serde_json::from_str(r#"{"name": "John Doe"}"#).unwrap();
         storage.assign(100, &payload).unwrap();
         storage.wipe().unwrap();
@@ -113,9 +125,9 @@ mod tests {
         let payload: Payload = serde_json::from_str(data).unwrap();
         let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
         let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
-        let mut storage = SimplePayloadStorage::open(db).unwrap();
+        let mut storage = SimplePayloadStorage::open(db, None).unwrap();
         storage.assign(100, &payload).unwrap();
         let pload = storage.payload(100).unwrap();
-        assert_eq!(pload, payload);
+        assert_eq!(*pload, payload);
     }
 }
@@ -0,0 +1,185 @@
+//! Python bindings of the segment, enabled by the `python` feature
+//!
+//! The extension module `qdrant_segment` is built with maturin, see `pyproject.toml`:
+//! `maturin develop && pytest tests/python`.
+//!
+//! Payloads and filters are passed as dicts, they are converted through JSON, so filters are parsed
+//! by [`Filter::from_json`], the same way, as the filters of the API. Errors of the segment are raised
+//! as `SegmentError`, and malformed filters and payloads as `ValueError`, with the same messages.
+
+use std::borrow::Cow;
+
+use numpy::PyReadonlyArray1;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::data_types::vectors::{only_default_vector, VectorElementType, DEFAULT_VECTOR_NAME};
+use crate::entry::entry_point::{OperationError, SegmentEntry};
+use crate::segment::Segment;
+use crate::segment_constructor::load_segment;
+use crate::segment_constructor::simple_segment_constructor::build_simple_segment;
+use crate::types::{Distance, ExtendedPointId, Filter, Payload, WithPayload};
+
+create_exception!(qdrant_segment, SegmentError, PyException);
+
+fn segment_error(err: OperationError) -> PyErr {
+    SegmentError::new_err(err.to_string())
+}
+
+/// Vector as a numpy array, read in place if it is contiguous, or as a list of floats
+#[derive(FromPyObject)]
+enum VectorArg<'py> {
+    Array(PyReadonlyArray1<'py, VectorElementType>),
+    List(Vec<VectorElementType>),
+}
+
+impl VectorArg<'_> {
+    fn as_slice(&self) -> Cow<'_, [VectorElementType]> {
+        match self {
+            VectorArg::Array(array) => match array.as_slice() {
+                Ok(slice) => Cow::Borrowed(slice),
+                // Strided arrays are copied
+                Err(_) => Cow::Owned(array.as_array().to_vec()),
+            },
+            VectorArg::List(list) => Cow::Borrowed(list),
+        }
+    }
+}
+
+fn to_json(py: Python<'_>, object: &PyAny) -> PyResult<String> {
+    py.import("json")?
+        .call_method1("dumps", (object,))?
+        .extract()
+}
+
+fn from_json(py: Python<'_>, json: &str) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+}
+
+/// Segment with the default vector, opened from a directory
+#[pyclass]
+pub struct PySegment {
+    segment: Segment,
+}
+
+#[pymethods]
+impl PySegment {
+    /// Build a new segment with the plain index in a subdirectory of `path`
+    #[new]
+    #[pyo3(signature = (path, dim, distance = "Cosine"))]
+    fn new(path: &str, dim: usize, distance: &str) -> PyResult<Self> {
+        let distance: Distance =
+            serde_json::from_value(serde_json::Value::String(distance.to_string()))
+                .map_err(|_| PyValueError::new_err(format!("unknown distance {distance:?}")))?;
+        let segment = build_simple_segment(path.as_ref(), dim, distance).map_err(segment_error)?;
+        Ok(Self { segment })
+    }
+
+    /// Load the segment from its directory
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let segment = load_segment(path.as_ref())
+            .map_err(segment_error)?
+            .ok_or_else(|| SegmentError::new_err(format!("no segment in {path}")))?;
+        Ok(Self { segment })
+    }
+
+    /// Insert or replace the point, as the next operation of the segment
+    ///
+    /// Returns, if an existing point was replaced.
+    #[pyo3(signature = (id, vector, payload = None))]
+    fn upsert(
+        &mut self,
+        py: Python<'_>,
+        id: u64,
+        vector: VectorArg<'_>,
+        payload: Option<&PyDict>,
+    ) -> PyResult<bool> {
+        let payload: Option<Payload> = payload
+            .map(|payload| {
+                serde_json::from_str(&to_json(py, payload)?)
+                    .map_err(|err| PyValueError::new_err(format!("malformed payload: {err}")))
+            })
+            .transpose()?;
+        let vector = vector.as_slice();
+        let op_num = self.segment.version() + 1;
+        let replaced = self
+            .segment
+            .upsert_points(
+                op_num,
+                &[id.into()],
+                &[only_default_vector(&vector)],
+                &[payload.as_ref()],
+            )
+            .map_err(segment_error)?;
+        Ok(replaced[0])
+    }
+
+    /// Search for the `top` closest points, returns the ids, the scores and the payloads of the found points
+    #[pyo3(signature = (vector, top, filter = None))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        vector: VectorArg<'_>,
+        top: usize,
+        filter: Option<&PyDict>,
+    ) -> PyResult<Vec<(PyObject, f32, PyObject)>> {
+        let filter = filter
+            .map(|filter| {
+                Filter::from_json(&to_json(py, filter)?)
+                    .map_err(|err| PyValueError::new_err(err.to_string()))
+            })
+            .transpose()?;
+        let vector = vector.as_slice();
+        let found = py
+            .allow_threads(|| {
+                self.segment.search(
+                    DEFAULT_VECTOR_NAME,
+                    &vector,
+                    &WithPayload::from(true),
+                    &false.into(),
+                    filter.as_ref(),
+                    top,
+                    None,
+                )
+            })
+            .map_err(segment_error)?;
+        found
+            .into_iter()
+            .map(|point| {
+                let id = match point.id {
+                    ExtendedPointId::NumId(id) => id.into_py(py),
+                    ExtendedPointId::Uuid(uuid) => uuid.to_string().into_py(py),
+                    ExtendedPointId::Str(id) => id.as_str().into_py(py),
+                };
+                let payload = match &point.payload {
+                    Some(payload) => {
+                        from_json(py, &serde_json::to_string(payload.as_ref()).unwrap())?
+                    }
+                    None => py.None(),
+                };
+                Ok((id, point.score, payload))
+            })
+            .collect()
+    }
+
+    /// Persist the applied operations, returns the persisted version
+    fn flush(&self, py: Python<'_>) -> PyResult<u64> {
+        py.allow_threads(|| self.segment.flush(true))
+            .map_err(segment_error)
+    }
+
+    fn __len__(&self) -> usize {
+        self.segment.points_count()
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "qdrant_segment")]
+fn qdrant_segment_module(py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PySegment>()?;
+    module.add("SegmentError", py.get_type::<SegmentError>())?;
+    Ok(())
+}
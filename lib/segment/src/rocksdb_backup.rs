@@ -1,6 +1,7 @@
 use std::fs;
 use std::path::Path;
 
+use crate::common::rocksdb_wrapper::{open_db_with_existing_cf, recreate_cf};
 use crate::entry::entry_point::{OperationError, OperationResult};
 
 pub fn create(db: &rocksdb::DB, backup_path: &Path) -> OperationResult<()> {
@@ -19,6 +20,47 @@ pub fn create(db: &rocksdb::DB, backup_path: &Path) -> OperationResult<()> {
         })
 }
 
+/// Back up the database without the data of the `excluded_cfs` column families
+///
+/// Excluded column families are emptied in a checkpoint of the database, which is then backed up.
+pub fn create_excluding(
+    db: &rocksdb::DB,
+    backup_path: &Path,
+    excluded_cfs: &[String],
+) -> OperationResult<()> {
+    let mut checkpoint_name = backup_path.file_name().unwrap_or_default().to_owned();
+    checkpoint_name.push(".checkpoint");
+    let checkpoint_path = backup_path.with_file_name(checkpoint_name);
+
+    rocksdb::checkpoint::Checkpoint::new(db)
+        .and_then(|checkpoint| checkpoint.create_checkpoint(&checkpoint_path))
+        .map_err(|err| {
+            OperationError::service_error(format!(
+                "failed to create RocksDB checkpoint {checkpoint_path:?}: {err}"
+            ))
+        })?;
+
+    let result = open_db_with_existing_cf(&checkpoint_path)
+        .and_then(|checkpoint_db| {
+            for cf in excluded_cfs {
+                let exists = checkpoint_db.read().cf_handle(cf).is_some();
+                if exists {
+                    recreate_cf(checkpoint_db.clone(), cf)?;
+                }
+            }
+            Ok(checkpoint_db)
+        })
+        .map_err(|err| {
+            OperationError::service_error(format!(
+                "failed to exclude column families from RocksDB checkpoint: {err}"
+            ))
+        })
+        .and_then(|checkpoint_db| create(&checkpoint_db.read(), backup_path));
+
+    fs::remove_dir_all(&checkpoint_path)?;
+    result
+}
+
 pub fn restore(backup_path: &Path, restore_path: &Path) -> OperationResult<()> {
     backup_engine(backup_path)?
         .restore_from_latest_backup(restore_path, restore_path, &Default::default())
@@ -0,0 +1,185 @@
+use std::cmp::Ordering;
+
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::OperationResult;
+use crate::segment::Segment;
+use crate::types::{Filter, ScoredPoint, SearchParams, WithPayload, WithVector};
+use crate::vector_storage::ScoredPointOffset;
+
+/// Scored point, ordered by score and then by lower offset
+///
+/// Gives a strict order of the search results, so the next batch can start right after the last yielded point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OrderedScoredPoint(pub ScoredPointOffset);
+
+impl Eq for OrderedScoredPoint {}
+
+impl Ord for OrderedScoredPoint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .cmp(&other.0)
+            .then_with(|| other.0.idx.cmp(&self.0.idx))
+    }
+}
+
+impl PartialOrd for OrderedScoredPoint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+enum Candidates {
+    /// Candidates are scored for each batch, starting after the last yielded point
+    Exact { last: Option<ScoredPointOffset> },
+    /// Vector index is searched for each batch, with the `top` widened by the yielded points
+    Index {
+        last: Option<ScoredPointOffset>,
+        yielded: usize,
+    },
+}
+
+/// Search results of a segment, best first, produced by batches
+///
+/// Exact searches score all candidates for each batch, keeping only the top, which goes after
+/// the last yielded point. So memory usage is bounded by the batch size, and storages are borrowed
+/// only while a batch is produced.
+/// Approximate searches can't resume the graph search, so the vector index is searched again
+/// for each batch, for the yielded points and the batch, which widens the `ef` of the search along
+/// with the `top`. Only the batch is kept between the searches, and the last batch is searched
+/// with the whole `top`, the same way as [`crate::entry::entry_point::SegmentEntry::search`] does.
+/// Points, which only the wider search finds before the last yielded point, are skipped.
+pub struct ScoredPointsIterator<'a> {
+    segment: &'a Segment,
+    vector_name: String,
+    vector: Vec<VectorElementType>,
+    with_payload: WithPayload,
+    with_vector: WithVector,
+    filter: Option<Filter>,
+    params: Option<SearchParams>,
+    /// Number of points left to yield
+    remaining: usize,
+    batch_size: usize,
+    candidates: Candidates,
+    batch: std::vec::IntoIter<ScoredPoint>,
+}
+
+impl<'a> ScoredPointsIterator<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        segment: &'a Segment,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        batch_size: usize,
+        exact: bool,
+    ) -> Self {
+        let candidates = if exact {
+            Candidates::Exact { last: None }
+        } else {
+            Candidates::Index {
+                last: None,
+                yielded: 0,
+            }
+        };
+        Self {
+            segment,
+            vector_name: vector_name.to_owned(),
+            vector: vector.to_vec(),
+            with_payload: with_payload.clone(),
+            with_vector: with_vector.clone(),
+            filter,
+            params: params.cloned(),
+            remaining: top,
+            batch_size: batch_size.max(1),
+            candidates,
+            batch: Vec::new().into_iter(),
+        }
+    }
+
+    /// Offsets of the next batch, empty if there are no more results
+    fn next_offsets(&mut self) -> Vec<ScoredPointOffset> {
+        let limit = self.batch_size.min(self.remaining);
+        match &mut self.candidates {
+            Candidates::Exact { last } => {
+                let offsets = self.segment.exact_search_after(
+                    &self.vector_name,
+                    &self.vector,
+                    self.filter.as_ref(),
+                    limit,
+                    *last,
+                );
+                if let Some(offset) = offsets.last() {
+                    *last = Some(*offset);
+                }
+                offsets
+            }
+            Candidates::Index { last, yielded } => {
+                let mut found = self.segment.vector_data[&self.vector_name]
+                    .vector_index
+                    .read()
+                    .search(
+                        &[self.vector.as_slice()],
+                        self.filter.as_ref(),
+                        *yielded + limit,
+                        self.params.as_ref(),
+                    )
+                    .pop()
+                    .unwrap_or_default();
+                // Points of the same score may come in any order, which has to be strict to skip
+                // the yielded ones
+                found.sort_unstable_by(|a, b| OrderedScoredPoint(*b).cmp(&OrderedScoredPoint(*a)));
+                let after = last.map(OrderedScoredPoint);
+                let offsets: Vec<_> = found
+                    .into_iter()
+                    .filter(|&point| after.map_or(true, |after| OrderedScoredPoint(point) < after))
+                    .take(limit)
+                    .collect();
+                if let Some(offset) = offsets.last() {
+                    *last = Some(*offset);
+                }
+                *yielded += offsets.len();
+                offsets
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for ScoredPointsIterator<'a> {
+    type Item = OperationResult<ScoredPoint>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(point) = self.batch.next() {
+            return Some(Ok(point));
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let offsets = self.next_offsets();
+        if offsets.is_empty() {
+            self.remaining = 0;
+            return None;
+        }
+        self.remaining -= offsets.len();
+
+        match self.segment.process_search_result(
+            &self.vector_name,
+            &offsets,
+            &self.with_payload,
+            &self.with_vector,
+        ) {
+            Ok(points) => {
+                self.batch = points.into_iter();
+                self.next()
+            }
+            Err(err) => {
+                self.remaining = 0;
+                Some(Err(err))
+            }
+        }
+    }
+}
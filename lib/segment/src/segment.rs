@@ -1,47 +1,130 @@
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread::JoinHandle;
 
-use atomic_refcell::AtomicRefCell;
 use parking_lot::{Mutex, RwLock};
 use rocksdb::DB;
 use tar::Builder;
 use uuid::Uuid;
 
+use crate::common::arc_rwlock_iterator::ArcRwLockIterator;
+#[cfg(feature = "arrow")]
+use crate::common::arrow_export::{arrow_error, ExportTable, ARROW_BATCH_SIZE};
+use crate::common::atomic_rename::{commit_file, temp_path, write_dir_atomically};
+use crate::common::checksums::{save_checksums, verify_unpacked_checksums, CHECKSUMS_FILE};
+use crate::common::encryption::{is_encrypted, ENCRYPTION_FILE};
 use crate::common::file_operations::{atomic_save_json, read_json};
+use crate::common::flush_scheduler::{FlushScheduler, FlushStats};
+use crate::common::grouping::search_groups;
+use crate::common::jsonl::{
+    read_points, ImportLineError, ImportReport, JsonlPoint, DEFAULT_IMPORT_ERROR_BUDGET,
+};
+use crate::common::npy::NpyReader;
+use crate::common::payload_selection::PayloadSelection;
+use crate::common::rocksdb_wrapper::{DB_PAYLOAD_CF, DB_VECTOR_CF};
+use crate::common::search_deadline::SearchDeadline;
+use crate::common::search_telemetry::{SearchCounters, SearchPhase, SearchTelemetry};
+use crate::common::segment_lock::SegmentLock;
+use crate::common::stats_cache::{SegmentStats, StatsCache};
+use crate::common::utils::reservoir_sample;
 use crate::common::version::{StorageVersion, VERSION_FILE};
+use crate::common::warm_up::{warm_up_files, WarmUpParts};
+use crate::common::write_hook::{call_write_hook, WriteEvent, WriteHook, WriteOperationKind};
 use crate::common::{check_vector_name, check_vectors_set};
 use crate::data_types::named_vectors::NamedVectors;
 use crate::data_types::vectors::VectorElementType;
 use crate::entry::entry_point::OperationError::TypeInferenceError;
 use crate::entry::entry_point::{
-    get_service_error, OperationError, OperationResult, SegmentEntry, SegmentFailedState,
+    check_points_capacity, check_process_stopped, get_service_error, OperationError,
+    OperationErrorContext, OperationResult, SegmentEntry, SegmentFailedState,
 };
 use crate::id_tracker::IdTrackerSS;
 use crate::index::field_index::CardinalityEstimation;
+use crate::index::hnsw_index::graph_layers::IndexStats;
+use crate::index::index_build_handle::IndexBuildHandle;
+use crate::index::query_plan::QueryPlanExplain;
 use crate::index::struct_payload_index::StructPayloadIndex;
-use crate::index::{PayloadIndex, VectorIndex, VectorIndexEnum};
-use crate::spaces::tools::peek_top_smallest_iterable;
+use crate::index::{PayloadIndex, SearchCostEstimate, VectorIndex, VectorIndexEnum};
+use crate::payload_storage::filter_explain::{explain_payload, FilterExplain, FilterMatchCounts};
+use crate::scored_points_iterator::{OrderedScoredPoint, ScoredPointsIterator};
+use crate::segment_constructor::segment_migration::{
+    load_format_version, CURRENT_FORMAT_VERSION, FORMAT_VERSION_FILE,
+};
+use crate::segment_constructor::{
+    get_vector_index_path, get_vector_index_rebuild_path, get_vector_name_with_prefix,
+    load_segment, open_vector_index, segment_type_of,
+};
+use crate::snapshot_archive::ArchiveCache;
+use crate::snapshot_delta::{self, SnapshotManifest};
+use crate::spaces::tools::{peek_top_largest_iterable, peek_top_smallest_iterable};
 use crate::telemetry::SegmentTelemetry;
 use crate::types::{
-    Filter, Payload, PayloadFieldSchema, PayloadIndexInfo, PayloadKeyType, PayloadKeyTypeRef,
-    PayloadSchemaType, PointIdType, PointOffsetType, ScoredPoint, SearchParams, SegmentConfig,
-    SegmentInfo, SegmentState, SegmentType, SeqNumberType, WithPayload, WithVector,
+    default_quantization_ignore_value, Condition, Filter, HnswConfig, Indexes, Order, OrderBy,
+    Payload, PayloadFieldSchema, PayloadIndexInfo, PayloadKeyType, PayloadKeyTypeRef,
+    PayloadSchemaType, PayloadSelector, PayloadStorageType, PointGroup, PointIdType,
+    PointOffsetType, ScoreBoost, ScoreType, ScoredPoint, SearchParams, SegmentConfig, SegmentInfo,
+    SegmentState, SegmentType, SeqNumberType, SnapshotContents, StorageType, VectorIndexInfo,
+    VectorIndexType, WithPayload, WithVector, VECTOR_ELEMENT_SIZE,
 };
 use crate::utils;
-use crate::vector_storage::{ScoredPointOffset, VectorStorage, VectorStorageEnum};
+use crate::vector_storage::batch_scorer::BatchScorerSS;
+use crate::vector_storage::{new_raw_scorer, ScoredPointOffset, VectorStorage, VectorStorageEnum};
 
 pub const SEGMENT_STATE_FILE: &str = "segment.json";
 
-const SNAPSHOT_PATH: &str = "snapshot";
+const BYTES_IN_KB: usize = 1024;
+
+pub(crate) const SNAPSHOT_PATH: &str = "snapshot";
 
 // Sub-directories of `SNAPSHOT_PATH`:
 const DB_BACKUP_PATH: &str = "db_backup";
 const PAYLOAD_DB_BACKUP_PATH: &str = "payload_index_db_backup";
-const SNAPSHOT_FILES_PATH: &str = "files";
+pub(crate) const SNAPSHOT_FILES_PATH: &str = "files";
+
+/// Number of points, upserted by one batch of the import
+const IMPORT_BATCH_SIZE: usize = 256;
+
+/// Max number of ids, listed in [`OrphanReport`] for each kind of inconsistency
+const ORPHAN_SAMPLES_LIMIT: usize = 100;
+
+/// Number of found points per requested one, if scores are boosted,
+/// so the boost may promote the points just outside of the top
+const SCORE_BOOST_OVERSAMPLING: usize = 4;
+
+/// Number of points to search in the vector index for the requested `top`
+fn index_search_top(top: usize, params: Option<&SearchParams>) -> usize {
+    let score_boost = params.and_then(|params| params.score_boost.as_ref());
+    match score_boost {
+        Some(_) => top.saturating_mul(SCORE_BOOST_OVERSAMPLING),
+        None => top,
+    }
+}
+
+/// Fail the search, which reached its deadline, unless the partial results are requested by `params`
+fn check_search_deadline(
+    deadline: &SearchDeadline,
+    params: Option<&SearchParams>,
+) -> OperationResult<()> {
+    if deadline.is_stopped() {
+        return Err(OperationError::Cancelled {
+            description: "search cancelled by the caller".to_string(),
+        });
+    }
+    match params {
+        Some(params) if deadline.is_expired() && !params.partial_on_timeout => {
+            Err(OperationError::Timeout {
+                timeout_ms: params.timeout_ms.unwrap_or_default(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
 
 pub struct SegmentVersion;
 
@@ -66,24 +149,144 @@ pub struct Segment {
     /// Path of the storage root
     pub current_path: PathBuf,
     /// Component for mapping external ids to internal and also keeping track of point versions
-    pub id_tracker: Arc<AtomicRefCell<IdTrackerSS>>,
+    pub id_tracker: Arc<RwLock<IdTrackerSS>>,
     pub vector_data: HashMap<String, VectorData>,
-    pub payload_index: Arc<AtomicRefCell<StructPayloadIndex>>,
+    pub payload_index: Arc<RwLock<StructPayloadIndex>>,
     /// Shows if it is possible to insert more points into this segment
     pub appendable_flag: bool,
     /// Shows what kind of indexes and storages are used in this segment
     pub segment_type: SegmentType,
     pub segment_config: SegmentConfig,
+    /// Index config, which is applied on the next rebuild of the vector index
+    pub pending_index: Option<Indexes>,
     /// Last unhandled error
     /// If not None, all update operations will be aborted until original operation is performed properly
     pub error_status: Option<SegmentFailedState>,
     pub database: Arc<RwLock<DB>>,
     pub flush_thread: Mutex<Option<JoinHandle<OperationResult<SeqNumberType>>>>,
+    /// Held for writing by update operations.
+    /// Background index builds hold it for reading, while the storages are borrowed.
+    pub update_lock: Arc<RwLock<()>>,
+    /// External scorer, registered in the vector indexes
+    pub batch_scorer: Option<Arc<BatchScorerSS>>,
+    /// Flushes the applied operations according to the flush policy of the config.
+    /// If None - the policy is applied by the write-ahead log, see [`crate::segment_wal::SegmentWal::take_flush_policy`].
+    pub flush_scheduler: Option<FlushScheduler>,
+    /// Layout of the segment can't be updated by this version, see [`crate::segment_constructor::segment_migration::MigrationKind::ReadOnly`]
+    pub read_only: bool,
+    /// Outcome of the last flush, reported in the segment info
+    pub flush_stats: Arc<Mutex<FlushStats>>,
+    /// Parts of the data, present in the segment.
+    /// Segment, restored from a partial snapshot, rejects operations, which require the missing parts.
+    pub contents: SnapshotContents,
+    /// Advisory lock of the segment directory, held by the loaded segment
+    pub lock: Option<SegmentLock>,
+    /// Fields of the segment state of the newer versions, saved back with the state
+    pub unknown_state_fields: serde_json::Map<String, serde_json::Value>,
+    /// Segment is closed or its data is dropped, so it is not flushed on drop
+    pub(crate) closed: bool,
+    /// Files, extracted from the snapshot archive, the segment is opened from.
+    /// Declared last, so the directory is removed after the storages are closed.
+    pub(crate) archive_cache: Option<ArchiveCache>,
+    /// Stats, reported by [`SegmentEntry::info`], which walk the storages of the segment
+    pub stats_cache: Arc<StatsCache>,
+    /// Called after each successful write operation, see [`Segment::set_on_write_hook`]
+    pub write_hook: Option<WriteHook>,
 }
 
 pub struct VectorData {
-    pub vector_index: Arc<AtomicRefCell<VectorIndexEnum>>,
-    pub vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
+    pub vector_index: Arc<RwLock<VectorIndexEnum>>,
+    pub vector_storage: Arc<RwLock<VectorStorageEnum>>,
+}
+
+/// Inconsistencies between the id tracker and the storages of the segment
+///
+/// Vectors are written before the point is linked in the id tracker, so an interrupted
+/// update may leave either a vector without a mapping or a mapping without vectors.
+/// Vectors of deleted points are kept in storages by design and are not reported.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OrphanReport {
+    /// Number of points, mapped to offsets missing in some of the vector storages
+    pub mapped_without_vectors: usize,
+    pub mapped_without_vectors_ids: Vec<PointIdType>,
+    /// Number of vectors, stored beyond the last offset known to the id tracker
+    pub vectors_without_mapping: usize,
+    pub vectors_without_mapping_offsets: Vec<PointOffsetType>,
+    /// Number of not mapped offsets, which still have a payload
+    pub payloads_without_mapping: usize,
+    pub payloads_without_mapping_offsets: Vec<PointOffsetType>,
+    /// Dangling mappings and payloads were dropped
+    pub repaired: bool,
+}
+
+impl OrphanReport {
+    pub fn is_empty(&self) -> bool {
+        self.mapped_without_vectors == 0
+            && self.vectors_without_mapping == 0
+            && self.payloads_without_mapping == 0
+    }
+}
+
+/// Points of the import, upserted with the same op num
+#[derive(Default)]
+struct ImportBatch {
+    ids: Vec<PointIdType>,
+    vectors: Vec<NamedVectors<'static>>,
+    payloads: Vec<Payload>,
+}
+
+/// Points of a batch write, checked and preprocessed before anything is written
+#[derive(Default)]
+struct PointsBatch {
+    /// Positions in the batch and offsets of the existing points, updated in-place
+    updated: Vec<(usize, PointOffsetType)>,
+    /// Positions in the batch of the new points, appended from `start_offset`
+    inserted: Vec<usize>,
+    start_offset: PointOffsetType,
+    /// Vectors of the updated points, followed by the vectors of the inserted ones
+    vectors: HashMap<String, Vec<Vec<VectorElementType>>>,
+}
+
+impl PointsBatch {
+    fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.inserted.is_empty()
+    }
+
+    fn inserted_offsets(&self) -> Range<PointOffsetType> {
+        self.start_offset..self.start_offset + self.inserted.len() as PointOffsetType
+    }
+}
+
+/// Previous state of the data, overwritten by a batch write
+#[derive(Default)]
+struct BatchUndo {
+    vectors: Vec<(String, PointOffsetType, Vec<VectorElementType>)>,
+    /// External ids of the appended points, linked in the id tracker
+    linked: Vec<PointIdType>,
+    payloads: Vec<(PointOffsetType, Option<Payload>)>,
+    versions: Vec<(PointOffsetType, Option<SeqNumberType>)>,
+}
+
+/// Total size of the file or of the files in the directory, unreadable entries are skipped
+fn files_size(path: &Path) -> usize {
+    let Ok(metadata) = fs::metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len() as usize;
+    }
+    fs::read_dir(path).map_or(0, |entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| files_size(&entry.path()))
+            .sum()
+    })
+}
+
+fn push_sample<T>(samples: &mut Vec<T>, value: T) {
+    if samples.len() < ORPHAN_SAMPLES_LIMIT {
+        samples.push(value);
+    }
 }
 
 impl Segment {
@@ -99,8 +302,377 @@ impl Segment {
         for (vector_name, vector) in vectors {
             let vector_name: &str = &vector_name;
             let vector_data = &self.vector_data[vector_name];
-            let mut vector_storage = vector_data.vector_storage.borrow_mut();
-            vector_storage.insert_vector(internal_id, &vector)?;
+            vector_data
+                .vector_storage
+                .write()
+                .insert_vector(internal_id, &vector)?;
+            vector_data
+                .vector_index
+                .write()
+                .update_vector(internal_id)?;
+        }
+        Ok(())
+    }
+
+    /// Let vector indexes link the point, inserted after they were built
+    fn update_vector_indexes(&self, internal_id: PointOffsetType) -> OperationResult<()> {
+        for vector_data in self.vector_data.values() {
+            vector_data
+                .vector_index
+                .write()
+                .update_vector(internal_id)?;
+        }
+        Ok(())
+    }
+
+    /// Upsert a batch of points, taking each storage of the segment once for the whole batch
+    ///
+    /// Vectors are written first: existing points are updated in-place, new points are appended into
+    /// a contiguous block of offsets. Then the block is linked in the id tracker, payloads are assigned,
+    /// versions of the points are set, and the segment version is bumped once.
+    /// If any of these steps fails, the batch is rolled back: previous vectors, payloads and versions
+    /// of the existing points are restored, and the appended vectors are left without links, so they
+    /// are treated as deleted, and the next block is allocated after them.
+    /// Vector indexes are updated once the batch is applied.
+    ///
+    /// Returns for each point, if it replaced an existing one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "upsert_points",
+            level = "debug",
+            skip_all,
+            fields(op_num = op_num, points = point_ids.len()),
+            err
+        )
+    )]
+    fn write_points_batch(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+        payloads: Option<&[Option<&Payload>]>,
+    ) -> OperationResult<Vec<bool>> {
+        debug_assert_eq!(point_ids.len(), vectors.len());
+        for point_vectors in vectors {
+            check_vectors_set(point_vectors, &self.segment_config)?;
+        }
+        // Points, skipped by version, are found before the batch is applied, only if they are reported
+        let skipped = self.write_hook.as_ref().map(|_| {
+            point_ids
+                .iter()
+                .map(|point_id| {
+                    let internal_id = self.id_tracker.read().internal_id(point_id.clone());
+                    self.version.unwrap_or(0) > op_num
+                        || self.is_skipped_by_version(op_num, internal_id, Some(point_id.clone()))
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut results = vec![false; point_ids.len()];
+        self.handle_version_and_failure(op_num, None, |segment| {
+            let batch = segment.prepare_points_batch(op_num, point_ids, vectors)?;
+            if batch.is_empty() {
+                return Ok((false, None));
+            }
+            let mut undo = BatchUndo::default();
+            let applied =
+                segment.apply_points_batch(op_num, point_ids, payloads, &batch, &mut undo);
+            if let Err(err) = applied {
+                segment.rollback_points_batch(undo);
+                return Err(err);
+            }
+            for (position, _) in &batch.updated {
+                results[*position] = true;
+            }
+
+            for vector_data in segment.vector_data.values() {
+                let mut vector_index = vector_data.vector_index.write();
+                for (_, internal_id) in &batch.updated {
+                    vector_index.update_vector(*internal_id)?;
+                }
+                for internal_id in batch.inserted_offsets() {
+                    vector_index.update_vector(internal_id)?;
+                }
+            }
+            Ok((false, None))
+        })?;
+        if let Some(skipped) = skipped {
+            for (point_id, skipped) in point_ids.iter().zip(skipped) {
+                self.notify_write(WriteEvent {
+                    kind: WriteOperationKind::UpsertPoint,
+                    point_id: Some(point_id.clone()),
+                    op_num,
+                    skipped,
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    /// Check and preprocess the vectors of the batch, nothing is written yet
+    fn prepare_points_batch(
+        &self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+    ) -> OperationResult<PointsBatch> {
+        let mut batch = PointsBatch::default();
+        // Last occurrence of a duplicated point wins, so the point is never appended twice
+        let last_positions: HashMap<&PointIdType, usize> = point_ids
+            .iter()
+            .enumerate()
+            .map(|(position, point_id)| (point_id, position))
+            .collect();
+        {
+            let id_tracker = self.id_tracker.read();
+            for (position, point_id) in point_ids.iter().enumerate() {
+                if last_positions[point_id] != position {
+                    continue;
+                }
+                match id_tracker.internal_id(point_id.clone()) {
+                    Some(internal_id) => {
+                        if id_tracker
+                            .internal_version(internal_id)
+                            .map_or(false, |current_version| current_version > op_num)
+                        {
+                            continue;
+                        }
+                        batch.updated.push((position, internal_id));
+                    }
+                    None => {
+                        if id_tracker
+                            .deleted_version(point_id.clone())
+                            .map_or(false, |deleted_version| deleted_version > op_num)
+                        {
+                            // Point was deleted by a later operation, do not resurrect it
+                            continue;
+                        }
+                        batch.inserted.push(position);
+                    }
+                }
+            }
+        }
+
+        let vector_dims: HashMap<&str, usize> = self
+            .vector_data
+            .iter()
+            .map(|(vector_name, vector_data)| {
+                (
+                    vector_name.as_str(),
+                    vector_data.vector_storage.read().vector_dim(),
+                )
+            })
+            .collect();
+        let positions = batch
+            .updated
+            .iter()
+            .map(|(position, _)| *position)
+            .chain(batch.inserted.iter().copied());
+        for position in positions {
+            for (vector_name, vector) in vectors[position].iter() {
+                let vector_dim = vector_dims[vector_name];
+                if vector_dim != vector.len() {
+                    return Err(OperationError::WrongVector {
+                        expected_dim: vector_dim,
+                        received_dim: vector.len(),
+                    });
+                }
+                let processed_vector = self.segment_config.vector_data[vector_name]
+                    .distance
+                    .preprocess_vector(vector)
+                    .unwrap_or_else(|| vector.to_vec());
+                batch
+                    .vectors
+                    .entry(vector_name.to_owned())
+                    .or_insert_with(|| Vec::with_capacity(point_ids.len()))
+                    .push(processed_vector);
+            }
+        }
+
+        // Skip vectors, left without links by an interrupted insertion
+        let start_offset = self
+            .vector_data
+            .values()
+            .map(|vector_data| vector_data.vector_storage.read().total_vector_count())
+            .fold(self.id_tracker.read().internal_size(), max);
+        // Whole batch is rejected, if the inserted points don't fit into the segment
+        check_points_capacity(start_offset + batch.inserted.len())?;
+        batch.start_offset = start_offset as PointOffsetType;
+        Ok(batch)
+    }
+
+    /// Write the prepared batch, everything written is recorded in `undo`
+    fn apply_points_batch(
+        &self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        payloads: Option<&[Option<&Payload>]>,
+        batch: &PointsBatch,
+        undo: &mut BatchUndo,
+    ) -> OperationResult<()> {
+        for (vector_name, processed_vectors) in &batch.vectors {
+            let (updated_vectors, inserted_vectors) =
+                processed_vectors.split_at(batch.updated.len());
+            let mut vector_storage = self.vector_data[vector_name].vector_storage.write();
+            for ((_, internal_id), vector) in batch.updated.iter().zip(updated_vectors) {
+                let previous_vector = vector_storage.get_vector(*internal_id).to_vec();
+                vector_storage.insert_vector(*internal_id, vector)?;
+                undo.vectors
+                    .push((vector_name.clone(), *internal_id, previous_vector));
+            }
+            if inserted_vectors.is_empty() {
+                continue;
+            }
+            if (vector_storage.total_vector_count() as PointOffsetType) < batch.start_offset {
+                // Align storage with the longest one, padding is never linked
+                let padding: Vec<VectorElementType> = vec![0.0; vector_storage.vector_dim()];
+                vector_storage.insert_vector(batch.start_offset - 1, &padding)?;
+            }
+            let offsets = vector_storage.put_vectors(inserted_vectors)?;
+            debug_assert_eq!(offsets.start, batch.start_offset);
+        }
+
+        {
+            let mut id_tracker = self.id_tracker.write();
+            let inserted_ids: Vec<_> = batch
+                .inserted
+                .iter()
+                .map(|position| point_ids[*position].clone())
+                .collect();
+            // Links, set before a failure, are dropped by the rollback
+            undo.linked = inserted_ids.clone();
+            id_tracker.set_links_range(&inserted_ids, batch.start_offset)?;
+        }
+
+        if let Some(payloads) = payloads {
+            let mut payload_index = self.payload_index.write();
+            let positions = batch
+                .updated
+                .iter()
+                .copied()
+                .chain(batch.inserted.iter().copied().zip(batch.inserted_offsets()));
+            for (position, internal_id) in positions {
+                if let Some(payload) = payloads[position] {
+                    let previous_payload = payload_index.drop(internal_id)?;
+                    undo.payloads.push((internal_id, previous_payload));
+                    payload_index.assign(internal_id, payload)?;
+                }
+            }
+        }
+
+        let mut id_tracker = self.id_tracker.write();
+        for (_, internal_id) in &batch.updated {
+            let previous_version = id_tracker.internal_version(*internal_id);
+            id_tracker.set_internal_version(*internal_id, op_num)?;
+            undo.versions.push((*internal_id, previous_version));
+        }
+        for internal_id in batch.inserted_offsets() {
+            id_tracker.set_internal_version(internal_id, op_num)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the state of the segment before the failed batch, on the best-effort basis
+    fn rollback_points_batch(&self, undo: BatchUndo) {
+        let log_error = |result: OperationResult<()>| {
+            if let Err(err) = result {
+                log::error!(
+                    "Failed to roll back the batch in segment {}: {err}",
+                    self.current_path.display()
+                );
+            }
+        };
+        {
+            let mut id_tracker = self.id_tracker.write();
+            for (internal_id, previous_version) in undo.versions.into_iter().rev() {
+                if let Some(previous_version) = previous_version {
+                    log_error(id_tracker.set_internal_version(internal_id, previous_version));
+                }
+            }
+        }
+        {
+            let mut payload_index = self.payload_index.write();
+            for (internal_id, previous_payload) in undo.payloads.into_iter().rev() {
+                log_error(payload_index.drop(internal_id).map(|_| ()));
+                if let Some(previous_payload) = previous_payload {
+                    log_error(payload_index.assign(internal_id, &previous_payload));
+                }
+            }
+        }
+        {
+            // Appended vectors without links are treated as deleted
+            let mut id_tracker = self.id_tracker.write();
+            for point_id in undo.linked {
+                if id_tracker.internal_id(point_id.clone()).is_some() {
+                    log_error(id_tracker.drop(point_id));
+                }
+            }
+        }
+        for (vector_name, internal_id, previous_vector) in undo.vectors.into_iter().rev() {
+            let mut vector_storage = self.vector_data[&vector_name].vector_storage.write();
+            log_error(vector_storage.insert_vector(internal_id, &previous_vector));
+        }
+    }
+
+    /// Walk the storages of the segment for [`SegmentStats`]
+    fn compute_stats(&self) -> SegmentStats {
+        let payload_index = self.payload_index.read();
+        let index_schema = payload_index
+            .indexed_fields()
+            .into_iter()
+            .map(|(key, index_schema)| {
+                let points_count = payload_index.indexed_points(&key);
+                (key, PayloadIndexInfo::new(index_schema, points_count))
+            })
+            .collect();
+
+        let mut ram_usage_bytes = 0;
+        if self.segment_config.storage_type == StorageType::InMemory {
+            for vector_data in self.vector_data.values() {
+                let vector_storage = vector_data.vector_storage.read();
+                ram_usage_bytes += vector_storage.total_vector_count()
+                    * vector_storage.vector_dim()
+                    * VECTOR_ELEMENT_SIZE;
+            }
+        }
+        if self.segment_config.payload_storage_type == PayloadStorageType::InMemory {
+            for internal_id in self.id_tracker.read().iter_ids() {
+                // Payload, which fails to load, is reported by the reads, not by the stats
+                if let Ok(payload) = payload_index.payload(internal_id) {
+                    if !payload.is_empty() {
+                        ram_usage_bytes +=
+                            serde_json::to_vec(&payload).map_or(0, |json| json.len());
+                    }
+                }
+            }
+        }
+
+        SegmentStats {
+            ram_usage_bytes,
+            disk_usage_bytes: files_size(&self.current_path),
+            index_schema,
+        }
+    }
+
+    /// Name of the segment directory, which names the snapshots of the segment
+    fn segment_id(&self) -> OperationResult<&str> {
+        self.current_path
+            .file_stem()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| {
+                OperationError::service_error(format!(
+                    "Segment path {} has no valid name",
+                    self.current_path.display()
+                ))
+            })
+    }
+
+    /// Segment, opened from the snapshot archive, rejects all updates
+    fn check_appendable(&self) -> OperationResult<()> {
+        if self.archive_cache.is_some() {
+            return Err(OperationError::SegmentNotAppendable {
+                path: self.current_path.clone(),
+            });
         }
         Ok(())
     }
@@ -145,7 +717,18 @@ impl Segment {
             } // else: Re-try operation
         }
 
+        self.check_appendable()?;
+        if self.read_only {
+            return Err(OperationError::service_error(format!(
+                "Segment {} is loaded read-only, it is updated after the rebuild",
+                self.current_path.display()
+            )));
+        }
+
+        let update_lock = self.update_lock.clone();
+        let update_guard = update_lock.write();
         let res = self.handle_version(op_num, op_point_offset, operation);
+        drop(update_guard);
 
         match get_service_error(&res) {
             None => {
@@ -154,7 +737,7 @@ impl Segment {
                     None => {} // all good
                     Some(error) => {
                         let point_id = op_point_offset.and_then(|point_offset| {
-                            self.id_tracker.borrow().external_id(point_offset)
+                            self.id_tracker.read().external_id(point_offset)
                         });
                         if error.point_id == point_id {
                             // Fixed
@@ -172,7 +755,7 @@ impl Segment {
                     error
                 );
                 let point_id = op_point_offset
-                    .and_then(|point_offset| self.id_tracker.borrow().external_id(point_offset));
+                    .and_then(|point_offset| self.id_tracker.read().external_id(point_offset));
                 self.error_status = Some(SegmentFailedState {
                     version: op_num,
                     point_id,
@@ -180,9 +763,84 @@ impl Segment {
                 });
             }
         }
+
+        let flush_due = res.is_ok()
+            && self
+                .flush_scheduler
+                .as_ref()
+                .map_or(false, |scheduler| scheduler.operation_applied());
+        if flush_due {
+            self.flush(true)?;
+        }
         res
     }
 
+    /// Apply the operation with [`Segment::handle_version_and_failure`], and report it to the write hook
+    ///
+    /// Without the hook, the operation is applied as is.
+    fn apply_write<F>(
+        &mut self,
+        kind: WriteOperationKind,
+        point_id: Option<PointIdType>,
+        op_num: SeqNumberType,
+        op_point_offset: Option<PointOffsetType>,
+        operation: F,
+    ) -> OperationResult<bool>
+    where
+        F: FnOnce(&mut Segment) -> OperationResult<(bool, Option<PointOffsetType>)>,
+    {
+        if self.write_hook.is_none() {
+            return self.handle_version_and_failure(op_num, op_point_offset, operation);
+        }
+        let skipped = self.is_skipped_by_version(op_num, op_point_offset, point_id.clone());
+        let updated = self.handle_version_and_failure(op_num, op_point_offset, operation)?;
+        self.notify_write(WriteEvent {
+            kind,
+            point_id,
+            op_num,
+            skipped,
+        });
+        Ok(updated)
+    }
+
+    /// Operation is older than the version of the point, or of the segment for the operations without the point.
+    /// Upsert of the point, deleted by a later operation, is skipped as well.
+    fn is_skipped_by_version(
+        &self,
+        op_num: SeqNumberType,
+        op_point_offset: Option<PointOffsetType>,
+        point_id: Option<PointIdType>,
+    ) -> bool {
+        let id_tracker = self.id_tracker.read();
+        match op_point_offset {
+            None => {
+                self.version.unwrap_or(0) > op_num
+                    || point_id
+                        .and_then(|point_id| id_tracker.deleted_version(point_id))
+                        .map_or(false, |deleted_version| deleted_version > op_num)
+            }
+            Some(point_offset) => id_tracker
+                .internal_version(point_offset)
+                .map_or(false, |current_version| current_version > op_num),
+        }
+    }
+
+    fn notify_write(&self, event: WriteEvent) {
+        if let Some(hook) = &self.write_hook {
+            call_write_hook(hook, &event);
+        }
+    }
+
+    /// Register the hook, called after each successful write operation of the segment, `None` to remove it
+    ///
+    /// Operations, skipped by version, are reported as well, with `skipped` set. Failed operations are not reported.
+    /// Batch upserts are reported for each point. The hook is called after the operation is completely applied,
+    /// under the exclusive borrow of the segment, so it should be fast, e.g. send the event into a channel.
+    /// Panics of the hook are caught and logged, they don't fail the operation.
+    pub fn set_on_write_hook(&mut self, hook: Option<WriteHook>) {
+        self.write_hook = hook;
+    }
+
     /// Manage segment version checking
     /// If current version if higher than operation version - do not perform the operation
     /// Update current version if operation successfully executed
@@ -199,6 +857,12 @@ impl Segment {
             None => {
                 // Not a point operation, use global version to check if already applied
                 if self.version.unwrap_or(0) > op_num {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        op_num,
+                        version = self.version,
+                        "operation is skipped by the segment version"
+                    );
                     return Ok(false); // Skip without execution
                 }
             }
@@ -206,10 +870,16 @@ impl Segment {
                 // Check if point not exists or have lower version
                 if self
                     .id_tracker
-                    .borrow()
+                    .read()
                     .internal_version(point_offset)
                     .map_or(false, |current_version| current_version > op_num)
                 {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        op_num,
+                        point_offset,
+                        "operation is skipped by the point version"
+                    );
                     return Ok(false);
                 }
             }
@@ -217,11 +887,15 @@ impl Segment {
 
         let res = operation(self);
 
+        // Any update might change points, matched by cached filters, and the stats of the segment
+        self.payload_index.read().invalidate_filter_cache();
+        self.stats_cache.invalidate();
+
         if res.is_ok() {
             self.version = Some(max(op_num, self.version.unwrap_or(0)));
             if let Ok((_, Some(point_id))) = res {
                 self.id_tracker
-                    .borrow_mut()
+                    .write()
                     .set_internal_version(point_id, op_num)?;
             }
         }
@@ -229,7 +903,7 @@ impl Segment {
     }
 
     fn lookup_internal_id(&self, point_id: PointIdType) -> OperationResult<PointOffsetType> {
-        let internal_id_opt = self.id_tracker.borrow().internal_id(point_id);
+        let internal_id_opt = self.id_tracker.read().internal_id(point_id.clone());
         match internal_id_opt {
             Some(internal_id) => Ok(internal_id),
             None => Err(OperationError::PointIdError {
@@ -242,1451 +916,6018 @@ impl Segment {
         SegmentState {
             version: self.version,
             config: self.segment_config.clone(),
+            pending_index: self.pending_index,
+            contents: self.contents,
+            unknown_fields: self.unknown_state_fields.clone(),
         }
     }
 
-    pub fn save_state(state: &SegmentState, current_path: &Path) -> OperationResult<()> {
-        let state_path = current_path.join(SEGMENT_STATE_FILE);
-        Ok(atomic_save_json(&state_path, state)?)
-    }
-
-    pub fn load_state(current_path: &Path) -> OperationResult<SegmentState> {
-        let state_path = current_path.join(SEGMENT_STATE_FILE);
-        Ok(read_json(&state_path)?)
+    /// Check that the parts of the data, required by the operation, are present in the segment
+    fn check_contents(&self, vectors: bool, payload: bool) -> OperationResult<()> {
+        let missing = if vectors && !self.contents.vectors {
+            "vectors"
+        } else if payload && !self.contents.payload {
+            "payload"
+        } else {
+            return Ok(());
+        };
+        Err(OperationError::service_error(format!(
+            "segment {} is restored from a partial snapshot without {missing}",
+            self.current_path.display()
+        )))
     }
 
-    /// Retrieve vector by internal ID
+    /// Build the vector indexes, which were not included in the snapshot, the segment is restored from
     ///
-    /// Returns None if the vector does not exists or deleted
-    #[inline]
-    fn vector_by_offset(
-        &self,
-        vector_name: &str,
-        point_offset: PointOffsetType,
-    ) -> OperationResult<Option<Vec<VectorElementType>>> {
-        check_vector_name(vector_name, &self.segment_config)?;
-        let vector_data = &self.vector_data[vector_name];
-        if !self.id_tracker.borrow().is_deleted(point_offset) {
-            Ok(Some(
-                vector_data
-                    .vector_storage
-                    .borrow()
-                    .get_vector(point_offset)
-                    .to_vec(),
-            ))
-        } else {
-            Ok(None)
+    /// Payload indexes are built, when the segment is loaded.
+    pub(crate) fn build_missing_indexes(&mut self) -> OperationResult<()> {
+        let stopped = AtomicBool::new(false);
+        for vector_data in self.vector_data.values() {
+            vector_data.vector_index.write().build_index(&stopped)?;
         }
+        self.contents.index = true;
+        self.save_current_state()?;
+        save_checksums(&self.current_path, &self.checksum_files())
     }
 
-    fn all_vectors_by_offset(
-        &self,
-        point_offset: PointOffsetType,
-    ) -> OperationResult<NamedVectors> {
-        let mut vectors = NamedVectors::default();
-        for (vector_name, vector_data) in &self.vector_data {
-            vectors.insert(
-                vector_name.clone(),
-                vector_data
-                    .vector_storage
-                    .borrow()
-                    .get_vector(point_offset)
-                    .to_vec(),
-            );
-        }
-        Ok(vectors)
+    /// Upsert the points of the JSON Lines, written by [`SegmentEntry::export_jsonl`]
+    ///
+    /// See [`Segment::import_jsonl_with_error_budget`], up to [`DEFAULT_IMPORT_ERROR_BUDGET`] malformed lines are skipped.
+    pub fn import_jsonl(
+        &mut self,
+        reader: &mut dyn BufRead,
+        op_num_start: SeqNumberType,
+    ) -> OperationResult<ImportReport> {
+        self.import_jsonl_with_error_budget(reader, op_num_start, DEFAULT_IMPORT_ERROR_BUDGET)
     }
 
-    /// Retrieve payload by internal ID
-    #[inline]
-    fn payload_by_offset(&self, point_offset: PointOffsetType) -> OperationResult<Payload> {
-        self.payload_index.borrow().payload(point_offset)
-    }
-
-    pub fn save_current_state(&self) -> OperationResult<()> {
-        Self::save_state(&self.get_state(), &self.current_path)
-    }
+    /// Upsert the points of the JSON Lines in batches, each batch is applied with the next op num from `op_num_start`
+    ///
+    /// Points replace the existing ones with the same id, along with their payload.
+    /// Malformed lines, e.g. with vectors of the wrong dimension, are skipped and reported,
+    /// until there are more of them than the `error_budget`. Points before the abort are imported.
+    pub fn import_jsonl_with_error_budget(
+        &mut self,
+        reader: &mut dyn BufRead,
+        op_num_start: SeqNumberType,
+        error_budget: usize,
+    ) -> OperationResult<ImportReport> {
+        if !self.is_appendable() {
+            return Err(OperationError::service_error(format!(
+                "Points can't be imported into non-appendable segment {}",
+                self.current_path.display()
+            )));
+        }
 
-    fn infer_from_payload_data(
-        &self,
-        key: PayloadKeyTypeRef,
-    ) -> OperationResult<Option<PayloadSchemaType>> {
-        let payload_index = self.payload_index.borrow();
-        payload_index.infer_payload_type(key)
+        let mut report = ImportReport {
+            next_op_num: op_num_start,
+            ..Default::default()
+        };
+        let mut batch = ImportBatch::default();
+        for line in read_points(reader) {
+            let (line_number, point) = line?;
+            let point = point
+                .map_err(|err| err.to_string())
+                .and_then(|point| self.validate_import_point(point));
+            match point {
+                Ok((point_id, vectors, payload)) => {
+                    // Repeated id is upserted by the next batch, so it is not inserted twice
+                    if batch.ids.len() >= IMPORT_BATCH_SIZE || batch.ids.contains(&point_id) {
+                        self.import_batch(&mut batch, &mut report)?;
+                    }
+                    batch.ids.push(point_id);
+                    batch.vectors.push(vectors);
+                    batch.payloads.push(payload);
+                }
+                Err(error) => {
+                    report.errors.push(ImportLineError {
+                        line: line_number,
+                        error,
+                    });
+                    if report.errors.len() > error_budget {
+                        report.aborted = true;
+                        break;
+                    }
+                }
+            }
+        }
+        self.import_batch(&mut batch, &mut report)?;
+        Ok(report)
     }
 
-    pub fn restore_snapshot(snapshot_path: &Path, segment_id: &str) -> OperationResult<()> {
-        let segment_path = snapshot_path.parent().unwrap().join(segment_id);
-
-        let archive_file = File::open(snapshot_path).map_err(|err| {
-            OperationError::service_error(format!(
-                "failed to open segment snapshot archive {snapshot_path:?}: {err}"
-            ))
-        })?;
-
-        tar::Archive::new(archive_file)
-            .unpack(&segment_path)
-            .map_err(|err| {
-                OperationError::service_error(format!(
-                    "failed to unpack segment snapshot archive {snapshot_path:?}: {err}"
+    /// Upsert the vectors of the `.npy` matrix with the `op_num`, the rows are streamed in batches
+    ///
+    /// Points get the ids from the `ids_path` file, one per line, or the sequential ones from `first_id`.
+    /// Segment must have a single vector of the dimension of the rows. Returns the number of imported points.
+    pub fn import_npy(
+        &mut self,
+        vectors_path: &Path,
+        ids_path: Option<&Path>,
+        first_id: u64,
+        op_num: SeqNumberType,
+    ) -> OperationResult<usize> {
+        if !self.is_appendable() {
+            return Err(OperationError::service_error(format!(
+                "Points can't be imported into non-appendable segment {}",
+                self.current_path.display()
+            )));
+        }
+        let (vector_name, vector_config) = match self.segment_config.vector_data.iter().next() {
+            Some((vector_name, vector_config)) if self.segment_config.vector_data.len() == 1 => {
+                (vector_name.clone(), vector_config.clone())
+            }
+            _ => {
+                return Err(OperationError::service_error(
+                    "Vectors of .npy matrix are imported only into segment with a single vector",
                 ))
-            })?;
-
-        let snapshot_path = segment_path.join(SNAPSHOT_PATH);
-
-        if snapshot_path.exists() {
-            let db_backup_path = snapshot_path.join(DB_BACKUP_PATH);
-            let payload_index_db_backup = snapshot_path.join(PAYLOAD_DB_BACKUP_PATH);
-
-            crate::rocksdb_backup::restore(&db_backup_path, &segment_path)?;
-
-            if payload_index_db_backup.is_dir() {
-                StructPayloadIndex::restore_database_snapshot(
-                    &payload_index_db_backup,
-                    &segment_path,
-                )?;
             }
+        };
 
-            let files_path = snapshot_path.join(SNAPSHOT_FILES_PATH);
-            utils::fs::move_all(&files_path, &segment_path)?;
+        let mut reader = NpyReader::open(vectors_path)?;
+        let header = *reader.header();
+        if header.cols != vector_config.size {
+            return Err(OperationError::WrongVector {
+                expected_dim: vector_config.size,
+                received_dim: header.cols,
+            });
+        }
 
-            fs::remove_dir_all(&snapshot_path).map_err(|err| {
+        let mut ids: Box<dyn Iterator<Item = OperationResult<PointIdType>> + '_> = match ids_path {
+            Some(ids_path) => Box::new(
+                BufReader::new(File::open(ids_path)?)
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+                    .map(move |(index, line)| {
+                        let line = line?;
+                        // Numbers in the file are numeric ids, not strings
+                        let id = line.trim();
+                        id.parse::<u64>().map(PointIdType::from).or_else(|_| {
+                            id.parse().map_err(|()| {
+                                OperationError::service_error(format!(
+                                    "Invalid point id {line:?} at line {} of {ids_path:?}",
+                                    index + 1
+                                ))
+                            })
+                        })
+                    }),
+            ),
+            None => Box::new((first_id..).map(|point_id| Ok(point_id.into()))),
+        };
+
+        let mut imported = 0;
+        let mut batch_ids = Vec::with_capacity(IMPORT_BATCH_SIZE);
+        let mut batch_vectors = Vec::with_capacity(IMPORT_BATCH_SIZE);
+        while let Some(row) = reader.read_row()? {
+            let point_id = ids.next().transpose()?.ok_or_else(|| {
                 OperationError::service_error(format!(
-                    "failed to remove {snapshot_path:?} directory: {err}"
+                    "Ids file has fewer ids than {} rows of {vectors_path:?}",
+                    header.rows
                 ))
             })?;
-        } else {
-            log::info!("Attempt to restore legacy snapshot format");
-            // Do nothing, legacy format is just plain archive
+            // Repeated id is upserted by the next batch, so it is not inserted twice
+            if batch_ids.len() >= IMPORT_BATCH_SIZE || batch_ids.contains(&point_id) {
+                self.upsert_vectors(op_num, &batch_ids, &batch_vectors)?;
+                imported += batch_ids.len();
+                batch_ids.clear();
+                batch_vectors.clear();
+            }
+            batch_ids.push(point_id);
+            batch_vectors.push(NamedVectors::from([(vector_name.clone(), row)]));
         }
-
-        Ok(())
-    }
-
-    // Joins flush thread if exists
-    // Returns lock to guarantee that there will be no other flush in a different thread
-    fn lock_flushing(
-        &self,
-    ) -> OperationResult<parking_lot::MutexGuard<Option<JoinHandle<OperationResult<SeqNumberType>>>>>
-    {
-        let mut lock = self.flush_thread.lock();
-        let mut join_handle: Option<JoinHandle<OperationResult<SeqNumberType>>> = None;
-        std::mem::swap(&mut join_handle, &mut lock);
-        if let Some(join_handle) = join_handle {
-            // Flush result was reported to segment, so we don't need this value anymore
-            let _background_flush_result = join_handle
-                .join()
-                .map_err(|_err| OperationError::service_error("failed to join flush thread"))??;
+        if !batch_ids.is_empty() {
+            self.upsert_vectors(op_num, &batch_ids, &batch_vectors)?;
+            imported += batch_ids.len();
         }
-        Ok(lock)
-    }
-
-    fn is_background_flushing(&self) -> bool {
-        let lock = self.flush_thread.lock();
-        if let Some(join_handle) = lock.as_ref() {
-            !join_handle.is_finished()
-        } else {
-            false
+        if ids.next().is_some() && ids_path.is_some() {
+            log::warn!(
+                "Ids file has more ids than {} rows of {vectors_path:?}",
+                header.rows
+            );
         }
+        Ok(imported)
     }
 
-    /// Converts raw ScoredPointOffset search result into ScoredPoint result
-    fn process_search_result(
+    /// Call `f` with each point, which passes the filter, in the order of the ids. Returns the number of points
+    fn for_each_export_point(
         &self,
-        internal_result: &[ScoredPointOffset],
-        with_payload: &WithPayload,
-        with_vector: &WithVector,
-    ) -> OperationResult<Vec<ScoredPoint>> {
-        let id_tracker = self.id_tracker.borrow();
-        internal_result
-            .iter()
-            .filter_map(|&scored_point_offset| {
-                let point_offset = scored_point_offset.idx;
-                let external_id = id_tracker.external_id(point_offset);
-                match external_id {
-                    Some(point_id) => Some((point_id, scored_point_offset)),
-                    None => {
-                        log::warn!(
-                            "Point with internal ID {} not found in id tracker, skipping",
-                            point_offset
-                        );
-                        None
-                    }
+        with_vectors: bool,
+        filter: Option<&Filter>,
+        mut f: impl FnMut(PointIdType, Option<NamedVectors>, Payload) -> OperationResult<()>,
+    ) -> OperationResult<usize> {
+        self.check_contents(with_vectors, true)?;
+        let condition = filter.map(|filter| self.prepare_filter(filter));
+        let payload_index = self.payload_index.read();
+        let filter_context = condition
+            .as_ref()
+            .map(|condition| payload_index.filter_context(condition));
+
+        let mut exported = 0;
+        for (external_id, internal_id) in self.id_tracker.read().iter_from(None) {
+            if let Some(filter_context) = &filter_context {
+                if !filter_context.check(internal_id) {
+                    continue;
                 }
-            })
-            .map(|(point_id, scored_point_offset)| {
-                let point_offset = scored_point_offset.idx;
-                let point_version = id_tracker.internal_version(point_offset).ok_or_else(|| {
-                    OperationError::service_error(format!(
-                        "Corrupter id_tracker, no version for point {point_id}"
-                    ))
-                })?;
-                let payload = if with_payload.enable {
-                    let initial_payload = self.payload_by_offset(point_offset)?;
-                    let processed_payload = if let Some(i) = &with_payload.payload_selector {
-                        i.process(initial_payload)
-                    } else {
-                        initial_payload
-                    };
-                    Some(processed_payload)
-                } else {
-                    None
-                };
-                let vector = match with_vector {
-                    WithVector::Bool(false) => None,
-                    WithVector::Bool(true) => {
-                        Some(self.all_vectors_by_offset(point_offset)?.into())
-                    }
-                    WithVector::Selector(vectors) => {
-                        let mut result = NamedVectors::default();
-                        for vector_name in vectors {
-                            let vector_opt = self.vector_by_offset(vector_name, point_offset)?;
-                            match vector_opt {
-                                None => {
-                                    return Err(OperationError::service_error(
-                                        "Vector {vector_name} not found at offset {point_offset}",
-                                    ))
-                                }
-                                Some(vector) => result.insert(vector_name.clone(), vector),
-                            }
-                        }
-                        Some(result.into())
-                    }
-                };
-
-                Ok(ScoredPoint {
-                    id: point_id,
-                    version: point_version,
-                    score: scored_point_offset.score,
-                    payload,
-                    vector,
-                })
-            })
-            .collect()
+            }
+            let vectors = if with_vectors {
+                Some(self.all_vectors_by_offset(internal_id)?)
+            } else {
+                None
+            };
+            let payload = Payload::from_shared(self.payload_by_offset(internal_id)?);
+            f(external_id, vectors, payload)?;
+            exported += 1;
+        }
+        Ok(exported)
     }
 
-    pub fn filtered_read_by_index(
+    /// Export the points into the Arrow IPC file, see [`ExportTable`] for the columns of the table
+    ///
+    /// Points are written in the record batches of [`ARROW_BATCH_SIZE`]. Returns the number of exported points.
+    #[cfg(feature = "arrow")]
+    pub fn export_arrow(
         &self,
-        offset: Option<PointIdType>,
-        limit: Option<usize>,
-        condition: &Filter,
-    ) -> Vec<PointIdType> {
-        let payload_index = self.payload_index.borrow();
-        let id_tracker = self.id_tracker.borrow();
-
-        let ids_iterator = payload_index
-            .query_points(condition)
-            .filter_map(|internal_id| {
-                let external_id = id_tracker.external_id(internal_id);
-                match external_id {
-                    Some(external_id) => match offset {
-                        Some(offset) if external_id < offset => None,
-                        _ => Some(external_id),
-                    },
-                    None => None,
-                }
-            });
-
-        let mut page = match limit {
-            Some(limit) => peek_top_smallest_iterable(ids_iterator, limit),
-            None => ids_iterator.collect(),
-        };
-        page.sort_unstable();
-        page
+        path: &Path,
+        with_vectors: bool,
+        filter: Option<&Filter>,
+    ) -> OperationResult<usize> {
+        let table = ExportTable::new(
+            &self.segment_config,
+            &self.get_indexed_fields(),
+            with_vectors,
+        );
+        let mut writer = arrow::ipc::writer::FileWriter::try_new(
+            File::create(path)?,
+            &table.empty_batch()?.schema(),
+        )
+        .map_err(arrow_error)?;
+        let exported = self.export_record_batches(&table, filter, |batch| {
+            writer.write(batch).map_err(arrow_error)
+        })?;
+        writer.finish().map_err(arrow_error)?;
+        Ok(exported)
     }
 
-    pub fn filtered_read_by_id_stream(
+    /// Export the points into the Parquet file, see [`ExportTable`] for the columns of the table
+    ///
+    /// Points are written in the record batches of [`ARROW_BATCH_SIZE`]. Returns the number of exported points.
+    #[cfg(feature = "arrow")]
+    pub fn export_parquet(
         &self,
-        offset: Option<PointIdType>,
-        limit: Option<usize>,
-        condition: &Filter,
-    ) -> Vec<PointIdType> {
-        let payload_index = self.payload_index.borrow();
-        let filter_context = payload_index.filter_context(condition);
-        self.id_tracker
-            .borrow()
-            .iter_from(offset)
-            .filter(move |(_, internal_id)| filter_context.check(*internal_id))
-            .map(|(external_id, _)| external_id)
-            .take(limit.unwrap_or(usize::MAX))
-            .collect()
+        path: &Path,
+        with_vectors: bool,
+        filter: Option<&Filter>,
+    ) -> OperationResult<usize> {
+        let table = ExportTable::new(
+            &self.segment_config,
+            &self.get_indexed_fields(),
+            with_vectors,
+        );
+        let mut writer = parquet::arrow::ArrowWriter::try_new(
+            File::create(path)?,
+            table.empty_batch()?.schema(),
+            None,
+        )
+        .map_err(arrow_error)?;
+        let exported = self.export_record_batches(&table, filter, |batch| {
+            writer.write(batch).map_err(arrow_error)
+        })?;
+        writer.close().map_err(arrow_error)?;
+        Ok(exported)
     }
 
-    /// Check consistency of the segment's data and repair it if possible.
-    pub fn check_consistency_and_repair(&mut self) -> OperationResult<()> {
-        let mut internal_ids_to_delete = HashSet::new();
-        let id_tracker = self.id_tracker.borrow();
-        for internal_id in id_tracker.iter_ids() {
-            if id_tracker.external_id(internal_id).is_none() {
-                internal_ids_to_delete.insert(internal_id);
-            }
+    #[cfg(feature = "arrow")]
+    fn export_record_batches(
+        &self,
+        table: &ExportTable,
+        filter: Option<&Filter>,
+        mut write_batch: impl FnMut(&arrow::record_batch::RecordBatch) -> OperationResult<()>,
+    ) -> OperationResult<usize> {
+        let mut batch = table.batch_builder();
+        let no_vectors = NamedVectors::default();
+        let exported =
+            self.for_each_export_point(table.with_vectors(), filter, |id, vectors, payload| {
+                batch.append(id, vectors.as_ref().unwrap_or(&no_vectors), &payload)?;
+                if batch.len() >= ARROW_BATCH_SIZE {
+                    write_batch(&batch.finish()?)?;
+                }
+                Ok(())
+            })?;
+        if !batch.is_empty() {
+            write_batch(&batch.finish()?)?;
         }
+        Ok(exported)
+    }
 
-        if !internal_ids_to_delete.is_empty() {
-            log::info!(
-                "Found {} points in vector storage without external id - those will be deleted",
-                internal_ids_to_delete.len(),
-            );
-            for internal_id in &internal_ids_to_delete {
-                self.payload_index.borrow_mut().drop(*internal_id)?;
+    fn validate_import_point(
+        &self,
+        point: JsonlPoint,
+    ) -> Result<(PointIdType, NamedVectors<'static>, Payload), String> {
+        let vectors = point
+            .vector
+            .ok_or_else(|| format!("point {} has no vector", point.id))?
+            .into_all_vectors();
+        check_vectors_set(&vectors, &self.segment_config).map_err(|err| err.to_string())?;
+        for (vector_name, vector) in vectors.iter() {
+            let expected_dim = self.segment_config.vector_data[vector_name].size;
+            if vector.len() != expected_dim {
+                let err = OperationError::WrongVector {
+                    expected_dim,
+                    received_dim: vector.len(),
+                };
+                return Err(format!(
+                    "vector {vector_name:?} of point {}: {err}",
+                    point.id
+                ));
             }
-
-            // We do not drop version here, because it is already not loaded into memory.
-            // There are no explicit mapping between internal ID and version, so all dangling
-            // versions will be ignored automatically.
-            // Those versions could be overwritten by new points, but it is not a problem.
-            // They will also be deleted by the next optimization.
         }
+        Ok((point.id, vectors, point.payload))
+    }
 
-        // flush entire segment if needed
-        if !internal_ids_to_delete.is_empty() {
-            self.flush(true)?;
+    fn import_batch(
+        &mut self,
+        batch: &mut ImportBatch,
+        report: &mut ImportReport,
+    ) -> OperationResult<()> {
+        if batch.ids.is_empty() {
+            return Ok(());
         }
+        let op_num = report.next_op_num;
+        let payloads: Vec<_> = batch.payloads.iter().map(Some).collect();
+        self.upsert_points(op_num, &batch.ids, &batch.vectors, &payloads)?;
+        report.imported += batch.ids.len();
+        report.next_op_num += 1;
+        *batch = ImportBatch::default();
         Ok(())
     }
-}
 
-/// This is a basic implementation of `SegmentEntry`,
-/// meaning that it implements the _actual_ operations with data and not any kind of proxy or wrapping
-impl SegmentEntry for Segment {
-    fn version(&self) -> SeqNumberType {
-        self.version.unwrap_or(0)
+    /// Flush the segment and release its directory lock, reporting the flush error unlike the drop
+    ///
+    /// Segment, which is dropped without the close, is flushed on the best-effort basis.
+    pub fn close(mut self) -> OperationResult<()> {
+        self.closed = true;
+        let result = if self.read_only {
+            self.lock_flushing().map(|_| ())
+        } else {
+            self.flush(true).map(|_| ())
+        };
+        self.lock.take();
+        result
     }
 
-    fn point_version(&self, point_id: PointIdType) -> Option<SeqNumberType> {
-        let id_tracker = self.id_tracker.borrow();
-        id_tracker
-            .internal_id(point_id)
-            .and_then(|internal_id| id_tracker.internal_version(internal_id))
+    /// Flush the segment, even if no operations were applied since the last flush,
+    /// e.g. after a repair, which doesn't change the version
+    pub(crate) fn force_flush(&self) -> OperationResult<SeqNumberType> {
+        *self.persisted_version.lock() = None;
+        self.flush(true)
     }
 
-    fn search(
-        &self,
-        vector_name: &str,
-        vector: &[VectorElementType],
-        with_payload: &WithPayload,
-        with_vector: &WithVector,
-        filter: Option<&Filter>,
-        top: usize,
-        params: Option<&SearchParams>,
-    ) -> OperationResult<Vec<ScoredPoint>> {
-        check_vector_name(vector_name, &self.segment_config)?;
-        let vector_data = &self.vector_data[vector_name];
-        let expected_vector_dim = vector_data.vector_storage.borrow().vector_dim();
-        if vector.len() != expected_vector_dim {
-            return Err(OperationError::WrongVector {
-                expected_dim: expected_vector_dim,
-                received_dim: vector.len(),
-            });
-        }
+    pub fn save_state(state: &SegmentState, current_path: &Path) -> OperationResult<()> {
+        let state_path = current_path.join(SEGMENT_STATE_FILE);
+        Ok(atomic_save_json(&state_path, state)?)
+    }
 
-        let internal_result =
-            &vector_data
-                .vector_index
-                .borrow()
-                .search(&[vector], filter, top, params)[0];
+    pub fn load_state(current_path: &Path) -> OperationResult<SegmentState> {
+        let state_path = current_path.join(SEGMENT_STATE_FILE);
+        if !state_path.exists() {
+            return Err(OperationError::service_error(format!(
+                "Segment {} can't be loaded, its config {SEGMENT_STATE_FILE} is not found",
+                current_path.display()
+            )));
+        }
+        Ok(read_json(&state_path)?)
+    }
 
-        self.process_search_result(internal_result, with_payload, with_vector)
+    /// Files of the vector and payload indexes and the vector storages, covered by the checksums
+    fn checksum_files(&self) -> Vec<PathBuf> {
+        let mut files = self.payload_index.read().files();
+        for vector_data in self.vector_data.values() {
+            files.extend(vector_data.vector_index.read().files());
+            files.extend(vector_data.vector_storage.read().files());
+        }
+        files
     }
 
-    fn search_batch(
+    /// Retrieve vector by internal ID
+    ///
+    /// Returns None if the vector does not exists or deleted
+    #[inline]
+    fn vector_by_offset(
         &self,
         vector_name: &str,
-        vectors: &[&[VectorElementType]],
-        with_payload: &WithPayload,
-        with_vector: &WithVector,
-        filter: Option<&Filter>,
-        top: usize,
-        params: Option<&SearchParams>,
-    ) -> OperationResult<Vec<Vec<ScoredPoint>>> {
+        point_offset: PointOffsetType,
+    ) -> OperationResult<Option<Vec<VectorElementType>>> {
         check_vector_name(vector_name, &self.segment_config)?;
+        self.check_contents(true, false)?;
         let vector_data = &self.vector_data[vector_name];
-        let expected_vector_dim = vector_data.vector_storage.borrow().vector_dim();
-        for vector in vectors {
-            if vector.len() != expected_vector_dim {
-                return Err(OperationError::WrongVector {
-                    expected_dim: expected_vector_dim,
-                    received_dim: vector.len(),
-                });
-            }
+        if !self.id_tracker.read().is_deleted(point_offset) {
+            Ok(Some(
+                vector_data
+                    .vector_storage
+                    .read()
+                    .get_vector(point_offset)
+                    .to_vec(),
+            ))
+        } else {
+            Ok(None)
         }
+    }
 
-        let internal_results = vector_data
-            .vector_index
-            .borrow()
-            .search(vectors, filter, top, params);
+    fn all_vectors_by_offset(
+        &self,
+        point_offset: PointOffsetType,
+    ) -> OperationResult<NamedVectors> {
+        let mut vectors = NamedVectors::default();
+        for (vector_name, vector_data) in &self.vector_data {
+            vectors.insert(
+                vector_name.clone(),
+                vector_data
+                    .vector_storage
+                    .read()
+                    .get_vector(point_offset)
+                    .to_vec(),
+            );
+        }
+        Ok(vectors)
+    }
 
-        let res = internal_results
-            .iter()
-            .map(|internal_result| {
-                self.process_search_result(internal_result, with_payload, with_vector)
-            })
-            .collect();
+    /// Retrieve payload by internal ID
+    #[inline]
+    fn payload_by_offset(&self, point_offset: PointOffsetType) -> OperationResult<Arc<Payload>> {
+        self.check_contents(false, true)?;
+        self.payload_index.read().payload(point_offset)
+    }
 
-        res
+    fn selected_payload_by_offset(
+        &self,
+        point_offset: PointOffsetType,
+        selection: &PayloadSelection,
+    ) -> OperationResult<Payload> {
+        self.check_contents(false, true)?;
+        self.payload_index
+            .read()
+            .selected_payload(point_offset, selection)
     }
 
-    fn upsert_vector(
-        &mut self,
-        op_num: SeqNumberType,
-        point_id: PointIdType,
-        vectors: &NamedVectors,
-    ) -> OperationResult<bool> {
-        debug_assert!(self.is_appendable());
-        check_vectors_set(vectors, &self.segment_config)?;
-        let stored_internal_point = self.id_tracker.borrow().internal_id(point_id);
-        self.handle_version_and_failure(op_num, stored_internal_point, |segment| {
-            let mut processed_vectors = NamedVectors::default();
-            for (vector_name, vector) in vectors.iter() {
-                let vector_name: &str = vector_name;
-                let vector: &[VectorElementType] = vector;
-                let vector_data = &segment.vector_data[vector_name];
-                let vector_dim = vector_data.vector_storage.borrow().vector_dim();
-                if vector_dim != vector.len() {
-                    return Err(OperationError::WrongVector {
-                        expected_dim: vector_dim,
-                        received_dim: vector.len(),
-                    });
+    /// Check that every payload key, referred by the filter, has an index
+    ///
+    /// Keys of the nested filters are checked as the keys of the array objects, e.g. `items[].color`,
+    /// which is the way they are indexed.
+    fn check_strict_filter(&self, filter: &Filter) -> OperationResult<()> {
+        let payload_index = self.payload_index.read();
+        let indexed_fields = payload_index.indexed_fields();
+
+        // Filters along with the array key of the nested filter, which they belong to
+        let mut filters: Vec<(Option<String>, &Filter)> = vec![(None, filter)];
+        while let Some((array_key, filter)) = filters.pop() {
+            for condition in filter.iter_conditions() {
+                let key = match condition {
+                    Condition::Field(field_condition) => &field_condition.key,
+                    Condition::IsEmpty(is_empty) => &is_empty.is_empty.key,
+                    Condition::IsNull(is_null) => &is_null.is_null.key,
+                    Condition::HasId(_) | Condition::HasVector(_) => continue,
+                    Condition::Nested(nested) => {
+                        let key = match &array_key {
+                            Some(array_key) => format!("{array_key}.{}[]", nested.nested.key),
+                            None => format!("{}[]", nested.nested.key),
+                        };
+                        filters.push((Some(key), &nested.nested.filter));
+                        continue;
+                    }
+                    Condition::Filter(nested) => {
+                        filters.push((array_key.clone(), nested));
+                        continue;
+                    }
+                };
+                let key = match &array_key {
+                    Some(array_key) => format!("{array_key}.{key}"),
+                    None => key.clone(),
+                };
+                if indexed_fields.contains_key(&key) {
+                    continue;
                 }
+                return Err(if payload_index.has_payload_key(&key)? {
+                    OperationError::UnindexedField { field_name: key }
+                } else {
+                    OperationError::UnknownField { field_name: key }
+                });
+            }
+        }
+        Ok(())
+    }
 
-                let processed_vector_opt = segment.segment_config.vector_data[vector_name]
-                    .distance
-                    .preprocess_vector(vector);
-                match processed_vector_opt {
-                    None => processed_vectors.insert_ref(vector_name, vector),
-                    Some(preprocess_vector) => {
-                        processed_vectors.insert(vector_name.to_string(), preprocess_vector)
+    /// Resolve conditions on vectors of the segment and simplify the filter for the payload index
+    fn prepare_filter(&self, filter: &Filter) -> Filter {
+        filter
+            .resolve_has_vector(&|vector_name: &str| self.vector_data.contains_key(vector_name))
+            .simplify()
+    }
+
+    fn explain_filter_by_offset(
+        &self,
+        filter: &Filter,
+        point_offset: PointOffsetType,
+    ) -> OperationResult<FilterExplain> {
+        let payload = self.payload_by_offset(point_offset)?;
+        let id_tracker = self.id_tracker.read();
+        // Not simplified, so the explanation mirrors the structure of the original filter
+        let filter = filter
+            .resolve_has_vector(&|vector_name: &str| self.vector_data.contains_key(vector_name));
+        Ok(explain_payload(
+            || (&payload).into(),
+            &id_tracker,
+            &filter,
+            point_offset,
+        ))
+    }
+
+    /// Cross-check the id tracker against vector and payload storages
+    ///
+    /// Only offsets below the size of the id tracker or of the vector storages are checked
+    /// for payloads, as payload storage can't be iterated.
+    /// With `repair`, mappings without vectors are dropped together with their payloads,
+    /// and payloads of not mapped offsets are dropped. Vectors can't be removed from
+    /// the storages, so vectors without mapping are only reported.
+    /// The check is cancelled by `stopped` before the repair, so the repair is applied completely or not at all.
+    pub fn find_orphans(
+        &self,
+        repair: bool,
+        stopped: Option<&AtomicBool>,
+    ) -> OperationResult<OrphanReport> {
+        let not_stopped = AtomicBool::new(false);
+        let stopped = stopped.unwrap_or(&not_stopped);
+
+        let mut report = OrphanReport::default();
+        let vector_counts = self
+            .vector_data
+            .values()
+            .map(|vector_data| vector_data.vector_storage.read().total_vector_count())
+            .collect::<Vec<_>>();
+        let min_vector_count = vector_counts.iter().copied().min();
+        let max_vector_count = vector_counts.iter().copied().max().unwrap_or(0);
+
+        let mut dangling_mappings = vec![];
+        let mut dangling_payloads = vec![];
+        {
+            let id_tracker = self.id_tracker.read();
+            let payload_index = self.payload_index.read();
+
+            if let Some(min_vector_count) = min_vector_count {
+                for (external_id, internal_id) in id_tracker.iter_from(None) {
+                    check_process_stopped(stopped)?;
+                    if internal_id as usize >= min_vector_count {
+                        report.mapped_without_vectors += 1;
+                        push_sample(&mut report.mapped_without_vectors_ids, external_id.clone());
+                        dangling_mappings.push((external_id, internal_id));
                     }
                 }
             }
 
-            if let Some(existing_internal_id) = stored_internal_point {
-                segment.update_vector(existing_internal_id, processed_vectors)?;
-                Ok((true, Some(existing_internal_id)))
-            } else {
-                let new_index = segment.id_tracker.borrow().internal_size() as PointOffsetType;
-
-                for (vector_name, processed_vector) in processed_vectors {
-                    let vector_name: &str = &vector_name;
-                    segment.vector_data[vector_name]
-                        .vector_storage
-                        .borrow_mut()
-                        .insert_vector(new_index, &processed_vector)?;
+            let internal_size = id_tracker.internal_size();
+            for offset in internal_size..max_vector_count {
+                report.vectors_without_mapping += 1;
+                push_sample(
+                    &mut report.vectors_without_mapping_offsets,
+                    offset as PointOffsetType,
+                );
+            }
+
+            for offset in 0..max(internal_size, max_vector_count) as PointOffsetType {
+                check_process_stopped(stopped)?;
+                if id_tracker.is_deleted(offset) && !payload_index.payload(offset)?.is_empty() {
+                    report.payloads_without_mapping += 1;
+                    push_sample(&mut report.payloads_without_mapping_offsets, offset);
+                    dangling_payloads.push(offset);
                 }
-                segment
-                    .id_tracker
-                    .borrow_mut()
-                    .set_link(point_id, new_index)?;
-                Ok((false, Some(new_index)))
             }
-        })
-    }
+        }
 
-    fn delete_point(
-        &mut self,
-        op_num: SeqNumberType,
-        point_id: PointIdType,
-    ) -> OperationResult<bool> {
-        let internal_id = self.id_tracker.borrow().internal_id(point_id);
-        match internal_id {
-            None => Ok(false), // Point already not exists
-            Some(internal_id) => {
-                self.handle_version_and_failure(op_num, Some(internal_id), |segment| {
-                    segment.payload_index.borrow_mut().drop(internal_id)?;
-                    segment.id_tracker.borrow_mut().drop(point_id)?;
-                    Ok((true, Some(internal_id)))
-                })
+        check_process_stopped(stopped)?;
+        if repair {
+            let mut id_tracker = self.id_tracker.write();
+            let mut payload_index = self.payload_index.write();
+            for (external_id, internal_id) in dangling_mappings {
+                payload_index.drop(internal_id)?;
+                id_tracker.drop(external_id)?;
             }
+            for offset in dangling_payloads {
+                payload_index.drop(offset)?;
+            }
+            report.repaired = true;
+            // Repair bypasses `handle_version`, which invalidates the caches on updates
+            payload_index.invalidate_filter_cache();
+            self.stats_cache.invalidate();
         }
+
+        Ok(report)
     }
 
-    fn set_full_payload(
-        &mut self,
-        op_num: SeqNumberType,
-        point_id: PointIdType,
-        full_payload: &Payload,
-    ) -> OperationResult<bool> {
-        let internal_id = self.id_tracker.borrow().internal_id(point_id);
-        self.handle_version_and_failure(op_num, internal_id, |segment| match internal_id {
-            Some(internal_id) => {
-                segment
-                    .payload_index
-                    .borrow_mut()
-                    .assign_all(internal_id, full_payload)?;
-                Ok((true, Some(internal_id)))
-            }
-            None => Err(OperationError::PointIdError {
-                missed_point_id: point_id,
-            }),
-        })
+    /// Maximal number of vectors in the storages, including deleted ones
+    fn total_vectors_count(&self) -> usize {
+        self.vector_data
+            .values()
+            .map(|vector_data| vector_data.vector_storage.read().total_vector_count())
+            .max()
+            .unwrap_or(0)
     }
 
-    fn set_payload(
-        &mut self,
-        op_num: SeqNumberType,
-        point_id: PointIdType,
-        payload: &Payload,
-    ) -> OperationResult<bool> {
-        let internal_id = self.id_tracker.borrow().internal_id(point_id);
-        self.handle_version_and_failure(op_num, internal_id, |segment| match internal_id {
-            Some(internal_id) => {
-                segment
-                    .payload_index
-                    .borrow_mut()
-                    .assign(internal_id, payload)?;
-                Ok((true, Some(internal_id)))
+    /// Drop vector indexes, built in rebuild paths, keeping the current ones
+    fn remove_rebuilt_indexes(&self) -> OperationResult<()> {
+        for vector_name in self.vector_data.keys() {
+            let rebuild_path = get_vector_index_rebuild_path(&self.current_path, vector_name);
+            if rebuild_path.exists() {
+                fs::remove_dir_all(&rebuild_path)?;
             }
-            None => Err(OperationError::PointIdError {
-                missed_point_id: point_id,
-            }),
-        })
+        }
+        Ok(())
     }
 
-    fn delete_payload(
+    /// Replace vector index of the named vector with the one, built in its rebuild path
+    fn install_vector_index(&mut self, vector_name: &str, index: &Indexes) -> OperationResult<()> {
+        let vector_index_path = get_vector_index_path(&self.current_path, vector_name);
+        let rebuild_path = get_vector_index_rebuild_path(&self.current_path, vector_name);
+        if vector_index_path.exists() {
+            fs::remove_dir_all(&vector_index_path)?;
+        }
+        if rebuild_path.exists() {
+            fs::rename(&rebuild_path, &vector_index_path)?;
+        }
+        let vector_data = &self.vector_data[vector_name];
+        let vector_index = open_vector_index(
+            &vector_index_path,
+            index,
+            &self.segment_config.vector_data[vector_name],
+            self.segment_config.max_search_threads,
+            self.id_tracker.clone(),
+            vector_data.vector_storage.clone(),
+            self.payload_index.clone(),
+        )?;
+        vector_index
+            .write()
+            .set_batch_scorer(self.batch_scorer.clone());
+        self.vector_data
+            .get_mut(vector_name)
+            .ok_or_else(|| {
+                OperationError::inconsistent_storage(format!(
+                    "Vector {vector_name} is removed during the index reload"
+                ))
+            })?
+            .vector_index = vector_index;
+        Ok(())
+    }
+
+    /// Register external scorer in the vector indexes, `None` to score on CPU only
+    ///
+    /// The scorer is kept for the indexes, rebuilt later.
+    pub fn set_batch_scorer(&mut self, batch_scorer: Option<Arc<BatchScorerSS>>) {
+        for vector_data in self.vector_data.values() {
+            vector_data
+                .vector_index
+                .write()
+                .set_batch_scorer(batch_scorer.clone());
+        }
+        self.batch_scorer = batch_scorer;
+    }
+
+    /// Replace vector indexes with the ones, built in rebuild paths, and apply their config
+    fn install_vector_indexes(&mut self, index: Indexes) -> OperationResult<()> {
+        let vector_names: Vec<_> = self.vector_data.keys().cloned().collect();
+        for vector_name in &vector_names {
+            self.install_vector_index(vector_name, &index)?;
+        }
+
+        self.segment_config.index = index;
+        self.pending_index = None;
+        let (segment_type, appendable_flag) = segment_type_of(&self.segment_config);
+        self.segment_type = segment_type;
+        self.appendable_flag = appendable_flag;
+        self.save_current_state()
+    }
+
+    /// Change HNSW config of a single named vector, overriding the config of the segment index
+    ///
+    /// The config is persisted and applied, when the index of the vector is built.
+    /// If `rebuild` is set, only the index of this vector is rebuilt right away,
+    /// indexes of other vectors keep serving searches.
+    pub fn update_vector_index_config(
         &mut self,
         op_num: SeqNumberType,
-        point_id: PointIdType,
-        key: PayloadKeyTypeRef,
+        vector_name: &str,
+        hnsw_config: Option<HnswConfig>,
+        rebuild: bool,
     ) -> OperationResult<bool> {
-        let internal_id = self.id_tracker.borrow().internal_id(point_id);
-        self.handle_version_and_failure(op_num, internal_id, |segment| match internal_id {
-            Some(internal_id) => {
-                segment
-                    .payload_index
-                    .borrow_mut()
-                    .delete(internal_id, key)?;
-                Ok((true, Some(internal_id)))
-            }
-            None => Err(OperationError::PointIdError {
-                missed_point_id: point_id,
-            }),
-        })
+        check_vector_name(vector_name, &self.segment_config)?;
+        let updated = self.apply_write(
+            WriteOperationKind::UpdateVectorIndexConfig,
+            None,
+            op_num,
+            None,
+            |segment| {
+                if let Some(vector_config) = segment.segment_config.vector_data.get_mut(vector_name)
+                {
+                    vector_config.hnsw_config = hnsw_config;
+                }
+                segment.save_current_state()?;
+                Ok((true, None))
+            },
+        )?;
+        if updated && rebuild {
+            self.rebuild_vector_index(op_num, vector_name, None)?;
+        }
+        Ok(updated)
     }
 
-    fn clear_payload(
+    /// Rebuild the index of a single named vector, with the current config of the segment index
+    ///
+    /// Pending config of the segment index is not applied. The current index of the vector is
+    /// replaced only after the new one is built.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, stopped), err)
+    )]
+    pub fn rebuild_vector_index(
         &mut self,
         op_num: SeqNumberType,
-        point_id: PointIdType,
+        vector_name: &str,
+        stopped: Option<&AtomicBool>,
     ) -> OperationResult<bool> {
-        let internal_id = self.id_tracker.borrow().internal_id(point_id);
-        self.handle_version_and_failure(op_num, internal_id, |segment| match internal_id {
-            Some(internal_id) => {
-                segment.payload_index.borrow_mut().drop(internal_id)?;
-                Ok((true, Some(internal_id)))
-            }
-            None => Err(OperationError::PointIdError {
-                missed_point_id: point_id,
-            }),
-        })
+        check_vector_name(vector_name, &self.segment_config)?;
+        let not_stopped = AtomicBool::new(false);
+        let stopped = stopped.unwrap_or(&not_stopped);
+        let index = self.segment_config.index;
+
+        self.apply_write(
+            WriteOperationKind::RebuildVectorIndex,
+            None,
+            op_num,
+            None,
+            |segment| {
+                let rebuild_path =
+                    get_vector_index_rebuild_path(&segment.current_path, vector_name);
+                if rebuild_path.exists() {
+                    fs::remove_dir_all(&rebuild_path)?;
+                }
+                let build_result = open_vector_index(
+                    &rebuild_path,
+                    &index,
+                    &segment.segment_config.vector_data[vector_name],
+                    segment.segment_config.max_search_threads,
+                    segment.id_tracker.clone(),
+                    segment.vector_data[vector_name].vector_storage.clone(),
+                    segment.payload_index.clone(),
+                )
+                .and_then(|vector_index| vector_index.write().build_index(stopped));
+
+                if let Err(err) = build_result {
+                    if rebuild_path.exists() {
+                        fs::remove_dir_all(&rebuild_path)?;
+                    }
+                    return Err(err);
+                }
+
+                segment.install_vector_index(vector_name, &index)?;
+                Ok((true, None))
+            },
+        )
     }
 
-    fn vector(
-        &self,
-        vector_name: &str,
-        point_id: PointIdType,
-    ) -> OperationResult<Vec<VectorElementType>> {
-        let internal_id = self.lookup_internal_id(point_id)?;
-        let vector_opt = self.vector_by_offset(vector_name, internal_id)?;
-        if let Some(vector) = vector_opt {
-            Ok(vector)
-        } else {
-            let segment_path = self.current_path.display();
-            Err(OperationError::service_error(format!(
-                "Vector {vector_name} not found at offset {internal_id} for point {point_id}, segment {segment_path}",
-            )))
+    /// State of the vector index of the named vector
+    fn vector_index_info(&self, vector_name: &str) -> VectorIndexInfo {
+        let hnsw_config = match self.segment_config.index {
+            Indexes::Hnsw(hnsw_config) => Some(
+                self.segment_config.vector_data[vector_name]
+                    .hnsw_config
+                    .unwrap_or(hnsw_config),
+            ),
+            Indexes::Plain {} | Indexes::Ivf(_) => None,
+        };
+        let (index_type, is_built, build_memory_estimate) = match &*self.vector_data[vector_name]
+            .vector_index
+            .read()
+        {
+            VectorIndexEnum::Plain(_) => (VectorIndexType::Plain, true, None),
+            VectorIndexEnum::HnswRam(index) => (
+                VectorIndexType::Hnsw,
+                index.graph().is_some(),
+                Some(index.estimate_build_memory()),
+            ),
+            VectorIndexEnum::HnswMmap(index) => (
+                VectorIndexType::Hnsw,
+                index.graph().is_some(),
+                Some(index.estimate_build_memory()),
+            ),
+            VectorIndexEnum::Ivf(index) => (VectorIndexType::Ivf, index.lists().is_some(), None),
+        };
+        VectorIndexInfo {
+            index_type,
+            is_built,
+            hnsw_config,
+            build_memory_estimate,
         }
     }
 
-    fn all_vectors(&self, point_id: PointIdType) -> OperationResult<NamedVectors> {
-        let mut result = NamedVectors::default();
-        for vector_name in self.vector_data.keys() {
-            result.insert(vector_name.clone(), self.vector(vector_name, point_id)?);
+    /// Pending vector index should be built, as segment has reached its indexing threshold
+    pub fn index_required(&self) -> bool {
+        if self.pending_index.is_none() || !self.contents.vectors {
+            return false;
+        }
+        match self.segment_config.indexing_threshold {
+            None => true,
+            Some(indexing_threshold) => {
+                let max_dim = self
+                    .segment_config
+                    .vector_data
+                    .values()
+                    .map(|vector_config| vector_config.size)
+                    .max()
+                    .unwrap_or(0);
+                let vectors_size = self.points_count() * max_dim * VECTOR_ELEMENT_SIZE;
+                vectors_size >= indexing_threshold.saturating_mul(BYTES_IN_KB)
+            }
         }
-        Ok(result)
     }
 
-    fn payload(&self, point_id: PointIdType) -> OperationResult<Payload> {
-        let internal_id = self.lookup_internal_id(point_id)?;
-        self.payload_by_offset(internal_id)
+    pub fn save_current_state(&self) -> OperationResult<()> {
+        Self::save_state(&self.get_state(), &self.current_path)
     }
 
-    fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
-        // Sorry for that, but I didn't find any way easier.
-        // If you try simply return iterator - it won't work because AtomicRef should exist
-        // If you try to make callback instead - you won't be able to create <dyn SegmentEntry>
-        // Attempt to create return borrowed value along with iterator failed because of insane lifetimes
-        unsafe { self.id_tracker.as_ptr().as_ref().unwrap().iter_external() }
+    fn infer_from_payload_data(
+        &self,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<Option<PayloadSchemaType>> {
+        let payload_index = self.payload_index.read();
+        payload_index.infer_payload_type(key)
     }
 
-    fn read_filtered<'a>(
-        &'a self,
-        offset: Option<PointIdType>,
-        limit: Option<usize>,
-        filter: Option<&'a Filter>,
-    ) -> Vec<PointIdType> {
-        match filter {
-            None => self
-                .id_tracker
-                .borrow()
-                .iter_from(offset)
-                .map(|x| x.0)
-                .take(limit.unwrap_or(usize::MAX))
-                .collect(),
-            Some(condition) => {
-                let query_cardinality = {
-                    let payload_index = self.payload_index.borrow();
-                    payload_index.estimate_cardinality(condition)
-                };
+    /// Restore the segment from the archive, created by [`SegmentEntry::take_snapshot`], into `segment_path`
+    ///
+    /// Segment is unpacked and validated aside, and moved into place once complete, so `segment_path` is accepted by [`load_segment`].
+    /// Existing non-empty `segment_path` is replaced only if `force` is set.
+    pub fn restore_snapshot(
+        snapshot_path: &Path,
+        segment_path: &Path,
+        force: bool,
+    ) -> OperationResult<()> {
+        Self::restore_snapshot_chain(snapshot_path, &[], segment_path, force)
+    }
 
-                // ToDo: Add telemetry for this heuristics
+    /// Restore the segment from the archive and the chain of deltas on top of it, see [`Segment::take_snapshot_delta`]
+    ///
+    /// Deltas are applied in order, each one only on top of the snapshot it was taken against.
+    pub fn restore_snapshot_chain(
+        snapshot_path: &Path,
+        delta_paths: &[PathBuf],
+        segment_path: &Path,
+        force: bool,
+    ) -> OperationResult<()> {
+        if !force && segment_path.exists() && segment_path.read_dir()?.next().is_some() {
+            return Err(OperationError::service_error(format!(
+                "failed to restore segment snapshot {snapshot_path:?}: {segment_path:?} is not empty"
+            )));
+        }
 
-                // Calculate expected number of condition checks required for
-                // this scroll request with is stream strategy.
-                // Example:
-                //  - cardinality = 1000
-                //  - limit = 10
-                //  - total = 10000
-                //  - point filter prob = 1000 / 10000 = 0.1
-                //  - expected_checks = 10 / 0.1  = 100
-                //  -------------------------------
-                //  - cardinality = 10
-                //  - limit = 10
-                //  - total = 10000
-                //  - point filter prob = 10 / 10000 = 0.001
-                //  - expected_checks = 10 / 0.001  = 10000
+        write_dir_atomically(segment_path, |unpacked_path| {
+            Self::unpack_snapshot(snapshot_path, delta_paths, unpacked_path)?;
+            Self::validate_restored_snapshot(unpacked_path)?;
+            if segment_path.exists() {
+                // Segment, loaded from the target, is not removed underneath
+                let _lock = SegmentLock::exclusive(segment_path)?;
+                fs::remove_dir_all(segment_path)?;
+            }
+            Ok(())
+        })
+    }
 
-                let total_points = self.points_count() + 1 /* + 1 for division-by-zero */;
-                // Expected number of successful checks per point
-                let check_probability = (query_cardinality.exp as f64 + 1.0/* protect from zero */)
-                    / total_points as f64;
-                let exp_stream_checks =
-                    (limit.unwrap_or(total_points) as f64 / check_probability) as usize;
+    /// Restore the segment from the archive into `segment_path`, see [`Segment::restore_snapshot`], and load it
+    pub fn load_snapshot(
+        snapshot_path: &Path,
+        segment_path: &Path,
+        force: bool,
+    ) -> OperationResult<Segment> {
+        Self::restore_snapshot(snapshot_path, segment_path, force)?;
+        load_segment(segment_path)?.ok_or_else(|| {
+            OperationError::service_error(format!(
+                "restored segment snapshot {snapshot_path:?} is not loaded from {segment_path:?}"
+            ))
+        })
+    }
 
-                // Assume it would require about `query cardinality` checks.
-                // We are interested in approximate number of checks, so we can
-                // use `query cardinality` as a starting point.
-                let exp_index_checks = query_cardinality.max;
+    fn unpack_snapshot(
+        snapshot_path: &Path,
+        delta_paths: &[PathBuf],
+        segment_path: &Path,
+    ) -> OperationResult<()> {
+        let archive_file = File::open(snapshot_path).map_err(|err| {
+            OperationError::service_error(format!(
+                "failed to open segment snapshot archive {snapshot_path:?}: {err}"
+            ))
+        })?;
 
-                if exp_stream_checks > exp_index_checks {
-                    self.filtered_read_by_index(offset, limit, condition)
-                } else {
-                    self.filtered_read_by_id_stream(offset, limit, condition)
-                }
-            }
+        tar::Archive::new(archive_file)
+            .unpack(segment_path)
+            .map_err(|err| {
+                OperationError::service_error(format!(
+                    "failed to unpack segment snapshot archive {snapshot_path:?}: {err}"
+                ))
+            })?;
+
+        for delta_path in delta_paths {
+            snapshot_delta::apply_delta(segment_path, delta_path)?;
         }
+
+        Self::restore_unpacked_snapshot(segment_path)
     }
 
-    fn read_range(&self, from: Option<PointIdType>, to: Option<PointIdType>) -> Vec<PointIdType> {
-        let id_tracker = self.id_tracker.borrow();
-        let iterator = id_tracker.iter_from(from).map(|x| x.0);
-        match to {
-            None => iterator.collect(),
-            Some(to_id) => iterator.take_while(|x| *x < to_id).collect(),
+    /// Restore the databases of the unpacked archive and move its files into place
+    pub(crate) fn restore_unpacked_snapshot(segment_path: &Path) -> OperationResult<()> {
+        let snapshot_path = segment_path.join(SNAPSHOT_PATH);
+
+        if snapshot_path.exists() {
+            let db_backup_path = snapshot_path.join(DB_BACKUP_PATH);
+            let payload_index_db_backup = snapshot_path.join(PAYLOAD_DB_BACKUP_PATH);
+
+            crate::rocksdb_backup::restore(&db_backup_path, segment_path)?;
+
+            if payload_index_db_backup.is_dir() {
+                StructPayloadIndex::restore_database_snapshot(
+                    &payload_index_db_backup,
+                    segment_path,
+                )?;
+            }
+
+            let files_path = snapshot_path.join(SNAPSHOT_FILES_PATH);
+            utils::fs::move_all(&files_path, segment_path)?;
+
+            fs::remove_dir_all(&snapshot_path).map_err(|err| {
+                OperationError::service_error(format!(
+                    "failed to remove {snapshot_path:?} directory: {err}"
+                ))
+            })?;
+        } else {
+            log::info!("Attempt to restore legacy snapshot format");
+            // Do nothing, legacy format is just plain archive
         }
-    }
 
-    fn has_point(&self, point_id: PointIdType) -> bool {
-        self.id_tracker.borrow().internal_id(point_id).is_some()
+        Ok(())
     }
 
-    fn points_count(&self) -> usize {
-        self.id_tracker.borrow().points_count()
+    /// Take the delta of the segment since the snapshot of the `base` manifest, see [`crate::snapshot_delta`]
+    ///
+    /// Delta holds only the chunks of the snapshot files, changed since the base.
+    /// Delta of the segment without updates since the base is empty, e.g. of a non-appendable segment.
+    pub fn take_snapshot_delta(
+        &self,
+        snapshot_dir_path: &Path,
+        base: &SnapshotManifest,
+    ) -> OperationResult<PathBuf> {
+        let segment_id = self.segment_id()?;
+        let delta_path = snapshot_dir_path.join(format!(
+            "{segment_id}-delta-{}-{}.tar",
+            base.segment_version.unwrap_or(0),
+            self.version(),
+        ));
+
+        if self.version == base.segment_version {
+            snapshot_delta::write_empty_delta(base, &delta_path)?;
+            return Ok(delta_path);
+        }
+
+        // Full snapshot is taken aside, and only its changed chunks are kept
+        let full_snapshot_dir = snapshot_dir_path.join(format!("tmp-{}", Uuid::new_v4()));
+        fs::create_dir_all(&full_snapshot_dir)?;
+        let result = self
+            .take_snapshot(&full_snapshot_dir, SnapshotContents::default())
+            .and_then(|full_snapshot_path| {
+                snapshot_delta::write_delta(&full_snapshot_path, base, &delta_path)
+            });
+        fs::remove_dir_all(&full_snapshot_dir)?;
+        result?;
+        Ok(delta_path)
     }
 
-    fn estimate_points_count<'a>(&'a self, filter: Option<&'a Filter>) -> CardinalityEstimation {
-        match filter {
-            None => {
-                let total_count = self.points_count();
-                CardinalityEstimation {
-                    primary_clauses: vec![],
-                    min: total_count,
-                    exp: total_count,
-                    max: total_count,
+    /// Validate the unpacked segment: format version, vector dimensions and checksums of the files
+    fn validate_restored_snapshot(segment_path: &Path) -> OperationResult<()> {
+        if !SegmentVersion::check_exists(segment_path) {
+            return Err(OperationError::service_error(format!(
+                "segment snapshot has no {VERSION_FILE} file"
+            )));
+        }
+        let stored_version = SegmentVersion::load(segment_path)?.parse()?;
+        let format_version = load_format_version(segment_path, &stored_version)?;
+        if format_version > CURRENT_FORMAT_VERSION {
+            return Err(OperationError::UnsupportedFormatVersion {
+                format_version,
+                supported_version: CURRENT_FORMAT_VERSION,
+            });
+        }
+
+        // State of the legacy layout is validated by its migration
+        let mut partial = false;
+        if format_version > 0 {
+            let state = Self::load_state(segment_path)?;
+            for (vector_name, vector_config) in &state.config.vector_data {
+                if vector_config.size == 0 {
+                    return Err(OperationError::service_error(format!(
+                        "segment snapshot has zero dimension of vector {vector_name}"
+                    )));
                 }
             }
-            Some(filter) => {
-                let payload_index = self.payload_index.borrow();
-                payload_index.estimate_cardinality(filter)
-            }
+            partial = !state.contents.is_full();
         }
+
+        verify_unpacked_checksums(segment_path, partial)
     }
 
-    fn deleted_count(&self) -> usize {
-        self.id_tracker.borrow().deleted_count()
+    // Joins flush thread if exists
+    // Returns lock to guarantee that there will be no other flush in a different thread
+    fn lock_flushing(
+        &self,
+    ) -> OperationResult<parking_lot::MutexGuard<Option<JoinHandle<OperationResult<SeqNumberType>>>>>
+    {
+        let mut lock = self.flush_thread.lock();
+        let mut join_handle: Option<JoinHandle<OperationResult<SeqNumberType>>> = None;
+        std::mem::swap(&mut join_handle, &mut lock);
+        if let Some(join_handle) = join_handle {
+            // Flush result was reported to segment, so we don't need this value anymore
+            let _background_flush_result = join_handle
+                .join()
+                .map_err(|_err| OperationError::service_error("failed to join flush thread"))??;
+        }
+        Ok(lock)
     }
 
-    fn segment_type(&self) -> SegmentType {
-        self.segment_type
+    pub(crate) fn is_background_flushing(&self) -> bool {
+        let lock = self.flush_thread.lock();
+        if let Some(join_handle) = lock.as_ref() {
+            !join_handle.is_finished()
+        } else {
+            false
+        }
     }
 
-    fn info(&self) -> SegmentInfo {
-        let payload_index = self.payload_index.borrow();
-        let schema = payload_index
-            .indexed_fields()
-            .into_iter()
-            .map(|(key, index_schema)| {
-                let points_count = payload_index.indexed_points(&key);
-                (key, PayloadIndexInfo::new(index_schema, points_count))
-            })
-            .collect();
+    /// Check that the vector fits the named vector storage and the filter is allowed by `params`
+    fn check_search_query(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<()> {
+        check_vector_name(vector_name, &self.segment_config)?;
+        self.check_contents(true, filter.is_some())?;
+        let expected_vector_dim = self.vector_data[vector_name]
+            .vector_storage
+            .read()
+            .vector_dim();
+        if vector.len() != expected_vector_dim {
+            return Err(OperationError::WrongVector {
+                expected_dim: expected_vector_dim,
+                received_dim: vector.len(),
+            });
+        }
 
-        SegmentInfo {
-            segment_type: self.segment_type,
-            num_vectors: self.points_count() * self.vector_data.len(),
-            num_points: self.points_count(),
-            num_deleted_vectors: self.deleted_count(),
-            ram_usage_bytes: 0,  // ToDo: Implement
-            disk_usage_bytes: 0, // ToDo: Implement
-            is_appendable: self.appendable_flag,
-            index_schema: schema,
+        if let Some(filter) = filter {
+            if params.map_or(false, |params| params.strict_filter) {
+                self.check_strict_filter(filter)?;
+            }
         }
+        Ok(())
     }
 
-    fn config(&self) -> SegmentConfig {
-        self.segment_config.clone()
+    /// Streaming variant of [`SegmentEntry::search`], yielding the best points first
+    ///
+    /// Points are produced by batches of `batch_size`, see [`ScoredPointsIterator`].
+    /// Exhausted iterator yields the same points, as `search` with the same parameters,
+    /// if the vector index finds the same points with the narrower `ef` of the first batches.
+    /// Boosted scores are known only once all the oversampled points are scored,
+    /// so `score_boost` of the params is rejected.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_iter(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        batch_size: usize,
+    ) -> OperationResult<ScoredPointsIterator<'_>> {
+        self.check_search_query(vector_name, vector, filter, params)?;
+        if params.map_or(false, |params| params.score_boost.is_some()) {
+            return Err(OperationError::UnsupportedSearchParams {
+                description: "score boost can't be applied to the streaming search, use the regular search instead".to_string(),
+            });
+        }
+
+        let exact = params.map_or(false, |params| params.exact)
+            || matches!(
+                &*self.vector_data[vector_name].vector_index.read(),
+                VectorIndexEnum::Plain(_)
+            );
+        let filter = filter.map(|filter| self.prepare_filter(filter));
+        Ok(ScoredPointsIterator::new(
+            self,
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            filter,
+            top,
+            params,
+            batch_size,
+            exact,
+        ))
     }
 
-    fn is_appendable(&self) -> bool {
-        self.appendable_flag
+    /// [`SegmentEntry::search`], which is stopped early once the `stopped` flag is set
+    ///
+    /// Returns `OperationError::Cancelled`, if the flag was set before the search was finished.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_with_stop_flag(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        stopped: Arc<AtomicBool>,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        let deadline = SearchDeadline::from_params(params).with_stop_flag(stopped);
+        let (result, _) = self.search_until_deadline(
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            filter,
+            top,
+            params,
+            deadline,
+        )?;
+        Ok(result)
     }
 
-    fn flush(&self, sync: bool) -> OperationResult<SeqNumberType> {
-        let current_persisted_version: Option<SeqNumberType> = *self.persisted_version.lock();
-        if !sync && self.is_background_flushing() {
-            return Ok(current_persisted_version.unwrap_or(0));
-        }
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "search",
+            level = "debug",
+            skip_all,
+            fields(
+                vector_name = vector_name,
+                top = top,
+                filter = filter.is_some(),
+                found = tracing::field::Empty
+            ),
+            err
+        )
+    )]
+    fn search_until_deadline(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+        deadline: SearchDeadline,
+    ) -> OperationResult<(Vec<ScoredPoint>, Option<SearchTelemetry>)> {
+        let counters = SearchCounters::from_params(params);
+        let filter = {
+            let _phase_timer = counters.measure(SearchPhase::Planning);
+            self.check_search_query(vector_name, vector, filter, params)?;
+            filter.map(|filter| self.prepare_filter(filter))
+        };
+        let vector_data = &self.vector_data[vector_name];
 
-        let mut background_flush_lock = self.lock_flushing()?;
-        match (self.version, current_persisted_version) {
-            (None, _) => {
-                // Segment is empty, nothing to flush
-                return Ok(current_persisted_version.unwrap_or(0));
-            }
-            (Some(version), Some(persisted_version)) => {
-                if version == persisted_version {
-                    // Segment is already flushed
-                    return Ok(persisted_version);
-                }
-            }
-            (_, _) => {}
-        }
+        let internal_result = &vector_data.vector_index.read().search_with_deadline(
+            &[vector],
+            filter.as_ref(),
+            index_search_top(top, params),
+            params,
+            &deadline,
+            &counters,
+        )[0];
+        check_search_deadline(&deadline, params)?;
+
+        let result = {
+            let _phase_timer = counters.measure(SearchPhase::IdMapping);
+            self.process_boosted_search_result(
+                vector_name,
+                internal_result,
+                with_payload,
+                with_vector,
+                top,
+                params,
+            )?
+        };
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("found", result.len());
+        Ok((result, counters.telemetry()))
+    }
 
-        let vector_storage_flushers: Vec<_> = self
-            .vector_data
-            .values()
-            .map(|v| v.vector_storage.borrow().flusher())
-            .collect();
-        let state = self.get_state();
-        let current_path = self.current_path.clone();
-        let id_tracker_mapping_flusher = self.id_tracker.borrow().mapping_flusher();
-        let payload_index_flusher = self.payload_index.borrow().flusher();
-        let id_tracker_versions_flusher = self.id_tracker.borrow().versions_flusher();
-        let persisted_version = self.persisted_version.clone();
+    /// Exact top of the points, going after `after` in the order of [`OrderedScoredPoint`]
+    pub(crate) fn exact_search_after(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        limit: usize,
+        after: Option<ScoredPointOffset>,
+    ) -> Vec<ScoredPointOffset> {
+        let vector_storage = self.vector_data[vector_name].vector_storage.read();
+        let id_tracker = self.id_tracker.read();
+        let payload_index = self.payload_index.read();
+        let raw_scorer = new_raw_scorer(
+            vector.to_vec(),
+            &vector_storage,
+            id_tracker.deleted_bitvec(),
+        );
 
-        // Flush order is important:
-        //
-        // 1. Flush id mapping. So during recovery the point will be recovered er in proper segment.
-        // 2. Flush vectors and payloads.
-        // 3. Flush id versions last. So presence of version indicates that all other data is up-to-date.
-        //
-        // Example of recovery from WAL in case of partial flush:
-        //
-        // In-memory state:
-        //
-        //     Segment 1                  Segment 2
-        //
-        //    ID-mapping     vst.1       ID-mapping     vst.2
-        //   ext     int
-        //  ┌───┐   ┌───┐   ┌───┐       ┌───┐   ┌───┐   ┌───┐
-        //  │100├───┤1  │   │1  │       │300├───┤1  │   │1  │
-        //  └───┘   └───┘   │2  │       └───┘   └───┘   │2  │
-        //                  │   │                       │   │
-        //  ┌───┐   ┌───┐   │   │       ┌───┐   ┌───┐   │   │
-        //  │200├───┤2  │   │   │       │400├───┤2  │   │   │
-        //  └───┘   └───┘   └───┘       └───┘   └───┘   └───┘
-        //
-        //
-        //  ext - external id
-        //  int - internal id
-        //  vst - vector storage
-        //
-        //  ─────────────────────────────────────────────────
-        //   After flush, segments could be partially preserved:
-        //
-        //  ┌───┐   ┌───┐   ┌───┐       ┌───┐   ┌───┐   ┌───┐
-        //  │100├───┤1  │   │ 1 │       │300├───┤1  │   │ * │
-        //  └───┘   └───┘   │   │       └───┘   └───┘   │ * │
-        //                  │   │                       │ 3 │
-        //                  │   │       ┌───┐   ┌───┐   │   │
-        //                  │   │       │400├───┤2  │   │   │
-        //                  └───┘       └───┘   └───┘   └───┘
-        //  WAL:      ▲
-        //            │                 ┌───┐   ┌───┐
-        //  100───────┘      ┌────────► │200├───┤3  │
-        //                   |          └───┘   └───┘
-        //  200──────────────┘
-        //
-        //  300
-        //
-        //  400
+        let points: Box<dyn Iterator<Item = PointOffsetType> + '_> = match filter {
+            Some(filter) => payload_index.query_points(filter),
+            None => Box::new(0..vector_storage.total_vector_count() as PointOffsetType),
+        };
+        let after = after.map(OrderedScoredPoint);
+        let scored = points
+            .filter(|&point_id| raw_scorer.check_point(point_id))
+            .map(|idx| {
+                OrderedScoredPoint(ScoredPointOffset {
+                    idx,
+                    score: raw_scorer.score_point(idx),
+                })
+            })
+            .filter(|point| after.map_or(true, |after| *point < after));
 
-        let flush_op = move || {
-            // Flush mapping first to prevent having orphan internal ids.
-            id_tracker_mapping_flusher().map_err(|err| {
-                OperationError::service_error(format!("Failed to flush id_tracker mapping: {err}"))
-            })?;
-            for vector_storage_flusher in vector_storage_flushers {
-                vector_storage_flusher().map_err(|err| {
-                    OperationError::service_error(format!("Failed to flush vector_storage: {err}"))
+        peek_top_largest_iterable(scored, limit)
+            .into_iter()
+            .map(|point| point.0)
+            .collect()
+    }
+
+    /// Search for the `top` points among the given candidates, without the vector index
+    ///
+    /// Only the candidates are scored. Candidates, missing in the segment, are skipped,
+    /// and duplicated ones are scored once.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_among(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        candidates: &[PointIdType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        self.check_search_query(vector_name, vector, None, params)?;
+
+        let id_tracker = self.id_tracker.read();
+        let mut offsets: Vec<_> = candidates
+            .iter()
+            .filter_map(|point_id| id_tracker.internal_id(point_id.clone()))
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+
+        let vector_storage = self.vector_data[vector_name].vector_storage.read();
+        let ignore_quantization = params
+            .and_then(|params| params.quantization)
+            .map(|quantization| quantization.ignore)
+            .unwrap_or(default_quantization_ignore_value());
+        let raw_scorer = match vector_storage.quantized_storage() {
+            Some(quantized_storage) if !ignore_quantization => {
+                quantized_storage.raw_scorer(vector, id_tracker.deleted_bitvec())
+            }
+            _ => new_raw_scorer(
+                vector.to_vec(),
+                &vector_storage,
+                id_tracker.deleted_bitvec(),
+            ),
+        };
+
+        let top_scores = raw_scorer.peek_top_iter(&mut offsets.into_iter(), top);
+
+        self.process_search_result(vector_name, &top_scores, with_payload, with_vector)
+    }
+
+    /// Converts raw ScoredPointOffset search result into ScoredPoint result
+    ///
+    /// Scores are postprocessed with the distance of the searched vector,
+    /// see [`crate::types::Distance::postprocess_score`].
+    pub(crate) fn process_search_result(
+        &self,
+        searched_vector: &str,
+        internal_result: &[ScoredPointOffset],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        let distance = self.segment_config.vector_data[searched_vector].distance;
+        let id_tracker = self.id_tracker.read();
+        let point_offsets = internal_result
+            .iter()
+            .map(|scored_point_offset| scored_point_offset.idx)
+            .collect::<Vec<_>>();
+        let external_ids = id_tracker.external_ids(&point_offsets);
+        let selection = with_payload
+            .payload_selector
+            .as_ref()
+            .map(PayloadSelection::new);
+        internal_result
+            .iter()
+            .zip(external_ids)
+            .filter_map(|(&scored_point_offset, external_id)| {
+                let point_offset = scored_point_offset.idx;
+                match external_id {
+                    Some(point_id) => Some((point_id, scored_point_offset)),
+                    None => {
+                        log::warn!(
+                            "Point with internal ID {} not found in id tracker, skipping",
+                            point_offset
+                        );
+                        None
+                    }
+                }
+            })
+            .map(|(point_id, scored_point_offset)| {
+                let point_offset = scored_point_offset.idx;
+                let point_version = id_tracker.internal_version(point_offset).ok_or_else(|| {
+                    OperationError::inconsistent_storage(format!(
+                        "Corrupted id_tracker, no version for point {point_id}"
+                    ))
                 })?;
+                let payload = if with_payload.enable {
+                    Some(match &selection {
+                        Some(selection) => {
+                            Arc::new(self.selected_payload_by_offset(point_offset, selection)?)
+                        }
+                        None => self.payload_by_offset(point_offset)?,
+                    })
+                } else {
+                    None
+                };
+                let vector = match with_vector {
+                    WithVector::Bool(false) => None,
+                    WithVector::Bool(true) => {
+                        Some(self.all_vectors_by_offset(point_offset)?.into())
+                    }
+                    WithVector::Selector(vectors) => {
+                        let mut result = NamedVectors::default();
+                        for vector_name in vectors {
+                            let vector_opt = self.vector_by_offset(vector_name, point_offset)?;
+                            match vector_opt {
+                                None => {
+                                    return Err(OperationError::inconsistent_storage(format!(
+                                        "Vector {vector_name} not found at offset {point_offset}"
+                                    )))
+                                }
+                                Some(vector) => result.insert(vector_name.clone(), vector),
+                            }
+                        }
+                        Some(result.into())
+                    }
+                };
+
+                Ok(ScoredPoint {
+                    id: point_id,
+                    version: point_version,
+                    score: distance.postprocess_score(scored_point_offset.score),
+                    payload,
+                    vector,
+                })
+            })
+            .collect()
+    }
+
+    /// Same as [`Self::process_search_result`], but boosts the scores by the `score_boost` of the
+    /// params, if any, and keeps the best `top` of the points
+    fn process_boosted_search_result(
+        &self,
+        searched_vector: &str,
+        internal_result: &[ScoredPointOffset],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        let score_boost = match params.and_then(|params| params.score_boost.as_ref()) {
+            Some(score_boost) => score_boost,
+            None => {
+                return self.process_search_result(
+                    searched_vector,
+                    internal_result,
+                    with_payload,
+                    with_vector,
+                )
             }
-            payload_index_flusher().map_err(|err| {
-                OperationError::service_error(format!("Failed to flush payload_index: {err}"))
-            })?;
-            // Id Tracker contains versions of points. We need to flush it after vector_storage and payload_index flush.
-            // This is because vector_storage and payload_index flush are not atomic.
-            // If payload or vector flush fails, we will be able to recover data from WAL.
-            // If Id Tracker flush fails, we are also able to recover data from WAL
-            //  by simply overriding data in vector and payload storages.
-            // Once versions are saved - points are considered persisted.
-            id_tracker_versions_flusher().map_err(|err| {
-                OperationError::service_error(format!("Failed to flush id_tracker versions: {err}"))
-            })?;
-            Self::save_state(&state, &current_path).map_err(|err| {
-                OperationError::service_error(format!("Failed to flush segment state: {err}"))
-            })?;
-            *persisted_version.lock() = state.version;
+        };
+        let boosted =
+            self.boost_search_result(searched_vector, internal_result, score_boost, top)?;
+        let offsets: Vec<_> = boosted
+            .iter()
+            .map(|(scored_point, _)| *scored_point)
+            .collect();
+        let mut points =
+            self.process_search_result(searched_vector, &offsets, with_payload, with_vector)?;
+        for (point, (_, score)) in points.iter_mut().zip(&boosted) {
+            point.score = *score;
+        }
+        Ok(points)
+    }
 
-            debug_assert!(state.version.is_some());
-            Ok(state.version.unwrap_or(0))
+    /// Best `top` of the found points by the boosted score, along with the score
+    ///
+    /// Boost is added to the postprocessed score, so it is of the same scale as the returned scores.
+    fn boost_search_result(
+        &self,
+        searched_vector: &str,
+        internal_result: &[ScoredPointOffset],
+        score_boost: &ScoreBoost,
+        top: usize,
+    ) -> OperationResult<Vec<(ScoredPointOffset, ScoreType)>> {
+        let distance = self.segment_config.vector_data[searched_vector].distance;
+        let id_tracker = self.id_tracker.read();
+        let mut boosted = internal_result
+            .iter()
+            // Points without external id are skipped by `process_search_result`
+            .filter(|scored_point| id_tracker.external_id(scored_point.idx).is_some())
+            .map(|&scored_point| {
+                let payload = self.payload_by_offset(scored_point.idx)?;
+                let score =
+                    distance.postprocess_score(scored_point.score) + score_boost.boost(&payload);
+                Ok((scored_point, score))
+            })
+            .collect::<OperationResult<Vec<_>>>()?;
+        match distance.distance_order() {
+            Order::LargeBetter => boosted.sort_by(|(_, a), (_, b)| b.total_cmp(a)),
+            Order::SmallBetter => boosted.sort_by(|(_, a), (_, b)| a.total_cmp(b)),
+        }
+        boosted.truncate(top);
+        Ok(boosted)
+    }
+
+    pub fn filtered_read_by_index(
+        &self,
+        offset: Option<PointIdType>,
+        limit: Option<usize>,
+        condition: &Filter,
+    ) -> Vec<PointIdType> {
+        let payload_index = self.payload_index.read();
+        let id_tracker = self.id_tracker.read();
+
+        let ids_iterator = payload_index
+            .query_points(condition)
+            .filter_map(|internal_id| {
+                let external_id = id_tracker.external_id(internal_id);
+                match external_id {
+                    Some(external_id) => match &offset {
+                        Some(offset) if &external_id < offset => None,
+                        _ => Some(external_id),
+                    },
+                    None => None,
+                }
+            });
+
+        let mut page = match limit {
+            Some(limit) => peek_top_smallest_iterable(ids_iterator, limit),
+            None => ids_iterator.collect(),
         };
+        page.sort_unstable();
+        page
+    }
 
-        if sync {
-            flush_op()
-        } else {
-            *background_flush_lock = Some(
-                std::thread::Builder::new()
-                    .name("background_flush".to_string())
-                    .spawn(flush_op)
-                    .unwrap(),
+    pub fn filtered_read_by_id_stream(
+        &self,
+        offset: Option<PointIdType>,
+        limit: Option<usize>,
+        condition: &Filter,
+    ) -> Vec<PointIdType> {
+        let payload_index = self.payload_index.read();
+        let filter_context = payload_index.filter_context(condition);
+        self.id_tracker
+            .read()
+            .iter_from(offset)
+            .filter(move |(_, internal_id)| filter_context.check(*internal_id))
+            .map(|(external_id, _)| external_id)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Check consistency of the segment's data and repair it if possible.
+    ///
+    /// The check is cancelled by `stopped` before the repair, leaving the segment unchanged.
+    pub fn check_consistency_and_repair(
+        &mut self,
+        stopped: Option<&AtomicBool>,
+    ) -> OperationResult<()> {
+        let not_stopped = AtomicBool::new(false);
+        let stopped = stopped.unwrap_or(&not_stopped);
+        let mut internal_ids_to_delete = HashSet::new();
+        let id_tracker = self.id_tracker.read();
+        for internal_id in id_tracker.iter_ids() {
+            check_process_stopped(stopped)?;
+            if id_tracker.external_id(internal_id).is_none() {
+                internal_ids_to_delete.insert(internal_id);
+            }
+        }
+
+        if !internal_ids_to_delete.is_empty() {
+            log::info!(
+                "Found {} points in vector storage without external id - those will be deleted",
+                internal_ids_to_delete.len(),
+            );
+            for internal_id in &internal_ids_to_delete {
+                self.payload_index.write().drop(*internal_id)?;
+            }
+            self.payload_index.read().invalidate_filter_cache();
+            self.stats_cache.invalidate();
+
+            // We do not drop version here, because it is already not loaded into memory.
+            // There are no explicit mapping between internal ID and version, so all dangling
+            // versions will be ignored automatically.
+            // Those versions could be overwritten by new points, but it is not a problem.
+            // They will also be deleted by the next optimization.
+        }
+
+        // flush entire segment if needed
+        if !internal_ids_to_delete.is_empty() {
+            self.flush(true)?;
+        }
+        Ok(())
+    }
+}
+
+/// This is a basic implementation of `SegmentEntry`,
+/// meaning that it implements the _actual_ operations with data and not any kind of proxy or wrapping
+impl SegmentEntry for Segment {
+    fn version(&self) -> SeqNumberType {
+        self.version.unwrap_or(0)
+    }
+
+    fn point_version(&self, point_id: PointIdType) -> Option<SeqNumberType> {
+        let id_tracker = self.id_tracker.read();
+        id_tracker
+            .internal_id(point_id)
+            .and_then(|internal_id| id_tracker.internal_version(internal_id))
+    }
+
+    fn search(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        let (result, _) = self.search_with_telemetry(
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            filter,
+            top,
+            params,
+        )?;
+        Ok(result)
+    }
+
+    fn search_with_telemetry(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<(Vec<ScoredPoint>, Option<SearchTelemetry>)> {
+        self.search_until_deadline(
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            filter,
+            top,
+            params,
+            SearchDeadline::from_params(params),
+        )
+    }
+
+    fn search_batch(
+        &self,
+        vector_name: &str,
+        vectors: &[&[VectorElementType]],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<Vec<ScoredPoint>>> {
+        check_vector_name(vector_name, &self.segment_config)?;
+        self.check_contents(true, filter.is_some())?;
+        let vector_data = &self.vector_data[vector_name];
+        let expected_vector_dim = vector_data.vector_storage.read().vector_dim();
+        for vector in vectors {
+            if vector.len() != expected_vector_dim {
+                return Err(OperationError::WrongVector {
+                    expected_dim: expected_vector_dim,
+                    received_dim: vector.len(),
+                });
+            }
+        }
+
+        if let Some(filter) = filter {
+            if params.map_or(false, |params| params.strict_filter) {
+                self.check_strict_filter(filter)?;
+            }
+        }
+
+        let filter = filter.map(|filter| self.prepare_filter(filter));
+        let deadline = SearchDeadline::from_params(params);
+        let internal_results = vector_data.vector_index.read().search_with_deadline(
+            vectors,
+            filter.as_ref(),
+            index_search_top(top, params),
+            params,
+            &deadline,
+            &SearchCounters::disabled(),
+        );
+        check_search_deadline(&deadline, params)?;
+
+        let res = internal_results
+            .iter()
+            .map(|internal_result| {
+                self.process_boosted_search_result(
+                    vector_name,
+                    internal_result,
+                    with_payload,
+                    with_vector,
+                    top,
+                    params,
+                )
+            })
+            .collect();
+
+        res
+    }
+
+    fn search_groups(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        with_payload: &WithPayload,
+        with_vector: &WithVector,
+        group_by: &PayloadKeyType,
+        group_size: usize,
+        groups: usize,
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<Vec<PointGroup>> {
+        search_groups(
+            self,
+            vector_name,
+            vector,
+            with_payload,
+            with_vector,
+            group_by,
+            group_size,
+            groups,
+            filter,
+            params,
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "upsert_point", level = "debug", skip(self, vectors), err)
+    )]
+    fn upsert_vector(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        vectors: &NamedVectors,
+    ) -> OperationResult<bool> {
+        debug_assert!(self.is_appendable());
+        check_vectors_set(vectors, &self.segment_config)?;
+        let stored_internal_point = self.id_tracker.read().internal_id(point_id.clone());
+        self.apply_write(
+            WriteOperationKind::UpsertPoint,
+            Some(point_id.clone()),
+            op_num,
+            stored_internal_point,
+            |segment| {
+                if stored_internal_point.is_none()
+                    && segment
+                        .id_tracker
+                        .read()
+                        .deleted_version(point_id.clone())
+                        .map_or(false, |deleted_version| deleted_version > op_num)
+                {
+                    // Point was deleted by a later operation, do not resurrect it
+                    return Ok((false, None));
+                }
+
+                let mut processed_vectors = NamedVectors::default();
+                for (vector_name, vector) in vectors.iter() {
+                    let vector_name: &str = vector_name;
+                    let vector: &[VectorElementType] = vector;
+                    let vector_data = &segment.vector_data[vector_name];
+                    let vector_dim = vector_data.vector_storage.read().vector_dim();
+                    if vector_dim != vector.len() {
+                        return Err(OperationError::WrongVector {
+                            expected_dim: vector_dim,
+                            received_dim: vector.len(),
+                        });
+                    }
+
+                    let processed_vector_opt = segment.segment_config.vector_data[vector_name]
+                        .distance
+                        .preprocess_vector(vector);
+                    match processed_vector_opt {
+                        None => processed_vectors.insert_ref(vector_name, vector),
+                        Some(preprocess_vector) => {
+                            processed_vectors.insert(vector_name.to_string(), preprocess_vector)
+                        }
+                    }
+                }
+
+                if let Some(existing_internal_id) = stored_internal_point {
+                    segment.update_vector(existing_internal_id, processed_vectors)?;
+                    Ok((true, Some(existing_internal_id)))
+                } else {
+                    let internal_size = segment.id_tracker.read().internal_size();
+                    check_points_capacity(internal_size + 1)?;
+                    let new_index = internal_size as PointOffsetType;
+
+                    for (vector_name, processed_vector) in processed_vectors {
+                        let vector_name: &str = &vector_name;
+                        segment.vector_data[vector_name]
+                            .vector_storage
+                            .write()
+                            .insert_vector(new_index, &processed_vector)?;
+                    }
+                    let previous_internal_id =
+                        segment.id_tracker.write().set_link(point_id, new_index)?;
+                    segment.update_vector_indexes(new_index)?;
+                    match previous_internal_id {
+                        None => Ok((false, Some(new_index))),
+                        Some(previous_internal_id) => {
+                            // Point was linked after the lookup, keep its payload at the new offset
+                            segment
+                                .payload_index
+                                .write()
+                                .move_entry(previous_internal_id, new_index)?;
+                            Ok((true, Some(new_index)))
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    fn upsert_vectors(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+    ) -> OperationResult<Vec<bool>> {
+        debug_assert!(self.is_appendable());
+        self.write_points_batch(op_num, point_ids, vectors, None)
+    }
+
+    fn upsert_points(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+        vectors: &[NamedVectors],
+        payloads: &[Option<&Payload>],
+    ) -> OperationResult<Vec<bool>> {
+        debug_assert!(self.is_appendable());
+        debug_assert_eq!(point_ids.len(), payloads.len());
+        self.write_points_batch(op_num, point_ids, vectors, Some(payloads))
+    }
+
+    fn delete_point(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+    ) -> OperationResult<bool> {
+        let internal_id = self.id_tracker.read().internal_id(point_id.clone());
+        match internal_id {
+            None => Ok(false), // Point already not exists
+            Some(internal_id) => self.apply_write(
+                WriteOperationKind::DeletePoint,
+                Some(point_id.clone()),
+                op_num,
+                Some(internal_id),
+                |segment| {
+                    segment.payload_index.write().drop(internal_id)?;
+                    let mut id_tracker = segment.id_tracker.write();
+                    id_tracker.drop(point_id.clone())?;
+                    id_tracker.set_deleted_version(point_id, op_num)?;
+                    Ok((true, Some(internal_id)))
+                },
+            ),
+        }
+    }
+
+    fn set_full_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        full_payload: &Payload,
+    ) -> OperationResult<bool> {
+        let internal_id = self.id_tracker.read().internal_id(point_id.clone());
+        self.apply_write(
+            WriteOperationKind::SetFullPayload,
+            Some(point_id.clone()),
+            op_num,
+            internal_id,
+            |segment| match internal_id {
+                Some(internal_id) => {
+                    segment
+                        .payload_index
+                        .write()
+                        .assign_all(internal_id, full_payload)?;
+                    Ok((true, Some(internal_id)))
+                }
+                None => Err(OperationError::PointIdError {
+                    missed_point_id: point_id,
+                }),
+            },
+        )
+    }
+
+    fn set_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        payload: &Payload,
+    ) -> OperationResult<bool> {
+        // Payload is merged into the existing one
+        self.check_contents(false, true)?;
+        let internal_id = self.id_tracker.read().internal_id(point_id.clone());
+        self.apply_write(
+            WriteOperationKind::SetPayload,
+            Some(point_id.clone()),
+            op_num,
+            internal_id,
+            |segment| match internal_id {
+                Some(internal_id) => {
+                    segment.payload_index.write().assign(internal_id, payload)?;
+                    Ok((true, Some(internal_id)))
+                }
+                None => Err(OperationError::PointIdError {
+                    missed_point_id: point_id,
+                }),
+            },
+        )
+    }
+
+    fn delete_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool> {
+        let internal_id = self.id_tracker.read().internal_id(point_id.clone());
+        self.apply_write(
+            WriteOperationKind::DeletePayload,
+            Some(point_id.clone()),
+            op_num,
+            internal_id,
+            |segment| match internal_id {
+                Some(internal_id) => {
+                    segment.payload_index.write().delete(internal_id, key)?;
+                    Ok((true, Some(internal_id)))
+                }
+                None => Err(OperationError::PointIdError {
+                    missed_point_id: point_id,
+                }),
+            },
+        )
+    }
+
+    fn clear_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+    ) -> OperationResult<bool> {
+        let internal_id = self.id_tracker.read().internal_id(point_id.clone());
+        self.apply_write(
+            WriteOperationKind::ClearPayload,
+            Some(point_id.clone()),
+            op_num,
+            internal_id,
+            |segment| match internal_id {
+                Some(internal_id) => {
+                    segment.payload_index.write().drop(internal_id)?;
+                    Ok((true, Some(internal_id)))
+                }
+                None => Err(OperationError::PointIdError {
+                    missed_point_id: point_id,
+                }),
+            },
+        )
+    }
+
+    fn vector(
+        &self,
+        vector_name: &str,
+        point_id: PointIdType,
+    ) -> OperationResult<Vec<VectorElementType>> {
+        let internal_id = self.lookup_internal_id(point_id.clone())?;
+        let vector_opt = self.vector_by_offset(vector_name, internal_id)?;
+        if let Some(vector) = vector_opt {
+            Ok(vector)
+        } else {
+            let segment_path = self.current_path.display();
+            Err(OperationError::inconsistent_storage(format!(
+                "Vector {vector_name} not found at offset {internal_id} for point {point_id}, segment {segment_path}",
+            )))
+        }
+    }
+
+    fn all_vectors(&self, point_id: PointIdType) -> OperationResult<NamedVectors> {
+        let mut result = NamedVectors::default();
+        for vector_name in self.vector_data.keys() {
+            result.insert(
+                vector_name.clone(),
+                self.vector(vector_name, point_id.clone())?,
+            );
+        }
+        Ok(result)
+    }
+
+    fn payload(&self, point_id: PointIdType) -> OperationResult<Arc<Payload>> {
+        let internal_id = self.lookup_internal_id(point_id)?;
+        self.payload_by_offset(internal_id)
+    }
+
+    fn selected_payload(
+        &self,
+        point_id: PointIdType,
+        selector: &PayloadSelector,
+    ) -> OperationResult<Payload> {
+        let internal_id = self.lookup_internal_id(point_id)?;
+        self.selected_payload_by_offset(internal_id, &PayloadSelection::new(selector))
+    }
+
+    fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
+        // Iterator holds the read guard of the id tracker, see [`ArcRwLockIterator`]
+        Box::new(ArcRwLockIterator::new(
+            self.id_tracker.clone(),
+            |id_tracker| id_tracker.iter_external(),
+        ))
+    }
+
+    fn read_filtered<'a>(
+        &'a self,
+        offset: Option<PointIdType>,
+        limit: Option<usize>,
+        filter: Option<&'a Filter>,
+    ) -> Vec<PointIdType> {
+        match filter {
+            None => self
+                .id_tracker
+                .read()
+                .iter_from(offset)
+                .map(|x| x.0)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect(),
+            Some(condition) => {
+                let condition = &self.prepare_filter(condition);
+                let query_cardinality = {
+                    let payload_index = self.payload_index.read();
+                    payload_index.estimate_cardinality(condition)
+                };
+
+                // ToDo: Add telemetry for this heuristics
+
+                // Calculate expected number of condition checks required for
+                // this scroll request with is stream strategy.
+                // Example:
+                //  - cardinality = 1000
+                //  - limit = 10
+                //  - total = 10000
+                //  - point filter prob = 1000 / 10000 = 0.1
+                //  - expected_checks = 10 / 0.1  = 100
+                //  -------------------------------
+                //  - cardinality = 10
+                //  - limit = 10
+                //  - total = 10000
+                //  - point filter prob = 10 / 10000 = 0.001
+                //  - expected_checks = 10 / 0.001  = 10000
+
+                let total_points = self.points_count() + 1 /* + 1 for division-by-zero */;
+                // Expected number of successful checks per point
+                let check_probability = (query_cardinality.exp as f64 + 1.0/* protect from zero */)
+                    / total_points as f64;
+                let exp_stream_checks =
+                    (limit.unwrap_or(total_points) as f64 / check_probability) as usize;
+
+                // Assume it would require about `query cardinality` checks.
+                // We are interested in approximate number of checks, so we can
+                // use `query cardinality` as a starting point.
+                let exp_index_checks = query_cardinality.max;
+
+                if exp_stream_checks > exp_index_checks {
+                    self.filtered_read_by_index(offset, limit, condition)
+                } else {
+                    self.filtered_read_by_id_stream(offset, limit, condition)
+                }
+            }
+        }
+    }
+
+    fn read_ordered_filtered<'a>(
+        &'a self,
+        limit: Option<usize>,
+        filter: Option<&'a Filter>,
+        order_by: &'a OrderBy,
+        offset: Option<PointIdType>,
+    ) -> OperationResult<Vec<(f64, PointIdType)>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let filter = filter.map(|filter| self.prepare_filter(filter));
+        let payload_index = self.payload_index.read();
+        let id_tracker = self.id_tracker.read();
+        let filter_context = filter
+            .as_ref()
+            .map(|filter| payload_index.filter_context(filter));
+        let ordered_points = payload_index
+            .ordered_values(&order_by.key, order_by.start_from, order_by.direction)?
+            .filter(|&(_, internal_id)| {
+                filter_context
+                    .as_ref()
+                    .map_or(true, |filter_context| filter_context.check(internal_id))
+            })
+            // Deleted points have no external id
+            .filter_map(|(value, internal_id)| Some((value, id_tracker.external_id(internal_id)?)))
+            .filter(|(value, point_id)| match (order_by.start_from, &offset) {
+                (Some(start_from), Some(offset)) if *value == start_from => point_id >= offset,
+                _ => true,
+            });
+
+        // Index orders equal values by internal id, so all the points with the last value are read to be ordered by id
+        let mut points: Vec<(f64, PointIdType)> = Vec::new();
+        for (value, point_id) in ordered_points {
+            if points.len() >= limit && points.last().map_or(true, |&(last, _)| last != value) {
+                break;
+            }
+            points.push((value, point_id));
+        }
+        points.sort_unstable_by(|a, b| order_by.compare((a.0, &a.1), (b.0, &b.1)));
+        points.truncate(limit);
+        Ok(points)
+    }
+
+    fn export_jsonl(
+        &self,
+        writer: &mut dyn Write,
+        with_vectors: bool,
+        filter: Option<&Filter>,
+    ) -> OperationResult<usize> {
+        let exported =
+            self.for_each_export_point(with_vectors, filter, |id, vectors, payload| {
+                let point = JsonlPoint {
+                    id,
+                    vector: vectors.map(Into::into),
+                    payload,
+                };
+                point.write_line(writer)
+            })?;
+        writer.flush()?;
+        Ok(exported)
+    }
+
+    fn read_range(&self, from: Option<PointIdType>, to: Option<PointIdType>) -> Vec<PointIdType> {
+        let id_tracker = self.id_tracker.read();
+        let iterator = id_tracker.iter_from(from).map(|x| x.0);
+        match to {
+            None => iterator.collect(),
+            Some(to_id) => iterator.take_while(|x| *x < to_id).collect(),
+        }
+    }
+
+    fn sample_ids<'a>(
+        &'a self,
+        filter: Option<&'a Filter>,
+        k: usize,
+        rng_seed: Option<u64>,
+    ) -> Vec<PointIdType> {
+        match filter {
+            None => self.id_tracker.read().sample_ids(k, rng_seed),
+            Some(filter) => {
+                let filter = self.prepare_filter(filter);
+                let payload_index = self.payload_index.read();
+                let id_tracker = self.id_tracker.read();
+                // Deleted points have no external id
+                let external_ids = payload_index
+                    .query_points(&filter)
+                    .filter_map(|internal_id| id_tracker.external_id(internal_id));
+                reservoir_sample(external_ids, k, rng_seed)
+            }
+        }
+    }
+
+    fn has_point(&self, point_id: PointIdType) -> bool {
+        self.id_tracker.read().internal_id(point_id).is_some()
+    }
+
+    fn points_count(&self) -> usize {
+        self.id_tracker.read().points_count()
+    }
+
+    fn estimate_points_count<'a>(&'a self, filter: Option<&'a Filter>) -> CardinalityEstimation {
+        match filter {
+            None => {
+                let total_count = self.points_count();
+                CardinalityEstimation {
+                    primary_clauses: vec![],
+                    min: total_count,
+                    exp: total_count,
+                    max: total_count,
+                }
+            }
+            Some(filter) => {
+                let payload_index = self.payload_index.read();
+                payload_index.estimate_cardinality(&self.prepare_filter(filter))
+            }
+        }
+    }
+
+    fn deleted_count(&self) -> usize {
+        self.id_tracker.read().deleted_count()
+    }
+
+    fn segment_type(&self) -> SegmentType {
+        self.segment_type
+    }
+
+    fn info(&self) -> SegmentInfo {
+        // Counts and flags are cheap, so they are not cached
+        let stats = self.stats_cache.get_or_compute(|| self.compute_stats());
+        let flush_stats = self.flush_stats.lock().clone();
+
+        SegmentInfo {
+            segment_type: self.segment_type,
+            num_vectors: self.points_count() * self.vector_data.len(),
+            num_points: self.points_count(),
+            num_deleted_vectors: self.deleted_count(),
+            ram_usage_bytes: stats.ram_usage_bytes,
+            disk_usage_bytes: stats.disk_usage_bytes,
+            is_appendable: self.appendable_flag,
+            index_outdated: self.index_required(),
+            index_schema: stats.index_schema,
+            vector_indexes: self
+                .vector_data
+                .keys()
+                .map(|vector_name| (vector_name.clone(), self.vector_index_info(vector_name)))
+                .collect(),
+            flush_policy: self.segment_config.flush_policy,
+            last_flush_time: flush_stats.last_flush_time,
+            last_flush_error: flush_stats.last_flush_error,
+        }
+    }
+
+    fn config(&self) -> SegmentConfig {
+        self.segment_config.clone()
+    }
+
+    fn is_appendable(&self) -> bool {
+        self.appendable_flag
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    fn flush(&self, sync: bool) -> OperationResult<SeqNumberType> {
+        let current_persisted_version: Option<SeqNumberType> = *self.persisted_version.lock();
+        if !sync && self.is_background_flushing() {
+            return Ok(current_persisted_version.unwrap_or(0));
+        }
+
+        let mut background_flush_lock = self.lock_flushing()?;
+        if let Some(flush_scheduler) = &self.flush_scheduler {
+            flush_scheduler.flushed();
+        }
+        match (self.version, current_persisted_version) {
+            (None, _) => {
+                // Segment is empty, nothing to flush
+                return Ok(current_persisted_version.unwrap_or(0));
+            }
+            (Some(version), Some(persisted_version)) => {
+                if version == persisted_version {
+                    // Segment is already flushed
+                    return Ok(persisted_version);
+                }
+            }
+            (_, _) => {}
+        }
+
+        if let Some(persisted_version) = current_persisted_version {
+            self.id_tracker
+                .write()
+                .purge_deleted_versions(persisted_version)?;
+        }
+
+        // Incrementally updated graphs are saved before the versions,
+        // so the points, recovered as already applied, are present in the graphs
+        for vector_data in self.vector_data.values() {
+            vector_data.vector_index.read().flush()?;
+        }
+
+        let vector_storage_flushers: Vec<_> = self
+            .vector_data
+            .values()
+            .map(|v| v.vector_storage.read().flusher())
+            .collect();
+        let state = self.get_state();
+        let current_path = self.current_path.clone();
+        let id_tracker_mapping_flusher = self.id_tracker.read().mapping_flusher();
+        let payload_index_flusher = self.payload_index.read().flusher();
+        let id_tracker_versions_flusher = self.id_tracker.read().versions_flusher();
+        let persisted_version = self.persisted_version.clone();
+        let checksum_files = self.checksum_files();
+        let flush_stats = self.flush_stats.clone();
+        let stats_cache = self.stats_cache.clone();
+
+        // Flush order is important:
+        //
+        // 1. Flush id mapping. So during recovery the point will be recovered er in proper segment.
+        // 2. Flush vectors and payloads.
+        // 3. Flush id versions last. So presence of version indicates that all other data is up-to-date.
+        //
+        // Example of recovery from WAL in case of partial flush:
+        //
+        // In-memory state:
+        //
+        //     Segment 1                  Segment 2
+        //
+        //    ID-mapping     vst.1       ID-mapping     vst.2
+        //   ext     int
+        //  ┌───┐   ┌───┐   ┌───┐       ┌───┐   ┌───┐   ┌───┐
+        //  │100├───┤1  │   │1  │       │300├───┤1  │   │1  │
+        //  └───┘   └───┘   │2  │       └───┘   └───┘   │2  │
+        //                  │   │                       │   │
+        //  ┌───┐   ┌───┐   │   │       ┌───┐   ┌───┐   │   │
+        //  │200├───┤2  │   │   │       │400├───┤2  │   │   │
+        //  └───┘   └───┘   └───┘       └───┘   └───┘   └───┘
+        //
+        //
+        //  ext - external id
+        //  int - internal id
+        //  vst - vector storage
+        //
+        //  ─────────────────────────────────────────────────
+        //   After flush, segments could be partially preserved:
+        //
+        //  ┌───┐   ┌───┐   ┌───┐       ┌───┐   ┌───┐   ┌───┐
+        //  │100├───┤1  │   │ 1 │       │300├───┤1  │   │ * │
+        //  └───┘   └───┘   │   │       └───┘   └───┘   │ * │
+        //                  │   │                       │ 3 │
+        //                  │   │       ┌───┐   ┌───┐   │   │
+        //                  │   │       │400├───┤2  │   │   │
+        //                  └───┘       └───┘   └───┘   └───┘
+        //  WAL:      ▲
+        //            │                 ┌───┐   ┌───┐
+        //  100───────┘      ┌────────► │200├───┤3  │
+        //                   |          └───┘   └───┘
+        //  200──────────────┘
+        //
+        //  300
+        //
+        //  400
+
+        let flush_data = move || {
+            // Flush mapping first to prevent having orphan internal ids.
+            id_tracker_mapping_flusher().map_err(|err| {
+                OperationError::service_error(format!("Failed to flush id_tracker mapping: {err}"))
+            })?;
+            for vector_storage_flusher in vector_storage_flushers {
+                vector_storage_flusher().map_err(|err| {
+                    OperationError::service_error(format!("Failed to flush vector_storage: {err}"))
+                })?;
+            }
+            payload_index_flusher().map_err(|err| {
+                OperationError::service_error(format!("Failed to flush payload_index: {err}"))
+            })?;
+            // Id Tracker contains versions of points. We need to flush it after vector_storage and payload_index flush.
+            // This is because vector_storage and payload_index flush are not atomic.
+            // If payload or vector flush fails, we will be able to recover data from WAL.
+            // If Id Tracker flush fails, we are also able to recover data from WAL
+            //  by simply overriding data in vector and payload storages.
+            // Once versions are saved - points are considered persisted.
+            id_tracker_versions_flusher().map_err(|err| {
+                OperationError::service_error(format!("Failed to flush id_tracker versions: {err}"))
+            })?;
+            Self::save_state(&state, &current_path).map_err(|err| {
+                OperationError::service_error(format!("Failed to flush segment state: {err}"))
+            })?;
+            save_checksums(&current_path, &checksum_files).map_err(|err| {
+                OperationError::service_error(format!("Failed to save segment checksums: {err}"))
+            })?;
+            *persisted_version.lock() = state.version;
+
+            debug_assert!(state.version.is_some());
+            Ok(state.version.unwrap_or(0))
+        };
+        let flush_op = move || {
+            let result = flush_data();
+            flush_stats.lock().record(&result);
+            // Files of the segment are changed by the flush
+            stats_cache.invalidate();
+            result
+        };
+
+        if sync {
+            flush_op()
+        } else {
+            *background_flush_lock = Some(
+                std::thread::Builder::new()
+                    .name("background_flush".to_string())
+                    .spawn(flush_op)
+                    .context("failed to spawn background flush thread")?,
+            );
+            Ok(current_persisted_version.unwrap_or(0))
+        }
+    }
+
+    fn drop_data(mut self) -> OperationResult<()> {
+        let current_path = self.current_path.clone();
+        // Data is removed, so it is not flushed
+        self.closed = true;
+        drop(self);
+        let mut deleted_path = current_path.clone();
+        deleted_path.set_extension("deleted");
+        fs::rename(&current_path, &deleted_path)?;
+        fs::remove_dir_all(&deleted_path).map_err(|err| {
+            OperationError::service_error(format!(
+                "Can't remove segment data at {}, error: {}",
+                deleted_path.to_str().unwrap_or_default(),
+                err
+            ))
+        })
+    }
+
+    fn data_path(&self) -> PathBuf {
+        self.current_path.clone()
+    }
+
+    fn delete_field_index(&mut self, op_num: u64, key: PayloadKeyTypeRef) -> OperationResult<bool> {
+        self.apply_write(
+            WriteOperationKind::DeleteFieldIndex,
+            None,
+            op_num,
+            None,
+            |segment| {
+                segment.payload_index.write().drop_index(key)?;
+                Ok((true, None))
+            },
+        )
+    }
+
+    fn create_field_index(
+        &mut self,
+        op_num: u64,
+        key: PayloadKeyTypeRef,
+        field_type: Option<&PayloadFieldSchema>,
+    ) -> OperationResult<bool> {
+        // Field indexes keep the payload values in plaintext
+        if is_encrypted(&self.current_path) {
+            return Err(OperationError::EncryptedFieldIndex {
+                field_name: key.to_string(),
+            });
+        }
+        self.apply_write(
+            WriteOperationKind::CreateFieldIndex,
+            None,
+            op_num,
+            None,
+            |segment| match field_type {
+                Some(schema) => {
+                    segment
+                        .payload_index
+                        .write()
+                        .set_indexed(key, schema.clone())?;
+                    Ok((true, None))
+                }
+                None => match segment.infer_from_payload_data(key)? {
+                    None => Err(TypeInferenceError {
+                        field_name: key.to_string(),
+                    }),
+                    Some(schema_type) => {
+                        segment
+                            .payload_index
+                            .write()
+                            .set_indexed(key, schema_type.into())?;
+                        Ok((true, None))
+                    }
+                },
+            },
+        )
+    }
+
+    fn update_index_config(
+        &mut self,
+        op_num: SeqNumberType,
+        config: Indexes,
+        rebuild: bool,
+    ) -> OperationResult<bool> {
+        let updated = self.apply_write(
+            WriteOperationKind::UpdateIndexConfig,
+            None,
+            op_num,
+            None,
+            |segment| {
+                segment.pending_index = if segment.segment_config.index == config {
+                    None
+                } else {
+                    Some(config)
+                };
+                segment.save_current_state()?;
+                Ok((true, None))
+            },
+        )?;
+        if updated && rebuild && self.pending_index.is_some() {
+            self.rebuild_index(op_num, None)?;
+        }
+        Ok(updated)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, stopped), err)
+    )]
+    fn rebuild_index(
+        &mut self,
+        op_num: SeqNumberType,
+        stopped: Option<&AtomicBool>,
+    ) -> OperationResult<bool> {
+        self.check_contents(true, false)?;
+        let not_stopped = AtomicBool::new(false);
+        let stopped = stopped.unwrap_or(&not_stopped);
+        let index = self.pending_index.unwrap_or(self.segment_config.index);
+
+        self.apply_write(
+            WriteOperationKind::RebuildIndex,
+            None,
+            op_num,
+            None,
+            |segment| {
+                let build_result =
+                    segment
+                        .vector_data
+                        .iter()
+                        .try_for_each(|(vector_name, vector_data)| {
+                            let rebuild_path =
+                                get_vector_index_rebuild_path(&segment.current_path, vector_name);
+                            if rebuild_path.exists() {
+                                fs::remove_dir_all(&rebuild_path)?;
+                            }
+
+                            // Current index is used until the new one is completely built
+                            open_vector_index(
+                                &rebuild_path,
+                                &index,
+                                &segment.segment_config.vector_data[vector_name],
+                                segment.segment_config.max_search_threads,
+                                segment.id_tracker.clone(),
+                                vector_data.vector_storage.clone(),
+                                segment.payload_index.clone(),
+                            )?
+                            .write()
+                            .build_index(stopped)
+                        });
+
+                if let Err(err) = build_result {
+                    segment.remove_rebuilt_indexes()?;
+                    return Err(err);
+                }
+
+                segment.install_vector_indexes(index)?;
+                Ok((true, None))
+            },
+        )
+    }
+
+    fn build_index_in_background(&self, index: Indexes) -> OperationResult<IndexBuildHandle> {
+        let mut rebuild_paths = Vec::with_capacity(self.vector_data.len());
+        let mut vector_indexes = Vec::with_capacity(self.vector_data.len());
+        for (vector_name, vector_data) in self.vector_data.iter() {
+            let rebuild_path = get_vector_index_rebuild_path(&self.current_path, vector_name);
+            if rebuild_path.exists() {
+                fs::remove_dir_all(&rebuild_path)?;
+            }
+            vector_indexes.push(open_vector_index(
+                &rebuild_path,
+                &index,
+                &self.segment_config.vector_data[vector_name],
+                self.segment_config.max_search_threads,
+                self.id_tracker.clone(),
+                vector_data.vector_storage.clone(),
+                self.payload_index.clone(),
+            )?);
+            rebuild_paths.push(rebuild_path);
+        }
+
+        let vectors_count = self.total_vectors_count();
+        let total = self.id_tracker.read().points_count() * self.vector_data.len();
+        let update_lock = self.update_lock.clone();
+
+        IndexBuildHandle::spawn(index, vectors_count, total, move |stopped, progress| {
+            let result =
+                vector_indexes.iter().try_for_each(|vector_index| {
+                    match &mut *vector_index.write() {
+                        VectorIndexEnum::Plain(_) => Ok(()),
+                        VectorIndexEnum::HnswRam(index) => {
+                            index.build_index_with_progress(stopped, &update_lock, progress)
+                        }
+                        VectorIndexEnum::HnswMmap(index) => {
+                            index.build_index_with_progress(stopped, &update_lock, progress)
+                        }
+                        VectorIndexEnum::Ivf(index) => {
+                            index.build_index_with_progress(stopped, &update_lock, progress)
+                        }
+                    }
+                });
+            if result.is_err() {
+                drop(vector_indexes);
+                for rebuild_path in &rebuild_paths {
+                    if rebuild_path.exists() {
+                        fs::remove_dir_all(rebuild_path)?;
+                    }
+                }
+            }
+            result
+        })
+    }
+
+    fn install_built_index(&mut self, mut handle: IndexBuildHandle) -> OperationResult<bool> {
+        self.check_appendable()?;
+        match handle.join() {
+            Ok(()) => {}
+            Err(OperationError::Cancelled { .. }) => return Ok(false),
+            Err(err) => return Err(err),
+        }
+
+        if self.total_vectors_count() > handle.vectors_count {
+            self.remove_rebuilt_indexes()?;
+            return Err(OperationError::service_error(
+                "Points were added to the segment during index building, index should be rebuilt",
+            ));
+        }
+
+        self.install_vector_indexes(handle.index)?;
+        Ok(true)
+    }
+
+    fn get_indexed_fields(&self) -> HashMap<PayloadKeyType, PayloadFieldSchema> {
+        self.payload_index.read().indexed_fields()
+    }
+
+    fn check_error(&self) -> Option<SegmentFailedState> {
+        self.error_status.clone()
+    }
+
+    fn delete_filtered<'a>(
+        &'a mut self,
+        op_num: SeqNumberType,
+        filter: &'a Filter,
+    ) -> OperationResult<usize> {
+        let mut deleted_points = 0;
+        for point_id in self.read_filtered(None, None, Some(filter)) {
+            deleted_points += self.delete_point(op_num, point_id)? as usize;
+        }
+
+        Ok(deleted_points)
+    }
+
+    fn explain_filter(
+        &self,
+        filter: &Filter,
+        point_id: PointIdType,
+    ) -> OperationResult<FilterExplain> {
+        let internal_id = self.lookup_internal_id(point_id)?;
+        self.explain_filter_by_offset(filter, internal_id)
+    }
+
+    fn explain_filter_counts(&self, filter: &Filter) -> OperationResult<FilterMatchCounts> {
+        let mut counts = FilterMatchCounts::new(filter);
+        let internal_ids: Vec<_> = self.id_tracker.read().iter_ids().collect();
+        for internal_id in internal_ids {
+            counts.add(&self.explain_filter_by_offset(filter, internal_id)?);
+        }
+        Ok(counts)
+    }
+
+    fn estimate_recall(
+        &self,
+        vector_name: &str,
+        sample_queries: usize,
+        top: usize,
+        filter: Option<&Filter>,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<f64> {
+        let queries = self
+            .sample_ids(None, sample_queries, None)
+            .into_iter()
+            .map(|point_id| self.vector(vector_name, point_id))
+            .collect::<OperationResult<Vec<_>>>()?;
+        let queries: Vec<_> = queries.iter().map(|query| query.as_slice()).collect();
+
+        let search = |params: Option<&SearchParams>| {
+            self.search_batch(
+                vector_name,
+                &queries,
+                &false.into(),
+                &false.into(),
+                filter,
+                top,
+                params,
+            )
+        };
+        let exact_params = SearchParams {
+            exact: true,
+            ..params.cloned().unwrap_or_default()
+        };
+        let index_results = search(params)?;
+        let exact_results = search(Some(&exact_params))?;
+
+        let mut recall_sum = 0.0;
+        let mut measured_queries = 0;
+        for (index_result, exact_result) in index_results.iter().zip(&exact_results) {
+            // Filter may exclude all points
+            if exact_result.is_empty() {
+                continue;
+            }
+            let exact_ids: HashSet<_> = exact_result.iter().map(|point| point.id.clone()).collect();
+            let found = index_result
+                .iter()
+                .filter(|point| exact_ids.contains(&point.id))
+                .count();
+            recall_sum += found as f64 / exact_ids.len() as f64;
+            measured_queries += 1;
+        }
+
+        if measured_queries == 0 {
+            return Ok(1.0);
+        }
+        Ok(recall_sum / measured_queries as f64)
+    }
+
+    fn index_stats(
+        &self,
+        vector_name: &str,
+        check_reachability: bool,
+    ) -> OperationResult<Option<IndexStats>> {
+        check_vector_name(vector_name, &self.segment_config)?;
+        let vector_index = self.vector_data[vector_name].vector_index.read();
+        Ok(match &*vector_index {
+            VectorIndexEnum::Plain(_) | VectorIndexEnum::Ivf(_) => None,
+            VectorIndexEnum::HnswRam(index) => index.index_stats(check_reachability),
+            VectorIndexEnum::HnswMmap(index) => index.index_stats(check_reachability),
+        })
+    }
+
+    fn warm_up(&self, parts: WarmUpParts) -> OperationResult<usize> {
+        let mut files = vec![];
+        for vector_data in self.vector_data.values() {
+            if parts.vectors && self.segment_config.storage_type == StorageType::Mmap {
+                files.extend(vector_data.vector_storage.read().files());
+            }
+            if parts.vector_index {
+                let vector_index = vector_data.vector_index.read();
+                // Links of the other indexes are loaded into memory
+                if let VectorIndexEnum::HnswMmap(index) = &*vector_index {
+                    files.extend(index.files());
+                }
+            }
+        }
+        if parts.payload_index
+            && self.segment_config.payload_storage_type == PayloadStorageType::OnDisk
+        {
+            files.push(self.database.read().path().to_owned());
+            files.extend(self.payload_index.read().files());
+        }
+        warm_up_files(&files, parts.max_bytes_per_sec)
+    }
+
+    fn explain_search(
+        &self,
+        vector_name: &str,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<QueryPlanExplain> {
+        self.check_search_query(vector_name, vector, filter, params)?;
+        let filter = filter.map(|filter| self.prepare_filter(filter));
+        Ok(self.vector_data[vector_name]
+            .vector_index
+            .read()
+            .explain_search(vector, filter.as_ref(), top, params))
+    }
+
+    fn estimate_search_cost(
+        &self,
+        vector_name: &str,
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> OperationResult<SearchCostEstimate> {
+        check_vector_name(vector_name, &self.segment_config)?;
+        let filter = filter.map(|filter| self.prepare_filter(filter));
+        Ok(self.vector_data[vector_name]
+            .vector_index
+            .read()
+            .estimate_search_cost(filter.as_ref(), top, params))
+    }
+
+    fn vector_dim(&self, vector_name: &str) -> OperationResult<usize> {
+        check_vector_name(vector_name, &self.segment_config)?;
+        let vector_data_config = &self.segment_config.vector_data[vector_name];
+        Ok(vector_data_config.size)
+    }
+
+    fn vector_dims(&self) -> HashMap<String, usize> {
+        self.segment_config
+            .vector_data
+            .iter()
+            .map(|(vector_name, vector_config)| (vector_name.clone(), vector_config.size))
+            .collect()
+    }
+
+    fn take_snapshot(
+        &self,
+        snapshot_dir_path: &Path,
+        contents: SnapshotContents,
+    ) -> OperationResult<PathBuf> {
+        log::debug!(
+            "Taking snapshot {:?} of segment {:?} into {:?}",
+            contents,
+            self.current_path,
+            snapshot_dir_path
+        );
+        let contents = contents.intersect(&self.contents);
+
+        if !snapshot_dir_path.exists() {
+            return Err(OperationError::service_error(format!(
+                "the snapshot path {snapshot_dir_path:?} does not exist"
+            )));
+        }
+
+        if !snapshot_dir_path.is_dir() {
+            return Err(OperationError::service_error(format!(
+                "the snapshot path {snapshot_dir_path:?} is not a directory",
+            )));
+        }
+
+        // flush segment to capture latest state
+        self.flush(true)?;
+        // Files may be rewritten after the last flush, e.g. by a rebuild of the index
+        save_checksums(&self.current_path, &self.checksum_files())?;
+
+        let tmp_path = self.current_path.join(format!("tmp-{}", Uuid::new_v4()));
+
+        let db_backup_path = tmp_path.join(DB_BACKUP_PATH);
+        let payload_index_db_backup_path = tmp_path.join(PAYLOAD_DB_BACKUP_PATH);
+
+        {
+            let mut excluded_cfs = Vec::new();
+            if !contents.vectors {
+                excluded_cfs.extend(
+                    self.vector_data
+                        .keys()
+                        .map(|vector_name| get_vector_name_with_prefix(DB_VECTOR_CF, vector_name)),
+                );
+            }
+            if !contents.payload {
+                excluded_cfs.push(DB_PAYLOAD_CF.to_owned());
+            }
+
+            let db = self.database.read();
+            if excluded_cfs.is_empty() {
+                crate::rocksdb_backup::create(&db, &db_backup_path)?;
+            } else {
+                crate::rocksdb_backup::create_excluding(&db, &db_backup_path, &excluded_cfs)?;
+            }
+        }
+
+        if contents.payload && contents.index {
+            self.payload_index
+                .read()
+                .take_database_snapshot(&payload_index_db_backup_path)?;
+        }
+
+        let files = Path::new(SNAPSHOT_PATH).join(SNAPSHOT_FILES_PATH);
+
+        // State of the partial snapshot records the included parts
+        if !contents.is_full() {
+            let mut state = Self::load_state(&self.current_path)?;
+            state.contents = contents;
+            let state_dir = tmp_path.join(SNAPSHOT_FILES_PATH);
+            fs::create_dir_all(&state_dir)?;
+            Self::save_state(&state, &state_dir)?;
+        }
+
+        let segment_id = self.segment_id()?;
+
+        let archive_path = snapshot_dir_path.join(format!("{segment_id}.tar"));
+        // Archive is renamed into place once complete, so a crash never leaves a partial one
+        let temp_archive_path = temp_path(&archive_path);
+
+        let file = File::create(&temp_archive_path).map_err(|err| {
+            OperationError::service_error(format!(
+                "failed to create segment snapshot archive {temp_archive_path:?}: {err}"
+            ))
+        })?;
+
+        let mut builder = Builder::new(file);
+
+        builder
+            .append_dir_all(SNAPSHOT_PATH, &tmp_path)
+            .map_err(|err| utils::tar::failed_to_append_error(&tmp_path, err))?;
+
+        for vector_data in self.vector_data.values() {
+            if contents.vectors && contents.index {
+                for file in vector_data.vector_index.read().files() {
+                    utils::tar::append_file_relative_to_base(
+                        &mut builder,
+                        &self.current_path,
+                        &file,
+                        &files,
+                    )?;
+                }
+            }
+
+            if contents.vectors {
+                for file in vector_data.vector_storage.read().files() {
+                    utils::tar::append_file_relative_to_base(
+                        &mut builder,
+                        &self.current_path,
+                        &file,
+                        &files,
+                    )?;
+                }
+            }
+        }
+
+        for file in self.payload_index.read().files() {
+            utils::tar::append_file_relative_to_base(
+                &mut builder,
+                &self.current_path,
+                &file,
+                &files,
+            )?;
+        }
+
+        if contents.is_full() {
+            utils::tar::append_file(
+                &mut builder,
+                &self.current_path.join(SEGMENT_STATE_FILE),
+                &files.join(SEGMENT_STATE_FILE),
+            )?;
+        }
+
+        utils::tar::append_file(
+            &mut builder,
+            &self.current_path.join(VERSION_FILE),
+            &files.join(VERSION_FILE),
+        )?;
+
+        for file_name in [FORMAT_VERSION_FILE, CHECKSUMS_FILE, ENCRYPTION_FILE] {
+            let file_path = self.current_path.join(file_name);
+            if file_path.exists() {
+                utils::tar::append_file(&mut builder, &file_path, &files.join(file_name))?;
+            }
+        }
+
+        builder.finish()?;
+        drop(builder);
+
+        // If `archive_path` exists, we still want to overwrite it
+        commit_file(&temp_archive_path, &archive_path)?;
+
+        // remove tmp directory in background
+        let _ = std::thread::spawn(move || {
+            let res = std::fs::remove_dir_all(&tmp_path);
+            if let Err(err) = res {
+                log::error!(
+                    "Failed to remove tmp directory at {}: {:?}",
+                    tmp_path.display(),
+                    err
+                );
+            }
+        });
+
+        Ok(archive_path)
+    }
+
+    fn get_telemetry_data(&self) -> SegmentTelemetry {
+        let vector_index_searches: Vec<_> = self
+            .vector_data
+            .iter()
+            .map(|(k, v)| {
+                let mut telemetry = v.vector_index.read().get_telemetry_data();
+                telemetry.index_name = Some(k.clone());
+                telemetry
+            })
+            .collect();
+
+        SegmentTelemetry {
+            info: self.info(),
+            config: self.config(),
+            vector_index_searches,
+            payload_field_indices: self.payload_index.read().get_telemetry_data(),
+            filter_cache: self.payload_index.read().get_filter_cache_telemetry(),
+        }
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        if self.closed || self.read_only {
+            let _lock = self.lock_flushing();
+            return;
+        }
+        // Best-effort flush of the operations, applied since the last flush
+        if let Err(err) = self.flush(true) {
+            log::error!(
+                "Failed to flush segment {} on drop: {err}",
+                self.current_path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    use rand::Rng;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::common::flush_scheduler::BackgroundFlusher;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::OperationError::PointIdError;
+    use crate::index::hnsw_index::graph_layers::GraphLayersBase;
+    use crate::index::query_plan::{PlanReason, SearchStrategy};
+    use crate::segment_constructor::{build_segment, load_segment};
+    use crate::segment_wal::SegmentWal;
+    use crate::types::{
+        BoostFunction, Distance, FlushPolicy, HnswConfig, Indexes, ScoreType, SegmentConfig,
+        StorageType, VectorDataConfig,
+    };
+
+    /// Config of the segment with the single default vector
+    fn segment_config(
+        dim: usize,
+        distance: Distance,
+        index: Indexes,
+        storage_type: StorageType,
+    ) -> SegmentConfig {
+        SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index,
+            storage_type,
+            ..Default::default()
+        }
+    }
+
+    // no longer valid since users are now allowed to store arbitrary json objects.
+    // TODO(gvelo): add tests for invalid payload types on indexed fields.
+    // #[test]
+    // fn test_set_invalid_payload_from_json() {
+    //     let data1 = r#"
+    //     {
+    //         "invalid_data"
+    //     }"#;
+    //     let data2 = r#"
+    //     {
+    //         "array": [1, "hello"],
+    //     }"#;
+    //
+    //     let dir = Builder::new().prefix("payload_dir").tempdir().unwrap();
+    //     let dim = 2;
+    //     let config = SegmentConfig {
+    //         vector_size: dim,
+    //         index: Indexes::Plain {},
+    //         payload_index: Some(PayloadIndexType::Plain),
+    //         storage_type: StorageType::InMemory,
+    //         distance: Distance::Dot,
+    //     };
+    //
+    //     let mut segment =
+    //         build_segment(dir.path(), &config, Arc::new(SchemaStorage::new())).unwrap();
+    //     segment.upsert_point(0, 0.into(), &[1.0, 1.0]).unwrap();
+    //
+    //     let result1 = segment.set_full_payload_with_json(0, 0.into(), &data1.to_string());
+    //     assert!(result1.is_err());
+    //
+    //     let result2 = segment.set_full_payload_with_json(0, 0.into(), &data2.to_string());
+    //     assert!(result2.is_err());
+    // }
+
+    #[test]
+    fn test_search_batch_equivalence_single() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+
+        let vec4 = vec![1.1, 1.0, 0.0, 1.0];
+        segment
+            .upsert_vector(100, 4.into(), &only_default_vector(&vec4))
+            .unwrap();
+        let vec6 = vec![1.0, 1.0, 0.5, 1.0];
+        segment
+            .upsert_vector(101, 6.into(), &only_default_vector(&vec6))
+            .unwrap();
+        segment.delete_point(102, 1.into()).unwrap();
+
+        let query_vector = vec![1.0, 1.0, 1.0, 1.0];
+        let search_result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query_vector,
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                None,
+            )
+            .unwrap();
+        eprintln!("search_result = {search_result:#?}");
+
+        let search_batch_result = segment
+            .search_batch(
+                DEFAULT_VECTOR_NAME,
+                &[&query_vector],
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                None,
+            )
+            .unwrap();
+        eprintln!("search_batch_result = {search_batch_result:#?}");
+
+        assert!(!search_result.is_empty());
+        assert_eq!(search_result, search_batch_result[0].clone())
+    }
+
+    #[test]
+    fn test_from_filter_attributes() {
+        let data = r#"
+        {
+            "name": "John Doe",
+            "age": 43,
+            "metadata": {
+                "height": 50,
+                "width": 60
+            }
+        }"#;
+
+        let dir = Builder::new().prefix("payload_dir").tempdir().unwrap();
+        let dim = 2;
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        segment
+            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
+            .unwrap();
+
+        let payload: Payload = serde_json::from_str(data).unwrap();
+
+        segment.set_full_payload(0, 0.into(), &payload).unwrap();
+
+        let filter_valid_str = r#"
+        {
+            "must": [
+                {
+                    "key": "metadata.height",
+                    "match": {
+                        "value": 50
+                    }
+                }
+            ]
+        }"#;
+
+        let filter_valid: Filter = serde_json::from_str(filter_valid_str).unwrap();
+        let filter_invalid_str = r#"
+        {
+            "must": [
+                {
+                    "key": "metadata.height",
+                    "match": {
+                        "value": 60
+                    }
+                }
+            ]
+        }"#;
+
+        let filter_invalid: Filter = serde_json::from_str(filter_invalid_str).unwrap();
+        let results_with_valid_filter = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &[1.0, 1.0],
+                &WithPayload::default(),
+                &false.into(),
+                Some(&filter_valid),
+                1,
+                None,
+            )
+            .unwrap();
+        assert_eq!(results_with_valid_filter.len(), 1);
+        assert_eq!(results_with_valid_filter.first().unwrap().id, 0.into());
+        let results_with_invalid_filter = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &[1.0, 1.0],
+                &WithPayload::default(),
+                &false.into(),
+                Some(&filter_invalid),
+                1,
+                None,
+            )
+            .unwrap();
+        assert!(results_with_invalid_filter.is_empty());
+    }
+
+    #[test]
+    fn test_filter_cache_invalidation() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            filter_cache_size: 4,
+            ..segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory)
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let red: Payload = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        let blue: Payload = serde_json::from_str(r#"{"color": "blue"}"#).unwrap();
+        let points = [(0u64, &red), (1u64, &blue)];
+        for (op_num, (point_id, payload)) in points.into_iter().enumerate() {
+            let op_num = op_num as SeqNumberType * 2;
+            segment
+                .upsert_vector(op_num, point_id.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+            segment
+                .set_full_payload(op_num + 1, point_id.into(), payload)
+                .unwrap();
+        }
+
+        let filter: Filter =
+            serde_json::from_str(r#"{"must": [{"key": "color", "match": {"value": "red"}}]}"#)
+                .unwrap();
+
+        assert_eq!(
+            segment.read_filtered(None, None, Some(&filter)),
+            vec![0.into()]
+        );
+        assert_eq!(
+            segment.read_filtered(None, None, Some(&filter)),
+            vec![0.into()]
+        );
+        let telemetry = segment.get_telemetry_data().filter_cache.unwrap();
+        assert!(telemetry.hits > 0);
+
+        // Cached points must not be served after the update
+        segment.set_full_payload(10, 1.into(), &red).unwrap();
+        assert_eq!(
+            segment.read_filtered(None, None, Some(&filter)),
+            vec![0.into(), 1.into()]
+        );
+
+        segment.delete_point(11, 0.into()).unwrap();
+        assert_eq!(
+            segment.read_filtered(None, None, Some(&filter)),
+            vec![1.into()]
+        );
+    }
+
+    #[test]
+    fn test_filter_cache_concurrent_updates() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            filter_cache_size: 4,
+            ..segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory)
+        };
+        let num_points = 20;
+        let red: Payload = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        let blue: Payload = serde_json::from_str(r#"{"color": "blue"}"#).unwrap();
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut red_points = BTreeSet::new();
+        for point_id in 0..num_points {
+            segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+            let payload = if point_id % 2 == 0 {
+                red_points.insert(point_id);
+                &red
+            } else {
+                &blue
+            };
+            segment
+                .set_full_payload(point_id, point_id.into(), payload)
+                .unwrap();
+        }
+        let filter: Filter =
+            serde_json::from_str(r#"{"must": [{"key": "color", "match": {"value": "red"}}]}"#)
+                .unwrap();
+
+        // Points, which are expected to match the filter, are changed along with the segment
+        let state = RwLock::new((segment, red_points));
+        let stopped = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while !stopped.load(std::sync::atomic::Ordering::Relaxed) {
+                        let state = state.read();
+                        let (segment, red_points) = &*state;
+                        let expected: Vec<PointIdType> =
+                            red_points.iter().map(|&point_id| point_id.into()).collect();
+                        assert_eq!(segment.read_filtered(None, None, Some(&filter)), expected);
+                    }
+                });
+            }
+            for op_num in num_points..num_points * 20 {
+                let point_id = op_num % num_points;
+                {
+                    let mut state = state.write();
+                    let (segment, red_points) = &mut *state;
+                    let payload = if red_points.remove(&point_id) {
+                        &blue
+                    } else {
+                        red_points.insert(point_id);
+                        &red
+                    };
+                    segment
+                        .set_full_payload(op_num, point_id.into(), payload)
+                        .unwrap();
+                }
+                // Readers cache the filter between the writes
+                std::thread::sleep(Duration::from_micros(100));
+            }
+            stopped.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+
+        let telemetry = state.read().0.get_telemetry_data().filter_cache.unwrap();
+        assert!(telemetry.hits > 0);
+    }
+
+    #[test]
+    fn test_explain_filter() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for point_id in 0..10u64 {
+            let payload: Payload =
+                serde_json::from_str(&format!(r#"{{"number": {point_id}}}"#)).unwrap();
+            segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+            segment
+                .set_full_payload(point_id, point_id.into(), &payload)
+                .unwrap();
+        }
+
+        let filter: Filter = serde_json::from_str(
+            r#"{
+                "should": [
+                    {"key": "number", "range": {"lt": 3}},
+                    {"key": "number", "match": {"value": 7}}
+                ],
+                "must_not": [{"has_id": [1]}]
+            }"#,
+        )
+        .unwrap();
+
+        let matched = segment.read_filtered(None, None, Some(&filter));
+        for point_id in 0..10u64 {
+            let explain = segment.explain_filter(&filter, point_id.into()).unwrap();
+            assert_eq!(explain.matched, matched.contains(&point_id.into()));
+        }
+
+        let explain = segment.explain_filter(&filter, 1.into()).unwrap();
+        let should = explain.should.unwrap();
+        assert!(should[0].matched);
+        assert_eq!(should[0].values, vec![serde_json::json!(1)]);
+        assert!(explain.must_not.unwrap()[0].matched);
+
+        let counts = segment.explain_filter_counts(&filter).unwrap();
+        assert_eq!(counts.matched, matched.len());
+        let should = counts.should.unwrap();
+        assert_eq!(should[0].matched, 3);
+        assert_eq!(should[1].matched, 1);
+        assert_eq!(counts.must_not.unwrap()[0].matched, 1);
+
+        assert!(segment.explain_filter(&filter, 100.into()).is_err());
+    }
+
+    #[test]
+    fn test_strict_filter() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let payload: Payload = serde_json::from_str(
+            r#"{"city": "Berlin", "rating": 5, "items": [{"color": "red", "size": 3}]}"#,
+        )
+        .unwrap();
+        segment
+            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
+            .unwrap();
+        segment.set_full_payload(1, 0.into(), &payload).unwrap();
+        segment
+            .create_field_index(2, "city", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+        segment
+            .create_field_index(3, "items[].color", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+
+        let search = |filter: &str, strict_filter: bool| {
+            let filter: Filter = serde_json::from_str(filter).unwrap();
+            segment.search(
+                DEFAULT_VECTOR_NAME,
+                &[1.0, 1.0],
+                &WithPayload::default(),
+                &false.into(),
+                Some(&filter),
+                10,
+                Some(&SearchParams {
+                    strict_filter,
+                    ..Default::default()
+                }),
+            )
+        };
+
+        let indexed = r#"{"must": [{"key": "city", "match": {"value": "Berlin"}}]}"#;
+        let unindexed = r#"{"should": [{"must": [{"key": "rating", "range": {"gt": 3}}]}]}"#;
+        let unknown = r#"{"must_not": [{"is_empty": {"key": "citty"}}]}"#;
+        let nested = |key: &str, condition: &str| {
+            format!(
+                r#"{{"must": [{{"nested": {{"key": "items", "filter": {{"must": [{{"key": "{key}", {condition}}}]}}}}}}]}}"#
+            )
+        };
+        let nested_indexed = nested("color", r#""match": {"value": "red"}"#);
+        let nested_unindexed = nested("size", r#""range": {"gte": 3}"#);
+
+        // Permissive by default
+        assert_eq!(search(indexed, false).unwrap().len(), 1);
+        assert_eq!(search(unindexed, false).unwrap().len(), 1);
+        assert!(search(unknown, false).unwrap().is_empty());
+        assert_eq!(search(&nested_indexed, false).unwrap().len(), 1);
+        assert_eq!(search(&nested_unindexed, false).unwrap().len(), 1);
+
+        assert_eq!(search(indexed, true).unwrap().len(), 1);
+        // Keys of the nested filter are checked within the array
+        assert_eq!(search(&nested_indexed, true).unwrap().len(), 1);
+        assert!(matches!(
+            search(&nested_unindexed, true),
+            Err(OperationError::UnindexedField { field_name }) if field_name == "items[].size"
+        ));
+        assert!(matches!(
+            search(unindexed, true),
+            Err(OperationError::UnindexedField { field_name }) if field_name == "rating"
+        ));
+        assert!(matches!(
+            search(unknown, true),
+            Err(OperationError::UnknownField { field_name }) if field_name == "citty"
+        ));
+    }
+
+    #[test]
+    fn test_search_timeout() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 256;
+        let num_points = 50_000;
+        let config = segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rng = rand::thread_rng();
+        for (op_num, chunk) in (0..num_points)
+            .collect::<Vec<u64>>()
+            .chunks(1000)
+            .enumerate()
+        {
+            let point_ids: Vec<PointIdType> = chunk.iter().map(|&id| id.into()).collect();
+            let vectors: Vec<_> = chunk
+                .iter()
+                .map(|_| {
+                    let vector: Vec<_> = (0..dim).map(|_| rng.gen::<f32>()).collect();
+                    only_default_vector(&vector)
+                })
+                .collect();
+            segment
+                .upsert_vectors(op_num as SeqNumberType, &point_ids, &vectors)
+                .unwrap();
+        }
+
+        let query = vec![1.0; dim];
+        let search = |params: Option<&SearchParams>| {
+            let start = std::time::Instant::now();
+            let result = segment.search(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                params,
+            );
+            (result, start.elapsed())
+        };
+
+        let (full, full_elapsed) = search(None);
+        assert_eq!(full.unwrap().len(), 10);
+
+        let timeout = SearchParams {
+            timeout_ms: Some(1),
+            ..Default::default()
+        };
+        let (timed_out, elapsed) = search(Some(&timeout));
+        assert!(matches!(
+            timed_out,
+            Err(OperationError::Timeout { timeout_ms: 1 })
+        ));
+        assert!(
+            elapsed < full_elapsed / 2,
+            "search is stopped in {elapsed:?}, full search takes {full_elapsed:?}"
+        );
+
+        let partial = SearchParams {
+            timeout_ms: Some(1),
+            partial_on_timeout: true,
+            ..Default::default()
+        };
+        let (partial_result, elapsed) = search(Some(&partial));
+        assert!(partial_result.unwrap().len() <= 10);
+        assert!(elapsed < full_elapsed / 2);
+
+        // Generous timeout is not reached
+        let generous = SearchParams {
+            timeout_ms: Some(60_000),
+            ..Default::default()
+        };
+        assert_eq!(search(Some(&generous)).0.unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_has_vector_condition() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let vector_config = VectorDataConfig {
+            size: 2,
+            distance: Distance::Dot,
+            hnsw_config: None,
+            quantization_config: None,
+        };
+        let config = SegmentConfig {
+            vector_data: HashMap::from([
+                ("image".to_owned(), vector_config.clone()),
+                ("text".to_owned(), vector_config),
+            ]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for idx in 0..3 {
+            let vectors = NamedVectors::from([
+                ("image".to_owned(), vec![1.0, 1.0]),
+                ("text".to_owned(), vec![1.0, 0.0]),
+            ]);
+            segment.upsert_vector(idx, idx.into(), &vectors).unwrap();
+        }
+
+        // Every point of the segment has all of its vectors
+        let missing_text = NamedVectors::from([("image".to_owned(), vec![1.0, 1.0])]);
+        assert!(matches!(
+            segment.upsert_vector(3, 3.into(), &missing_text),
+            Err(OperationError::MissedVectorName { .. })
+        ));
+
+        let has_vector = |name: &str| Condition::HasVector(name.to_owned().into());
+        let check = |filter: Filter, expected: usize| {
+            let estimation = segment.estimate_points_count(Some(&filter));
+            assert_eq!(estimation.min, expected);
+            assert_eq!(estimation.max, expected);
+            assert_eq!(
+                segment.read_filtered(None, None, Some(&filter)).len(),
+                expected
+            );
+            let found = segment
+                .search(
+                    "image",
+                    &[1.0, 1.0],
+                    &WithPayload::default(),
+                    &false.into(),
+                    Some(&filter),
+                    10,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(found.len(), expected);
+        };
+
+        check(Filter::new_must(has_vector("image")), 3);
+        check(Filter::new_must_not(has_vector("image")), 0);
+        check(Filter::new_must(has_vector("audio")), 0);
+        check(Filter::new_must_not(has_vector("audio")), 3);
+        check(
+            Filter::new_should(Condition::Filter(Filter {
+                should: None,
+                must: Some(vec![has_vector("text")]),
+                must_not: Some(vec![Condition::HasId(HashSet::from([0.into()]).into())]),
+            })),
+            2,
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let data = r#"
+        {
+            "name": "John Doe",
+            "age": 43,
+            "metadata": {
+                "height": 50,
+                "width": 60
+            }
+        }"#;
+
+        let segment_base_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(segment_base_dir.path(), &config).unwrap();
+
+        segment
+            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
+            .unwrap();
+
+        segment
+            .set_full_payload(1, 0.into(), &serde_json::from_str(data).unwrap())
+            .unwrap();
+
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+
+        // snapshotting!
+        let archive = segment
+            .take_snapshot(snapshot_dir.path(), Default::default())
+            .unwrap();
+        let archive_extension = archive.extension().unwrap();
+        let archive_name = archive.file_name().unwrap().to_str().unwrap().to_string();
+
+        // correct file extension
+        assert_eq!(archive_extension, "tar");
+
+        // archive name contains segment id
+        let segment_id = segment
+            .current_path
+            .file_stem()
+            .and_then(|f| f.to_str())
+            .unwrap();
+        assert!(archive_name.starts_with(segment_id));
+
+        // restore snapshot
+        Segment::restore_snapshot(&archive, &snapshot_dir.path().join(segment_id), false).unwrap();
+
+        let restored_segment = load_segment(&snapshot_dir.path().join(segment_id))
+            .unwrap()
+            .unwrap();
+
+        // validate restored snapshot is the same as original segment
+        assert_eq!(segment.vector_dims(), restored_segment.vector_dims());
+
+        assert_eq!(segment.points_count(), restored_segment.points_count());
+
+        for id in segment.iter_points() {
+            let vectors = segment.all_vectors(id).unwrap();
+            let restored_vectors = restored_segment.all_vectors(id).unwrap();
+            assert_eq!(vectors, restored_vectors);
+
+            let payload = segment.payload(id).unwrap();
+            let restored_payload = restored_segment.payload(id).unwrap();
+            assert_eq!(payload, restored_payload);
+        }
+    }
+
+    #[test]
+    fn test_restore_snapshot_round_trip() {
+        let segment_base_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let restore_dir = Builder::new().prefix("restore_dir").tempdir().unwrap();
+        let config = segment_config(
+            4,
+            Distance::Cosine,
+            Indexes::Plain {},
+            StorageType::InMemory,
+        );
+
+        let mut rng = rand::thread_rng();
+        let mut segment = build_segment(segment_base_dir.path(), &config).unwrap();
+        for point_id in 0..100u64 {
+            let vector: Vec<_> = (0..4).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+            let payload: Payload =
+                serde_json::from_value(serde_json::json!({ "number": point_id })).unwrap();
+            segment
+                .set_full_payload(point_id, point_id.into(), &payload)
+                .unwrap();
+        }
+        let queries: Vec<Vec<VectorElementType>> = (0..10)
+            .map(|_| (0..4).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+        let search = |segment: &Segment| {
+            queries
+                .iter()
+                .map(|query| {
+                    segment
+                        .search(
+                            DEFAULT_VECTOR_NAME,
+                            query,
+                            &true.into(),
+                            &true.into(),
+                            None,
+                            10,
+                            None,
+                        )
+                        .unwrap()
+                        .into_iter()
+                        .map(|point| (point.id, point.score, point.payload, point.vector))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let expected = search(&segment);
+
+        let archive = segment
+            .take_snapshot(snapshot_dir.path(), Default::default())
+            .unwrap();
+        let original_path = segment.current_path.clone();
+        drop(segment);
+        fs::remove_dir_all(&original_path).unwrap();
+
+        let restore_path = restore_dir.path().join("restored");
+        let restored = Segment::load_snapshot(&archive, &restore_path, false).unwrap();
+        assert_eq!(search(&restored), expected);
+        drop(restored);
+
+        // Non-empty target is replaced only by force
+        let err = Segment::restore_snapshot(&archive, &restore_path, false).unwrap_err();
+        assert!(err.to_string().contains("is not empty"), "{err}");
+        assert!(load_segment(&restore_path).unwrap().is_some());
+        let restored = Segment::load_snapshot(&archive, &restore_path, true).unwrap();
+        assert_eq!(search(&restored), expected);
+        assert!(!temp_path(&restore_path).exists());
+    }
+
+    #[test]
+    fn test_restore_vectors_only_snapshot() {
+        use crate::fixtures::payload_fixtures::{random_filter, random_vector};
+        use crate::segment_constructor::segment_builder::SegmentBuilder;
+        use crate::segment_constructor::simple_segment_constructor::build_simple_segment;
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let restore_dir = Builder::new().prefix("restore_dir").tempdir().unwrap();
+        let dim = 4;
+        let stopped = AtomicBool::new(false);
+        let mut rng = rand::thread_rng();
+
+        let mut plain_segment = build_simple_segment(dir.path(), dim, Distance::Dot).unwrap();
+        for point_id in 0..200u64 {
+            let vector = random_vector(&mut rng, dim);
+            plain_segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+            let payload: Payload =
+                serde_json::from_value(serde_json::json!({ "number": point_id })).unwrap();
+            plain_segment
+                .set_full_payload(point_id, point_id.into(), &payload)
+                .unwrap();
+        }
+        let config = SegmentConfig {
+            vector_data: plain_segment.segment_config.vector_data.clone(),
+            index: Indexes::Hnsw(HnswConfig {
+                full_scan_threshold: 1,
+                ..Default::default()
+            }),
+            storage_type: StorageType::Mmap,
+            ..Default::default()
+        };
+        let mut builder = SegmentBuilder::new(dir.path(), temp_dir.path(), &config).unwrap();
+        builder.update_from(&plain_segment, &stopped).unwrap();
+        let segment = builder.build(&stopped).unwrap();
+
+        let contents = SnapshotContents {
+            vectors: true,
+            payload: false,
+            index: false,
+        };
+        let archive = segment
+            .take_snapshot(snapshot_dir.path(), contents)
+            .unwrap();
+        let restore_path = restore_dir.path().join("restored");
+        Segment::restore_snapshot(&archive, &restore_path, false).unwrap();
+        assert_eq!(
+            Segment::load_state(&restore_path).unwrap().contents,
+            contents
+        );
+
+        // Vector index is rebuilt on load
+        let restored = load_segment(&restore_path).unwrap().unwrap();
+        assert!(restored.contents.vectors && restored.contents.index);
+        assert!(!restored.contents.payload);
+        assert!(restored.vector_index_info(DEFAULT_VECTOR_NAME).is_built);
+        assert_eq!(restored.points_count(), segment.points_count());
+
+        let exact = SearchParams {
+            exact: true,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            let query = random_vector(&mut rng, dim);
+            let search = |segment: &Segment, params| {
+                segment
+                    .search(
+                        DEFAULT_VECTOR_NAME,
+                        &query,
+                        &false.into(),
+                        &true.into(),
+                        None,
+                        5,
+                        params,
+                    )
+                    .unwrap()
+                    .into_iter()
+                    .map(|point| (point.id, point.score, point.vector))
+                    .collect::<Vec<_>>()
+            };
+            let expected = search(&segment, Some(&exact));
+            assert_eq!(search(&restored, Some(&exact)), expected);
+            assert_eq!(search(&restored, None), expected);
+        }
+
+        // Operations, which require the payload, are rejected
+        assert!(restored.payload(0.into()).is_err());
+        let query = random_vector(&mut rng, dim);
+        let filter = random_filter(&mut rng, 1);
+        assert!(restored
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &false.into(),
+                &false.into(),
+                Some(&filter),
+                5,
+                None,
+            )
+            .is_err());
+        assert!(restored
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &true.into(),
+                &false.into(),
+                None,
+                5,
+                None,
+            )
+            .is_err());
+        drop(restored);
+
+        // Partial segment is kept partial by the next load
+        let restored = load_segment(&restore_path).unwrap().unwrap();
+        assert!(!restored.contents.payload);
+    }
+
+    #[test]
+    fn test_restore_snapshot_of_newer_format() {
+        let segment_base_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+        let mut segment = build_segment(segment_base_dir.path(), &config).unwrap();
+        segment
+            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
+            .unwrap();
+        let archive = segment
+            .take_snapshot(snapshot_dir.path(), Default::default())
+            .unwrap();
+
+        // Archive of a segment, written by a newer version of the layout
+        let unpacked_dir = Builder::new().prefix("unpacked_dir").tempdir().unwrap();
+        tar::Archive::new(File::open(&archive).unwrap())
+            .unpack(unpacked_dir.path())
+            .unwrap();
+        let files_path = unpacked_dir
+            .path()
+            .join(SNAPSHOT_PATH)
+            .join(SNAPSHOT_FILES_PATH);
+        fs::write(
+            files_path.join(FORMAT_VERSION_FILE),
+            (CURRENT_FORMAT_VERSION + 1).to_string(),
+        )
+        .unwrap();
+        let newer_archive = snapshot_dir.path().join("newer.tar");
+        let mut builder = tar::Builder::new(File::create(&newer_archive).unwrap());
+        builder
+            .append_dir_all(SNAPSHOT_PATH, unpacked_dir.path().join(SNAPSHOT_PATH))
+            .unwrap();
+        builder.finish().unwrap();
+
+        let restore_path = snapshot_dir.path().join("restored");
+        let err = Segment::restore_snapshot(&newer_archive, &restore_path, false).unwrap_err();
+        assert!(matches!(
+            err,
+            OperationError::UnsupportedFormatVersion { .. }
+        ));
+        assert!(!restore_path.exists());
+        assert!(!temp_path(&restore_path).exists());
+    }
+
+    #[test]
+    fn test_background_flush() {
+        let data = r#"
+        {
+            "name": "John Doe",
+            "age": 43,
+            "metadata": {
+                "height": 50,
+                "width": 60
+            }
+        }"#;
+
+        let segment_base_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(segment_base_dir.path(), &config).unwrap();
+        segment
+            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
+            .unwrap();
+
+        let payload: Payload = serde_json::from_str(data).unwrap();
+        segment.set_full_payload(0, 0.into(), &payload).unwrap();
+        segment.flush(false).unwrap();
+
+        // call flush second time to check that background flush finished successful
+        segment.flush(true).unwrap();
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            payload_storage_type: Default::default(),
+            quantization_config: None,
+            filter_cache_size: 0,
+            id_tracker_type: Default::default(),
+            indexing_threshold: None,
+            max_search_threads: None,
+            flush_policy: Default::default(),
+            checksum_verification: Default::default(),
+            background_flush_interval_ms: None,
+            vector_layout: Default::default(),
+            unknown_fields: Default::default(),
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+
+        let vec4 = vec![1.1, 1.0, 0.0, 1.0];
+        segment
+            .upsert_vector(100, 4.into(), &only_default_vector(&vec4))
+            .unwrap();
+        let vec6 = vec![1.0, 1.0, 0.5, 1.0];
+        segment
+            .upsert_vector(101, 6.into(), &only_default_vector(&vec6))
+            .unwrap();
+
+        // first pass on consistent data
+        segment.check_consistency_and_repair(None).unwrap();
+
+        let query_vector = vec![1.0, 1.0, 1.0, 1.0];
+        let search_result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query_vector,
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(search_result.len(), 2);
+        assert_eq!(search_result[0].id, 6.into());
+        assert_eq!(search_result[1].id, 4.into());
+
+        assert!(matches!(
+            segment.vector(DEFAULT_VECTOR_NAME, 6.into()),
+            Ok(_)
+        ));
+
+        let internal_id = segment.lookup_internal_id(6.into()).unwrap();
+
+        // make id_tracker inconsistent
+        segment.id_tracker.write().drop(6.into()).unwrap();
+
+        let search_result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query_vector,
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                None,
+            )
+            .unwrap();
+
+        // only one result because of inconsistent id_tracker
+        assert_eq!(search_result.len(), 1);
+        assert_eq!(search_result[0].id, 4.into());
+
+        // querying by external id is broken
+        assert!(
+            matches!(segment.vector(DEFAULT_VECTOR_NAME, 6.into()), Err(PointIdError {missed_point_id }) if missed_point_id == 6.into())
+        );
+
+        // but querying by internal id still works
+        matches!(
+            segment.vector_by_offset(DEFAULT_VECTOR_NAME, internal_id),
+            Ok(Some(_))
+        );
+
+        // fix segment's data
+        segment.check_consistency_and_repair(None).unwrap();
+
+        // querying by internal id now consistent
+        matches!(
+            segment.vector_by_offset(DEFAULT_VECTOR_NAME, internal_id),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_find_orphans() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            filter_cache_size: 4,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let payload: Payload = serde_json::json!({"color": "red"}).into();
+        for idx in 0..3 {
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+            segment
+                .set_full_payload(idx + 10, idx.into(), &payload)
+                .unwrap();
+        }
+        segment.delete_point(20, 1.into()).unwrap();
+        // Vectors of deleted points are expected to stay in storage
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
+
+        // Vector, written without linking, and payload of the deleted point
+        segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_storage
+            .write()
+            .insert_vector(3, &[1.0, 0.0])
+            .unwrap();
+        segment.payload_index.write().assign(1, &payload).unwrap();
+
+        let report = segment.find_orphans(false, None).unwrap();
+        assert_eq!(report.mapped_without_vectors, 0);
+        assert_eq!(report.vectors_without_mapping, 1);
+        assert_eq!(report.vectors_without_mapping_offsets, vec![3]);
+        assert_eq!(report.payloads_without_mapping, 1);
+        assert_eq!(report.payloads_without_mapping_offsets, vec![1]);
+        assert!(!report.repaired);
+
+        let filter: Filter =
+            serde_json::from_str(r#"{"must": [{"key": "color", "match": {"value": "red"}}]}"#)
+                .unwrap();
+        let cache_misses = |segment: &Segment| {
+            segment.read_filtered(None, None, Some(&filter));
+            segment.get_telemetry_data().filter_cache.unwrap().misses
+        };
+        let misses = cache_misses(&segment);
+        assert_eq!(cache_misses(&segment), misses);
+
+        assert!(segment.find_orphans(true, None).unwrap().repaired);
+        // Filter, cached before the repair, is resolved again
+        assert_eq!(cache_misses(&segment), misses + 1);
+        assert!(segment.payload_index.read().payload(1).unwrap().is_empty());
+        let report = segment.find_orphans(false, None).unwrap();
+        assert_eq!(report.payloads_without_mapping, 0);
+        // Vectors can't be removed from storage
+        assert_eq!(report.vectors_without_mapping, 1);
+
+        // Point, linked to the offset without vectors
+        segment.id_tracker.write().set_link(100.into(), 10).unwrap();
+        let report = segment.find_orphans(true, None).unwrap();
+        assert_eq!(report.mapped_without_vectors, 1);
+        assert_eq!(
+            report.mapped_without_vectors_ids,
+            vec![PointIdType::NumId(100)]
+        );
+        assert!(!segment.has_point(100.into()));
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_point_tombstone() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let vector = only_default_vector(&[1.0, 1.0]);
+        let payload: Payload = serde_json::json!({"owner": "a"}).into();
+
+        segment.upsert_vector(1, 1.into(), &vector).unwrap();
+        segment.set_full_payload(2, 1.into(), &payload).unwrap();
+        let offset_a = segment.lookup_internal_id(1.into()).unwrap();
+        assert!(segment.delete_point(3, 1.into()).unwrap());
+        assert!(segment
+            .payload_index
+            .read()
+            .payload(offset_a)
+            .unwrap()
+            .is_empty());
+
+        // New point must not inherit the payload of the deleted one
+        segment.upsert_vector(4, 2.into(), &vector).unwrap();
+        assert!(segment.payload(2.into()).unwrap().is_empty());
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
+
+        // Replayed upsert, which is older than the deletion, is ignored
+        assert!(!segment.upsert_vector(1, 1.into(), &vector).unwrap());
+        assert!(!segment.has_point(1.into()));
+
+        // Newer upsert inserts the point again
+        segment.upsert_vector(5, 1.into(), &vector).unwrap();
+        assert!(segment.has_point(1.into()));
+        assert!(segment.payload(1.into()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_upsert_vectors_block() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let ids: Vec<PointIdType> = vec![1.into(), 2.into(), 3.into()];
+        let vectors = vec![
+            only_default_vector(&[1.0, 0.0]),
+            only_default_vector(&[2.0, 0.0]),
+            only_default_vector(&[3.0, 0.0]),
+        ];
+        let res = segment.upsert_vectors(1, &ids, &vectors).unwrap();
+        assert_eq!(res, vec![false, false, false]);
+        for (offset, point_id) in ids.iter().enumerate() {
+            assert_eq!(
+                segment.lookup_internal_id(point_id.clone()).unwrap(),
+                offset as PointOffsetType
             );
-            Ok(current_persisted_version.unwrap_or(0))
+            assert_eq!(segment.point_version(point_id.clone()), Some(1));
         }
-    }
 
-    fn drop_data(self) -> OperationResult<()> {
-        let current_path = self.current_path.clone();
-        drop(self);
-        let mut deleted_path = current_path.clone();
-        deleted_path.set_extension("deleted");
-        fs::rename(&current_path, &deleted_path)?;
-        fs::remove_dir_all(&deleted_path).map_err(|err| {
-            OperationError::service_error(format!(
-                "Can't remove segment data at {}, error: {}",
-                deleted_path.to_str().unwrap_or_default(),
-                err
-            ))
-        })
-    }
+        // Existing points are updated in-place, new ones are appended
+        let ids: Vec<PointIdType> = vec![2.into(), 4.into()];
+        let vectors = vec![
+            only_default_vector(&[20.0, 0.0]),
+            only_default_vector(&[4.0, 0.0]),
+        ];
+        let res = segment.upsert_vectors(2, &ids, &vectors).unwrap();
+        assert_eq!(res, vec![true, false]);
+        assert_eq!(segment.lookup_internal_id(2.into()).unwrap(), 1);
+        assert_eq!(segment.lookup_internal_id(4.into()).unwrap(), 3);
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 2.into()).unwrap(),
+            vec![20.0, 0.0]
+        );
 
-    fn data_path(&self) -> PathBuf {
-        self.current_path.clone()
-    }
+        // Wrong dimension in the middle of the block, nothing is written
+        let ids: Vec<PointIdType> = vec![5.into(), 6.into()];
+        let vectors = vec![
+            only_default_vector(&[5.0, 0.0]),
+            only_default_vector(&[6.0, 0.0, 0.0]),
+        ];
+        assert!(segment.upsert_vectors(3, &ids, &vectors).is_err());
+        assert!(!segment.has_point(5.into()));
+        assert_eq!(
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .read()
+                .total_vector_count(),
+            4
+        );
 
-    fn delete_field_index(&mut self, op_num: u64, key: PayloadKeyTypeRef) -> OperationResult<bool> {
-        self.handle_version_and_failure(op_num, None, |segment| {
-            segment.payload_index.borrow_mut().drop_index(key)?;
-            Ok((true, None))
-        })
-    }
+        // Block, interrupted after writing vectors, but before linking ids
+        let offsets = segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_storage
+            .write()
+            .put_vectors(&[vec![5.0, 0.0], vec![6.0, 0.0]])
+            .unwrap();
+        assert_eq!(offsets, 4..6);
+        assert_eq!(
+            segment
+                .find_orphans(false, None)
+                .unwrap()
+                .vectors_without_mapping,
+            2
+        );
 
-    fn create_field_index(
-        &mut self,
-        op_num: u64,
-        key: PayloadKeyTypeRef,
-        field_type: Option<&PayloadFieldSchema>,
-    ) -> OperationResult<bool> {
-        self.handle_version_and_failure(op_num, None, |segment| match field_type {
-            Some(schema) => {
-                segment
-                    .payload_index
-                    .borrow_mut()
-                    .set_indexed(key, schema.clone())?;
-                Ok((true, None))
-            }
-            None => match segment.infer_from_payload_data(key)? {
-                None => Err(TypeInferenceError {
-                    field_name: key.to_string(),
-                }),
-                Some(schema_type) => {
-                    segment
-                        .payload_index
-                        .borrow_mut()
-                        .set_indexed(key, schema_type.into())?;
-                    Ok((true, None))
-                }
-            },
-        })
-    }
+        // Retry allocates the next block after unlinked vectors, which are treated as deleted
+        let vectors = vec![
+            only_default_vector(&[5.0, 0.0]),
+            only_default_vector(&[6.0, 0.0]),
+        ];
+        let res = segment.upsert_vectors(4, &ids, &vectors).unwrap();
+        assert_eq!(res, vec![false, false]);
+        assert_eq!(segment.lookup_internal_id(5.into()).unwrap(), 6);
+        assert_eq!(segment.lookup_internal_id(6.into()).unwrap(), 7);
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 6.into()).unwrap(),
+            vec![6.0, 0.0]
+        );
+        assert_eq!(segment.points_count(), 6);
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
 
-    fn get_indexed_fields(&self) -> HashMap<PayloadKeyType, PayloadFieldSchema> {
-        self.payload_index.borrow().indexed_fields()
+        // Single upsert continues after the block
+        segment
+            .upsert_vector(5, 7.into(), &only_default_vector(&[7.0, 0.0]))
+            .unwrap();
+        assert_eq!(segment.lookup_internal_id(7.into()).unwrap(), 8);
     }
 
-    fn check_error(&self) -> Option<SegmentFailedState> {
-        self.error_status.clone()
-    }
+    #[test]
+    fn test_info_stats_cache() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
 
-    fn delete_filtered<'a>(
-        &'a mut self,
-        op_num: SeqNumberType,
-        filter: &'a Filter,
-    ) -> OperationResult<usize> {
-        let mut deleted_points = 0;
-        for point_id in self.read_filtered(None, None, Some(filter)) {
-            deleted_points += self.delete_point(op_num, point_id)? as usize;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let payload: Payload = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        for point_id in 0..10u64 {
+            segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&[1.0, 0.0]))
+                .unwrap();
+            segment
+                .set_full_payload(point_id, point_id.into(), &payload)
+                .unwrap();
         }
 
-        Ok(deleted_points)
+        let info = segment.info();
+        assert!(info.ram_usage_bytes >= 10 * 2 * VECTOR_ELEMENT_SIZE);
+        assert!(info.disk_usage_bytes > 0);
+        assert_eq!(segment.stats_cache.computations(), 1);
+
+        // Payload storage is not walked again without writes
+        for _ in 0..5 {
+            assert_eq!(segment.info().ram_usage_bytes, info.ram_usage_bytes);
+        }
+        assert_eq!(segment.stats_cache.computations(), 1);
+
+        // Write invalidates the stats, counts are not cached
+        segment.delete_point(10, 0.into()).unwrap();
+        let info_after_delete = segment.info();
+        assert_eq!(info_after_delete.num_points, 9);
+        assert!(info_after_delete.ram_usage_bytes < info.ram_usage_bytes);
+        assert_eq!(segment.stats_cache.computations(), 2);
+
+        segment
+            .create_field_index(11, "color", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+        assert_eq!(segment.info().index_schema["color"].points, 9);
+        assert_eq!(segment.stats_cache.computations(), 3);
     }
 
-    fn vector_dim(&self, vector_name: &str) -> OperationResult<usize> {
-        check_vector_name(vector_name, &self.segment_config)?;
-        let vector_data_config = &self.segment_config.vector_data[vector_name];
-        Ok(vector_data_config.size)
+    #[test]
+    fn test_vector_updates_keep_payload() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        segment
+            .create_field_index(1, "color", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+        let red: Payload = serde_json::json!({ "color": "red", "tags": ["a", "b"] }).into();
+        let blue: Payload = serde_json::json!({ "color": "blue", "tags": ["c"] }).into();
+        for point_id in 0..10 {
+            let op_num = 10 + point_id * 2;
+            segment
+                .upsert_vector(op_num, point_id.into(), &only_default_vector(&[1.0, 0.0]))
+                .unwrap();
+            let payload = if point_id % 2 == 0 { &red } else { &blue };
+            segment
+                .set_full_payload(op_num + 1, point_id.into(), payload)
+                .unwrap();
+        }
+        let serialized_payloads = segment.payload_index.read().serialized_payloads();
+        let filter: Filter =
+            serde_json::from_str(r#"{"must": [{"key": "color", "match": {"value": "red"}}]}"#)
+                .unwrap();
+
+        // Vector-only updates keep the internal ids, payloads are not touched
+        for round in 0..20 {
+            for point_id in 0..10 {
+                let op_num = 100 + round * 10 + point_id;
+                let vector = [round as f32, point_id as f32];
+                assert!(segment
+                    .upsert_vector(op_num, point_id.into(), &only_default_vector(&vector))
+                    .unwrap());
+            }
+        }
+        assert_eq!(
+            segment.payload_index.read().serialized_payloads(),
+            serialized_payloads
+        );
+        for point_id in 0..10 {
+            let expected = if point_id % 2 == 0 { &red } else { &blue };
+            assert_eq!(
+                segment.lookup_internal_id(point_id.into()).unwrap(),
+                point_id as PointOffsetType
+            );
+            assert_eq!(*segment.payload(point_id.into()).unwrap(), *expected);
+        }
+        assert_eq!(
+            segment.read_filtered(None, None, Some(&filter)),
+            vec![0.into(), 2.into(), 4.into(), 6.into(), 8.into()]
+        );
+
+        // Moved payload is relabeled in the storage and in the field indexes
+        segment.payload_index.write().move_entry(0, 1).unwrap();
+        assert_eq!(
+            segment.payload_index.read().serialized_payloads(),
+            serialized_payloads
+        );
+        assert!(segment.payload(0.into()).unwrap().is_empty());
+        assert_eq!(*segment.payload(1.into()).unwrap(), red);
+        assert_eq!(
+            segment.read_filtered(None, None, Some(&filter)),
+            vec![1.into(), 2.into(), 4.into(), 6.into(), 8.into()]
+        );
     }
 
-    fn vector_dims(&self) -> HashMap<String, usize> {
-        self.segment_config
-            .vector_data
-            .iter()
-            .map(|(vector_name, vector_config)| (vector_name.clone(), vector_config.size))
-            .collect()
+    #[test]
+    fn test_upsert_points_batch_rollback() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let payload: Payload = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        let ids: Vec<PointIdType> = vec![1.into(), 2.into()];
+        let vectors = vec![
+            only_default_vector(&[1.0, 0.0]),
+            only_default_vector(&[2.0, 0.0]),
+        ];
+        let res = segment
+            .upsert_points(1, &ids, &vectors, &[Some(&payload), None])
+            .unwrap();
+        assert_eq!(res, vec![false, false]);
+        assert_eq!(*segment.payload(1.into()).unwrap(), payload);
+        assert!(segment.payload(2.into()).unwrap().is_empty());
+        assert_eq!(segment.version(), 1);
+
+        // Batch, which fails after all of its writes, leaves the segment as it was
+        let new_payload: Payload = serde_json::from_str(r#"{"color": "blue"}"#).unwrap();
+        let ids: Vec<PointIdType> = vec![1.into(), 3.into()];
+        let vectors = vec![
+            only_default_vector(&[10.0, 0.0]),
+            only_default_vector(&[3.0, 0.0]),
+        ];
+        let payloads = [Some(&new_payload), Some(&new_payload)];
+        let batch = segment.prepare_points_batch(2, &ids, &vectors).unwrap();
+        assert_eq!(batch.updated, vec![(0, 0)]);
+        assert_eq!(batch.inserted_offsets(), 2..3);
+        let mut undo = BatchUndo::default();
+        segment
+            .apply_points_batch(2, &ids, Some(&payloads), &batch, &mut undo)
+            .unwrap();
+        assert!(segment.has_point(3.into()));
+        assert_eq!(segment.point_version(1.into()), Some(2));
+        segment.rollback_points_batch(undo);
+
+        assert!(!segment.has_point(3.into()));
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 1.into()).unwrap(),
+            vec![1.0, 0.0]
+        );
+        assert_eq!(*segment.payload(1.into()).unwrap(), payload);
+        assert_eq!(segment.point_version(1.into()), Some(1));
+        assert_eq!(segment.points_count(), 2);
+        // Appended vector is unlinked, so it is treated as deleted
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
+
+        // Retry is applied after the unlinked vector, the version is bumped once
+        let res = segment.upsert_points(2, &ids, &vectors, &payloads).unwrap();
+        assert_eq!(res, vec![true, false]);
+        assert_eq!(segment.lookup_internal_id(3.into()).unwrap(), 3);
+        assert_eq!(*segment.payload(3.into()).unwrap(), new_payload);
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 1.into()).unwrap(),
+            vec![10.0, 0.0]
+        );
+        assert_eq!(segment.version(), 2);
+
+        // Points, updated by a later operation, are skipped
+        let res = segment
+            .upsert_points(1, &ids[..1], &vectors[..1], &[Some(&payload)])
+            .unwrap();
+        assert_eq!(res, vec![false]);
+        assert_eq!(*segment.payload(1.into()).unwrap(), new_payload);
     }
 
-    fn take_snapshot(&self, snapshot_dir_path: &Path) -> OperationResult<PathBuf> {
-        log::debug!(
-            "Taking snapshot of segment {:?} into {:?}",
-            self.current_path,
-            snapshot_dir_path
+    #[test]
+    fn test_upsert_points_batch_duplicates() {
+        use crate::segment_constructor::simple_segment_constructor::build_simple_segment;
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let mut segment = build_simple_segment(dir.path(), 2, Distance::Dot).unwrap();
+        let red: Payload = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        let blue: Payload = serde_json::from_str(r#"{"color": "blue"}"#).unwrap();
+        let ids: Vec<PointIdType> = vec![1.into(), 2.into(), 1.into()];
+        let vectors = vec![
+            only_default_vector(&[1.0, 0.0]),
+            only_default_vector(&[2.0, 0.0]),
+            only_default_vector(&[3.0, 0.0]),
+        ];
+
+        // Only the last occurrence of the point is appended
+        let batch = segment.prepare_points_batch(1, &ids, &vectors).unwrap();
+        assert_eq!(batch.inserted, vec![1, 2]);
+        assert_eq!(batch.inserted_offsets(), 0..2);
+        segment
+            .upsert_points(1, &ids, &vectors, &[Some(&red), None, Some(&blue)])
+            .unwrap();
+        assert_eq!(segment.points_count(), 2);
+        assert_eq!(segment.id_tracker.read().internal_size(), 2);
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 1.into()).unwrap(),
+            vec![3.0, 0.0]
         );
+        assert_eq!(*segment.payload(1.into()).unwrap(), blue);
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
 
-        if !snapshot_dir_path.exists() {
-            return Err(OperationError::service_error(format!(
-                "the snapshot path {snapshot_dir_path:?} does not exist"
-            )));
-        }
+        // Same for the existing points
+        let res = segment
+            .upsert_points(2, &ids, &vectors, &[Some(&blue), None, Some(&red)])
+            .unwrap();
+        assert_eq!(res, vec![false, true, true]);
+        assert_eq!(segment.id_tracker.read().internal_size(), 2);
+        assert_eq!(*segment.payload(1.into()).unwrap(), red);
+    }
 
-        if !snapshot_dir_path.is_dir() {
-            return Err(OperationError::service_error(format!(
-                "the snapshot path {snapshot_dir_path:?} is not a directory",
-            )));
+    #[test]
+    fn test_update_index_config() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(
+            2,
+            Distance::Euclid,
+            Indexes::Plain {},
+            StorageType::InMemory,
+        );
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 8,
+            ef_construct: 16,
+            full_scan_threshold: 10,
+            max_indexing_threads: 1,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for idx in 0..100u64 {
+            let vector = [idx as VectorElementType, 1.0];
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
         }
+        let search = |segment: &Segment| {
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &[50.0, 1.0],
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    1,
+                    None,
+                )
+                .unwrap()[0]
+                .id
+                .clone()
+        };
 
-        // flush segment to capture latest state
-        self.flush(true)?;
+        // Deferred rebuild, the old index is still used
+        assert!(segment.update_index_config(100, hnsw_index, false).unwrap());
+        assert!(segment.info().index_outdated);
+        assert_eq!(segment.config().index, Indexes::Plain {});
+        assert_eq!(search(&segment), 50.into());
 
-        let tmp_path = self.current_path.join(format!("tmp-{}", Uuid::new_v4()));
+        // Pending config survives reload
+        segment.flush(true).unwrap();
+        let segment_path = segment.current_path.clone();
+        drop(segment);
+        let mut segment = load_segment(&segment_path).unwrap().unwrap();
+        assert!(segment.info().index_outdated);
+        assert_eq!(segment.pending_index, Some(hnsw_index));
+        assert_eq!(segment.segment_type(), SegmentType::Plain);
+
+        assert!(segment.rebuild_index(100, None).unwrap());
+        assert!(!segment.info().index_outdated);
+        assert_eq!(segment.config().index, hnsw_index);
+        assert_eq!(segment.segment_type(), SegmentType::Indexed);
+        assert_eq!(search(&segment), 50.into());
+        // Without pending config, the current one is rebuilt
+        assert!(segment.rebuild_index(100, None).unwrap());
+        assert_eq!(segment.config().index, hnsw_index);
 
-        let db_backup_path = tmp_path.join(DB_BACKUP_PATH);
-        let payload_index_db_backup_path = tmp_path.join(PAYLOAD_DB_BACKUP_PATH);
+        segment.flush(true).unwrap();
+        drop(segment);
+        let mut segment = load_segment(&segment_path).unwrap().unwrap();
+        assert!(!segment.info().index_outdated);
+        assert_eq!(segment.config().index, hnsw_index);
+        assert_eq!(search(&segment), 50.into());
+
+        // Immediate rebuild
+        assert!(segment
+            .update_index_config(101, Indexes::Plain {}, true)
+            .unwrap());
+        assert!(!segment.info().index_outdated);
+        assert_eq!(segment.config().index, Indexes::Plain {});
+        assert_eq!(search(&segment), 50.into());
+
+        // Outdated operation is skipped
+        assert!(!segment.update_index_config(1, hnsw_index, false).unwrap());
+        assert!(!segment.info().index_outdated);
+    }
 
-        {
-            let db = self.database.read();
-            crate::rocksdb_backup::create(&db, &db_backup_path)?;
-        }
+    #[test]
+    fn test_indexing_threshold() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let config = SegmentConfig {
+            // 64 vectors of dim 4
+            indexing_threshold: Some(1),
+            ..segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory)
+        };
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 8,
+            ef_construct: 16,
+            full_scan_threshold: 10,
+            max_indexing_threads: 1,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
 
-        self.payload_index
-            .borrow()
-            .take_database_snapshot(&payload_index_db_backup_path)?;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        assert!(segment.update_index_config(0, hnsw_index, false).unwrap());
 
-        let segment_id = self
-            .current_path
-            .file_stem()
-            .and_then(|f| f.to_str())
-            .unwrap();
+        let upsert = |segment: &mut Segment, ids: std::ops::Range<u64>| {
+            for idx in ids {
+                let vector = vec![idx as VectorElementType; dim];
+                segment
+                    .upsert_vector(idx + 1, idx.into(), &only_default_vector(&vector))
+                    .unwrap();
+            }
+        };
+        let search = |segment: &Segment| {
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &vec![1.0; dim],
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    1,
+                    None,
+                )
+                .unwrap()[0]
+                .id
+                .clone()
+        };
+        let searches = |segment: &Segment| {
+            let telemetry = segment.get_telemetry_data();
+            let vector_index_searches = &telemetry.vector_index_searches[0];
+            (
+                vector_index_searches.unfiltered_plain.count,
+                vector_index_searches.unfiltered_hnsw.count,
+            )
+        };
 
-        let archive_path = snapshot_dir_path.join(format!("{segment_id}.tar"));
+        // Below threshold, plain index is used
+        upsert(&mut segment, 0..50);
+        assert!(!segment.index_required());
+        assert!(!segment.info().index_outdated);
+        assert_eq!(search(&segment), 49.into());
+        assert_eq!(searches(&segment), (1, 0));
+
+        upsert(&mut segment, 50..100);
+        assert!(segment.index_required());
+        assert!(segment.info().index_outdated);
+        // Index is not built until requested
+        assert_eq!(search(&segment), 99.into());
+        assert_eq!(searches(&segment), (2, 0));
+
+        assert!(segment.rebuild_index(100, None).unwrap());
+        assert!(!segment.index_required());
+        assert_eq!(segment.segment_type(), SegmentType::Indexed);
+        assert_eq!(search(&segment), 99.into());
+        assert_eq!(searches(&segment), (0, 1));
+
+        // Repeated build keeps the applied config
+        assert!(segment.rebuild_index(100, None).unwrap());
+        assert!(!segment.index_required());
+        assert_eq!(search(&segment), 99.into());
+        assert_eq!(searches(&segment), (0, 1));
+    }
 
-        // If `archive_path` exists, we still want to overwrite it
-        let file = File::create(&archive_path).map_err(|err| {
-            OperationError::service_error(format!(
-                "failed to create segment snapshot archive {archive_path:?}: {err}"
-            ))
-        })?;
+    #[test]
+    fn test_build_index_in_background() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 1000u64;
+        let config = segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 8,
+            ef_construct: 16,
+            full_scan_threshold: 10,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
 
-        let mut builder = Builder::new(file);
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for idx in 0..num_points {
+            let vector = vec![idx as VectorElementType; dim];
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let rebuild_path =
+            get_vector_index_rebuild_path(&segment.current_path, DEFAULT_VECTOR_NAME);
+        let search = |segment: &Segment| {
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &vec![1.0; dim],
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    1,
+                    None,
+                )
+                .unwrap()[0]
+                .id
+                .clone()
+        };
 
-        builder
-            .append_dir_all(SNAPSHOT_PATH, &tmp_path)
-            .map_err(|err| utils::tar::failed_to_append_error(&tmp_path, err))?;
+        // Cancelled build leaves the current index
+        let update_guard = segment.update_lock.write();
+        let handle = segment.build_index_in_background(hnsw_index).unwrap();
+        // Build can't start reading points, while the lock is held
+        handle.cancel();
+        drop(update_guard);
+        assert!(!segment.install_built_index(handle).unwrap());
+        assert!(!rebuild_path.exists());
+        assert_eq!(segment.segment_type(), SegmentType::Plain);
+
+        // Points, added during the build, are not covered by the new index
+        let handle = segment.build_index_in_background(hnsw_index).unwrap();
+        let vector = vec![num_points as VectorElementType; dim];
+        segment
+            .upsert_vector(num_points, num_points.into(), &only_default_vector(&vector))
+            .unwrap();
+        assert!(segment.install_built_index(handle).is_err());
+        assert!(!rebuild_path.exists());
+        assert_eq!(segment.config().index, Indexes::Plain {});
+        assert_eq!(search(&segment), num_points.into());
 
-        let files = Path::new(SNAPSHOT_PATH).join(SNAPSHOT_FILES_PATH);
+        // Deletions are applied concurrently with the build
+        let mut handle = segment.build_index_in_background(hnsw_index).unwrap();
+        segment
+            .delete_point(num_points + 1, num_points.into())
+            .unwrap();
+        assert_eq!(search(&segment), (num_points - 1).into());
+        segment
+            .delete_point(num_points + 2, (num_points - 1).into())
+            .unwrap();
+        handle.join().unwrap();
+        assert!(handle.is_finished());
+        assert_eq!(handle.progress(), 1.0);
+        // Searches use the old index until the new one is installed
+        assert_eq!(segment.config().index, Indexes::Plain {});
+        assert_eq!(search(&segment), (num_points - 2).into());
+
+        assert!(segment.install_built_index(handle).unwrap());
+        assert!(!rebuild_path.exists());
+        assert_eq!(segment.config().index, hnsw_index);
+        assert_eq!(segment.segment_type(), SegmentType::Indexed);
+        assert_eq!(search(&segment), (num_points - 2).into());
+        let telemetry = segment.get_telemetry_data();
+        assert_eq!(telemetry.vector_index_searches[0].unfiltered_hnsw.count, 1);
+
+        // Index survives reload
+        segment.flush(true).unwrap();
+        let segment_path = segment.current_path.clone();
+        drop(segment);
+        let segment = load_segment(&segment_path).unwrap().unwrap();
+        assert_eq!(segment.config().index, hnsw_index);
+        assert_eq!(search(&segment), (num_points - 2).into());
+    }
 
-        for vector_data in self.vector_data.values() {
-            for file in vector_data.vector_index.borrow().files() {
-                utils::tar::append_file_relative_to_base(
-                    &mut builder,
-                    &self.current_path,
-                    &file,
-                    &files,
-                )?;
-            }
+    #[test]
+    fn test_rebuild_index_after_deletes() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 1000u64;
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 8,
+            ef_construct: 32,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
+        let config = segment_config(dim, Distance::Euclid, hnsw_index, StorageType::InMemory);
 
-            for file in vector_data.vector_storage.borrow().files() {
-                utils::tar::append_file_relative_to_base(
-                    &mut builder,
-                    &self.current_path,
-                    &file,
-                    &files,
-                )?;
-            }
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
         }
+        let mut op_num = num_points;
+        assert!(segment.rebuild_index(op_num, None).unwrap());
 
-        for file in self.payload_index.borrow().files() {
-            utils::tar::append_file_relative_to_base(
-                &mut builder,
-                &self.current_path,
-                &file,
-                &files,
-            )?;
+        for idx in (0..num_points).step_by(2) {
+            op_num += 1;
+            segment.delete_point(op_num, idx.into()).unwrap();
         }
 
-        utils::tar::append_file(
-            &mut builder,
-            &self.current_path.join(SEGMENT_STATE_FILE),
-            &files.join(SEGMENT_STATE_FILE),
-        )?;
+        // Cancelled build keeps the current index
+        let stopped = AtomicBool::new(true);
+        let error = segment.rebuild_index(op_num, Some(&stopped)).unwrap_err();
+        assert!(matches!(error, OperationError::Cancelled { .. }));
+        assert!(segment.check_error().is_none());
+        let rebuild_path =
+            get_vector_index_rebuild_path(&segment.current_path, DEFAULT_VECTOR_NAME);
+        assert!(!rebuild_path.exists());
+
+        // Outdated operation is skipped
+        assert!(!segment.rebuild_index(1, None).unwrap());
+
+        assert!(segment.rebuild_index(op_num, None).unwrap());
+        assert!(!segment.info().index_outdated);
+
+        let vector_data = &segment.vector_data[DEFAULT_VECTOR_NAME];
+        let id_tracker = segment.id_tracker.read();
+        match &*vector_data.vector_index.read() {
+            VectorIndexEnum::HnswRam(index) => {
+                let graph = index.graph().unwrap();
+                for offset in 0..num_points as PointOffsetType {
+                    graph.links_map(offset, 0, |link| {
+                        assert!(!id_tracker.is_deleted(link), "deleted {link} is linked");
+                        assert!(!id_tracker.is_deleted(offset), "deleted {offset} has links");
+                    });
+                }
+            }
+            _ => panic!("HNSW index is expected"),
+        }
+        drop(id_tracker);
+
+        let mut found = 0;
+        let top = 10;
+        let queries = 20;
+        for _ in 0..queries {
+            let query: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            let search = |exact| {
+                segment
+                    .search(
+                        DEFAULT_VECTOR_NAME,
+                        &query,
+                        &WithPayload::default(),
+                        &false.into(),
+                        None,
+                        top,
+                        Some(&SearchParams {
+                            exact,
+                            ..Default::default()
+                        }),
+                    )
+                    .unwrap()
+            };
+            let exact_result = search(true);
+            let index_result = search(false);
+            assert_eq!(index_result.len(), top);
+            found += index_result
+                .iter()
+                .filter(|point| exact_result.iter().any(|exact| exact.id == point.id))
+                .count();
+        }
+        let recall = found as f64 / (queries * top) as f64;
+        assert!(recall >= 0.9, "recall = {recall}");
+    }
 
-        utils::tar::append_file(
-            &mut builder,
-            &self.current_path.join(VERSION_FILE),
-            &files.join(VERSION_FILE),
-        )?;
+    #[test]
+    fn test_estimate_recall() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 200u64;
+        let config = segment_config(
+            dim,
+            Distance::Euclid,
+            Indexes::Plain {},
+            StorageType::InMemory,
+        );
 
-        builder.finish()?;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let filter = Filter::new_must(Condition::HasId(
+            (0..num_points)
+                .step_by(2)
+                .map(PointIdType::from)
+                .collect::<HashSet<_>>()
+                .into(),
+        ));
 
-        // remove tmp directory in background
-        let _ = std::thread::spawn(move || {
-            let res = std::fs::remove_dir_all(&tmp_path);
-            if let Err(err) = res {
-                log::error!(
-                    "Failed to remove tmp directory at {}: {:?}",
-                    tmp_path.display(),
-                    err
-                );
-            }
+        // Plain index is exact
+        let recall = |segment: &Segment, filter: Option<&Filter>| {
+            let params = SearchParams {
+                hnsw_ef: Some(num_points as usize),
+                ..Default::default()
+            };
+            segment
+                .estimate_recall(DEFAULT_VECTOR_NAME, 50, 10, filter, Some(&params))
+                .unwrap()
+        };
+        assert_eq!(recall(&segment, None), 1.0);
+        assert_eq!(recall(&segment, Some(&filter)), 1.0);
+
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 16,
+            ef_construct: 100,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
         });
+        assert!(segment
+            .update_index_config(num_points, hnsw_index, true)
+            .unwrap());
+        assert!(matches!(
+            &*segment.vector_data[DEFAULT_VECTOR_NAME].vector_index.read(),
+            VectorIndexEnum::HnswRam(_)
+        ));
 
-        Ok(archive_path)
+        // Beam covers the whole small graph
+        assert_eq!(recall(&segment, None), 1.0);
+        let filtered_recall = recall(&segment, Some(&filter));
+        assert!(filtered_recall >= 0.9, "recall = {filtered_recall}");
+
+        // No points to compare
+        let empty_filter = Filter::new_must(Condition::HasId(HashSet::new().into()));
+        assert_eq!(recall(&segment, Some(&empty_filter)), 1.0);
     }
 
-    fn get_telemetry_data(&self) -> SegmentTelemetry {
-        let vector_index_searches: Vec<_> = self
-            .vector_data
-            .iter()
-            .map(|(k, v)| {
-                let mut telemetry = v.vector_index.borrow().get_telemetry_data();
-                telemetry.index_name = Some(k.clone());
-                telemetry
-            })
-            .collect();
+    #[test]
+    fn test_index_stats() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 500u64;
+        let config = segment_config(
+            dim,
+            Distance::Euclid,
+            Indexes::Plain {},
+            StorageType::InMemory,
+        );
 
-        SegmentTelemetry {
-            info: self.info(),
-            config: self.config(),
-            vector_index_searches,
-            payload_field_indices: self.payload_index.borrow().get_telemetry_data(),
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        assert!(segment
+            .index_stats(DEFAULT_VECTOR_NAME, true)
+            .unwrap()
+            .is_none());
+        assert!(segment.index_stats("unknown", true).is_err());
+
+        let m = 16;
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m,
+            ef_construct: 64,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
+        let mut op_num = num_points;
+        assert!(segment
+            .update_index_config(op_num, hnsw_index, true)
+            .unwrap());
+        // Deleted points are not accounted
+        for idx in (0..num_points).step_by(5) {
+            op_num += 1;
+            segment.delete_point(op_num, idx.into()).unwrap();
         }
-    }
-}
 
-impl Drop for Segment {
-    fn drop(&mut self) {
-        let _lock = self.lock_flushing();
+        let stats = segment
+            .index_stats(DEFAULT_VECTOR_NAME, true)
+            .unwrap()
+            .unwrap();
+        let points_count = segment.points_count();
+        assert_eq!(stats.levels[0].points, points_count);
+        let top_level_points: usize = stats
+            .levels
+            .iter()
+            .map(|level| level.top_level_points)
+            .sum();
+        assert_eq!(top_level_points, points_count);
+        for pair in stats.levels.windows(2) {
+            assert!(pair[0].points >= pair[1].points);
+            assert_eq!(pair[0].points - pair[0].top_level_points, pair[1].points);
+        }
+        for level in &stats.levels {
+            assert!(level.min_degree as f64 <= level.avg_degree);
+            assert!(level.avg_degree <= level.max_degree as f64);
+        }
+        assert!(stats.levels[0].max_degree <= m * 2);
+        assert!(stats.links_bytes > 0);
+        let unreachable_points = stats.unreachable_points.unwrap();
+        assert!(
+            unreachable_points < points_count / 10,
+            "unreachable = {unreachable_points}"
+        );
+
+        let fast_stats = segment
+            .index_stats(DEFAULT_VECTOR_NAME, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fast_stats.unreachable_points, None);
+        assert_eq!(fast_stats.levels, stats.levels);
+        let json = serde_json::to_value(&fast_stats).unwrap();
+        assert!(json.get("unreachable_points").is_none());
+
+        // Points, deleted after the build, are still linked
+        let deleted_count = num_points as usize - points_count;
+        assert_eq!(stats.linked_points, num_points as usize);
+        assert_eq!(stats.deleted_points, deleted_count);
+        assert_eq!(
+            stats.deleted_ratio,
+            deleted_count as f64 / num_points as f64
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use tempfile::Builder;
+    #[test]
+    fn test_estimate_search_cost() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 10_000u64;
+        let config = segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
 
-    use super::*;
-    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
-    use crate::entry::entry_point::OperationError::PointIdError;
-    use crate::segment_constructor::{build_segment, load_segment};
-    use crate::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let top = 10;
+        let estimate =
+            |segment: &Segment, filter: Option<&Filter>, params: Option<&SearchParams>| {
+                segment
+                    .estimate_search_cost(DEFAULT_VECTOR_NAME, filter, top, params)
+                    .unwrap()
+            };
+        assert!(segment
+            .estimate_search_cost("unknown", None, top, None)
+            .is_err());
+
+        let full_scan = estimate(&segment, None, None);
+        assert!(full_scan.full_scan);
+        assert_eq!(full_scan.scored_points, num_points as usize);
+        assert_eq!(full_scan.index_hops, 0);
+
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 16,
+            ef_construct: 64,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
+        assert!(segment
+            .update_index_config(num_points, hnsw_index, true)
+            .unwrap());
 
-    // no longer valid since users are now allowed to store arbitrary json objects.
-    // TODO(gvelo): add tests for invalid payload types on indexed fields.
-    // #[test]
-    // fn test_set_invalid_payload_from_json() {
-    //     let data1 = r#"
-    //     {
-    //         "invalid_data"
-    //     }"#;
-    //     let data2 = r#"
-    //     {
-    //         "array": [1, "hello"],
-    //     }"#;
-    //
-    //     let dir = Builder::new().prefix("payload_dir").tempdir().unwrap();
-    //     let dim = 2;
-    //     let config = SegmentConfig {
-    //         vector_size: dim,
-    //         index: Indexes::Plain {},
-    //         payload_index: Some(PayloadIndexType::Plain),
-    //         storage_type: StorageType::InMemory,
-    //         distance: Distance::Dot,
-    //     };
-    //
-    //     let mut segment =
-    //         build_segment(dir.path(), &config, Arc::new(SchemaStorage::new())).unwrap();
-    //     segment.upsert_point(0, 0.into(), &[1.0, 1.0]).unwrap();
-    //
-    //     let result1 = segment.set_full_payload_with_json(0, 0.into(), &data1.to_string());
-    //     assert!(result1.is_err());
-    //
-    //     let result2 = segment.set_full_payload_with_json(0, 0.into(), &data2.to_string());
-    //     assert!(result2.is_err());
-    // }
+        let indexed = estimate(&segment, None, None);
+        assert!(!indexed.full_scan);
+        assert!(indexed.index_hops > 0);
+        assert!(
+            indexed.scored_points * 4 < full_scan.scored_points,
+            "{indexed:?} vs {full_scan:?}",
+        );
+        let small_ef = estimate(
+            &segment,
+            None,
+            Some(&SearchParams {
+                hnsw_ef: Some(top),
+                ..Default::default()
+            }),
+        );
+        assert!(small_ef.scored_points < indexed.scored_points);
+
+        // Exact search scores all points
+        let exact = estimate(
+            &segment,
+            None,
+            Some(&SearchParams {
+                exact: true,
+                ..Default::default()
+            }),
+        );
+        assert_eq!(exact, full_scan);
+
+        // Small filters are checked without the graph
+        let small_filter = Filter::new_must(Condition::HasId(
+            (0..5u64).map(Into::into).collect::<HashSet<_>>().into(),
+        ));
+        let filtered = estimate(&segment, Some(&small_filter), None);
+        assert!(filtered.full_scan);
+        assert_eq!(filtered.scored_points, 5);
+    }
 
     #[test]
-    fn test_search_batch_equivalence_single() {
+    fn test_explain_search() {
         let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
         let dim = 4;
-        let config = SegmentConfig {
-            vector_data: HashMap::from([(
-                DEFAULT_VECTOR_NAME.to_owned(),
-                VectorDataConfig {
-                    size: dim,
-                    distance: Distance::Dot,
-                    hnsw_config: None,
-                    quantization_config: None,
-                },
-            )]),
-            index: Indexes::Plain {},
-            storage_type: StorageType::InMemory,
-            ..Default::default()
-        };
+        let config = segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
         let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        let num_points = 1_000u64;
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let query = vec![1.0; dim];
+        let filter = Filter::new_must(Condition::HasId(
+            (0..10u64).map(Into::into).collect::<HashSet<_>>().into(),
+        ));
+        assert!(segment
+            .explain_search(DEFAULT_VECTOR_NAME, &query[1..], None, 10, None)
+            .is_err());
 
-        let vec4 = vec![1.1, 1.0, 0.0, 1.0];
-        segment
-            .upsert_vector(100, 4.into(), &only_default_vector(&vec4))
-            .unwrap();
-        let vec6 = vec![1.0, 1.0, 0.5, 1.0];
-        segment
-            .upsert_vector(101, 6.into(), &only_default_vector(&vec6))
+        let plan = segment
+            .explain_search(DEFAULT_VECTOR_NAME, &query, Some(&filter), 10, None)
             .unwrap();
-        segment.delete_point(102, 1.into()).unwrap();
-
-        let query_vector = vec![1.0, 1.0, 1.0, 1.0];
-        let search_result = segment
-            .search(
-                DEFAULT_VECTOR_NAME,
-                &query_vector,
-                &WithPayload::default(),
-                &false.into(),
-                None,
-                10,
-                None,
-            )
+        assert_eq!(plan.index_type, VectorIndexType::Plain);
+        assert_eq!(plan.strategy, SearchStrategy::FullScan);
+        assert_eq!(plan.reason, PlanReason::NoIndex);
+        assert_eq!(plan.filter_cardinality.unwrap().exp, 10);
+
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 16,
+            ef_construct: 64,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
+        assert!(segment
+            .update_index_config(num_points, hnsw_index, true)
+            .unwrap());
+        let plan = segment
+            .explain_search(DEFAULT_VECTOR_NAME, &query, None, 10, None)
             .unwrap();
-        eprintln!("search_result = {search_result:#?}");
+        assert_eq!(plan.index_type, VectorIndexType::Hnsw);
+        assert_eq!(plan.strategy, SearchStrategy::Index);
+        assert_eq!(plan.reason, PlanReason::Unfiltered);
+        assert_eq!(plan.ef, Some(64));
 
-        let search_batch_result = segment
-            .search_batch(
-                DEFAULT_VECTOR_NAME,
-                &[&query_vector],
-                &WithPayload::default(),
-                &false.into(),
-                None,
-                10,
-                None,
-            )
+        let plan = segment
+            .explain_search(DEFAULT_VECTOR_NAME, &query, Some(&filter), 10, None)
             .unwrap();
-        eprintln!("search_batch_result = {search_batch_result:#?}");
-
-        assert!(!search_result.is_empty());
-        assert_eq!(search_result, search_batch_result[0].clone())
+        assert_eq!(plan.strategy, SearchStrategy::FullScan);
+        assert_eq!(plan.reason, PlanReason::SmallCardinality);
     }
 
     #[test]
-    fn test_from_filter_attributes() {
-        let data = r#"
-        {
-            "name": "John Doe",
-            "age": 43,
-            "metadata": {
-                "height": 50,
-                "width": 60
-            }
-        }"#;
-
-        let dir = Builder::new().prefix("payload_dir").tempdir().unwrap();
-        let dim = 2;
+    fn test_per_vector_indexes() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let num_points = 500u64;
+        let hnsw_config = |m| HnswConfig {
+            m,
+            ef_construct: 64,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        };
+        let dims = [("text", 4), ("image", 8)];
         let config = SegmentConfig {
-            vector_data: HashMap::from([(
-                DEFAULT_VECTOR_NAME.to_owned(),
-                VectorDataConfig {
-                    size: dim,
-                    distance: Distance::Dot,
-                    hnsw_config: None,
-                    quantization_config: None,
-                },
-            )]),
+            vector_data: HashMap::from([
+                (
+                    "text".to_owned(),
+                    VectorDataConfig {
+                        size: 4,
+                        distance: Distance::Cosine,
+                        hnsw_config: Some(hnsw_config(4)),
+                        quantization_config: None,
+                    },
+                ),
+                (
+                    "image".to_owned(),
+                    VectorDataConfig {
+                        size: 8,
+                        distance: Distance::Euclid,
+                        hnsw_config: None,
+                        quantization_config: None,
+                    },
+                ),
+            ]),
             index: Indexes::Plain {},
             storage_type: StorageType::InMemory,
             ..Default::default()
         };
 
         let mut segment = build_segment(dir.path(), &config).unwrap();
-        segment
-            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
-            .unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vectors = NamedVectors::from(dims.map(|(vector_name, dim)| {
+                let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+                (vector_name.to_owned(), vector)
+            }));
+            segment.upsert_vector(idx, idx.into(), &vectors).unwrap();
+        }
 
-        let payload: Payload = serde_json::from_str(data).unwrap();
+        // Text vectors override the config of the segment index
+        let mut op_num = num_points;
+        assert!(segment
+            .update_index_config(op_num, Indexes::Hnsw(hnsw_config(32)), true)
+            .unwrap());
+        let info = segment.info();
+        for (vector_name, _) in dims {
+            let vector_index_info = &info.vector_indexes[vector_name];
+            assert_eq!(vector_index_info.index_type, VectorIndexType::Hnsw);
+            assert!(vector_index_info.is_built);
+        }
+        assert_eq!(info.vector_indexes["text"].hnsw_config.unwrap().m, 4);
+        assert_eq!(info.vector_indexes["image"].hnsw_config.unwrap().m, 32);
+        let max_degree = |segment: &Segment, vector_name: &str| {
+            segment
+                .index_stats(vector_name, false)
+                .unwrap()
+                .unwrap()
+                .levels[0]
+                .max_degree
+        };
+        assert!(max_degree(&segment, "text") <= 4 * 2);
+        assert!(max_degree(&segment, "image") > 4 * 2);
+
+        // Only the index of the changed vector is rebuilt
+        let image_index = segment.vector_data["image"].vector_index.clone();
+        let text_index = segment.vector_data["text"].vector_index.clone();
+        op_num += 1;
+        assert!(segment
+            .update_vector_index_config(op_num, "text", Some(hnsw_config(16)), true)
+            .unwrap());
+        assert!(Arc::ptr_eq(
+            &segment.vector_data["image"].vector_index,
+            &image_index
+        ));
+        assert!(!Arc::ptr_eq(
+            &segment.vector_data["text"].vector_index,
+            &text_index
+        ));
+        assert_eq!(
+            segment.config().vector_data["text"].hnsw_config.unwrap().m,
+            16
+        );
+        assert_eq!(
+            segment.info().vector_indexes["text"].hnsw_config.unwrap().m,
+            16
+        );
+        op_num += 1;
+        assert!(segment
+            .update_vector_index_config(op_num, "audio", None, true)
+            .is_err());
+
+        // Searches are routed to the index of the requested vector,
+        // deleted points are excluded by all of them
+        op_num += 1;
+        segment.delete_point(op_num, 0.into()).unwrap();
+        for (vector_name, _) in dims {
+            let query = segment.vector(vector_name, 1.into()).unwrap();
+            let result = segment
+                .search(
+                    vector_name,
+                    &query,
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    10,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(result.len(), 10);
+            assert_eq!(result[0].id, 1.into());
+            assert!(result.iter().all(|point| point.id != 0.into()));
+        }
+    }
 
-        segment.set_full_payload(0, 0.into(), &payload).unwrap();
+    #[test]
+    fn test_search_iter() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 300u64;
+        let top = 100;
+        let config = segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
 
-        let filter_valid_str = r#"
-        {
-            "must": [
-                {
-                    "key": "metadata.height",
-                    "match": {
-                        "value": 50
-                    }
-                }
-            ]
-        }"#;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let filter = Filter::new_must(Condition::HasId(
+            (0..num_points)
+                .step_by(3)
+                .map(PointIdType::from)
+                .collect::<HashSet<_>>()
+                .into(),
+        ));
+        let query: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+
+        let check = |segment: &Segment, filter: Option<&Filter>| {
+            let expected = segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &true.into(),
+                    &false.into(),
+                    filter,
+                    top,
+                    None,
+                )
+                .unwrap();
+            for batch_size in [1, 7, top * 2] {
+                let result = segment
+                    .search_iter(
+                        DEFAULT_VECTOR_NAME,
+                        &query,
+                        &true.into(),
+                        &false.into(),
+                        filter,
+                        top,
+                        None,
+                        batch_size,
+                    )
+                    .unwrap()
+                    .collect::<OperationResult<Vec<_>>>()
+                    .unwrap();
+                let points = |result: &[ScoredPoint]| {
+                    result
+                        .iter()
+                        .map(|point| (point.id.clone(), point.score, point.payload.clone()))
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(
+                    points(&result),
+                    points(&expected),
+                    "batch_size = {batch_size}"
+                );
+            }
+        };
 
-        let filter_valid: Filter = serde_json::from_str(filter_valid_str).unwrap();
-        let filter_invalid_str = r#"
-        {
-            "must": [
-                {
-                    "key": "metadata.height",
-                    "match": {
-                        "value": 60
-                    }
-                }
-            ]
-        }"#;
+        check(&segment, None);
+        check(&segment, Some(&filter));
+
+        let hnsw_index = Indexes::Hnsw(HnswConfig {
+            m: 16,
+            ef_construct: 100,
+            full_scan_threshold: 1,
+            max_indexing_threads: 2,
+            on_disk: None,
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        });
+        assert!(segment
+            .update_index_config(num_points, hnsw_index, true)
+            .unwrap());
 
-        let filter_invalid: Filter = serde_json::from_str(filter_invalid_str).unwrap();
-        let results_with_valid_filter = segment
-            .search(
+        check(&segment, None);
+        check(&segment, Some(&filter));
+
+        let wrong_vector = vec![1.0; dim + 1];
+        assert!(segment
+            .search_iter(
                 DEFAULT_VECTOR_NAME,
-                &[1.0, 1.0],
-                &WithPayload::default(),
+                &wrong_vector,
+                &true.into(),
                 &false.into(),
-                Some(&filter_valid),
-                1,
                 None,
-            )
-            .unwrap();
-        assert_eq!(results_with_valid_filter.len(), 1);
-        assert_eq!(results_with_valid_filter.first().unwrap().id, 0.into());
-        let results_with_invalid_filter = segment
-            .search(
-                DEFAULT_VECTOR_NAME,
-                &[1.0, 1.0],
-                &WithPayload::default(),
-                &false.into(),
-                Some(&filter_invalid),
-                1,
+                top,
                 None,
+                10,
             )
-            .unwrap();
-        assert!(results_with_invalid_filter.is_empty());
+            .is_err());
     }
 
     #[test]
-    fn test_snapshot() {
-        let data = r#"
-        {
-            "name": "John Doe",
-            "age": 43,
-            "metadata": {
-                "height": 50,
-                "width": 60
-            }
-        }"#;
-
-        let segment_base_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
-        let config = SegmentConfig {
-            vector_data: HashMap::from([(
-                DEFAULT_VECTOR_NAME.to_owned(),
-                VectorDataConfig {
-                    size: 2,
-                    distance: Distance::Dot,
-                    hnsw_config: None,
-                    quantization_config: None,
-                },
-            )]),
-            index: Indexes::Plain {},
-            storage_type: StorageType::InMemory,
-            ..Default::default()
+    fn test_search_among() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let dim = 4;
+        let num_points = 100u64;
+        let config = segment_config(dim, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut rnd = rand::thread_rng();
+        for idx in 0..num_points {
+            let vector: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+            segment
+                .upsert_vector(idx, idx.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        segment.delete_point(num_points, 5.into()).unwrap();
+
+        let query: Vec<_> = (0..dim).map(|_| rnd.gen::<VectorElementType>()).collect();
+        // Duplicated, deleted and missing candidates
+        let candidates: Vec<PointIdType> = [3, 10, 3, 5, 42, 1000, 77, 10]
+            .into_iter()
+            .map(PointIdType::from)
+            .collect();
+
+        let search = |top: usize| {
+            segment
+                .search_among(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &candidates,
+                    &false.into(),
+                    &true.into(),
+                    top,
+                    None,
+                )
+                .unwrap()
         };
 
-        let mut segment = build_segment(segment_base_dir.path(), &config).unwrap();
+        let mut expected: Vec<(PointIdType, ScoreType)> = [3u64, 10, 42, 77]
+            .into_iter()
+            .map(|idx| {
+                let vector = segment.vector(DEFAULT_VECTOR_NAME, idx.into()).unwrap();
+                let score = vector.iter().zip(&query).map(|(a, b)| a * b).sum();
+                (idx.into(), score)
+            })
+            .collect();
+        expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let result = search(10);
+        assert_eq!(result.len(), expected.len());
+        for (scored_point, (point_id, score)) in result.iter().zip(&expected) {
+            assert_eq!(scored_point.id, *point_id);
+            assert!((scored_point.score - score).abs() < 1e-5);
+            assert!(scored_point.vector.is_some());
+        }
 
-        segment
-            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
-            .unwrap();
+        let result = search(2);
+        let ids: Vec<_> = result.iter().map(|point| point.id.clone()).collect();
+        let expected_ids: Vec<_> = expected.iter().take(2).map(|(id, _)| *id).collect();
+        assert_eq!(ids, expected_ids);
+    }
 
-        segment
-            .set_full_payload(1, 0.into(), &serde_json::from_str(data).unwrap())
-            .unwrap();
+    #[test]
+    fn test_sample_ids_with_filter() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
 
-        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let num_points = 200u64;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for point_id in 0..num_points {
+            let op_num = point_id * 2;
+            let payload: Payload = serde_json::json!({ "num": point_id }).into();
+            segment
+                .upsert_vector(op_num, point_id.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+            segment
+                .set_full_payload(op_num + 1, point_id.into(), &payload)
+                .unwrap();
+        }
+        segment.delete_point(2 * num_points, 10.into()).unwrap();
 
-        // snapshotting!
-        let archive = segment.take_snapshot(snapshot_dir.path()).unwrap();
-        let archive_extension = archive.extension().unwrap();
-        let archive_name = archive.file_name().unwrap().to_str().unwrap().to_string();
+        let filter: Filter =
+            serde_json::from_str(r#"{"must": [{"key": "num", "range": {"lt": 100}}]}"#).unwrap();
+        let matching: Vec<PointIdType> = (0..100u64)
+            .filter(|&point_id| point_id != 10)
+            .map(Into::into)
+            .collect();
 
-        // correct file extension
-        assert_eq!(archive_extension, "tar");
+        let sample = segment.sample_ids(Some(&filter), 10, Some(42));
+        assert_eq!(sample.len(), 10);
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 10);
+        assert!(sample.iter().all(|point_id| matching.contains(point_id)));
+        assert_eq!(sample, segment.sample_ids(Some(&filter), 10, Some(42)));
+        assert_ne!(sample, segment.sample_ids(Some(&filter), 10, Some(43)));
+
+        // All matching points, if there are not enough of them
+        let mut sample = segment.sample_ids(Some(&filter), 1000, None);
+        sample.sort_unstable();
+        assert_eq!(sample, matching);
+        let sample = segment.sample_ids(None, 1000, None);
+        assert_eq!(sample.len(), num_points as usize - 1);
+        assert!(segment.sample_ids(Some(&filter), 0, None).is_empty());
+
+        // Each matching point is sampled about equally often
+        let (runs, k) = (3000, 5);
+        let mut counts: HashMap<PointIdType, usize> = HashMap::new();
+        for seed in 0..runs {
+            for point_id in segment.sample_ids(Some(&filter), k, Some(seed)) {
+                *counts.entry(point_id).or_default() += 1;
+            }
+        }
+        assert_eq!(counts.len(), matching.len());
+        let expected = (runs as usize * k) as f64 / matching.len() as f64;
+        let chi_square: f64 = counts
+            .values()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // 99.9% quantile of chi-square distribution with 98 degrees of freedom is about 148
+        assert!(chi_square < 148.0, "chi_square = {chi_square}");
+    }
 
-        // archive name contains segment id
-        let segment_id = segment
-            .current_path
-            .file_stem()
-            .and_then(|f| f.to_str())
-            .unwrap();
-        assert!(archive_name.starts_with(segment_id));
+    #[test]
+    fn test_score_boost() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
 
-        // restore snapshot
-        Segment::restore_snapshot(&archive, segment_id).unwrap();
+        let num_points = 100u64;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for point_id in 0..num_points {
+            // Score of the point is `point_id / 100`
+            let vector = [point_id as VectorElementType / 100.0, 1.0];
+            segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let payloads = [
+            (96u64, serde_json::json!({ "popularity": 10 })),
+            (98, serde_json::json!({ "popularity": "high" })),
+            // Too far from the top to be found
+            (0, serde_json::json!({ "popularity": 1000 })),
+        ];
+        for (op_num, (point_id, payload)) in payloads.into_iter().enumerate() {
+            segment
+                .set_full_payload(
+                    num_points + op_num as SeqNumberType,
+                    point_id.into(),
+                    &payload.into(),
+                )
+                .unwrap();
+        }
 
-        let restored_segment = load_segment(&snapshot_dir.path().join(segment_id))
-            .unwrap()
-            .unwrap();
+        let query = [1.0, 0.0];
+        let search = |segment: &Segment, function: Option<BoostFunction>| {
+            let params = SearchParams {
+                score_boost: function.map(|function| ScoreBoost {
+                    key: "popularity".to_owned(),
+                    factor: 0.1,
+                    function,
+                }),
+                ..Default::default()
+            };
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &false.into(),
+                    &false.into(),
+                    None,
+                    3,
+                    Some(&params),
+                )
+                .unwrap()
+                .into_iter()
+                .map(|point| (point.id, point.score))
+                .collect::<Vec<_>>()
+        };
+        let assert_result = |result: Vec<(PointIdType, ScoreType)>,
+                             expected: &[(u64, ScoreType)]| {
+            assert_eq!(result.len(), expected.len(), "{result:?}");
+            for ((point_id, score), (expected_id, expected_score)) in result.iter().zip(expected) {
+                assert_eq!(*point_id, (*expected_id).into(), "{result:?}");
+                assert!((score - expected_score).abs() < 1e-4, "{result:?}");
+            }
+        };
 
-        // validate restored snapshot is the same as original segment
-        assert_eq!(segment.vector_dims(), restored_segment.vector_dims());
+        for rebuild_with_hnsw in [false, true] {
+            if rebuild_with_hnsw {
+                let hnsw_index = Indexes::Hnsw(HnswConfig {
+                    m: 16,
+                    ef_construct: 64,
+                    full_scan_threshold: 1,
+                    max_indexing_threads: 2,
+                    on_disk: None,
+                    payload_m: None,
+                    neighbor_selection: None,
+                    seed: None,
+                    incremental: None,
+                    ef_policy: None,
+                    max_build_memory_mb: None,
+                });
+                assert!(segment
+                    .update_index_config(2 * num_points, hnsw_index, true)
+                    .unwrap());
+            }
 
-        assert_eq!(segment.points_count(), restored_segment.points_count());
+            assert_result(
+                search(&segment, None),
+                &[(99, 0.99), (98, 0.98), (97, 0.97)],
+            );
+            // Non-numeric and missing values are not boosted
+            assert_result(
+                search(&segment, Some(BoostFunction::Linear)),
+                &[(96, 1.96), (99, 0.99), (98, 0.98)],
+            );
+            assert_result(
+                search(&segment, Some(BoostFunction::Sqrt)),
+                &[(96, 0.96 + 0.1 * 10f32.sqrt()), (99, 0.99), (98, 0.98)],
+            );
+        }
 
-        for id in segment.iter_points() {
-            let vectors = segment.all_vectors(id).unwrap();
-            let restored_vectors = restored_segment.all_vectors(id).unwrap();
-            assert_eq!(vectors, restored_vectors);
+        // Streaming search can't boost the scores
+        let params = SearchParams {
+            score_boost: Some(ScoreBoost {
+                key: "popularity".to_owned(),
+                factor: 0.1,
+                function: BoostFunction::Linear,
+            }),
+            ..Default::default()
+        };
+        let result = segment.search_iter(
+            DEFAULT_VECTOR_NAME,
+            &query,
+            &false.into(),
+            &false.into(),
+            None,
+            3,
+            Some(&params),
+            10,
+        );
+        assert!(matches!(
+            result,
+            Err(OperationError::UnsupportedSearchParams { .. })
+        ));
+    }
 
-            let payload = segment.payload(id).unwrap();
-            let restored_payload = restored_segment.payload(id).unwrap();
-            assert_eq!(payload, restored_payload);
+    #[test]
+    fn test_flush_policy() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let wal_dir = Builder::new().prefix("wal_dir").tempdir().unwrap();
+        let config = |flush_policy| SegmentConfig {
+            flush_policy,
+            ..segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory)
+        };
+        let upsert = |segment: &mut Segment, op_num: SeqNumberType| {
+            segment
+                .upsert_vector(op_num, op_num.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+        };
+
+        let mut segment = build_segment(dir.path(), &config(FlushPolicy::EveryNOps(10))).unwrap();
+        assert_eq!(segment.info().flush_policy, FlushPolicy::EveryNOps(10));
+        for op_num in 0..35 {
+            upsert(&mut segment, op_num);
+            let expected = (op_num + 1) / 10 * 10;
+            let persisted = *segment.persisted_version.lock();
+            assert_eq!(persisted, expected.checked_sub(1), "op {op_num}");
+        }
+
+        // Explicit flush restarts counting
+        segment.flush(true).unwrap();
+        for op_num in 35..44 {
+            upsert(&mut segment, op_num);
+            assert_eq!(*segment.persisted_version.lock(), Some(34));
+        }
+        upsert(&mut segment, 44);
+        assert_eq!(*segment.persisted_version.lock(), Some(44));
+
+        // With the log, the policy flushes the log instead of the storages
+        let mut wal = SegmentWal::open(wal_dir.path(), Default::default()).unwrap();
+        wal.take_flush_policy(&mut segment);
+        for op_num in 45..70 {
+            upsert(&mut segment, op_num);
         }
+        assert_eq!(*segment.persisted_version.lock(), Some(44));
+
+        let mut segment = build_segment(dir.path(), &config(FlushPolicy::Manual)).unwrap();
+        for op_num in 0..50 {
+            upsert(&mut segment, op_num);
+        }
+        assert_eq!(*segment.persisted_version.lock(), None);
+        assert_eq!(segment.flush(true).unwrap(), 49);
+        assert_eq!(*segment.persisted_version.lock(), Some(49));
     }
 
     #[test]
-    fn test_background_flush() {
-        let data = r#"
-        {
-            "name": "John Doe",
-            "age": 43,
-            "metadata": {
-                "height": 50,
-                "width": 60
+    fn test_background_flusher() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = |background_flush_interval_ms| SegmentConfig {
+            background_flush_interval_ms,
+            ..segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory)
+        };
+        let upsert = |segment: &RwLock<Segment>, op_num: SeqNumberType| {
+            segment
+                .write()
+                .upsert_vector(op_num, op_num.into(), &only_default_vector(&[1.0, 1.0]))
+                .unwrap();
+        };
+        let wait_persisted = |segment: &RwLock<Segment>, version: SeqNumberType| {
+            let deadline = std::time::Instant::now() + Duration::from_secs(10);
+            while *segment.read().persisted_version.lock() != Some(version) {
+                assert!(
+                    std::time::Instant::now() < deadline,
+                    "version {version} is not flushed"
+                );
+                std::thread::sleep(Duration::from_millis(5));
             }
-        }"#;
+        };
 
-        let segment_base_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = Arc::new(RwLock::new(
+            build_segment(dir.path(), &config(None)).unwrap(),
+        ));
+        assert!(BackgroundFlusher::for_segment(&segment).is_none());
+
+        let segment = Arc::new(RwLock::new(
+            build_segment(dir.path(), &config(Some(10))).unwrap(),
+        ));
+        assert_eq!(segment.read().info().last_flush_time, None);
+        let flusher = BackgroundFlusher::for_segment(&segment).unwrap();
+        for op_num in 0..10 {
+            upsert(&segment, op_num);
+        }
+        wait_persisted(&segment, 9);
+        let info = segment.read().info();
+        let first_flush_time = info.last_flush_time.unwrap();
+        assert_eq!(info.last_flush_error, None);
+
+        for op_num in 10..20 {
+            upsert(&segment, op_num);
+        }
+        wait_persisted(&segment, 19);
+        assert!(segment.read().info().last_flush_time.unwrap() >= first_flush_time);
+
+        // Closed flusher doesn't flush anymore
+        flusher.close();
+        upsert(&segment, 20);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(*segment.read().persisted_version.lock(), Some(19));
+
+        // Flusher stops once the segment is dropped
+        let flusher = BackgroundFlusher::for_segment(&segment).unwrap();
+        drop(segment);
+        drop(flusher);
+    }
+
+    #[test]
+    fn test_export_import_jsonl() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let vector_config = VectorDataConfig {
+            size: 2,
+            distance: Distance::Dot,
+            hnsw_config: None,
+            quantization_config: None,
+        };
         let config = SegmentConfig {
-            vector_data: HashMap::from([(
-                DEFAULT_VECTOR_NAME.to_owned(),
-                VectorDataConfig {
-                    size: 2,
-                    distance: Distance::Dot,
-                    hnsw_config: None,
-                    quantization_config: None,
-                },
-            )]),
+            vector_data: HashMap::from([
+                ("image".to_owned(), vector_config.clone()),
+                ("text".to_owned(), vector_config),
+            ]),
             index: Indexes::Plain {},
             storage_type: StorageType::InMemory,
             ..Default::default()
         };
 
-        let mut segment = build_segment(segment_base_dir.path(), &config).unwrap();
-        segment
-            .upsert_vector(0, 0.into(), &only_default_vector(&[1.0, 1.0]))
-            .unwrap();
+        let num_points = 20u64;
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for point_id in 0..num_points {
+            let op_num = point_id * 2;
+            let vectors = NamedVectors::from([
+                ("image".to_owned(), vec![point_id as f32, 1.0]),
+                ("text".to_owned(), vec![1.0, point_id as f32]),
+            ]);
+            let payload: Payload = serde_json::json!({
+                "num": point_id,
+                "location": { "lon": 13.4, "lat": 52.5 },
+                "created_at": "2023-04-05T10:00:00Z",
+                "tags": ["a", "b"],
+            })
+            .into();
+            segment
+                .upsert_vector(op_num, point_id.into(), &vectors)
+                .unwrap();
+            segment
+                .set_full_payload(op_num + 1, point_id.into(), &payload)
+                .unwrap();
+        }
+        segment.delete_point(2 * num_points, 10.into()).unwrap();
 
-        let payload: Payload = serde_json::from_str(data).unwrap();
-        segment.set_full_payload(0, 0.into(), &payload).unwrap();
-        segment.flush(false).unwrap();
+        let mut exported = Vec::new();
+        assert_eq!(
+            segment.export_jsonl(&mut exported, true, None).unwrap(),
+            num_points as usize - 1
+        );
+        let first_line = exported.split(|byte| *byte == b'\n').next().unwrap();
+        let first_point: serde_json::Value = serde_json::from_slice(first_line).unwrap();
+        assert_eq!(first_point["id"], 0);
+        assert_eq!(first_point["vector"]["text"], serde_json::json!([1.0, 0.0]));
+        assert_eq!(
+            first_point["payload"]["location"],
+            serde_json::json!({ "lon": 13.4, "lat": 52.5 })
+        );
+        assert_eq!(first_point["payload"]["created_at"], "2023-04-05T10:00:00Z");
+
+        let import_dir = Builder::new().prefix("import_dir").tempdir().unwrap();
+        let mut imported = build_segment(import_dir.path(), &config).unwrap();
+        let report = imported.import_jsonl(&mut exported.as_slice(), 1).unwrap();
+        assert_eq!(report.imported, num_points as usize - 1);
+        assert!(report.errors.is_empty());
+        assert_eq!(imported.points_count(), segment.points_count());
+        for point_id in segment.iter_points() {
+            assert_eq!(
+                imported.all_vectors(point_id.clone()).unwrap(),
+                segment.all_vectors(point_id.clone()).unwrap()
+            );
+            assert_eq!(
+                imported.payload(point_id.clone()).unwrap(),
+                segment.payload(point_id).unwrap()
+            );
+        }
 
-        // call flush second time to check that background flush finished successful
+        let filter: Filter =
+            serde_json::from_str(r#"{"must": [{"key": "num", "range": {"lt": 5}}]}"#).unwrap();
+        let mut filtered = Vec::new();
+        assert_eq!(
+            segment
+                .export_jsonl(&mut filtered, false, Some(&filter))
+                .unwrap(),
+            5
+        );
+        let points: Vec<_> = read_points(&mut filtered.as_slice())
+            .map(|line| line.unwrap().1.unwrap())
+            .collect();
+        let ids: Vec<_> = points.iter().map(|point| point.id.clone()).collect();
+        assert_eq!(ids, (0..5u64).map(PointIdType::from).collect::<Vec<_>>());
+        assert!(points.iter().all(|point| point.vector.is_none()));
+    }
+
+    #[test]
+    fn test_import_jsonl_malformed_lines() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+
+        let lines = [
+            r#"{"id": 1, "vector": [1.0, 0.0], "payload": {"color": "red"}}"#,
+            // Wrong dimension
+            r#"{"id": 2, "vector": [1.0, 0.0, 0.0]}"#,
+            // Missing id
+            r#"{"vector": [1.0, 0.0]}"#,
+            "",
+            // Payload of unknown type
+            r#"{"id": 3, "vector": [1.0, 0.0], "payload": 42}"#,
+            r#"{"id": 4, "vector": [0.0, 1.0]}"#,
+            // Repeated id is upserted again
+            r#"{"id": 1, "vector": [0.5, 0.5], "payload": {"color": "blue"}}"#,
+            "not json",
+        ]
+        .join("\n");
+
+        let report = segment.import_jsonl(&mut lines.as_bytes(), 10).unwrap();
+        assert_eq!(report.imported, 3);
+        assert!(!report.aborted);
+        let error_lines: Vec<_> = report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(error_lines, [2, 3, 5, 8]);
+        assert!(report.errors[0].error.contains("expected dim: 2, got 3"));
+        assert!(report.errors[1].error.contains("id"));
+        // Batch is applied before the repeated id
+        assert_eq!(report.next_op_num, 12);
+
+        assert_eq!(segment.points_count(), 2);
+        assert_eq!(
+            segment.vector(DEFAULT_VECTOR_NAME, 1.into()).unwrap(),
+            vec![0.5, 0.5]
+        );
+        assert_eq!(
+            *segment.payload(1.into()).unwrap(),
+            Payload::from(serde_json::json!({"color": "blue"}))
+        );
+        assert!(segment.payload(4.into()).unwrap().is_empty());
+        assert_eq!(segment.point_version(1.into()), Some(11));
+
+        // Import is aborted beyond the error budget
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let report = segment
+            .import_jsonl_with_error_budget(&mut lines.as_bytes(), 1, 1)
+            .unwrap();
+        assert!(report.aborted);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.imported, 1);
+        assert_eq!(segment.points_count(), 1);
+    }
+
+    #[test]
+    fn test_load_segment_state() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        segment
+            .upsert_vector(1, 1.into(), &only_default_vector(&[1.0, 0.0]))
+            .unwrap();
+        segment.flush(true).unwrap();
+        let path = segment.current_path.clone();
+        drop(segment);
+
+        // Fields of the newer version survive the update of the reopened segment
+        let state_path = path.join(SEGMENT_STATE_FILE);
+        let mut state: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+        state["future_field"] = serde_json::json!({ "enabled": true });
+        state["config"]["future_config_field"] = serde_json::json!(42);
+        fs::write(&state_path, state.to_string()).unwrap();
+        let mut segment = load_segment(&path).unwrap().unwrap();
+        assert_eq!(segment.segment_config.storage_type, StorageType::InMemory);
+        segment
+            .upsert_vector(2, 2.into(), &only_default_vector(&[0.0, 1.0]))
+            .unwrap();
         segment.flush(true).unwrap();
+        drop(segment);
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+        assert_eq!(saved["version"], 2);
+        assert_eq!(saved["future_field"], state["future_field"]);
+        assert_eq!(saved["config"]["future_config_field"], 42);
+
+        // Unknown storage type
+        state["config"]["storage_type"] = serde_json::json!({ "type": "quantum" });
+        fs::write(&state_path, state.to_string()).unwrap();
+        let error = load_segment(&path).err().unwrap().to_string();
+        assert!(error.contains("quantum"), "{error}");
+
+        // Absent state
+        fs::remove_file(&state_path).unwrap();
+        let error = load_segment(&path).err().unwrap().to_string();
+        assert!(error.contains(SEGMENT_STATE_FILE), "{error}");
     }
 
     #[test]
-    fn test_check_consistency() {
+    fn test_flush_on_drop_and_close() {
         let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
-        let dim = 4;
         let config = SegmentConfig {
-            vector_data: HashMap::from([(
-                DEFAULT_VECTOR_NAME.to_owned(),
-                VectorDataConfig {
-                    size: dim,
-                    distance: Distance::Dot,
-                    hnsw_config: None,
-                    quantization_config: None,
-                },
-            )]),
-            index: Indexes::Plain {},
-            storage_type: StorageType::InMemory,
-            payload_storage_type: Default::default(),
-            quantization_config: None,
+            flush_policy: FlushPolicy::Manual,
+            ..segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory)
         };
         let mut segment = build_segment(dir.path(), &config).unwrap();
-
-        let vec4 = vec![1.1, 1.0, 0.0, 1.0];
+        let path = segment.current_path.clone();
         segment
-            .upsert_vector(100, 4.into(), &only_default_vector(&vec4))
+            .upsert_vector(1, 1.into(), &only_default_vector(&[1.0, 0.0]))
             .unwrap();
-        let vec6 = vec![1.0, 1.0, 0.5, 1.0];
         segment
-            .upsert_vector(101, 6.into(), &only_default_vector(&vec6))
+            .set_full_payload(2, 1.into(), &serde_json::json!({ "a": 1 }).into())
             .unwrap();
-
-        // first pass on consistent data
-        segment.check_consistency_and_repair().unwrap();
-
-        let query_vector = vec![1.0, 1.0, 1.0, 1.0];
-        let search_result = segment
-            .search(
-                DEFAULT_VECTOR_NAME,
-                &query_vector,
-                &WithPayload::default(),
-                &false.into(),
-                None,
-                10,
-                None,
-            )
+        // Dropped without the flush
+        drop(segment);
+
+        let mut segment = load_segment(&path).unwrap().unwrap();
+        assert_eq!(segment.version(), 2);
+        assert!(segment.has_point(1.into()));
+        assert_eq!(
+            *segment.payload(1.into()).unwrap(),
+            Payload::from(serde_json::json!({ "a": 1 }))
+        );
+        segment
+            .upsert_vector(3, 2.into(), &only_default_vector(&[0.0, 1.0]))
             .unwrap();
+        segment.close().unwrap();
 
-        assert_eq!(search_result.len(), 2);
-        assert_eq!(search_result[0].id, 6.into());
-        assert_eq!(search_result[1].id, 4.into());
+        // Lock is released by the close, the closed segment is not flushed again on drop
+        let segment = load_segment(&path).unwrap().unwrap();
+        assert_eq!(segment.version(), 3);
+        assert_eq!(segment.points_count(), 2);
+        segment.close().unwrap();
 
-        assert!(matches!(
-            segment.vector(DEFAULT_VECTOR_NAME, 6.into()),
-            Ok(_)
-        ));
+        // Read-only segment is closed without the flush
+        let segment = crate::segment_constructor::load_segment_read_only(&path)
+            .unwrap()
+            .unwrap();
+        segment.close().unwrap();
+    }
 
-        let internal_id = segment.lookup_internal_id(6.into()).unwrap();
+    #[test]
+    fn test_point_versions() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let payload: Payload = serde_json::json!({"color": "red"}).into();
+        let key = "color".to_string();
 
-        // make id_tracker inconsistent
-        segment.id_tracker.borrow_mut().drop(6.into()).unwrap();
+        segment
+            .upsert_vector(1, 1.into(), &only_default_vector(&[1.0, 0.0]))
+            .unwrap();
+        segment
+            .upsert_vector(2, 2.into(), &only_default_vector(&[0.0, 1.0]))
+            .unwrap();
+        assert_eq!(segment.point_version(1.into()), Some(1));
+
+        // Every update of the vectors or the payload bumps the version of the point only
+        let mut versions = vec![segment.point_version(1.into()).unwrap()];
+        segment.set_full_payload(3, 1.into(), &payload).unwrap();
+        versions.push(segment.point_version(1.into()).unwrap());
+        segment.set_payload(4, 1.into(), &payload).unwrap();
+        versions.push(segment.point_version(1.into()).unwrap());
+        segment.delete_payload(5, 1.into(), &key).unwrap();
+        versions.push(segment.point_version(1.into()).unwrap());
+        segment.clear_payload(6, 1.into()).unwrap();
+        versions.push(segment.point_version(1.into()).unwrap());
+        segment
+            .upsert_vector(7, 1.into(), &only_default_vector(&[1.0, 1.0]))
+            .unwrap();
+        versions.push(segment.point_version(1.into()).unwrap());
+        assert_eq!(versions, vec![1, 3, 4, 5, 6, 7]);
+        assert_eq!(segment.point_version(2.into()), Some(2));
+        assert_eq!(segment.point_version(3.into()), None);
 
-        let search_result = segment
+        // Search results carry the versions
+        let results = segment
             .search(
                 DEFAULT_VECTOR_NAME,
-                &query_vector,
+                &[1.0, 1.0],
                 &WithPayload::default(),
                 &false.into(),
                 None,
@@ -1694,29 +6935,88 @@ mod tests {
                 None,
             )
             .unwrap();
-
-        // only one result because of inconsistent id_tracker
-        assert_eq!(search_result.len(), 1);
-        assert_eq!(search_result[0].id, 4.into());
-
-        // querying by external id is broken
-        assert!(
-            matches!(segment.vector(DEFAULT_VECTOR_NAME, 6.into()), Err(PointIdError {missed_point_id }) if missed_point_id == 6.into())
+        let result_versions: HashMap<_, _> = results
+            .iter()
+            .map(|point| (point.id.clone(), point.version))
+            .collect();
+        assert_eq!(
+            result_versions,
+            HashMap::from([(1.into(), 7), (2.into(), 2)])
         );
 
-        // but querying by internal id still works
-        matches!(
-            segment.vector_by_offset(DEFAULT_VECTOR_NAME, internal_id),
-            Ok(Some(_))
+        // Versions are persisted
+        segment.flush(true).unwrap();
+        let segment_path = segment.current_path.clone();
+        segment.close().unwrap();
+        let segment = load_segment(&segment_path).unwrap().unwrap();
+        assert_eq!(segment.point_version(1.into()), Some(7));
+        assert_eq!(segment.point_version(2.into()), Some(2));
+    }
+
+    #[test]
+    fn test_write_hook() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = segment_config(2, Distance::Dot, Indexes::Plain {}, StorageType::InMemory);
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        segment.set_on_write_hook(Some(Box::new(move |event: &WriteEvent| {
+            recorded.lock().push(event.clone())
+        })));
+
+        let vector = only_default_vector(&[1.0, 0.0]);
+        let payload: Payload = serde_json::json!({"color": "red"}).into();
+        segment.upsert_vector(1, 1.into(), &vector).unwrap();
+        segment.upsert_vector(2, 2.into(), &vector).unwrap();
+        segment.set_payload(3, 1.into(), &payload).unwrap();
+        // Point is updated by a later operation
+        segment.upsert_vector(2, 1.into(), &vector).unwrap();
+        segment.delete_point(4, 2.into()).unwrap();
+        // Segment is updated by a later operation
+        segment.upsert_vector(3, 2.into(), &vector).unwrap();
+        segment.create_field_index(5, "color", None).unwrap();
+        segment.delete_field_index(4, "color").unwrap();
+        segment
+            .upsert_points(
+                6,
+                &[1.into(), 3.into()],
+                &[vector.clone(), vector.clone()],
+                &[None, Some(&payload)],
+            )
+            .unwrap();
+        // Failed operations are not reported
+        segment.set_payload(7, 10.into(), &payload).unwrap_err();
+
+        let event = |kind, point_id: Option<u64>, op_num, skipped| WriteEvent {
+            kind,
+            point_id: point_id.map(Into::into),
+            op_num,
+            skipped,
+        };
+        assert_eq!(
+            *events.lock(),
+            vec![
+                event(WriteOperationKind::UpsertPoint, Some(1), 1, false),
+                event(WriteOperationKind::UpsertPoint, Some(2), 2, false),
+                event(WriteOperationKind::SetPayload, Some(1), 3, false),
+                event(WriteOperationKind::UpsertPoint, Some(1), 2, true),
+                event(WriteOperationKind::DeletePoint, Some(2), 4, false),
+                event(WriteOperationKind::UpsertPoint, Some(2), 3, true),
+                event(WriteOperationKind::CreateFieldIndex, None, 5, false),
+                event(WriteOperationKind::DeleteFieldIndex, None, 4, true),
+                event(WriteOperationKind::UpsertPoint, Some(1), 6, false),
+                event(WriteOperationKind::UpsertPoint, Some(3), 6, false),
+            ]
         );
 
-        // fix segment's data
-        segment.check_consistency_and_repair().unwrap();
+        // Panic of the hook does not fail the operation
+        segment.set_on_write_hook(Some(Box::new(|_: &WriteEvent| panic!("hook failure"))));
+        segment.upsert_vector(7, 4.into(), &vector).unwrap();
+        assert!(segment.check_error().is_none());
+        assert_eq!(segment.point_version(4.into()), Some(7));
 
-        // querying by internal id now consistent
-        matches!(
-            segment.vector_by_offset(DEFAULT_VECTOR_NAME, internal_id),
-            Ok(None)
-        );
+        segment.set_on_write_hook(None);
+        segment.upsert_vector(8, 5.into(), &vector).unwrap();
+        assert_eq!(events.lock().len(), 10);
     }
 }
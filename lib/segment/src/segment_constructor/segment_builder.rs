@@ -1,9 +1,11 @@
 use core::cmp;
 use std::collections::HashMap;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 
 use super::get_vector_storage_path;
+use crate::common::atomic_rename::commit_dir;
 use crate::common::error_logging::LogError;
 use crate::entry::entry_point::{
     check_process_stopped, OperationError, OperationResult, SegmentEntry,
@@ -15,6 +17,9 @@ use crate::types::{PayloadFieldSchema, PayloadKeyType, SegmentConfig};
 use crate::vector_storage::VectorStorage;
 
 /// Structure for constructing segment out of several other segments
+///
+/// Segment is built in the temporary directory, which is removed if the builder is dropped
+/// before the build is finished, e.g. when the build is cancelled.
 pub struct SegmentBuilder {
     pub segment: Option<Segment>,
     pub destination_path: PathBuf,
@@ -60,28 +65,25 @@ impl SegmentBuilder {
             Some(self_segment) => {
                 self_segment.version = Some(cmp::max(self_segment.version(), other.version()));
 
-                let other_id_tracker = other.id_tracker.borrow();
+                let other_id_tracker = other.id_tracker.read();
                 let other_vector_storages: HashMap<_, _> = other
                     .vector_data
                     .iter()
                     .map(|(vector_name, vector_data)| {
-                        (vector_name.to_owned(), vector_data.vector_storage.borrow())
+                        (vector_name.to_owned(), vector_data.vector_storage.read())
                     })
                     .collect();
-                let other_payload_index = other.payload_index.borrow();
+                let other_payload_index = other.payload_index.read();
 
-                let mut id_tracker = self_segment.id_tracker.borrow_mut();
+                let mut id_tracker = self_segment.id_tracker.write();
                 let mut vector_storages: HashMap<_, _> = self_segment
                     .vector_data
                     .iter()
                     .map(|(vector_name, vector_data)| {
-                        (
-                            vector_name.to_owned(),
-                            vector_data.vector_storage.borrow_mut(),
-                        )
+                        (vector_name.to_owned(), vector_data.vector_storage.write())
                     })
                     .collect();
-                let mut payload_index = self_segment.payload_index.borrow_mut();
+                let mut payload_index = self_segment.payload_index.write();
 
                 if vector_storages.len() != other_vector_storages.len() {
                     return Err(OperationError::service_error(
@@ -117,7 +119,11 @@ impl SegmentBuilder {
                 }
 
                 if let Some(new_internal_range) = new_internal_range {
+                    let block_start = new_internal_range.start;
+                    let block_len = new_internal_range.len();
                     let internal_id_iter = new_internal_range.zip(other_id_tracker.iter_ids());
+                    let mut new_links = vec![];
+                    let mut new_versions = vec![];
 
                     for (new_internal_id, old_internal_id) in internal_id_iter {
                         check_process_stopped(stopped)?;
@@ -133,11 +139,11 @@ impl SegmentBuilder {
                         let other_version =
                             other_id_tracker.internal_version(old_internal_id).unwrap();
 
-                        match id_tracker.internal_id(external_id) {
+                        match id_tracker.internal_id(external_id.clone()) {
                             None => {
                                 // New point, just insert
-                                id_tracker.set_link(external_id, new_internal_id)?;
-                                id_tracker.set_internal_version(new_internal_id, other_version)?;
+                                new_links.push((external_id, new_internal_id));
+                                new_versions.push((new_internal_id, other_version));
                                 payload_index.assign(
                                     new_internal_id,
                                     &other_payload_index.payload(old_internal_id)?,
@@ -149,11 +155,9 @@ impl SegmentBuilder {
                                 let existing_version =
                                     id_tracker.internal_version(existing_internal_id).unwrap();
                                 if existing_version < other_version {
-                                    // Other version is the newest, remove the existing one and replace
-                                    id_tracker.drop(external_id)?;
-                                    id_tracker.set_link(external_id, new_internal_id)?;
-                                    id_tracker
-                                        .set_internal_version(new_internal_id, other_version)?;
+                                    // Other version is the newest, new link replaces the existing one
+                                    new_links.push((external_id, new_internal_id));
+                                    new_versions.push((new_internal_id, other_version));
                                     payload_index.drop(existing_internal_id)?;
                                     payload_index.assign(
                                         new_internal_id,
@@ -163,9 +167,23 @@ impl SegmentBuilder {
                             }
                         }
                     }
+
+                    if new_links.len() == block_len {
+                        // Every appended vector is linked, so the whole block is linked at once
+                        let new_ids: Vec<_> = new_links
+                            .iter()
+                            .map(|(external_id, _)| external_id.clone())
+                            .collect();
+                        id_tracker.set_links_range(&new_ids, block_start)?;
+                    } else {
+                        id_tracker.relink_batch(&new_links)?;
+                    }
+                    for (new_internal_id, other_version) in new_versions {
+                        id_tracker.set_internal_version(new_internal_id, other_version)?;
+                    }
                 }
 
-                for (field, payload_schema) in other.payload_index.borrow().indexed_fields() {
+                for (field, payload_schema) in other.payload_index.read().indexed_fields() {
                     self.indexed_fields.insert(field, payload_schema);
                 }
 
@@ -176,10 +194,9 @@ impl SegmentBuilder {
 
     pub fn build(mut self, stopped: &AtomicBool) -> Result<Segment, OperationError> {
         {
-            let mut segment = self.segment.ok_or_else(|| {
+            let mut segment = self.segment.take().ok_or_else(|| {
                 OperationError::service_error("Segment building error: created segment not found")
             })?;
-            self.segment = None;
 
             for (field, payload_schema) in &self.indexed_fields {
                 segment.create_field_index(segment.version(), field, Some(payload_schema))?;
@@ -189,7 +206,7 @@ impl SegmentBuilder {
             Self::update_quantization(&segment, stopped)?;
 
             for vector_data in segment.vector_data.values_mut() {
-                vector_data.vector_index.borrow_mut().build_index(stopped)?;
+                vector_data.vector_index.write().build_index(stopped)?;
             }
 
             segment.flush(true)?;
@@ -197,7 +214,7 @@ impl SegmentBuilder {
         }
 
         // Move fully constructed segment into collection directory and load back to RAM
-        std::fs::rename(&self.temp_path, &self.destination_path)
+        commit_dir(&self.temp_path, &self.destination_path)
             .describe("Moving segment data after optimization")?;
 
         let loaded_segment = load_segment(&self.destination_path)?.ok_or_else(|| {
@@ -219,10 +236,25 @@ impl SegmentBuilder {
                 let vector_storage_path = get_vector_storage_path(segment_path, vector_name);
                 vector_data
                     .vector_storage
-                    .borrow_mut()
+                    .write()
                     .quantize(&vector_storage_path, quantization)?;
             }
         }
         Ok(())
     }
 }
+
+impl Drop for SegmentBuilder {
+    fn drop(&mut self) {
+        // Files of the segment are closed, before its directory is removed
+        self.segment.take();
+        if self.temp_path.exists() {
+            if let Err(err) = fs::remove_dir_all(&self.temp_path) {
+                log::warn!(
+                    "Failed to remove incomplete segment {:?}: {err}",
+                    self.temp_path
+                );
+            }
+        }
+    }
+}
@@ -4,43 +4,65 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
 use log::info;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
+use rocksdb::DB;
 use semver::Version;
 use serde::Deserialize;
 use uuid::Uuid;
-
+use validator::Validate;
+
+use crate::common::atomic_rename::{is_temp_path, write_dir_atomically};
+use crate::common::checksums::{verify_checksums, verify_unpacked_checksums};
+#[cfg(feature = "encryption")]
+use crate::common::encryption::EncryptionKey;
+use crate::common::encryption::{check_segment_cipher, SegmentCipher};
+use crate::common::flush_scheduler::FlushScheduler;
+use crate::common::parallel::{get_load_threads, run_parallel, ParallelTask};
 use crate::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
+use crate::common::segment_lock::SegmentLock;
 use crate::common::version::StorageVersion;
 use crate::data_types::vectors::DEFAULT_VECTOR_NAME;
 use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::id_tracker::compact_id_tracker::CompactIdTracker;
 use crate::id_tracker::simple_id_tracker::SimpleIdTracker;
+use crate::id_tracker::IdTrackerSS;
 use crate::index::hnsw_index::graph_links::{GraphLinksMmap, GraphLinksRam};
 use crate::index::hnsw_index::hnsw::HNSWIndex;
+use crate::index::ivf_index::ivf::IvfIndex;
 use crate::index::plain_payload_index::PlainIndex;
 use crate::index::struct_payload_index::StructPayloadIndex;
-use crate::index::VectorIndexEnum;
+use crate::index::{VectorIndex, VectorIndexEnum};
 use crate::payload_storage::on_disk_payload_storage::OnDiskPayloadStorage;
+use crate::payload_storage::payload_storage_enum::PayloadStorageEnum;
 use crate::payload_storage::simple_payload_storage::SimplePayloadStorage;
 use crate::segment::{Segment, SegmentVersion, VectorData, SEGMENT_STATE_FILE};
+use crate::segment_constructor::segment_migration::{
+    load_format_version, migrate_segment, save_format_version, CURRENT_FORMAT_VERSION, MIGRATIONS,
+};
+use crate::snapshot_archive::{ArchiveCache, ArchiveOpenOptions, SnapshotArchive};
 use crate::types::{
-    Distance, Indexes, PayloadStorageType, SegmentConfig, SegmentState, SegmentType, SeqNumberType,
-    StorageType, VectorDataConfig,
+    ChecksumVerification, Distance, IdTrackerType, Indexes, PayloadStorageType, SegmentConfig,
+    SegmentState, SegmentType, SeqNumberType, StorageType, VectorDataConfig, VectorLayout,
+};
+use crate::vector_storage::encrypted_vector_storage::open_encrypted_vector_storage;
+use crate::vector_storage::memmap_vector_storage::{
+    open_archived_memmap_vector_storage, open_memmap_vector_storage,
 };
-use crate::vector_storage::memmap_vector_storage::open_memmap_vector_storage;
 use crate::vector_storage::simple_vector_storage::open_simple_vector_storage;
-use crate::vector_storage::VectorStorage;
+use crate::vector_storage::{VectorStorage, VectorStorageEnum};
 
 pub const PAYLOAD_INDEX_PATH: &str = "payload_index";
+/// Extension of the segment directory, moved aside because of a corrupted file
+pub const QUARANTINE_EXTENSION: &str = "quarantined";
 pub const VECTOR_STORAGE_PATH: &str = "vector_storage";
 pub const VECTOR_INDEX_PATH: &str = "vector_index";
 
-fn sp<T>(t: T) -> Arc<AtomicRefCell<T>> {
-    Arc::new(AtomicRefCell::new(t))
+fn sp<T>(t: T) -> Arc<RwLock<T>> {
+    Arc::new(RwLock::new(t))
 }
 
-fn get_vector_name_with_prefix(prefix: &str, vector_name: &str) -> String {
+pub(crate) fn get_vector_name_with_prefix(prefix: &str, vector_name: &str) -> String {
     if !vector_name.is_empty() {
         format!("{prefix}-{vector_name}")
     } else {
@@ -59,11 +81,157 @@ pub fn get_vector_index_path(segment_path: &Path, vector_name: &str) -> PathBuf
     segment_path.join(get_vector_name_with_prefix(VECTOR_INDEX_PATH, vector_name))
 }
 
-fn create_segment(
+/// Path, where the new vector index is built, before replacing the current one
+pub fn get_vector_index_rebuild_path(segment_path: &Path, vector_name: &str) -> PathBuf {
+    let mut rebuild_path = get_vector_index_path(segment_path, vector_name).into_os_string();
+    rebuild_path.push(".rebuild");
+    PathBuf::from(rebuild_path)
+}
+
+/// Open vector index of the given type, existing index data in `vector_index_path` is loaded
+pub(crate) fn open_vector_index(
+    vector_index_path: &Path,
+    index: &Indexes,
+    vector_config: &VectorDataConfig,
+    max_search_threads: Option<usize>,
+    id_tracker: Arc<RwLock<IdTrackerSS>>,
+    vector_storage: Arc<RwLock<VectorStorageEnum>>,
+    payload_index: Arc<RwLock<StructPayloadIndex>>,
+) -> OperationResult<Arc<RwLock<VectorIndexEnum>>> {
+    let vector_index = match index {
+        Indexes::Plain {} => sp(VectorIndexEnum::Plain(PlainIndex::new(
+            id_tracker,
+            vector_storage,
+            payload_index,
+            max_search_threads,
+        ))),
+        Indexes::Hnsw(collection_hnsw_config) => {
+            let hnsw_config = vector_config.hnsw_config.unwrap_or(*collection_hnsw_config);
+            sp(if hnsw_config.on_disk == Some(true) {
+                VectorIndexEnum::HnswMmap(HNSWIndex::<GraphLinksMmap>::open(
+                    vector_index_path,
+                    id_tracker,
+                    vector_storage,
+                    payload_index,
+                    hnsw_config,
+                )?)
+            } else {
+                VectorIndexEnum::HnswRam(HNSWIndex::<GraphLinksRam>::open(
+                    vector_index_path,
+                    id_tracker,
+                    vector_storage,
+                    payload_index,
+                    hnsw_config,
+                )?)
+            })
+        }
+        Indexes::Ivf(ivf_config) => sp(VectorIndexEnum::Ivf(IvfIndex::open(
+            vector_index_path,
+            id_tracker,
+            vector_storage,
+            payload_index,
+            *ivf_config,
+        )?)),
+    };
+    vector_index.write().set_search_threads(max_search_threads);
+    Ok(vector_index)
+}
+
+/// Segment type and appendable flag, defined by the config
+pub(crate) fn segment_type_of(config: &SegmentConfig) -> (SegmentType, bool) {
+    let segment_type = match config.index {
+        Indexes::Plain {} => SegmentType::Plain,
+        Indexes::Hnsw { .. } | Indexes::Ivf { .. } => SegmentType::Indexed,
+    };
+
+    let appendable_flag =
+        segment_type == SegmentType::Plain {} && config.storage_type == StorageType::InMemory;
+
+    (segment_type, appendable_flag)
+}
+
+/// Component of the segment, loaded independently of the others
+enum LoadedComponent {
+    PayloadStorage(Arc<RwLock<PayloadStorageEnum>>),
+    IdTracker(Arc<RwLock<IdTrackerSS>>),
+    VectorStorage(String, Arc<RwLock<VectorStorageEnum>>),
+}
+
+/// Open the vector storage of the segment along with its quantization data, if exists
+fn open_vector_storage(
+    segment_path: &Path,
+    config: &SegmentConfig,
+    vector_name: &str,
+    vector_config: &VectorDataConfig,
+    database: &Arc<RwLock<DB>>,
+    cipher: &Option<Arc<SegmentCipher>>,
+    archive: Option<&SnapshotArchive>,
+) -> OperationResult<Arc<RwLock<VectorStorageEnum>>> {
+    let vector_storage_path = get_vector_storage_path(segment_path, vector_name);
+
+    let vector_storage = match config.storage_type {
+        StorageType::InMemory => {
+            let db_column_name = get_vector_name_with_prefix(DB_VECTOR_CF, vector_name);
+            open_simple_vector_storage(
+                database.clone(),
+                &db_column_name,
+                vector_config.size,
+                vector_config.distance,
+                cipher.clone(),
+                config.vector_layout,
+            )?
+        }
+        StorageType::Mmap => match (cipher, archive) {
+            (Some(cipher), _) => open_encrypted_vector_storage(
+                &vector_storage_path,
+                vector_config.size,
+                vector_config.distance,
+                cipher.clone(),
+            )?,
+            (None, Some(archive)) => open_archived_memmap_vector_storage(
+                archive,
+                &vector_storage_path,
+                vector_config.size,
+                vector_config.distance,
+            )?,
+            (None, None) => open_memmap_vector_storage(
+                &vector_storage_path,
+                vector_config.size,
+                vector_config.distance,
+                config.vector_layout,
+            )?,
+        },
+    };
+
+    if config.quantization_config(vector_name).is_some() {
+        let quantized_data_path = vector_storage_path;
+        // Try to load quantization data from disk, if exists
+        // If not exists or it's a new segment, just ignore it
+        vector_storage
+            .write()
+            .load_quantization(&quantized_data_path)?;
+    }
+
+    Ok(vector_storage)
+}
+
+pub(super) fn create_segment(
     version: Option<SeqNumberType>,
     segment_path: &Path,
     config: &SegmentConfig,
+    cipher: Option<Arc<SegmentCipher>>,
+    archive: Option<&SnapshotArchive>,
 ) -> OperationResult<Segment> {
+    if cipher.is_some()
+        && config
+            .vector_data
+            .keys()
+            .any(|vector_name| config.quantization_config(vector_name).is_some())
+    {
+        return Err(OperationError::service_error(
+            "Quantization of the encrypted segment is not supported",
+        ));
+    }
     let vector_db_names: Vec<String> = config
         .vector_data
         .keys()
@@ -72,95 +240,105 @@ fn create_segment(
     let database = open_db(segment_path, &vector_db_names)
         .map_err(|err| OperationError::service_error(format!("RocksDB open error: {err}")))?;
 
-    let payload_storage = match config.payload_storage_type {
-        PayloadStorageType::InMemory => sp(SimplePayloadStorage::open(database.clone())?.into()),
-        PayloadStorageType::OnDisk => sp(OnDiskPayloadStorage::open(database.clone())?.into()),
-    };
+    let load_threads = get_load_threads();
+
+    // Components live in independent files, so they are loaded in parallel
+    let mut tasks: Vec<ParallelTask<LoadedComponent>> = vec![
+        Box::new(|| {
+            let payload_storage: PayloadStorageEnum = match config.payload_storage_type {
+                PayloadStorageType::InMemory => {
+                    SimplePayloadStorage::open(database.clone(), cipher.clone())?.into()
+                }
+                PayloadStorageType::OnDisk => {
+                    OnDiskPayloadStorage::open(database.clone(), cipher.clone())?.into()
+                }
+            };
+            Ok(LoadedComponent::PayloadStorage(sp(payload_storage)))
+        }) as ParallelTask<_>,
+        Box::new(|| {
+            let id_tracker: Arc<RwLock<IdTrackerSS>> = match config.id_tracker_type {
+                IdTrackerType::Simple => sp(SimpleIdTracker::open(database.clone())?),
+                IdTrackerType::Compact => sp(CompactIdTracker::open(database.clone())?),
+            };
+            Ok(LoadedComponent::IdTracker(id_tracker))
+        }) as ParallelTask<_>,
+    ];
+    for (vector_name, vector_config) in &config.vector_data {
+        let database = &database;
+        let cipher = &cipher;
+        tasks.push(Box::new(move || {
+            let vector_storage = open_vector_storage(
+                segment_path,
+                config,
+                vector_name,
+                vector_config,
+                database,
+                cipher,
+                archive,
+            )?;
+            Ok(LoadedComponent::VectorStorage(
+                vector_name.to_owned(),
+                vector_storage,
+            ))
+        }));
+    }
 
-    let id_tracker = sp(SimpleIdTracker::open(database.clone())?);
+    let mut payload_storage = None;
+    let mut id_tracker = None;
+    let mut vector_storages = HashMap::new();
+    for component in run_parallel(load_threads, tasks)? {
+        match component {
+            LoadedComponent::PayloadStorage(storage) => payload_storage = Some(storage),
+            LoadedComponent::IdTracker(tracker) => id_tracker = Some(tracker),
+            LoadedComponent::VectorStorage(vector_name, storage) => {
+                vector_storages.insert(vector_name, storage);
+            }
+        }
+    }
+    let (Some(payload_storage), Some(id_tracker)) = (payload_storage, id_tracker) else {
+        unreachable!("payload storage and id tracker are always loaded")
+    };
 
+    // Payload index depends on the payload storage and the id tracker, its fields are loaded in parallel
     let payload_index_path = segment_path.join(PAYLOAD_INDEX_PATH);
-    let payload_index: Arc<AtomicRefCell<StructPayloadIndex>> = sp(StructPayloadIndex::open(
+    let payload_index: Arc<RwLock<StructPayloadIndex>> = sp(StructPayloadIndex::open(
         payload_storage,
         id_tracker.clone(),
         &payload_index_path,
+        config.filter_cache_size,
     )?);
 
-    let mut vector_data = HashMap::new();
-    for (vector_name, vector_config) in &config.vector_data {
-        let vector_storage_path = get_vector_storage_path(segment_path, vector_name);
-        let vector_index_path = get_vector_index_path(segment_path, vector_name);
-
-        let vector_storage = match config.storage_type {
-            StorageType::InMemory => {
-                let db_column_name = get_vector_name_with_prefix(DB_VECTOR_CF, vector_name);
-                open_simple_vector_storage(
-                    database.clone(),
-                    &db_column_name,
-                    vector_config.size,
-                    vector_config.distance,
-                )?
-            }
-            StorageType::Mmap => open_memmap_vector_storage(
-                &vector_storage_path,
-                vector_config.size,
-                vector_config.distance,
-            )?,
-        };
-
-        if config.quantization_config(vector_name).is_some() {
-            let quantized_data_path = vector_storage_path;
-            // Try to load quantization data from disk, if exists
-            // If not exists or it's a new segment, just ignore it
-            vector_storage
-                .borrow_mut()
-                .load_quantization(&quantized_data_path)?;
-        }
-
-        let vector_index: Arc<AtomicRefCell<VectorIndexEnum>> = match config.index {
-            Indexes::Plain {} => sp(VectorIndexEnum::Plain(PlainIndex::new(
-                id_tracker.clone(),
-                vector_storage.clone(),
-                payload_index.clone(),
-            ))),
-            Indexes::Hnsw(collection_hnsw_config) => {
-                let hnsw_config = vector_config.hnsw_config.unwrap_or(collection_hnsw_config);
-                sp(if hnsw_config.on_disk == Some(true) {
-                    VectorIndexEnum::HnswMmap(HNSWIndex::<GraphLinksMmap>::open(
-                        &vector_index_path,
-                        id_tracker.clone(),
-                        vector_storage.clone(),
-                        payload_index.clone(),
-                        hnsw_config,
-                    )?)
-                } else {
-                    VectorIndexEnum::HnswRam(HNSWIndex::<GraphLinksRam>::open(
-                        &vector_index_path,
-                        id_tracker.clone(),
-                        vector_storage.clone(),
-                        payload_index.clone(),
-                        hnsw_config,
-                    )?)
-                })
-            }
-        };
-
-        vector_data.insert(
-            vector_name.to_owned(),
-            VectorData {
-                vector_storage,
-                vector_index,
-            },
-        );
-    }
-
-    let segment_type = match config.index {
-        Indexes::Plain {} => SegmentType::Plain,
-        Indexes::Hnsw { .. } => SegmentType::Indexed,
-    };
+    let index_tasks: Vec<ParallelTask<_>> = vector_storages
+        .into_iter()
+        .map(|(vector_name, vector_storage)| {
+            let id_tracker = id_tracker.clone();
+            let payload_index = payload_index.clone();
+            Box::new(move || {
+                let vector_config = &config.vector_data[&vector_name];
+                let vector_index = open_vector_index(
+                    &get_vector_index_path(segment_path, &vector_name),
+                    &config.index,
+                    vector_config,
+                    config.max_search_threads,
+                    id_tracker,
+                    vector_storage.clone(),
+                    payload_index,
+                )?;
+                Ok((
+                    vector_name,
+                    VectorData {
+                        vector_storage,
+                        vector_index,
+                    },
+                ))
+            }) as ParallelTask<_>
+        })
+        .collect();
+    let vector_data: HashMap<_, _> = run_parallel(load_threads, index_tasks)?
+        .into_iter()
+        .collect();
 
-    let appendable_flag =
-        segment_type == SegmentType::Plain {} && config.storage_type == StorageType::InMemory;
+    let (segment_type, appendable_flag) = segment_type_of(config);
 
     Ok(Segment {
         version,
@@ -172,13 +350,119 @@ fn create_segment(
         appendable_flag,
         payload_index,
         segment_config: config.clone(),
+        pending_index: None,
         error_status: None,
         database,
         flush_thread: Mutex::new(None),
+        update_lock: Arc::new(RwLock::new(())),
+        batch_scorer: None,
+        flush_scheduler: Some(FlushScheduler::new(config.flush_policy)),
+        read_only: false,
+        flush_stats: Default::default(),
+        contents: Default::default(),
+        lock: None,
+        unknown_state_fields: Default::default(),
+        closed: false,
+        archive_cache: None,
+        stats_cache: Default::default(),
+        write_hook: None,
     })
 }
 
+/// Load the segment for writing
+///
+/// Segment is locked exclusively, until it is dropped, see [`SegmentLock`].
 pub fn load_segment(path: &Path) -> OperationResult<Option<Segment>> {
+    open_segment(path, false, None)
+}
+
+/// Load the segment without changes of its files, updates of the loaded segment are rejected
+///
+/// Segment is locked shared, so it can't be loaded for writing, until it is dropped.
+/// Layout of the older format version is not migrated, such segment can't be loaded read-only.
+pub fn load_segment_read_only(path: &Path) -> OperationResult<Option<Segment>> {
+    open_segment(path, true, None)
+}
+
+/// Load the encrypted segment for writing, fails if the key doesn't match the key of the segment
+#[cfg(feature = "encryption")]
+pub fn load_encrypted_segment(
+    path: &Path,
+    key: &EncryptionKey,
+) -> OperationResult<Option<Segment>> {
+    open_segment(path, false, Some(Arc::new(SegmentCipher::new(key))))
+}
+
+/// Open the segment from its snapshot archive, see [`crate::segment::Segment::take_snapshot`], without the full unpack
+///
+/// Archive is indexed, and the files, needed to open the segment, are extracted into the cache directory of the `options`.
+/// Mem-mapped vectors are read through the archive, on demand, see [`crate::snapshot_archive`].
+/// Checksums of the read through files are not verified.
+///
+/// Segment is read-only and not appendable, all its updates fail with [`OperationError::SegmentNotAppendable`].
+/// Extracted files are removed, once the segment is dropped.
+pub fn load_segment_from_archive(
+    archive_path: &Path,
+    options: &ArchiveOpenOptions,
+) -> OperationResult<Segment> {
+    let cache = ArchiveCache::create(&options.cache_dir, archive_path)?;
+    let path = cache.path();
+    let archive = SnapshotArchive::open(archive_path, path)?;
+    archive.extract(options.cache_size_limit)?;
+    Segment::restore_unpacked_snapshot(path)?;
+
+    if !SegmentVersion::check_exists(path) {
+        return Err(OperationError::service_error(format!(
+            "segment snapshot archive {archive_path:?} has no segment version file"
+        )));
+    }
+    let (lock, _) = lock_and_migrate(path, true)?;
+    check_segment_cipher(path, None)?;
+    // Files, which are not extracted, are skipped
+    verify_unpacked_checksums(path, true)?;
+
+    let segment_state = Segment::load_state(path)?;
+    let mut segment = create_segment(
+        segment_state.version,
+        path,
+        &segment_state.config,
+        None,
+        Some(&archive),
+    )?;
+    segment.pending_index = segment_state.pending_index;
+    segment.read_only = true;
+    segment.appendable_flag = false;
+    segment.contents = segment_state.contents;
+    segment.lock = Some(lock);
+    segment.unknown_state_fields = segment_state.unknown_fields;
+    segment.archive_cache = Some(cache);
+    Ok(segment)
+}
+
+fn open_segment(
+    path: &Path,
+    read_only: bool,
+    cipher: Option<Arc<SegmentCipher>>,
+) -> OperationResult<Option<Segment>> {
+    if path
+        .extension()
+        .map_or(false, |extension| extension == QUARANTINE_EXTENSION)
+    {
+        log::warn!("Skipping quarantined segment: {}", path.display());
+        return Ok(None);
+    }
+
+    if is_temp_path(path) {
+        // Server crashed before the segment was moved into place
+        if read_only {
+            log::warn!("Skipping incomplete segment: {}", path.display());
+        } else {
+            log::warn!("Removing incomplete segment: {}", path.display());
+            std::fs::remove_dir_all(path)?;
+        }
+        return Ok(None);
+    }
+
     if !SegmentVersion::check_exists(path) {
         // Assume segment was not properly saved.
         // Server might have crashed before saving the segment fully.
@@ -189,6 +473,59 @@ pub fn load_segment(path: &Path) -> OperationResult<Option<Segment>> {
         return Ok(None);
     }
 
+    let (lock, read_only) = lock_and_migrate(path, read_only)?;
+    check_segment_cipher(path, cipher.as_deref())?;
+
+    let segment_state = Segment::load_state(path)?;
+
+    match segment_state.config.checksum_verification {
+        ChecksumVerification::Always => verify_checksums(path)?,
+        // Read-only load doesn't move the segment
+        ChecksumVerification::Quarantine if read_only => verify_checksums(path)?,
+        ChecksumVerification::Never => {}
+        ChecksumVerification::Quarantine => {
+            if let Err(err) = verify_checksums(path) {
+                let mut quarantine_path = path.as_os_str().to_owned();
+                quarantine_path.push(format!(".{QUARANTINE_EXTENSION}"));
+                log::error!("Quarantining segment {}: {err}", path.display());
+                std::fs::rename(path, &quarantine_path)?;
+                return Ok(None);
+            }
+        }
+    }
+
+    let mut segment = create_segment(
+        segment_state.version,
+        path,
+        &segment_state.config,
+        cipher,
+        None,
+    )?;
+    segment.pending_index = segment_state.pending_index;
+    segment.read_only = read_only;
+    segment.contents = segment_state.contents;
+    segment.lock = Some(lock);
+    segment.unknown_state_fields = segment_state.unknown_fields;
+
+    if !segment.contents.index && segment.contents.vectors && !read_only {
+        // Segment is restored from a snapshot without indexes
+        segment.build_missing_indexes()?;
+    }
+
+    Ok(Some(segment))
+}
+
+/// Lock the segment and upgrade its layout, returns if the segment is loaded read-only
+pub(super) fn lock_and_migrate(
+    path: &Path,
+    read_only: bool,
+) -> OperationResult<(SegmentLock, bool)> {
+    let mut lock = if read_only {
+        SegmentLock::shared(path)?
+    } else {
+        SegmentLock::exclusive(path)?
+    };
+
     let stored_version: Version = SegmentVersion::load(path)?.parse()?;
     let app_version: Version = SegmentVersion::current().parse()?;
 
@@ -207,20 +544,33 @@ pub fn load_segment(path: &Path) -> OperationResult<Option<Segment>> {
                 "Segment version({stored_version}) is not compatible with current version({app_version})"
             )));
         }
+    }
 
-        if stored_version.major == 0 && stored_version.minor == 3 {
-            let segment_state = load_segment_state_v3(path)?;
-            Segment::save_state(&segment_state, path)?;
+    let layout_read_only = if read_only {
+        let format_version = load_format_version(path, &stored_version)?;
+        if format_version > CURRENT_FORMAT_VERSION {
+            return Err(OperationError::UnsupportedFormatVersion {
+                format_version,
+                supported_version: CURRENT_FORMAT_VERSION,
+            });
         }
-
+        if format_version < CURRENT_FORMAT_VERSION {
+            return Err(OperationError::service_error(format!(
+                "Segment {} of format version {format_version} must be migrated, before it is loaded read-only",
+                path.display()
+            )));
+        }
+        false
+    } else {
+        migrate_segment(path, &stored_version, MIGRATIONS)?
+    };
+    if layout_read_only {
+        lock.downgrade()?;
+    }
+    if stored_version != app_version && !read_only && !layout_read_only {
         SegmentVersion::save(path)?
     }
-
-    let segment_state = Segment::load_state(path)?;
-
-    let segment = create_segment(segment_state.version, path, &segment_state.config)?;
-
-    Ok(Some(segment))
+    Ok((lock, read_only || layout_read_only))
 }
 
 /// Build segment instance using given configuration.
@@ -233,21 +583,84 @@ pub fn load_segment(path: &Path) -> OperationResult<Option<Segment>> {
 ///
 ///
 pub fn build_segment(path: &Path, config: &SegmentConfig) -> OperationResult<Segment> {
-    let segment_path = path.join(Uuid::new_v4().to_string());
+    build_segment_with_cipher(path, config, None)
+}
+
+/// Build the segment, which vectors and payloads are encrypted with the key
+///
+/// Segment is opened with [`load_encrypted_segment`] and the same key.
+/// Payload fields of the encrypted segment can't be indexed, as the field indexes are not encrypted.
+#[cfg(feature = "encryption")]
+pub fn build_encrypted_segment(
+    path: &Path,
+    config: &SegmentConfig,
+    key: &EncryptionKey,
+) -> OperationResult<Segment> {
+    build_segment_with_cipher(path, config, Some(Arc::new(SegmentCipher::new(key))))
+}
 
-    std::fs::create_dir_all(&segment_path)?;
+/// Check, that the segment can be built with the config, before anything is written
+///
+/// Returns `OperationError::InvalidConfig` for the vectors of zero dimension and the index params,
+/// the index can't be built with.
+pub fn validate_segment_config(config: &SegmentConfig) -> OperationResult<()> {
+    let invalid = |description: String| Err(OperationError::InvalidConfig { description });
+    for (vector_name, vector_config) in &config.vector_data {
+        if vector_config.size == 0 {
+            return invalid(format!("vector {vector_name:?} has zero dimension"));
+        }
+        match &config.index {
+            Indexes::Plain {} => {}
+            Indexes::Hnsw(hnsw_config) => {
+                // Graph can't be built without the candidates to link
+                let hnsw_config = vector_config.hnsw_config.unwrap_or(*hnsw_config);
+                if hnsw_config.ef_construct == 0 {
+                    return invalid(format!(
+                        "HNSW index of vector {vector_name:?} has zero ef_construct"
+                    ));
+                }
+            }
+            Indexes::Ivf(ivf_config) => {
+                if let Err(err) = ivf_config.validate() {
+                    return invalid(format!("IVF index of vector {vector_name:?}: {err}"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-    let segment = create_segment(None, &segment_path, config)?;
-    segment.save_current_state()?;
+fn build_segment_with_cipher(
+    path: &Path,
+    config: &SegmentConfig,
+    cipher: Option<Arc<SegmentCipher>>,
+) -> OperationResult<Segment> {
+    validate_segment_config(config)?;
+    let segment_path = path.join(Uuid::new_v4().to_string());
 
-    // Version is the last file to save, as it will be used to check if segment was built correctly.
-    // If it is not saved, segment will be skipped.
-    SegmentVersion::save(&segment_path)?;
+    std::fs::create_dir_all(path)?;
 
-    Ok(segment)
+    write_dir_atomically(&segment_path, |temp_path| {
+        if let Some(cipher) = &cipher {
+            cipher.save_header(temp_path)?;
+        }
+        let segment = create_segment(None, temp_path, config, cipher.clone(), None)?;
+        segment.save_current_state()?;
+        save_format_version(temp_path, CURRENT_FORMAT_VERSION)?;
+
+        // Version is the last file to save, as it will be used to check if segment was built correctly.
+        // If it is not saved, segment will be skipped.
+        SegmentVersion::save(temp_path)?;
+        Ok(())
+    })?;
+
+    // Segment is opened again at its final path
+    open_segment(&segment_path, false, cipher)?.ok_or_else(|| {
+        OperationError::service_error(format!("Segment loading error: {}", segment_path.display()))
+    })
 }
 
-fn load_segment_state_v3(segment_path: &Path) -> OperationResult<SegmentState> {
+pub(super) fn load_segment_state_v3(segment_path: &Path) -> OperationResult<SegmentState> {
     #[derive(Deserialize)]
     #[serde(rename_all = "snake_case")]
     pub struct ObsoleteSegmentState {
@@ -294,7 +707,19 @@ fn load_segment_state_v3(segment_path: &Path) -> OperationResult<SegmentState> {
                     storage_type: state.config.storage_type,
                     payload_storage_type: state.config.payload_storage_type,
                     quantization_config: None,
+                    filter_cache_size: 0,
+                    id_tracker_type: Default::default(),
+                    indexing_threshold: None,
+                    max_search_threads: None,
+                    flush_policy: Default::default(),
+                    checksum_verification: Default::default(),
+                    background_flush_interval_ms: None,
+                    vector_layout: VectorLayout::Packed,
+                    unknown_fields: Default::default(),
                 },
+                pending_index: None,
+                contents: Default::default(),
+                unknown_fields: Default::default(),
             }
         })
         .map_err(|err| {
@@ -305,3 +730,73 @@ fn load_segment_state_v3(segment_path: &Path) -> OperationResult<SegmentState> {
             ))
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::types::{HnswConfig, IvfConfig};
+
+    fn config(size: usize, index: Indexes) -> SegmentConfig {
+        SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_build_segment_with_invalid_config() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let invalid_configs = [
+            config(0, Indexes::Plain {}),
+            config(
+                4,
+                Indexes::Hnsw(HnswConfig {
+                    ef_construct: 0,
+                    ..Default::default()
+                }),
+            ),
+            config(
+                4,
+                Indexes::Ivf(IvfConfig {
+                    n_lists: 0,
+                    ..Default::default()
+                }),
+            ),
+        ];
+        for invalid_config in &invalid_configs {
+            let error = build_segment(dir.path(), invalid_config).err().unwrap();
+            assert!(
+                matches!(error, OperationError::InvalidConfig { .. }),
+                "{error}"
+            );
+        }
+        // Nothing is written for the rejected configs
+        assert_eq!(dir.path().read_dir().unwrap().count(), 0);
+
+        // Vector config overrides the index config
+        let mut vector_override = config(4, Indexes::Hnsw(Default::default()));
+        vector_override
+            .vector_data
+            .get_mut(DEFAULT_VECTOR_NAME)
+            .unwrap()
+            .hnsw_config = Some(HnswConfig {
+            ef_construct: 0,
+            ..Default::default()
+        });
+        assert!(validate_segment_config(&vector_override).is_err());
+
+        let segment = build_segment(dir.path(), &config(4, Indexes::Plain {})).unwrap();
+        assert!(segment.current_path.join(SEGMENT_STATE_FILE).exists());
+    }
+}
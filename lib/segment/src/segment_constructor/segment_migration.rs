@@ -0,0 +1,352 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use atomicwrites::{AllowOverwrite, AtomicFile};
+use log::info;
+use semver::Version;
+
+use super::segment_constructor_base::{get_vector_storage_path, load_segment_state_v3};
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::segment::Segment;
+use crate::types::{StorageType, VectorLayout};
+use crate::vector_storage::memmap_vector_storage::migrate_to_aligned_layout;
+
+pub const FORMAT_VERSION_FILE: &str = "format_version";
+
+/// Version of the layout of the segment directory, written by this code
+pub const CURRENT_FORMAT_VERSION: u32 = 3;
+
+/// How a segment of the older layout is upgraded
+pub enum MigrationKind {
+    /// Files of the segment are rewritten in place.
+    /// Each file is replaced atomically, and the format version is saved last,
+    /// so the migration is repeated if it is interrupted.
+    InPlace(fn(&Path) -> OperationResult<()>),
+    /// Layout is loadable, but can't be updated by this code.
+    /// Segment is loaded read-only, until it is rebuilt by an optimizer.
+    ReadOnly,
+}
+
+/// Upgrade of the segment layout from the `from` format version to the next one
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub kind: MigrationKind,
+}
+
+/// Migrations of all older layouts, in order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "single vector config of the segment state to the named vectors",
+        kind: MigrationKind::InPlace(migrate_single_vector_state),
+    },
+    Migration {
+        from: 1,
+        description: "format version file",
+        kind: MigrationKind::InPlace(|_| Ok(())),
+    },
+    Migration {
+        from: 2,
+        description: "aligned layout of the vectors",
+        kind: MigrationKind::InPlace(migrate_aligned_vector_layout),
+    },
+];
+
+fn migrate_single_vector_state(path: &Path) -> OperationResult<()> {
+    let segment_state = load_segment_state_v3(path)?;
+    Segment::save_state(&segment_state, path)
+}
+
+/// Memmapped vectors are rewritten padded and aligned, in-memory vectors are aligned, when they are loaded
+fn migrate_aligned_vector_layout(path: &Path) -> OperationResult<()> {
+    let mut segment_state = Segment::load_state(path)?;
+    if segment_state.config.storage_type == StorageType::Mmap {
+        for (vector_name, vector_config) in &segment_state.config.vector_data {
+            let vector_storage_path = get_vector_storage_path(path, vector_name);
+            migrate_to_aligned_layout(&vector_storage_path, vector_config.size)?;
+        }
+    }
+    segment_state.config.vector_layout = VectorLayout::Aligned;
+    Segment::save_state(&segment_state, path)
+}
+
+/// Format version of the segment directory
+///
+/// Segments, written before the format version file was introduced, are told by the application version.
+pub fn load_format_version(path: &Path, stored_version: &Version) -> OperationResult<u32> {
+    let format_version_path = path.join(FORMAT_VERSION_FILE);
+    if !format_version_path.exists() {
+        let legacy_version = if stored_version.major == 0 && stored_version.minor == 3 {
+            0
+        } else {
+            1
+        };
+        return Ok(legacy_version);
+    }
+    let contents = fs::read_to_string(&format_version_path)?;
+    contents.trim().parse().map_err(|err| {
+        OperationError::service_error(format!(
+            "Can't parse segment format version {contents:?} of {}: {err}",
+            path.display()
+        ))
+    })
+}
+
+pub fn save_format_version(path: &Path, format_version: u32) -> OperationResult<()> {
+    let format_version_path = path.join(FORMAT_VERSION_FILE);
+    AtomicFile::new(&format_version_path, AllowOverwrite)
+        .write(|file| file.write_all(format_version.to_string().as_bytes()))
+        .map_err(|err| {
+            OperationError::service_error(format!(
+                "Can't write {format_version_path:?}, error: {err}"
+            ))
+        })
+}
+
+/// Upgrade the layout of the segment to the current format version
+///
+/// Returns `true` if the layout can't be upgraded in place, and the segment should be loaded read-only.
+pub fn migrate_segment(
+    path: &Path,
+    stored_version: &Version,
+    migrations: &[Migration],
+) -> OperationResult<bool> {
+    let mut format_version = load_format_version(path, stored_version)?;
+    if format_version > CURRENT_FORMAT_VERSION {
+        return Err(OperationError::UnsupportedFormatVersion {
+            format_version,
+            supported_version: CURRENT_FORMAT_VERSION,
+        });
+    }
+
+    while format_version < CURRENT_FORMAT_VERSION {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from == format_version)
+            .ok_or_else(|| {
+                OperationError::service_error(format!(
+                    "No migration of the segment format version {format_version} of {}",
+                    path.display()
+                ))
+            })?;
+        match migration.kind {
+            MigrationKind::InPlace(migrate) => {
+                info!(
+                    "Migrating segment {} format {} -> {}: {}",
+                    path.display(),
+                    format_version,
+                    format_version + 1,
+                    migration.description,
+                );
+                migrate(path)?;
+                format_version += 1;
+                save_format_version(path, format_version)?;
+            }
+            MigrationKind::ReadOnly => {
+                log::warn!(
+                    "Segment {} of format version {format_version} is loaded read-only: {}",
+                    path.display(),
+                    migration.description,
+                );
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::common::version::{StorageVersion, VERSION_FILE};
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment::{SegmentVersion, SEGMENT_STATE_FILE};
+    use crate::segment_constructor::{build_segment, load_segment};
+    use crate::types::{Distance, Indexes, SegmentConfig, VectorDataConfig, WithPayload};
+    use crate::vector_storage::VectorStorage;
+
+    const NUM_POINTS: u64 = 20;
+
+    fn segment_fixture(dir: &Path) -> PathBuf {
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir, &config).unwrap();
+        for point_id in 0..NUM_POINTS {
+            let vector = [point_id as f32, 1.0];
+            segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        segment.flush(true).unwrap();
+        segment.current_path.clone()
+    }
+
+    /// Segment directory in the layout of 0.3: single vector config in the state, no format version
+    fn legacy_segment_fixture(dir: &Path) -> PathBuf {
+        let path = segment_fixture(dir);
+        let state = Segment::load_state(&path).unwrap();
+        let obsolete_state = serde_json::json!({
+            "version": state.version,
+            "config": {
+                "vector_size": 2,
+                "distance": Distance::Dot,
+                "index": state.config.index,
+                "storage_type": state.config.storage_type,
+            },
+        });
+        fs::write(
+            path.join(SEGMENT_STATE_FILE),
+            serde_json::to_vec(&obsolete_state).unwrap(),
+        )
+        .unwrap();
+        fs::write(path.join(VERSION_FILE), "0.3.1").unwrap();
+        fs::remove_file(path.join(FORMAT_VERSION_FILE)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_migrate_legacy_segment() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let path = legacy_segment_fixture(dir.path());
+
+        let segment = load_segment(&path).unwrap().unwrap();
+        assert!(!segment.read_only);
+        assert_eq!(segment.points_count(), NUM_POINTS as usize);
+        assert_eq!(segment.version(), NUM_POINTS - 1);
+        let result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &[1.0, 0.0],
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                3,
+                None,
+            )
+            .unwrap();
+        let ids: Vec<_> = result.iter().map(|point| point.id.clone()).collect();
+        assert_eq!(ids, [19.into(), 18.into(), 17.into()]);
+        drop(segment);
+
+        // Layout is upgraded in place
+        let stored_version: Version = SegmentVersion::load(&path).unwrap().parse().unwrap();
+        assert_eq!(stored_version.to_string(), SegmentVersion::current());
+        assert_eq!(
+            load_format_version(&path, &stored_version).unwrap(),
+            CURRENT_FORMAT_VERSION
+        );
+        let state = Segment::load_state(&path).unwrap();
+        assert_eq!(state.config.vector_data[DEFAULT_VECTOR_NAME].size, 2);
+        assert!(load_segment(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_migrate_aligned_vector_layout() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let path = segment_fixture(dir.path());
+        assert_eq!(
+            Segment::load_state(&path).unwrap().config.vector_layout,
+            VectorLayout::Aligned
+        );
+
+        // State of the format version 2 has no vector layout
+        let mut state: serde_json::Value =
+            serde_json::from_slice(&fs::read(path.join(SEGMENT_STATE_FILE)).unwrap()).unwrap();
+        state["config"]
+            .as_object_mut()
+            .unwrap()
+            .remove("vector_layout");
+        fs::write(
+            path.join(SEGMENT_STATE_FILE),
+            serde_json::to_vec(&state).unwrap(),
+        )
+        .unwrap();
+        save_format_version(&path, 2).unwrap();
+        assert_eq!(
+            Segment::load_state(&path).unwrap().config.vector_layout,
+            VectorLayout::Packed
+        );
+
+        let segment = load_segment(&path).unwrap().unwrap();
+        assert!(!segment.read_only);
+        let vector_storage = segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_storage
+            .clone();
+        assert_eq!(vector_storage.read().vector_layout(), VectorLayout::Aligned);
+        assert_eq!(vector_storage.read().get_vector(3), &[3.0, 1.0]);
+        drop(segment);
+
+        let stored_version: Version = SegmentVersion::load(&path).unwrap().parse().unwrap();
+        assert_eq!(
+            load_format_version(&path, &stored_version).unwrap(),
+            CURRENT_FORMAT_VERSION
+        );
+        assert_eq!(
+            Segment::load_state(&path).unwrap().config.vector_layout,
+            VectorLayout::Aligned
+        );
+    }
+
+    #[test]
+    fn test_newer_format_is_rejected() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let path = segment_fixture(dir.path());
+        save_format_version(&path, CURRENT_FORMAT_VERSION + 1).unwrap();
+
+        let err = load_segment(&path).err().unwrap();
+        assert!(matches!(
+            err,
+            OperationError::UnsupportedFormatVersion {
+                format_version,
+                supported_version: CURRENT_FORMAT_VERSION,
+            } if format_version == CURRENT_FORMAT_VERSION + 1
+        ));
+        let message = err.to_string();
+        assert!(message.contains(&(CURRENT_FORMAT_VERSION + 1).to_string()));
+        assert!(message.contains(&CURRENT_FORMAT_VERSION.to_string()));
+    }
+
+    #[test]
+    fn test_read_only_migration() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let path = segment_fixture(dir.path());
+        save_format_version(&path, 1).unwrap();
+        let stored_version: Version = SegmentVersion::current().parse().unwrap();
+
+        let migrations = [Migration {
+            from: 1,
+            description: "layout, which is not upgraded in place",
+            kind: MigrationKind::ReadOnly,
+        }];
+        assert!(migrate_segment(&path, &stored_version, &migrations).unwrap());
+        assert_eq!(load_format_version(&path, &stored_version).unwrap(), 1);
+
+        let mut segment = load_segment(&path).unwrap().unwrap();
+        segment.read_only = true;
+        let vector = [1.0, 1.0];
+        assert!(segment
+            .upsert_vector(NUM_POINTS, NUM_POINTS.into(), &only_default_vector(&vector))
+            .is_err());
+        assert_eq!(segment.points_count(), NUM_POINTS as usize);
+    }
+}
@@ -0,0 +1,330 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use super::segment_constructor_base::{
+    create_segment, get_vector_index_path, lock_and_migrate, PAYLOAD_INDEX_PATH,
+};
+use crate::common::checksums::{damaged_files, CHECKSUMS_FILE};
+use crate::common::encryption::check_segment_cipher;
+use crate::common::version::StorageVersion;
+use crate::entry::entry_point::{OperationError, OperationResult, SegmentEntry};
+use crate::index::VectorIndex;
+use crate::segment::{OrphanReport, Segment, SegmentVersion};
+
+/// What was lost by [`recover_segment`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Files, which failed the checksum verification, relative to the segment directory
+    pub damaged_files: Vec<String>,
+    /// Damaged files, which were removed, as they can't be loaded partially
+    pub removed_files: Vec<String>,
+    /// Names of the vectors, whose indexes were rebuilt from the surviving points
+    pub rebuilt_vector_indexes: Vec<String>,
+    /// Points, dropped as some of their vectors are lost
+    pub orphans: OrphanReport,
+    /// Number of points, left in the recovered segment
+    pub surviving_points: usize,
+}
+
+impl RecoveryReport {
+    pub fn dropped_points(&self) -> usize {
+        self.orphans.mapped_without_vectors
+    }
+
+    /// Nothing was damaged, the segment is loaded as is
+    pub fn is_clean(&self) -> bool {
+        self.damaged_files.is_empty() && self.orphans.is_empty()
+    }
+}
+
+/// Load as much of the corrupted segment, as it passes the validation, e.g. of the quarantined one
+///
+/// Damaged vector indexes and the payload index config are removed, and the vector indexes are rebuilt.
+/// Truncated vector storages are loaded up to the last complete vector, points beyond it are dropped.
+/// Damaged RocksDB storages can't be recovered, and fail the recovery.
+/// Recovered segment is flushed with the new checksums, so it passes the strict [`super::load_segment`].
+pub fn recover_segment(path: &Path) -> OperationResult<(Segment, RecoveryReport)> {
+    if !SegmentVersion::check_exists(path) {
+        return Err(OperationError::service_error(format!(
+            "Segment {} can't be recovered, it was not fully saved",
+            path.display()
+        )));
+    }
+
+    let (lock, read_only) = lock_and_migrate(path, false)?;
+    check_segment_cipher(path, None)?;
+    if read_only {
+        return Err(OperationError::service_error(format!(
+            "Segment {} of the read-only layout can't be recovered",
+            path.display()
+        )));
+    }
+    let segment_state = Segment::load_state(path)?;
+
+    let mut report = RecoveryReport {
+        damaged_files: damaged_files(path),
+        ..Default::default()
+    };
+
+    let payload_index_path = path.join(PAYLOAD_INDEX_PATH);
+    for relative_path in &report.damaged_files {
+        let file_path = path.join(relative_path);
+        let damaged_index =
+            segment_state.config.vector_data.keys().find(|vector_name| {
+                file_path.starts_with(get_vector_index_path(path, vector_name))
+            });
+        if let Some(vector_name) = damaged_index {
+            let index_path = get_vector_index_path(path, vector_name);
+            if index_path.exists() {
+                log::warn!("Removing damaged vector index {}", index_path.display());
+                fs::remove_dir_all(&index_path)?;
+            }
+            if !report.rebuilt_vector_indexes.contains(vector_name) {
+                report.rebuilt_vector_indexes.push(vector_name.clone());
+            }
+        } else if file_path.starts_with(&payload_index_path) || relative_path == CHECKSUMS_FILE {
+            if file_path.exists() {
+                log::warn!("Removing damaged file {}", file_path.display());
+                fs::remove_file(&file_path)?;
+            }
+            report.removed_files.push(relative_path.clone());
+        } else {
+            log::warn!(
+                "Damaged file {} is loaded as far as it is readable",
+                file_path.display()
+            );
+        }
+    }
+
+    let mut segment = create_segment(
+        segment_state.version,
+        path,
+        &segment_state.config,
+        None,
+        None,
+    )?;
+    segment.pending_index = segment_state.pending_index;
+    segment.contents = segment_state.contents;
+    segment.lock = Some(lock);
+    segment.unknown_state_fields = segment_state.unknown_fields;
+
+    if segment.contents.vectors {
+        report.orphans = segment.find_orphans(true, None)?;
+        if report.dropped_points() > 0 {
+            // Vector indexes link the dropped offsets
+            report.rebuilt_vector_indexes = segment.vector_data.keys().cloned().collect();
+        }
+        if segment.contents.index {
+            let stopped = AtomicBool::new(false);
+            for vector_name in &report.rebuilt_vector_indexes {
+                segment.vector_data[vector_name]
+                    .vector_index
+                    .write()
+                    .build_index(&stopped)?;
+            }
+        }
+    }
+    report.rebuilt_vector_indexes.sort();
+
+    segment.force_flush()?;
+    report.surviving_points = segment.points_count();
+
+    if report.is_clean() {
+        log::info!("Segment {} is recovered without losses", path.display());
+    } else {
+        log::warn!("Segment {} is recovered: {report:?}", path.display());
+    }
+    Ok((segment, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::path::PathBuf;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::fixtures::payload_fixtures::random_vector;
+    use crate::index::hnsw_index::graph_layers::GraphLayers;
+    use crate::index::hnsw_index::graph_links::GraphLinksRam;
+    use crate::index::payload_config::PayloadConfig;
+    use crate::segment_constructor::segment_builder::SegmentBuilder;
+    use crate::segment_constructor::simple_segment_constructor::build_simple_segment;
+    use crate::segment_constructor::{get_vector_storage_path, load_segment};
+    use crate::types::{
+        Distance, HnswConfig, Indexes, Payload, PayloadFieldSchema, PayloadSchemaType,
+        SegmentConfig, StorageType, WithPayload,
+    };
+
+    const DIM: usize = 4;
+    const NUM_POINTS: u64 = 200;
+
+    fn indexed_segment(dir: &Path, temp_dir: &Path) -> PathBuf {
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+        let mut plain_segment = build_simple_segment(dir, DIM, Distance::Dot).unwrap();
+        for point_id in 0..NUM_POINTS {
+            let vector = random_vector(&mut rnd, DIM);
+            plain_segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+            let payload: Payload = serde_json::from_value(json!({ "number": point_id })).unwrap();
+            plain_segment
+                .set_full_payload(NUM_POINTS + point_id, point_id.into(), &payload)
+                .unwrap();
+        }
+        plain_segment
+            .create_field_index(
+                2 * NUM_POINTS,
+                "number",
+                Some(&PayloadFieldSchema::FieldType(PayloadSchemaType::Integer)),
+            )
+            .unwrap();
+
+        let config = SegmentConfig {
+            vector_data: plain_segment.segment_config.vector_data.clone(),
+            index: Indexes::Hnsw(HnswConfig {
+                full_scan_threshold: 1,
+                ..Default::default()
+            }),
+            storage_type: StorageType::Mmap,
+            ..Default::default()
+        };
+        let mut builder = SegmentBuilder::new(dir, temp_dir, &config).unwrap();
+        builder.update_from(&plain_segment, &stopped).unwrap();
+        let segment = builder.build(&stopped).unwrap();
+        segment.current_path.clone()
+    }
+
+    fn truncate(path: &Path, len: u64) {
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_len(len)
+            .unwrap();
+    }
+
+    fn relative(segment_path: &Path, path: &Path) -> String {
+        path.strip_prefix(segment_path)
+            .unwrap()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Recover the segment with the file truncated to the `len`, check the recovered segment is searchable
+    fn recover_truncated(
+        file: impl Fn(&Path) -> PathBuf,
+        len: u64,
+        check: impl FnOnce(&Segment, &RecoveryReport),
+    ) {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let path = indexed_segment(dir.path(), temp_dir.path());
+        let file_path = file(&path);
+        truncate(&file_path, len);
+
+        let (segment, report) = recover_segment(&path).unwrap();
+        assert_eq!(report.damaged_files, [relative(&path, &file_path)]);
+        assert_eq!(report.surviving_points, segment.points_count());
+        let result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &[1.0; DIM],
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.len(), 10);
+        check(&segment, &report);
+        drop(segment);
+
+        // Recovered segment is saved with the new checksums
+        let segment = load_segment(&path).unwrap().unwrap();
+        assert_eq!(segment.points_count(), report.surviving_points);
+        drop(segment);
+        let (_, report) = recover_segment(&path).unwrap();
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_recover_truncated_vector_storage() {
+        let vector_size = (DIM * std::mem::size_of::<f32>()) as u64;
+        // Header, 150 complete vectors and a part of the next one
+        let len = 4 + 150 * vector_size + vector_size / 2;
+        recover_truncated(
+            |path| get_vector_storage_path(path, DEFAULT_VECTOR_NAME).join("matrix.dat"),
+            len,
+            |segment, report| {
+                assert_eq!(report.surviving_points, 150);
+                assert_eq!(report.dropped_points(), NUM_POINTS as usize - 150);
+                assert_eq!(report.rebuilt_vector_indexes, [DEFAULT_VECTOR_NAME]);
+                assert!(report.removed_files.is_empty());
+                for point_id in 0..NUM_POINTS {
+                    assert_eq!(segment.has_point(point_id.into()), point_id < 150);
+                }
+                assert_eq!(segment.get_indexed_fields().len(), 1);
+            },
+        );
+    }
+
+    #[test]
+    fn test_recover_truncated_vector_index() {
+        recover_truncated(
+            |path| {
+                GraphLayers::<GraphLinksRam>::get_path(&get_vector_index_path(
+                    path,
+                    DEFAULT_VECTOR_NAME,
+                ))
+            },
+            10,
+            |segment, report| {
+                assert_eq!(report.surviving_points, NUM_POINTS as usize);
+                assert_eq!(report.dropped_points(), 0);
+                assert_eq!(report.rebuilt_vector_indexes, [DEFAULT_VECTOR_NAME]);
+                // Graph files are listed once the graph is built
+                assert!(!segment.vector_data[DEFAULT_VECTOR_NAME]
+                    .vector_index
+                    .read()
+                    .files()
+                    .is_empty());
+            },
+        );
+    }
+
+    #[test]
+    fn test_recover_truncated_payload_index_config() {
+        recover_truncated(
+            |path| PayloadConfig::get_config_path(&path.join(PAYLOAD_INDEX_PATH)),
+            1,
+            |segment, report| {
+                assert_eq!(report.surviving_points, NUM_POINTS as usize);
+                assert_eq!(report.removed_files, report.damaged_files);
+                // Schema of the field indexes is lost with the config
+                assert!(segment.get_indexed_fields().is_empty());
+            },
+        );
+    }
+
+    #[test]
+    fn test_recover_truncated_checksums() {
+        recover_truncated(
+            |path| path.join(CHECKSUMS_FILE),
+            1,
+            |segment, report| {
+                assert_eq!(report.surviving_points, NUM_POINTS as usize);
+                assert_eq!(report.removed_files, [CHECKSUMS_FILE]);
+                assert_eq!(segment.get_indexed_fields().len(), 1);
+            },
+        );
+    }
+}
@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::file_operations::{atomic_save_json, read_json};
+use crate::common::flush_scheduler::FlushScheduler;
+use crate::data_types::named_vectors::NamedVectors;
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::{OperationError, OperationResult, SegmentEntry};
+use crate::segment::Segment;
+use crate::types::{Payload, PayloadKeyType, PointIdType, SeqNumberType};
+
+/// Version of the format of the log files, written as the first byte of each file
+pub const WAL_FORMAT_VERSION: u8 = 1;
+
+const WAL_FILE_PREFIX: &str = "wal-";
+const WAL_FILE_EXTENSION: &str = "log";
+const WAL_ACK_FILE: &str = "wal_ack.json";
+
+/// Length of the record body and its checksum
+const RECORD_HEADER_SIZE: usize = 4 + 8;
+
+/// Operation of the segment, which is logged before it is applied
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentOperation {
+    UpsertVector {
+        point_id: PointIdType,
+        vectors: HashMap<String, Vec<VectorElementType>>,
+    },
+    DeletePoint {
+        point_id: PointIdType,
+    },
+    SetPayload {
+        point_id: PointIdType,
+        payload: Payload,
+    },
+    SetFullPayload {
+        point_id: PointIdType,
+        payload: Payload,
+    },
+    DeletePayload {
+        point_id: PointIdType,
+        key: PayloadKeyType,
+    },
+    ClearPayload {
+        point_id: PointIdType,
+    },
+}
+
+impl SegmentOperation {
+    /// Apply the operation to the segment as the operation number `op_num`
+    pub fn apply(
+        &self,
+        op_num: SeqNumberType,
+        segment: &mut dyn SegmentEntry,
+    ) -> OperationResult<bool> {
+        match self {
+            SegmentOperation::UpsertVector { point_id, vectors } => segment.upsert_vector(
+                op_num,
+                point_id.clone(),
+                &NamedVectors::from_map_ref(vectors),
+            ),
+            SegmentOperation::DeletePoint { point_id } => {
+                segment.delete_point(op_num, point_id.clone())
+            }
+            SegmentOperation::SetPayload { point_id, payload } => {
+                segment.set_payload(op_num, point_id.clone(), payload)
+            }
+            SegmentOperation::SetFullPayload { point_id, payload } => {
+                segment.set_full_payload(op_num, point_id.clone(), payload)
+            }
+            SegmentOperation::DeletePayload { point_id, key } => {
+                segment.delete_payload(op_num, point_id.clone(), key)
+            }
+            SegmentOperation::ClearPayload { point_id } => {
+                segment.clear_payload(op_num, point_id.clone())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct WalRecord {
+    pub op_num: SeqNumberType,
+    pub operation: SegmentOperation,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentWalOptions {
+    /// Size of the log file, after which the next record is written into a new file
+    pub max_file_size: usize,
+}
+
+impl Default for SegmentWalOptions {
+    fn default() -> Self {
+        SegmentWalOptions {
+            max_file_size: 32 * 1024 * 1024,
+        }
+    }
+}
+
+/// Operations of the log, which are not acknowledged yet, and have to be replayed on recovery
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct WalUnackedStats {
+    pub operations: usize,
+    /// Size of the not acknowledged records in the log files
+    pub bytes: usize,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct WalAck {
+    /// Version of the segment, which is durably flushed
+    version: Option<SeqNumberType>,
+}
+
+/// Write-ahead log of the operations of a single segment
+///
+/// Operations are appended to the log before they are applied to the segment, so the operations
+/// since the last flush of the segment can be replayed after a crash.
+/// Log is split into files by `max_file_size`, each file is named by the `op_num` of its first record.
+/// File is a version byte, followed by records: length and checksum of the body, and the body itself,
+/// serialized with CBOR.
+/// Record, torn by a crash, is ignored, and is overwritten by the next append.
+pub struct SegmentWal {
+    path: PathBuf,
+    options: SegmentWalOptions,
+    /// `op_num` of the first record of each file, in order
+    files: Vec<SeqNumberType>,
+    writer: Option<File>,
+    /// Size of the last file
+    file_size: usize,
+    acked_version: Option<SeqNumberType>,
+    /// Flush policy of the segment, applied to the log, see [`Self::take_flush_policy`]
+    flush_scheduler: Option<FlushScheduler>,
+}
+
+impl SegmentWal {
+    pub fn open(path: &Path, options: SegmentWalOptions) -> OperationResult<Self> {
+        fs::create_dir_all(path)?;
+        let ack_path = path.join(WAL_ACK_FILE);
+        let ack: WalAck = if ack_path.exists() {
+            read_json(&ack_path)?
+        } else {
+            WalAck::default()
+        };
+
+        let mut files = vec![];
+        for entry in fs::read_dir(path)? {
+            let file_name = entry?.file_name();
+            let first_op_num = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(WAL_FILE_PREFIX))
+                .and_then(|name| name.strip_suffix(&format!(".{WAL_FILE_EXTENSION}")))
+                .and_then(|op_num| op_num.parse().ok());
+            if let Some(first_op_num) = first_op_num {
+                files.push(first_op_num);
+            }
+        }
+        files.sort_unstable();
+
+        let mut wal = SegmentWal {
+            path: path.to_owned(),
+            options,
+            files,
+            writer: None,
+            file_size: 0,
+            acked_version: ack.version,
+            flush_scheduler: None,
+        };
+        if let Some(&last_file) = wal.files.last() {
+            let file_path = wal.file_path(last_file);
+            let LogFile {
+                version,
+                size: valid_size,
+                ..
+            } = read_file(&file_path)?;
+            if version.is_none() {
+                // Even the version byte is not written
+                fs::remove_file(&file_path)?;
+                wal.files.pop();
+            } else if version != Some(WAL_FORMAT_VERSION) {
+                // Records are never appended to the files of other versions, the next one starts a new file
+                log::warn!(
+                    "{file_path:?} is written by the WAL format version {}, current is {WAL_FORMAT_VERSION}",
+                    version.unwrap_or_default(),
+                );
+            } else {
+                let file = OpenOptions::new().append(true).open(&file_path)?;
+                let file_size = file.metadata()?.len() as usize;
+                if file_size > valid_size {
+                    log::warn!(
+                        "Dropping torn record of {} bytes at the end of {file_path:?}",
+                        file_size - valid_size,
+                    );
+                    file.set_len(valid_size as u64)?;
+                }
+                wal.writer = Some(file);
+                wal.file_size = valid_size;
+            }
+        }
+        Ok(wal)
+    }
+
+    fn file_path(&self, first_op_num: SeqNumberType) -> PathBuf {
+        self.path.join(format!(
+            "{WAL_FILE_PREFIX}{first_op_num:020}.{WAL_FILE_EXTENSION}"
+        ))
+    }
+
+    /// Version of the segment, which is durably flushed, see [`Self::ack`]
+    pub fn acked_version(&self) -> Option<SeqNumberType> {
+        self.acked_version
+    }
+
+    /// Apply the flush policy of the segment to the log
+    ///
+    /// Logged operations are recovered by replay, so the log is flushed by the policy,
+    /// and the storages of the segment are only flushed explicitly.
+    pub fn take_flush_policy(&mut self, segment: &mut Segment) {
+        if let Some(flush_scheduler) = segment.flush_scheduler.take() {
+            self.flush_scheduler = Some(flush_scheduler);
+        }
+    }
+
+    /// Append the record to the log
+    ///
+    /// `op_num` of the records are expected to increase.
+    /// Record is written to the OS, but is only durable after [`Self::flush`],
+    /// or once it is due by the flush policy, see [`Self::take_flush_policy`].
+    pub fn append(&mut self, record: &WalRecord) -> OperationResult<()> {
+        let body = serde_cbor::to_vec(record)?;
+        let body_len = u32::try_from(body.len()).map_err(|_| {
+            OperationError::service_error(format!("WAL record is too large: {}", body.len()))
+        })?;
+
+        let file_is_full = self.file_size + RECORD_HEADER_SIZE + body.len()
+            > self.options.max_file_size
+            && self.file_size > 1;
+        if self.writer.is_none() || (file_is_full && self.files.last() != Some(&record.op_num)) {
+            self.rotate(record.op_num)?;
+        }
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("log file is opened by the rotation");
+
+        let mut data = Vec::with_capacity(RECORD_HEADER_SIZE + body.len());
+        data.extend_from_slice(&body_len.to_le_bytes());
+        data.extend_from_slice(&seahash::hash(&body).to_le_bytes());
+        data.extend_from_slice(&body);
+        writer.write_all(&data)?;
+        self.file_size += data.len();
+
+        let flush_due = self
+            .flush_scheduler
+            .as_ref()
+            .map_or(false, |scheduler| scheduler.operation_applied());
+        if flush_due {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Start a new log file with the record `first_op_num`
+    fn rotate(&mut self, first_op_num: SeqNumberType) -> OperationResult<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.sync_all()?;
+        }
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.file_path(first_op_num))?;
+        file.write_all(&[WAL_FORMAT_VERSION])?;
+        self.files.push(first_op_num);
+        self.writer = Some(file);
+        self.file_size = 1;
+        Ok(())
+    }
+
+    /// Make the appended records durable
+    pub fn flush(&self) -> OperationResult<()> {
+        if let Some(writer) = &self.writer {
+            writer.sync_data()?;
+        }
+        if let Some(flush_scheduler) = &self.flush_scheduler {
+            flush_scheduler.flushed();
+        }
+        Ok(())
+    }
+
+    /// Record, that the segment has durably flushed all operations up to `version`
+    ///
+    /// Files, which only contain the acknowledged records, are removed, regardless of their format version.
+    /// File with any record after `version` is kept, as well as the last file, which is being appended.
+    pub fn ack(&mut self, version: SeqNumberType) -> OperationResult<()> {
+        if self.acked_version.map_or(false, |acked| acked >= version) {
+            return Ok(());
+        }
+        atomic_save_json(
+            &self.path.join(WAL_ACK_FILE),
+            &WalAck {
+                version: Some(version),
+            },
+        )?;
+        self.acked_version = Some(version);
+
+        let fully_acked = self.fully_acked_files();
+        for &first_op_num in &self.files[..fully_acked] {
+            match fs::remove_file(self.file_path(first_op_num)) {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+        self.files.drain(..fully_acked);
+        Ok(())
+    }
+
+    /// Number of the leading files, which only contain acknowledged records
+    ///
+    /// Records of a file precede the first record of the next one, so the last file is never counted.
+    fn fully_acked_files(&self) -> usize {
+        let acked_version = match self.acked_version {
+            Some(acked_version) => acked_version,
+            None => return 0,
+        };
+        self.files
+            .windows(2)
+            .take_while(|files| files[1] <= acked_version.saturating_add(1))
+            .count()
+    }
+
+    fn is_acked(&self, op_num: SeqNumberType) -> bool {
+        self.acked_version
+            .map_or(false, |acked_version| op_num <= acked_version)
+    }
+
+    /// Operations, which are replayed on recovery, and the size they take in the log
+    ///
+    /// Records of the files of other format versions are not parsed, their whole size is counted.
+    pub fn unacked_stats(&self) -> OperationResult<WalUnackedStats> {
+        let mut stats = WalUnackedStats::default();
+        for &first_op_num in &self.files[self.fully_acked_files()..] {
+            let file = read_file(&self.file_path(first_op_num))?;
+            if file.version != Some(WAL_FORMAT_VERSION) {
+                stats.bytes += file.size.saturating_sub(1);
+                continue;
+            }
+            for (record, record_size) in file.records {
+                if !self.is_acked(record.op_num) {
+                    stats.operations += 1;
+                    stats.bytes += record_size;
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    fn read_files(&self, first_op_nums: &[SeqNumberType]) -> OperationResult<Vec<WalRecord>> {
+        let mut records = vec![];
+        for &first_op_num in first_op_nums {
+            let file_path = self.file_path(first_op_num);
+            let file = read_file(&file_path)?;
+            match file.version {
+                Some(WAL_FORMAT_VERSION) | None => {}
+                Some(version) => {
+                    return Err(OperationError::service_error(format!(
+                        "Unsupported WAL format version {version} of {file_path:?}"
+                    )))
+                }
+            }
+            records.extend(file.records.into_iter().map(|(record, _)| record));
+        }
+        Ok(records)
+    }
+
+    /// All complete records of the log, in order
+    pub fn read_all(&self) -> OperationResult<Vec<WalRecord>> {
+        self.read_files(&self.files)
+    }
+
+    /// Records, which are not acknowledged yet, to be replayed onto the segment
+    ///
+    /// Fully acknowledged files are not read, so they may be written by other format versions.
+    pub fn read_unacked(&self) -> OperationResult<Vec<WalRecord>> {
+        let mut records = self.read_files(&self.files[self.fully_acked_files()..])?;
+        records.retain(|record| !self.is_acked(record.op_num));
+        Ok(records)
+    }
+}
+
+struct LogFile {
+    /// Format version of the file, `None` if the file is empty
+    version: Option<u8>,
+    /// Complete records with their size in the file
+    records: Vec<(WalRecord, usize)>,
+    /// Size of the version byte and the complete records
+    ///
+    /// Whole size of the file, if it is written by other format version.
+    size: usize,
+}
+
+/// Complete records of the log file
+///
+/// Reading stops at the first torn or corrupted record.
+/// Records of other format versions are not parsed.
+fn read_file(path: &Path) -> OperationResult<LogFile> {
+    let data = fs::read(path)?;
+    let version = match data.first() {
+        Some(&version) => version,
+        None => {
+            return Ok(LogFile {
+                version: None,
+                records: vec![],
+                size: 0,
+            })
+        }
+    };
+    if version != WAL_FORMAT_VERSION {
+        return Ok(LogFile {
+            version: Some(version),
+            records: vec![],
+            size: data.len(),
+        });
+    }
+
+    let mut records = vec![];
+    let mut offset = 1;
+    while data.len() >= offset + RECORD_HEADER_SIZE {
+        let body_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let checksum = u64::from_le_bytes(data[offset + 4..offset + 12].try_into().unwrap());
+        let body_start = offset + RECORD_HEADER_SIZE;
+        let body = match data.get(body_start..body_start + body_len) {
+            Some(body) if seahash::hash(body) == checksum => body,
+            _ => break,
+        };
+        match serde_cbor::from_slice(body) {
+            Ok(record) => records.push((record, RECORD_HEADER_SIZE + body_len)),
+            Err(err) => {
+                log::warn!("Skipping the rest of {path:?}, record is not parsed: {err}");
+                break;
+            }
+        }
+        offset = body_start + body_len;
+    }
+    Ok(LogFile {
+        version: Some(version),
+        records,
+        size: offset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::DEFAULT_VECTOR_NAME;
+    use crate::segment_constructor::build_segment;
+    use crate::types::{Distance, Indexes, SegmentConfig, StorageType, VectorDataConfig};
+
+    fn operation(point_id: u64) -> SegmentOperation {
+        match point_id % 3 {
+            0 => SegmentOperation::UpsertVector {
+                point_id: point_id.into(),
+                vectors: HashMap::from([(
+                    DEFAULT_VECTOR_NAME.to_owned(),
+                    vec![point_id as VectorElementType, 1.0],
+                )]),
+            },
+            1 => SegmentOperation::SetFullPayload {
+                point_id: (point_id - 1).into(),
+                payload: serde_json::json!({ "num": point_id, "tags": ["a", "b"] }).into(),
+            },
+            _ => SegmentOperation::DeletePayload {
+                point_id: (point_id - 2).into(),
+                key: "tags".to_owned(),
+            },
+        }
+    }
+
+    fn records(op_nums: std::ops::Range<u64>) -> Vec<WalRecord> {
+        op_nums
+            .map(|op_num| WalRecord {
+                op_num,
+                operation: operation(op_num),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_torn_record_is_dropped() {
+        let dir = Builder::new().prefix("wal_dir").tempdir().unwrap();
+        let options = SegmentWalOptions { max_file_size: 512 };
+        let num_records = 100;
+
+        let mut wal = SegmentWal::open(dir.path(), options).unwrap();
+        let written = records(0..num_records);
+        for record in &written {
+            wal.append(record).unwrap();
+        }
+        wal.flush().unwrap();
+        assert!(wal.files.len() > 1, "log is not rotated");
+        assert_eq!(wal.read_all().unwrap(), written);
+
+        // Crash in the middle of the last record
+        let last_file = wal.file_path(*wal.files.last().unwrap());
+        drop(wal);
+        let file_size = fs::metadata(&last_file).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&last_file)
+            .unwrap()
+            .set_len(file_size - 3)
+            .unwrap();
+
+        let mut wal = SegmentWal::open(dir.path(), options).unwrap();
+        let complete = num_records as usize - 1;
+        assert_eq!(wal.read_all().unwrap(), written[..complete]);
+
+        // Torn record is overwritten by the next one
+        let next = records(num_records..num_records + 1);
+        wal.append(&next[0]).unwrap();
+        drop(wal);
+        let wal = SegmentWal::open(dir.path(), options).unwrap();
+        let mut expected = written[..complete].to_vec();
+        expected.extend(next);
+        assert_eq!(wal.read_all().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_ack_straddling_file() {
+        let dir = Builder::new().prefix("wal_dir").tempdir().unwrap();
+        let options = SegmentWalOptions { max_file_size: 512 };
+        let num_records = 100;
+
+        let mut wal = SegmentWal::open(dir.path(), options).unwrap();
+        let written = records(0..num_records);
+        for record in &written {
+            wal.append(record).unwrap();
+        }
+        assert!(wal.files.len() > 3, "log is not rotated");
+        let files = wal.files.clone();
+        let unacked_size = |wal: &SegmentWal| -> usize {
+            wal.files
+                .iter()
+                .map(|&first_op_num| {
+                    fs::metadata(wal.file_path(first_op_num)).unwrap().len() as usize - 1
+                })
+                .sum()
+        };
+        assert_eq!(
+            wal.unacked_stats().unwrap(),
+            WalUnackedStats {
+                operations: num_records as usize,
+                bytes: unacked_size(&wal),
+            }
+        );
+
+        // Last record of the second file is not acked, so the file is kept
+        let version = files[2] - 2;
+        wal.ack(version).unwrap();
+        assert_eq!(wal.files, files[1..]);
+        assert!(!wal.file_path(files[0]).exists());
+        assert_eq!(wal.read_unacked().unwrap(), written[version as usize + 1..]);
+        let stats = wal.unacked_stats().unwrap();
+        assert_eq!(stats.operations, (num_records - version - 1) as usize);
+        assert!(stats.bytes < unacked_size(&wal));
+
+        // Last record of the second file is acked
+        let version = files[2] - 1;
+        wal.ack(version).unwrap();
+        assert_eq!(wal.files, files[2..]);
+        assert_eq!(
+            wal.unacked_stats().unwrap(),
+            WalUnackedStats {
+                operations: (num_records - version - 1) as usize,
+                bytes: unacked_size(&wal),
+            }
+        );
+
+        // Last file is kept, though it is fully acked
+        wal.ack(num_records + 10).unwrap();
+        assert_eq!(wal.files, files[files.len() - 1..]);
+        assert_eq!(wal.unacked_stats().unwrap(), WalUnackedStats::default());
+        assert!(wal.read_unacked().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_other_format_version() {
+        let dir = Builder::new().prefix("wal_dir").tempdir().unwrap();
+        let options = SegmentWalOptions { max_file_size: 512 };
+        fs::write(
+            dir.path()
+                .join(format!("{WAL_FILE_PREFIX}{:020}.{WAL_FILE_EXTENSION}", 0)),
+            [WAL_FORMAT_VERSION + 1, 1, 2, 3],
+        )
+        .unwrap();
+
+        let mut wal = SegmentWal::open(dir.path(), options).unwrap();
+        // Records are appended to a new file
+        let written = records(1..20);
+        for record in &written {
+            wal.append(record).unwrap();
+        }
+        assert_eq!(wal.files[..2], [0, 1]);
+        assert!(wal.read_unacked().is_err());
+        assert_eq!(wal.unacked_stats().unwrap().operations, written.len());
+
+        // File of other version is removed, once it is acked
+        wal.ack(0).unwrap();
+        assert_eq!(wal.files[0], 1);
+        assert_eq!(wal.read_unacked().unwrap(), written);
+    }
+
+    #[test]
+    fn test_ack_and_replay() {
+        let dir = Builder::new().prefix("wal_dir").tempdir().unwrap();
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let options = SegmentWalOptions { max_file_size: 512 };
+
+        let mut wal = SegmentWal::open(dir.path(), options).unwrap();
+        let written = records(0..100);
+        for record in &written {
+            wal.append(record).unwrap();
+        }
+        let num_files = wal.files.len();
+        wal.ack(50).unwrap();
+        assert!(wal.files.len() < num_files, "acked files are not removed");
+        // Rotated files only contain the records after the acked version
+        assert!(wal.read_all().unwrap()[0].op_num <= 51);
+        drop(wal);
+
+        let wal = SegmentWal::open(dir.path(), options).unwrap();
+        assert_eq!(wal.acked_version(), Some(50));
+        let unacked = wal.read_unacked().unwrap();
+        assert_eq!(unacked, written[51..]);
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(segment_dir.path(), &config).unwrap();
+        for record in &unacked {
+            record.operation.apply(record.op_num, &mut segment).unwrap();
+        }
+        assert_eq!(segment.version(), 99);
+        // Points 51, 54, ..., 99
+        assert_eq!(segment.points_count(), 17);
+        let payload = segment.payload(54.into()).unwrap();
+        assert_eq!(
+            payload.get_value("num").next(),
+            Some(&serde_json::json!(55))
+        );
+        assert!(payload.get_value("tags").next().is_none());
+    }
+}
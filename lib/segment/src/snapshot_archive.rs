@@ -0,0 +1,434 @@
+//! Segment, opened read-only from its snapshot archive without the full unpack
+//!
+//! Tar archive keeps the files uncompressed, so each member is located by the offset of its data.
+//! Files, needed to open the segment - the state, the database with the id mapping and payloads, and the indexes -
+//! are extracted into the cache directory. Mem-mapped vectors are read through the archive, see [`SnapshotArchive::map_file`],
+//! so their pages are only loaded on the first access.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use memmap2::{Mmap, MmapOptions};
+use uuid::Uuid;
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::madvise;
+use crate::segment::{SNAPSHOT_FILES_PATH, SNAPSHOT_PATH};
+use crate::segment_constructor::VECTOR_STORAGE_PATH;
+use crate::vector_storage::memmap_vector_storage::MMAP_VECTORS_FILE;
+
+/// Options of [`crate::segment_constructor::load_segment_from_archive`]
+#[derive(Debug, Clone)]
+pub struct ArchiveOpenOptions {
+    /// Directory, the files of the archive are extracted into
+    pub cache_dir: PathBuf,
+    /// Limit of the total size of the extracted files, the open fails if the segment needs more
+    pub cache_size_limit: Option<u64>,
+}
+
+/// Location of the member data in the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveMember {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Snapshot archive, indexed by the paths of its files
+pub struct SnapshotArchive {
+    path: PathBuf,
+    file: File,
+    /// Directory of the segment files in the archive, empty for the legacy format
+    files_path: PathBuf,
+    /// Files by their path in the archive
+    members: BTreeMap<PathBuf, ArchiveMember>,
+    /// Directory, the archive is extracted into
+    segment_path: PathBuf,
+}
+
+impl SnapshotArchive {
+    /// Index the members of the archive, which is extracted into `segment_path`
+    ///
+    /// Only the tar headers are read, the data of the members is skipped.
+    pub fn open(path: &Path, segment_path: &Path) -> OperationResult<Self> {
+        let file = File::open(path).map_err(|err| {
+            OperationError::service_error(format!(
+                "failed to open segment snapshot archive {path:?}: {err}"
+            ))
+        })?;
+
+        let mut members = BTreeMap::new();
+        let mut archive = tar::Archive::new(file.try_clone()?);
+        for entry in archive
+            .entries_with_seek()
+            .map_err(|err| archive_error(path, err))?
+        {
+            let entry = entry.map_err(|err| archive_error(path, err))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let member_path = entry.path().map_err(|err| archive_error(path, err))?;
+            members.insert(
+                member_path.into_owned(),
+                ArchiveMember {
+                    offset: entry.raw_file_position(),
+                    size: entry.size(),
+                },
+            );
+        }
+
+        let files_path = Path::new(SNAPSHOT_PATH).join(SNAPSHOT_FILES_PATH);
+        let files_path = if members
+            .keys()
+            .any(|member| member.starts_with(SNAPSHOT_PATH))
+        {
+            files_path
+        } else {
+            PathBuf::new()
+        };
+
+        Ok(SnapshotArchive {
+            path: path.to_owned(),
+            file,
+            files_path,
+            members,
+            segment_path: segment_path.to_owned(),
+        })
+    }
+
+    pub fn members(&self) -> &BTreeMap<PathBuf, ArchiveMember> {
+        &self.members
+    }
+
+    /// Path of the member in the segment directory, if it is one of the segment files
+    fn segment_file(&self, member_path: &Path) -> Option<PathBuf> {
+        member_path
+            .strip_prefix(&self.files_path)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    /// Files, which are read through the archive instead of being extracted
+    fn is_lazy(&self, member_path: &Path) -> bool {
+        self.segment_file(member_path).map_or(false, |file| {
+            let in_vector_storage = file
+                .parent()
+                .and_then(Path::file_name)
+                .map_or(false, |dir| {
+                    dir.to_string_lossy().starts_with(VECTOR_STORAGE_PATH)
+                });
+            in_vector_storage && file.file_name() == Some(MMAP_VECTORS_FILE.as_ref())
+        })
+    }
+
+    /// Total size of the files, extracted by [`SnapshotArchive::extract`]
+    pub fn extracted_size(&self) -> u64 {
+        self.members
+            .iter()
+            .filter(|(member_path, _)| !self.is_lazy(member_path))
+            .map(|(_, member)| member.size)
+            .sum()
+    }
+
+    /// Extract all files but the lazily read ones into the segment directory, as they are laid out in the archive
+    pub fn extract(&self, size_limit: Option<u64>) -> OperationResult<()> {
+        let extracted_size = self.extracted_size();
+        if let Some(size_limit) = size_limit {
+            if extracted_size > size_limit {
+                return Err(OperationError::service_error(format!(
+                    "segment snapshot archive {:?} needs {extracted_size} bytes of the cache, the limit is {size_limit} bytes",
+                    self.path
+                )));
+            }
+        }
+
+        fs::create_dir_all(&self.segment_path)?;
+        let mut archive = tar::Archive::new(self.file.try_clone()?);
+        for entry in archive
+            .entries_with_seek()
+            .map_err(|err| archive_error(&self.path, err))?
+        {
+            let mut entry = entry.map_err(|err| archive_error(&self.path, err))?;
+            let member_path = entry
+                .path()
+                .map_err(|err| archive_error(&self.path, err))?
+                .into_owned();
+            if self.is_lazy(&member_path) {
+                continue;
+            }
+            entry
+                .unpack_in(&self.segment_path)
+                .map_err(|err| archive_error(&self.path, err))?;
+        }
+        Ok(())
+    }
+
+    /// Map the data of the lazily read file, e.g. `<segment path>/vector_storage/matrix.dat`
+    pub fn map_file(&self, file_path: &Path) -> OperationResult<Mmap> {
+        let member = file_path
+            .strip_prefix(&self.segment_path)
+            .ok()
+            .map(|file| self.files_path.join(file))
+            .and_then(|member_path| self.members.get(&member_path))
+            .ok_or_else(|| {
+                OperationError::service_error(format!(
+                    "segment snapshot archive {:?} has no file {file_path:?}",
+                    self.path
+                ))
+            })?;
+        let mmap = unsafe {
+            MmapOptions::new()
+                .offset(member.offset)
+                .len(member.size as usize)
+                .map(&self.file)?
+        };
+        madvise::madvise(&mmap, madvise::get_global())?;
+        Ok(mmap)
+    }
+}
+
+fn archive_error(path: &Path, err: impl std::fmt::Display) -> OperationError {
+    OperationError::service_error(format!(
+        "failed to read segment snapshot archive {path:?}: {err}"
+    ))
+}
+
+/// Directory of the files, extracted from the archive, removed with the segment
+#[derive(Debug)]
+pub struct ArchiveCache {
+    path: PathBuf,
+}
+
+impl ArchiveCache {
+    /// Create the unique directory for the archive in the `cache_dir`
+    pub fn create(cache_dir: &Path, archive_path: &Path) -> OperationResult<Self> {
+        let archive_name = archive_path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let path = cache_dir.join(format!("{archive_name}-{}", Uuid::new_v4()));
+        fs::create_dir_all(&path)?;
+        Ok(ArchiveCache { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ArchiveCache {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.path) {
+            log::error!(
+                "Failed to remove segment archive cache {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::fixtures::index_fixtures::random_vector;
+    use crate::segment::Segment;
+    use crate::segment_constructor::segment_builder::SegmentBuilder;
+    use crate::segment_constructor::{build_segment, load_segment_from_archive};
+    use crate::types::{
+        Condition, Distance, FieldCondition, Filter, HnswConfig, Indexes, Payload,
+        PayloadSchemaType, SegmentConfig, SnapshotContents, StorageType, VectorDataConfig,
+        WithPayload, WithVector,
+    };
+
+    const DIM: usize = 8;
+
+    fn segment_config(index: Indexes, storage_type: StorageType) -> SegmentConfig {
+        SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index,
+            storage_type,
+            ..Default::default()
+        }
+    }
+
+    /// Indexed segment with mem-mapped vectors and the keyword index
+    fn indexed_segment(segment_path: &Path, temp_path: &Path) -> Segment {
+        let stopped = AtomicBool::new(false);
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut plain_segment = build_segment(
+            segment_path,
+            &segment_config(Indexes::Plain {}, StorageType::InMemory),
+        )
+        .unwrap();
+        for point_id in 0..500u64 {
+            let vector = random_vector(&mut rng, DIM);
+            plain_segment
+                .upsert_vector(point_id, point_id.into(), &only_default_vector(&vector))
+                .unwrap();
+            let color = if point_id % 3 == 0 { "red" } else { "blue" };
+            let payload: Payload = json!({ "color": color }).into();
+            plain_segment
+                .set_full_payload(point_id, point_id.into(), &payload)
+                .unwrap();
+        }
+        plain_segment
+            .create_field_index(500, "color", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+
+        let config = segment_config(
+            Indexes::Hnsw(HnswConfig {
+                full_scan_threshold: 1,
+                ..Default::default()
+            }),
+            StorageType::Mmap,
+        );
+        let mut builder = SegmentBuilder::new(segment_path, temp_path, &config).unwrap();
+        builder.update_from(&plain_segment, &stopped).unwrap();
+        builder.build(&stopped).unwrap()
+    }
+
+    #[test]
+    fn test_load_segment_from_archive() {
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let cache_dir = Builder::new().prefix("cache_dir").tempdir().unwrap();
+
+        let segment = indexed_segment(segment_dir.path(), temp_dir.path());
+        let archive_path = segment
+            .take_snapshot(snapshot_dir.path(), SnapshotContents::default())
+            .unwrap();
+
+        let options = ArchiveOpenOptions {
+            cache_dir: cache_dir.path().to_owned(),
+            cache_size_limit: None,
+        };
+        let mut opened = load_segment_from_archive(&archive_path, &options).unwrap();
+        assert!(!opened.is_appendable());
+        assert_eq!(opened.points_count(), segment.points_count());
+        assert_eq!(opened.get_indexed_fields(), segment.get_indexed_fields());
+
+        // Vectors are read through the archive
+        let cache_path = opened.current_path.clone();
+        assert!(cache_path.starts_with(cache_dir.path()));
+        assert!(!cache_path
+            .join("vector_storage")
+            .join(MMAP_VECTORS_FILE)
+            .exists());
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let red = Filter::new_must(Condition::Field(FieldCondition::new_match(
+            "color".to_string(),
+            "red".to_owned().into(),
+        )));
+        for _ in 0..10 {
+            let query = random_vector(&mut rng, DIM);
+            for filter in [None, Some(&red)] {
+                let search = |segment: &Segment| {
+                    segment
+                        .search(
+                            DEFAULT_VECTOR_NAME,
+                            &query,
+                            &WithPayload::from(true),
+                            &WithVector::Bool(true),
+                            filter,
+                            10,
+                            None,
+                        )
+                        .unwrap()
+                };
+                let found = search(&opened);
+                let expected = search(&segment);
+                assert_eq!(found.len(), 10);
+                assert_eq!(found, expected);
+                for (found, expected) in found.iter().zip(&expected) {
+                    assert_eq!(found.payload, expected.payload);
+                    assert_eq!(found.vector, expected.vector);
+                }
+            }
+        }
+
+        // All updates are rejected
+        let vector = vec![1.0; DIM];
+        assert!(matches!(
+            opened.upsert_vector(1000, 1000.into(), &only_default_vector(&vector)),
+            Err(OperationError::SegmentNotAppendable { .. })
+        ));
+        assert!(matches!(
+            opened.delete_point(1000, 1.into()),
+            Err(OperationError::SegmentNotAppendable { .. })
+        ));
+        assert!(matches!(
+            opened.set_full_payload(1000, 1.into(), &Payload::default()),
+            Err(OperationError::SegmentNotAppendable { .. })
+        ));
+        assert!(matches!(
+            opened.delete_field_index(1000, "color"),
+            Err(OperationError::SegmentNotAppendable { .. })
+        ));
+        assert_eq!(opened.points_count(), segment.points_count());
+
+        // Extracted files are removed with the segment
+        drop(opened);
+        assert!(!cache_path.exists());
+    }
+
+    #[test]
+    fn test_archive_cache_size_limit() {
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let cache_dir = Builder::new().prefix("cache_dir").tempdir().unwrap();
+
+        let segment = indexed_segment(segment_dir.path(), temp_dir.path());
+        let archive_path = segment
+            .take_snapshot(snapshot_dir.path(), SnapshotContents::default())
+            .unwrap();
+
+        let archive = SnapshotArchive::open(&archive_path, cache_dir.path()).unwrap();
+        let vectors_member = Path::new(SNAPSHOT_PATH)
+            .join(SNAPSHOT_FILES_PATH)
+            .join("vector_storage")
+            .join(MMAP_VECTORS_FILE);
+        let vectors_size = archive.members()[&vectors_member].size;
+        let total_size: u64 = archive.members().values().map(|member| member.size).sum();
+        assert_eq!(archive.extracted_size(), total_size - vectors_size);
+
+        let options = ArchiveOpenOptions {
+            cache_dir: cache_dir.path().to_owned(),
+            cache_size_limit: Some(archive.extracted_size() - 1),
+        };
+        let error = load_segment_from_archive(&archive_path, &options)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("the limit is"), "{error}");
+        // Cache of the failed open is removed
+        assert_eq!(cache_dir.path().read_dir().unwrap().count(), 0);
+
+        let options = ArchiveOpenOptions {
+            cache_size_limit: Some(archive.extracted_size()),
+            ..options
+        };
+        let opened = load_segment_from_archive(&archive_path, &options).unwrap();
+        assert_eq!(opened.points_count(), segment.points_count());
+    }
+}
@@ -0,0 +1,728 @@
+//! Delta snapshots of the segment
+//!
+//! Snapshot archive is described by the [`SnapshotManifest`]: checksums of the fixed size chunks of each file in it.
+//! Delta archive holds only the chunks, changed since the base manifest, and the [`DeltaManifest`],
+//! which tells how the files of the next snapshot are reassembled from the chunks of the base and the delta.
+//! Snapshot is restored from the base archive and the chain of deltas, see [`crate::segment::Segment::restore_snapshot_chain`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::atomic_rename::{commit_file, temp_path};
+use crate::common::file_operations::{atomic_save_json, read_json};
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::segment::{SEGMENT_STATE_FILE, SNAPSHOT_FILES_PATH, SNAPSHOT_PATH};
+use crate::types::{SegmentState, SeqNumberType};
+use crate::utils;
+
+/// Format version of the manifests, written by this code
+pub const SNAPSHOT_MANIFEST_FORMAT: u32 = 1;
+
+/// Size of the chunks, compared by the delta
+pub const DELTA_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Largest chunk size of the delta, which is accepted, as a buffer of the chunk size is allocated
+const MAX_DELTA_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Manifest of the delta, the first entry of its archive
+pub const DELTA_MANIFEST_FILE: &str = "delta_manifest.json";
+
+/// Directory of the delta archive with the changed chunks, as `chunks/<file path>/<chunk index>`
+const DELTA_CHUNKS_PATH: &str = "chunks";
+
+/// Directory, the delta is unpacked into, while it is applied
+const DELTA_UNPACK_PATH: &str = ".delta";
+
+/// Files of the snapshot archive, chunked by their checksums
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SnapshotManifest {
+    pub format: u32,
+    /// Version of the segment in the snapshot, if it is known
+    pub segment_version: Option<SeqNumberType>,
+    pub chunk_size: u64,
+    /// Files by their path in the archive
+    pub files: BTreeMap<String, FileChunks>,
+    /// Directories of the archive, which are not implied by the files
+    pub dirs: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FileChunks {
+    pub size: u64,
+    /// Crc32 of each chunk of the file
+    pub chunks: Vec<u32>,
+}
+
+impl FileChunks {
+    fn chunk_len(&self, chunk_size: u64, index: usize) -> u64 {
+        let offset = index as u64 * chunk_size;
+        chunk_size.min(self.size.saturating_sub(offset))
+    }
+}
+
+/// Instructions to reassemble the next snapshot from the base one
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeltaManifest {
+    pub format: u32,
+    pub base_version: Option<SeqNumberType>,
+    /// Digest of the files of the base manifest, the delta is applied only on top of it
+    pub base_digest: u32,
+    /// Manifest of the snapshot, assembled by the delta
+    pub manifest: SnapshotManifest,
+    /// Indexes of the chunks, stored in the delta, by the file path.
+    /// Chunks, not listed here, are taken at the same offset from the base file.
+    pub included_chunks: BTreeMap<String, Vec<usize>>,
+}
+
+impl SnapshotManifest {
+    /// Manifest of the snapshot archive, created by [`crate::entry::entry_point::SegmentEntry::take_snapshot`]
+    pub fn of_archive(archive_path: &Path) -> OperationResult<Self> {
+        let (manifest, _) = scan_archive(archive_path, None, None)?;
+        Ok(manifest)
+    }
+
+    /// Manifest of the snapshot, assembled by the delta, the base of the next delta of the chain
+    pub fn of_delta(delta_path: &Path) -> OperationResult<Self> {
+        Ok(DeltaManifest::read(delta_path)?.manifest)
+    }
+
+    /// Manifest of the unpacked snapshot archive
+    fn of_dir(dir: &Path) -> OperationResult<Self> {
+        let mut manifest = Self::empty();
+        let mut pending_dirs = vec![dir.to_owned()];
+        while let Some(current_dir) = pending_dirs.pop() {
+            for entry in fs::read_dir(&current_dir)? {
+                let path = entry?.path();
+                let relative_path = archive_path_of(dir, &path)?;
+                if path.is_dir() {
+                    manifest.dirs.insert(relative_path);
+                    pending_dirs.push(path);
+                } else {
+                    let chunks =
+                        read_chunks(File::open(&path)?, manifest.chunk_size, |_, _| Ok(()))?;
+                    manifest.files.insert(relative_path, chunks);
+                }
+            }
+        }
+        let state_path = dir.join(snapshot_state_path());
+        if state_path.exists() {
+            manifest.segment_version = read_json::<SegmentState>(&state_path)?.version;
+        }
+        Ok(manifest)
+    }
+
+    fn empty() -> Self {
+        SnapshotManifest {
+            format: SNAPSHOT_MANIFEST_FORMAT,
+            segment_version: None,
+            chunk_size: DELTA_CHUNK_SIZE,
+            files: Default::default(),
+            dirs: Default::default(),
+        }
+    }
+
+    /// Checksum of the files, identifies the snapshot regardless of the directories
+    pub fn digest(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        for (path, chunks) in &self.files {
+            hasher.update(path.as_bytes());
+            hasher.update(&chunks.size.to_le_bytes());
+            for chunk in &chunks.chunks {
+                hasher.update(&chunk.to_le_bytes());
+            }
+        }
+        hasher.finalize()
+    }
+
+    pub fn check_format(&self) -> OperationResult<()> {
+        check_format(self.format)
+    }
+}
+
+impl DeltaManifest {
+    /// Read the manifest of the delta archive, created by [`write_delta`]
+    pub fn read(delta_path: &Path) -> OperationResult<Self> {
+        let mut archive = tar::Archive::new(open_archive(delta_path)?);
+        for entry in archive.entries()? {
+            let entry = entry?;
+            if entry.path()? == Path::new(DELTA_MANIFEST_FILE) {
+                let delta: DeltaManifest = serde_json::from_reader(entry)?;
+                check_format(delta.format)?;
+                check_format(delta.manifest.format)?;
+                delta.check_paths(delta_path)?;
+                return Ok(delta);
+            }
+        }
+        Err(OperationError::service_error(format!(
+            "segment snapshot delta {delta_path:?} has no {DELTA_MANIFEST_FILE}"
+        )))
+    }
+
+    /// Delta doesn't change the base snapshot
+    pub fn is_empty(&self) -> bool {
+        self.included_chunks.is_empty() && self.manifest.digest() == self.base_digest
+    }
+
+    /// Reject the delta, which would write outside of the directory it is applied to,
+    /// or allocate a chunk buffer of an unreasonable size
+    fn check_paths(&self, delta_path: &Path) -> OperationResult<()> {
+        let chunk_size = self.manifest.chunk_size;
+        if chunk_size == 0 || chunk_size > MAX_DELTA_CHUNK_SIZE {
+            return Err(OperationError::service_error(format!(
+                "segment snapshot delta {delta_path:?} has chunk size {chunk_size}, \
+                expected from 1 to {MAX_DELTA_CHUNK_SIZE}"
+            )));
+        }
+        let paths = self
+            .manifest
+            .files
+            .keys()
+            .chain(&self.manifest.dirs)
+            .chain(self.included_chunks.keys());
+        for path in paths {
+            let is_relative = Path::new(path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if path.is_empty() || !is_relative {
+                return Err(OperationError::service_error(format!(
+                    "segment snapshot delta {delta_path:?} has invalid path {path:?}, \
+                    expected a relative one without `..`"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_format(format: u32) -> OperationResult<()> {
+    if format > SNAPSHOT_MANIFEST_FORMAT {
+        return Err(OperationError::service_error(format!(
+            "snapshot manifest format {format} is newer than supported {SNAPSHOT_MANIFEST_FORMAT}"
+        )));
+    }
+    Ok(())
+}
+
+/// Path of the segment state in the snapshot archive
+fn snapshot_state_path() -> PathBuf {
+    Path::new(SNAPSHOT_PATH)
+        .join(SNAPSHOT_FILES_PATH)
+        .join(SEGMENT_STATE_FILE)
+}
+
+fn archive_path_of(base: &Path, path: &Path) -> OperationResult<String> {
+    let relative_path = utils::path::strip_prefix(path, base)?;
+    Ok(relative_path.to_string_lossy().into_owned())
+}
+
+fn chunk_path(chunks_dir: &Path, file: &str, index: usize) -> PathBuf {
+    chunks_dir.join(file).join(index.to_string())
+}
+
+fn open_archive(archive_path: &Path) -> OperationResult<File> {
+    File::open(archive_path).map_err(|err| {
+        OperationError::service_error(format!(
+            "failed to open segment snapshot archive {archive_path:?}: {err}"
+        ))
+    })
+}
+
+/// Read the chunks of the file, `visit`ing each with its index
+fn read_chunks(
+    mut reader: impl Read,
+    chunk_size: u64,
+    mut visit: impl FnMut(usize, &[u8]) -> OperationResult<()>,
+) -> OperationResult<FileChunks> {
+    let mut file_chunks = FileChunks {
+        size: 0,
+        chunks: vec![],
+    };
+    let mut buffer = vec![0; chunk_size as usize];
+    loop {
+        let mut len = 0;
+        while len < buffer.len() {
+            let read = reader.read(&mut buffer[len..])?;
+            if read == 0 {
+                break;
+            }
+            len += read;
+        }
+        if len == 0 {
+            break;
+        }
+        let chunk = &buffer[..len];
+        visit(file_chunks.chunks.len(), chunk)?;
+        file_chunks.chunks.push(crc32fast::hash(chunk));
+        file_chunks.size += len as u64;
+        if len < buffer.len() {
+            break;
+        }
+    }
+    Ok(file_chunks)
+}
+
+/// Manifest of the archive, and the chunks, changed since the `base`, which are written into the `chunks_dir`
+fn scan_archive(
+    archive_path: &Path,
+    base: Option<&SnapshotManifest>,
+    chunks_dir: Option<&Path>,
+) -> OperationResult<(SnapshotManifest, BTreeMap<String, Vec<usize>>)> {
+    let mut manifest = SnapshotManifest::empty();
+    let mut included_chunks: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let state_path = snapshot_state_path();
+
+    let mut archive = tar::Archive::new(open_archive(archive_path)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let file = path.to_string_lossy().trim_end_matches('/').to_owned();
+        if entry.header().entry_type().is_dir() {
+            manifest.dirs.insert(file);
+            continue;
+        }
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let base_chunks = base.and_then(|base| base.files.get(&file));
+        let mut state = Vec::new();
+        let chunks = read_chunks(entry, manifest.chunk_size, |index, chunk| {
+            if path == state_path {
+                state.extend_from_slice(chunk);
+            }
+            let chunks_dir = match chunks_dir {
+                Some(chunks_dir) => chunks_dir,
+                None => return Ok(()),
+            };
+            let unchanged = base_chunks.map_or(false, |base_chunks| {
+                base_chunks.chunks.get(index) == Some(&crc32fast::hash(chunk))
+                    && base_chunks.chunk_len(manifest.chunk_size, index) == chunk.len() as u64
+            });
+            if !unchanged {
+                let chunk_path = chunk_path(chunks_dir, &file, index);
+                if let Some(parent) = chunk_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(chunk_path, chunk)?;
+                included_chunks.entry(file.clone()).or_default().push(index);
+            }
+            Ok(())
+        })?;
+        if !state.is_empty() {
+            manifest.segment_version = serde_json::from_slice::<SegmentState>(&state)?.version;
+        }
+        manifest.files.insert(file, chunks);
+    }
+
+    // Parents of the files are created along with them
+    let implied_dirs: BTreeSet<_> = manifest
+        .files
+        .keys()
+        .flat_map(|file| Path::new(file).ancestors().skip(1))
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .collect();
+    manifest.dirs.retain(|dir| !implied_dirs.contains(dir));
+    Ok((manifest, included_chunks))
+}
+
+/// Write the delta of the snapshot `archive_path` since the `base` into `delta_path`
+pub fn write_delta(
+    archive_path: &Path,
+    base: &SnapshotManifest,
+    delta_path: &Path,
+) -> OperationResult<DeltaManifest> {
+    base.check_format()?;
+    let work_path = temp_path(delta_path);
+    fs::create_dir_all(&work_path)?;
+    let result = scan_archive(
+        archive_path,
+        Some(base),
+        Some(&work_path.join(DELTA_CHUNKS_PATH)),
+    )
+    .and_then(|(manifest, included_chunks)| {
+        let delta = DeltaManifest {
+            format: SNAPSHOT_MANIFEST_FORMAT,
+            base_version: base.segment_version,
+            base_digest: base.digest(),
+            manifest,
+            included_chunks,
+        };
+        pack_delta(&delta, &work_path, delta_path)?;
+        Ok(delta)
+    });
+    fs::remove_dir_all(&work_path)?;
+    result
+}
+
+/// Write the delta, which doesn't change the `base`, into `delta_path`
+pub fn write_empty_delta(
+    base: &SnapshotManifest,
+    delta_path: &Path,
+) -> OperationResult<DeltaManifest> {
+    base.check_format()?;
+    let delta = DeltaManifest {
+        format: SNAPSHOT_MANIFEST_FORMAT,
+        base_version: base.segment_version,
+        base_digest: base.digest(),
+        manifest: base.clone(),
+        included_chunks: Default::default(),
+    };
+    let work_path = temp_path(delta_path);
+    fs::create_dir_all(&work_path)?;
+    let result = pack_delta(&delta, &work_path, delta_path);
+    fs::remove_dir_all(&work_path)?;
+    result.map(|()| delta)
+}
+
+fn pack_delta(delta: &DeltaManifest, work_path: &Path, delta_path: &Path) -> OperationResult<()> {
+    let manifest_path = work_path.join(DELTA_MANIFEST_FILE);
+    atomic_save_json(&manifest_path, delta)?;
+
+    // Archive is renamed into place once complete, so a crash never leaves a partial one
+    let temp_archive_path = work_path.join("delta.tar");
+    let file = File::create(&temp_archive_path).map_err(|err| {
+        OperationError::service_error(format!(
+            "failed to create segment snapshot delta {temp_archive_path:?}: {err}"
+        ))
+    })?;
+    let mut builder = tar::Builder::new(file);
+    utils::tar::append_file(&mut builder, &manifest_path, Path::new(DELTA_MANIFEST_FILE))?;
+    let chunks_path = work_path.join(DELTA_CHUNKS_PATH);
+    if chunks_path.exists() {
+        builder
+            .append_dir_all(DELTA_CHUNKS_PATH, &chunks_path)
+            .map_err(|err| utils::tar::failed_to_append_error(&chunks_path, err))?;
+    }
+    builder.finish()?;
+    drop(builder);
+    commit_file(&temp_archive_path, delta_path)
+}
+
+/// Apply the delta to the snapshot archive, unpacked into `dir`
+///
+/// Delta is rejected, unless it is based on the unpacked snapshot.
+pub fn apply_delta(dir: &Path, delta_path: &Path) -> OperationResult<()> {
+    let current = SnapshotManifest::of_dir(dir)?;
+    let delta = DeltaManifest::read(delta_path)?;
+    if current.digest() != delta.base_digest {
+        return Err(OperationError::service_error(format!(
+            "segment snapshot delta {delta_path:?} of the base version {:?} \
+            doesn't apply to the snapshot of version {:?}",
+            delta.base_version, current.segment_version,
+        )));
+    }
+
+    let unpack_path = dir.join(DELTA_UNPACK_PATH);
+    tar::Archive::new(open_archive(delta_path)?)
+        .unpack(&unpack_path)
+        .map_err(|err| {
+            OperationError::service_error(format!(
+                "failed to unpack segment snapshot delta {delta_path:?}: {err}"
+            ))
+        })?;
+    let chunks_dir = unpack_path.join(DELTA_CHUNKS_PATH);
+
+    let manifest = &delta.manifest;
+    let mut buffer = vec![0; manifest.chunk_size as usize];
+    for (file, file_chunks) in &manifest.files {
+        let included = delta.included_chunks.get(file);
+        let target_path = dir.join(file);
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut base_file = if target_path.exists() {
+            Some(File::open(&target_path)?)
+        } else {
+            None
+        };
+
+        let assembled_path = temp_path(&target_path);
+        let mut assembled = File::create(&assembled_path)?;
+        for (index, checksum) in file_chunks.chunks.iter().enumerate() {
+            let chunk = &mut buffer[..file_chunks.chunk_len(manifest.chunk_size, index) as usize];
+            if included.map_or(false, |included| included.contains(&index)) {
+                File::open(chunk_path(&chunks_dir, file, index))?.read_exact(chunk)?;
+            } else {
+                let base_file = base_file.as_mut().ok_or_else(|| {
+                    OperationError::service_error(format!(
+                        "file {file} of segment snapshot delta {delta_path:?} is missing in the base"
+                    ))
+                })?;
+                base_file.seek(SeekFrom::Start(index as u64 * manifest.chunk_size))?;
+                base_file.read_exact(chunk)?;
+            }
+            if crc32fast::hash(chunk) != *checksum {
+                return Err(OperationError::service_error(format!(
+                    "checksum mismatch of chunk {index} of file {file}, \
+                    assembled by segment snapshot delta {delta_path:?}"
+                )));
+            }
+            assembled.write_all(chunk)?;
+        }
+        drop(base_file);
+        drop(assembled);
+        fs::rename(&assembled_path, &target_path)?;
+    }
+    fs::remove_dir_all(&unpack_path)?;
+
+    for file in current.files.keys() {
+        if !manifest.files.contains_key(file) {
+            fs::remove_file(dir.join(file))?;
+        }
+    }
+    for manifest_dir in &manifest.dirs {
+        fs::create_dir_all(dir.join(manifest_dir))?;
+    }
+    // Deepest directories go first, so their emptied parents are removed too
+    for current_dir in current.dirs.iter().rev() {
+        let path = dir.join(current_dir);
+        if !manifest.dirs.contains(current_dir) && path.read_dir()?.next().is_none() {
+            fs::remove_dir(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use crate::entry::entry_point::SegmentEntry;
+    use crate::segment::Segment;
+    use crate::segment_constructor::segment_builder::SegmentBuilder;
+    use crate::segment_constructor::{build_segment, load_segment};
+    use crate::types::{
+        Distance, Indexes, Payload, SegmentConfig, SnapshotContents, StorageType, VectorDataConfig,
+    };
+
+    fn segment_config(storage_type: StorageType) -> SegmentConfig {
+        SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type,
+            ..Default::default()
+        }
+    }
+
+    fn upsert(segment: &mut Segment, op_num: SeqNumberType, point_id: u64) {
+        let vector = [op_num as f32, point_id as f32];
+        segment
+            .upsert_vector(op_num, point_id.into(), &only_default_vector(&vector))
+            .unwrap();
+        let payload: Payload = serde_json::from_value(json!({ "op_num": op_num })).unwrap();
+        segment
+            .set_full_payload(op_num, point_id.into(), &payload)
+            .unwrap();
+    }
+
+    fn assert_same_points(segment: &Segment, restored: &Segment) {
+        assert_eq!(restored.version(), segment.version());
+        assert_eq!(restored.points_count(), segment.points_count());
+        for point_id in segment.iter_points() {
+            assert_eq!(
+                restored.point_version(point_id.clone()),
+                segment.point_version(point_id.clone())
+            );
+            assert_eq!(
+                restored.all_vectors(point_id.clone()).unwrap(),
+                segment.all_vectors(point_id.clone()).unwrap()
+            );
+            assert_eq!(
+                restored.payload(point_id.clone()).unwrap(),
+                segment.payload(point_id).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_restore_delta_chain() {
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let restore_dir = Builder::new().prefix("restore_dir").tempdir().unwrap();
+
+        let mut segment =
+            build_segment(segment_dir.path(), &segment_config(StorageType::InMemory)).unwrap();
+        for point_id in 0..20 {
+            upsert(&mut segment, point_id, point_id);
+        }
+        let snapshot_path = segment
+            .take_snapshot(snapshot_dir.path(), SnapshotContents::default())
+            .unwrap();
+        let base = SnapshotManifest::of_archive(&snapshot_path).unwrap();
+        assert_eq!(base.segment_version, Some(19));
+
+        upsert(&mut segment, 20, 3);
+        upsert(&mut segment, 21, 25);
+        segment.delete_point(22, 7.into()).unwrap();
+        let first_delta_path = segment
+            .take_snapshot_delta(snapshot_dir.path(), &base)
+            .unwrap();
+        let first_delta = DeltaManifest::read(&first_delta_path).unwrap();
+        assert!(!first_delta.is_empty());
+        assert_eq!(first_delta.base_version, Some(19));
+        assert_eq!(first_delta.manifest.segment_version, Some(22));
+
+        upsert(&mut segment, 23, 26);
+        segment.clear_payload(24, 4.into()).unwrap();
+        let second_delta_path = segment
+            .take_snapshot_delta(
+                snapshot_dir.path(),
+                &SnapshotManifest::of_delta(&first_delta_path).unwrap(),
+            )
+            .unwrap();
+
+        // Delta applies only on top of its base
+        let restore_path = restore_dir.path().join("segment");
+        assert!(Segment::restore_snapshot_chain(
+            &snapshot_path,
+            &[second_delta_path.clone()],
+            &restore_path,
+            false,
+        )
+        .is_err());
+        assert!(!restore_path.exists());
+
+        Segment::restore_snapshot_chain(
+            &snapshot_path,
+            &[first_delta_path, second_delta_path],
+            &restore_path,
+            false,
+        )
+        .unwrap();
+        let restored = load_segment(&restore_path).unwrap().unwrap();
+        assert_same_points(&segment, &restored);
+        assert!(!restored.has_point(7.into()));
+        assert!(restored.payload(4.into()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delta_of_immutable_segment_is_empty() {
+        let stopped = AtomicBool::new(false);
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+        let snapshot_dir = Builder::new().prefix("snapshot_dir").tempdir().unwrap();
+        let restore_dir = Builder::new().prefix("restore_dir").tempdir().unwrap();
+
+        let mut plain_segment =
+            build_segment(segment_dir.path(), &segment_config(StorageType::InMemory)).unwrap();
+        for point_id in 0..20 {
+            upsert(&mut plain_segment, point_id, point_id);
+        }
+        let mut builder = SegmentBuilder::new(
+            segment_dir.path(),
+            temp_dir.path(),
+            &segment_config(StorageType::Mmap),
+        )
+        .unwrap();
+        builder.update_from(&plain_segment, &stopped).unwrap();
+        let segment = builder.build(&stopped).unwrap();
+        assert!(!segment.is_appendable());
+
+        let snapshot_path = segment
+            .take_snapshot(snapshot_dir.path(), SnapshotContents::default())
+            .unwrap();
+        let base = SnapshotManifest::of_archive(&snapshot_path).unwrap();
+        let delta_path = segment
+            .take_snapshot_delta(snapshot_dir.path(), &base)
+            .unwrap();
+        let delta = DeltaManifest::read(&delta_path).unwrap();
+        assert!(delta.is_empty());
+        assert_eq!(SnapshotManifest::of_delta(&delta_path).unwrap(), base);
+
+        let restore_path = restore_dir.path().join("segment");
+        Segment::restore_snapshot_chain(&snapshot_path, &[delta_path], &restore_path, false)
+            .unwrap();
+        let restored = load_segment(&restore_path).unwrap().unwrap();
+        assert_same_points(&segment, &restored);
+    }
+
+    #[test]
+    fn test_hostile_delta_manifest() {
+        let delta_dir = Builder::new().prefix("delta_dir").tempdir().unwrap();
+        let restore_dir = Builder::new().prefix("restore_dir").tempdir().unwrap();
+        let outside_path = delta_dir.path().join("outside");
+
+        // Delta of the single file, based on the empty snapshot
+        let pack = |update: &dyn Fn(&mut DeltaManifest)| {
+            let mut manifest = SnapshotManifest::empty();
+            let file_chunks = FileChunks {
+                size: 1,
+                chunks: vec![crc32fast::hash(b"x")],
+            };
+            manifest.files.insert("file".to_owned(), file_chunks);
+            let mut delta = DeltaManifest {
+                format: SNAPSHOT_MANIFEST_FORMAT,
+                base_version: None,
+                base_digest: SnapshotManifest::empty().digest(),
+                manifest,
+                included_chunks: BTreeMap::from([("file".to_owned(), vec![0])]),
+            };
+            update(&mut delta);
+
+            let work_path = delta_dir.path().join("work");
+            let chunks_dir = work_path.join(DELTA_CHUNKS_PATH);
+            fs::create_dir_all(&chunks_dir).unwrap();
+            let chunk_path = chunk_path(&chunks_dir, "file", 0);
+            fs::create_dir_all(chunk_path.parent().unwrap()).unwrap();
+            fs::write(chunk_path, b"x").unwrap();
+            let delta_path = delta_dir.path().join("delta.tar");
+            pack_delta(&delta, &work_path, &delta_path).unwrap();
+            fs::remove_dir_all(&work_path).unwrap();
+            delta_path
+        };
+        let rename_file = |delta: &mut DeltaManifest, file: &str| {
+            let file_chunks = delta.manifest.files.remove("file").unwrap();
+            delta.manifest.files.insert(file.to_owned(), file_chunks);
+        };
+
+        let outside = outside_path.to_string_lossy().into_owned();
+        let hostile_updates: Vec<Box<dyn Fn(&mut DeltaManifest) + '_>> = vec![
+            Box::new(|delta: &mut DeltaManifest| rename_file(delta, &outside)),
+            Box::new(|delta: &mut DeltaManifest| rename_file(delta, "../outside")),
+            Box::new(|delta: &mut DeltaManifest| rename_file(delta, "dir/../../outside")),
+            Box::new(|delta: &mut DeltaManifest| rename_file(delta, "")),
+            Box::new(|delta: &mut DeltaManifest| {
+                delta.manifest.dirs.insert(outside.clone());
+            }),
+            Box::new(|delta: &mut DeltaManifest| {
+                let included = delta.included_chunks.remove("file").unwrap();
+                delta.included_chunks.insert("../file".to_owned(), included);
+            }),
+            Box::new(|delta: &mut DeltaManifest| delta.manifest.chunk_size = u64::MAX),
+            Box::new(|delta: &mut DeltaManifest| delta.manifest.chunk_size = 0),
+        ];
+        for update in &hostile_updates {
+            let delta_path = pack(update.as_ref());
+            assert!(DeltaManifest::read(&delta_path).is_err());
+            assert!(apply_delta(restore_dir.path(), &delta_path).is_err());
+            assert!(!outside_path.exists());
+            assert!(fs::read_dir(restore_dir.path()).unwrap().next().is_none());
+        }
+
+        // Same delta with the valid paths is applied
+        let delta_path = pack(&|_: &mut DeltaManifest| {});
+        apply_delta(restore_dir.path(), &delta_path).unwrap();
+        assert_eq!(fs::read(restore_dir.path().join("file")).unwrap(), b"x");
+    }
+}
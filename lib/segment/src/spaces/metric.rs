@@ -8,6 +8,13 @@ pub trait Metric {
     /// Greater the value - closer the vectors
     fn similarity(v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType;
 
+    /// Similarity of the vectors with the zero padding to the multiple of 32 bytes, which start at 32-byte boundary
+    ///
+    /// Padding doesn't change the similarity, so it falls back to [`Metric::similarity`] without the aligned kernels.
+    fn similarity_aligned(v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType {
+        Self::similarity(v1, v2)
+    }
+
     /// Necessary vector transformations performed before adding it to the collection (like normalization)
     /// Return None if metric does not required preprocessing
     fn preprocess(vector: &[VectorElementType]) -> Option<Vec<VectorElementType>>;
@@ -60,6 +60,24 @@ impl Metric for EuclidMetric {
         euclid_similarity(v1, v2)
     }
 
+    fn similarity_aligned(v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
+                return unsafe { euclid_similarity_avx_aligned(v1, v2) };
+            }
+        }
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("sse") {
+                return unsafe { euclid_similarity_sse_aligned(v1, v2) };
+            }
+        }
+
+        Self::similarity(v1, v2)
+    }
+
     fn preprocess(_vector: &[VectorElementType]) -> Option<Vec<VectorElementType>> {
         None
     }
@@ -102,6 +120,24 @@ impl Metric for DotProductMetric {
         dot_similarity(v1, v2)
     }
 
+    fn similarity_aligned(v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
+                return unsafe { dot_similarity_avx_aligned(v1, v2) };
+            }
+        }
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("sse") {
+                return unsafe { dot_similarity_sse_aligned(v1, v2) };
+            }
+        }
+
+        Self::similarity(v1, v2)
+    }
+
     fn preprocess(_vector: &[VectorElementType]) -> Option<Vec<VectorElementType>> {
         None
     }
@@ -144,6 +180,24 @@ impl Metric for CosineMetric {
         dot_similarity(v1, v2)
     }
 
+    fn similarity_aligned(v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
+                return unsafe { dot_similarity_avx_aligned(v1, v2) };
+            }
+        }
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("sse") {
+                return unsafe { dot_similarity_sse_aligned(v1, v2) };
+            }
+        }
+
+        Self::similarity(v1, v2)
+    }
+
     fn preprocess(vector: &[VectorElementType]) -> Option<Vec<VectorElementType>> {
         #[cfg(target_arch = "x86_64")]
         {
@@ -152,6 +152,86 @@ pub(crate) unsafe fn dot_similarity_avx(
     result
 }
 
+/// Euclid similarity of the padded vectors, aligned to 32 bytes, see [`crate::types::VectorLayout::Aligned`]
+///
+/// Length of the vectors is a multiple of 8, so there is no scalar tail.
+#[target_feature(enable = "avx")]
+#[target_feature(enable = "fma")]
+pub(crate) unsafe fn euclid_similarity_avx_aligned(
+    v1: &[VectorElementType],
+    v2: &[VectorElementType],
+) -> ScoreType {
+    debug_assert_eq!(v1.len() % 8, 0);
+    let n = v1.len();
+    let m = n - (n % 16);
+    let ptr1: *const f32 = v1.as_ptr();
+    let ptr2: *const f32 = v2.as_ptr();
+    let mut sum256_1: __m256 = _mm256_setzero_ps();
+    let mut sum256_2: __m256 = _mm256_setzero_ps();
+    let mut i: usize = 0;
+    while i < m {
+        let sub256_1: __m256 =
+            _mm256_sub_ps(_mm256_load_ps(ptr1.add(i)), _mm256_load_ps(ptr2.add(i)));
+        sum256_1 = _mm256_fmadd_ps(sub256_1, sub256_1, sum256_1);
+
+        let sub256_2: __m256 = _mm256_sub_ps(
+            _mm256_load_ps(ptr1.add(i + 8)),
+            _mm256_load_ps(ptr2.add(i + 8)),
+        );
+        sum256_2 = _mm256_fmadd_ps(sub256_2, sub256_2, sum256_2);
+
+        i += 16;
+    }
+    if i < n {
+        let sub256_1: __m256 =
+            _mm256_sub_ps(_mm256_load_ps(ptr1.add(i)), _mm256_load_ps(ptr2.add(i)));
+        sum256_1 = _mm256_fmadd_ps(sub256_1, sub256_1, sum256_1);
+    }
+
+    -(hsum256_ps_avx(sum256_1) + hsum256_ps_avx(sum256_2))
+}
+
+/// Dot similarity of the padded vectors, aligned to 32 bytes, see [`crate::types::VectorLayout::Aligned`]
+///
+/// Length of the vectors is a multiple of 8, so there is no scalar tail.
+#[target_feature(enable = "avx")]
+#[target_feature(enable = "fma")]
+pub(crate) unsafe fn dot_similarity_avx_aligned(
+    v1: &[VectorElementType],
+    v2: &[VectorElementType],
+) -> ScoreType {
+    debug_assert_eq!(v1.len() % 8, 0);
+    let n = v1.len();
+    let m = n - (n % 16);
+    let ptr1: *const f32 = v1.as_ptr();
+    let ptr2: *const f32 = v2.as_ptr();
+    let mut sum256_1: __m256 = _mm256_setzero_ps();
+    let mut sum256_2: __m256 = _mm256_setzero_ps();
+    let mut i: usize = 0;
+    while i < m {
+        sum256_1 = _mm256_fmadd_ps(
+            _mm256_load_ps(ptr1.add(i)),
+            _mm256_load_ps(ptr2.add(i)),
+            sum256_1,
+        );
+        sum256_2 = _mm256_fmadd_ps(
+            _mm256_load_ps(ptr1.add(i + 8)),
+            _mm256_load_ps(ptr2.add(i + 8)),
+            sum256_2,
+        );
+        i += 16;
+    }
+    if i < n {
+        sum256_1 = _mm256_fmadd_ps(
+            _mm256_load_ps(ptr1.add(i)),
+            _mm256_load_ps(ptr2.add(i)),
+            sum256_1,
+        );
+    }
+
+    hsum256_ps_avx(sum256_1) + hsum256_ps_avx(sum256_2)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -190,4 +270,29 @@ mod tests {
             println!("avx test skipped");
         }
     }
+
+    #[test]
+    fn test_spaces_avx_aligned() {
+        use super::*;
+        use crate::spaces::simple::*;
+        use crate::vector_storage::chunked_vectors::{aligned_vector, blocks_as_slice};
+
+        if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
+            // Padded dimensions with and without the odd block
+            for dim in [19, 30] {
+                let v1: Vec<f32> = (0..dim).map(|i| i as f32 / 4.0).collect();
+                let v2: Vec<f32> = (0..dim).map(|i| (dim - i) as f32 / 8.0).collect();
+                let (a1, a2) = (aligned_vector(&v1), aligned_vector(&v2));
+                let (p1, p2) = (blocks_as_slice(&a1), blocks_as_slice(&a2));
+
+                let euclid_simd = unsafe { euclid_similarity_avx_aligned(p1, p2) };
+                assert!((euclid_simd - euclid_similarity(&v1, &v2)).abs() < 1e-3);
+
+                let dot_simd = unsafe { dot_similarity_avx_aligned(p1, p2) };
+                assert!((dot_simd - dot_similarity(&v1, &v2)).abs() < 1e-3);
+            }
+        } else {
+            println!("avx test skipped");
+        }
+    }
 }
@@ -147,6 +147,64 @@ pub(crate) unsafe fn dot_similarity_sse(
     result
 }
 
+/// Euclid similarity of the padded vectors, aligned to 32 bytes, see [`crate::types::VectorLayout::Aligned`]
+///
+/// Length of the vectors is a multiple of 8, so there is no scalar tail.
+#[target_feature(enable = "sse")]
+pub(crate) unsafe fn euclid_similarity_sse_aligned(
+    v1: &[VectorElementType],
+    v2: &[VectorElementType],
+) -> ScoreType {
+    debug_assert_eq!(v1.len() % 8, 0);
+    let n = v1.len();
+    let ptr1: *const f32 = v1.as_ptr();
+    let ptr2: *const f32 = v2.as_ptr();
+    let mut sum128_1: __m128 = _mm_setzero_ps();
+    let mut sum128_2: __m128 = _mm_setzero_ps();
+    let mut i: usize = 0;
+    while i < n {
+        let sub128_1 = _mm_sub_ps(_mm_load_ps(ptr1.add(i)), _mm_load_ps(ptr2.add(i)));
+        sum128_1 = _mm_add_ps(_mm_mul_ps(sub128_1, sub128_1), sum128_1);
+
+        let sub128_2 = _mm_sub_ps(_mm_load_ps(ptr1.add(i + 4)), _mm_load_ps(ptr2.add(i + 4)));
+        sum128_2 = _mm_add_ps(_mm_mul_ps(sub128_2, sub128_2), sum128_2);
+
+        i += 8;
+    }
+
+    -(hsum128_ps_sse(sum128_1) + hsum128_ps_sse(sum128_2))
+}
+
+/// Dot similarity of the padded vectors, aligned to 32 bytes, see [`crate::types::VectorLayout::Aligned`]
+///
+/// Length of the vectors is a multiple of 8, so there is no scalar tail.
+#[target_feature(enable = "sse")]
+pub(crate) unsafe fn dot_similarity_sse_aligned(
+    v1: &[VectorElementType],
+    v2: &[VectorElementType],
+) -> ScoreType {
+    debug_assert_eq!(v1.len() % 8, 0);
+    let n = v1.len();
+    let ptr1: *const f32 = v1.as_ptr();
+    let ptr2: *const f32 = v2.as_ptr();
+    let mut sum128_1: __m128 = _mm_setzero_ps();
+    let mut sum128_2: __m128 = _mm_setzero_ps();
+    let mut i: usize = 0;
+    while i < n {
+        sum128_1 = _mm_add_ps(
+            _mm_mul_ps(_mm_load_ps(ptr1.add(i)), _mm_load_ps(ptr2.add(i))),
+            sum128_1,
+        );
+        sum128_2 = _mm_add_ps(
+            _mm_mul_ps(_mm_load_ps(ptr1.add(i + 4)), _mm_load_ps(ptr2.add(i + 4))),
+            sum128_2,
+        );
+        i += 8;
+    }
+
+    hsum128_ps_sse(sum128_1) + hsum128_ps_sse(sum128_2)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -183,4 +241,27 @@ mod tests {
             println!("sse test skipped");
         }
     }
+
+    #[test]
+    fn test_spaces_sse_aligned() {
+        use super::*;
+        use crate::spaces::simple::*;
+        use crate::vector_storage::chunked_vectors::{aligned_vector, blocks_as_slice};
+
+        if is_x86_feature_detected!("sse") {
+            let dim = 19;
+            let v1: Vec<f32> = (0..dim).map(|i| i as f32 / 4.0).collect();
+            let v2: Vec<f32> = (0..dim).map(|i| (dim - i) as f32 / 8.0).collect();
+            let (a1, a2) = (aligned_vector(&v1), aligned_vector(&v2));
+            let (p1, p2) = (blocks_as_slice(&a1), blocks_as_slice(&a2));
+
+            let euclid_simd = unsafe { euclid_similarity_sse_aligned(p1, p2) };
+            assert!((euclid_simd - euclid_similarity(&v1, &v2)).abs() < 1e-3);
+
+            let dot_simd = unsafe { dot_similarity_sse_aligned(p1, p2) };
+            assert!((dot_simd - dot_similarity(&v1, &v2)).abs() < 1e-3);
+        } else {
+            println!("sse test skipped");
+        }
+    }
 }
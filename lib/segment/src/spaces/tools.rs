@@ -15,9 +15,17 @@ pub struct FixedLengthPriorityQueue<T: Ord> {
 
 impl<T: Ord> FixedLengthPriorityQueue<T> {
     pub fn new(length: usize) -> Self {
+        Self::with_capacity(length, length)
+    }
+
+    /// Queue, which allocates for at most `capacity` elements upfront
+    ///
+    /// Queue never holds more than `length` elements, so a smaller capacity is only useful,
+    /// if less elements are expected to be pushed.
+    pub fn with_capacity(length: usize, capacity: usize) -> Self {
         assert!(length > 0);
         FixedLengthPriorityQueue::<T> {
-            heap: BinaryHeap::with_capacity(length + 1),
+            heap: BinaryHeap::with_capacity(capacity.min(length)),
             length,
         }
     }
@@ -63,6 +71,11 @@ impl<T: Ord> FixedLengthPriorityQueue<T> {
     pub fn is_empty(&self) -> bool {
         self.heap.is_empty()
     }
+
+    /// Checks if the queue holds the maximal number of elements
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.length
+    }
 }
 
 pub struct Iter<'a, T> {
@@ -111,6 +124,14 @@ impl<T: Ord> IntoIterator for FixedLengthPriorityQueue<T> {
     }
 }
 
+/// Capacity of the queue for the top of the elements, not allocating for more elements than expected
+fn capacity_hint<I: Iterator>(elements: &I, top: usize) -> usize {
+    match elements.size_hint() {
+        (_, Some(upper)) => upper.min(top),
+        (_, None) => top,
+    }
+}
+
 pub fn peek_top_smallest_iterable<I, E: Ord>(elements: I, top: usize) -> Vec<E>
 where
     I: IntoIterator<Item = E>,
@@ -121,7 +142,8 @@ where
 
     // If small values is better - PQ should pop-out big values first.
     // Hence is should be min-heap
-    let mut pq = FixedLengthPriorityQueue::new(top);
+    let elements = elements.into_iter();
+    let mut pq = FixedLengthPriorityQueue::with_capacity(top, capacity_hint(&elements, top));
     for element in elements {
         pq.push(Reverse(element));
     }
@@ -138,7 +160,8 @@ where
 
     // If big values is better - PQ should pop-out small values first.
     // Hence is should be min-heap
-    let mut pq = FixedLengthPriorityQueue::new(top);
+    let elements = elements.into_iter();
+    let mut pq = FixedLengthPriorityQueue::with_capacity(top, capacity_hint(&elements, top));
     for element in elements {
         pq.push(element);
     }
@@ -151,7 +174,12 @@ pub fn peek_top_scores<E: Ord + Clone>(scores: &[E], top: usize) -> Vec<E> {
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
     use super::*;
+    use crate::vector_storage::ScoredPointOffset;
 
     #[test]
     fn test_peek_top() {
@@ -166,4 +194,33 @@ mod tests {
         let res = peek_top_smallest_iterable(data.into_iter(), 3);
         assert_eq!(res, vec![5, 10, 20]);
     }
+
+    #[test]
+    fn test_peek_top_same_as_sort() {
+        let mut rng = StdRng::seed_from_u64(42);
+        // Few distinct scores, so most of the points are tied
+        let mut points: Vec<_> = (0..10_000)
+            .map(|idx| ScoredPointOffset {
+                idx,
+                score: rng.gen_range(0..100) as f32,
+            })
+            .collect();
+
+        for top in [1, 10, 100, 20_000] {
+            // Sort all points by score, ties are won by smaller ids
+            let mut expected = points.clone();
+            expected.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.idx.cmp(&b.idx)));
+            expected.truncate(top);
+
+            for _ in 0..3 {
+                points.shuffle(&mut rng);
+                let res = peek_top_largest_iterable(points.iter().copied(), top);
+                assert_eq!(res.len(), expected.len());
+                for (found, expected) in res.iter().zip(&expected) {
+                    assert_eq!(found.idx, expected.idx);
+                    assert_eq!(found.score, expected.score);
+                }
+            }
+        }
+    }
 }
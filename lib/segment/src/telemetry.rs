@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::common::anonymize::Anonymize;
 use crate::common::operation_time_statistics::OperationDurationStatistics;
-use crate::types::{PayloadIndexInfo, SegmentConfig, SegmentInfo, VectorDataConfig};
+use crate::types::{
+    PayloadIndexInfo, SegmentConfig, SegmentInfo, VectorDataConfig, VectorIndexInfo,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct VectorIndexesTelemetry {
@@ -16,6 +18,17 @@ pub struct SegmentTelemetry {
     pub config: SegmentConfig,
     pub vector_index_searches: Vec<VectorIndexSearchesTelemetry>,
     pub payload_field_indices: Vec<PayloadIndexTelemetry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub filter_cache: Option<FilterCacheTelemetry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct FilterCacheTelemetry {
+    pub capacity: usize,
+    pub size: usize,
+    pub hits: usize,
+    pub misses: usize,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -41,12 +54,19 @@ pub struct VectorIndexSearchesTelemetry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub index_name: Option<String>,
 
+    /// Filtered searches, matching less points than this, are done without the index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexing_threshold: Option<usize>,
+
     #[serde(skip_serializing_if = "OperationDurationStatistics::is_empty")]
     pub unfiltered_plain: OperationDurationStatistics,
 
     #[serde(skip_serializing_if = "OperationDurationStatistics::is_empty")]
     pub unfiltered_hnsw: OperationDurationStatistics,
 
+    #[serde(skip_serializing_if = "OperationDurationStatistics::is_empty")]
+    pub unfiltered_ivf: OperationDurationStatistics,
+
     #[serde(skip_serializing_if = "OperationDurationStatistics::is_empty")]
     pub filtered_plain: OperationDurationStatistics,
 
@@ -70,6 +90,7 @@ impl Anonymize for SegmentTelemetry {
             config: self.config.anonymize(),
             vector_index_searches: self.vector_index_searches.anonymize(),
             payload_field_indices: self.payload_field_indices.anonymize(),
+            filter_cache: self.filter_cache.clone(),
         }
     }
 }
@@ -84,11 +105,16 @@ impl Anonymize for SegmentInfo {
             ram_usage_bytes: self.ram_usage_bytes.anonymize(),
             disk_usage_bytes: self.disk_usage_bytes.anonymize(),
             is_appendable: self.is_appendable,
+            index_outdated: self.index_outdated,
             index_schema: self
                 .index_schema
                 .iter()
                 .map(|(k, v)| (k.anonymize(), v.anonymize()))
                 .collect(),
+            vector_indexes: self.vector_indexes.anonymize(),
+            flush_policy: self.flush_policy,
+            last_flush_time: self.last_flush_time.anonymize(),
+            last_flush_error: self.last_flush_error.clone(),
         }
     }
 }
@@ -111,6 +137,15 @@ impl Anonymize for SegmentConfig {
             storage_type: self.storage_type,
             payload_storage_type: self.payload_storage_type,
             quantization_config: self.quantization_config.clone(),
+            filter_cache_size: self.filter_cache_size,
+            id_tracker_type: self.id_tracker_type,
+            indexing_threshold: self.indexing_threshold,
+            max_search_threads: self.max_search_threads,
+            flush_policy: self.flush_policy,
+            checksum_verification: self.checksum_verification,
+            background_flush_interval_ms: self.background_flush_interval_ms,
+            vector_layout: self.vector_layout,
+            unknown_fields: Default::default(),
         }
     }
 }
@@ -126,12 +161,25 @@ impl Anonymize for VectorDataConfig {
     }
 }
 
+impl Anonymize for VectorIndexInfo {
+    fn anonymize(&self) -> Self {
+        VectorIndexInfo {
+            index_type: self.index_type,
+            is_built: self.is_built,
+            hnsw_config: None,
+            build_memory_estimate: self.build_memory_estimate,
+        }
+    }
+}
+
 impl Anonymize for VectorIndexSearchesTelemetry {
     fn anonymize(&self) -> Self {
         VectorIndexSearchesTelemetry {
             index_name: None,
+            indexing_threshold: self.indexing_threshold,
             unfiltered_plain: self.unfiltered_plain.anonymize(),
             unfiltered_hnsw: self.unfiltered_hnsw.anonymize(),
+            unfiltered_ivf: self.unfiltered_ivf.anonymize(),
             filtered_plain: self.filtered_plain.anonymize(),
             filtered_small_cardinality: self.filtered_small_cardinality.anonymize(),
             filtered_large_cardinality: self.filtered_large_cardinality.anonymize(),
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Formatter;
@@ -5,7 +6,10 @@ use std::mem::size_of;
 use std::ops::Deref;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use geo::prelude::HaversineDistance;
 use geo::Point;
 use itertools::Itertools;
@@ -14,10 +18,12 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use uuid::Uuid;
-use validator::{Validate, ValidationErrors};
+use validator::{Validate, ValidationError, ValidationErrors};
 
+use crate::common::payload_selection::PayloadSelection;
 use crate::common::utils;
 use crate::common::utils::MultiValue;
+use crate::data_types::keyword_index::KeywordIndexParams;
 use crate::data_types::text_index::TextIndexParams;
 use crate::data_types::vectors::{VectorElementType, VectorStruct};
 use crate::spaces::metric::Metric;
@@ -25,6 +31,12 @@ use crate::spaces::simple::{CosineMetric, DotProductMetric, EuclidMetric};
 
 /// Type of point index inside a segment
 pub type PointOffsetType = u32;
+
+/// Max number of the points in a segment, including the deleted ones
+///
+/// Offsets are 32-bit, and the number of the points is also used as the end of the offset ranges,
+/// so the last offset is never assigned.
+pub const MAX_SEGMENT_POINTS: usize = PointOffsetType::MAX as usize;
 pub type PayloadKeyType = String;
 pub type PayloadKeyTypeRef<'a> = &'a str;
 pub type SeqNumberType = u64;
@@ -39,12 +51,40 @@ pub type IntPayloadType = i64;
 
 pub const VECTOR_ELEMENT_SIZE: usize = size_of::<VectorElementType>();
 
+/// Arbitrary string point ID
+///
+/// String is shared, so clones of the ID are cheap, and it is released with the last clone.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, JsonSchema)]
+#[serde(transparent)]
+pub struct StrPointId(Arc<str>);
+
+impl StrPointId {
+    pub fn new(id: &str) -> Self {
+        StrPointId(Arc::from(id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for StrPointId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 /// Type, used for specifying point ID in user interface
-#[derive(Debug, Serialize, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, JsonSchema)]
+///
+/// Strings in UUID format are always parsed as UUIDs, any other non-empty string is a string ID.
+/// IDs of all types can be mixed in a segment, numeric IDs are ordered first, then UUIDs,
+/// then strings in lexicographic order.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, JsonSchema)]
 #[serde(untagged)]
 pub enum ExtendedPointId {
     NumId(u64),
     Uuid(Uuid),
+    Str(StrPointId),
 }
 
 impl std::fmt::Display for ExtendedPointId {
@@ -52,6 +92,7 @@ impl std::fmt::Display for ExtendedPointId {
         match self {
             ExtendedPointId::NumId(idx) => write!(f, "{idx}"),
             ExtendedPointId::Uuid(uuid) => write!(f, "{uuid}"),
+            ExtendedPointId::Str(id) => write!(f, "{id}"),
         }
     }
 }
@@ -62,18 +103,20 @@ impl From<u64> for ExtendedPointId {
     }
 }
 
+/// Same rule as for the JSON strings: UUID strings are UUIDs, any other non-empty string is a string ID
+///
+/// Numeric strings are not numeric IDs, so the sources, where numbers are written as text, parse `u64` first.
 impl FromStr for ExtendedPointId {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let try_num: Result<u64, _> = s.parse();
-        if let Ok(num) = try_num {
-            return Ok(Self::NumId(num));
-        }
         let try_uuid = Uuid::from_str(s);
         if let Ok(uuid) = try_uuid {
             return Ok(Self::Uuid(uuid));
         }
+        if !s.is_empty() {
+            return Ok(Self::Str(StrPointId::new(s)));
+        }
         Err(())
     }
 }
@@ -96,9 +139,15 @@ impl<'de> serde::Deserialize<'de> for ExtendedPointId {
             return Ok(ExtendedPointId::Uuid(uuid));
         }
 
+        if let serde_value::Value::String(id) = &value {
+            if !id.is_empty() {
+                return Ok(ExtendedPointId::Str(StrPointId::new(id)));
+            }
+        }
+
         Err(serde::de::Error::custom(format!(
             "value {} is not a valid point ID, \
-             valid values are either an unsigned integer or a UUID",
+             valid values are either an unsigned integer, a UUID or a non-empty string",
             crate::utils::fmt::SerdeValue(&value),
         )))
     }
@@ -133,6 +182,16 @@ impl Distance {
         }
     }
 
+    /// Converts internal score, where larger is always better, into the user-facing one
+    ///
+    /// - `Cosine`: cosine similarity `dot(a, b) / (|a| * |b|)`. Vectors are normalized on
+    ///   insertion and the query is normalized before scoring, so the internal dot product is
+    ///   already the similarity, returned as is.
+    /// - `Euclid`: euclidean distance `sqrt(sum((a_i - b_i)^2))`. Internal score is the negated
+    ///   squared distance, so the result is `sqrt(|score|)`.
+    /// - `Dot`: dot product `dot(a, b)`, returned as is.
+    ///
+    /// Postprocessed scores are ordered by [`Distance::distance_order`].
     pub fn postprocess_score(&self, score: ScoreType) -> ScoreType {
         match self {
             Distance::Cosine => CosineMetric::postprocess(score),
@@ -141,6 +200,16 @@ impl Distance {
         }
     }
 
+    /// Similarity of preprocessed vectors, larger is always better
+    pub fn similarity(&self, v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType {
+        match self {
+            Distance::Cosine => CosineMetric::similarity(v1, v2),
+            Distance::Euclid => EuclidMetric::similarity(v1, v2),
+            Distance::Dot => DotProductMetric::similarity(v1, v2),
+        }
+    }
+
+    /// Order of the postprocessed scores: similarities are better when larger, distances when smaller
     pub fn distance_order(&self) -> Order {
         match self {
             Distance::Cosine | Distance::Dot => Order::LargeBetter,
@@ -148,7 +217,7 @@ impl Distance {
         }
     }
 
-    /// Checks if score satisfies threshold condition
+    /// Checks if postprocessed score satisfies threshold condition
     pub fn check_threshold(&self, score: ScoreType, threshold: ScoreType) -> bool {
         match self.distance_order() {
             Order::LargeBetter => score > threshold,
@@ -157,11 +226,80 @@ impl Distance {
     }
 }
 
+/// Order of the postprocessed scores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Order {
     LargeBetter,
     SmallBetter,
 }
 
+impl Order {
+    /// Key of the score, which is larger for the better score in either order
+    pub fn score_key(&self, score: ScoreType) -> ScoreType {
+        match self {
+            Order::LargeBetter => score,
+            Order::SmallBetter => -score,
+        }
+    }
+}
+
+/// Direction of the order of the payload values
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// From the smallest value to the largest
+    #[default]
+    Asc,
+    /// From the largest value to the smallest
+    Desc,
+}
+
+impl Direction {
+    /// Compare the values, the first one in the direction is less
+    pub fn compare(&self, a: f64, b: f64) -> Ordering {
+        match self {
+            Direction::Asc => a.total_cmp(&b),
+            Direction::Desc => b.total_cmp(&a),
+        }
+    }
+}
+
+/// Order of the points by the values of a numeric payload field
+///
+/// Points without the field are skipped. Point with several values is ordered by its first value in the direction.
+/// Points with equal values are ordered by id, so the pages are stable while the points are not changed.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderBy {
+    /// Payload key to order by, the field must have the integer or float index
+    pub key: PayloadKeyType,
+    /// Direction of the order. Default: asc
+    #[serde(default)]
+    pub direction: Direction,
+    /// Start from this value, inclusive. If not provided - from the first value in the direction
+    pub start_from: Option<f64>,
+}
+
+impl OrderBy {
+    /// Compare the points by their values in the order, points with equal values are ordered by id
+    pub fn compare(
+        &self,
+        (value_a, id_a): (f64, &PointIdType),
+        (value_b, id_b): (f64, &PointIdType),
+    ) -> Ordering {
+        self.direction
+            .compare(value_a, value_b)
+            .then(id_a.cmp(id_b))
+    }
+
+    /// Whether the value is not before `start_from` in the direction
+    pub fn is_started(&self, value: f64) -> bool {
+        self.start_from.map_or(true, |start_from| {
+            self.direction.compare(value, start_from) != Ordering::Less
+        })
+    }
+}
+
 /// Search result
 #[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
 pub struct ScoredPoint {
@@ -172,7 +310,7 @@ pub struct ScoredPoint {
     /// Points vector distance to the query vector
     pub score: ScoreType,
     /// Payload - values assigned to the point
-    pub payload: Option<Payload>,
+    pub payload: Option<Arc<Payload>>,
     /// Vector of the point
     pub vector: Option<VectorStruct>,
 }
@@ -193,10 +331,19 @@ impl PartialOrd for ScoredPoint {
 
 impl PartialEq for ScoredPoint {
     fn eq(&self, other: &Self) -> bool {
-        (self.id, &self.score) == (other.id, &other.score)
+        (&self.id, &self.score) == (&other.id, &other.score)
     }
 }
 
+/// Hits of the search, which share the value of the group-by payload field
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+pub struct PointGroup {
+    /// Value of the group-by field
+    pub id: ValueVariants,
+    /// Best hits of the group, best first
+    pub hits: Vec<ScoredPoint>,
+}
+
 /// Type of segment
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -235,6 +382,11 @@ impl PayloadIndexInfo {
                     params: Some(schema_params),
                     points: points_count,
                 },
+                PayloadSchemaParams::Keyword(_) => PayloadIndexInfo {
+                    data_type: PayloadSchemaType::Keyword,
+                    params: Some(schema_params),
+                    points: points_count,
+                },
             },
         }
     }
@@ -251,7 +403,59 @@ pub struct SegmentInfo {
     pub ram_usage_bytes: usize,
     pub disk_usage_bytes: usize,
     pub is_appendable: bool,
+    /// Vector index waits for rebuild with pending config, and segment has reached its
+    /// indexing threshold
+    #[serde(default)]
+    pub index_outdated: bool,
     pub index_schema: HashMap<PayloadKeyType, PayloadIndexInfo>,
+    /// State of the vector index of each named vector
+    #[serde(default)]
+    pub vector_indexes: HashMap<String, VectorIndexInfo>,
+    /// When applied operations are flushed to the disk
+    #[serde(default)]
+    pub flush_policy: FlushPolicy,
+    /// Time of the last successful flush
+    #[serde(default)]
+    pub last_flush_time: Option<DateTime<Utc>>,
+    /// Error of the last flush, cleared by the next successful one
+    #[serde(default)]
+    pub last_flush_error: Option<String>,
+}
+
+/// Type of the vector index
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorIndexType {
+    Plain,
+    Hnsw,
+    Ivf,
+}
+
+/// State of the vector index of a single named vector
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct VectorIndexInfo {
+    pub index_type: VectorIndexType,
+    /// Index is built. Indexes, which are not built yet, score all points exactly
+    pub is_built: bool,
+    /// HNSW config of the vector, if the index is HNSW
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hnsw_config: Option<HnswConfig>,
+    /// Estimated peak memory of the next build of the index over the current points, if the index is HNSW
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_memory_estimate: Option<BuildMemoryEstimate>,
+}
+
+/// Estimated peak memory of the HNSW index build, in addition to the memory of the segment itself
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct BuildMemoryEstimate {
+    /// Memory of the graphs, the points are linked into, and of the indexing threads
+    pub linking_bytes: usize,
+    /// Memory of the built links, once they are converted. Links of the on-disk index are not loaded
+    pub converted_bytes: usize,
+    /// Peak of the build, the budget is compared with
+    pub peak_bytes: usize,
 }
 
 /// Additional parameters of the search
@@ -278,7 +482,7 @@ pub fn default_quantization_rescore_value() -> bool {
 }
 
 /// Additional parameters of the search
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub struct SearchParams {
     /// Params relevant to HNSW index
@@ -292,6 +496,95 @@ pub struct SearchParams {
     /// Quantization params
     #[serde(default)]
     pub quantization: Option<QuantizationSearchParams>,
+
+    /// Strict filtering. If set to true, search fails if the filter refers to a payload key without an index.
+    #[serde(default)]
+    pub strict_filter: bool,
+
+    /// Params relevant to IVF index
+    /// Number of the closest clusters to scan. Larger the value - more accurate the result, more time required for search.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ivf_nprobe: Option<usize>,
+
+    /// Boost of the scores by a numeric payload value, e.g. by freshness or popularity of the points
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_boost: Option<ScoreBoost>,
+
+    /// Timeout of the search in each segment, in milliseconds. If reached, search fails, unless `partial_on_timeout` is set
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// If set to true, the search, which reached the timeout, returns the results found so far instead of the failure
+    #[serde(default)]
+    pub partial_on_timeout: bool,
+
+    /// If set to true, telemetry of the search in each segment is collected: numbers of the scored points,
+    /// index hops and filter checks, and times of the search phases
+    #[serde(default)]
+    pub with_telemetry: bool,
+}
+
+/// Boost of the score by a numeric payload value: `score = score + factor * function(value)`
+///
+/// Points without a numeric value under the `key` are not boosted.
+/// Smaller score is better for Euclid distance, so negative `factor` promotes the points with large values.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ScoreBoost {
+    /// Payload key with the numeric value
+    pub key: PayloadKeyType,
+    /// Weight of the boost
+    pub factor: ScoreType,
+    /// Function, applied to the payload value. Default: linear
+    #[serde(default)]
+    pub function: BoostFunction,
+}
+
+impl Eq for ScoreBoost {}
+
+impl ScoreBoost {
+    /// Boost of the point with the `payload`, zero if there is no numeric value under the key
+    pub fn boost(&self, payload: &Payload) -> ScoreType {
+        let value = payload
+            .get_value(&self.key)
+            .find_map(|value| value.as_f64());
+        let boost = match value {
+            Some(value) => self.factor * self.function.apply(value) as ScoreType,
+            None => 0.0,
+        };
+        if boost.is_finite() {
+            boost
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Function of the [`ScoreBoost`], applied to the payload value
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BoostFunction {
+    /// Value as is
+    #[default]
+    Linear,
+    /// `ln(1 + value)`, dampens large values. Negative values are not boosted
+    Log1p,
+    /// Square root of the value, dampens large values. Negative values are not boosted
+    Sqrt,
+}
+
+impl BoostFunction {
+    fn apply(&self, value: f64) -> f64 {
+        match self {
+            BoostFunction::Linear => value,
+            BoostFunction::Log1p if value >= 0.0 => value.ln_1p(),
+            BoostFunction::Sqrt if value >= 0.0 => value.sqrt(),
+            BoostFunction::Log1p | BoostFunction::Sqrt => 0.0,
+        }
+    }
 }
 
 /// Vector index configuration of the segment
@@ -305,6 +598,9 @@ pub enum Indexes {
     /// Use filterable HNSW index for approximate search. Is very fast even on a very huge collections,
     /// but require additional space to store index and additional time to build it.
     Hnsw(HnswConfig),
+    /// Use inverted file index for approximate search. Vectors are split into clusters around trained centroids,
+    /// only clusters closest to the query are scanned. Faster to build than HNSW, but requires more scanning for the same precision.
+    Ivf(IvfConfig),
 }
 
 /// Config of HNSW index
@@ -334,12 +630,109 @@ pub struct HnswConfig {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")] // Better backward compatibility
     pub payload_m: Option<usize>,
+    /// Strategy of selecting neighbours of a new point during the index building. Default: heuristic
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] // Better backward compatibility
+    pub neighbor_selection: Option<NeighborSelection>,
+    /// Seed for the random generator, used during the index building. If not set, random seed will be used.
+    /// Builds with the same seed over the same points are reproducible, if `max_indexing_threads` = 1
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] // Better backward compatibility
+    pub seed: Option<u64>,
+    /// Link points, inserted into an indexed appendable segment, into the graph right away.
+    /// If false, new points are only indexed by the next rebuild of the index. Default: false
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] // Better backward compatibility
+    pub incremental: Option<bool>,
+    /// Policy of choosing `ef` of the search by the requested `top`, if `hnsw_ef` is not set in the search params.
+    /// If not set, `ef_construct` is used as `ef`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] // Better backward compatibility
+    #[validate]
+    pub ef_policy: Option<EfPolicy>,
+    /// Max memory of a single build of the index in megabytes. Builds, estimated to use more, are refused before they start.
+    /// If not set or 0 - the memory of the builds is not limited.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")] // Better backward compatibility
+    pub max_build_memory_mb: Option<usize>,
+}
+
+/// Policy of choosing `ef` of the HNSW search by the requested `top`: `ef = max(top * factor, min_ef)`
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct EfPolicy {
+    /// Multiplier of `top`. Default: 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1.0))]
+    pub factor: Option<f32>,
+    /// Minimal `ef`, used for small `top`. Default: 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_ef: Option<usize>,
+    /// Recall, the factor is calibrated for. If set, `factor` is replaced with the one, calibrated
+    /// after each build of the index by comparing searches of sampled points with the exact ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub target_recall: Option<f32>,
+}
+
+impl Eq for EfPolicy {}
+
+impl std::hash::Hash for EfPolicy {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.factor.map(f32::to_bits).hash(state);
+        self.min_ef.hash(state);
+        self.target_recall.map(f32::to_bits).hash(state);
+    }
 }
 
 fn default_max_indexing_threads() -> usize {
     0
 }
 
+/// Config of IVF index
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct IvfConfig {
+    /// Number of clusters (inverted lists) to split vectors into. Larger the value - faster the search, more time required to build index.
+    #[validate(range(min = 1))]
+    pub n_lists: usize,
+    /// Default number of the closest clusters to scan during the search.
+    #[validate(range(min = 1))]
+    pub nprobe: usize,
+    /// Number of parallel threads used for background index building. If 0 - auto selection.
+    #[serde(default = "default_max_indexing_threads")]
+    pub max_indexing_threads: usize,
+    /// Seed for the random generator, used for the k-means training. If not set, random seed will be used.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for IvfConfig {
+    fn default() -> Self {
+        IvfConfig {
+            n_lists: DEFAULT_IVF_N_LISTS,
+            nprobe: DEFAULT_IVF_NPROBE,
+            max_indexing_threads: 0,
+            seed: None,
+        }
+    }
+}
+
+/// Strategy of selecting neighbours of a new point during the HNSW graph building
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighborSelection {
+    /// Link the closest candidates
+    Simple,
+    /// Skip candidates, which are closer to an already selected neighbour than to the new point.
+    /// Gives better connected graphs on clustered data
+    #[default]
+    Heuristic,
+    /// Same as `heuristic`, but fill the remaining links with the skipped candidates
+    HeuristicKeepPruned,
+}
+
 #[derive(Default, Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ScalarType {
@@ -417,6 +810,11 @@ impl Default for HnswConfig {
             max_indexing_threads: 0,
             on_disk: Some(false),
             payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
         }
     }
 }
@@ -469,6 +867,66 @@ pub enum PayloadStorageType {
     OnDisk,
 }
 
+/// Type of the id tracker, which maps external point ids to internal offsets
+#[derive(Default, Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdTrackerType {
+    // Keep ordered maps of external ids, fastest lookups
+    #[default]
+    Simple,
+    // Keep offsets sorted by external ids, requires less RAM for large segments, slower lookups
+    Compact,
+}
+
+/// When the segment flushes applied operations to the disk
+///
+/// With a write-ahead log of the segment, the policy is applied to the log instead of the storages.
+#[derive(Default, Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlushPolicy {
+    /// Flush after every applied operation
+    Always,
+    /// Flush after each given number of applied operations
+    EveryNOps(u64),
+    /// Flush at the first operation, applied after the interval since the last flush
+    Interval(Duration),
+    /// Flush only on explicit request
+    #[default]
+    Manual,
+}
+
+/// How the checksums of the segment files are verified, when the segment is loaded
+#[derive(Default, Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumVerification {
+    /// Refuse to load the segment with a corrupted file
+    #[default]
+    Always,
+    /// Load the segment without verification
+    Never,
+    /// Move the segment with a corrupted file aside, and skip it
+    Quarantine,
+}
+
+/// How the vectors are laid out in the vector storage
+#[derive(Default, Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorLayout {
+    /// Vectors are stored one after another, as they are
+    Packed,
+    /// Each vector starts at a 32-byte boundary and is padded with zeros to a multiple of 32 bytes,
+    /// so it is scored with the aligned SIMD loads and without the scalar tail
+    #[default]
+    Aligned,
+}
+
+impl VectorLayout {
+    /// Layout of the segments, which were created before the layout was configurable
+    pub fn packed() -> Self {
+        VectorLayout::Packed
+    }
+}
+
 #[derive(Default, Debug, Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct SegmentConfig {
@@ -483,6 +941,38 @@ pub struct SegmentConfig {
     /// Quantization parameters. If none - quantization is disabled.
     #[serde(default)]
     pub quantization_config: Option<QuantizationConfig>,
+    /// Number of filters, which matched points are cached for. If 0 - caching is disabled.
+    #[serde(default)]
+    pub filter_cache_size: usize,
+    /// Type of the id tracker
+    #[serde(default)]
+    pub id_tracker_type: IdTrackerType,
+    /// Minimal size (in KiloBytes) of vectors, from which pending vector index should be built.
+    /// If none - pending index should be built regardless of the segment size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexing_threshold: Option<usize>,
+    /// Maximal number of threads, used by the plain index to score a single query, and by any index to search the queries of a batch.
+    /// If none - queries are searched in the calling thread. If 0 - auto selection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_search_threads: Option<usize>,
+    /// When applied operations are flushed to the disk
+    #[serde(default)]
+    pub flush_policy: FlushPolicy,
+    /// How the checksums of the segment files are verified on load
+    #[serde(default)]
+    pub checksum_verification: ChecksumVerification,
+    /// Interval (in milliseconds) of the background flush of the segment.
+    /// If none - background flush is disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_flush_interval_ms: Option<u64>,
+    /// Layout of the vectors in the vector storages, created for the segment.
+    /// Memmapped vector files keep the layout, they were written with.
+    #[serde(default = "VectorLayout::packed")]
+    pub vector_layout: VectorLayout,
+    /// Fields of the newer versions, kept so the config is saved back without losing them
+    #[serde(flatten)]
+    #[schemars(skip)]
+    pub unknown_fields: Map<String, Value>,
 }
 
 impl SegmentConfig {
@@ -519,12 +1009,62 @@ pub struct VectorDataConfig {
 /// Default value based on <https://github.com/google-research/google-research/blob/master/scann/docs/algorithms.md>
 pub const DEFAULT_FULL_SCAN_THRESHOLD: usize = 20_000;
 
+pub const DEFAULT_IVF_N_LISTS: usize = 256;
+
+pub const DEFAULT_IVF_NPROBE: usize = 8;
+
 /// Persistable state of segment configuration
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct SegmentState {
     pub version: Option<SeqNumberType>,
     pub config: SegmentConfig,
+    /// Index config, which is applied on the next rebuild of the vector index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_index: Option<Indexes>,
+    /// Parts of the data, present in the segment, restored from a partial snapshot
+    #[serde(default, skip_serializing_if = "SnapshotContents::is_full")]
+    pub contents: SnapshotContents,
+    /// Fields of the newer versions, kept so the state is saved back without losing them
+    #[serde(flatten)]
+    pub unknown_fields: Map<String, Value>,
+}
+
+/// Parts of the segment data, included in the snapshot
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct SnapshotContents {
+    /// Vector storages
+    pub vectors: bool,
+    /// Payload storage
+    pub payload: bool,
+    /// Vector and payload indexes. If not included, indexes are rebuilt on restore.
+    pub index: bool,
+}
+
+impl Default for SnapshotContents {
+    fn default() -> Self {
+        SnapshotContents {
+            vectors: true,
+            payload: true,
+            index: true,
+        }
+    }
+}
+
+impl SnapshotContents {
+    pub fn is_full(&self) -> bool {
+        self.vectors && self.payload && self.index
+    }
+
+    /// Parts, included in both
+    pub fn intersect(&self, other: &SnapshotContents) -> SnapshotContents {
+        SnapshotContents {
+            vectors: self.vectors && other.vectors,
+            payload: self.payload && other.payload,
+            index: self.index && other.index,
+        }
+    }
 }
 
 /// Geo point payload schema
@@ -589,6 +1129,11 @@ impl TryFrom<GeoPointShadow> for GeoPoint {
 pub struct Payload(pub Map<String, Value>);
 
 impl Payload {
+    /// Payload out of the shared one, it is copied only if it is still referenced by the others
+    pub fn from_shared(payload: Arc<Payload>) -> Payload {
+        Arc::try_unwrap(payload).unwrap_or_else(|payload| payload.as_ref().clone())
+    }
+
     pub fn merge(&mut self, value: &Payload) {
         for (key, value) in &value.0 {
             match value {
@@ -746,6 +1291,7 @@ pub enum PayloadSchemaType {
 #[serde(untagged)]
 pub enum PayloadSchemaParams {
     Text(TextIndexParams),
+    Keyword(KeywordIndexParams),
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Hash, Eq)]
@@ -770,6 +1316,9 @@ impl TryFrom<PayloadIndexInfo> for PayloadFieldSchema {
             (PayloadSchemaType::Text, Some(PayloadSchemaParams::Text(params))) => Ok(
                 PayloadFieldSchema::FieldParams(PayloadSchemaParams::Text(params)),
             ),
+            (PayloadSchemaType::Keyword, Some(PayloadSchemaParams::Keyword(params))) => Ok(
+                PayloadFieldSchema::FieldParams(PayloadSchemaParams::Keyword(params)),
+            ),
             (data_type, Some(_)) => Err(format!(
                 "Payload field with type {data_type:?} has unexpected params"
             )),
@@ -824,7 +1373,7 @@ where
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum ValueVariants {
     Keyword(String),
@@ -841,6 +1390,7 @@ pub enum AnyVariants {
 
 /// Exact match of the given value
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct MatchValue {
     pub value: ValueVariants,
@@ -848,6 +1398,7 @@ pub struct MatchValue {
 
 /// Full-text match of the strings.
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct MatchText {
     pub text: String,
@@ -859,13 +1410,36 @@ impl From<String> for MatchText {
     }
 }
 
+/// Case-insensitive exact match of the keyword
+///
+/// Values are compared after unicode-aware lowercasing. Only keyword index, created with
+/// `insensitive` option, can serve this match, otherwise the payload of the points is checked.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub struct MatchInsensitive {
+    pub insensitive: String,
+}
+
 /// Exact match on any of the given values
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct MatchAny {
     pub any: AnyVariants,
 }
 
+/// Exact match on none of the given values
+///
+/// Point matches if none of its values are equal to any of the given values,
+/// so points without values of the field match as well, consistently with `must_not`.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub struct MatchExcept {
+    pub except: AnyVariants,
+}
+
 /// Match filter request
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -874,6 +1448,8 @@ pub enum MatchInterface {
     Value(MatchValue),
     Text(MatchText),
     Any(MatchAny),
+    Except(MatchExcept),
+    Insensitive(MatchInsensitive),
 }
 
 /// Match filter request
@@ -884,6 +1460,8 @@ pub enum Match {
     Value(MatchValue),
     Text(MatchText),
     Any(MatchAny),
+    Except(MatchExcept),
+    Insensitive(MatchInsensitive),
 }
 
 impl From<MatchInterface> for Match {
@@ -892,6 +1470,12 @@ impl From<MatchInterface> for Match {
             MatchInterface::Value(value) => Self::Value(MatchValue { value: value.value }),
             MatchInterface::Text(text) => Self::Text(MatchText { text: text.text }),
             MatchInterface::Any(any) => Self::Any(MatchAny { any: any.any }),
+            MatchInterface::Except(except) => Self::Except(MatchExcept {
+                except: except.except,
+            }),
+            MatchInterface::Insensitive(insensitive) => Self::Insensitive(MatchInsensitive {
+                insensitive: insensitive.insensitive,
+            }),
         }
     }
 }
@@ -938,6 +1522,7 @@ impl From<Vec<IntPayloadType>> for Match {
 
 /// Range filter request
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct Range {
     /// point.key < range.lt
@@ -959,8 +1544,40 @@ impl Range {
     }
 }
 
+fn validation_error(code: &'static str, message: &'static str) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message.replace(Cow::from(message));
+    error
+}
+
+impl Validate for Range {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if self.lt.is_none() && self.gt.is_none() && self.gte.is_none() && self.lte.is_none() {
+            errors.add(
+                "range",
+                validation_error("bounds", "at least one range bound must be specified"),
+            );
+        }
+        if self.gt.is_some() && self.gte.is_some() {
+            errors.add(
+                "gt",
+                validation_error("bounds", "`gt` and `gte` can't be used together"),
+            );
+        }
+        if self.lt.is_some() && self.lte.is_some() {
+            errors.add(
+                "lt",
+                validation_error("bounds", "`lt` and `lte` can't be used together"),
+            );
+        }
+        errors.errors().is_empty().then_some(()).ok_or(errors)
+    }
+}
+
 /// Values count filter request
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct ValuesCount {
     /// point.key.length() < values_count.lt
@@ -992,6 +1609,7 @@ impl ValuesCount {
 ///
 /// Matches coordinates inside the rectangle, described by coordinates of lop-left and bottom-right edges
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct GeoBoundingBox {
     /// Coordinates of the top left point of the area rectangle
@@ -1013,6 +1631,7 @@ impl GeoBoundingBox {
 ///
 /// Matches coordinates inside the circle of `radius` and center with coordinates `center`
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct GeoRadius {
     /// Coordinates of the top left point of the area rectangle
@@ -1028,43 +1647,227 @@ impl GeoRadius {
     }
 }
 
-/// All possible payload filtering conditions
+/// Ordered sequence of GeoPoints representing the line
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
-pub struct FieldCondition {
-    /// Payload key
-    pub key: PayloadKeyType,
-    /// Check if point has field with a given value
-    pub r#match: Option<Match>,
-    /// Check if points value lies in a given range
-    pub range: Option<Range>,
-    /// Check if points geo location lies in a given area
-    pub geo_bounding_box: Option<GeoBoundingBox>,
-    /// Check if geo point is within a given radius
-    pub geo_radius: Option<GeoRadius>,
-    /// Check number of values of the field
-    pub values_count: Option<ValuesCount>,
+pub struct GeoLineString {
+    pub points: Vec<GeoPoint>,
 }
 
-impl FieldCondition {
-    pub fn new_match(key: PayloadKeyType, r#match: Match) -> Self {
-        Self {
-            key,
-            r#match: Some(r#match),
-            range: None,
-            geo_bounding_box: None,
-            geo_radius: None,
-            values_count: None,
-        }
-    }
+/// Minimal number of points in a closed ring of the polygon
+pub const GEO_POLYGON_MIN_POINTS: usize = 4;
 
-    pub fn new_range(key: PayloadKeyType, range: Range) -> Self {
-        Self {
-            key,
+/// Points closer to the polygon edge are considered to be on the edge
+const GEO_POLYGON_EDGE_EPS: f64 = 1e-9;
+
+/// Position of a point relative to a ring of the polygon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RingPosition {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+/// Geo filter request
+///
+/// Matches coordinates inside the polygon, defined by `exterior` and `interiors`.
+/// Points on the edges of the polygon are considered to be inside.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub struct GeoPolygon {
+    /// The exterior line bounds the surface,
+    /// must consist of a minimum of 4 points, and the first and last points must be the same.
+    pub exterior: GeoLineString,
+    /// Interior lines (if present) bound holes within the surface,
+    /// each must consist of a minimum of 4 points, and the first and last points must be the same.
+    pub interiors: Option<Vec<GeoLineString>>,
+}
+
+impl GeoPolygon {
+    /// Polygon crosses the antimeridian, if any of its edges spans more than half of the globe
+    fn crosses_antimeridian(&self) -> bool {
+        self.exterior
+            .points
+            .windows(2)
+            .any(|edge| (edge[0].lon - edge[1].lon).abs() > 180.0)
+    }
+
+    /// Shift western longitudes by 360 degrees for polygons crossing the antimeridian,
+    /// so that the polygon is continuous in the shifted coordinates
+    fn normalize_lon(lon: f64, crosses_antimeridian: bool) -> f64 {
+        if crosses_antimeridian && lon < 0.0 {
+            lon + 360.0
+        } else {
+            lon
+        }
+    }
+
+    /// Ray casting, `lon` is expected to be normalized
+    fn ring_position(
+        ring: &GeoLineString,
+        lon: f64,
+        lat: f64,
+        crosses_antimeridian: bool,
+    ) -> RingPosition {
+        let points = &ring.points;
+        let mut inside = false;
+        // Explicitly close the ring, closing edge is zero length for properly closed rings
+        for (start, end) in points.iter().zip(points.iter().cycle().skip(1)) {
+            let (x1, y1) = (
+                Self::normalize_lon(start.lon, crosses_antimeridian),
+                start.lat,
+            );
+            let (x2, y2) = (Self::normalize_lon(end.lon, crosses_antimeridian), end.lat);
+
+            let cross_product = (x2 - x1) * (lat - y1) - (y2 - y1) * (lon - x1);
+            if cross_product.abs() <= GEO_POLYGON_EDGE_EPS
+                && x1.min(x2) - GEO_POLYGON_EDGE_EPS <= lon
+                && lon <= x1.max(x2) + GEO_POLYGON_EDGE_EPS
+                && y1.min(y2) - GEO_POLYGON_EDGE_EPS <= lat
+                && lat <= y1.max(y2) + GEO_POLYGON_EDGE_EPS
+            {
+                return RingPosition::Boundary;
+            }
+
+            if (y1 > lat) != (y2 > lat) {
+                let intersection_lon = x1 + (lat - y1) * (x2 - x1) / (y2 - y1);
+                if lon < intersection_lon {
+                    inside = !inside;
+                }
+            }
+        }
+        if inside {
+            RingPosition::Inside
+        } else {
+            RingPosition::Outside
+        }
+    }
+
+    pub fn check_point(&self, lon: f64, lat: f64) -> bool {
+        let crosses_antimeridian = self.crosses_antimeridian();
+        let lon = Self::normalize_lon(lon, crosses_antimeridian);
+
+        match Self::ring_position(&self.exterior, lon, lat, crosses_antimeridian) {
+            RingPosition::Outside => return false,
+            RingPosition::Boundary => return true,
+            RingPosition::Inside => {}
+        }
+
+        // Edges of the holes belong to the polygon
+        self.interiors.iter().flatten().all(|interior| {
+            Self::ring_position(interior, lon, lat, crosses_antimeridian) != RingPosition::Inside
+        })
+    }
+
+    /// Minimal rectangle, which contains the whole polygon
+    ///
+    /// For polygons crossing the antimeridian, longitude of the top left corner is greater than
+    /// the bottom right one.
+    pub fn bounding_box(&self) -> GeoBoundingBox {
+        let crosses_antimeridian = self.crosses_antimeridian();
+        let mut min_lon = f64::MAX;
+        let mut max_lon = f64::MIN;
+        let mut min_lat = f64::MAX;
+        let mut max_lat = f64::MIN;
+        for point in &self.exterior.points {
+            let lon = Self::normalize_lon(point.lon, crosses_antimeridian);
+            min_lon = min_lon.min(lon);
+            max_lon = max_lon.max(lon);
+            min_lat = min_lat.min(point.lat);
+            max_lat = max_lat.max(point.lat);
+        }
+
+        let denormalize_lon = |lon: f64| if lon > 180.0 { lon - 360.0 } else { lon };
+        GeoBoundingBox {
+            top_left: GeoPoint {
+                lon: denormalize_lon(min_lon),
+                lat: max_lat,
+            },
+            bottom_right: GeoPoint {
+                lon: denormalize_lon(max_lon),
+                lat: min_lat,
+            },
+        }
+    }
+}
+
+fn validate_geo_ring(ring: &GeoLineString) -> Option<ValidationError> {
+    if ring.points.len() < GEO_POLYGON_MIN_POINTS {
+        Some(validation_error(
+            "geo_polygon",
+            "polygon ring must consist of at least 4 points",
+        ))
+    } else if ring.points.first() != ring.points.last() {
+        Some(validation_error(
+            "geo_polygon",
+            "polygon ring must be closed: the first and the last points must be the same",
+        ))
+    } else {
+        None
+    }
+}
+
+impl Validate for GeoPolygon {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if let Some(error) = validate_geo_ring(&self.exterior) {
+            errors.add("exterior", error);
+        }
+        for interior in self.interiors.iter().flatten() {
+            if let Some(error) = validate_geo_ring(interior) {
+                errors.add("interiors", error);
+            }
+        }
+        errors.errors().is_empty().then_some(()).ok_or(errors)
+    }
+}
+
+/// All possible payload filtering conditions
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub struct FieldCondition {
+    /// Payload key
+    pub key: PayloadKeyType,
+    /// Check if point has field with a given value
+    pub r#match: Option<Match>,
+    /// Check if points value lies in a given range
+    #[validate]
+    pub range: Option<Range>,
+    /// Check if points geo location lies in a given area
+    pub geo_bounding_box: Option<GeoBoundingBox>,
+    /// Check if geo point is within a given radius
+    pub geo_radius: Option<GeoRadius>,
+    /// Check if geo point is within a given polygon
+    #[validate]
+    pub geo_polygon: Option<GeoPolygon>,
+    /// Check number of values of the field
+    pub values_count: Option<ValuesCount>,
+}
+
+impl FieldCondition {
+    pub fn new_match(key: PayloadKeyType, r#match: Match) -> Self {
+        Self {
+            key,
+            r#match: Some(r#match),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+            geo_polygon: None,
+            values_count: None,
+        }
+    }
+
+    pub fn new_range(key: PayloadKeyType, range: Range) -> Self {
+        Self {
+            key,
             r#match: None,
             range: Some(range),
             geo_bounding_box: None,
             geo_radius: None,
+            geo_polygon: None,
             values_count: None,
         }
     }
@@ -1076,6 +1879,7 @@ impl FieldCondition {
             range: None,
             geo_bounding_box: Some(geo_bounding_box),
             geo_radius: None,
+            geo_polygon: None,
             values_count: None,
         }
     }
@@ -1087,6 +1891,19 @@ impl FieldCondition {
             range: None,
             geo_bounding_box: None,
             geo_radius: Some(geo_radius),
+            geo_polygon: None,
+            values_count: None,
+        }
+    }
+
+    pub fn new_geo_polygon(key: PayloadKeyType, geo_polygon: GeoPolygon) -> Self {
+        Self {
+            key,
+            r#match: None,
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+            geo_polygon: Some(geo_polygon),
             values_count: None,
         }
     }
@@ -1098,6 +1915,7 @@ impl FieldCondition {
             range: None,
             geo_bounding_box: None,
             geo_radius: None,
+            geo_polygon: None,
             values_count: Some(values_count),
         }
     }
@@ -1105,6 +1923,7 @@ impl FieldCondition {
 
 /// Payload field
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct PayloadField {
     /// Payload field name
     pub key: PayloadKeyType,
@@ -1112,18 +1931,21 @@ pub struct PayloadField {
 
 /// Select points with empty payload for a specified field
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct IsEmptyCondition {
     pub is_empty: PayloadField,
 }
 
 /// Select points with null payload for a specified field
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct IsNullCondition {
     pub is_null: PayloadField,
 }
 
 /// ID-based filtering condition
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct HasIdCondition {
     pub has_id: HashSet<PointIdType>,
 }
@@ -1134,6 +1956,70 @@ impl From<HashSet<PointIdType>> for HasIdCondition {
     }
 }
 
+/// Select points, which have a vector with the given name
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HasVectorCondition {
+    pub has_vector: String,
+}
+
+impl From<String> for HasVectorCondition {
+    fn from(vector_name: String) -> Self {
+        HasVectorCondition {
+            has_vector: vector_name,
+        }
+    }
+}
+
+/// Filter, applied to each object of the array separately
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub struct Nested {
+    /// Key of the array of objects, keys of the nested filter are relative to the objects
+    pub key: PayloadKeyType,
+    /// Filter, which has to be satisfied by a single object of the array
+    pub filter: Filter,
+}
+
+/// Select points, which have at least one object in the array, satisfying the whole nested filter
+///
+/// Unlike conditions on the flattened array, e.g. `variants[].color`,
+/// all conditions of the nested filter are checked against the same object.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NestedCondition {
+    pub nested: Nested,
+}
+
+impl NestedCondition {
+    pub fn new(key: PayloadKeyType, filter: Filter) -> Self {
+        Self {
+            nested: Nested { key, filter },
+        }
+    }
+}
+
+impl Validate for NestedCondition {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let filter = &self.nested.filter;
+        let mut errors = ValidationErrors::new();
+        // Ids and vectors belong to points, not to the objects of the array
+        if filter.has_point_condition() {
+            errors.add(
+                "nested",
+                validation_error(
+                    "nested_point_condition",
+                    "has_id and has_vector are not supported inside of nested filter",
+                ),
+            );
+        }
+        let errors = ValidationErrors::merge(Err(errors), "filter", filter.validate_conditions())
+            .unwrap_err();
+        errors.errors().is_empty().then_some(()).ok_or(errors)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
 #[serde(untagged)]
 #[allow(clippy::large_enum_variant)]
@@ -1146,10 +2032,28 @@ pub enum Condition {
     IsNull(IsNullCondition),
     /// Check if points id is in a given set
     HasId(HasIdCondition),
+    /// Check if point has vector with a given name
+    HasVector(HasVectorCondition),
+    /// Check if some object of the array satisfies the whole nested filter
+    Nested(NestedCondition),
     /// Nested filter
     Filter(Filter),
 }
 
+impl Validate for Condition {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        match self {
+            Condition::Field(field_condition) => field_condition.validate(),
+            Condition::Filter(filter) => filter.validate_conditions(),
+            Condition::Nested(nested) => nested.validate(),
+            Condition::IsEmpty(_)
+            | Condition::IsNull(_)
+            | Condition::HasId(_)
+            | Condition::HasVector(_) => Ok(()),
+        }
+    }
+}
+
 /// Options for specifying which payload to include or not
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
@@ -1238,7 +2142,7 @@ impl From<&WithPayloadInterface> for WithPayload {
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct PayloadSelectorInclude {
-    /// Only include this payload keys
+    /// Only include this payload keys, nested keys are selected by dot-separated paths
     pub include: Vec<PayloadKeyType>,
 }
 
@@ -1252,7 +2156,7 @@ impl PayloadSelectorInclude {
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct PayloadSelectorExclude {
-    /// Exclude this fields from returning payload
+    /// Exclude this fields from returning payload, nested keys are selected by dot-separated paths
     pub exclude: Vec<PayloadKeyType>,
 }
 
@@ -1300,10 +2204,11 @@ impl PayloadSelector {
         }
     }
 
-    pub fn process(&self, x: Payload) -> Payload {
-        let map: serde_json::Map<String, Value> =
-            x.into_iter().filter(|(key, _)| self.check(key)).collect();
-        map.into()
+    /// Selected fields of the payload, only they are copied
+    ///
+    /// To select from many payloads, prepare the selector once with [`PayloadSelection::new`].
+    pub fn process(&self, x: &Payload) -> Payload {
+        PayloadSelection::new(self).select(x)
     }
 }
 
@@ -1317,7 +2222,13 @@ pub struct WithPayload {
     pub payload_selector: Option<PayloadSelector>,
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+/// Maximal allowed depth of nested filters.
+///
+/// Filter checking and cardinality estimation recurse into nested filters,
+/// so the depth has to be limited to keep the stack usage bounded.
+pub const MAX_FILTER_NESTING_DEPTH: usize = 32;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct Filter {
@@ -1353,6 +2264,375 @@ impl Filter {
             must_not: Some(vec![condition]),
         }
     }
+
+    /// Combine two filters, so that the result matches only points, matched by both of them
+    pub fn merge(&self, other: &Filter) -> Filter {
+        self.clone().merge_owned(other.clone())
+    }
+
+    /// Same as [`Filter::merge`], but reuses conditions of both filters
+    ///
+    /// `should` clauses of two filters are independent OR-groups, combining them into one group
+    /// would weaken the result. So only one of them stays on the top level and the other one
+    /// is moved into `must` as a nested filter.
+    pub fn merge_owned(self, other: Filter) -> Filter {
+        let merge_clauses =
+            |this: Option<Vec<Condition>>, other: Option<Vec<Condition>>| match (this, other) {
+                (None, clause) | (clause, None) => clause,
+                (Some(mut this), Some(other)) => {
+                    this.extend(other);
+                    Some(this)
+                }
+            };
+
+        // Empty `should` clause is not a constraint
+        let this_should = self.should.filter(|conditions| !conditions.is_empty());
+        let other_should = other.should.filter(|conditions| !conditions.is_empty());
+
+        let (should, nested_should) = match (this_should, other_should) {
+            (None, should) | (should, None) => (should, None),
+            (should, Some(other_should)) => (
+                should,
+                Some(vec![Condition::Filter(Filter {
+                    should: Some(other_should),
+                    must: None,
+                    must_not: None,
+                })]),
+            ),
+        };
+
+        Filter {
+            should,
+            must: merge_clauses(merge_clauses(self.must, other.must), nested_should),
+            must_not: merge_clauses(self.must_not, other.must_not),
+        }
+    }
+
+    /// Merge optional filters, see [`Filter::merge`]
+    pub fn merge_opt(this: Option<Filter>, other: Option<Filter>) -> Option<Filter> {
+        match (this, other) {
+            (None, filter) | (filter, None) => filter,
+            (Some(this), Some(other)) => Some(this.merge_owned(other)),
+        }
+    }
+
+    /// Convert filter into canonical form, which matches exactly the same points
+    ///
+    /// * Removes empty clauses and duplicated conditions
+    /// * Flattens nested filters of the same type and single-child nested filters
+    /// * Collapses sub-filters, which always or never match
+    ///
+    /// A filter which never matches is represented as `must_not` with an empty nested filter.
+    pub fn simplify(&self) -> Filter {
+        match simplify_filter(self.clone()) {
+            Simplified::Always => Filter::default(),
+            Simplified::Never => Filter::new_must_not(Condition::Filter(Filter::default())),
+            Simplified::Reduced(filter) => filter,
+        }
+    }
+
+    /// Iterate over all conditions of the filter, regardless of the clause
+    pub fn iter_conditions(&self) -> impl Iterator<Item = &Condition> {
+        self.should
+            .iter()
+            .chain(self.must.iter())
+            .chain(self.must_not.iter())
+            .flatten()
+    }
+
+    /// Check if the filter or any of its nested filters has `has_id` or `has_vector` condition
+    ///
+    /// Filters of nested conditions are not checked, as they are validated on their own.
+    fn has_point_condition(&self) -> bool {
+        self.iter_conditions().any(|condition| match condition {
+            Condition::HasId(_) | Condition::HasVector(_) => true,
+            Condition::Filter(nested) => nested.has_point_condition(),
+            _ => false,
+        })
+    }
+
+    /// Replace `has_vector` conditions with sub-filters, which always or never match
+    ///
+    /// Vectors are not a part of the payload, so the conditions have to be resolved
+    /// against vector storages before the filter is passed to the payload index.
+    /// Every point of a segment has all of its vectors, see `check_vectors_set`, so the condition
+    /// is the same for all the points, and the payload checkers never see it.
+    pub fn resolve_has_vector<F>(&self, has_vector: &F) -> Filter
+    where
+        F: Fn(&str) -> bool,
+    {
+        let resolve_clause = |conditions: &Option<Vec<Condition>>| {
+            conditions.as_ref().map(|conditions| {
+                conditions
+                    .iter()
+                    .map(|condition| match condition {
+                        Condition::HasVector(condition) => {
+                            if has_vector(&condition.has_vector) {
+                                Condition::Filter(Filter::default())
+                            } else {
+                                Condition::Filter(Filter::new_must_not(Condition::Filter(
+                                    Filter::default(),
+                                )))
+                            }
+                        }
+                        Condition::Filter(filter) => {
+                            Condition::Filter(filter.resolve_has_vector(has_vector))
+                        }
+                        condition => condition.clone(),
+                    })
+                    .collect()
+            })
+        };
+
+        Filter {
+            should: resolve_clause(&self.should),
+            must: resolve_clause(&self.must),
+            must_not: resolve_clause(&self.must_not),
+        }
+    }
+
+    /// Number of filter levels in this filter, filter without nested filters has depth of 1
+    ///
+    /// Computed iteratively, so it is safe to call on arbitrary deep filters
+    pub fn nesting_depth(&self) -> usize {
+        let mut max_depth = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((filter, depth)) = stack.pop() {
+            max_depth = max_depth.max(depth);
+            for condition in filter.iter_conditions() {
+                match condition {
+                    Condition::Filter(nested) => stack.push((nested, depth + 1)),
+                    Condition::Nested(nested) => stack.push((&nested.nested.filter, depth + 1)),
+                    _ => {}
+                }
+            }
+        }
+        max_depth
+    }
+}
+
+/// Result of the filter simplification
+enum Simplified<T> {
+    /// Matches every point
+    Always,
+    /// Matches no points
+    Never,
+    /// Depends on the point
+    Reduced(T),
+}
+
+/// Remove duplicated conditions, preserving the order of the first occurrences
+fn dedup_conditions(conditions: &mut Vec<Condition>) {
+    let mut unique: Vec<Condition> = Vec::with_capacity(conditions.len());
+    for condition in conditions.drain(..) {
+        if !unique.contains(&condition) {
+            unique.push(condition);
+        }
+    }
+    *conditions = unique;
+}
+
+fn non_empty(conditions: Vec<Condition>) -> Option<Vec<Condition>> {
+    if conditions.is_empty() {
+        None
+    } else {
+        Some(conditions)
+    }
+}
+
+fn simplify_condition(condition: Condition) -> Simplified<Condition> {
+    match condition {
+        Condition::Filter(filter) => match simplify_filter(filter) {
+            Simplified::Always => Simplified::Always,
+            Simplified::Never => Simplified::Never,
+            Simplified::Reduced(filter) => match filter {
+                // Single-child nested filter is equivalent to the child itself
+                Filter {
+                    should: Some(mut conditions),
+                    must: None,
+                    must_not: None,
+                }
+                | Filter {
+                    should: None,
+                    must: Some(mut conditions),
+                    must_not: None,
+                } if conditions.len() == 1 => Simplified::Reduced(conditions.pop().unwrap()),
+                filter => Simplified::Reduced(Condition::Filter(filter)),
+            },
+        },
+        Condition::HasId(has_id) if has_id.has_id.is_empty() => Simplified::Never,
+        // Nested filter can't be collapsed, as it only applies to points with objects in the array
+        Condition::Nested(NestedCondition {
+            nested: Nested { key, filter },
+        }) => match simplify_filter(filter) {
+            Simplified::Never => Simplified::Never,
+            Simplified::Always => Simplified::Reduced(Condition::Nested(NestedCondition::new(
+                key,
+                Filter::default(),
+            ))),
+            Simplified::Reduced(filter) => {
+                Simplified::Reduced(Condition::Nested(NestedCondition::new(key, filter)))
+            }
+        },
+        condition => Simplified::Reduced(condition),
+    }
+}
+
+/// Add simplified condition into `should` clause
+fn push_should(should: &mut Vec<Condition>, condition: Condition) {
+    match condition {
+        // `should` inside of `should`: A OR (B OR C) = A OR B OR C
+        Condition::Filter(Filter {
+            should: Some(conditions),
+            must: None,
+            must_not: None,
+        }) => conditions
+            .into_iter()
+            .for_each(|condition| push_should(should, condition)),
+        condition => should.push(condition),
+    }
+}
+
+/// Add simplified condition into `must` clause
+fn push_must(must: &mut Vec<Condition>, must_not: &mut Vec<Condition>, condition: Condition) {
+    match condition {
+        // `must` inside of `must`: A AND (B AND NOT C) = A AND B AND NOT C
+        Condition::Filter(Filter {
+            should: None,
+            must: nested_must,
+            must_not: nested_must_not,
+        }) => {
+            for condition in nested_must.unwrap_or_default() {
+                push_must(must, must_not, condition);
+            }
+            for condition in nested_must_not.unwrap_or_default() {
+                push_must_not(must, must_not, condition);
+            }
+        }
+        condition => must.push(condition),
+    }
+}
+
+/// Add simplified condition into `must_not` clause
+fn push_must_not(must: &mut Vec<Condition>, must_not: &mut Vec<Condition>, condition: Condition) {
+    match condition {
+        // NOT (A OR B) = NOT A AND NOT B
+        Condition::Filter(Filter {
+            should: Some(conditions),
+            must: None,
+            must_not: None,
+        }) => {
+            for condition in conditions {
+                push_must_not(must, must_not, condition);
+            }
+        }
+        // NOT (NOT A) = A
+        Condition::Filter(Filter {
+            should: None,
+            must: None,
+            must_not: Some(mut conditions),
+        }) if conditions.len() == 1 => push_must(must, must_not, conditions.pop().unwrap()),
+        condition => must_not.push(condition),
+    }
+}
+
+fn simplify_filter(filter: Filter) -> Simplified<Filter> {
+    let mut should = vec![];
+    let mut must = vec![];
+    let mut must_not = vec![];
+
+    for condition in filter.must.unwrap_or_default() {
+        match simplify_condition(condition) {
+            Simplified::Always => {}
+            Simplified::Never => return Simplified::Never,
+            Simplified::Reduced(condition) => push_must(&mut must, &mut must_not, condition),
+        }
+    }
+
+    for condition in filter.must_not.unwrap_or_default() {
+        match simplify_condition(condition) {
+            Simplified::Always => return Simplified::Never,
+            Simplified::Never => {}
+            Simplified::Reduced(condition) => push_must_not(&mut must, &mut must_not, condition),
+        }
+    }
+
+    // Empty `should` clause is not a constraint, same as absent one
+    let should_conditions = filter.should.unwrap_or_default();
+    if !should_conditions.is_empty() {
+        let mut always = false;
+        for condition in should_conditions {
+            match simplify_condition(condition) {
+                Simplified::Always => always = true,
+                Simplified::Never => {}
+                Simplified::Reduced(condition) => push_should(&mut should, condition),
+            }
+        }
+        if always {
+            should.clear();
+        } else if should.is_empty() {
+            // None of the alternatives can match
+            return Simplified::Never;
+        }
+    }
+
+    dedup_conditions(&mut should);
+    dedup_conditions(&mut must);
+    dedup_conditions(&mut must_not);
+
+    // A AND NOT A
+    if must.iter().any(|condition| must_not.contains(condition)) {
+        return Simplified::Never;
+    }
+
+    if should.is_empty() && must.is_empty() && must_not.is_empty() {
+        return Simplified::Always;
+    }
+
+    Simplified::Reduced(Filter {
+        should: non_empty(should),
+        must: non_empty(must),
+        must_not: non_empty(must_not),
+    })
+}
+
+impl Validate for Filter {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let depth = self.nesting_depth();
+        if depth > MAX_FILTER_NESTING_DEPTH {
+            let mut errors = ValidationErrors::new();
+            errors.add("filter", {
+                let mut error = ValidationError::new("nesting_depth");
+                error.add_param(Cow::from("value"), &depth);
+                error.add_param(Cow::from("max"), &MAX_FILTER_NESTING_DEPTH);
+                error.message.replace(Cow::from(format!(
+                    "filter nesting depth {depth} exceeds maximum of {MAX_FILTER_NESTING_DEPTH}"
+                )));
+                error
+            });
+            return Err(errors);
+        }
+
+        self.validate_conditions()
+    }
+}
+
+impl Filter {
+    /// Validate the conditions, errors are keyed by their path, e.g. `should[0].must[1]`
+    ///
+    /// Nesting depth is checked only once, by the validation of the top level filter.
+    fn validate_conditions(&self) -> Result<(), ValidationErrors> {
+        [
+            ("should", &self.should),
+            ("must", &self.must),
+            ("must_not", &self.must_not),
+        ]
+        .into_iter()
+        .fold(Ok(()), |result, (clause, conditions)| {
+            let conditions = conditions.iter().flatten().map(Validate::validate);
+            ValidationErrors::merge_all(result, clause, conditions.collect())
+        })
+    }
 }
 
 #[cfg(test)]
@@ -1360,9 +2640,13 @@ mod tests {
     use serde::de::DeserializeOwned;
     use serde_json;
     use serde_json::json;
+    use validator::ValidationErrorsKind;
 
     use super::*;
+    use crate::common::stats_cache::SegmentStats;
     use crate::common::utils::remove_value_from_json_map;
+    use crate::entry::entry_point::OperationError;
+    use crate::payload_storage::query_checker::{check_field_condition, check_filter};
 
     #[allow(dead_code)]
     fn check_rms_serialization<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(
@@ -1384,6 +2668,77 @@ mod tests {
         eprintln!("de_record = {de_record:#?}");
     }
 
+    #[test]
+    fn test_point_id_serde() {
+        let uuid_str = "550e8400-e29b-41d4-a716-446655440000";
+        let uuid = Uuid::from_str(uuid_str).unwrap();
+
+        let num_id: PointIdType = serde_json::from_value(json!(42)).unwrap();
+        assert_eq!(num_id, PointIdType::NumId(42));
+        // Numeric ids are serialized as bare integers, same as before UUIDs were supported
+        assert_eq!(serde_json::to_value(&num_id).unwrap(), json!(42));
+
+        let uuid_id: PointIdType = serde_json::from_value(json!(uuid_str)).unwrap();
+        assert_eq!(uuid_id, PointIdType::Uuid(uuid));
+        assert_eq!(serde_json::to_value(&uuid_id).unwrap(), json!(uuid_str));
+
+        assert!(serde_json::from_value::<PointIdType>(json!(-1)).is_err());
+        assert!(serde_json::from_value::<PointIdType>(json!("")).is_err());
+        assert!(serde_json::from_value::<PointIdType>(json!(1.5)).is_err());
+
+        assert_eq!(PointIdType::from_str(uuid_str), Ok(uuid_id));
+        assert_eq!(PointIdType::from_str(""), Err(()));
+
+        // Numeric ids go first, scroll relies on this order
+        let mut ids = vec![uuid_id.clone(), 100.into(), num_id.clone()];
+        ids.sort();
+        assert_eq!(ids, vec![num_id.clone(), 100.into(), uuid_id.clone()]);
+
+        let error = OperationError::PointIdError {
+            missed_point_id: num_id,
+        };
+        assert_eq!(error.to_string(), "No point with id 42");
+        let error = OperationError::PointIdError {
+            missed_point_id: uuid_id,
+        };
+        assert_eq!(error.to_string(), format!("No point with id {uuid_str}"));
+    }
+
+    #[test]
+    fn test_str_point_id() {
+        let str_id: PointIdType = serde_json::from_value(json!("doc-2024-00017")).unwrap();
+        assert_eq!(str_id, PointIdType::Str(StrPointId::new("doc-2024-00017")));
+        assert_eq!(
+            serde_json::to_value(&str_id).unwrap(),
+            json!("doc-2024-00017")
+        );
+        assert_eq!(str_id.to_string(), "doc-2024-00017");
+        assert_eq!(PointIdType::from_str("doc-2024-00017"), Ok(str_id));
+
+        // Numeric strings are not converted into numeric ids, both in JSON and in plain strings
+        let numeric_str: PointIdType = serde_json::from_value(json!("42")).unwrap();
+        assert_eq!(numeric_str, PointIdType::Str(StrPointId::new("42")));
+        assert_eq!(PointIdType::from_str("42"), Ok(numeric_str));
+
+        let uuid = Uuid::from_u128(123_u128);
+        let mut ids = vec![
+            PointIdType::Str(StrPointId::new("b")),
+            PointIdType::Uuid(uuid),
+            PointIdType::Str(StrPointId::new("a")),
+            PointIdType::NumId(100),
+        ];
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                PointIdType::NumId(100),
+                PointIdType::Uuid(uuid),
+                PointIdType::Str(StrPointId::new("a")),
+                PointIdType::Str(StrPointId::new("b")),
+            ]
+        );
+    }
+
     #[test]
     fn test_serialize_query() {
         let filter = Filter {
@@ -1398,6 +2753,552 @@ mod tests {
         eprintln!("{json}")
     }
 
+    #[test]
+    fn test_nested_filter_serde_round_trip() {
+        let query = r#"
+        {
+            "should": [
+                {
+                    "must": [
+                        { "key": "a", "match": { "value": true } },
+                        { "key": "b", "match": { "value": true } }
+                    ]
+                },
+                {
+                    "must": [
+                        { "key": "c", "match": { "value": true } }
+                    ],
+                    "must_not": [
+                        { "key": "d", "match": { "value": true } }
+                    ]
+                }
+            ]
+        }
+        "#;
+        let filter: Filter = serde_json::from_str(query).unwrap();
+        assert_eq!(filter.nesting_depth(), 2);
+        match filter.should.as_ref().unwrap().get(1) {
+            Some(Condition::Filter(nested)) => assert!(nested.must_not.is_some()),
+            _ => panic!("Condition::Filter expected"),
+        }
+
+        let json = serde_json::to_string(&filter).unwrap();
+        let de_filter: Filter = serde_json::from_str(&json).unwrap();
+        assert_eq!(filter, de_filter);
+    }
+
+    #[test]
+    fn test_filter_nesting_depth_validation() {
+        let condition = Condition::Field(FieldCondition::new_match(
+            "hello".to_owned(),
+            "world".to_owned().into(),
+        ));
+
+        let mut filter = Filter::new_must(condition);
+        assert_eq!(filter.nesting_depth(), 1);
+        for _ in 1..MAX_FILTER_NESTING_DEPTH {
+            filter = Filter::new_must_not(Condition::Filter(filter));
+        }
+        assert_eq!(filter.nesting_depth(), MAX_FILTER_NESTING_DEPTH);
+        assert!(filter.validate().is_ok());
+
+        let filter = Filter::new_should(Condition::Filter(filter));
+        assert_eq!(filter.nesting_depth(), MAX_FILTER_NESTING_DEPTH + 1);
+        assert!(filter.validate().is_err());
+    }
+
+    #[test]
+    fn test_filter_simplify() {
+        let condition = |key: &str| {
+            Condition::Field(FieldCondition::new_match(
+                key.to_owned(),
+                "world".to_owned().into(),
+            ))
+        };
+
+        // Empty clauses, duplicates and `must` inside of `must`
+        let filter = Filter {
+            should: Some(vec![]),
+            must: Some(vec![
+                condition("a"),
+                Condition::Filter(Filter {
+                    should: None,
+                    must: Some(vec![condition("a"), condition("b")]),
+                    must_not: Some(vec![condition("c")]),
+                }),
+            ]),
+            must_not: Some(vec![]),
+        };
+        assert_eq!(
+            filter.simplify(),
+            Filter {
+                should: None,
+                must: Some(vec![condition("a"), condition("b")]),
+                must_not: Some(vec![condition("c")]),
+            }
+        );
+
+        // Single-child filters and `should` inside of `should`
+        let filter = Filter::new_should(Condition::Filter(Filter {
+            should: Some(vec![
+                Condition::Filter(Filter::new_must(condition("a"))),
+                Condition::Filter(Filter {
+                    should: Some(vec![condition("b"), condition("c")]),
+                    must: None,
+                    must_not: None,
+                }),
+            ]),
+            must: None,
+            must_not: None,
+        }));
+        assert_eq!(
+            filter.simplify(),
+            Filter {
+                should: Some(vec![condition("a"), condition("b"), condition("c")]),
+                must: None,
+                must_not: None,
+            }
+        );
+
+        // Trivially true sub-filter makes `should` clause irrelevant
+        let filter = Filter {
+            should: Some(vec![condition("a"), Condition::Filter(Filter::default())]),
+            must: Some(vec![condition("b")]),
+            must_not: None,
+        };
+        assert_eq!(filter.simplify(), Filter::new_must(condition("b")));
+
+        // Contradiction and empty id set never match
+        let never = Filter::new_must_not(Condition::Filter(Filter::default()));
+        let filter = Filter {
+            should: None,
+            must: Some(vec![condition("a")]),
+            must_not: Some(vec![condition("a")]),
+        };
+        assert_eq!(filter.simplify(), never);
+        let filter = Filter::new_should(Condition::HasId(HashSet::<PointIdType>::new().into()));
+        assert_eq!(filter.simplify(), never);
+        assert_eq!(never.simplify(), never);
+    }
+
+    #[test]
+    fn test_nested_condition() {
+        let query = r#"
+        {
+            "must": [
+                {
+                    "nested": {
+                        "key": "variants",
+                        "filter": {
+                            "must": [
+                                { "key": "color", "match": { "value": "red" } },
+                                { "key": "size", "range": { "gte": 12 } }
+                            ]
+                        }
+                    }
+                }
+            ]
+        }
+        "#;
+        let filter: Filter = serde_json::from_str(query).unwrap();
+        let nested = match filter.must.as_ref().unwrap().first() {
+            Some(Condition::Nested(nested)) => nested.clone(),
+            _ => panic!("Condition::Nested expected"),
+        };
+        assert_eq!(nested.nested.key, "variants");
+        assert_eq!(nested.nested.filter.must.as_ref().unwrap().len(), 2);
+        assert_eq!(filter.nesting_depth(), 2);
+        assert!(filter.validate().is_ok());
+
+        // Ids belong to the points, not to the objects of the array
+        let filter = Filter::new_must(Condition::Nested(NestedCondition::new(
+            "variants".to_string(),
+            Filter::new_must_not(Condition::HasId(
+                HashSet::from([ExtendedPointId::NumId(1)]).into(),
+            )),
+        )));
+        assert!(filter.validate().is_err());
+
+        // Empty nested filter still requires an object in the array
+        let filter = Filter::new_must(Condition::Nested(NestedCondition::new(
+            "variants".to_string(),
+            Filter::new_should(Condition::Filter(Filter::default())),
+        )));
+        assert_eq!(
+            filter.simplify(),
+            Filter::new_must(Condition::Nested(NestedCondition::new(
+                "variants".to_string(),
+                Filter::default(),
+            )))
+        );
+    }
+
+    #[test]
+    fn test_resolve_has_vector() {
+        let filter: Filter = serde_json::from_str(
+            r#"
+            {
+                "must": [ { "has_vector": "image" } ],
+                "must_not": [ { "should": [ { "has_vector": "audio" } ] } ]
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            filter.must.as_ref().unwrap()[0],
+            Condition::HasVector("image".to_string().into())
+        );
+
+        let never = Filter::new_must_not(Condition::Filter(Filter::default()));
+        let resolved = |names: &[&str]| {
+            filter
+                .resolve_has_vector(&|name: &str| names.contains(&name))
+                .simplify()
+        };
+        assert_eq!(resolved(&["image"]), Filter::default());
+        assert_eq!(resolved(&["image", "audio"]), never);
+        assert_eq!(resolved(&[]), never);
+
+        // Vectors belong to points, not to the objects of the array
+        let filter = Filter::new_must(Condition::Nested(NestedCondition::new(
+            "variants".to_string(),
+            Filter::new_must(Condition::HasVector("image".to_string().into())),
+        )));
+        assert!(filter.validate().is_err());
+    }
+
+    #[test]
+    fn test_range_validation() {
+        let range = |gt, gte, lt, lte| Range { lt, gt, gte, lte };
+
+        assert!(range(Some(1.), None, None, None).validate().is_ok());
+        assert!(range(None, None, None, Some(1.)).validate().is_ok());
+        assert!(range(Some(1.), None, None, Some(2.)).validate().is_ok());
+
+        let errors = range(None, None, None, None).validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("range"));
+
+        let errors = range(Some(1.), Some(1.), None, None)
+            .validate()
+            .unwrap_err();
+        assert!(errors.field_errors().contains_key("gt"));
+
+        let errors = range(None, None, Some(2.), Some(2.))
+            .validate()
+            .unwrap_err();
+        assert!(errors.field_errors().contains_key("lt"));
+
+        // Invalid range is reported from the nested filter as well
+        let filter = Filter::new_must(Condition::Filter(Filter::new_should(Condition::Field(
+            FieldCondition::new_range("price".to_owned(), range(None, None, None, None)),
+        ))));
+        assert!(filter.validate().is_err());
+    }
+
+    #[test]
+    fn test_filter_validation_paths() {
+        let valid = || {
+            Condition::Field(FieldCondition::new_match(
+                "color".to_owned(),
+                "red".to_owned().into(),
+            ))
+        };
+        let invalid = || {
+            Condition::Field(FieldCondition::new_range(
+                "price".to_owned(),
+                Range {
+                    lt: None,
+                    gt: None,
+                    gte: None,
+                    lte: None,
+                },
+            ))
+        };
+        let positions = |errors: &ValidationErrors, clause: &str| -> Vec<usize> {
+            match errors.errors().get(clause) {
+                Some(ValidationErrorsKind::List(errors)) => errors.keys().copied().collect(),
+                other => panic!("unexpected errors of {clause}: {other:?}"),
+            }
+        };
+
+        // Both of the invalid siblings are reported
+        let filter = Filter {
+            should: None,
+            must: Some(vec![valid(), invalid(), valid(), invalid()]),
+            must_not: None,
+        };
+        let errors = filter.validate().unwrap_err();
+        assert_eq!(positions(&errors, "must"), vec![1, 3]);
+
+        // Errors of the nested filter are keyed by its position, `should[0].must[1]`
+        let filter = Filter {
+            should: Some(vec![Condition::Filter(Filter::new_must(valid()))]),
+            must: None,
+            must_not: Some(vec![valid()]),
+        };
+        assert!(filter.validate().is_ok());
+        let filter = Filter {
+            should: Some(vec![Condition::Filter(Filter {
+                should: None,
+                must: Some(vec![valid(), invalid()]),
+                must_not: None,
+            })]),
+            must: None,
+            must_not: None,
+        };
+        let errors = filter.validate().unwrap_err();
+        assert_eq!(positions(&errors, "should"), vec![0]);
+        let Some(ValidationErrorsKind::List(nested)) = errors.errors().get("should") else {
+            unreachable!();
+        };
+        assert_eq!(positions(&nested[&0], "must"), vec![1]);
+    }
+
+    fn geo_ring(points: &[(f64, f64)]) -> GeoLineString {
+        GeoLineString {
+            points: points
+                .iter()
+                .map(|&(lon, lat)| GeoPoint { lon, lat })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_geo_polygon_check_point() {
+        // Concave U-shaped polygon
+        let polygon = GeoPolygon {
+            exterior: geo_ring(&[
+                (0., 0.),
+                (3., 0.),
+                (3., 3.),
+                (2., 3.),
+                (2., 1.),
+                (1., 1.),
+                (1., 3.),
+                (0., 3.),
+                (0., 0.),
+            ]),
+            interiors: None,
+        };
+        assert!(polygon.check_point(0.5, 2.));
+        assert!(polygon.check_point(2.5, 2.));
+        assert!(polygon.check_point(1.5, 0.5));
+        assert!(!polygon.check_point(1.5, 2.));
+        assert!(!polygon.check_point(4., 1.));
+        // Rays through the vertices
+        assert!(!polygon.check_point(-1., 3.));
+        assert!(!polygon.check_point(-1., 1.));
+        assert!(polygon.check_point(0.5, 1.));
+        // Points on the edges and vertices
+        assert!(polygon.check_point(1.5, 1.));
+        assert!(polygon.check_point(0., 1.5));
+        assert!(polygon.check_point(3., 3.));
+
+        // Polygon with a hole
+        let polygon = GeoPolygon {
+            exterior: geo_ring(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.), (0., 0.)]),
+            interiors: Some(vec![geo_ring(&[
+                (4., 4.),
+                (6., 4.),
+                (6., 6.),
+                (4., 6.),
+                (4., 4.),
+            ])]),
+        };
+        assert!(polygon.check_point(1., 1.));
+        assert!(!polygon.check_point(5., 5.));
+        assert!(polygon.check_point(4., 5.));
+        assert!(!polygon.check_point(11., 5.));
+
+        // Polygon crossing the antimeridian
+        let polygon = GeoPolygon {
+            exterior: geo_ring(&[
+                (170., -10.),
+                (-170., -10.),
+                (-170., 10.),
+                (170., 10.),
+                (170., -10.),
+            ]),
+            interiors: None,
+        };
+        assert!(polygon.check_point(179., 0.));
+        assert!(polygon.check_point(-179., 0.));
+        assert!(polygon.check_point(180., 0.));
+        assert!(!polygon.check_point(0., 0.));
+        assert!(!polygon.check_point(160., 0.));
+        assert!(!polygon.check_point(-160., 0.));
+
+        let bounding_box = polygon.bounding_box();
+        assert_eq!(
+            bounding_box.top_left,
+            GeoPoint {
+                lon: 170.,
+                lat: 10.
+            }
+        );
+        assert_eq!(
+            bounding_box.bottom_right,
+            GeoPoint {
+                lon: -170.,
+                lat: -10.
+            }
+        );
+    }
+
+    #[test]
+    fn test_geo_polygon_validation() {
+        let closed = geo_ring(&[(0., 0.), (1., 0.), (1., 1.), (0., 0.)]);
+        let polygon = GeoPolygon {
+            exterior: closed.clone(),
+            interiors: Some(vec![closed.clone()]),
+        };
+        assert!(polygon.validate().is_ok());
+
+        let polygon = GeoPolygon {
+            exterior: geo_ring(&[(0., 0.), (1., 0.), (1., 1.), (0., 1.)]),
+            interiors: None,
+        };
+        let errors = polygon.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("exterior"));
+
+        let polygon = GeoPolygon {
+            exterior: closed,
+            interiors: Some(vec![geo_ring(&[(0., 0.), (1., 0.), (0., 0.)])]),
+        };
+        let errors = polygon.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("interiors"));
+
+        let condition: FieldCondition = serde_json::from_str(
+            r#"{"key": "location", "geo_polygon": {"exterior": {"points": [
+                {"lon": 0, "lat": 0}, {"lon": 1, "lat": 0}, {"lon": 1, "lat": 1}
+            ]}}}"#,
+        )
+        .unwrap();
+        assert!(condition.validate().is_err());
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let range = Range {
+            lt: None,
+            gt: None,
+            gte: Some(10.),
+            lte: Some(20.),
+        };
+        assert!(range.check_range(10.));
+        assert!(range.check_range(20.));
+        assert!(!range.check_range(9.999));
+        assert!(!range.check_range(20.001));
+
+        let range = Range {
+            lt: Some(20.),
+            gt: Some(10.),
+            gte: None,
+            lte: None,
+        };
+        assert!(!range.check_range(10.));
+        assert!(!range.check_range(20.));
+        assert!(range.check_range(15.));
+
+        // Open-ended range
+        let range = Range {
+            lt: None,
+            gt: Some(-1.5),
+            gte: None,
+            lte: None,
+        };
+        assert!(range.check_range(-1.));
+        assert!(range.check_range(FloatPayloadType::MAX));
+        assert!(!range.check_range(-1.5));
+    }
+
+    #[test]
+    fn test_filter_merge() {
+        let flag =
+            |key: &str| Condition::Field(FieldCondition::new_match(key.to_string(), true.into()));
+        let any = |conditions: Vec<Condition>| Filter {
+            should: Some(conditions),
+            must: None,
+            must_not: None,
+        };
+
+        let cases = [
+            // (A OR B) AND (C OR D)
+            (
+                any(vec![flag("a"), flag("b")]),
+                any(vec![flag("c"), flag("d")]),
+                (|a, b, c, d| (a || b) && (c || d)) as fn(bool, bool, bool, bool) -> bool,
+            ),
+            // (A OR B) AND C AND NOT D
+            (
+                any(vec![flag("a"), flag("b")]),
+                Filter {
+                    should: None,
+                    must: Some(vec![flag("c")]),
+                    must_not: Some(vec![flag("d")]),
+                },
+                |a, b, c, d| (a || b) && c && !d,
+            ),
+            // (A AND NOT B) AND (C OR D)
+            (
+                Filter {
+                    should: Some(vec![]),
+                    must: Some(vec![flag("a")]),
+                    must_not: Some(vec![flag("b")]),
+                },
+                any(vec![flag("c"), flag("d")]),
+                |a, b, c, d| a && !b && (c || d),
+            ),
+            // (A OR (B AND C)) AND (NOT D OR A)
+            (
+                any(vec![
+                    flag("a"),
+                    Condition::Filter(Filter {
+                        should: None,
+                        must: Some(vec![flag("b"), flag("c")]),
+                        must_not: None,
+                    }),
+                ]),
+                any(vec![
+                    Condition::Filter(Filter::new_must_not(flag("d"))),
+                    flag("a"),
+                ]),
+                |a, b, c, d| (a || (b && c)) && (!d || a),
+            ),
+        ];
+
+        for (this, other, expected) in cases {
+            let merged = this.merge(&other);
+            let merged_reverse = other.clone().merge_owned(this.clone());
+            let merged_opt = Filter::merge_opt(Some(this.clone()), Some(other.clone())).unwrap();
+
+            assert_eq!(
+                Filter::merge_opt(Some(this.clone()), None),
+                Some(this.clone())
+            );
+            assert_eq!(
+                Filter::merge_opt(None, Some(other.clone())),
+                Some(other.clone())
+            );
+
+            for mask in 0..16u8 {
+                let (a, b, c, d) = (mask & 1 > 0, mask & 2 > 0, mask & 4 > 0, mask & 8 > 0);
+                let payload: Payload = json!({ "a": a, "b": b, "c": c, "d": d }).into();
+                let checker = |condition: &Condition| match condition {
+                    Condition::Field(field_condition) => {
+                        check_field_condition(field_condition, &payload)
+                    }
+                    _ => unreachable!(),
+                };
+
+                let expected = expected(a, b, c, d);
+                assert_eq!(check_filter(&checker, &merged), expected, "{merged:?}");
+                assert_eq!(check_filter(&checker, &merged_reverse), expected);
+                assert_eq!(check_filter(&checker, &merged_opt), expected);
+            }
+        }
+    }
+
     #[test]
     fn test_deny_unknown_fields() {
         let query1 = r#"
@@ -1499,6 +3400,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_match_except() {
+        let query = r#"
+        {
+            "must": [
+                {
+                    "key": "color",
+                    "match": {
+                        "except": ["red", "blue"]
+                    }
+                },
+                {
+                    "key": "size",
+                    "match": {
+                        "except": [1, 2]
+                    }
+                }
+            ]
+        }
+        "#;
+
+        let filter: Filter = serde_json::from_str(query).unwrap();
+        let must = filter.must.unwrap();
+        let matches: Vec<_> = must
+            .iter()
+            .map(|condition| match condition {
+                Condition::Field(c) => c.r#match.clone().unwrap(),
+                _ => panic!("Condition::Field expected"),
+            })
+            .collect();
+
+        assert_eq!(
+            matches,
+            vec![
+                Match::Except(MatchExcept {
+                    except: AnyVariants::Keywords(vec!["red".to_string(), "blue".to_string()]),
+                }),
+                Match::Except(MatchExcept {
+                    except: AnyVariants::Integers(vec![1, 2]),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_match_insensitive() {
+        let query = r#"
+        {
+            "must": [
+                {
+                    "key": "fruit",
+                    "match": {
+                        "insensitive": "Äpfel"
+                    }
+                }
+            ]
+        }
+        "#;
+
+        let filter: Filter = serde_json::from_str(query).unwrap();
+        match filter.must.unwrap().first() {
+            Some(Condition::Field(c)) => assert_eq!(
+                c.r#match,
+                Some(Match::Insensitive(MatchInsensitive {
+                    insensitive: "Äpfel".to_string(),
+                }))
+            ),
+            _ => panic!("Condition::Field expected"),
+        }
+    }
+
     #[test]
     fn test_parse_match_any_mixed_types() {
         let query = r#"
@@ -1841,6 +3813,277 @@ mod tests {
         let query = r#""keyword""#;
         let field_type: PayloadSchemaType = serde_json::from_str(query).unwrap();
         eprintln!("field_type = {field_type:?}");
+
+        let query = r#"{"type": "keyword", "insensitive": true}"#;
+        let field_schema: PayloadFieldSchema = serde_json::from_str(query).unwrap();
+        assert_eq!(
+            field_schema,
+            PayloadFieldSchema::FieldParams(PayloadSchemaParams::Keyword(KeywordIndexParams {
+                r#type: Default::default(),
+                insensitive: Some(true),
+            }))
+        );
+        let field_schema: PayloadFieldSchema = serde_json::from_str(r#"{"type": "text"}"#).unwrap();
+        assert!(matches!(
+            field_schema,
+            PayloadFieldSchema::FieldParams(PayloadSchemaParams::Text(_))
+        ));
+    }
+
+    #[test]
+    fn test_postprocess_score() {
+        let a = [1.0, 2.0, 2.0];
+        let b = [3.0, 0.0, 4.0];
+        let score = |distance: Distance| {
+            let a = distance.preprocess_vector(&a).unwrap_or(a.to_vec());
+            let b = distance.preprocess_vector(&b).unwrap_or(b.to_vec());
+            distance.postprocess_score(distance.similarity(&a, &b))
+        };
+        let eps = 1e-5;
+
+        // dot(a, b) / (|a| * |b|) = 11 / (3 * 5)
+        assert!((score(Distance::Cosine) - 11.0 / 15.0).abs() < eps);
+        // sqrt(2^2 + 2^2 + 2^2)
+        assert!((score(Distance::Euclid) - 12f32.sqrt()).abs() < eps);
+        // 1 * 3 + 2 * 0 + 2 * 4
+        assert!((score(Distance::Dot) - 11.0).abs() < eps);
+
+        assert_eq!(Distance::Cosine.distance_order(), Order::LargeBetter);
+        assert_eq!(Distance::Dot.distance_order(), Order::LargeBetter);
+        assert_eq!(Distance::Euclid.distance_order(), Order::SmallBetter);
+
+        // Better score has larger key, and passes the threshold of the worse one
+        for (distance, better, worse) in [
+            (Distance::Cosine, 0.9, 0.5),
+            (Distance::Dot, 2.0, -1.0),
+            (Distance::Euclid, 0.5, 3.0),
+        ] {
+            let order = distance.distance_order();
+            assert!(order.score_key(better) > order.score_key(worse));
+            assert!(distance.check_threshold(better, worse));
+            assert!(!distance.check_threshold(worse, better));
+        }
+    }
+
+    /// Serialized value is parsed back into the value with the same serialization
+    fn assert_json_round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+        let json = serde_json::to_value(value).unwrap();
+        let parsed: T = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let uuid: PointIdType = "550e8400-e29b-41d4-a716-446655440000".parse().unwrap();
+        assert_json_round_trip(&PointIdType::NumId(42));
+        assert_json_round_trip(&uuid);
+
+        let filter = Filter {
+            should: Some(vec![
+                Condition::HasId(HashSet::from([uuid]).into()),
+                Condition::Field(FieldCondition::new_match(
+                    "tags".to_string(),
+                    Match::Any(MatchAny {
+                        any: AnyVariants::Integers(vec![1, 2]),
+                    }),
+                )),
+            ]),
+            must: Some(vec![
+                Condition::Field(FieldCondition::new_values_count(
+                    "tags".to_string(),
+                    ValuesCount {
+                        lt: None,
+                        gt: Some(1),
+                        gte: None,
+                        lte: None,
+                    },
+                )),
+                Condition::IsEmpty(IsEmptyCondition {
+                    is_empty: PayloadField {
+                        key: "description".to_string(),
+                    },
+                }),
+                Condition::Filter(Filter::new_must(Condition::HasVector(
+                    "image".to_string().into(),
+                ))),
+            ]),
+            must_not: Some(vec![
+                Condition::IsNull(IsNullCondition {
+                    is_null: PayloadField {
+                        key: "price".to_string(),
+                    },
+                }),
+                Condition::Nested(NestedCondition::new(
+                    "variants".to_string(),
+                    Filter::new_must(Condition::Field(FieldCondition::new_geo_radius(
+                        "location".to_string(),
+                        GeoRadius {
+                            center: GeoPoint {
+                                lon: 13.4,
+                                lat: 52.5,
+                            },
+                            radius: 1000.0,
+                        },
+                    ))),
+                )),
+            ]),
+        };
+        assert_json_round_trip(&filter);
+
+        assert_json_round_trip(&SearchParams {
+            hnsw_ef: Some(128),
+            quantization: Some(QuantizationSearchParams {
+                ignore: false,
+                rescore: true,
+            }),
+            ivf_nprobe: Some(4),
+            score_boost: Some(ScoreBoost {
+                key: "popularity".to_string(),
+                factor: 0.5,
+                function: BoostFunction::Log1p,
+            }),
+            timeout_ms: Some(100),
+            ..Default::default()
+        });
+
+        let payload: Payload = json!({
+            "color": "red",
+            "price": 9.5,
+            "count": 3,
+            "tags": ["a", "b"],
+            "location": {"lon": 13.4, "lat": 52.5},
+            "empty": null,
+        })
+        .into();
+        assert_json_round_trip(&payload);
+        assert_json_round_trip(&ScoredPoint {
+            id: uuid,
+            version: 3,
+            score: 0.5,
+            payload: Some(Arc::new(payload)),
+            vector: Some(VectorStruct::Multi(HashMap::from([(
+                "image".to_string(),
+                vec![1.0, 0.0],
+            )]))),
+        });
+
+        for schema_type in [
+            PayloadSchemaType::Keyword,
+            PayloadSchemaType::Integer,
+            PayloadSchemaType::Float,
+            PayloadSchemaType::Geo,
+            PayloadSchemaType::Text,
+        ] {
+            assert_json_round_trip(&PayloadFieldSchema::FieldType(schema_type));
+        }
+        assert_json_round_trip(&SegmentStats {
+            ram_usage_bytes: 1024,
+            disk_usage_bytes: 4096,
+            index_schema: HashMap::from([(
+                "color".to_string(),
+                PayloadIndexInfo {
+                    data_type: PayloadSchemaType::Keyword,
+                    params: None,
+                    points: 10,
+                },
+            )]),
+        });
+        assert_json_round_trip(&SegmentConfig::default());
+        assert_json_round_trip(&WithPayloadInterface::Fields(vec!["color".to_string()]));
+        assert_json_round_trip(&WithVector::Selector(vec!["image".to_string()]));
+    }
+
+    /// JSON of the filters and the search results is a part of the API, it must not change
+    #[test]
+    fn test_json_shape() {
+        let filter = Filter {
+            should: None,
+            must: Some(vec![
+                Condition::Field(FieldCondition::new_match(
+                    "color".to_string(),
+                    Match::Value(MatchValue {
+                        value: ValueVariants::Keyword("red".to_string()),
+                    }),
+                )),
+                Condition::Field(FieldCondition::new_range(
+                    "price".to_string(),
+                    Range {
+                        lt: None,
+                        gt: None,
+                        gte: Some(10.0),
+                        lte: Some(100.0),
+                    },
+                )),
+            ]),
+            must_not: Some(vec![Condition::HasId(
+                HashSet::from([PointIdType::NumId(7)]).into(),
+            )]),
+        };
+        let filter_json = json!({
+            "should": null,
+            "must": [
+                {
+                    "key": "color",
+                    "match": {"value": "red"},
+                    "range": null,
+                    "geo_bounding_box": null,
+                    "geo_radius": null,
+                    "geo_polygon": null,
+                    "values_count": null,
+                },
+                {
+                    "key": "price",
+                    "match": null,
+                    "range": {"lt": null, "gt": null, "gte": 10.0, "lte": 100.0},
+                    "geo_bounding_box": null,
+                    "geo_radius": null,
+                    "geo_polygon": null,
+                    "values_count": null,
+                },
+            ],
+            "must_not": [{"has_id": [7]}],
+        });
+        assert_eq!(serde_json::to_value(&filter).unwrap(), filter_json);
+        assert_eq!(
+            serde_json::from_value::<Filter>(filter_json).unwrap(),
+            filter
+        );
+
+        let scored_point = ScoredPoint {
+            id: "550e8400-e29b-41d4-a716-446655440000".parse().unwrap(),
+            version: 3,
+            score: 0.5,
+            payload: Some(Arc::new(json!({"color": "red"}).into())),
+            vector: Some(VectorStruct::Single(vec![1.0, 0.0])),
+        };
+        assert_eq!(
+            serde_json::to_value(&scored_point).unwrap(),
+            json!({
+                "id": "550e8400-e29b-41d4-a716-446655440000",
+                "version": 3,
+                "score": 0.5,
+                "payload": {"color": "red"},
+                "vector": [1.0, 0.0],
+            })
+        );
+    }
+
+    #[test]
+    fn test_filter_unknown_fields() {
+        let invalid_filters = [
+            // Unknown field of the field condition
+            json!({"must": [{"key": "color", "match": {"value": "red"}, "unknown": 1}]}),
+            // Ambiguous match
+            json!({"must": [{"key": "color", "match": {"value": "red", "text": "red"}}]}),
+            json!({"must": [{"key": "price", "range": {"gte": 1.0, "from": 0.0}}]}),
+            json!({"must": [{"is_empty": {"key": "color", "strict": true}}]}),
+            json!({"must": [{"has_id": [1], "has_vector": "image"}]}),
+            json!({"must": [{"nested": {"key": "variants", "filter": {}, "limit": 1}}]}),
+        ];
+        for invalid_filter in invalid_filters {
+            let result = serde_json::from_value::<Filter>(invalid_filter.clone());
+            assert!(result.is_err(), "{invalid_filter}");
+        }
     }
 }
 
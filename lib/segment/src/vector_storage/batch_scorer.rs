@@ -0,0 +1,122 @@
+use super::{RawScorer, ScoredPointOffset, VectorStorageEnum};
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::OperationResult;
+use crate::spaces::tools::{peek_top_largest_iterable, FixedLengthPriorityQueue};
+use crate::types::{PointOffsetType, ScoreType};
+
+/// Maximal number of points, offered to the provider at once
+pub const BATCH_SCORER_BLOCK_SIZE: usize = 64 * 1024;
+
+/// External scorer of blocks of vectors, e.g. offloading the scoring to an accelerator
+///
+/// Provider is optional: blocks, which it declines or fails to score, are scored on CPU.
+pub trait BatchScorerProvider {
+    /// Scores of the `query` against the stored vectors of the points at `offsets`, in the same order
+    ///
+    /// Scores should be of the same scale as of [`RawScorer`]: similarity of preprocessed vectors
+    /// by the distance of `vector_storage`, larger is better.
+    /// Returns `Ok(None)` to decline the block, e.g. if it is too small to offload.
+    fn score_block(
+        &self,
+        query: &[VectorElementType],
+        vector_storage: &VectorStorageEnum,
+        offsets: &[PointOffsetType],
+    ) -> OperationResult<Option<Vec<ScoreType>>>;
+}
+
+pub type BatchScorerSS = dyn BatchScorerProvider + Sync + Send;
+
+/// Scores of the block by the provider, `None` if it declined or failed to score the block
+pub fn provider_scores(
+    provider: &BatchScorerSS,
+    query: &[VectorElementType],
+    vector_storage: &VectorStorageEnum,
+    offsets: &[PointOffsetType],
+) -> Option<Vec<ScoreType>> {
+    match provider.score_block(query, vector_storage, offsets) {
+        Ok(Some(scores)) if scores.len() == offsets.len() => Some(scores),
+        Ok(Some(scores)) => {
+            log::warn!(
+                "Batch scorer returned {} scores for {} points, scoring on CPU",
+                scores.len(),
+                offsets.len(),
+            );
+            None
+        }
+        Ok(None) => None,
+        Err(err) => {
+            log::warn!("Batch scorer failed, scoring on CPU: {err}");
+            None
+        }
+    }
+}
+
+/// Top of the `points`, offered to the provider by blocks of [`BATCH_SCORER_BLOCK_SIZE`]
+///
+/// Same as [`RawScorer::peek_top_iter`], points, rejected by `raw_scorer`, are skipped.
+pub fn peek_top_with_provider(
+    provider: &BatchScorerSS,
+    raw_scorer: &dyn RawScorer,
+    query: &[VectorElementType],
+    vector_storage: &VectorStorageEnum,
+    points: &mut dyn Iterator<Item = PointOffsetType>,
+    top: usize,
+) -> Vec<ScoredPointOffset> {
+    if top == 0 {
+        return vec![];
+    }
+    let mut top_points = FixedLengthPriorityQueue::new(top);
+    let mut block = Vec::with_capacity(BATCH_SCORER_BLOCK_SIZE);
+    let mut cpu_scores = vec![];
+    loop {
+        block.clear();
+        block.extend(points.by_ref().take(BATCH_SCORER_BLOCK_SIZE));
+        if block.is_empty() {
+            break;
+        }
+        match provider_scores(provider, query, vector_storage, &block) {
+            Some(scores) => {
+                for (&idx, score) in block.iter().zip(scores) {
+                    if raw_scorer.check_point(idx) {
+                        top_points.push(ScoredPointOffset { idx, score });
+                    }
+                }
+            }
+            None => {
+                cpu_scores.resize(block.len(), ScoredPointOffset::default());
+                let count = raw_scorer.score_points(&block, &mut cpu_scores);
+                for &scored_point in &cpu_scores[..count] {
+                    top_points.push(scored_point);
+                }
+            }
+        }
+    }
+    top_points.into_vec()
+}
+
+/// Replace scores of the points with the ones of the provider, if any, or of `raw_scorer`
+///
+/// Points are reordered by the new scores, only the `top` of them are kept.
+pub fn rescore_points(
+    provider: Option<&BatchScorerSS>,
+    raw_scorer: &dyn RawScorer,
+    query: &[VectorElementType],
+    vector_storage: &VectorStorageEnum,
+    mut points: Vec<ScoredPointOffset>,
+    top: usize,
+) -> Vec<ScoredPointOffset> {
+    let scores = provider.and_then(|provider| {
+        let offsets: Vec<_> = points.iter().map(|point| point.idx).collect();
+        provider_scores(provider, query, vector_storage, &offsets)
+    });
+    match scores {
+        Some(scores) => points
+            .iter_mut()
+            .zip(scores)
+            .for_each(|(point, score)| point.score = score),
+        None => points
+            .iter_mut()
+            .for_each(|point| point.score = raw_scorer.score_point(point.idx)),
+    }
+    peek_top_largest_iterable(points, top)
+}
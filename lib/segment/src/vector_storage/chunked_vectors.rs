@@ -3,7 +3,9 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::mem;
 use std::path::Path;
+use std::slice;
 
+use crate::data_types::vectors::VectorElementType;
 use crate::types::PointOffsetType;
 
 // chunk size in bytes
@@ -73,6 +75,96 @@ impl<T: Copy + Clone + Default> ChunkedVectors<T> {
     }
 }
 
+/// Alignment of the vectors of the [`crate::types::VectorLayout::Aligned`] layout, in bytes
+pub const VECTOR_ALIGNMENT: usize = 32;
+
+/// Number of the vector elements in one aligned block
+pub const ALIGNED_BLOCK_DIM: usize = VECTOR_ALIGNMENT / mem::size_of::<VectorElementType>();
+
+/// Elements of the vector at the 32-byte boundary, so the buffers of the blocks are aligned
+#[repr(C, align(32))]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AlignedBlock(pub [VectorElementType; ALIGNED_BLOCK_DIM]);
+
+/// Dimension of the vector, padded to the whole number of the aligned blocks
+pub fn padded_dim(dim: usize) -> usize {
+    (dim + ALIGNED_BLOCK_DIM - 1) / ALIGNED_BLOCK_DIM * ALIGNED_BLOCK_DIM
+}
+
+/// Copy of the vector in the aligned blocks, padded with zeros
+pub fn aligned_vector(vector: &[VectorElementType]) -> Vec<AlignedBlock> {
+    let mut blocks = vec![AlignedBlock::default(); padded_dim(vector.len()) / ALIGNED_BLOCK_DIM];
+    blocks_as_slice_mut(&mut blocks)[..vector.len()].copy_from_slice(vector);
+    blocks
+}
+
+pub fn blocks_as_slice(blocks: &[AlignedBlock]) -> &[VectorElementType] {
+    // Blocks are `repr(C)` arrays of the elements without the gaps
+    unsafe { slice::from_raw_parts(blocks.as_ptr().cast(), blocks.len() * ALIGNED_BLOCK_DIM) }
+}
+
+fn blocks_as_slice_mut(blocks: &mut [AlignedBlock]) -> &mut [VectorElementType] {
+    unsafe {
+        slice::from_raw_parts_mut(blocks.as_mut_ptr().cast(), blocks.len() * ALIGNED_BLOCK_DIM)
+    }
+}
+
+/// [`ChunkedVectors`] of the aligned layout: each vector starts at the 32-byte boundary,
+/// and is padded with zeros to the multiple of 32 bytes
+pub struct AlignedChunkedVectors {
+    dim: usize,
+    chunks: ChunkedVectors<AlignedBlock>,
+    /// Padded copy of the inserted vector, its padding is never written
+    insert_buffer: Vec<AlignedBlock>,
+}
+
+impl AlignedChunkedVectors {
+    pub fn new(dim: usize) -> Self {
+        let blocks_per_vector = padded_dim(dim) / ALIGNED_BLOCK_DIM;
+        Self {
+            dim,
+            chunks: ChunkedVectors::new(blocks_per_vector),
+            insert_buffer: vec![AlignedBlock::default(); blocks_per_vector],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Vector without the padding
+    pub fn get<TKey>(&self, key: TKey) -> &[VectorElementType]
+    where
+        TKey: num_traits::cast::AsPrimitive<usize>,
+    {
+        &self.get_padded(key)[..self.dim]
+    }
+
+    /// Vector with the zero padding, its start is aligned to [`VECTOR_ALIGNMENT`]
+    pub fn get_padded<TKey>(&self, key: TKey) -> &[VectorElementType]
+    where
+        TKey: num_traits::cast::AsPrimitive<usize>,
+    {
+        blocks_as_slice(self.chunks.get(key))
+    }
+
+    pub fn push(&mut self, vector: &[VectorElementType]) -> PointOffsetType {
+        let new_id = self.len() as PointOffsetType;
+        self.insert(new_id, vector);
+        new_id
+    }
+
+    pub fn insert(&mut self, key: PointOffsetType, vector: &[VectorElementType]) {
+        debug_assert_eq!(vector.len(), self.dim);
+        blocks_as_slice_mut(&mut self.insert_buffer)[..vector.len()].copy_from_slice(vector);
+        self.chunks.insert(key, &self.insert_buffer);
+    }
+}
+
 impl quantization::EncodedStorage for ChunkedVectors<u8> {
     fn get_vector_data(&self, index: usize, _vector_size: usize) -> &[u8] {
         self.get(index)
@@ -0,0 +1,375 @@
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+use parking_lot::{Mutex, RwLock};
+
+use super::quantized::quantized_vectors_base::QuantizedVectorsStorage;
+use super::VectorStorageEnum;
+use crate::common::encryption::{SegmentCipher, ENCRYPTION_OVERHEAD};
+use crate::common::Flusher;
+use crate::data_types::vectors::VectorElementType;
+use crate::entry::entry_point::{
+    check_points_capacity, check_process_stopped, OperationError, OperationResult,
+};
+use crate::types::{Distance, PointOffsetType, QuantizationConfig};
+use crate::vector_storage::VectorStorage;
+
+const HEADER_SIZE: usize = 4;
+const VECTORS_HEADER: &[u8; 4] = b"encv";
+
+/// Approximate size of the plaintext of the block, blocks hold the whole vectors
+pub const ENCRYPTION_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Stores all vectors in the file of the encrypted blocks, in place of the mem-mapped storage
+///
+/// Ciphertext can't be mem-mapped, so the blocks are read and decrypted on the first access,
+/// and kept in the block cache while the storage is open.
+/// Each block but the last one holds the same number of vectors, so the blocks are located by their number.
+///
+/// Like the mem-mapped storage, it can only be constructed from another storage
+pub struct EncryptedVectorStorage {
+    vectors_path: PathBuf,
+    dim: usize,
+    distance: Distance,
+    cipher: Arc<SegmentCipher>,
+    num_vectors: usize,
+    file: Mutex<File>,
+    blocks: Vec<OnceCell<Box<[VectorElementType]>>>,
+}
+
+pub fn open_encrypted_vector_storage(
+    path: &Path,
+    dim: usize,
+    distance: Distance,
+    cipher: Arc<SegmentCipher>,
+) -> OperationResult<Arc<RwLock<VectorStorageEnum>>> {
+    create_dir_all(path)?;
+
+    let vectors_path = storage_path(path);
+    if !vectors_path.exists() {
+        let mut file = File::create(&vectors_path)?;
+        file.write_all(VECTORS_HEADER)?;
+        file.sync_all()?;
+    }
+
+    let mut storage = EncryptedVectorStorage {
+        file: Mutex::new(
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&vectors_path)?,
+        ),
+        vectors_path,
+        dim,
+        distance,
+        cipher,
+        num_vectors: 0,
+        blocks: vec![],
+    };
+    storage.reset_blocks()?;
+
+    Ok(Arc::new(RwLock::new(VectorStorageEnum::Encrypted(
+        Box::new(storage),
+    ))))
+}
+
+fn storage_path(path: &Path) -> PathBuf {
+    path.join("encrypted_matrix.dat")
+}
+
+impl EncryptedVectorStorage {
+    fn block_vectors(&self) -> usize {
+        (ENCRYPTION_BLOCK_SIZE / self.vector_size()).max(1)
+    }
+
+    fn vector_size(&self) -> usize {
+        self.dim * size_of::<VectorElementType>()
+    }
+
+    fn full_block_size(&self) -> usize {
+        self.block_vectors() * self.vector_size() + ENCRYPTION_OVERHEAD
+    }
+
+    fn block_count(&self) -> usize {
+        (self.num_vectors + self.block_vectors() - 1) / self.block_vectors()
+    }
+
+    fn block_offset(&self, block: usize) -> u64 {
+        (HEADER_SIZE + block * self.full_block_size()) as u64
+    }
+
+    fn block_len(&self, block: usize) -> usize {
+        self.block_vectors()
+            .min(self.num_vectors - block * self.block_vectors())
+    }
+
+    /// Count the vectors of the file and drop the cached blocks
+    fn reset_blocks(&mut self) -> OperationResult<()> {
+        let file_len = self.file.get_mut().metadata()?.len() as usize;
+        let data_len = file_len.checked_sub(HEADER_SIZE).ok_or_else(|| {
+            OperationError::service_error(format!(
+                "Encrypted data file {} is truncated",
+                self.vectors_path.display()
+            ))
+        })?;
+        let full_blocks = data_len / self.full_block_size();
+        let last_block_size = data_len % self.full_block_size();
+        let last_block_vectors = match last_block_size {
+            0 => 0,
+            size if size > ENCRYPTION_OVERHEAD
+                && (size - ENCRYPTION_OVERHEAD) % self.vector_size() == 0 =>
+            {
+                (size - ENCRYPTION_OVERHEAD) / self.vector_size()
+            }
+            _ => {
+                return Err(OperationError::service_error(format!(
+                    "Encrypted data file {} is truncated",
+                    self.vectors_path.display()
+                )))
+            }
+        };
+        self.num_vectors = full_blocks * self.block_vectors() + last_block_vectors;
+        self.blocks = (0..self.block_count()).map(|_| OnceCell::new()).collect();
+        Ok(())
+    }
+
+    fn read_block(&self, block: usize) -> OperationResult<Vec<VectorElementType>> {
+        let mut encrypted =
+            vec![0; self.block_len(block) * self.vector_size() + ENCRYPTION_OVERHEAD];
+        {
+            let mut file = self.file.lock();
+            file.seek(SeekFrom::Start(self.block_offset(block)))?;
+            file.read_exact(&mut encrypted)?;
+        }
+        let plaintext = self
+            .cipher
+            .decrypt(&(block as u64).to_le_bytes(), &encrypted)
+            .map_err(|err| {
+                OperationError::service_error(format!(
+                    "Failed to decrypt block {block} of {}: {err}",
+                    self.vectors_path.display()
+                ))
+            })?;
+        Ok(plaintext
+            .chunks_exact(size_of::<VectorElementType>())
+            .map(|bytes| VectorElementType::from_le_bytes(bytes.try_into().unwrap()))
+            .collect())
+    }
+
+    fn write_block(
+        &self,
+        file: &mut File,
+        block: usize,
+        vectors: &[VectorElementType],
+    ) -> OperationResult<()> {
+        let plaintext: Vec<u8> = vectors.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let encrypted = self
+            .cipher
+            .encrypt(&(block as u64).to_le_bytes(), &plaintext);
+        file.seek(SeekFrom::Start(self.block_offset(block)))?;
+        file.write_all(&encrypted)?;
+        Ok(())
+    }
+
+    fn block(&self, block: usize) -> &[VectorElementType] {
+        self.blocks[block]
+            .get_or_try_init(|| self.read_block(block).map(Vec::into_boxed_slice))
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+impl VectorStorage for EncryptedVectorStorage {
+    fn vector_dim(&self) -> usize {
+        self.dim
+    }
+
+    fn distance(&self) -> Distance {
+        self.distance
+    }
+
+    fn total_vector_count(&self) -> usize {
+        self.num_vectors
+    }
+
+    fn get_vector(&self, key: PointOffsetType) -> &[VectorElementType] {
+        let key = key as usize;
+        assert!(key < self.num_vectors, "vector {key} is out of the storage");
+        let block = self.block(key / self.block_vectors());
+        let offset = (key % self.block_vectors()) * self.dim;
+        &block[offset..offset + self.dim]
+    }
+
+    fn insert_vector(
+        &mut self,
+        _key: PointOffsetType,
+        _vector: &[VectorElementType],
+    ) -> OperationResult<()> {
+        panic!("Can't directly update vector in encrypted storage")
+    }
+
+    fn update_from(
+        &mut self,
+        other: &VectorStorageEnum,
+        other_ids: &mut dyn Iterator<Item = PointOffsetType>,
+        stopped: &AtomicBool,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let start_index = self.num_vectors as PointOffsetType;
+        let block_vectors = self.block_vectors();
+
+        // Partial last block is written again with the appended vectors
+        let mut block = self.num_vectors / block_vectors;
+        let mut pending = if self.num_vectors % block_vectors == 0 {
+            vec![]
+        } else {
+            self.read_block(block)?
+        };
+        let mut end_index = start_index;
+
+        let mut file = OpenOptions::new().write(true).open(&self.vectors_path)?;
+        file.set_len(self.block_offset(block))?;
+        for id in other_ids {
+            check_process_stopped(stopped)?;
+            end_index = check_points_capacity(end_index as usize + 1)?;
+            pending.extend_from_slice(other.get_vector(id));
+            if pending.len() == block_vectors * self.dim {
+                self.write_block(&mut file, block, &pending)?;
+                pending.clear();
+                block += 1;
+            }
+        }
+        if !pending.is_empty() {
+            self.write_block(&mut file, block, &pending)?;
+        }
+        file.sync_all()?;
+        drop(file);
+
+        self.reset_blocks()?;
+        Ok(start_index..end_index)
+    }
+
+    fn flusher(&self) -> Flusher {
+        Box::new(|| Ok(()))
+    }
+
+    fn quantize(
+        &mut self,
+        _data_path: &Path,
+        _quantization_config: &QuantizationConfig,
+    ) -> OperationResult<()> {
+        Err(OperationError::service_error(
+            "Quantization of the encrypted vectors is not supported",
+        ))
+    }
+
+    fn load_quantization(&mut self, _data_path: &Path) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn quantized_storage(&self) -> Option<&QuantizedVectorsStorage> {
+        None
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.vectors_path.clone()]
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::common::encryption::EncryptionKey;
+    use crate::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
+    use crate::vector_storage::simple_vector_storage::open_simple_vector_storage;
+
+    fn vector(id: u64, dim: usize) -> Vec<f32> {
+        (0..dim)
+            .map(|i| 0.123 + id as f32 + i as f32 / 7.0)
+            .collect()
+    }
+
+    fn vector_bytes(vector: &[f32]) -> Vec<u8> {
+        vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_encrypted_vector_storage() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db_dir = Builder::new().prefix("db_dir").tempdir().unwrap();
+        let cipher = Arc::new(SegmentCipher::new(&EncryptionKey::new([1; 32])));
+        let stopped = AtomicBool::new(false);
+        // Several blocks and the partial last one
+        let dim = 64;
+        let num_vectors = 1000u64;
+
+        let db = open_db(db_dir.path(), &[DB_VECTOR_CF]).unwrap();
+        let source = open_simple_vector_storage(
+            db,
+            DB_VECTOR_CF,
+            dim,
+            Distance::Dot,
+            None,
+            Default::default(),
+        )
+        .unwrap();
+        {
+            let mut source = source.write();
+            for id in 0..num_vectors {
+                source.insert_vector(id as u32, &vector(id, dim)).unwrap();
+            }
+        }
+
+        let storage =
+            open_encrypted_vector_storage(dir.path(), dim, Distance::Dot, cipher.clone()).unwrap();
+        {
+            let source = source.read();
+            let mut storage = storage.write();
+            // Partial last block is rewritten by the second update
+            let range = storage
+                .update_from(&source, &mut (0..600), &stopped)
+                .unwrap();
+            assert_eq!(range, 0..600);
+            let range = storage
+                .update_from(&source, &mut (600..num_vectors as u32), &stopped)
+                .unwrap();
+            assert_eq!(range, 600..num_vectors as u32);
+        }
+        drop(storage);
+
+        let storage =
+            open_encrypted_vector_storage(dir.path(), dim, Distance::Dot, cipher).unwrap();
+        let storage = storage.read();
+        assert_eq!(storage.total_vector_count(), num_vectors as usize);
+        for id in [0, 1, 255, 256, 599, 600, 999] {
+            assert_eq!(storage.get_vector(id as u32), vector(id, dim));
+        }
+
+        let plaintexts: Vec<_> = [0, 600, 999]
+            .into_iter()
+            .map(|id| vector_bytes(&vector(id, dim)[..16]))
+            .collect();
+        let data = std::fs::read(&storage_path(dir.path())).unwrap();
+        for plaintext in plaintexts {
+            assert!(!data
+                .windows(plaintext.len())
+                .any(|window| window == plaintext));
+        }
+
+        // Wrong key fails the authentication of the block
+        let wrong_cipher = Arc::new(SegmentCipher::new(&EncryptionKey::new([2; 32])));
+        let wrong =
+            open_encrypted_vector_storage(dir.path(), dim, Distance::Dot, wrong_cipher).unwrap();
+        let wrong = wrong.read();
+        match &*wrong {
+            VectorStorageEnum::Encrypted(wrong) => assert!(wrong.read_block(0).is_err()),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -6,18 +6,22 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
+use parking_lot::RwLock;
 
 use super::quantized::quantized_vectors_base::QuantizedVectorsStorage;
 use super::VectorStorageEnum;
 use crate::common::Flusher;
 use crate::data_types::vectors::VectorElementType;
-use crate::entry::entry_point::{check_process_stopped, OperationResult};
-use crate::types::{Distance, PointOffsetType, QuantizationConfig};
-use crate::vector_storage::mmap_vectors::MmapVectors;
+use crate::entry::entry_point::{check_points_capacity, check_process_stopped, OperationResult};
+use crate::snapshot_archive::SnapshotArchive;
+use crate::types::{Distance, PointOffsetType, QuantizationConfig, VectorLayout};
+use crate::vector_storage::mmap_vectors::{convert_to_aligned_layout, MmapVectors};
 use crate::vector_storage::quantized::quantized_vectors_base::QuantizedVectors;
 use crate::vector_storage::VectorStorage;
 
+/// Data file of the storage in its directory
+pub const MMAP_VECTORS_FILE: &str = "matrix.dat";
+
 fn vf_to_u8<T>(v: &[T]) -> &[u8] {
     unsafe { std::slice::from_raw_parts(v.as_ptr() as *const u8, v.len() * size_of::<T>()) }
 }
@@ -32,25 +36,63 @@ pub struct MemmapVectorStorage {
     vectors_path: PathBuf,
     mmap_store: Option<MmapVectors>,
     distance: Distance,
+    /// Layout of the data file, if it is created by the storage
+    layout: VectorLayout,
 }
 
 pub fn open_memmap_vector_storage(
     path: &Path,
     dim: usize,
     distance: Distance,
-) -> OperationResult<Arc<AtomicRefCell<VectorStorageEnum>>> {
+    layout: VectorLayout,
+) -> OperationResult<Arc<RwLock<VectorStorageEnum>>> {
     create_dir_all(path)?;
 
-    let vectors_path = path.join("matrix.dat");
-    let mmap_store = MmapVectors::open(&vectors_path, dim)?;
+    let vectors_path = path.join(MMAP_VECTORS_FILE);
+    let mmap_store = MmapVectors::open(&vectors_path, dim, layout)?;
 
-    Ok(Arc::new(AtomicRefCell::new(VectorStorageEnum::Memmap(
-        Box::new(MemmapVectorStorage {
+    Ok(Arc::new(RwLock::new(VectorStorageEnum::Memmap(Box::new(
+        MemmapVectorStorage {
             vectors_path,
             mmap_store: Some(mmap_store),
             distance,
-        }),
-    ))))
+            layout,
+        },
+    )))))
+}
+
+/// Rewrite the data file of the storage in the given directory to the aligned layout
+///
+/// Returns false, if there is no data file or it is already aligned.
+pub fn migrate_to_aligned_layout(path: &Path, dim: usize) -> OperationResult<bool> {
+    let vectors_path = path.join(MMAP_VECTORS_FILE);
+    if !vectors_path.exists() {
+        return Ok(false);
+    }
+    convert_to_aligned_layout(&vectors_path, dim)
+}
+
+/// Open the storage, which data file is read through the snapshot archive, see [`SnapshotArchive::map_file`]
+///
+/// Storage is read-only, it can't be updated from another storage.
+pub fn open_archived_memmap_vector_storage(
+    archive: &SnapshotArchive,
+    path: &Path,
+    dim: usize,
+    distance: Distance,
+) -> OperationResult<Arc<RwLock<VectorStorageEnum>>> {
+    let vectors_path = path.join(MMAP_VECTORS_FILE);
+    let mmap_store = MmapVectors::from_mmap(archive.map_file(&vectors_path)?, &vectors_path, dim)?;
+    let layout = mmap_store.layout();
+
+    Ok(Arc::new(RwLock::new(VectorStorageEnum::Memmap(Box::new(
+        MemmapVectorStorage {
+            vectors_path,
+            mmap_store: Some(mmap_store),
+            distance,
+            layout,
+        },
+    )))))
 }
 
 impl VectorStorage for MemmapVectorStorage {
@@ -70,6 +112,14 @@ impl VectorStorage for MemmapVectorStorage {
         self.mmap_store.as_ref().unwrap().get_vector(key)
     }
 
+    fn vector_layout(&self) -> VectorLayout {
+        self.mmap_store.as_ref().unwrap().layout()
+    }
+
+    fn get_padded_vector(&self, key: PointOffsetType) -> Option<&[VectorElementType]> {
+        self.mmap_store.as_ref().unwrap().get_padded_vector(key)
+    }
+
     fn insert_vector(
         &mut self,
         _key: PointOffsetType,
@@ -88,6 +138,8 @@ impl VectorStorage for MemmapVectorStorage {
 
         let start_index = self.mmap_store.as_ref().unwrap().num_vectors as PointOffsetType;
         let mut end_index = start_index;
+        // Vectors are appended in the layout of the file
+        let padding = self.mmap_store.as_ref().unwrap().vector_padding();
 
         self.mmap_store = None;
 
@@ -101,15 +153,16 @@ impl VectorStorage for MemmapVectorStorage {
 
             for id in other_ids {
                 check_process_stopped(stopped)?;
+                end_index = check_points_capacity(end_index as usize + 1)?;
                 let vector = other.get_vector(id);
                 let raw_bites = vf_to_u8(vector);
                 file.write_all(raw_bites)?;
-                end_index += 1;
+                file.write_all(&padding)?;
             }
 
             file.flush()?;
         }
-        self.mmap_store = Some(MmapVectors::open(&self.vectors_path, dim)?);
+        self.mmap_store = Some(MmapVectors::open(&self.vectors_path, dim, self.layout)?);
 
         Ok(start_index..end_index)
     }
@@ -159,6 +212,7 @@ mod tests {
     use crate::fixtures::payload_context_fixture::FixtureIdTracker;
     use crate::id_tracker::IdTracker;
     use crate::types::{PointIdType, ScalarQuantizationConfig};
+    use crate::vector_storage::chunked_vectors::VECTOR_ALIGNMENT;
     use crate::vector_storage::simple_vector_storage::open_simple_vector_storage;
     use crate::vector_storage::{new_raw_scorer, ScoredPointOffset};
 
@@ -174,23 +228,25 @@ mod tests {
             vec![1.0, 1.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
         ];
-        let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(points.len())));
-        let storage = open_memmap_vector_storage(dir.path(), 4, dist).unwrap();
-        let mut borrowed_id_tracker = id_tracker.borrow_mut();
-        let mut borrowed_storage = storage.borrow_mut();
+        let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(points.len())));
+        let storage = open_memmap_vector_storage(dir.path(), 4, dist, Default::default()).unwrap();
+        let mut borrowed_id_tracker = id_tracker.write();
+        let mut borrowed_storage = storage.write();
 
         {
             let dir2 = Builder::new().prefix("db_dir").tempdir().unwrap();
             let db = open_db(dir2.path(), &[DB_VECTOR_CF]).unwrap();
-            let storage2 = open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist).unwrap();
+            let storage2 =
+                open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist, None, Default::default())
+                    .unwrap();
             {
-                let mut borrowed_storage2 = storage2.borrow_mut();
+                let mut borrowed_storage2 = storage2.write();
                 borrowed_storage2.insert_vector(0, &points[0]).unwrap();
                 borrowed_storage2.insert_vector(1, &points[1]).unwrap();
                 borrowed_storage2.insert_vector(2, &points[2]).unwrap();
             }
             borrowed_storage
-                .update_from(&storage2.borrow(), &mut Box::new(0..3), &Default::default())
+                .update_from(&storage2.read(), &mut Box::new(0..3), &Default::default())
                 .unwrap();
         }
 
@@ -205,14 +261,16 @@ mod tests {
         {
             let dir2 = Builder::new().prefix("db_dir").tempdir().unwrap();
             let db = open_db(dir2.path(), &[DB_VECTOR_CF]).unwrap();
-            let storage2 = open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist).unwrap();
+            let storage2 =
+                open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist, None, Default::default())
+                    .unwrap();
             {
-                let mut borrowed_storage2 = storage2.borrow_mut();
+                let mut borrowed_storage2 = storage2.write();
                 borrowed_storage2.insert_vector(3, &points[3]).unwrap();
                 borrowed_storage2.insert_vector(4, &points[4]).unwrap();
             }
             borrowed_storage
-                .update_from(&storage2.borrow(), &mut Box::new(0..2), &Default::default())
+                .update_from(&storage2.read(), &mut Box::new(0..2), &Default::default())
                 .unwrap();
         }
 
@@ -251,17 +309,19 @@ mod tests {
             vec![1.0, 1.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
         ];
-        let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(points.len())));
-        let storage = open_memmap_vector_storage(dir.path(), 4, dist).unwrap();
-        let borrowed_id_tracker = id_tracker.borrow_mut();
-        let mut borrowed_storage = storage.borrow_mut();
+        let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(points.len())));
+        let storage = open_memmap_vector_storage(dir.path(), 4, dist, Default::default()).unwrap();
+        let borrowed_id_tracker = id_tracker.write();
+        let mut borrowed_storage = storage.write();
 
         {
             let dir2 = Builder::new().prefix("db_dir").tempdir().unwrap();
             let db = open_db(dir2.path(), &[DB_VECTOR_CF]).unwrap();
-            let storage2 = open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist).unwrap();
+            let storage2 =
+                open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist, None, Default::default())
+                    .unwrap();
             {
-                let mut borrowed_storage2 = storage2.borrow_mut();
+                let mut borrowed_storage2 = storage2.write();
                 for (i, vec) in points.iter().enumerate() {
                     borrowed_storage2
                         .insert_vector(i as PointOffsetType, vec)
@@ -270,7 +330,7 @@ mod tests {
             }
             borrowed_storage
                 .update_from(
-                    &storage2.borrow(),
+                    &storage2.read(),
                     &mut Box::new(0..points.len() as PointOffsetType),
                     &Default::default(),
                 )
@@ -329,17 +389,19 @@ mod tests {
             vec![1.0, 1.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
         ];
-        let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(points.len())));
-        let storage = open_memmap_vector_storage(dir.path(), 4, dist).unwrap();
-        let borrowed_id_tracker = id_tracker.borrow_mut();
-        let mut borrowed_storage = storage.borrow_mut();
+        let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(points.len())));
+        let storage = open_memmap_vector_storage(dir.path(), 4, dist, Default::default()).unwrap();
+        let borrowed_id_tracker = id_tracker.write();
+        let mut borrowed_storage = storage.write();
 
         {
             let dir2 = Builder::new().prefix("db_dir").tempdir().unwrap();
             let db = open_db(dir2.path(), &[DB_VECTOR_CF]).unwrap();
-            let storage2 = open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist).unwrap();
+            let storage2 =
+                open_simple_vector_storage(db, DB_VECTOR_CF, 4, dist, None, Default::default())
+                    .unwrap();
             {
-                let mut borrowed_storage2 = storage2.borrow_mut();
+                let mut borrowed_storage2 = storage2.write();
                 for (i, vec) in points.iter().enumerate() {
                     borrowed_storage2
                         .insert_vector(i as PointOffsetType, vec)
@@ -348,7 +410,7 @@ mod tests {
             }
             borrowed_storage
                 .update_from(
-                    &storage2.borrow(),
+                    &storage2.read(),
                     &mut Box::new(0..points.len() as PointOffsetType),
                     &Default::default(),
                 )
@@ -410,4 +472,67 @@ mod tests {
             assert!((orig - quant).abs() < 0.15);
         }
     }
+
+    #[test]
+    fn test_aligned_layout_migration() {
+        let dist = Distance::Dot;
+        let dim = 5;
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let points: Vec<Vec<VectorElementType>> = (0..10)
+            .map(|i| {
+                (0..dim)
+                    .map(|j| (i * dim + j) as VectorElementType)
+                    .collect()
+            })
+            .collect();
+
+        {
+            let storage =
+                open_memmap_vector_storage(dir.path(), dim, dist, VectorLayout::Packed).unwrap();
+            let db_dir = Builder::new().prefix("db_dir").tempdir().unwrap();
+            let db = open_db(db_dir.path(), &[DB_VECTOR_CF]).unwrap();
+            let storage2 =
+                open_simple_vector_storage(db, DB_VECTOR_CF, dim, dist, None, VectorLayout::Packed)
+                    .unwrap();
+            for (i, vec) in points.iter().enumerate() {
+                storage2
+                    .write()
+                    .insert_vector(i as PointOffsetType, vec)
+                    .unwrap();
+            }
+            let mut borrowed_storage = storage.write();
+            borrowed_storage
+                .update_from(
+                    &storage2.read(),
+                    &mut Box::new(0..points.len() as PointOffsetType),
+                    &Default::default(),
+                )
+                .unwrap();
+            assert_eq!(borrowed_storage.vector_layout(), VectorLayout::Packed);
+            assert!(borrowed_storage.get_padded_vector(0).is_none());
+        }
+
+        assert!(migrate_to_aligned_layout(dir.path(), dim).unwrap());
+        assert!(!migrate_to_aligned_layout(dir.path(), dim).unwrap());
+
+        // Layout of the existing file is kept, regardless of the requested one
+        let storage =
+            open_memmap_vector_storage(dir.path(), dim, dist, VectorLayout::Packed).unwrap();
+        let borrowed_storage = storage.read();
+        assert_eq!(borrowed_storage.vector_layout(), VectorLayout::Aligned);
+        assert_eq!(borrowed_storage.total_vector_count(), points.len());
+        for (i, vec) in points.iter().enumerate() {
+            assert_eq!(
+                borrowed_storage.get_vector(i as PointOffsetType),
+                vec.as_slice()
+            );
+            let padded = borrowed_storage
+                .get_padded_vector(i as PointOffsetType)
+                .unwrap();
+            assert_eq!(padded.as_ptr() as usize % VECTOR_ALIGNMENT, 0);
+            assert_eq!(padded.len(), 8);
+            assert_eq!(&padded[..dim], vec.as_slice());
+            assert!(padded[dim..].iter().all(|x| *x == 0.0));
+        }
+    }
 }
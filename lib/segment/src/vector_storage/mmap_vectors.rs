@@ -1,5 +1,5 @@
-use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
 use std::mem::{size_of, transmute};
 use std::path::Path;
 
@@ -7,22 +7,49 @@ use memmap2::{Mmap, MmapOptions};
 
 use crate::common::error_logging::LogError;
 use crate::data_types::vectors::VectorElementType;
-use crate::entry::entry_point::OperationResult;
+use crate::entry::entry_point::{OperationError, OperationResult};
 use crate::madvise;
-use crate::types::{Distance, PointOffsetType, QuantizationConfig};
+use crate::types::{Distance, PointOffsetType, QuantizationConfig, VectorLayout};
+use crate::vector_storage::chunked_vectors::{padded_dim, VECTOR_ALIGNMENT};
 use crate::vector_storage::quantized::quantized_vectors_base::QuantizedVectorsStorage;
 
 const HEADER_SIZE: usize = 4;
 const VECTORS_HEADER: &[u8; 4] = b"data";
+/// Header of the file of the aligned layout, it is padded to [`VECTOR_ALIGNMENT`] bytes,
+/// so the vectors after it are aligned within the page-aligned mapping
+const ALIGNED_VECTORS_HEADER: &[u8; 4] = b"alnd";
 
 /// Mem-mapped file
 pub struct MmapVectors {
     pub dim: usize,
     pub num_vectors: usize,
+    /// Layout of the file, told by its header
+    layout: VectorLayout,
+    header_size: usize,
+    /// Number of the elements between the starts of the vectors
+    stride: usize,
     mmap: Mmap,
     pub quantized_vectors: Option<QuantizedVectorsStorage>,
 }
 
+fn file_header(layout: VectorLayout) -> Vec<u8> {
+    match layout {
+        VectorLayout::Packed => VECTORS_HEADER.to_vec(),
+        VectorLayout::Aligned => {
+            let mut header = vec![0; VECTOR_ALIGNMENT];
+            header[..ALIGNED_VECTORS_HEADER.len()].copy_from_slice(ALIGNED_VECTORS_HEADER);
+            header
+        }
+    }
+}
+
+fn vector_stride(dim: usize, layout: VectorLayout) -> usize {
+    match layout {
+        VectorLayout::Packed => dim,
+        VectorLayout::Aligned => padded_dim(dim),
+    }
+}
+
 fn open_read(path: &Path) -> OperationResult<Mmap> {
     let file = OpenOptions::new()
         .read(true)
@@ -46,20 +73,59 @@ fn ensure_mmap_file_exists(path: &Path, header: &[u8]) -> OperationResult<()> {
 }
 
 impl MmapVectors {
-    pub fn open(vectors_path: &Path, dim: usize) -> OperationResult<Self> {
-        ensure_mmap_file_exists(vectors_path, VECTORS_HEADER).describe("Create mmap data file")?;
+    /// Open the data file, it is created with the given layout, if it doesn't exist
+    pub fn open(vectors_path: &Path, dim: usize, layout: VectorLayout) -> OperationResult<Self> {
+        ensure_mmap_file_exists(vectors_path, &file_header(layout))
+            .describe("Create mmap data file")?;
 
         let mmap = open_read(vectors_path).describe("Open mmap for reading")?;
-        let num_vectors = (mmap.len() - HEADER_SIZE) / dim / size_of::<VectorElementType>();
+        Self::from_mmap(mmap, vectors_path, dim)
+    }
+
+    /// Vectors of the mapped data file, e.g. of the member of the snapshot archive
+    pub fn from_mmap(mmap: Mmap, vectors_path: &Path, dim: usize) -> OperationResult<Self> {
+        let truncated = || {
+            OperationError::service_error(format!(
+                "Mmap data file {} is truncated",
+                vectors_path.display()
+            ))
+        };
+        let layout = match mmap.get(..HEADER_SIZE) {
+            Some(header) if header == ALIGNED_VECTORS_HEADER => VectorLayout::Aligned,
+            Some(_) => VectorLayout::Packed,
+            None => return Err(truncated()),
+        };
+        let header_size = file_header(layout).len();
+        let stride = vector_stride(dim, layout);
+        let data_len = mmap.len().checked_sub(header_size).ok_or_else(truncated)?;
+        let num_vectors = data_len / stride / size_of::<VectorElementType>();
 
         Ok(MmapVectors {
             dim,
             num_vectors,
+            layout,
+            header_size,
+            stride,
             mmap,
             quantized_vectors: None,
         })
     }
 
+    /// Layout of the vectors, it is aligned only if the mapping itself is aligned
+    pub fn layout(&self) -> VectorLayout {
+        match self.layout {
+            VectorLayout::Aligned if self.mmap.as_ptr() as usize % VECTOR_ALIGNMENT == 0 => {
+                VectorLayout::Aligned
+            }
+            _ => VectorLayout::Packed,
+        }
+    }
+
+    /// Zeros, written after each appended vector of the file
+    pub fn vector_padding(&self) -> Vec<u8> {
+        vec![0; (self.stride - self.dim) * size_of::<VectorElementType>()]
+    }
+
     pub fn quantize(
         &mut self,
         distance: Distance,
@@ -95,8 +161,8 @@ impl MmapVectors {
     }
 
     pub fn data_offset(&self, key: PointOffsetType) -> Option<usize> {
-        let vector_data_length = self.dim * size_of::<VectorElementType>();
-        let offset = (key as usize) * vector_data_length + HEADER_SIZE;
+        let vector_data_length = self.stride * size_of::<VectorElementType>();
+        let offset = (key as usize) * vector_data_length + self.header_size;
         if key >= (self.num_vectors as PointOffsetType) {
             return None;
         }
@@ -118,4 +184,50 @@ impl MmapVectors {
         let offset = self.data_offset(key).unwrap();
         self.raw_vector_offset(offset)
     }
+
+    /// Vector with its zero padding, if the vectors are aligned, see [`MmapVectors::layout`]
+    pub fn get_padded_vector(&self, key: PointOffsetType) -> Option<&[VectorElementType]> {
+        if self.layout() != VectorLayout::Aligned {
+            return None;
+        }
+        let offset = self.data_offset(key).unwrap();
+        let byte_slice =
+            &self.mmap[offset..(offset + self.stride * size_of::<VectorElementType>())];
+        let arr: &[VectorElementType] = unsafe { transmute(byte_slice) };
+        Some(&arr[0..self.stride])
+    }
+}
+
+/// Rewrite the data file of the packed layout to the aligned one, returns false if it is already aligned
+///
+/// The new file is written aside and renamed over the old one, so the file is never partially converted.
+pub fn convert_to_aligned_layout(vectors_path: &Path, dim: usize) -> OperationResult<bool> {
+    let packed = MmapVectors::from_mmap(open_read(vectors_path)?, vectors_path, dim)?;
+    if packed.layout == VectorLayout::Aligned {
+        return Ok(false);
+    }
+
+    let tmp_path = vectors_path.with_extension("aligned.tmp");
+    {
+        let mut file = BufWriter::new(File::create(&tmp_path)?);
+        file.write_all(&file_header(VectorLayout::Aligned))?;
+        let padding = vec![0u8; (padded_dim(dim) - dim) * size_of::<VectorElementType>()];
+        for key in 0..packed.num_vectors as PointOffsetType {
+            let vector = packed.get_vector(key);
+            let raw_bytes = unsafe {
+                std::slice::from_raw_parts(
+                    vector.as_ptr() as *const u8,
+                    vector.len() * size_of::<VectorElementType>(),
+                )
+            };
+            file.write_all(raw_bytes)?;
+            file.write_all(&padding)?;
+        }
+        file.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+    }
+    drop(packed);
+    fs::rename(&tmp_path, vectors_path)?;
+    Ok(true)
 }
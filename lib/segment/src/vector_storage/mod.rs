@@ -1,4 +1,6 @@
+pub mod batch_scorer;
 pub mod chunked_vectors;
+pub mod encrypted_vector_storage;
 pub mod memmap_vector_storage;
 mod mmap_vectors;
 pub mod quantized;
@@ -50,6 +50,10 @@ where
         (point as usize) < self.deleted.len() && !self.deleted[point as usize]
     }
 
+    fn is_deleted(&self, point: PointOffsetType) -> bool {
+        (point as usize) < self.deleted.len() && self.deleted[point as usize]
+    }
+
     fn score_point(&self, point: PointOffsetType) -> ScoreType {
         self.quantized_data.score_point(&self.query, point)
     }
@@ -1,13 +1,16 @@
 use std::marker::PhantomData;
+use std::ops::Range;
+use std::thread;
 
 use bitvec::vec::BitVec;
 
+use super::chunked_vectors::{aligned_vector, blocks_as_slice, AlignedBlock};
 use super::{ScoredPointOffset, VectorStorage, VectorStorageEnum};
 use crate::data_types::vectors::VectorElementType;
 use crate::spaces::metric::Metric;
 use crate::spaces::simple::{CosineMetric, DotProductMetric, EuclidMetric};
 use crate::spaces::tools::peek_top_largest_iterable;
-use crate::types::{Distance, PointOffsetType, ScoreType};
+use crate::types::{Distance, PointOffsetType, ScoreType, VectorLayout};
 
 /// Optimized scorer for multiple scoring requests comparing with a single query
 /// Holds current query and params, receives only subset of points to score
@@ -17,6 +20,9 @@ pub trait RawScorer {
     /// Return true if point satisfies current search context (exists and not deleted)
     fn check_point(&self, point: PointOffsetType) -> bool;
 
+    /// Return true if point exists, but is marked as deleted
+    fn is_deleted(&self, point: PointOffsetType) -> bool;
+
     /// Score stored vector with vector under the given index
     fn score_point(&self, point: PointOffsetType) -> ScoreType;
 
@@ -33,9 +39,53 @@ pub trait RawScorer {
     fn peek_top_all(&self, top: usize) -> Vec<ScoredPointOffset>;
 }
 
+/// Minimal number of points per thread, for which parallel scoring pays off
+pub const MIN_POINTS_PER_SEARCH_THREAD: usize = 16 * 1024;
+
+/// Top of `num_points` points, scored in chunks by up to `max_threads` scoped threads
+///
+/// `score_chunk` returns the top of the points at the given range of positions.
+/// It is called from multiple threads, so it should create its own [`RawScorer`] for each chunk.
+/// Tops of the chunks are merged, which gives the same result as scoring all points at once.
+pub fn peek_top_chunked<F>(
+    num_points: usize,
+    top: usize,
+    max_threads: usize,
+    score_chunk: F,
+) -> Vec<ScoredPointOffset>
+where
+    F: Fn(Range<usize>) -> Vec<ScoredPointOffset> + Sync,
+{
+    let num_chunks = max_threads
+        .min(num_points / MIN_POINTS_PER_SEARCH_THREAD)
+        .max(1);
+    if num_chunks == 1 {
+        return score_chunk(0..num_points);
+    }
+
+    let chunk_size = (num_points + num_chunks - 1) / num_chunks;
+    let chunk_tops: Vec<_> = thread::scope(|scope| {
+        let score_chunk = &score_chunk;
+        let handles: Vec<_> = (0..num_chunks)
+            .map(|chunk| {
+                let range = chunk * chunk_size..((chunk + 1) * chunk_size).min(num_points);
+                scope.spawn(move || score_chunk(range))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("search thread panicked"))
+            .collect()
+    });
+
+    peek_top_largest_iterable(chunk_tops.into_iter().flatten(), top)
+}
+
 pub struct RawScorerImpl<'a, TMetric: Metric, TVectorStorage: VectorStorage> {
     pub points_count: PointOffsetType,
     pub query: Vec<VectorElementType>,
+    /// Padded copy of the query, if the vectors of the storage are aligned
+    pub padded_query: Option<Vec<AlignedBlock>>,
     pub vector_storage: &'a TVectorStorage,
     pub deleted: &'a BitVec,
     pub metric: PhantomData<TMetric>,
@@ -53,6 +103,9 @@ pub fn new_raw_scorer<'a>(
         VectorStorageEnum::Memmap(vector_storage) => {
             raw_scorer_impl(vector, vector_storage.as_ref(), deleted)
         }
+        VectorStorageEnum::Encrypted(vector_storage) => {
+            raw_scorer_impl(vector, vector_storage.as_ref(), deleted)
+        }
     }
 }
 
@@ -61,29 +114,44 @@ fn raw_scorer_impl<'a, TVectorStorage: VectorStorage>(
     vector_storage: &'a TVectorStorage,
     deleted: &'a BitVec,
 ) -> Box<dyn RawScorer + 'a> {
-    let points_count = vector_storage.total_vector_count() as PointOffsetType;
     match vector_storage.distance() {
-        Distance::Cosine => Box::new(RawScorerImpl::<'a, CosineMetric, TVectorStorage> {
-            points_count,
-            query: CosineMetric::preprocess(&vector).unwrap_or(vector),
-            vector_storage,
-            deleted,
-            metric: PhantomData,
-        }),
-        Distance::Euclid => Box::new(RawScorerImpl::<'a, EuclidMetric, TVectorStorage> {
-            points_count,
-            query: EuclidMetric::preprocess(&vector).unwrap_or(vector),
-            vector_storage,
-            deleted,
-            metric: PhantomData,
-        }),
-        Distance::Dot => Box::new(RawScorerImpl::<'a, DotProductMetric, TVectorStorage> {
-            points_count,
-            query: DotProductMetric::preprocess(&vector).unwrap_or(vector),
-            vector_storage,
-            deleted,
-            metric: PhantomData,
-        }),
+        Distance::Cosine => metric_raw_scorer::<CosineMetric, _>(vector, vector_storage, deleted),
+        Distance::Euclid => metric_raw_scorer::<EuclidMetric, _>(vector, vector_storage, deleted),
+        Distance::Dot => metric_raw_scorer::<DotProductMetric, _>(vector, vector_storage, deleted),
+    }
+}
+
+fn metric_raw_scorer<'a, TMetric: Metric + 'a, TVectorStorage: VectorStorage>(
+    vector: Vec<VectorElementType>,
+    vector_storage: &'a TVectorStorage,
+    deleted: &'a BitVec,
+) -> Box<dyn RawScorer + 'a> {
+    let query = TMetric::preprocess(&vector).unwrap_or(vector);
+    let padded_query =
+        (vector_storage.vector_layout() == VectorLayout::Aligned).then(|| aligned_vector(&query));
+    Box::new(RawScorerImpl::<'a, TMetric, TVectorStorage> {
+        points_count: vector_storage.total_vector_count() as PointOffsetType,
+        query,
+        padded_query,
+        vector_storage,
+        deleted,
+        metric: PhantomData,
+    })
+}
+
+impl<'a, TMetric, TVectorStorage> RawScorerImpl<'a, TMetric, TVectorStorage>
+where
+    TMetric: Metric,
+    TVectorStorage: VectorStorage,
+{
+    /// Similarity of the query with the stored vector, using the aligned kernels if the vectors are aligned
+    fn score_stored(&self, point: PointOffsetType) -> ScoreType {
+        if let Some(padded_query) = &self.padded_query {
+            if let Some(other_vector) = self.vector_storage.get_padded_vector(point) {
+                return TMetric::similarity_aligned(blocks_as_slice(padded_query), other_vector);
+            }
+        }
+        TMetric::similarity(&self.query, self.vector_storage.get_vector(point))
     }
 }
 
@@ -98,10 +166,9 @@ where
             if !self.check_point(point_id) {
                 continue;
             }
-            let other_vector = self.vector_storage.get_vector(point_id);
             scores[size] = ScoredPointOffset {
                 idx: point_id,
-                score: TMetric::similarity(&self.query, other_vector),
+                score: self.score_stored(point_id),
             };
 
             size += 1;
@@ -118,12 +185,23 @@ where
             && !self.deleted[point as usize]
     }
 
+    fn is_deleted(&self, point: PointOffsetType) -> bool {
+        point < self.points_count
+            && (point as usize) < self.deleted.len()
+            && self.deleted[point as usize]
+    }
+
     fn score_point(&self, point: PointOffsetType) -> ScoreType {
-        let other_vector = self.vector_storage.get_vector(point);
-        TMetric::similarity(&self.query, other_vector)
+        self.score_stored(point)
     }
 
     fn score_internal(&self, point_a: PointOffsetType, point_b: PointOffsetType) -> ScoreType {
+        if let (Some(vector_a), Some(vector_b)) = (
+            self.vector_storage.get_padded_vector(point_a),
+            self.vector_storage.get_padded_vector(point_b),
+        ) {
+            return TMetric::similarity_aligned(vector_a, vector_b);
+        }
         let vector_a = self.vector_storage.get_vector(point_a);
         let vector_b = self.vector_storage.get_vector(point_b);
         TMetric::similarity(vector_a, vector_b)
@@ -136,12 +214,9 @@ where
     ) -> Vec<ScoredPointOffset> {
         let scores = points
             .filter(|point_id| self.check_point(*point_id))
-            .map(|point_id| {
-                let other_vector = self.vector_storage.get_vector(point_id);
-                ScoredPointOffset {
-                    idx: point_id,
-                    score: TMetric::similarity(&self.query, other_vector),
-                }
+            .map(|point_id| ScoredPointOffset {
+                idx: point_id,
+                score: self.score_stored(point_id),
             });
         peek_top_largest_iterable(scores, top)
     }
@@ -149,14 +224,134 @@ where
     fn peek_top_all(&self, top: usize) -> Vec<ScoredPointOffset> {
         let scores = (0..self.points_count)
             .filter(|point_id| self.check_point(*point_id))
-            .map(|point_id| {
-                let point_id = point_id as PointOffsetType;
-                let other_vector = &self.vector_storage.get_vector(point_id);
-                ScoredPointOffset {
-                    idx: point_id,
-                    score: TMetric::similarity(&self.query, other_vector),
-                }
+            .map(|point_id| ScoredPointOffset {
+                idx: point_id,
+                score: self.score_stored(point_id),
             });
         peek_top_largest_iterable(scores, top)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
+    use crate::fixtures::payload_context_fixture::FixtureIdTracker;
+    use crate::fixtures::payload_fixtures::random_vector;
+    use crate::id_tracker::IdTracker;
+    use crate::vector_storage::simple_vector_storage::open_simple_vector_storage;
+
+    #[test]
+    fn test_peek_top_chunked() {
+        let num_points = MIN_POINTS_PER_SEARCH_THREAD * 4 + 123;
+        let dim = 8;
+        let top = 10;
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+        let storage = open_simple_vector_storage(
+            db,
+            DB_VECTOR_CF,
+            dim,
+            Distance::Dot,
+            None,
+            Default::default(),
+        )
+        .unwrap();
+        let mut id_tracker = FixtureIdTracker::new(num_points);
+        {
+            let mut storage = storage.write();
+            for idx in 0..num_points {
+                let vector = random_vector(&mut rng, dim);
+                storage
+                    .insert_vector(idx as PointOffsetType, &vector)
+                    .unwrap();
+            }
+        }
+        for idx in (0..num_points).step_by(7) {
+            id_tracker.drop((idx as u64).into()).unwrap();
+        }
+
+        let storage = storage.read();
+        let deleted = id_tracker.deleted_bitvec();
+        let subset: Vec<PointOffsetType> = (0..num_points as PointOffsetType).step_by(3).collect();
+
+        for _ in 0..5 {
+            let query = random_vector(&mut rng, dim);
+            let scorer = || new_raw_scorer(query.clone(), &storage, deleted);
+
+            let expected_all = scorer().peek_top_all(top);
+            let expected_subset = scorer().peek_top_iter(&mut subset.iter().copied(), top);
+
+            for max_threads in [1, 2, 3, 8] {
+                let all = peek_top_chunked(num_points, top, max_threads, |range| {
+                    let mut points = range.start as PointOffsetType..range.end as PointOffsetType;
+                    scorer().peek_top_iter(&mut points, top)
+                });
+                assert_eq!(all, expected_all);
+
+                let subset_top = peek_top_chunked(subset.len(), top, max_threads, |range| {
+                    scorer().peek_top_iter(&mut subset[range].iter().copied(), top)
+                });
+                assert_eq!(subset_top, expected_subset);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aligned_layout_scores() {
+        let num_points = 500;
+        let dim = 37;
+        let top = 10;
+        let mut rng = StdRng::seed_from_u64(42);
+        let vectors: Vec<_> = (0..num_points)
+            .map(|_| random_vector(&mut rng, dim))
+            .collect();
+        let id_tracker = FixtureIdTracker::new(num_points);
+        let deleted = id_tracker.deleted_bitvec();
+
+        for distance in [Distance::Cosine, Distance::Euclid, Distance::Dot] {
+            let storages: Vec<_> = [VectorLayout::Packed, VectorLayout::Aligned]
+                .into_iter()
+                .map(|layout| {
+                    let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+                    let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+                    let storage =
+                        open_simple_vector_storage(db, DB_VECTOR_CF, dim, distance, None, layout)
+                            .unwrap();
+                    for (idx, vector) in vectors.iter().enumerate() {
+                        storage
+                            .write()
+                            .insert_vector(idx as PointOffsetType, vector)
+                            .unwrap();
+                    }
+                    assert_eq!(storage.read().vector_layout(), layout);
+                    (dir, storage)
+                })
+                .collect();
+            let packed = storages[0].1.read();
+            let aligned = storages[1].1.read();
+            assert_eq!(aligned.get_vector(3), vectors[3].as_slice());
+            assert_eq!(aligned.get_padded_vector(3).unwrap().len(), 40);
+
+            let query = random_vector(&mut rng, dim);
+            let packed_top = new_raw_scorer(query.clone(), &packed, deleted).peek_top_all(top);
+            let aligned_scorer = new_raw_scorer(query, &aligned, deleted);
+            let aligned_top = aligned_scorer.peek_top_all(top);
+            assert_eq!(packed_top.len(), aligned_top.len());
+            for (packed, aligned) in packed_top.iter().zip(&aligned_top) {
+                assert_eq!(packed.idx, aligned.idx);
+                assert!((packed.score - aligned.score).abs() < 1e-4);
+            }
+
+            let packed_scorer = new_raw_scorer(vectors[0].clone(), &packed, deleted);
+            let internal = aligned_scorer.score_internal(1, 2);
+            assert!((internal - packed_scorer.score_internal(1, 2)).abs() < 1e-4);
+        }
+    }
+}
@@ -4,20 +4,22 @@ use std::path::Path;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
 use log::debug;
 use parking_lot::RwLock;
 use rocksdb::DB;
 use serde::{Deserialize, Serialize};
 
-use super::chunked_vectors::ChunkedVectors;
+use super::chunked_vectors::{AlignedChunkedVectors, ChunkedVectors};
 use super::vector_storage_base::VectorStorage;
 use super::VectorStorageEnum;
+use crate::common::encryption::SegmentCipher;
 use crate::common::rocksdb_wrapper::DatabaseColumnWrapper;
 use crate::common::Flusher;
 use crate::data_types::vectors::VectorElementType;
-use crate::entry::entry_point::{check_process_stopped, OperationError, OperationResult};
-use crate::types::{Distance, PointOffsetType, QuantizationConfig};
+use crate::entry::entry_point::{
+    check_points_capacity, check_process_stopped, OperationError, OperationResult,
+};
+use crate::types::{Distance, PointOffsetType, QuantizationConfig, VectorLayout};
 use crate::vector_storage::quantized::quantized_vectors_base::{
     QuantizedVectors, QuantizedVectorsStorage,
 };
@@ -26,12 +28,62 @@ use crate::vector_storage::quantized::quantized_vectors_base::{
 pub struct SimpleVectorStorage {
     dim: usize,
     distance: Distance,
-    vectors: ChunkedVectors<VectorElementType>,
+    vectors: InMemoryVectors,
     quantized_vectors: Option<QuantizedVectorsStorage>,
     db_wrapper: DatabaseColumnWrapper,
     update_buffer: StoredRecord,
 }
 
+/// Vectors of the storage in the configured layout
+enum InMemoryVectors {
+    Packed(ChunkedVectors<VectorElementType>),
+    Aligned(AlignedChunkedVectors),
+}
+
+impl InMemoryVectors {
+    fn new(dim: usize, layout: VectorLayout) -> Self {
+        match layout {
+            VectorLayout::Packed => InMemoryVectors::Packed(ChunkedVectors::new(dim)),
+            VectorLayout::Aligned => InMemoryVectors::Aligned(AlignedChunkedVectors::new(dim)),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            InMemoryVectors::Packed(vectors) => vectors.len(),
+            InMemoryVectors::Aligned(vectors) => vectors.len(),
+        }
+    }
+
+    fn get(&self, key: PointOffsetType) -> &[VectorElementType] {
+        match self {
+            InMemoryVectors::Packed(vectors) => vectors.get(key),
+            InMemoryVectors::Aligned(vectors) => vectors.get(key),
+        }
+    }
+
+    fn get_padded(&self, key: PointOffsetType) -> Option<&[VectorElementType]> {
+        match self {
+            InMemoryVectors::Packed(_) => None,
+            InMemoryVectors::Aligned(vectors) => Some(vectors.get_padded(key)),
+        }
+    }
+
+    fn push(&mut self, vector: &[VectorElementType]) -> PointOffsetType {
+        match self {
+            InMemoryVectors::Packed(vectors) => vectors.push(vector),
+            InMemoryVectors::Aligned(vectors) => vectors.push(vector),
+        }
+    }
+
+    fn insert(&mut self, key: PointOffsetType, vector: &[VectorElementType]) {
+        match self {
+            InMemoryVectors::Packed(vectors) => vectors.insert(key, vector),
+            InMemoryVectors::Aligned(vectors) => vectors.insert(key, vector),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct StoredRecord {
     pub deleted: bool,
@@ -43,13 +95,16 @@ pub fn open_simple_vector_storage(
     database_column_name: &str,
     dim: usize,
     distance: Distance,
-) -> OperationResult<Arc<AtomicRefCell<VectorStorageEnum>>> {
-    let mut vectors = ChunkedVectors::new(dim);
+    cipher: Option<Arc<SegmentCipher>>,
+    layout: VectorLayout,
+) -> OperationResult<Arc<RwLock<VectorStorageEnum>>> {
+    let mut vectors = InMemoryVectors::new(dim, layout);
 
-    let db_wrapper = DatabaseColumnWrapper::new(database, database_column_name);
+    let db_wrapper = DatabaseColumnWrapper::new(database, database_column_name).with_cipher(cipher);
     for (key, value) in db_wrapper.lock_db().iter()? {
         let point_id: PointOffsetType = bincode::deserialize(&key)
             .map_err(|_| OperationError::service_error("cannot deserialize point id from db"))?;
+        let value = db_wrapper.decrypt_value(&key, &value)?;
         let stored_record: StoredRecord = bincode::deserialize(&value)
             .map_err(|_| OperationError::service_error("cannot deserialize record from db"))?;
         vectors.insert(point_id, &stored_record.vector);
@@ -61,7 +116,7 @@ pub fn open_simple_vector_storage(
         vectors.len() * dim * size_of::<VectorElementType>() / 1024 / 1024
     );
 
-    Ok(Arc::new(AtomicRefCell::new(VectorStorageEnum::Simple(
+    Ok(Arc::new(RwLock::new(VectorStorageEnum::Simple(
         SimpleVectorStorage {
             dim,
             distance,
@@ -108,11 +163,23 @@ impl VectorStorage for SimpleVectorStorage {
         self.vectors.get(key)
     }
 
+    fn vector_layout(&self) -> VectorLayout {
+        match self.vectors {
+            InMemoryVectors::Packed(_) => VectorLayout::Packed,
+            InMemoryVectors::Aligned(_) => VectorLayout::Aligned,
+        }
+    }
+
+    fn get_padded_vector(&self, key: PointOffsetType) -> Option<&[VectorElementType]> {
+        self.vectors.get_padded(key)
+    }
+
     fn insert_vector(
         &mut self,
         key: PointOffsetType,
         vector: &[VectorElementType],
     ) -> OperationResult<()> {
+        check_points_capacity(key as usize + 1)?;
         self.vectors.insert(key, vector);
         self.update_stored(key, vector)?;
         Ok(())
@@ -128,6 +195,7 @@ impl VectorStorage for SimpleVectorStorage {
         for point_id in other_ids {
             check_process_stopped(stopped)?;
             // Do not perform preprocessing - vectors should be already processed
+            check_points_capacity(self.vectors.len() + 1)?;
             let other_vector = other.get_vector(point_id);
             let new_id = self.vectors.push(other_vector);
             self.update_stored(new_id, other_vector)?;
@@ -187,9 +255,37 @@ mod tests {
     use crate::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
     use crate::fixtures::payload_context_fixture::FixtureIdTracker;
     use crate::id_tracker::{IdTracker, IdTrackerSS};
-    use crate::types::{PointIdType, ScalarQuantizationConfig};
+    use crate::types::{PointIdType, ScalarQuantizationConfig, MAX_SEGMENT_POINTS};
     use crate::vector_storage::{new_raw_scorer, ScoredPointOffset};
 
+    #[test]
+    fn test_insert_vector_over_capacity() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+        let storage = open_simple_vector_storage(
+            db,
+            DB_VECTOR_CF,
+            2,
+            Distance::Dot,
+            None,
+            Default::default(),
+        )
+        .unwrap();
+        let mut borrowed_storage = storage.write();
+
+        // The last offset is reserved, so the number of the vectors fits into the offset type
+        let result = borrowed_storage.insert_vector(PointOffsetType::MAX, &[1.0, 0.0]);
+        assert!(matches!(
+            result,
+            Err(OperationError::CapacityExceeded { .. })
+        ));
+        assert_eq!(borrowed_storage.total_vector_count(), 0);
+        assert_eq!(
+            check_points_capacity(MAX_SEGMENT_POINTS).unwrap(),
+            PointOffsetType::MAX
+        );
+    }
+
     #[test]
     fn test_score_points() {
         let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
@@ -203,11 +299,13 @@ mod tests {
             vec![1.0, 1.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
         ];
-        let id_tracker: Arc<AtomicRefCell<IdTrackerSS>> =
-            Arc::new(AtomicRefCell::new(FixtureIdTracker::new(points.len())));
-        let storage = open_simple_vector_storage(db, DB_VECTOR_CF, dim, distance).unwrap();
-        let mut borrowed_id_tracker = id_tracker.borrow_mut();
-        let mut borrowed_storage = storage.borrow_mut();
+        let id_tracker: Arc<RwLock<IdTrackerSS>> =
+            Arc::new(RwLock::new(FixtureIdTracker::new(points.len())));
+        let storage =
+            open_simple_vector_storage(db, DB_VECTOR_CF, dim, distance, None, Default::default())
+                .unwrap();
+        let mut borrowed_id_tracker = id_tracker.write();
+        let mut borrowed_storage = storage.write();
 
         for (i, vec) in points.iter().enumerate() {
             borrowed_storage
@@ -288,10 +386,12 @@ mod tests {
             vec![1.0, 1.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
         ];
-        let id_tracker = Arc::new(AtomicRefCell::new(FixtureIdTracker::new(points.len())));
-        let storage = open_simple_vector_storage(db, DB_VECTOR_CF, dim, distance).unwrap();
-        let mut borrowed_storage = storage.borrow_mut();
-        let borrowed_id_tracker = id_tracker.borrow_mut();
+        let id_tracker = Arc::new(RwLock::new(FixtureIdTracker::new(points.len())));
+        let storage =
+            open_simple_vector_storage(db, DB_VECTOR_CF, dim, distance, None, Default::default())
+                .unwrap();
+        let mut borrowed_storage = storage.write();
+        let borrowed_id_tracker = id_tracker.write();
 
         for (i, vec) in points.iter().enumerate() {
             borrowed_storage
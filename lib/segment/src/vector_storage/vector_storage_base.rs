@@ -5,25 +5,37 @@ use std::sync::atomic::AtomicBool;
 
 use ordered_float::OrderedFloat;
 
+use super::encrypted_vector_storage::EncryptedVectorStorage;
 use super::memmap_vector_storage::MemmapVectorStorage;
 use super::quantized::quantized_vectors_base::QuantizedVectorsStorage;
 use super::simple_vector_storage::SimpleVectorStorage;
 use crate::common::Flusher;
 use crate::data_types::vectors::VectorElementType;
-use crate::entry::entry_point::OperationResult;
-use crate::types::{Distance, PointOffsetType, QuantizationConfig, ScoreType};
+use crate::entry::entry_point::{check_points_capacity, OperationResult};
+use crate::types::{Distance, PointOffsetType, QuantizationConfig, ScoreType, VectorLayout};
 
-#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default)]
 pub struct ScoredPointOffset {
     pub idx: PointOffsetType,
     pub score: ScoreType,
 }
 
+impl PartialEq for ScoredPointOffset {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
 impl Eq for ScoredPointOffset {}
 
+/// Points are ordered by score, and points with the same score - by id, the smaller id is the larger point
+///
+/// So the top of the points doesn't depend on the order, they are scored in.
 impl Ord for ScoredPointOffset {
     fn cmp(&self, other: &Self) -> Ordering {
-        OrderedFloat(self.score).cmp(&OrderedFloat(other.score))
+        OrderedFloat(self.score)
+            .cmp(&OrderedFloat(other.score))
+            .then_with(|| other.idx.cmp(&self.idx))
     }
 }
 
@@ -47,12 +59,42 @@ pub trait VectorStorage {
     /// Number of all stored vectors including deleted
     fn get_vector(&self, key: PointOffsetType) -> &[VectorElementType];
 
+    /// Layout of the stored vectors
+    fn vector_layout(&self) -> VectorLayout {
+        VectorLayout::Packed
+    }
+
+    /// Vector with the zero padding to the multiple of 32 bytes, its start is aligned to 32 bytes
+    ///
+    /// None, if the storage doesn't have the [`VectorLayout::Aligned`] layout.
+    fn get_padded_vector(&self, _key: PointOffsetType) -> Option<&[VectorElementType]> {
+        None
+    }
+
     fn insert_vector(
         &mut self,
         key: PointOffsetType,
         vector: &[VectorElementType],
     ) -> OperationResult<()>;
 
+    /// Append vectors to the end of the storage, returns offsets of the appended vectors
+    ///
+    /// If writing fails halfway, vectors written so far are kept. They are not linked to any
+    /// point yet, so the segment treats them as deleted.
+    /// Vectors, which don't fit into the offsets, are rejected before writing.
+    fn put_vectors(
+        &mut self,
+        vectors: &[Vec<VectorElementType>],
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let total_vector_count = self.total_vector_count();
+        let end = check_points_capacity(total_vector_count + vectors.len())?;
+        let start = total_vector_count as PointOffsetType;
+        for (offset, vector) in (start..).zip(vectors) {
+            self.insert_vector(offset, vector)?;
+        }
+        Ok(start..end)
+    }
+
     fn update_from(
         &mut self,
         other: &VectorStorageEnum,
@@ -80,6 +122,7 @@ pub trait VectorStorage {
 pub enum VectorStorageEnum {
     Simple(SimpleVectorStorage),
     Memmap(Box<MemmapVectorStorage>),
+    Encrypted(Box<EncryptedVectorStorage>),
 }
 
 impl VectorStorage for VectorStorageEnum {
@@ -87,6 +130,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.vector_dim(),
             VectorStorageEnum::Memmap(v) => v.vector_dim(),
+            VectorStorageEnum::Encrypted(v) => v.vector_dim(),
         }
     }
 
@@ -94,6 +138,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.distance(),
             VectorStorageEnum::Memmap(v) => v.distance(),
+            VectorStorageEnum::Encrypted(v) => v.distance(),
         }
     }
 
@@ -101,6 +146,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.total_vector_count(),
             VectorStorageEnum::Memmap(v) => v.total_vector_count(),
+            VectorStorageEnum::Encrypted(v) => v.total_vector_count(),
         }
     }
 
@@ -108,6 +154,23 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.get_vector(key),
             VectorStorageEnum::Memmap(v) => v.get_vector(key),
+            VectorStorageEnum::Encrypted(v) => v.get_vector(key),
+        }
+    }
+
+    fn vector_layout(&self) -> VectorLayout {
+        match self {
+            VectorStorageEnum::Simple(v) => v.vector_layout(),
+            VectorStorageEnum::Memmap(v) => v.vector_layout(),
+            VectorStorageEnum::Encrypted(v) => v.vector_layout(),
+        }
+    }
+
+    fn get_padded_vector(&self, key: PointOffsetType) -> Option<&[VectorElementType]> {
+        match self {
+            VectorStorageEnum::Simple(v) => v.get_padded_vector(key),
+            VectorStorageEnum::Memmap(v) => v.get_padded_vector(key),
+            VectorStorageEnum::Encrypted(v) => v.get_padded_vector(key),
         }
     }
 
@@ -119,6 +182,18 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.insert_vector(key, vector),
             VectorStorageEnum::Memmap(v) => v.insert_vector(key, vector),
+            VectorStorageEnum::Encrypted(v) => v.insert_vector(key, vector),
+        }
+    }
+
+    fn put_vectors(
+        &mut self,
+        vectors: &[Vec<VectorElementType>],
+    ) -> OperationResult<Range<PointOffsetType>> {
+        match self {
+            VectorStorageEnum::Simple(v) => v.put_vectors(vectors),
+            VectorStorageEnum::Memmap(v) => v.put_vectors(vectors),
+            VectorStorageEnum::Encrypted(v) => v.put_vectors(vectors),
         }
     }
 
@@ -131,6 +206,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.update_from(other, other_ids, stopped),
             VectorStorageEnum::Memmap(v) => v.update_from(other, other_ids, stopped),
+            VectorStorageEnum::Encrypted(v) => v.update_from(other, other_ids, stopped),
         }
     }
 
@@ -138,6 +214,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.flusher(),
             VectorStorageEnum::Memmap(v) => v.flusher(),
+            VectorStorageEnum::Encrypted(v) => v.flusher(),
         }
     }
 
@@ -149,6 +226,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.quantize(data_path, quantization_config),
             VectorStorageEnum::Memmap(v) => v.quantize(data_path, quantization_config),
+            VectorStorageEnum::Encrypted(v) => v.quantize(data_path, quantization_config),
         }
     }
 
@@ -156,6 +234,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.load_quantization(data_path),
             VectorStorageEnum::Memmap(v) => v.load_quantization(data_path),
+            VectorStorageEnum::Encrypted(v) => v.load_quantization(data_path),
         }
     }
 
@@ -163,6 +242,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.quantized_storage(),
             VectorStorageEnum::Memmap(v) => v.quantized_storage(),
+            VectorStorageEnum::Encrypted(v) => v.quantized_storage(),
         }
     }
 
@@ -170,6 +250,114 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::Simple(v) => v.files(),
             VectorStorageEnum::Memmap(v) => v.files(),
+            VectorStorageEnum::Encrypted(v) => v.files(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::entry::entry_point::OperationError;
+    use crate::types::MAX_SEGMENT_POINTS;
+
+    /// Storage, which pretends to hold `base` vectors, so the offsets near the limit are checked
+    /// without storing billions of vectors
+    struct BoundaryVectorStorage {
+        base: usize,
+        vectors: HashMap<PointOffsetType, Vec<VectorElementType>>,
+    }
+
+    impl VectorStorage for BoundaryVectorStorage {
+        fn vector_dim(&self) -> usize {
+            2
+        }
+
+        fn distance(&self) -> Distance {
+            Distance::Dot
+        }
+
+        fn total_vector_count(&self) -> usize {
+            self.base + self.vectors.len()
+        }
+
+        fn get_vector(&self, key: PointOffsetType) -> &[VectorElementType] {
+            &self.vectors[&key]
+        }
+
+        fn insert_vector(
+            &mut self,
+            key: PointOffsetType,
+            vector: &[VectorElementType],
+        ) -> OperationResult<()> {
+            self.vectors.insert(key, vector.to_vec());
+            Ok(())
+        }
+
+        fn update_from(
+            &mut self,
+            _other: &VectorStorageEnum,
+            _other_ids: &mut dyn Iterator<Item = PointOffsetType>,
+            _stopped: &AtomicBool,
+        ) -> OperationResult<Range<PointOffsetType>> {
+            unimplemented!()
         }
+
+        fn flusher(&self) -> Flusher {
+            Box::new(|| Ok(()))
+        }
+
+        fn quantize(
+            &mut self,
+            _data_path: &Path,
+            _quantization_config: &QuantizationConfig,
+        ) -> OperationResult<()> {
+            unimplemented!()
+        }
+
+        fn load_quantization(&mut self, _data_path: &Path) -> OperationResult<()> {
+            Ok(())
+        }
+
+        fn quantized_storage(&self) -> Option<&QuantizedVectorsStorage> {
+            None
+        }
+
+        fn files(&self) -> Vec<PathBuf> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn test_put_vectors_near_capacity() {
+        let mut storage = BoundaryVectorStorage {
+            base: MAX_SEGMENT_POINTS - 2,
+            vectors: Default::default(),
+        };
+        let vectors = vec![vec![1.0, 0.0]; 3];
+
+        // Vectors, which don't fit, are rejected before writing
+        assert!(matches!(
+            storage.put_vectors(&vectors),
+            Err(OperationError::CapacityExceeded {
+                capacity: MAX_SEGMENT_POINTS
+            })
+        ));
+        assert!(storage.vectors.is_empty());
+
+        let offsets = storage.put_vectors(&vectors[..2]).unwrap();
+        assert_eq!(
+            offsets,
+            (MAX_SEGMENT_POINTS - 2) as PointOffsetType..PointOffsetType::MAX
+        );
+        assert_eq!(storage.total_vector_count(), MAX_SEGMENT_POINTS);
+        assert!(matches!(
+            storage.put_vectors(&vectors[..1]),
+            Err(OperationError::CapacityExceeded { .. })
+        ));
+        assert!(storage.vectors.contains_key(&(PointOffsetType::MAX - 1)));
+        assert!(!storage.vectors.contains_key(&PointOffsetType::MAX));
     }
 }
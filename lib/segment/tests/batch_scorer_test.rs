@@ -0,0 +1,255 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use segment::data_types::vectors::{
+        only_default_vector, VectorElementType, DEFAULT_VECTOR_NAME,
+    };
+    use segment::entry::entry_point::{OperationError, OperationResult, SegmentEntry};
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::index::hnsw_index::graph_links::GraphLinksRam;
+    use segment::index::hnsw_index::hnsw::HNSWIndex;
+    use segment::index::VectorIndex;
+    use segment::segment::Segment;
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Condition, Distance, Filter, HnswConfig, Indexes, PointOffsetType,
+        QuantizationSearchParams, ScalarQuantizationConfig, ScoreType, SearchParams, SegmentConfig,
+        SeqNumberType, StorageType, VectorDataConfig,
+    };
+    use segment::vector_storage::batch_scorer::BatchScorerProvider;
+    use segment::vector_storage::{ScoredPointOffset, VectorStorage, VectorStorageEnum};
+    use tempfile::{Builder, TempDir};
+
+    const DIM: usize = 16;
+    const NUM_VECTORS: u64 = 1_000;
+    const SHIFT: ScoreType = 100.0;
+
+    #[derive(Clone, Copy)]
+    enum Mode {
+        Score,
+        Decline,
+        Fail,
+    }
+
+    /// Scores exactly, but shifted by [`SHIFT`], so its scores are told from the CPU ones
+    struct ShiftedScorer {
+        mode: Mode,
+        blocks: AtomicUsize,
+    }
+
+    impl ShiftedScorer {
+        fn new(mode: Mode) -> Arc<Self> {
+            Arc::new(Self {
+                mode,
+                blocks: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    impl BatchScorerProvider for ShiftedScorer {
+        fn score_block(
+            &self,
+            query: &[VectorElementType],
+            vector_storage: &VectorStorageEnum,
+            offsets: &[PointOffsetType],
+        ) -> OperationResult<Option<Vec<ScoreType>>> {
+            self.blocks.fetch_add(1, Ordering::Relaxed);
+            match self.mode {
+                Mode::Score => Ok(Some(
+                    offsets
+                        .iter()
+                        .map(|&offset| {
+                            let vector = vector_storage.get_vector(offset);
+                            let dot: ScoreType = query.iter().zip(vector).map(|(a, b)| a * b).sum();
+                            dot + SHIFT
+                        })
+                        .collect(),
+                )),
+                Mode::Decline => Ok(None),
+                Mode::Fail => Err(OperationError::service_error("accelerator is unavailable")),
+            }
+        }
+    }
+
+    fn plain_segment(rnd: &mut StdRng) -> (TempDir, Segment) {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..NUM_VECTORS {
+            let vector = random_vector(rnd, DIM);
+            segment
+                .upsert_vector(n as SeqNumberType, n.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        (dir, segment)
+    }
+
+    fn assert_shifted(
+        result: &[ScoredPointOffset],
+        expected: &[ScoredPointOffset],
+        shift: ScoreType,
+    ) {
+        assert_eq!(result.len(), expected.len());
+        for (point, expected_point) in result.iter().zip(expected) {
+            assert_eq!(point.idx, expected_point.idx);
+            assert!(
+                (point.score - expected_point.score - shift).abs() < 1e-3,
+                "{point:?} is not {expected_point:?} shifted by {shift}",
+            );
+        }
+    }
+
+    #[test]
+    fn test_plain_search_with_batch_scorer() {
+        let top = 10;
+        let mut rnd = StdRng::seed_from_u64(42);
+        let (_dir, mut segment) = plain_segment(&mut rnd);
+
+        let query = random_vector(&mut rnd, DIM);
+        let filter = Filter::new_must(Condition::HasId(
+            (0..NUM_VECTORS / 2)
+                .map(Into::into)
+                .collect::<HashSet<_>>()
+                .into(),
+        ));
+        let search = |segment: &Segment, filter: Option<&Filter>| {
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_index
+                .read()
+                .search(&[query.as_slice()], filter, top, None)
+                .pop()
+                .unwrap()
+        };
+        let cpu_result = search(&segment, None);
+        let cpu_filtered_result = search(&segment, Some(&filter));
+
+        let batch_scorer = ShiftedScorer::new(Mode::Score);
+        segment.set_batch_scorer(Some(batch_scorer.clone()));
+        assert_shifted(&search(&segment, None), &cpu_result, SHIFT);
+        assert_shifted(
+            &search(&segment, Some(&filter)),
+            &cpu_filtered_result,
+            SHIFT,
+        );
+        assert_eq!(batch_scorer.blocks.load(Ordering::Relaxed), 2);
+
+        // Deleted points are skipped, though the provider scores them
+        let best_point_id = segment
+            .id_tracker
+            .read()
+            .external_id(cpu_result[0].idx)
+            .unwrap();
+        segment.delete_point(NUM_VECTORS, best_point_id).unwrap();
+        assert_shifted(&search(&segment, None)[..top - 1], &cpu_result[1..], SHIFT);
+
+        // Declined and failed blocks are scored on CPU
+        for mode in [Mode::Decline, Mode::Fail] {
+            let batch_scorer = ShiftedScorer::new(mode);
+            segment.set_batch_scorer(Some(batch_scorer.clone()));
+            assert_shifted(&search(&segment, None)[..top - 1], &cpu_result[1..], 0.0);
+            assert_eq!(batch_scorer.blocks.load(Ordering::Relaxed), 1);
+        }
+
+        segment.set_batch_scorer(None);
+        assert_shifted(&search(&segment, None)[..top - 1], &cpu_result[1..], 0.0);
+    }
+
+    #[test]
+    fn test_rescoring_with_batch_scorer() {
+        let top = 10;
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+        let (dir, segment) = plain_segment(&mut rnd);
+
+        segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_storage
+            .write()
+            .quantize(
+                dir.path(),
+                &ScalarQuantizationConfig {
+                    r#type: Default::default(),
+                    quantile: None,
+                    always_ram: None,
+                }
+                .into(),
+            )
+            .unwrap();
+
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
+            hnsw_dir.path(),
+            segment.id_tracker.clone(),
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .clone(),
+            segment.payload_index.clone(),
+            HnswConfig {
+                m: 16,
+                ef_construct: 64,
+                full_scan_threshold: 1,
+                max_indexing_threads: 2,
+                on_disk: Some(false),
+                payload_m: None,
+                neighbor_selection: None,
+                seed: None,
+                incremental: None,
+                ef_policy: None,
+                max_build_memory_mb: None,
+            },
+        )
+        .unwrap();
+        hnsw_index.build_index(&stopped).unwrap();
+
+        let query = random_vector(&mut rnd, DIM);
+        let search = |hnsw_index: &HNSWIndex<GraphLinksRam>, rescore: bool| {
+            hnsw_index
+                .search(
+                    &[query.as_slice()],
+                    None,
+                    top,
+                    Some(&SearchParams {
+                        quantization: Some(QuantizationSearchParams {
+                            ignore: false,
+                            rescore,
+                        }),
+                        ..Default::default()
+                    }),
+                )
+                .pop()
+                .unwrap()
+        };
+        let cpu_rescored = search(&hnsw_index, true);
+
+        let batch_scorer = ShiftedScorer::new(Mode::Score);
+        hnsw_index.set_batch_scorer(Some(batch_scorer.clone()));
+        // Graph is searched with quantized vectors, only the found points are offered
+        assert_shifted(&search(&hnsw_index, true), &cpu_rescored, SHIFT);
+        assert_eq!(batch_scorer.blocks.load(Ordering::Relaxed), 1);
+        assert!(search(&hnsw_index, false)
+            .iter()
+            .all(|point| point.score < SHIFT));
+        assert_eq!(batch_scorer.blocks.load(Ordering::Relaxed), 1);
+
+        hnsw_index.set_batch_scorer(Some(ShiftedScorer::new(Mode::Fail)));
+        assert_shifted(&search(&hnsw_index, true), &cpu_rescored, 0.0);
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::index::hnsw_index::graph_links::GraphLinksRam;
+    use segment::index::hnsw_index::hnsw::HNSWIndex;
+    use segment::index::{PayloadIndex, VectorIndex};
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Condition, Distance, FieldCondition, Filter, HnswConfig, Indexes, Payload,
+        PayloadSchemaType, Range, SearchParams, SegmentConfig, StorageType, VectorDataConfig,
+        WithPayload,
+    };
+    use serde_json::json;
+    use tempfile::Builder;
+
+    const DIM: usize = 16;
+    const NUM_POINTS: u64 = 3_000;
+    const NUM_QUERIES: usize = 64;
+    const TOP: usize = 10;
+
+    fn filters() -> Vec<Option<Filter>> {
+        vec![
+            None,
+            // Small cardinality, candidates are scored without the graph
+            Some(Filter::new_must(Condition::Field(
+                FieldCondition::new_range(
+                    "num".to_owned(),
+                    Range {
+                        lt: Some(2.0),
+                        gt: None,
+                        gte: None,
+                        lte: None,
+                    },
+                ),
+            ))),
+            // Large cardinality, graph is searched with the filter
+            Some(Filter::new_must(Condition::Field(
+                FieldCondition::new_range(
+                    "num".to_owned(),
+                    Range {
+                        lt: Some(80.0),
+                        gt: None,
+                        gte: None,
+                        lte: None,
+                    },
+                ),
+            ))),
+        ]
+    }
+
+    #[test]
+    fn test_parallel_batch_search_matches_sequential() {
+        let mut rnd = StdRng::seed_from_u64(42);
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            max_search_threads: Some(4),
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..NUM_POINTS {
+            segment
+                .upsert_vector(
+                    n,
+                    n.into(),
+                    &only_default_vector(&random_vector(&mut rnd, DIM)),
+                )
+                .unwrap();
+            let payload: Payload = json!({ "num": rnd.gen_range(0..100) }).into();
+            segment.set_full_payload(n, n.into(), &payload).unwrap();
+        }
+        segment
+            .payload_index
+            .write()
+            .set_indexed("num", PayloadSchemaType::Integer.into())
+            .unwrap();
+
+        let queries: Vec<_> = (0..NUM_QUERIES)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let query_refs: Vec<_> = queries.iter().map(|query| query.as_slice()).collect();
+
+        // Plain index of the segment, batch vs the queries one by one
+        for filter in filters() {
+            let batch = segment
+                .search_batch(
+                    DEFAULT_VECTOR_NAME,
+                    &query_refs,
+                    &WithPayload::from(true),
+                    &false.into(),
+                    filter.as_ref(),
+                    TOP,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(batch.len(), NUM_QUERIES);
+            for (query, batch_result) in queries.iter().zip(batch) {
+                let single = segment
+                    .search(
+                        DEFAULT_VECTOR_NAME,
+                        query,
+                        &WithPayload::from(true),
+                        &false.into(),
+                        filter.as_ref(),
+                        TOP,
+                        None,
+                    )
+                    .unwrap();
+                assert_eq!(batch_result, single);
+                for (batch_point, single_point) in batch_result.iter().zip(&single) {
+                    assert_eq!(batch_point.payload, single_point.payload);
+                }
+            }
+        }
+
+        let hnsw_config = HnswConfig {
+            m: 8,
+            ef_construct: 64,
+            full_scan_threshold: 16, // KB
+            max_indexing_threads: 2,
+            on_disk: Some(false),
+            payload_m: None,
+            neighbor_selection: None,
+            seed: Some(42),
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        };
+        let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
+            hnsw_dir.path(),
+            segment.id_tracker.clone(),
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .clone(),
+            segment.payload_index.clone(),
+            hnsw_config,
+        )
+        .unwrap();
+        hnsw_index.build_index(&AtomicBool::new(false)).unwrap();
+
+        let exact_params = SearchParams {
+            exact: true,
+            ..Default::default()
+        };
+        for params in [None, Some(&exact_params)] {
+            for filter in filters() {
+                hnsw_index.set_search_threads(Some(4));
+                let batch = hnsw_index.search(&query_refs, filter.as_ref(), TOP, params);
+                hnsw_index.set_search_threads(None);
+                let sequential: Vec<_> = query_refs
+                    .iter()
+                    .map(|query| hnsw_index.search(&[*query], filter.as_ref(), TOP, params))
+                    .map(|mut result| result.pop().unwrap())
+                    .collect();
+                assert_eq!(batch, sequential);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::{OperationError, SegmentEntry};
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::index::hnsw_index::graph_links::GraphLinksMmap;
+    use segment::index::hnsw_index::hnsw::HNSWIndex;
+    use segment::index::VectorIndex;
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Distance, HnswConfig, Indexes, SegmentConfig, SeqNumberType, StorageType, VectorDataConfig,
+    };
+    use tempfile::Builder;
+
+    const DIM: usize = 16;
+    const NUM_VECTORS: u64 = 3_000;
+    const MB: usize = 1024 * 1024;
+
+    #[test]
+    fn test_build_memory_budget() {
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let larger_budget_hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..NUM_VECTORS {
+            let vector = random_vector(&mut rnd, DIM);
+            segment
+                .upsert_vector(n as SeqNumberType, n.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let info = segment.info();
+        assert!(info.vector_indexes[DEFAULT_VECTOR_NAME]
+            .build_memory_estimate
+            .is_none());
+
+        let open_index = |path: &Path, max_build_memory_mb: Option<usize>| {
+            let hnsw_config = HnswConfig {
+                m: 64,
+                ef_construct: 64,
+                full_scan_threshold: 16, // KB
+                max_indexing_threads: 2,
+                on_disk: Some(true),
+                payload_m: None,
+                neighbor_selection: None,
+                seed: None,
+                incremental: None,
+                ef_policy: None,
+                max_build_memory_mb,
+            };
+            HNSWIndex::<GraphLinksMmap>::open(
+                path,
+                segment.id_tracker.clone(),
+                segment.vector_data[DEFAULT_VECTOR_NAME]
+                    .vector_storage
+                    .clone(),
+                segment.payload_index.clone(),
+                hnsw_config,
+            )
+            .unwrap()
+        };
+
+        // Build is refused before it starts
+        let mut hnsw_index = open_index(hnsw_dir.path(), Some(1));
+        let estimate = hnsw_index.estimate_build_memory();
+        // Links of the on-disk index are read from the file
+        assert_eq!(estimate.converted_bytes, 0);
+        assert!(estimate.peak_bytes > MB);
+        match hnsw_index.build_index(&stopped) {
+            Err(OperationError::MemoryBudgetExceeded {
+                estimated_bytes,
+                budget_bytes,
+            }) => {
+                assert_eq!(estimated_bytes, estimate.peak_bytes);
+                assert_eq!(budget_bytes, MB);
+            }
+            result => panic!("Expected the budget to be exceeded, got {result:?}"),
+        }
+        assert!(hnsw_index.graph().is_none());
+
+        // Build within the budget writes the links in slices
+        let mut hnsw_index = open_index(
+            larger_budget_hnsw_dir.path(),
+            Some(estimate.peak_bytes / MB + 1),
+        );
+        assert_eq!(hnsw_index.estimate_build_memory(), estimate);
+        hnsw_index.build_index(&stopped).unwrap();
+
+        let query = random_vector(&mut rnd, DIM);
+        let found = hnsw_index.search(&[&query], None, 10, None);
+        assert_eq!(found[0].len(), 10);
+    }
+}
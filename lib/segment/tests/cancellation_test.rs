@@ -0,0 +1,192 @@
+mod fixtures;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::{OperationError, SegmentEntry};
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::id_tracker::IdTracker;
+    use segment::index::PayloadIndex;
+    use segment::segment::Segment;
+    use segment::segment_constructor::segment_builder::SegmentBuilder;
+    use segment::segment_constructor::{build_segment, get_vector_index_rebuild_path};
+    use segment::types::{
+        Distance, HnswConfig, Indexes, Payload, PointIdType, SearchParams, SegmentConfig,
+        VectorDataConfig, WithPayload,
+    };
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use crate::fixtures::segment::{build_segment_1, build_segment_2};
+
+    const DIM: usize = 32;
+    const NUM_VECTORS: u64 = 5_000;
+
+    fn exact_search(segment: &Segment, query: &[f32]) -> Vec<PointIdType> {
+        segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                query,
+                &WithPayload::default(),
+                &false.into(),
+                None,
+                10,
+                Some(&SearchParams {
+                    exact: true,
+                    ..Default::default()
+                }),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|point| point.id)
+            .collect()
+    }
+
+    /// Set the flag from another thread, while the operation is running
+    fn stop_after(stopped: &Arc<AtomicBool>, millis: u64) {
+        let stopped = stopped.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(millis));
+            stopped.store(true, Ordering::Relaxed);
+        });
+    }
+
+    fn assert_cancelled<T>(result: Result<T, OperationError>) {
+        assert!(matches!(result, Err(OperationError::Cancelled { .. })));
+    }
+
+    #[test]
+    fn test_cancel_vector_index_rebuild() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let mut rnd = StdRng::seed_from_u64(42);
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Hnsw(HnswConfig {
+                full_scan_threshold: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..NUM_VECTORS {
+            let vector = random_vector(&mut rnd, DIM);
+            segment
+                .upsert_vector(n, n.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        let query = random_vector(&mut rnd, DIM);
+        let expected = exact_search(&segment, &query);
+        let version = segment.version();
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        stop_after(&stopped, 10);
+        assert_cancelled(segment.rebuild_vector_index(
+            NUM_VECTORS,
+            DEFAULT_VECTOR_NAME,
+            Some(&stopped),
+        ));
+
+        // Half-built index is removed, the current one keeps serving searches
+        let rebuild_path =
+            get_vector_index_rebuild_path(&segment.current_path, DEFAULT_VECTOR_NAME);
+        assert!(!rebuild_path.exists());
+        assert!(segment.check_error().is_none());
+        assert_eq!(segment.version(), version);
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
+        assert_eq!(exact_search(&segment, &query), expected);
+    }
+
+    #[test]
+    fn test_cancel_merge() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+
+        let segment1 = build_segment_1(dir.path());
+        let mut segment2 = build_segment_2(dir.path());
+        // Deleted points are dropped by the merge, like by the vacuum
+        segment2.delete_point(20, 11.into()).unwrap();
+        let query = [1.0, 0.0, 1.0, 1.0];
+        let expected = [
+            exact_search(&segment1, &query),
+            exact_search(&segment2, &query),
+        ];
+
+        let stopped = AtomicBool::new(false);
+        let mut builder =
+            SegmentBuilder::new(dir.path(), temp_dir.path(), &segment1.segment_config).unwrap();
+        builder.update_from(&segment1, &stopped).unwrap();
+        stopped.store(true, Ordering::Relaxed);
+        assert_cancelled(builder.update_from(&segment2, &stopped));
+        drop(builder);
+
+        // Partially merged segment is removed
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+
+        // Cancelled build is removed as well
+        let stopped = AtomicBool::new(false);
+        let mut builder =
+            SegmentBuilder::new(dir.path(), temp_dir.path(), &segment1.segment_config).unwrap();
+        builder.update_from(&segment1, &stopped).unwrap();
+        builder.update_from(&segment2, &stopped).unwrap();
+        stopped.store(true, Ordering::Relaxed);
+        assert_cancelled(builder.build(&stopped));
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+        assert_eq!(dir.path().read_dir().unwrap().count(), 2);
+
+        for (segment, expected) in [&segment1, &segment2].into_iter().zip(expected) {
+            assert!(segment.find_orphans(false, None).unwrap().is_empty());
+            assert_eq!(exact_search(segment, &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_cancel_consistency_check() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let mut segment = build_segment_1(dir.path());
+        let query = [1.0, 0.0, 1.0, 1.0];
+        let expected = exact_search(&segment, &query);
+
+        // Payload of the deleted point is left in the storage
+        let internal_id = segment.id_tracker.read().internal_id(5.into()).unwrap();
+        segment.delete_point(10, 5.into()).unwrap();
+        let payload: Payload = json!({ "color": "red" }).into();
+        segment
+            .payload_index
+            .write()
+            .assign(internal_id, &payload)
+            .unwrap();
+
+        let stopped = AtomicBool::new(true);
+        assert_cancelled(segment.find_orphans(true, Some(&stopped)));
+        assert_cancelled(segment.check_consistency_and_repair(Some(&stopped)));
+
+        // Nothing is repaired by the cancelled check
+        let report = segment.find_orphans(false, None).unwrap();
+        assert_eq!(report.payloads_without_mapping, 1);
+        assert!(segment.find_orphans(true, None).unwrap().repaired);
+        assert!(segment.find_orphans(false, None).unwrap().is_empty());
+        assert_eq!(
+            exact_search(&segment, &query),
+            expected
+                .into_iter()
+                .filter(|id| *id != 5.into())
+                .collect::<Vec<_>>()
+        );
+    }
+}
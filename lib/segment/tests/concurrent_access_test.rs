@@ -0,0 +1,215 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use parking_lot::RwLock;
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::index::VectorIndex;
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Distance, Filter, Indexes, Payload, PayloadStorageType, SegmentConfig, StorageType,
+        VectorDataConfig, WithPayload,
+    };
+    use segment::vector_storage::VectorStorage;
+    use serde_json::json;
+    use tempfile::Builder;
+
+    const DIM: usize = 4;
+    const NUM_POINTS: u64 = 100;
+    const STRESS_DURATION: Duration = Duration::from_secs(3);
+
+    /// Vector of the point, written by the iteration of the writer
+    fn vector(point_id: u64, iteration: u64) -> Vec<f32> {
+        vec![iteration as f32, point_id as f32, 1.0, 1.0]
+    }
+
+    #[test]
+    fn test_concurrent_reads_and_writes() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            payload_storage_type: PayloadStorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for point_id in 0..NUM_POINTS {
+            segment
+                .upsert_vector(
+                    0,
+                    point_id.into(),
+                    &only_default_vector(&vector(point_id, 0)),
+                )
+                .unwrap();
+        }
+        let vector_storage = segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_storage
+            .clone();
+        let vector_index = segment.vector_data[DEFAULT_VECTOR_NAME]
+            .vector_index
+            .clone();
+        let id_tracker = segment.id_tracker.clone();
+        let segment = Arc::new(RwLock::new(segment));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let writer = {
+            let segment = segment.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                let mut iteration = 0;
+                while !stopped.load(Ordering::Relaxed) {
+                    iteration += 1;
+                    for point_id in 0..NUM_POINTS {
+                        let mut segment = segment.write();
+                        let op_num = iteration * NUM_POINTS + point_id;
+                        segment
+                            .upsert_vector(
+                                op_num,
+                                point_id.into(),
+                                &only_default_vector(&vector(point_id, iteration)),
+                            )
+                            .unwrap();
+                        let payload: Payload = json!({ "iteration": iteration }).into();
+                        segment
+                            .set_full_payload(op_num, point_id.into(), &payload)
+                            .unwrap();
+                    }
+                }
+                iteration
+            })
+        };
+
+        // Readers through the segment, the iteration of each point never goes back
+        let segment_readers: Vec<_> = (0..3)
+            .map(|_| {
+                let segment = segment.clone();
+                let stopped = stopped.clone();
+                thread::spawn(move || {
+                    let mut seen = vec![0; NUM_POINTS as usize];
+                    while !stopped.load(Ordering::Relaxed) {
+                        let segment = segment.read();
+                        let found = segment
+                            .search(
+                                DEFAULT_VECTOR_NAME,
+                                &[1.0, 0.0, 0.0, 0.0],
+                                &WithPayload::from(true),
+                                &false.into(),
+                                None,
+                                5,
+                                None,
+                            )
+                            .unwrap();
+                        assert_eq!(found.len(), 5);
+                        assert_eq!(segment.iter_points().count(), NUM_POINTS as usize);
+                        for point_id in 0..NUM_POINTS {
+                            let stored = segment
+                                .vector(DEFAULT_VECTOR_NAME, point_id.into())
+                                .unwrap();
+                            let iteration = stored[0] as u64;
+                            assert_eq!(stored, vector(point_id, iteration));
+                            assert!(iteration >= seen[point_id as usize]);
+                            seen[point_id as usize] = iteration;
+                            // Payload is set along with the vector
+                            let payload = segment.payload(point_id.into()).unwrap();
+                            let payload_iteration =
+                                payload.0.get("iteration").and_then(|value| value.as_u64());
+                            assert_eq!(payload_iteration.unwrap_or(0), iteration);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Readers of the storages, shared with the segment, while it is updated
+        let storage_readers: Vec<_> = (0..2)
+            .map(|_| {
+                let vector_storage = vector_storage.clone();
+                let id_tracker = id_tracker.clone();
+                let stopped = stopped.clone();
+                thread::spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        // Locks are not nested, so they are taken in any order
+                        let internal_ids: Vec<_> = id_tracker.read().iter_ids().collect();
+                        let vector_storage = vector_storage.read();
+                        for internal_id in internal_ids {
+                            let vector = vector_storage.get_vector(internal_id);
+                            assert_eq!(vector.len(), DIM);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Searches through the vector index, shared with the segment, take the locks of the storages inside
+        let index_readers: Vec<_> = (0..2)
+            .map(|_| {
+                let vector_index = vector_index.clone();
+                let stopped = stopped.clone();
+                thread::spawn(move || {
+                    let filter: Filter = serde_json::from_value(json!({
+                        "must": [{ "has_id": (0..10).collect::<Vec<u64>>() }]
+                    }))
+                    .unwrap();
+                    while !stopped.load(Ordering::Relaxed) {
+                        let query: &[f32] = &[1.0, 0.0, 0.0, 0.0];
+                        for filter in [None, Some(&filter)] {
+                            let found = vector_index.read().search(&[query], filter, 5, None);
+                            assert_eq!(found.len(), 1);
+                            assert_eq!(found[0].len(), 5);
+                            assert!(found[0]
+                                .windows(2)
+                                .all(|hits| hits[0].score >= hits[1].score));
+                            if filter.is_some() {
+                                assert!(found[0].iter().all(|hit| hit.idx < 10));
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let start = Instant::now();
+        while start.elapsed() < STRESS_DURATION {
+            thread::sleep(Duration::from_millis(100));
+        }
+        stopped.store(true, Ordering::Relaxed);
+
+        let last_iteration = writer.join().unwrap();
+        for reader in segment_readers
+            .into_iter()
+            .chain(storage_readers)
+            .chain(index_readers)
+        {
+            reader.join().unwrap();
+        }
+        assert!(last_iteration > 0);
+
+        // No update is lost
+        let segment = segment.read();
+        assert_eq!(segment.points_count(), NUM_POINTS as usize);
+        for point_id in 0..NUM_POINTS {
+            assert_eq!(
+                segment
+                    .vector(DEFAULT_VECTOR_NAME, point_id.into())
+                    .unwrap(),
+                vector(point_id, last_iteration)
+            );
+            let expected: Payload = json!({ "iteration": last_iteration }).into();
+            assert_eq!(*segment.payload(point_id.into()).unwrap(), expected);
+        }
+    }
+}
@@ -14,8 +14,8 @@ mod tests {
     use segment::segment_constructor::build_segment;
     use segment::types::{
         Condition, Distance, FieldCondition, Filter, HnswConfig, Indexes, Payload,
-        PayloadSchemaType, PointOffsetType, Range, SearchParams, SegmentConfig, SeqNumberType,
-        StorageType, VectorDataConfig,
+        PayloadSchemaType, PointIdType, PointOffsetType, Range, SearchParams, SegmentConfig,
+        SeqNumberType, StorageType, VectorDataConfig, WithPayload,
     };
     use serde_json::json;
     use tempfile::Builder;
@@ -82,6 +82,11 @@ mod tests {
             max_indexing_threads: 2,
             on_disk: Some(false),
             payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
         };
 
         let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
@@ -98,10 +103,10 @@ mod tests {
         hnsw_index.build_index(&stopped).unwrap();
 
         payload_index_ptr
-            .borrow_mut()
+            .write()
             .set_indexed(int_key, PayloadSchemaType::Integer.into())
             .unwrap();
-        let borrowed_payload_index = payload_index_ptr.borrow();
+        let borrowed_payload_index = payload_index_ptr.read();
         let blocks = borrowed_payload_index
             .payload_blocks(int_key, indexing_threshold)
             .collect_vec();
@@ -114,7 +119,7 @@ mod tests {
 
         let mut coverage: HashMap<PointOffsetType, usize> = Default::default();
         for block in &blocks {
-            let px = payload_index_ptr.borrow();
+            let px = payload_index_ptr.read();
             let filter = Filter::new_must(Condition::Field(block.condition.clone()));
             let points = px.query_points(&filter);
             for point in points {
@@ -154,7 +159,7 @@ mod tests {
             );
             let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
                 .vector_index
-                .borrow()
+                .read()
                 .search(&[&query], None, top, None);
 
             assert_eq!(
@@ -189,7 +194,7 @@ mod tests {
             );
             let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
                 .vector_index
-                .borrow()
+                .read()
                 .search(&[&query], filter_query, top, None);
 
             assert_eq!(
@@ -198,4 +203,103 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn plain_search_brute_force_test() {
+        let dim = 4;
+        let num_vectors: u64 = 200;
+        let int_key = "int";
+
+        let mut rnd = thread_rng();
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        let mut points: HashMap<PointIdType, (Vec<f32>, u64)> = Default::default();
+        for n in 0..num_vectors {
+            let idx = n.into();
+            let vector = random_vector(&mut rnd, dim);
+            let payload: Payload = json!({ int_key: n % 10 }).into();
+
+            segment
+                .upsert_vector(n as SeqNumberType, idx, &only_default_vector(&vector))
+                .unwrap();
+            segment
+                .set_full_payload(n as SeqNumberType, idx, &payload)
+                .unwrap();
+            points.insert(idx, (vector, n % 10));
+        }
+
+        let mut op_num = num_vectors;
+        for n in (0..num_vectors).step_by(7) {
+            let point_id: PointIdType = n.into();
+            segment.delete_point(op_num, point_id.clone()).unwrap();
+            points.remove(&point_id);
+            op_num += 1;
+        }
+
+        let filter = Filter::new_must(Condition::Field(FieldCondition::new_range(
+            int_key.to_owned(),
+            Range {
+                lt: Some(3.0),
+                gt: None,
+                gte: None,
+                lte: None,
+            },
+        )));
+
+        for _ in 0..10 {
+            let query = random_vector(&mut rnd, dim);
+            for (filter, top) in [(None, 5), (Some(&filter), 5), (Some(&filter), 1000)] {
+                let mut expected: Vec<(PointIdType, f32)> = points
+                    .iter()
+                    .filter(|(_, (_, value))| filter.is_none() || *value < 3)
+                    .map(|(id, (vector, _))| {
+                        let score: f32 = vector.iter().zip(&query).map(|(a, b)| a * b).sum();
+                        (id.clone(), score)
+                    })
+                    .collect();
+                expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+                expected.truncate(top);
+
+                for exact in [false, true] {
+                    let result = segment
+                        .search(
+                            DEFAULT_VECTOR_NAME,
+                            &query,
+                            &WithPayload::default(),
+                            &false.into(),
+                            filter,
+                            top,
+                            Some(&SearchParams {
+                                exact,
+                                ..Default::default()
+                            }),
+                        )
+                        .unwrap();
+
+                    assert_eq!(result.len(), expected.len());
+                    for (point, (id, score)) in result.iter().zip(&expected) {
+                        assert_eq!(point.id, *id);
+                        assert!((point.score - score).abs() < 1e-4);
+                    }
+                }
+            }
+        }
+    }
 }
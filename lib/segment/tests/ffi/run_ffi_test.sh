@@ -0,0 +1,27 @@
+#!/usr/bin/env bash
+# Build the segment as a shared library, and run the C test program against it
+#
+# If cbindgen is installed, the header is checked to be up to date with src/ffi.rs.
+
+set -euo pipefail
+
+SEGMENT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/../.." && pwd)"
+TARGET_DIR="$(cd "$SEGMENT_DIR/../.." && pwd)/target"
+WORK_DIR="$(mktemp -d)"
+trap 'rm -rf "$WORK_DIR"' EXIT
+
+cd "$SEGMENT_DIR"
+
+cargo rustc -p segment --features ffi --lib --crate-type cdylib
+
+if command -v cbindgen > /dev/null; then
+    cbindgen --config cbindgen.toml --crate segment --output "$WORK_DIR/segment_ffi.h"
+    diff -u include/segment_ffi.h "$WORK_DIR/segment_ffi.h"
+fi
+
+"${CC:-cc}" -Wall -Werror -I include tests/ffi/segment_ffi_test.c \
+    -L "$TARGET_DIR/debug" -lsegment -o "$WORK_DIR/segment_ffi_test"
+
+mkdir "$WORK_DIR/segments"
+LD_LIBRARY_PATH="$TARGET_DIR/debug" DYLD_LIBRARY_PATH="$TARGET_DIR/debug" \
+    "$WORK_DIR/segment_ffi_test" "$WORK_DIR/segments"
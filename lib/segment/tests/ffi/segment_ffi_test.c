@@ -0,0 +1,78 @@
+/*
+ * Exercises the C interface of the segment through the generated header, see run_ffi_test.sh
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "segment_ffi.h"
+
+#define DIM 4
+#define TOP 3
+
+static const char *CONFIG =
+    "{\"vector_data\": {\"\": {\"size\": 4, \"distance\": \"Dot\"}},"
+    " \"index\": {\"type\": \"plain\", \"options\": {}},"
+    " \"storage_type\": {\"type\": \"in_memory\"}}";
+
+#define CHECK(call)                                                                    \
+    do {                                                                               \
+        SegmentStatus status = (call);                                                 \
+        if (status != SEGMENT_STATUS_OK) {                                             \
+            fprintf(stderr, "%s:%d: %s failed with %d: %s\n", __FILE__, __LINE__, #call, \
+                    status, segment_last_error_message());                             \
+            return 1;                                                                  \
+        }                                                                              \
+    } while (0)
+
+#define EXPECT(condition)                                                              \
+    do {                                                                               \
+        if (!(condition)) {                                                            \
+            fprintf(stderr, "%s:%d: expected %s\n", __FILE__, __LINE__, #condition);   \
+            return 1;                                                                  \
+        }                                                                              \
+    } while (0)
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <segments dir>\n", argv[0]);
+        return 2;
+    }
+
+    SegmentHandle *segment = NULL;
+    CHECK(segment_create(argv[1], CONFIG, &segment));
+
+    const float vectors[][DIM] = {
+        {1.0f, 0.0f, 0.0f, 0.0f},
+        {0.0f, 1.0f, 0.0f, 0.0f},
+        {1.0f, 1.0f, 0.0f, 0.0f},
+        {0.0f, 0.0f, 1.0f, 1.0f},
+    };
+    for (uint64_t id = 0; id < 4; id++) {
+        CHECK(segment_upsert(segment, id + 1, vectors[id], DIM));
+    }
+    CHECK(segment_flush(segment));
+
+    const float query[DIM] = {1.0f, 0.5f, 0.0f, 0.0f};
+    uint64_t ids[TOP];
+    float scores[TOP];
+    size_t found = 0;
+    CHECK(segment_search(segment, query, DIM, TOP, ids, scores, &found));
+    EXPECT(found == TOP);
+    EXPECT(ids[0] == 3 && scores[0] == 1.5f);
+    EXPECT(ids[1] == 1 && scores[1] == 1.0f);
+    EXPECT(ids[2] == 2 && scores[2] == 0.5f);
+
+    /* Errors are returned as codes, with the message */
+    const float short_vector[2] = {1.0f, 0.0f};
+    EXPECT(segment_upsert(segment, 5, short_vector, 2) == SEGMENT_STATUS_INVALID_ARGUMENT);
+    EXPECT(segment_last_error_message() != NULL);
+    EXPECT(strstr(segment_last_error_message(), "expected dim") != NULL);
+    EXPECT(segment_flush(NULL) == SEGMENT_STATUS_INVALID_ARGUMENT);
+    EXPECT(segment_open(NULL, &segment) == SEGMENT_STATUS_INVALID_ARGUMENT);
+
+    segment_free(segment);
+    segment_free(NULL);
+    printf("ffi test passed\n");
+    return 0;
+}
@@ -0,0 +1,112 @@
+#![cfg(feature = "ffi")]
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use segment::ffi::{
+        segment_create, segment_flush, segment_free, segment_last_error_message, segment_open,
+        segment_search, segment_upsert, SegmentHandle, SegmentStatus,
+    };
+    use tempfile::Builder;
+
+    const CONFIG: &str = r#"{
+        "vector_data": {"": {"size": 4, "distance": "Dot"}},
+        "index": {"type": "plain", "options": {}},
+        "storage_type": {"type": "in_memory"}
+    }"#;
+
+    fn last_error_message() -> String {
+        let message = segment_last_error_message();
+        assert!(!message.is_null());
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Ids and scores of the found points
+    unsafe fn search(handle: *const SegmentHandle, query: &[f32], top: usize) -> Vec<(u64, f32)> {
+        let mut ids = vec![0; top];
+        let mut scores = vec![0.0; top];
+        let mut found = 0;
+        let status = segment_search(
+            handle,
+            query.as_ptr(),
+            query.len(),
+            top,
+            ids.as_mut_ptr(),
+            scores.as_mut_ptr(),
+            &mut found,
+        );
+        assert_eq!(status, SegmentStatus::Ok);
+        ids.into_iter().zip(scores).take(found).collect()
+    }
+
+    #[test]
+    fn test_ffi_upsert_and_search() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let config = CString::new(CONFIG).unwrap();
+
+        unsafe {
+            let mut handle: *mut SegmentHandle = ptr::null_mut();
+            assert_eq!(
+                segment_create(path.as_ptr(), config.as_ptr(), &mut handle),
+                SegmentStatus::Ok
+            );
+            let vectors = [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0, 0.0],
+            ];
+            for (id, vector) in vectors.iter().enumerate() {
+                let status = segment_upsert(handle, id as u64 + 1, vector.as_ptr(), vector.len());
+                assert_eq!(status, SegmentStatus::Ok);
+            }
+            assert_eq!(segment_flush(handle), SegmentStatus::Ok);
+            let query = [1.0, 0.5, 0.0, 0.0];
+            assert_eq!(search(handle, &query, 2), vec![(3, 1.5), (1, 1.0)]);
+
+            // Errors are returned as codes, with the message of the thread
+            let short_vector = [1.0, 0.0];
+            assert_eq!(
+                segment_upsert(handle, 4, short_vector.as_ptr(), short_vector.len()),
+                SegmentStatus::InvalidArgument
+            );
+            assert!(last_error_message().contains("expected dim: 4, got 2"));
+            assert_eq!(
+                segment_flush(ptr::null_mut()),
+                SegmentStatus::InvalidArgument
+            );
+            assert_eq!(last_error_message(), "segment handle is null");
+            let malformed = CString::new("{").unwrap();
+            let mut other: *mut SegmentHandle = ptr::null_mut();
+            assert_eq!(
+                segment_create(path.as_ptr(), malformed.as_ptr(), &mut other),
+                SegmentStatus::InvalidArgument
+            );
+            assert!(other.is_null());
+            segment_free(handle);
+            segment_free(ptr::null_mut());
+
+            // Segment is opened again from its directory
+            let segment_dir = dir
+                .path()
+                .read_dir()
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap()
+                .path();
+            let segment_path = CString::new(segment_dir.to_str().unwrap()).unwrap();
+            let mut handle: *mut SegmentHandle = ptr::null_mut();
+            assert_eq!(
+                segment_open(segment_path.as_ptr(), &mut handle),
+                SegmentStatus::Ok
+            );
+            assert_eq!(search(handle, &query, 1), vec![(3, 1.5)]);
+            segment_free(handle);
+        }
+    }
+}
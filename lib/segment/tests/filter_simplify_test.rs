@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use segment::fixtures::payload_context_fixture::{
+        create_plain_payload_index, create_struct_payload_index,
+    };
+    use segment::fixtures::payload_fixtures::random_redundant_filter;
+    use segment::index::PayloadIndex;
+    use segment::types::{Filter, PointOffsetType};
+    use tempfile::Builder;
+
+    const NUM_POINTS: usize = 2000;
+    const ATTEMPTS: usize = 200;
+
+    fn matched_points(index: &impl PayloadIndex, filter: &Filter) -> Vec<usize> {
+        let filter_context = index.filter_context(filter);
+        (0..NUM_POINTS)
+            .filter(|point_id| filter_context.check(*point_id as PointOffsetType))
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_simplified_filter_consistency() {
+        let seed = 42;
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let plain_index = create_plain_payload_index(dir.path(), NUM_POINTS, seed);
+        let struct_index = create_struct_payload_index(dir.path(), NUM_POINTS, seed);
+
+        for _ in 0..ATTEMPTS {
+            let filter = random_redundant_filter(&mut rng, 3);
+            let simplified = filter.simplify();
+
+            assert_eq!(simplified, simplified.simplify(), "filter: {filter:#?}");
+
+            assert_eq!(
+                matched_points(&plain_index, &filter),
+                matched_points(&plain_index, &simplified),
+                "filter: {filter:#?}, simplified: {simplified:#?}"
+            );
+            assert_eq!(
+                matched_points(&struct_index, &filter),
+                matched_points(&struct_index, &simplified),
+                "filter: {filter:#?}, simplified: {simplified:#?}"
+            );
+        }
+    }
+}
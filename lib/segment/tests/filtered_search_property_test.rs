@@ -0,0 +1,85 @@
+#![cfg(feature = "testing")]
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::data_types::vectors::DEFAULT_VECTOR_NAME;
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::fixtures::test_utils::{
+        random_segment, reference_check, reference_search, FieldSpec, PayloadSpec,
+    };
+    use segment::types::{Distance, WithPayload};
+    use tempfile::Builder;
+
+    const DIM: usize = 8;
+    const NUM_POINTS: usize = 500;
+    const TOP: usize = 10;
+
+    fn payload_spec() -> PayloadSpec {
+        PayloadSpec {
+            fields: vec![
+                (
+                    "color".to_string(),
+                    FieldSpec::Keyword(
+                        ["red", "green", "blue", "yellow"]
+                            .map(String::from)
+                            .to_vec(),
+                    ),
+                ),
+                ("count".to_string(), FieldSpec::Integer(0..20)),
+                ("price".to_string(), FieldSpec::Float(0.0..100.0)),
+            ],
+            missing_probability: 0.1,
+        }
+    }
+
+    /// Filtered search with the payload index finds the same top, as the brute force over the payloads
+    #[test]
+    fn test_filtered_search_matches_reference() {
+        let payload_spec = payload_spec();
+        for seed in 0..5 {
+            let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+            let segment = random_segment(
+                dir.path(),
+                DIM,
+                Distance::Dot,
+                NUM_POINTS,
+                &payload_spec,
+                seed,
+            );
+            let mut rnd = StdRng::seed_from_u64(seed);
+
+            for _ in 0..20 {
+                let num_conditions = rnd.gen_range(1..=4);
+                let filter = payload_spec.random_filter(&mut rnd, num_conditions);
+                let query = random_vector(&mut rnd, DIM);
+
+                let found = segment
+                    .search(
+                        DEFAULT_VECTOR_NAME,
+                        &query,
+                        &WithPayload::default(),
+                        &false.into(),
+                        Some(&filter),
+                        TOP,
+                        None,
+                    )
+                    .unwrap();
+                let expected = reference_search(&segment, &query, Some(&filter), TOP);
+
+                assert_eq!(found.len(), expected.len(), "filter: {filter:?}");
+                for (point, (_, expected_score)) in found.iter().zip(&expected) {
+                    // Points with equal scores may be found in any order
+                    assert!(
+                        (point.score - expected_score).abs() < 1e-5,
+                        "filter: {filter:?}"
+                    );
+                    let payload = segment.payload(point.id.clone()).unwrap();
+                    assert!(reference_check(&filter, &payload), "filter: {filter:?}");
+                }
+            }
+        }
+    }
+}
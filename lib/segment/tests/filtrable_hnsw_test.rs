@@ -10,7 +10,8 @@ mod tests {
     use segment::entry::entry_point::SegmentEntry;
     use segment::fixtures::payload_fixtures::{random_int_payload, random_vector};
     use segment::index::hnsw_index::graph_links::GraphLinksRam;
-    use segment::index::hnsw_index::hnsw::HNSWIndex;
+    use segment::index::hnsw_index::hnsw::{FilteredSearchPlan, HNSWIndex};
+    use segment::index::query_plan::{PlanReason, SearchStrategy};
     use segment::index::{PayloadIndex, VectorIndex};
     use segment::segment_constructor::build_segment;
     use segment::types::{
@@ -83,6 +84,11 @@ mod tests {
             max_indexing_threads: 2,
             on_disk: Some(false),
             payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
         };
 
         let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
@@ -99,10 +105,10 @@ mod tests {
         hnsw_index.build_index(&stopped).unwrap();
 
         payload_index_ptr
-            .borrow_mut()
+            .write()
             .set_indexed(int_key, PayloadSchemaType::Integer.into())
             .unwrap();
-        let borrowed_payload_index = payload_index_ptr.borrow();
+        let borrowed_payload_index = payload_index_ptr.read();
         let blocks = borrowed_payload_index
             .payload_blocks(int_key, indexing_threshold)
             .collect_vec();
@@ -115,7 +121,7 @@ mod tests {
 
         let mut coverage: HashMap<PointOffsetType, usize> = Default::default();
         for block in &blocks {
-            let px = payload_index_ptr.borrow();
+            let px = payload_index_ptr.read();
             let filter = Filter::new_must(Condition::Field(block.condition.clone()));
             let points = px.query_points(&filter);
             for point in points {
@@ -173,7 +179,7 @@ mod tests {
 
             let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
                 .vector_index
-                .borrow()
+                .read()
                 .search(&[&query], filter_query, top, None);
 
             if plain_result.get(0).unwrap() == &index_result {
@@ -183,4 +189,343 @@ mod tests {
         assert!(attempts - hits < 5, "hits: {hits} of {attempts}"); // Not more than 5% failures
         eprintln!("hits = {hits:#?} out of {attempts}");
     }
+
+    #[test]
+    fn test_filtered_search_plan() {
+        let stopped = AtomicBool::new(false);
+
+        let dim = 8;
+        let num_vectors: u64 = 2_000;
+        let full_scan_threshold = 16; // KB
+        let indexing_threshold = full_scan_threshold * 1024 / (dim * 4); // num vectors
+        let top = 5;
+
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Euclid,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let int_key = "int";
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..num_vectors {
+            let idx = n.into();
+            let vector = random_vector(&mut rnd, dim);
+            let payload: Payload = json!({ int_key: n }).into();
+
+            segment
+                .upsert_vector(n as SeqNumberType, idx, &only_default_vector(&vector))
+                .unwrap();
+            segment
+                .set_full_payload(n as SeqNumberType, idx, &payload)
+                .unwrap();
+        }
+
+        let payload_index_ptr = segment.payload_index.clone();
+        payload_index_ptr
+            .write()
+            .set_indexed(int_key, PayloadSchemaType::Integer.into())
+            .unwrap();
+
+        let hnsw_config = HnswConfig {
+            m: 16,
+            ef_construct: 100,
+            full_scan_threshold,
+            max_indexing_threads: 2,
+            on_disk: Some(false),
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        };
+
+        let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
+            hnsw_dir.path(),
+            segment.id_tracker.clone(),
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .clone(),
+            payload_index_ptr,
+            hnsw_config,
+        )
+        .unwrap();
+        hnsw_index.build_index(&stopped).unwrap();
+
+        let telemetry = hnsw_index.get_telemetry_data();
+        assert_eq!(telemetry.indexing_threshold, Some(indexing_threshold));
+
+        let range_filter = |lte: usize| {
+            Filter::new_must(Condition::Field(FieldCondition::new_range(
+                int_key.to_owned(),
+                Range {
+                    lt: None,
+                    gt: None,
+                    gte: Some(0.0),
+                    lte: Some(lte as f64),
+                },
+            )))
+        };
+        let tiny_filter = range_filter(20);
+        let huge_filter = range_filter(num_vectors as usize);
+
+        assert_eq!(
+            hnsw_index.filtered_search_plan(&tiny_filter),
+            FilteredSearchPlan::Plain
+        );
+        assert_eq!(
+            hnsw_index.filtered_search_plan(&huge_filter),
+            FilteredSearchPlan::Graph
+        );
+
+        // Explanation follows the plan of the search
+        let explain_query = vec![0.0; dim];
+        let explain = |filter: Option<&Filter>, params: Option<&SearchParams>| {
+            hnsw_index.explain_search(&explain_query, filter, top, params)
+        };
+        let tiny_plan = explain(Some(&tiny_filter), None);
+        assert_eq!(tiny_plan.strategy, SearchStrategy::FullScan);
+        assert_eq!(tiny_plan.reason, PlanReason::SmallCardinality);
+        assert_eq!(tiny_plan.threshold, Some(indexing_threshold));
+        assert!(tiny_plan.filter_cardinality.unwrap().max < indexing_threshold);
+        assert_eq!(tiny_plan.ef, None);
+
+        let huge_plan = explain(Some(&huge_filter), None);
+        assert_eq!(huge_plan.strategy, SearchStrategy::Index);
+        assert_eq!(huge_plan.reason, PlanReason::LargeCardinality);
+        assert!(huge_plan.filter_cardinality.unwrap().min > indexing_threshold);
+        assert_eq!(huge_plan.ef, Some(hnsw_config.ef_construct));
+        assert!(!huge_plan.quantized);
+
+        // Plan flips once, as the filter gets less selective.
+        // Cardinalities are far from the threshold, so sampling is not ambiguous
+        let strategies: Vec<_> = [0, 100, 200, 1_000, 1_500, num_vectors as usize]
+            .into_iter()
+            .map(|lte| {
+                let filter = range_filter(lte);
+                let plan = explain(Some(&filter), None);
+                let expected_strategy = match hnsw_index.filtered_search_plan(&filter) {
+                    FilteredSearchPlan::Plain => SearchStrategy::FullScan,
+                    FilteredSearchPlan::Graph => SearchStrategy::Index,
+                };
+                assert_eq!(plan.strategy, expected_strategy, "lte: {lte}");
+                plan.strategy
+            })
+            .dedup()
+            .collect();
+        assert_eq!(
+            strategies,
+            vec![SearchStrategy::FullScan, SearchStrategy::Index]
+        );
+
+        let unfiltered_plan = explain(
+            None,
+            Some(&SearchParams {
+                hnsw_ef: Some(256),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(unfiltered_plan.reason, PlanReason::Unfiltered);
+        assert_eq!(unfiltered_plan.ef, Some(256));
+        assert_eq!(unfiltered_plan.filter_cardinality, None);
+        let exact_plan = explain(
+            Some(&huge_filter),
+            Some(&SearchParams {
+                exact: true,
+                ..Default::default()
+            }),
+        );
+        assert_eq!(exact_plan.strategy, SearchStrategy::FullScan);
+        assert_eq!(exact_plan.reason, PlanReason::Exact);
+        let json = serde_json::to_value(&tiny_plan).unwrap();
+        assert_eq!(json["strategy"], "full_scan");
+        assert_eq!(json["reason"], "small_cardinality");
+
+        let attempts = 20;
+        let mut hits = 0;
+        for _ in 0..attempts {
+            let query = random_vector(&mut rnd, dim);
+            let params = SearchParams {
+                hnsw_ef: Some(256),
+                ..Default::default()
+            };
+
+            let search = |filter: &Filter| {
+                let index_result = hnsw_index.search(&[&query], Some(filter), top, Some(&params));
+                let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
+                    .vector_index
+                    .read()
+                    .search(&[&query], Some(filter), top, None);
+                (index_result, plain_result)
+            };
+
+            // Plain plan is exhaustive, so it always gives exact results
+            let (index_result, plain_result) = search(&tiny_filter);
+            assert_eq!(index_result, plain_result);
+
+            let (index_result, plain_result) = search(&huge_filter);
+            if index_result == plain_result {
+                hits += 1;
+            }
+        }
+        assert!(attempts - hits <= 1, "hits: {hits} of {attempts}");
+
+        let telemetry = hnsw_index.get_telemetry_data();
+        assert_eq!(telemetry.filtered_small_cardinality.count, attempts);
+        assert_eq!(telemetry.filtered_large_cardinality.count, attempts);
+    }
+
+    #[test]
+    fn test_filtered_hnsw_recall_by_selectivity() {
+        let stopped = AtomicBool::new(false);
+
+        let dim = 8;
+        let m = 8;
+        let num_vectors: u64 = 5_000;
+        let ef = 64;
+        let top = 10;
+        let num_values = 100;
+
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: dim,
+                    distance: Distance::Cosine,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let int_key = "int";
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..num_vectors {
+            let idx = n.into();
+            let vector = random_vector(&mut rnd, dim);
+            let payload: Payload = json!({ int_key: rnd.gen_range(0..num_values) }).into();
+
+            segment
+                .upsert_vector(n as SeqNumberType, idx, &only_default_vector(&vector))
+                .unwrap();
+            segment
+                .set_full_payload(n as SeqNumberType, idx, &payload)
+                .unwrap();
+        }
+        segment
+            .payload_index
+            .write()
+            .set_indexed(int_key, PayloadSchemaType::Integer.into())
+            .unwrap();
+
+        // `payload_m = 0` disables additional links for payload blocks
+        let build_index = |payload_m: Option<usize>| {
+            let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+            let hnsw_config = HnswConfig {
+                m,
+                ef_construct: 16,
+                full_scan_threshold: 16, // KB
+                max_indexing_threads: 2,
+                on_disk: Some(false),
+                payload_m,
+                neighbor_selection: None,
+                seed: None,
+                incremental: None,
+                ef_policy: None,
+                max_build_memory_mb: None,
+            };
+            let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
+                hnsw_dir.path(),
+                segment.id_tracker.clone(),
+                segment.vector_data[DEFAULT_VECTOR_NAME]
+                    .vector_storage
+                    .clone(),
+                segment.payload_index.clone(),
+                hnsw_config,
+            )
+            .unwrap();
+            hnsw_index.build_index(&stopped).unwrap();
+            (hnsw_dir, hnsw_index)
+        };
+        let (_hnsw_dir, hnsw_index) = build_index(None);
+        let (_plain_hnsw_dir, no_payload_links_index) = build_index(Some(0));
+
+        let queries: Vec<_> = (0..50).map(|_| random_vector(&mut rnd, dim)).collect();
+
+        let recall = |hnsw_index: &HNSWIndex<GraphLinksRam>, filter: &Filter| {
+            let mut found = 0;
+            for query in &queries {
+                let index_result = hnsw_index.search_with_graph(
+                    query,
+                    Some(filter),
+                    top,
+                    Some(&SearchParams {
+                        hnsw_ef: Some(ef),
+                        ..Default::default()
+                    }),
+                );
+                let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
+                    .vector_index
+                    .read()
+                    .search(&[query.as_slice()], Some(filter), top, None);
+                let expected: Vec<_> = plain_result[0].iter().map(|point| point.idx).collect();
+                found += index_result
+                    .iter()
+                    .filter(|point| expected.contains(&point.idx))
+                    .count();
+            }
+            found as f64 / (queries.len() * top) as f64
+        };
+
+        // Share of matching points: 5%, 10%, 25%, 50%
+        for range_size in [5, 10, 25, 50] {
+            let filter = Filter::new_must(Condition::Field(FieldCondition::new_range(
+                int_key.to_owned(),
+                Range {
+                    lt: Some(range_size as f64),
+                    gt: None,
+                    gte: None,
+                    lte: None,
+                },
+            )));
+
+            let payload_links_recall = recall(&hnsw_index, &filter);
+            let base_recall = recall(&no_payload_links_index, &filter);
+            eprintln!(
+                "range_size = {range_size}, recall = {payload_links_recall}, without payload links = {base_recall}"
+            );
+            assert!(
+                payload_links_recall >= 0.9,
+                "range_size = {range_size}, recall = {payload_links_recall}"
+            );
+            if range_size == 5 {
+                // Graph is disconnected under selective filters without additional links
+                assert!(payload_links_recall > base_recall);
+            }
+        }
+    }
 }
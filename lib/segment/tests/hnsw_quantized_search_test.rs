@@ -68,7 +68,7 @@ mod tests {
         segment.vector_data.values_mut().for_each(|vector_storage| {
             vector_storage
                 .vector_storage
-                .borrow_mut()
+                .write()
                 .quantize(
                     quantized_data_path,
                     &ScalarQuantizationConfig {
@@ -88,6 +88,11 @@ mod tests {
             max_indexing_threads: 2,
             on_disk: Some(false),
             payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
         };
 
         let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
@@ -120,7 +125,7 @@ mod tests {
             );
             let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
                 .vector_index
-                .borrow()
+                .read()
                 .search(&[&query], None, top, None);
             sames += sames_count(&index_result, &plain_result);
         }
@@ -0,0 +1,637 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::index::hnsw_index::graph_layers::GraphLayers;
+    use segment::index::hnsw_index::graph_links::{GraphLinks, GraphLinksMmap, GraphLinksRam};
+    use segment::index::hnsw_index::hnsw::HNSWIndex;
+    use segment::index::VectorIndex;
+    use segment::segment::Segment;
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Distance, EfPolicy, HnswConfig, Indexes, NeighborSelection, PointOffsetType, SearchParams,
+        SegmentConfig, SeqNumberType, StorageType, VectorDataConfig,
+    };
+    use tempfile::{Builder, TempDir};
+
+    const DIM: usize = 16;
+    const NUM_VECTORS: u64 = 5_000;
+
+    /// Segment with random vectors and HNSW index built over them
+    ///
+    /// `num_deleted` random points are deleted before building the index.
+    fn random_segment_with_index(
+        m: usize,
+        ef_construct: usize,
+        num_deleted: usize,
+        rnd: &mut StdRng,
+    ) -> (TempDir, TempDir, Segment, HNSWIndex<GraphLinksRam>) {
+        let stopped = AtomicBool::new(false);
+
+        let vectors: Vec<_> = (0..NUM_VECTORS).map(|_| random_vector(rnd, DIM)).collect();
+        let (dir, mut segment) = plain_segment(&vectors);
+        for op_num in NUM_VECTORS..NUM_VECTORS + num_deleted as u64 {
+            let point_id = rnd.gen_range(0..NUM_VECTORS);
+            segment.delete_point(op_num, point_id.into()).unwrap();
+        }
+
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let mut hnsw_index = open_index(&segment, &hnsw_dir, hnsw_config(m, ef_construct));
+        hnsw_index.build_index(&stopped).unwrap();
+
+        (dir, hnsw_dir, segment, hnsw_index)
+    }
+
+    fn plain_segment(vectors: &[Vec<f32>]) -> (TempDir, Segment) {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Cosine,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for (n, vector) in vectors.iter().enumerate() {
+            let n = n as u64;
+            segment
+                .upsert_vector(n as SeqNumberType, n.into(), &only_default_vector(vector))
+                .unwrap();
+        }
+
+        (dir, segment)
+    }
+
+    /// Vectors, placed closely around random centers
+    fn clustered_vectors(rnd: &mut StdRng, centers: &[Vec<f32>], num: usize) -> Vec<Vec<f32>> {
+        (0..num)
+            .map(|_| {
+                let center = &centers[rnd.gen_range(0..centers.len())];
+                center
+                    .iter()
+                    .map(|x| x + rnd.gen_range(-0.05..0.05))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn hnsw_config(m: usize, ef_construct: usize) -> HnswConfig {
+        HnswConfig {
+            m,
+            ef_construct,
+            full_scan_threshold: 16, // KB
+            max_indexing_threads: 2,
+            on_disk: Some(false),
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        }
+    }
+
+    fn open_index<TGraphLinks: GraphLinks>(
+        segment: &Segment,
+        hnsw_dir: &TempDir,
+        hnsw_config: HnswConfig,
+    ) -> HNSWIndex<TGraphLinks> {
+        HNSWIndex::<TGraphLinks>::open(
+            hnsw_dir.path(),
+            segment.id_tracker.clone(),
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .clone(),
+            segment.payload_index.clone(),
+            hnsw_config,
+        )
+        .unwrap()
+    }
+
+    /// Share of the exact top results, found by the index
+    fn recall(
+        segment: &Segment,
+        hnsw_index: &dyn VectorIndex,
+        queries: &[Vec<f32>],
+        top: usize,
+        ef: usize,
+    ) -> f64 {
+        let params = SearchParams {
+            hnsw_ef: Some(ef),
+            ..Default::default()
+        };
+        recall_with_params(segment, hnsw_index, queries, top, Some(&params))
+    }
+
+    fn recall_with_params(
+        segment: &Segment,
+        hnsw_index: &dyn VectorIndex,
+        queries: &[Vec<f32>],
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> f64 {
+        let mut found = 0;
+        for query in queries {
+            let index_result = hnsw_index.search(&[query.as_slice()], None, top, params);
+            let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_index
+                .read()
+                .search(&[query.as_slice()], None, top, None);
+
+            let id_tracker = segment.id_tracker.read();
+            for scored_point in &index_result[0] {
+                assert!(
+                    !id_tracker.is_deleted(scored_point.idx),
+                    "deleted point {} is found",
+                    scored_point.idx
+                );
+            }
+
+            let expected: HashSet<PointOffsetType> =
+                plain_result[0].iter().map(|point| point.idx).collect();
+            found += index_result[0]
+                .iter()
+                .filter(|point| expected.contains(&point.idx))
+                .count();
+        }
+        found as f64 / (queries.len() * top) as f64
+    }
+
+    #[test]
+    fn test_hnsw_recall() {
+        let top = 10;
+        let ef = 64;
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let (_dir, _hnsw_dir, mut segment, hnsw_index) =
+            random_segment_with_index(16, 100, 250, &mut rnd);
+
+        // Deleted after the graph is built, still present in the graph links
+        for op_num in NUM_VECTORS + 250..NUM_VECTORS + 500 {
+            let point_id = rnd.gen_range(0..NUM_VECTORS);
+            segment.delete_point(op_num, point_id.into()).unwrap();
+        }
+
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let hnsw_recall = recall(&segment, &hnsw_index, &queries, top, ef);
+        eprintln!("recall@{top} = {hnsw_recall}");
+        assert!(hnsw_recall >= 0.95, "recall@{top} = {hnsw_recall}");
+    }
+
+    #[test]
+    fn test_hnsw_recall_after_mass_deletion() {
+        let top = 10;
+        let ef = 64;
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let (_dir, _hnsw_dir, mut segment, hnsw_index) =
+            random_segment_with_index(16, 100, 0, &mut rnd);
+
+        // Most of the graph is deleted after the build, searches pass through the deleted points
+        let deleted: Vec<_> = (0..NUM_VECTORS)
+            .filter(|point_id| point_id % 10 < 7)
+            .collect();
+        for (op_num, point_id) in (NUM_VECTORS..).zip(&deleted) {
+            segment.delete_point(op_num, (*point_id).into()).unwrap();
+        }
+
+        let stats = hnsw_index.index_stats(false).unwrap();
+        assert_eq!(stats.linked_points, NUM_VECTORS as usize);
+        assert_eq!(stats.deleted_points, deleted.len());
+        assert!((stats.deleted_ratio - 0.7).abs() < 1e-9, "{stats:?}");
+
+        // Deleted points are never returned, and the live ones are still found
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let hnsw_recall = recall(&segment, &hnsw_index, &queries, top, ef);
+        eprintln!("recall@{top} = {hnsw_recall}");
+        assert!(hnsw_recall >= 0.9, "recall@{top} = {hnsw_recall}");
+
+        for query in &queries {
+            let result = hnsw_index.search(&[query.as_slice()], None, top, None);
+            assert_eq!(result[0].len(), top);
+        }
+    }
+
+    #[test]
+    fn test_hnsw_recall_grows_with_ef() {
+        let top = 10;
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        // Sparse graph, so small beam misses some of the results
+        let (_dir, _hnsw_dir, segment, hnsw_index) = random_segment_with_index(4, 16, 0, &mut rnd);
+
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+        // ef smaller than top is clamped, so it gives the same result as ef == top
+        assert_eq!(
+            recall(&segment, &hnsw_index, &queries, top, 1),
+            recall(&segment, &hnsw_index, &queries, top, top),
+        );
+
+        let recalls: Vec<_> = [top, 32, 128, 512]
+            .into_iter()
+            .map(|ef| recall(&segment, &hnsw_index, &queries, top, ef))
+            .collect();
+        eprintln!("recalls = {recalls:?}");
+        for pair in recalls.windows(2) {
+            assert!(pair[0] <= pair[1], "recalls = {recalls:?}");
+        }
+        assert!(recalls[0] < recalls[recalls.len() - 1]);
+    }
+
+    #[test]
+    fn test_hnsw_reopen() {
+        let top = 10;
+        let ef = 64;
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let (_dir, hnsw_dir, segment, hnsw_index) = random_segment_with_index(16, 100, 0, &mut rnd);
+
+        let queries: Vec<_> = (0..20).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let search = |index: &dyn VectorIndex| -> Vec<_> {
+            queries
+                .iter()
+                .map(|query| {
+                    index.search(
+                        &[query.as_slice()],
+                        None,
+                        top,
+                        Some(&SearchParams {
+                            hnsw_ef: Some(ef),
+                            ..Default::default()
+                        }),
+                    )
+                })
+                .collect()
+        };
+
+        let expected = search(&hnsw_index);
+        drop(hnsw_index);
+
+        let ram_index = open_index::<GraphLinksRam>(&segment, &hnsw_dir, hnsw_config(16, 100));
+        assert_eq!(search(&ram_index), expected);
+        drop(ram_index);
+
+        let mmap_index = open_index::<GraphLinksMmap>(&segment, &hnsw_dir, hnsw_config(16, 100));
+        assert_eq!(search(&mmap_index), expected);
+        drop(mmap_index);
+
+        // Corrupted links are detected on load, and the graph is rebuilt
+        let links_path = GraphLayers::<GraphLinksRam>::get_links_path(hnsw_dir.path());
+        let mut links_data = std::fs::read(&links_path).unwrap();
+        let middle = links_data.len() / 2;
+        links_data[middle] ^= 0xFF;
+        std::fs::write(&links_path, &links_data).unwrap();
+
+        let rebuilt_index = open_index::<GraphLinksMmap>(&segment, &hnsw_dir, hnsw_config(16, 100));
+        let rebuilt_recall = recall(&segment, &rebuilt_index, &queries, top, ef);
+        assert!(rebuilt_recall >= 0.95, "recall@{top} = {rebuilt_recall}");
+        assert_ne!(std::fs::read(&links_path).unwrap(), links_data);
+    }
+
+    #[test]
+    fn test_hnsw_incremental_recall() {
+        let top = 10;
+        let ef = 64;
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let vectors: Vec<_> = (0..NUM_VECTORS / 2)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let (_dir, mut segment) = plain_segment(&vectors);
+
+        let incremental_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let mut incremental_index = open_index::<GraphLinksRam>(
+            &segment,
+            &incremental_dir,
+            HnswConfig {
+                incremental: Some(true),
+                ..hnsw_config(16, 100)
+            },
+        );
+        incremental_index.build_index(&stopped).unwrap();
+        let rebuild_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let mut rebuild_index =
+            open_index::<GraphLinksRam>(&segment, &rebuild_dir, hnsw_config(16, 100));
+        rebuild_index.build_index(&stopped).unwrap();
+
+        let mut upsert = |segment: &mut Segment, op_num: u64, point_id: u64, vector: &[f32]| {
+            segment
+                .upsert_vector(op_num, point_id.into(), &only_default_vector(vector))
+                .unwrap();
+            let internal_id = segment
+                .id_tracker
+                .read()
+                .internal_id(point_id.into())
+                .unwrap();
+            incremental_index.update_vector(internal_id).unwrap();
+            rebuild_index.update_vector(internal_id).unwrap();
+        };
+
+        // New points, inserted after the build
+        let mut op_num = NUM_VECTORS / 2;
+        for point_id in NUM_VECTORS / 2..NUM_VECTORS {
+            upsert(
+                &mut segment,
+                op_num,
+                point_id,
+                &random_vector(&mut rnd, DIM),
+            );
+            op_num += 1;
+        }
+        // Changed points of both the built graph and the inserted ones
+        let mut changed_points = vec![];
+        for _ in 0..500 {
+            let point_id = rnd.gen_range(0..NUM_VECTORS);
+            let vector = random_vector(&mut rnd, DIM);
+            upsert(&mut segment, op_num, point_id, &vector);
+            changed_points.push((point_id, vector));
+            op_num += 1;
+        }
+
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let incremental_recall = recall(&segment, &incremental_index, &queries, top, ef);
+        eprintln!("recall@{top} = {incremental_recall}");
+        assert!(
+            incremental_recall >= 0.9,
+            "recall@{top} = {incremental_recall}"
+        );
+
+        // Inserted and changed points are found by their own vectors
+        let nearest = |index: &dyn VectorIndex, vector: &[f32]| {
+            index.search(&[vector], None, 1, None)[0]
+                .first()
+                .map(|point| point.idx)
+        };
+        let new_point_vector = segment
+            .vector(DEFAULT_VECTOR_NAME, (NUM_VECTORS - 1).into())
+            .unwrap();
+        let new_offset = segment
+            .id_tracker
+            .read()
+            .internal_id((NUM_VECTORS - 1).into());
+        assert_eq!(nearest(&incremental_index, &new_point_vector), new_offset);
+        // Nothing links to the new point in the rebuild-only graph
+        assert_ne!(nearest(&rebuild_index, &new_point_vector), new_offset);
+
+        let mut found_changed = 0;
+        for (point_id, vector) in &changed_points {
+            let offset = segment.id_tracker.read().internal_id((*point_id).into());
+            found_changed += (nearest(&incremental_index, vector) == offset) as usize;
+        }
+        assert!(
+            found_changed * 10 >= changed_points.len() * 9,
+            "found {found_changed} of {} changed points",
+            changed_points.len()
+        );
+
+        // Flushed graph is loaded on reopen
+        let search = |index: &dyn VectorIndex| -> Vec<_> {
+            queries
+                .iter()
+                .map(|query| index.search(&[query.as_slice()], None, top, None))
+                .collect()
+        };
+        let expected = search(&incremental_index);
+        incremental_index.flush().unwrap();
+        drop(incremental_index);
+        let reopened =
+            open_index::<GraphLinksMmap>(&segment, &incremental_dir, hnsw_config(16, 100));
+        assert_eq!(search(&reopened), expected);
+    }
+
+    #[test]
+    fn test_hnsw_recall_neighbor_selection() {
+        let top = 10;
+        let ef = 16;
+        let stopped = AtomicBool::new(false);
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let centers: Vec<Vec<f32>> = (0..50)
+            .map(|_| (0..DIM).map(|_| rnd.gen_range(-1.0..1.0)).collect())
+            .collect();
+        let vectors = clustered_vectors(&mut rnd, &centers, NUM_VECTORS as usize);
+        let queries = clustered_vectors(&mut rnd, &centers, 100);
+        let (_dir, segment) = plain_segment(&vectors);
+
+        let selections = [
+            NeighborSelection::Simple,
+            NeighborSelection::Heuristic,
+            NeighborSelection::HeuristicKeepPruned,
+        ];
+        let mut recalls = HashMap::new();
+        for selection in selections {
+            let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+            // Sparse graph, so clusters are poorly connected without the heuristic
+            let config = HnswConfig {
+                neighbor_selection: Some(selection),
+                ..hnsw_config(4, 16)
+            };
+            let mut hnsw_index = open_index::<GraphLinksRam>(&segment, &hnsw_dir, config);
+            hnsw_index.build_index(&stopped).unwrap();
+            recalls.insert(selection, recall(&segment, &hnsw_index, &queries, top, ef));
+
+            // Selection is persisted with the index and is not affected by the new config
+            let config: serde_json::Value = serde_json::from_slice(
+                &std::fs::read(hnsw_dir.path().join("hnsw_config.json")).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(
+                config["neighbor_selection"],
+                serde_json::to_value(selection).unwrap()
+            );
+            let expected = queries
+                .iter()
+                .map(|query| hnsw_index.search(&[query.as_slice()], None, top, None))
+                .collect::<Vec<_>>();
+            drop(hnsw_index);
+            let config = HnswConfig {
+                neighbor_selection: Some(NeighborSelection::Simple),
+                ..hnsw_config(4, 16)
+            };
+            let reopened = open_index::<GraphLinksRam>(&segment, &hnsw_dir, config);
+            let reopened_result = queries
+                .iter()
+                .map(|query| reopened.search(&[query.as_slice()], None, top, None))
+                .collect::<Vec<_>>();
+            assert_eq!(reopened_result, expected);
+        }
+
+        eprintln!("recalls = {recalls:?}");
+        assert!(
+            recalls[&NeighborSelection::Heuristic] > recalls[&NeighborSelection::Simple],
+            "recalls = {recalls:?}"
+        );
+    }
+
+    #[test]
+    fn test_hnsw_ef_policy_calibration() {
+        let top = 10;
+        let target_recall = 0.95;
+        let stopped = AtomicBool::new(false);
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let vectors: Vec<_> = (0..NUM_VECTORS)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let (_dir, segment) = plain_segment(&vectors);
+
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        // Sparse graph, so `ef = top` is not enough to reach the target
+        let config = HnswConfig {
+            seed: Some(42),
+            ef_policy: Some(EfPolicy {
+                factor: None,
+                min_ef: None,
+                target_recall: Some(target_recall),
+            }),
+            max_build_memory_mb: None,
+            ..hnsw_config(4, 16)
+        };
+        let mut hnsw_index = open_index::<GraphLinksRam>(&segment, &hnsw_dir, config);
+        assert_eq!(hnsw_index.ef_calibration(), None);
+        hnsw_index.build_index(&stopped).unwrap();
+
+        let calibration = hnsw_index.ef_calibration().unwrap();
+        eprintln!("calibration = {calibration:?}");
+        assert_eq!(calibration.target_recall, target_recall);
+        assert!(calibration.factor > 1.0, "calibration = {calibration:?}");
+        assert!(calibration.recall >= target_recall);
+
+        let calibrated_ef = (top as f32 * calibration.factor).ceil() as usize;
+        let plan = hnsw_index.explain_search(&queries[0], None, top, None);
+        assert_eq!(plan.ef, Some(calibrated_ef));
+        let calibrated_recall = recall_with_params(&segment, &hnsw_index, &queries, top, None);
+        let uncalibrated_recall = recall(&segment, &hnsw_index, &queries, top, top);
+        eprintln!("calibrated = {calibrated_recall}, uncalibrated = {uncalibrated_recall}");
+        assert!(
+            calibrated_recall >= target_recall as f64 - 0.03,
+            "calibrated = {calibrated_recall}"
+        );
+        assert!(calibrated_recall > uncalibrated_recall);
+
+        // Explicit `hnsw_ef` wins over the policy
+        let params = SearchParams {
+            hnsw_ef: Some(3 * top),
+            ..Default::default()
+        };
+        let plan = hnsw_index.explain_search(&queries[0], None, top, Some(&params));
+        assert_eq!(plan.ef, Some(3 * top));
+
+        // Calibration is persisted with the index and is not affected by the new config
+        drop(hnsw_index);
+        let reopened = open_index::<GraphLinksRam>(&segment, &hnsw_dir, hnsw_config(4, 16));
+        assert_eq!(reopened.ef_calibration(), Some(calibration));
+        let plan = reopened.explain_search(&queries[0], None, top, None);
+        assert_eq!(plan.ef, Some(calibrated_ef));
+
+        // Without the target recall, `ef = max(top * factor, min_ef)`
+        let policy_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let config = HnswConfig {
+            ef_policy: Some(EfPolicy {
+                factor: Some(2.0),
+                min_ef: Some(50),
+                target_recall: None,
+            }),
+            max_build_memory_mb: None,
+            ..hnsw_config(4, 16)
+        };
+        let mut policy_index = open_index::<GraphLinksRam>(&segment, &policy_dir, config);
+        policy_index.build_index(&stopped).unwrap();
+        assert_eq!(policy_index.ef_calibration(), None);
+        for (top, ef) in [(10, 50), (40, 80)] {
+            let plan = policy_index.explain_search(&queries[0], None, top, None);
+            assert_eq!(plan.ef, Some(ef));
+        }
+    }
+
+    #[test]
+    fn test_hnsw_seeded_build_is_reproducible() {
+        let stopped = AtomicBool::new(false);
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let vectors: Vec<_> = (0..1_000).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let (_dir, segment) = plain_segment(&vectors);
+
+        let build = |seed: u64| {
+            let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+            let config = HnswConfig {
+                max_indexing_threads: 1,
+                seed: Some(seed),
+                incremental: None,
+                ..hnsw_config(16, 100)
+            };
+            let mut hnsw_index = open_index::<GraphLinksRam>(&segment, &hnsw_dir, config);
+            hnsw_index.build_index(&stopped).unwrap();
+            drop(hnsw_index);
+
+            let graph = std::fs::read(GraphLayers::<GraphLinksRam>::get_path(hnsw_dir.path()));
+            let links = std::fs::read(GraphLayers::<GraphLinksRam>::get_links_path(
+                hnsw_dir.path(),
+            ));
+            (graph.unwrap(), links.unwrap())
+        };
+
+        let (graph, links) = build(42);
+        let (same_graph, same_links) = build(42);
+        assert!(
+            graph == same_graph,
+            "graphs built with the same seed differ"
+        );
+        assert!(links == same_links, "links built with the same seed differ");
+
+        let (_, other_links) = build(43);
+        assert!(links != other_links, "seed does not affect the build");
+    }
+
+    #[test]
+    fn test_hnsw_parallel_build_recall() {
+        let top = 10;
+        let ef = 64;
+        let stopped = AtomicBool::new(false);
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let vectors: Vec<_> = (0..NUM_VECTORS)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+        let (_dir, segment) = plain_segment(&vectors);
+
+        let build_recall = |max_indexing_threads: usize| {
+            let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+            let config = HnswConfig {
+                max_indexing_threads,
+                seed: Some(42),
+                incremental: None,
+                ..hnsw_config(16, 100)
+            };
+            let mut hnsw_index = open_index::<GraphLinksRam>(&segment, &hnsw_dir, config);
+            hnsw_index.build_index(&stopped).unwrap();
+            recall(&segment, &hnsw_index, &queries, top, ef)
+        };
+
+        let single_recall = build_recall(1);
+        let parallel_recall = build_recall(4);
+        eprintln!("single = {single_recall}, parallel = {parallel_recall}");
+        assert!(
+            parallel_recall >= single_recall - 0.02,
+            "single = {single_recall}, parallel = {parallel_recall}"
+        );
+    }
+}
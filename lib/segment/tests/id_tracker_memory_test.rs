@@ -0,0 +1,77 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tracks the size of memory, allocated on the Rust side
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::common::rocksdb_wrapper::{open_db, DB_VECTOR_CF};
+    use segment::id_tracker::compact_id_tracker::CompactIdTracker;
+    use segment::id_tracker::simple_id_tracker::SimpleIdTracker;
+    use segment::id_tracker::IdTracker;
+    use segment::types::{PointIdType, PointOffsetType};
+    use tempfile::Builder;
+
+    use super::*;
+
+    const NUM_POINTS: usize = 200_000;
+
+    /// Open the id tracker and measure memory, retained by it
+    fn open_measured<T>(open: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let id_tracker = open();
+        let retained = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+        (id_tracker, retained)
+    }
+
+    #[test]
+    fn test_compact_id_tracker_memory() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let db = open_db(dir.path(), &[DB_VECTOR_CF]).unwrap();
+
+        {
+            let mut id_tracker = SimpleIdTracker::open(db.clone()).unwrap();
+            let mut rng = StdRng::seed_from_u64(42);
+            for internal_id in 0..NUM_POINTS as PointOffsetType {
+                let external_id = PointIdType::NumId(rng.gen());
+                id_tracker.set_link(external_id, internal_id).unwrap();
+                id_tracker.set_internal_version(internal_id, 1).unwrap();
+            }
+        }
+
+        let (simple, simple_memory) = open_measured(|| SimpleIdTracker::open(db.clone()).unwrap());
+        let (compact, compact_memory) =
+            open_measured(|| CompactIdTracker::open(db.clone()).unwrap());
+
+        assert_eq!(simple.points_count(), NUM_POINTS);
+        assert_eq!(compact.points_count(), NUM_POINTS);
+        assert!(compact.iter_from(None).eq(simple.iter_from(None)));
+
+        println!(
+            "bytes per point: simple {}, compact {}",
+            simple_memory / NUM_POINTS,
+            compact_memory / NUM_POINTS,
+        );
+        assert!(compact_memory < simple_memory);
+    }
+}
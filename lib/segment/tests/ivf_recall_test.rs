@@ -0,0 +1,271 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::index::ivf_index::inverted_lists::InvertedLists;
+    use segment::index::ivf_index::ivf::IvfIndex;
+    use segment::index::{VectorIndex, VectorIndexEnum};
+    use segment::segment::Segment;
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Condition, Distance, Filter, Indexes, IvfConfig, PointOffsetType, ScoredPoint,
+        SearchParams, SegmentConfig, SegmentType, SeqNumberType, StorageType, VectorDataConfig,
+    };
+    use tempfile::{Builder, TempDir};
+
+    const DIM: usize = 16;
+    const NUM_VECTORS: u64 = 5_000;
+    const N_LISTS: usize = 32;
+
+    fn plain_segment(vectors: &[Vec<f32>]) -> (TempDir, Segment) {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Cosine,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for (n, vector) in vectors.iter().enumerate() {
+            let n = n as u64;
+            segment
+                .upsert_vector(n as SeqNumberType, n.into(), &only_default_vector(vector))
+                .unwrap();
+        }
+
+        (dir, segment)
+    }
+
+    fn ivf_config(seed: Option<u64>) -> IvfConfig {
+        IvfConfig {
+            n_lists: N_LISTS,
+            nprobe: 4,
+            max_indexing_threads: 2,
+            seed,
+        }
+    }
+
+    fn open_index(segment: &Segment, ivf_dir: &TempDir, ivf_config: IvfConfig) -> IvfIndex {
+        IvfIndex::open(
+            ivf_dir.path(),
+            segment.id_tracker.clone(),
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .clone(),
+            segment.payload_index.clone(),
+            ivf_config,
+        )
+        .unwrap()
+    }
+
+    /// Share of the exact top results, found by the index
+    fn recall(
+        segment: &Segment,
+        ivf_index: &dyn VectorIndex,
+        queries: &[Vec<f32>],
+        top: usize,
+        nprobe: usize,
+    ) -> f64 {
+        let mut found = 0;
+        for query in queries {
+            let index_result = ivf_index.search(
+                &[query.as_slice()],
+                None,
+                top,
+                Some(&SearchParams {
+                    ivf_nprobe: Some(nprobe),
+                    ..Default::default()
+                }),
+            );
+            let plain_result = segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_index
+                .read()
+                .search(&[query.as_slice()], None, top, None);
+
+            let id_tracker = segment.id_tracker.read();
+            for scored_point in &index_result[0] {
+                assert!(
+                    !id_tracker.is_deleted(scored_point.idx),
+                    "deleted point {} is found",
+                    scored_point.idx
+                );
+            }
+
+            let expected: HashSet<PointOffsetType> =
+                plain_result[0].iter().map(|point| point.idx).collect();
+            found += index_result[0]
+                .iter()
+                .filter(|point| expected.contains(&point.idx))
+                .count();
+        }
+        found as f64 / (queries.len() * top) as f64
+    }
+
+    #[test]
+    fn test_ivf_recall() {
+        let top = 10;
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let vectors: Vec<_> = (0..NUM_VECTORS)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let (_dir, mut segment) = plain_segment(&vectors);
+
+        let ivf_dir = Builder::new().prefix("ivf_dir").tempdir().unwrap();
+        let mut ivf_index = open_index(&segment, &ivf_dir, ivf_config(Some(42)));
+        ivf_index.build_index(&stopped).unwrap();
+
+        let lists = ivf_index.lists().unwrap();
+        assert_eq!(lists.num_lists(), N_LISTS);
+        let all_lists: Vec<_> = (0..N_LISTS).collect();
+        assert_eq!(lists.lists_size(&all_lists), NUM_VECTORS as usize);
+
+        // Deleted after the lists are built, still present in the posting lists
+        for op_num in NUM_VECTORS..NUM_VECTORS + 250 {
+            let point_id = rnd.gen_range(0..NUM_VECTORS);
+            segment.delete_point(op_num, point_id.into()).unwrap();
+        }
+
+        let queries: Vec<_> = (0..100).map(|_| random_vector(&mut rnd, DIM)).collect();
+
+        // More probed lists - more candidates, so recall can't decrease
+        let recalls: Vec<_> = [1, 4, 16, N_LISTS]
+            .into_iter()
+            .map(|nprobe| recall(&segment, &ivf_index, &queries, top, nprobe))
+            .collect();
+        for pair in recalls.windows(2) {
+            assert!(pair[0] <= pair[1], "recall decreases: {recalls:?}");
+        }
+        assert!(recalls[1] > 0.5, "recall is too low: {recalls:?}");
+        // Probing all lists scores all points
+        assert_eq!(recalls[3], 1.0);
+
+        // Filter, matching fewer points than the probed lists, is scored without the lists
+        let filter = Filter::new_must(Condition::HasId(
+            HashSet::from([1.into(), 2.into(), 3.into()]).into(),
+        ));
+        let filtered_result = ivf_index.search(
+            &[queries[0].as_slice()],
+            Some(&filter),
+            top,
+            Some(&SearchParams {
+                ivf_nprobe: Some(1),
+                ..Default::default()
+            }),
+        );
+        let id_tracker = segment.id_tracker.read();
+        let expected_count = (1..=3u64)
+            .filter(|&id| id_tracker.internal_id(id.into()).is_some())
+            .count();
+        assert_eq!(filtered_result[0].len(), expected_count);
+    }
+
+    #[test]
+    fn test_ivf_reopen() {
+        let top = 10;
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let vectors: Vec<_> = (0..NUM_VECTORS)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let (_dir, segment) = plain_segment(&vectors);
+
+        let ivf_dir = Builder::new().prefix("ivf_dir").tempdir().unwrap();
+        let mut ivf_index = open_index(&segment, &ivf_dir, ivf_config(None));
+        ivf_index.build_index(&stopped).unwrap();
+        let lists = ivf_index.lists().cloned().unwrap();
+
+        let query = random_vector(&mut rnd, DIM);
+        let search = |index: &IvfIndex| index.search(&[query.as_slice()], None, top, None);
+        let expected = search(&ivf_index);
+        drop(ivf_index);
+
+        // Config is loaded from the index directory, the passed one is ignored
+        let reopened = open_index(&segment, &ivf_dir, ivf_config(Some(1)));
+        assert_eq!(reopened.lists(), Some(&lists));
+        assert_eq!(search(&reopened), expected);
+        drop(reopened);
+
+        // Corrupted lists are rebuilt on open
+        fs::write(InvertedLists::get_path(ivf_dir.path()), b"corrupted").unwrap();
+        let rebuilt = open_index(&segment, &ivf_dir, ivf_config(None));
+        assert_eq!(rebuilt.lists().unwrap().num_lists(), N_LISTS);
+    }
+
+    #[test]
+    fn test_ivf_segment() {
+        let top = 10;
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let vectors: Vec<_> = (0..NUM_VECTORS)
+            .map(|_| random_vector(&mut rnd, DIM))
+            .collect();
+        let (_dir, mut segment) = plain_segment(&vectors);
+
+        let op_num = NUM_VECTORS;
+        assert!(segment
+            .update_index_config(op_num, Indexes::Ivf(ivf_config(Some(42))), true)
+            .unwrap());
+        assert_eq!(segment.segment_type(), SegmentType::Indexed);
+        assert!(matches!(
+            &*segment.vector_data[DEFAULT_VECTOR_NAME].vector_index.read(),
+            VectorIndexEnum::Ivf(_),
+        ));
+
+        let query = random_vector(&mut rnd, DIM);
+        let exact_result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &Default::default(),
+                &false.into(),
+                None,
+                top,
+                Some(&SearchParams {
+                    exact: true,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        let all_lists_result = segment
+            .search(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &Default::default(),
+                &false.into(),
+                None,
+                top,
+                Some(&SearchParams {
+                    ivf_nprobe: Some(N_LISTS),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        let ids = |result: &[ScoredPoint]| {
+            result
+                .iter()
+                .map(|point| point.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&all_lists_result), ids(&exact_result));
+    }
+}
@@ -2,16 +2,17 @@ mod utils;
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
     use std::path::Path;
 
     use itertools::Itertools;
     use rand::prelude::StdRng;
     use rand::{Rng, SeedableRng};
+    use segment::data_types::keyword_index::KeywordIndexParams;
     use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
     use segment::entry::entry_point::SegmentEntry;
     use segment::fixtures::payload_fixtures::{
-        generate_diverse_nested_payload, generate_diverse_payload, random_filter,
+        generate_diverse_nested_payload, generate_diverse_payload, random_filter, random_keyword,
         random_nested_filter, random_vector, FLICKING_KEY, GEO_KEY, INT_KEY, INT_KEY_2, LAT_RANGE,
         LON_RANGE, STR_KEY, STR_PROJ_KEY, TEXT_KEY,
     };
@@ -19,10 +20,12 @@ mod tests {
     use segment::segment::Segment;
     use segment::segment_constructor::build_segment;
     use segment::types::{
-        Condition, Distance, FieldCondition, Filter, GeoPoint, GeoRadius, Indexes,
-        IsEmptyCondition, Payload, PayloadField, PayloadSchemaType, Range, SegmentConfig,
-        StorageType, VectorDataConfig, WithPayload,
+        AnyVariants, Condition, Distance, FieldCondition, Filter, GeoPoint, GeoRadius, Indexes,
+        IsEmptyCondition, IsNullCondition, Match, MatchExcept, MatchInsensitive, NestedCondition,
+        Payload, PayloadField, PayloadFieldSchema, PayloadSchemaParams, PayloadSchemaType,
+        PointIdType, Range, SegmentConfig, StorageType, VectorDataConfig, WithPayload,
     };
+    use serde_json::json;
     use tempfile::Builder;
 
     use crate::utils::scored_point_ties::ScoredPointTies;
@@ -122,7 +125,7 @@ mod tests {
                 .unwrap();
         }
 
-        for (field, indexes) in struct_segment.payload_index.borrow().field_indexes.iter() {
+        for (field, indexes) in struct_segment.payload_index.read().field_indexes.iter() {
             for index in indexes {
                 assert!(index.indexed_points() < num_points as usize);
                 if field != FLICKING_KEY {
@@ -231,7 +234,7 @@ mod tests {
                 .unwrap();
         }
 
-        for (_field, indexes) in struct_segment.payload_index.borrow().field_indexes.iter() {
+        for (_field, indexes) in struct_segment.payload_index.read().field_indexes.iter() {
             for index in indexes {
                 assert!(index.indexed_points() < num_points as usize);
                 assert!(
@@ -259,17 +262,17 @@ mod tests {
 
         let estimation_struct = struct_segment
             .payload_index
-            .borrow()
+            .read()
             .estimate_cardinality(&filter);
 
         let estimation_plain = plain_segment
             .payload_index
-            .borrow()
+            .read()
             .estimate_cardinality(&filter);
 
         let real_number = plain_segment
             .payload_index
-            .borrow()
+            .read()
             .query_points(&filter)
             .count();
 
@@ -289,6 +292,378 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_not_is_empty_conditions() {
+        let dir1 = Builder::new().prefix("segment1_dir").tempdir().unwrap();
+        let dir2 = Builder::new().prefix("segment2_dir").tempdir().unwrap();
+
+        let (struct_segment, plain_segment) = build_test_segments(dir1.path(), dir2.path());
+
+        let is_empty = Condition::IsEmpty(IsEmptyCondition {
+            is_empty: PayloadField {
+                key: FLICKING_KEY.to_string(),
+            },
+        });
+
+        let empty_filter = Filter::new_must(is_empty.clone());
+        let empty_result = plain_segment.read_filtered(None, None, Some(&empty_filter));
+
+        let filter = Filter::new_must_not(is_empty);
+
+        let estimation = struct_segment
+            .payload_index
+            .read()
+            .estimate_cardinality(&filter);
+
+        let struct_result = struct_segment.read_filtered(None, None, Some(&filter));
+        let plain_result = plain_segment.read_filtered(None, None, Some(&filter));
+
+        assert_eq!(struct_result, plain_result);
+        assert!(estimation.min <= struct_result.len());
+        assert!(estimation.max >= struct_result.len());
+
+        // Every point is either empty or has a value
+        assert_eq!(
+            empty_result.len() + struct_result.len(),
+            struct_segment.points_count()
+        );
+        assert!(struct_result.iter().all(|id| !empty_result.contains(id)));
+    }
+
+    #[test]
+    fn test_is_null_conditions() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+
+        let payloads = [
+            json!({ "items": [{ "count": 1 }] }),
+            json!({ "items": [{ "count": null }] }),
+            json!({ "items": [{ "count": 2 }, { "count": null }] }),
+            json!({ "items": [] }),
+            json!({}),
+        ];
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            let point_id = (idx as u64).into();
+            segment
+                .upsert_vector(
+                    idx as u64,
+                    point_id.clone(),
+                    &only_default_vector(&[1.0, 1.0]),
+                )
+                .unwrap();
+            segment
+                .set_full_payload(idx as u64, point_id, &payload.into())
+                .unwrap();
+        }
+        segment
+            .create_field_index(
+                10,
+                "items[].count",
+                Some(&PayloadSchemaType::Integer.into()),
+            )
+            .unwrap();
+
+        let is_null = Condition::IsNull(IsNullCondition {
+            is_null: PayloadField {
+                key: "items[].count".to_string(),
+            },
+        });
+        let is_empty = Condition::IsEmpty(IsEmptyCondition {
+            is_empty: PayloadField {
+                key: "items[].count".to_string(),
+            },
+        });
+
+        let check = |filter: Filter, expected: Vec<u64>| {
+            let estimation = segment.payload_index.read().estimate_cardinality(&filter);
+            let result = segment.read_filtered(None, None, Some(&filter));
+            assert_eq!(
+                result,
+                expected.into_iter().map(PointIdType::from).collect_vec()
+            );
+            assert!(estimation.min <= result.len());
+            assert!(estimation.max >= result.len());
+        };
+
+        // Null next to an indexed value is still a null
+        check(Filter::new_must(is_null.clone()), vec![1, 2]);
+        check(Filter::new_must_not(is_null), vec![0, 3, 4]);
+        check(Filter::new_must(is_empty.clone()), vec![1, 3, 4]);
+        check(Filter::new_must_not(is_empty), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_nested_conditions() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+
+        let payloads = [
+            json!({ "variants": [{ "color": "red", "size": 12 }] }),
+            json!({ "variants": [{ "color": "red", "size": 10 }, { "color": "blue", "size": 12 }] }),
+            json!({ "variants": { "color": "red", "size": 12 } }),
+            json!({ "variants": [] }),
+            json!({}),
+        ];
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            let point_id = (idx as u64).into();
+            segment
+                .upsert_vector(
+                    idx as u64,
+                    point_id.clone(),
+                    &only_default_vector(&[1.0, 1.0]),
+                )
+                .unwrap();
+            segment
+                .set_full_payload(idx as u64, point_id, &payload.into())
+                .unwrap();
+        }
+        segment
+            .create_field_index(
+                10,
+                "variants[].color",
+                Some(&PayloadSchemaType::Keyword.into()),
+            )
+            .unwrap();
+
+        let red_12 = Filter::new_must(Condition::Nested(NestedCondition::new(
+            "variants".to_string(),
+            Filter {
+                should: None,
+                must: Some(vec![
+                    Condition::Field(FieldCondition::new_match(
+                        "color".to_string(),
+                        "red".to_string().into(),
+                    )),
+                    Condition::Field(FieldCondition::new_match("size".to_string(), 12.into())),
+                ]),
+                must_not: None,
+            },
+        )));
+        let flattened_red_12 = Filter {
+            should: None,
+            must: Some(vec![
+                Condition::Field(FieldCondition::new_match(
+                    "variants[].color".to_string(),
+                    "red".to_string().into(),
+                )),
+                Condition::Field(FieldCondition::new_match(
+                    "variants[].size".to_string(),
+                    12.into(),
+                )),
+            ]),
+            must_not: None,
+        };
+
+        let check = |filter: Filter, expected: Vec<u64>| {
+            let estimation = segment.payload_index.read().estimate_cardinality(&filter);
+            let result = segment.read_filtered(None, None, Some(&filter));
+            assert_eq!(
+                result,
+                expected.into_iter().map(PointIdType::from).collect_vec()
+            );
+            assert!(estimation.min <= result.len());
+            assert!(estimation.max >= result.len());
+        };
+
+        // Flattened conditions may match values of different objects
+        check(flattened_red_12, vec![0, 1]);
+        check(red_12.clone(), vec![0, 2]);
+        check(
+            Filter::new_must_not(Condition::Filter(red_12)),
+            vec![1, 3, 4],
+        );
+    }
+
+    #[test]
+    fn test_match_except_conditions() {
+        let dir1 = Builder::new().prefix("segment1_dir").tempdir().unwrap();
+        let dir2 = Builder::new().prefix("segment2_dir").tempdir().unwrap();
+
+        let (struct_segment, plain_segment) = build_test_segments(dir1.path(), dir2.path());
+
+        let mut rnd = StdRng::seed_from_u64(42);
+        let keywords = (0..20).map(|_| random_keyword(&mut rnd)).collect_vec();
+        let conditions = [
+            FieldCondition::new_match(
+                STR_KEY.to_string(),
+                Match::Except(MatchExcept {
+                    except: AnyVariants::Keywords(keywords),
+                }),
+            ),
+            FieldCondition::new_match(
+                INT_KEY.to_string(),
+                Match::Except(MatchExcept {
+                    except: AnyVariants::Integers((0..250).collect()),
+                }),
+            ),
+        ];
+
+        for condition in conditions {
+            let filter = Filter::new_must(Condition::Field(condition));
+
+            let estimation = struct_segment
+                .payload_index
+                .read()
+                .estimate_cardinality(&filter);
+
+            let struct_result = struct_segment.read_filtered(None, None, Some(&filter));
+            let plain_result = plain_segment.read_filtered(None, None, Some(&filter));
+
+            assert_eq!(struct_result, plain_result);
+            assert!(!struct_result.is_empty());
+            assert!(estimation.min <= struct_result.len());
+            assert!(estimation.max >= struct_result.len());
+        }
+    }
+
+    #[test]
+    fn test_match_insensitive_conditions() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: 2,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+
+        let payloads = [
+            json!({ "brand": "Äpfel" }),
+            json!({ "brand": "äpfel" }),
+            json!({ "brand": ["ÄPFEL", "Birnen"] }),
+            json!({ "brand": "apfel" }),
+            json!({ "brand": 42 }),
+            json!({}),
+        ];
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            let point_id = (idx as u64).into();
+            segment
+                .upsert_vector(
+                    idx as u64,
+                    point_id.clone(),
+                    &only_default_vector(&[1.0, 1.0]),
+                )
+                .unwrap();
+            segment
+                .set_full_payload(idx as u64, point_id, &payload.into())
+                .unwrap();
+        }
+
+        let insensitive = Filter::new_must(Condition::Field(FieldCondition::new_match(
+            "brand".to_string(),
+            Match::Insensitive(MatchInsensitive {
+                insensitive: "äPFEL".to_string(),
+            }),
+        )));
+        let exact = Filter::new_must(Condition::Field(FieldCondition::new_match(
+            "brand".to_string(),
+            "Äpfel".to_string().into(),
+        )));
+
+        let check = |segment: &Segment, filter: &Filter, expected: Vec<u64>| {
+            let estimation = segment.payload_index.read().estimate_cardinality(filter);
+            let result = segment.read_filtered(None, None, Some(filter));
+            assert_eq!(
+                result,
+                expected.into_iter().map(PointIdType::from).collect_vec()
+            );
+            assert!(estimation.min <= result.len());
+            assert!(estimation.max >= result.len());
+        };
+
+        check(&segment, &insensitive, vec![0, 1, 2]);
+        check(&segment, &exact, vec![0]);
+
+        // Case-sensitive index can't serve the condition, payload is checked instead
+        segment
+            .create_field_index(10, "brand", Some(&PayloadSchemaType::Keyword.into()))
+            .unwrap();
+        check(&segment, &insensitive, vec![0, 1, 2]);
+        check(&segment, &exact, vec![0]);
+
+        segment.delete_field_index(11, "brand").unwrap();
+        let insensitive_schema =
+            PayloadFieldSchema::FieldParams(PayloadSchemaParams::Keyword(KeywordIndexParams {
+                insensitive: Some(true),
+                ..Default::default()
+            }));
+        segment
+            .create_field_index(12, "brand", Some(&insensitive_schema))
+            .unwrap();
+        check(&segment, &insensitive, vec![0, 1, 2]);
+        check(&segment, &exact, vec![0]);
+    }
+
+    #[test]
+    fn test_has_id_conditions() {
+        let dir1 = Builder::new().prefix("segment1_dir").tempdir().unwrap();
+        let dir2 = Builder::new().prefix("segment2_dir").tempdir().unwrap();
+
+        let (struct_segment, plain_segment) = build_test_segments(dir1.path(), dir2.path());
+
+        // Some of the ids are deleted and some never existed in the segment
+        let ids: HashSet<PointIdType> = (0..100u64).chain(10_000..10_010).map(Into::into).collect();
+        let existing_ids = ids
+            .iter()
+            .cloned()
+            .filter(|id| struct_segment.has_point(id.clone()))
+            .sorted()
+            .collect_vec();
+
+        let filter = Filter::new_must(Condition::HasId(ids.into()));
+
+        let estimation = struct_segment
+            .payload_index
+            .read()
+            .estimate_cardinality(&filter);
+
+        assert_eq!(estimation.min, existing_ids.len());
+        assert_eq!(estimation.exp, existing_ids.len());
+        assert_eq!(estimation.max, existing_ids.len());
+
+        let struct_result = struct_segment.read_filtered(None, None, Some(&filter));
+        let plain_result = plain_segment.read_filtered(None, None, Some(&filter));
+
+        assert_eq!(struct_result, existing_ids);
+        assert_eq!(plain_result, existing_ids);
+    }
+
     #[test]
     fn test_cardinality_estimation() {
         let dir1 = Builder::new().prefix("segment1_dir").tempdir().unwrap();
@@ -308,14 +683,14 @@ mod tests {
 
         let estimation = struct_segment
             .payload_index
-            .borrow()
+            .read()
             .estimate_cardinality(&filter);
 
-        let payload_index = struct_segment.payload_index.borrow();
+        let payload_index = struct_segment.payload_index.read();
         let filter_context = payload_index.filter_context(&filter);
         let exact = struct_segment
             .id_tracker
-            .borrow()
+            .read()
             .iter_ids()
             .filter(|x| filter_context.check(*x))
             .collect_vec()
@@ -370,13 +745,13 @@ mod tests {
 
             let estimation = struct_segment
                 .payload_index
-                .borrow()
+                .read()
                 .estimate_cardinality(&query_filter);
 
             assert!(estimation.min <= estimation.exp, "{estimation:#?}");
             assert!(estimation.exp <= estimation.max, "{estimation:#?}");
             assert!(
-                estimation.max <= struct_segment.id_tracker.borrow().points_count(),
+                estimation.max <= struct_segment.id_tracker.read().points_count(),
                 "{estimation:#?}"
             );
 
@@ -449,13 +824,13 @@ mod tests {
 
             let estimation = plain_segment
                 .payload_index
-                .borrow()
+                .read()
                 .estimate_cardinality(&query_filter);
 
             assert!(estimation.min <= estimation.exp, "{estimation:#?}");
             assert!(estimation.exp <= estimation.max, "{estimation:#?}");
             assert!(
-                estimation.max <= struct_segment.id_tracker.borrow().points_count(),
+                estimation.max <= struct_segment.id_tracker.read().points_count(),
                 "{estimation:#?}"
             );
 
@@ -473,13 +848,13 @@ mod tests {
 
             let estimation = struct_segment
                 .payload_index
-                .borrow()
+                .read()
                 .estimate_cardinality(&query_filter);
 
             assert!(estimation.min <= estimation.exp, "{estimation:#?}");
             assert!(estimation.exp <= estimation.max, "{estimation:#?}");
             assert!(
-                estimation.max <= struct_segment.id_tracker.borrow().points_count(),
+                estimation.max <= struct_segment.id_tracker.read().points_count(),
                 "{estimation:#?}"
             );
 
@@ -541,13 +916,13 @@ mod tests {
 
             let estimation = struct_segment
                 .payload_index
-                .borrow()
+                .read()
                 .estimate_cardinality(&query_filter);
 
             assert!(estimation.min <= estimation.exp, "{estimation:#?}");
             assert!(estimation.exp <= estimation.max, "{estimation:#?}");
             assert!(
-                estimation.max <= struct_segment.id_tracker.borrow().points_count(),
+                estimation.max <= struct_segment.id_tracker.read().points_count(),
                 "{estimation:#?}"
             );
 
@@ -0,0 +1,69 @@
+import numpy as np
+import pytest
+
+from qdrant_segment import PySegment, SegmentError
+
+
+@pytest.fixture
+def segment(tmp_path):
+    segment = PySegment(str(tmp_path), 4, "Dot")
+    segment.upsert(1, np.array([1.0, 0.0, 0.0, 0.0], dtype=np.float32), {"color": "red"})
+    segment.upsert(2, np.array([0.0, 1.0, 0.0, 0.0], dtype=np.float32), {"color": "blue"})
+    segment.upsert(3, [1.0, 1.0, 0.0, 0.0], {"color": "red", "count": 3})
+    return segment
+
+
+def test_search(segment):
+    assert len(segment) == 3
+    query = np.array([1.0, 0.5, 0.0, 0.0], dtype=np.float32)
+    assert segment.search(query, 2) == [
+        (3, 1.5, {"color": "red", "count": 3}),
+        (1, 1.0, {"color": "red"}),
+    ]
+
+
+def test_search_strided_vector(segment):
+    # Non-contiguous arrays are copied
+    query = np.array([1.0, 9.0, 0.5, 9.0, 0.0, 9.0, 0.0, 9.0], dtype=np.float32)[::2]
+    assert [point[0] for point in segment.search(query, 1)] == [3]
+
+
+def test_filtered_search(segment):
+    query = np.array([0.0, 1.0, 0.0, 0.0], dtype=np.float32)
+    found = segment.search(query, 10, {"must": [{"key": "color", "match": {"value": "red"}}]})
+    assert [point[0] for point in found] == [3, 1]
+
+
+def test_malformed_filter(segment):
+    query = np.zeros(4, dtype=np.float32)
+    with pytest.raises(ValueError, match="must\\[0\\]"):
+        segment.search(query, 10, {"must": [{"key": "color", "matches": {"value": "red"}}]})
+
+
+def test_upsert_replaces(segment):
+    assert segment.upsert(1, np.array([0.0, 0.0, 1.0, 0.0], dtype=np.float32))
+    query = np.array([0.0, 0.0, 1.0, 0.0], dtype=np.float32)
+    assert [point[:2] for point in segment.search(query, 1)] == [(1, 1.0)]
+
+
+def test_errors(segment, tmp_path):
+    with pytest.raises(SegmentError, match="expected dim: 4, got 2"):
+        segment.upsert(4, np.array([1.0, 0.0], dtype=np.float32))
+    with pytest.raises(SegmentError):
+        PySegment.load(str(tmp_path / "missing"))
+    with pytest.raises(ValueError):
+        PySegment(str(tmp_path), 4, "Manhattan")
+
+
+def test_load(tmp_path):
+    segment = PySegment(str(tmp_path), 4, "Dot")
+    segment.upsert(1, [1.0, 0.0, 0.0, 0.0])
+    segment.upsert(2, [0.0, 1.0, 0.0, 0.0])
+    segment.upsert(3, [1.0, 1.0, 0.0, 0.0])
+    segment.flush()
+    # Segment directory is locked, until the segment is dropped
+    del segment
+    (segment_dir,) = tmp_path.iterdir()
+    loaded = PySegment.load(str(segment_dir))
+    assert len(loaded) == 3
+    assert loaded.search([1.0, 1.0, 0.0, 0.0], 1)[0][0] == 3
@@ -0,0 +1,200 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::prelude::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::segment::Segment;
+    use segment::segment_constructor::simple_segment_constructor::build_simple_segment;
+    use segment::types::{
+        Distance, Payload, PayloadSelector, PointIdType, ValueVariants, WithPayload,
+    };
+    use serde_json::{json, Value};
+    use tempfile::Builder;
+
+    const DIM: usize = 8;
+    const NUM_DOCUMENTS: u64 = 50;
+    const CHUNKS_PER_DOCUMENT: u64 = 20;
+
+    /// Chunks of the documents, every 7th chunk also belongs to the next document, and every 11th has no document
+    fn chunked_segment(path: &std::path::Path) -> Segment {
+        let mut rnd = StdRng::seed_from_u64(42);
+        let mut segment = build_simple_segment(path, DIM, Distance::Dot).unwrap();
+        for document in 0..NUM_DOCUMENTS {
+            for chunk in 0..CHUNKS_PER_DOCUMENT {
+                let point_id = document * CHUNKS_PER_DOCUMENT + chunk;
+                let vector = random_vector(&mut rnd, DIM);
+                segment
+                    .upsert_vector(point_id + 1, point_id.into(), &only_default_vector(&vector))
+                    .unwrap();
+                let document_id = if point_id % 11 == 0 {
+                    Value::Null
+                } else if point_id % 7 == 0 {
+                    json!([document, document, (document + 1) % NUM_DOCUMENTS])
+                } else {
+                    json!(document)
+                };
+                let payload: Payload = json!({"document_id": document_id, "chunk": chunk}).into();
+                segment
+                    .set_full_payload(point_id + 1, point_id.into(), &payload)
+                    .unwrap();
+            }
+        }
+        segment
+    }
+
+    fn document_ids(payload: &Payload) -> Vec<i64> {
+        let mut ids: Vec<i64> = match &payload.0["document_id"] {
+            Value::Array(values) => values.iter().filter_map(Value::as_i64).collect(),
+            value => value.as_i64().into_iter().collect(),
+        };
+        ids.dedup();
+        ids
+    }
+
+    #[test]
+    fn test_search_groups() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = chunked_segment(dir.path());
+        let mut rnd = StdRng::seed_from_u64(43);
+        let group_by = "document_id".to_string();
+
+        for _ in 0..10 {
+            let query = random_vector(&mut rnd, DIM);
+            let group_size = rnd.gen_range(1..5);
+            let groups = rnd.gen_range(1..10);
+
+            let found = segment
+                .search_groups(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &WithPayload {
+                        enable: true,
+                        payload_selector: Some(PayloadSelector::new_include(vec![
+                            "chunk".to_string()
+                        ])),
+                    },
+                    &false.into(),
+                    &group_by,
+                    group_size,
+                    groups,
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            // Reference: all the points, grouped best first
+            let all_hits = segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &WithPayload::from(true),
+                    &false.into(),
+                    None,
+                    (NUM_DOCUMENTS * CHUNKS_PER_DOCUMENT) as usize,
+                    None,
+                )
+                .unwrap();
+            let mut expected: Vec<(i64, Vec<PointIdType>)> = Vec::new();
+            for hit in &all_hits {
+                for document_id in document_ids(hit.payload.as_ref().unwrap()) {
+                    match expected.iter_mut().find(|(id, _)| *id == document_id) {
+                        Some((_, hits)) if hits.len() < group_size => hits.push(hit.id.clone()),
+                        Some(_) => {}
+                        None if expected.len() < groups => {
+                            expected.push((document_id, vec![hit.id.clone()]))
+                        }
+                        None => {}
+                    }
+                }
+            }
+
+            let found_ids: Vec<(i64, Vec<PointIdType>)> = found
+                .iter()
+                .map(|group| {
+                    let ValueVariants::Integer(document_id) = group.id else {
+                        panic!("unexpected group {:?}", group.id);
+                    };
+                    (
+                        document_id,
+                        group.hits.iter().map(|hit| hit.id.clone()).collect(),
+                    )
+                })
+                .collect();
+            assert_eq!(found_ids, expected);
+
+            for group in &found {
+                assert!(group.hits.len() <= group_size);
+                // Point is a hit of a group once
+                let ids: HashSet<_> = group.hits.iter().map(|hit| hit.id.clone()).collect();
+                assert_eq!(ids.len(), group.hits.len());
+                assert!(group
+                    .hits
+                    .windows(2)
+                    .all(|hits| hits[0].score >= hits[1].score));
+                // Hits are returned with the requested payload, not with the group-by field
+                for hit in &group.hits {
+                    let payload = hit.payload.as_ref().unwrap();
+                    assert!(payload.0.contains_key("chunk"));
+                    assert!(!payload.0.contains_key("document_id"));
+                }
+            }
+            assert!(found
+                .windows(2)
+                .all(|groups| groups[0].hits[0].score >= groups[1].hits[0].score));
+        }
+    }
+
+    #[test]
+    fn test_search_groups_without_key() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = chunked_segment(dir.path());
+        let query = vec![1.0; DIM];
+
+        let found = segment
+            .search_groups(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &WithPayload::default(),
+                &false.into(),
+                &"missing".to_string(),
+                3,
+                5,
+                None,
+                None,
+            )
+            .unwrap();
+        assert!(found.is_empty());
+
+        // All the documents are found, if more groups are asked for
+        let found = segment
+            .search_groups(
+                DEFAULT_VECTOR_NAME,
+                &query,
+                &WithPayload::default(),
+                &false.into(),
+                &"document_id".to_string(),
+                CHUNKS_PER_DOCUMENT as usize * 2,
+                NUM_DOCUMENTS as usize * 2,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(found.len(), NUM_DOCUMENTS as usize);
+        let grouped: HashSet<_> = found
+            .iter()
+            .flat_map(|group| group.hits.iter().map(|hit| hit.id.clone()))
+            .collect();
+        // Points without the document are in no group
+        let without_document = (0..NUM_DOCUMENTS * CHUNKS_PER_DOCUMENT)
+            .filter(|point_id| point_id % 11 == 0)
+            .count();
+        assert_eq!(
+            grouped.len(),
+            (NUM_DOCUMENTS * CHUNKS_PER_DOCUMENT) as usize - without_document
+        );
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use segment::common::search_deadline::SearchDeadline;
+    use segment::common::search_telemetry::SearchCounters;
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::index::hnsw_index::graph_links::GraphLinksRam;
+    use segment::index::hnsw_index::hnsw::HNSWIndex;
+    use segment::index::VectorIndex;
+    use segment::segment_constructor::build_segment;
+    use segment::types::{
+        Distance, HnswConfig, Indexes, SearchParams, SegmentConfig, SeqNumberType, StorageType,
+        VectorDataConfig, WithPayload,
+    };
+    use tempfile::Builder;
+
+    const DIM: usize = 16;
+    const NUM_VECTORS: u64 = 2_000;
+    const TOP: usize = 10;
+
+    #[test]
+    fn test_search_telemetry() {
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let hnsw_dir = Builder::new().prefix("hnsw_dir").tempdir().unwrap();
+        let config = SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            ..Default::default()
+        };
+        let mut segment = build_segment(dir.path(), &config).unwrap();
+        for n in 0..NUM_VECTORS {
+            let vector = random_vector(&mut rnd, DIM);
+            segment
+                .upsert_vector(n as SeqNumberType, n.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+
+        let query = random_vector(&mut rnd, DIM);
+        let params = SearchParams {
+            with_telemetry: true,
+            ..Default::default()
+        };
+
+        // Plain segment
+        let search = |params: Option<&SearchParams>| {
+            segment
+                .search_with_telemetry(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    TOP,
+                    params,
+                )
+                .unwrap()
+        };
+        let (found, telemetry) = search(Some(&params));
+        assert_eq!(found.len(), TOP);
+        let telemetry = telemetry.unwrap();
+        assert_eq!(telemetry.scored_points, NUM_VECTORS as usize);
+        assert_eq!(telemetry.index_hops, 0);
+        assert_eq!(telemetry.rescored_points, 0);
+
+        // Telemetry is not gathered, unless requested
+        let (found_without_telemetry, telemetry) = search(None);
+        assert!(telemetry.is_none());
+        assert_eq!(
+            found_without_telemetry
+                .iter()
+                .map(|point| point.id.clone())
+                .collect::<Vec<_>>(),
+            found
+                .iter()
+                .map(|point| point.id.clone())
+                .collect::<Vec<_>>()
+        );
+
+        // Indexed segment
+        let hnsw_config = HnswConfig {
+            m: 16,
+            ef_construct: 64,
+            full_scan_threshold: 16, // KB
+            max_indexing_threads: 2,
+            on_disk: Some(false),
+            payload_m: None,
+            neighbor_selection: None,
+            seed: None,
+            incremental: None,
+            ef_policy: None,
+            max_build_memory_mb: None,
+        };
+        let mut hnsw_index = HNSWIndex::<GraphLinksRam>::open(
+            hnsw_dir.path(),
+            segment.id_tracker.clone(),
+            segment.vector_data[DEFAULT_VECTOR_NAME]
+                .vector_storage
+                .clone(),
+            segment.payload_index.clone(),
+            hnsw_config,
+        )
+        .unwrap();
+        hnsw_index.build_index(&stopped).unwrap();
+
+        let counters = SearchCounters::from_params(Some(&params));
+        let found = hnsw_index.search_with_deadline(
+            &[&query],
+            None,
+            TOP,
+            Some(&params),
+            &SearchDeadline::none(),
+            &counters,
+        );
+        assert_eq!(found[0].len(), TOP);
+        let telemetry = counters.telemetry().unwrap();
+        assert!(telemetry.index_hops > 0);
+        assert!(telemetry.scored_points >= found[0].len());
+        // Graph is traversed, not scanned
+        assert!(telemetry.scored_points < NUM_VECTORS as usize);
+        assert_eq!(telemetry.filter_checks, 0);
+
+        let counters = SearchCounters::disabled();
+        hnsw_index.search_with_deadline(
+            &[&query],
+            None,
+            TOP,
+            Some(&params),
+            &SearchDeadline::none(),
+            &counters,
+        );
+        assert!(counters.telemetry().is_none());
+    }
+}
@@ -170,4 +170,53 @@ mod tests {
             panic!("wrong upsert result")
         }
     }
+
+    #[test]
+    fn test_postprocessed_scores() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = build_segment_3(dir.path());
+        let query_vector = vec![1.0, 1.0, 1.0, 1.0];
+
+        let search = |vector_name: &str| {
+            segment
+                .search(
+                    vector_name,
+                    &query_vector,
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    10,
+                    None,
+                )
+                .unwrap()
+        };
+
+        // Euclid scores are distances, closest first
+        let res = search("vector3");
+        assert!(!res.is_empty());
+        for pair in res.windows(2) {
+            assert!(pair[0].score <= pair[1].score);
+        }
+        for scored_point in &res {
+            let vector = segment.vector("vector3", scored_point.id.clone()).unwrap();
+            let distance = vector
+                .iter()
+                .zip(&query_vector)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f32>()
+                .sqrt();
+            assert!((scored_point.score - distance).abs() < 1e-4);
+        }
+
+        // Dot scores are products, largest first
+        let res = search("vector1");
+        for pair in res.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+        for scored_point in &res {
+            let vector = segment.vector("vector1", scored_point.id.clone()).unwrap();
+            let product: f32 = vector.iter().zip(&query_vector).map(|(a, b)| a * b).sum();
+            assert!((scored_point.score - product).abs() < 1e-4);
+        }
+    }
 }
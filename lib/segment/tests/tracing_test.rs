@@ -0,0 +1,72 @@
+#![cfg(feature = "tracing")]
+
+mod fixtures;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use segment::data_types::vectors::DEFAULT_VECTOR_NAME;
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::types::{Condition, FieldCondition, Filter, WithPayload};
+    use tempfile::Builder;
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    use crate::fixtures::segment::build_segment_1;
+
+    /// Names of the created spans, with the names of their parents
+    #[derive(Clone, Default)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<(String, Option<String>)>>>,
+    }
+
+    impl<S> Layer<S> for SpanRecorder
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let span = ctx.span(id).unwrap();
+            let parent = span.parent().map(|parent| parent.name().to_string());
+            self.spans.lock().push((span.name().to_string(), parent));
+        }
+    }
+
+    #[test]
+    fn test_search_spans() {
+        let dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let segment = build_segment_1(dir.path());
+        let filter = Filter::new_must(Condition::Field(FieldCondition::new_match(
+            "color".to_string(),
+            "red".to_string().into(),
+        )));
+
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &[1.0, 0.0, 1.0, 1.0],
+                    &WithPayload::default(),
+                    &false.into(),
+                    Some(&filter),
+                    10,
+                    None,
+                )
+                .unwrap()
+        });
+
+        // Filter is evaluated within the search
+        assert_eq!(
+            *recorder.spans.lock(),
+            vec![
+                ("search".to_string(), None),
+                ("filter".to_string(), Some("search".to_string())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(all(test, unix))]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+
+    use rand::prelude::StdRng;
+    use rand::SeedableRng;
+    use segment::common::warm_up::WarmUpParts;
+    use segment::data_types::vectors::{only_default_vector, DEFAULT_VECTOR_NAME};
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::fixtures::payload_fixtures::random_vector;
+    use segment::segment_constructor::segment_builder::SegmentBuilder;
+    use segment::segment_constructor::{build_segment, load_segment};
+    use segment::types::{
+        Distance, HnswConfig, Indexes, PayloadStorageType, SegmentConfig, StorageType,
+        VectorDataConfig, WithPayload,
+    };
+    use tempfile::Builder;
+
+    const DIM: usize = 16;
+    const NUM_VECTORS: u64 = 1_000;
+
+    fn segment_config(index: Indexes, storage_type: StorageType) -> SegmentConfig {
+        SegmentConfig {
+            vector_data: HashMap::from([(
+                DEFAULT_VECTOR_NAME.to_owned(),
+                VectorDataConfig {
+                    size: DIM,
+                    distance: Distance::Dot,
+                    hnsw_config: None,
+                    quantization_config: None,
+                },
+            )]),
+            index,
+            storage_type,
+            payload_storage_type: PayloadStorageType::OnDisk,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let stopped = AtomicBool::new(false);
+        let mut rnd = StdRng::seed_from_u64(42);
+
+        let plain_dir = Builder::new()
+            .prefix("plain_segment_dir")
+            .tempdir()
+            .unwrap();
+        let segment_dir = Builder::new().prefix("segment_dir").tempdir().unwrap();
+        let temp_dir = Builder::new().prefix("segment_temp_dir").tempdir().unwrap();
+
+        let mut plain_config = segment_config(Indexes::Plain {}, StorageType::InMemory);
+        plain_config.payload_storage_type = PayloadStorageType::InMemory;
+        let mut plain_segment = build_segment(plain_dir.path(), &plain_config).unwrap();
+        for n in 0..NUM_VECTORS {
+            let vector = random_vector(&mut rnd, DIM);
+            plain_segment
+                .upsert_vector(n, n.into(), &only_default_vector(&vector))
+                .unwrap();
+        }
+        // Fully in-memory segment has nothing to warm up
+        assert_eq!(plain_segment.warm_up(WarmUpParts::all()).unwrap(), 0);
+
+        let config = segment_config(
+            Indexes::Hnsw(HnswConfig {
+                full_scan_threshold: 1,
+                on_disk: Some(true),
+                ..Default::default()
+            }),
+            StorageType::Mmap,
+        );
+        let mut builder =
+            SegmentBuilder::new(segment_dir.path(), temp_dir.path(), &config).unwrap();
+        builder.update_from(&plain_segment, &stopped).unwrap();
+        let segment = builder.build(&stopped).unwrap();
+        let segment_path = segment.current_path.clone();
+        segment.close().unwrap();
+
+        let segment = load_segment(&segment_path).unwrap().unwrap();
+        let query = random_vector(&mut rnd, DIM);
+        let search = || {
+            segment
+                .search(
+                    DEFAULT_VECTOR_NAME,
+                    &query,
+                    &WithPayload::default(),
+                    &false.into(),
+                    None,
+                    10,
+                    None,
+                )
+                .unwrap()
+                .into_iter()
+                .map(|point| point.id)
+                .collect::<Vec<_>>()
+        };
+        let cold = search();
+
+        let vectors_only = WarmUpParts {
+            vectors: true,
+            vector_index: false,
+            payload_index: false,
+            max_bytes_per_sec: None,
+        };
+        let vector_bytes = segment.warm_up(vectors_only).unwrap();
+        assert!(vector_bytes >= NUM_VECTORS as usize * DIM * 4);
+        let all_bytes = segment.warm_up(WarmUpParts::all()).unwrap();
+        assert!(all_bytes > vector_bytes);
+
+        // Warm up doesn't change the results
+        assert_eq!(search(), cold);
+    }
+}
@@ -10,7 +10,7 @@ use collection::operations::{CollectionUpdateOperations, CreateIndex, FieldIndex
 use collection::shards::replica_set::ReplicaState;
 use collection::shards::shard::{PeerId, ShardId};
 use collection::shards::CollectionId;
-use segment::types::{WithPayloadInterface, WithVector};
+use segment::types::{Payload, WithPayloadInterface, WithVector};
 use tokio::sync::RwLock;
 
 use crate::content_manager::collections_ops::Collections;
@@ -83,6 +83,7 @@ async fn replicate_shard_data(
             filter: None,
             with_payload: Some(WithPayloadInterface::Bool(true)),
             with_vector: WithVector::Bool(true),
+            order_by: None,
         };
 
         let collections_read = collections.read().await;
@@ -106,7 +107,7 @@ async fn replicate_shard_data(
             .map(|point| PointStruct {
                 id: point.id,
                 vector: point.vector.unwrap(),
-                payload: point.payload,
+                payload: point.payload.map(Payload::from_shared),
             })
             .collect();
 
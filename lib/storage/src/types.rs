@@ -23,6 +23,9 @@ pub struct PerformanceConfig {
     pub max_optimization_threads: usize,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub update_rate_limit: Option<usize>,
+    /// Max number of threads, which load the components of a segment in parallel. If 0 - auto selection.
+    #[serde(default)]
+    pub max_segment_load_threads: usize,
 }
 
 fn default_max_optimization_threads() -> usize {
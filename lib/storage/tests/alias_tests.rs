@@ -46,6 +46,7 @@ fn test_alias_operation() {
             max_search_threads: 1,
             max_optimization_threads: 1,
             update_rate_limit: None,
+            max_segment_load_threads: 0,
         },
         hnsw_index: Default::default(),
         quantization: None,
@@ -58,7 +58,12 @@ async fn get_point(
     let timing = Instant::now();
 
     let point_id: PointIdType = {
-        let parse_res = point.id.parse();
+        // Numbers in the path are numeric ids, not strings
+        let parse_res = point
+            .id
+            .parse::<u64>()
+            .map(PointIdType::from)
+            .or_else(|_| point.id.parse());
         match parse_res {
             Ok(x) => x,
             Err(_) => {
@@ -73,7 +78,7 @@ async fn get_point(
     let response = do_get_point(
         toc.get_ref(),
         &collection.name,
-        point_id,
+        point_id.clone(),
         params.consistency,
     )
     .await;
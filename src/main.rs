@@ -117,6 +117,9 @@ fn main() -> anyhow::Result<()> {
     setup_panic_hook(reporting_enabled, reporting_id.to_string());
 
     segment::madvise::set_global(settings.storage.mmap_advice);
+    segment::common::parallel::set_load_threads(
+        settings.storage.performance.max_segment_load_threads,
+    );
 
     welcome();
 
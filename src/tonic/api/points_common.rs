@@ -354,6 +354,21 @@ pub async fn create_field_index(
                 ))
             }
         },
+        (
+            Some(v),
+            Some(PayloadIndexParams {
+                index_params: Some(IndexParams::KeywordIndexParams(keyword_index_params)),
+            }),
+        ) => match v {
+            FieldType::Keyword => Some(PayloadFieldSchema::FieldParams(
+                PayloadSchemaParams::Keyword(keyword_index_params.into()),
+            )),
+            _ => {
+                return Err(Status::invalid_argument(
+                    "field_type and field_index_params do not match",
+                ))
+            }
+        },
         (Some(v), None | Some(PayloadIndexParams { index_params: None })) => match v {
             FieldType::Keyword => Some(PayloadSchemaType::Keyword.into()),
             FieldType::Integer => Some(PayloadSchemaType::Integer.into()),
@@ -628,6 +643,7 @@ pub async fn scroll(
         with_payload,
         with_vectors,
         read_consistency,
+        order_by,
     } = scroll_points;
 
     let scroll_request = ScrollRequest {
@@ -638,6 +654,7 @@ pub async fn scroll(
         with_vector: with_vectors
             .map(|selector| selector.into())
             .unwrap_or_default(),
+        order_by: order_by.map(|order_by| order_by.into()),
     };
 
     let read_consistency = ReadConsistency::try_from_optional(read_consistency)?;
@@ -661,6 +678,7 @@ pub async fn scroll(
             .map(|point| point.into())
             .collect(),
         time: timing.elapsed().as_secs_f64(),
+        next_page_start_from: scrolled_points.next_page_start_from,
     };
 
     Ok(Response::new(response))